@@ -133,6 +133,12 @@ impl ApplyPatchAction {
         &self.changes
     }
 
+    /// Wraps changes computed without an `apply_patch` invocation, e.g. by a
+    /// native file tool. All paths must be absolute.
+    pub fn from_changes(changes: HashMap<PathBuf, ApplyPatchFileChange>) -> Self {
+        Self { changes }
+    }
+
     /// Should be used exclusively for testing. (Not worth the overhead of
     /// creating a feature flag for this.)
     pub fn new_add_for_test(path: &Path, content: String) -> Self {
@@ -579,6 +585,14 @@ pub struct ApplyPatchFileUpdate {
     content: String,
 }
 
+/// Unified diff (with one line of context) turning `original` into `new`.
+pub fn unified_diff_between(original: &str, new: &str) -> String {
+    TextDiff::from_lines(original, new)
+        .unified_diff()
+        .context_radius(1)
+        .to_string()
+}
+
 pub fn unified_diff_from_chunks(
     path: &Path,
     chunks: &[UpdateFileChunk],
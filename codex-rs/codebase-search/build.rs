@@ -0,0 +1,37 @@
+fn main() {
+    #[cfg(feature = "grpc")]
+    generate_grpc_service();
+}
+
+/// Generate the server and client for `proto/codebase_search.proto`
+/// The messages are written by hand in `src/grpc.rs`, so only the service is generated
+/// here and building doesn't need protoc.
+#[cfg(feature = "grpc")]
+fn generate_grpc_service() {
+    use tonic_build::manual::Builder;
+    use tonic_build::manual::Method;
+    use tonic_build::manual::Service;
+
+    println!("cargo:rerun-if-changed=build.rs");
+    let method = |name: &str, route_name: &str, input_type: &str, output_type: &str| {
+        Method::builder()
+            .name(name)
+            .route_name(route_name)
+            .input_type(format!("super::{input_type}"))
+            .output_type(format!("super::{output_type}"))
+            .codec_path("tonic_prost::ProstCodec")
+    };
+    let service = Service::builder()
+        .name("CodebaseSearch")
+        .package("codebase_search.v1")
+        .method(method("index", "Index", "IndexRequest", "StatusResponse").build())
+        .method(method("search", "Search", "SearchRequest", "SearchResponse").build())
+        .method(method("status", "Status", "StatusRequest", "StatusResponse").build())
+        .method(
+            method("watch", "Watch", "WatchRequest", "IndexEvent")
+                .server_streaming()
+                .build(),
+        )
+        .build();
+    Builder::new().compile(&[service]);
+}
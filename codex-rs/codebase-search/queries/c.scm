@@ -0,0 +1,36 @@
+; C symbols
+; Only definitions are captured: prototypes and `struct foo *` references are left
+; out so each symbol is indexed once. Declarators are looked through to the name
+; they declare: `*(*handler)(int)` declares `handler`.
+
+(function_definition
+  declarator: [
+    (function_declarator declarator: (identifier) @name)
+    (pointer_declarator declarator: (function_declarator declarator: (identifier) @name))
+    (pointer_declarator
+      declarator: (pointer_declarator
+        declarator: (function_declarator declarator: (identifier) @name)))
+  ]) @definition.function
+
+[
+  (struct_specifier name: (type_identifier) @name body: (_))
+  (union_specifier name: (type_identifier) @name body: (_))
+] @definition.struct
+
+(enum_specifier name: (type_identifier) @name body: (_)) @definition.enum
+
+(type_definition
+  declarator: [
+    (type_identifier) @name
+    (pointer_declarator declarator: (type_identifier) @name)
+    (array_declarator declarator: (type_identifier) @name)
+    (function_declarator declarator: (type_identifier) @name)
+    (function_declarator
+      declarator: (parenthesized_declarator
+        (pointer_declarator declarator: (type_identifier) @name)))
+  ]) @definition.type
+
+[
+  (preproc_def name: (identifier) @name)
+  (preproc_function_def name: (identifier) @name)
+] @definition.macro
@@ -0,0 +1,41 @@
+; C++ symbols
+; Namespaces, classes, structs and unions scope their members, e.g.
+; `net::http::Client`. A function is named by its declarator, which may be
+; qualified: `void Client::send()` is a method of `Client` defined out of line.
+
+((namespace_definition name: (_) @name) @definition.module
+  (#set! scope "::"))
+
+((class_specifier name: (type_identifier) @name body: (_)) @definition.class
+  (#set! scope "::"))
+
+([
+  (struct_specifier name: (type_identifier) @name body: (_))
+  (union_specifier name: (type_identifier) @name body: (_))
+] @definition.struct
+  (#set! scope "::"))
+
+(function_definition
+  declarator: [
+    (function_declarator declarator: (_) @name)
+    (pointer_declarator declarator: (function_declarator declarator: (_) @name))
+    (reference_declarator (function_declarator declarator: (_) @name))
+    (pointer_declarator
+      declarator: (pointer_declarator
+        declarator: (function_declarator declarator: (_) @name)))
+  ]) @definition.function
+
+(enum_specifier name: (type_identifier) @name body: (_)) @definition.enum
+
+(alias_declaration name: (type_identifier) @name) @definition.type
+
+(type_definition
+  declarator: [
+    (type_identifier) @name
+    (pointer_declarator declarator: (type_identifier) @name)
+    (array_declarator declarator: (type_identifier) @name)
+    (function_declarator declarator: (type_identifier) @name)
+    (function_declarator
+      declarator: (parenthesized_declarator
+        (pointer_declarator declarator: (type_identifier) @name)))
+  ]) @definition.type
@@ -0,0 +1,35 @@
+; C# symbols
+; Namespaces and types scope their members, e.g. `Shop.Orders.OrderService`;
+; `namespace Shop.Orders;` scopes the declarations after it.
+
+((namespace_declaration name: (_) @name) @definition.module
+  (#set! scope "."))
+
+(file_scoped_namespace_declaration name: (_) @name) @definition.module
+
+(file_scoped_namespace_declaration name: (_) @name) @scope
+
+([
+  (class_declaration name: (identifier) @name)
+  (record_declaration name: (identifier) @name)
+] @definition.class
+  (#set! scope "."))
+
+((struct_declaration name: (identifier) @name) @definition.struct
+  (#set! scope "."))
+
+((interface_declaration name: (identifier) @name) @definition.interface
+  (#set! scope "."))
+
+(enum_declaration name: (identifier) @name) @definition.enum
+
+[
+  (method_declaration name: (identifier) @name)
+  (constructor_declaration name: (identifier) @name)
+] @definition.method
+
+(local_function_statement name: (identifier) @name) @definition.function
+
+(property_declaration name: (identifier) @name) @definition.property
+
+(delegate_declaration name: (identifier) @name) @definition.type
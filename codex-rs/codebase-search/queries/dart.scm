@@ -0,0 +1,36 @@
+; Dart symbols
+; Dart's grammar puts a function's signature and body side by side; the symbol is
+; extended over the body afterwards. Members get their class, mixin, extension or
+; enum as context, and local functions are left to their function's chunk.
+
+(class_declaration name: (identifier) @name) @definition.class
+
+(mixin_declaration name: (identifier) @name) @definition.trait
+
+(extension_declaration name: (identifier) @name) @definition.impl
+
+(enum_declaration name: (identifier) @name) @definition.enum
+
+; A method signature wraps the function, getter, setter or constructor signature
+; that holds its name
+(method_signature [
+  (function_signature name: (identifier) @name)
+  (getter_signature name: (identifier) @name)
+  (setter_signature name: (identifier) @name)
+  (factory_constructor_signature name: (identifier) @name)
+  (constructor_signature name: (identifier) @name)
+]) @definition.function
+
+; Top-level functions, and abstract members declared without a body
+
+(source_file [
+  (function_signature name: (identifier) @name)
+  (getter_signature name: (identifier) @name)
+  (setter_signature name: (identifier) @name)
+] @definition.function)
+
+(declaration [
+  (function_signature name: (identifier) @name)
+  (getter_signature name: (identifier) @name)
+  (setter_signature name: (identifier) @name)
+] @definition.function)
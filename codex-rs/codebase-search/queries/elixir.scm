@@ -0,0 +1,54 @@
+; Elixir symbols
+; Definitions are ordinary calls (`defmodule`, `def`, ...) in Elixir's grammar.
+; Modules are named in full (`MyAppWeb.UserController.Params`) and give the
+; definitions in their body that name as context. A struct takes the name of its
+; module, and function bodies hold no further definitions.
+
+((call
+  target: (identifier) @_keyword
+  (arguments . (alias) @name)) @definition.module
+  (#eq? @_keyword "defmodule")
+  (#set! scope "."))
+
+((call
+  target: (identifier) @_keyword
+  (arguments . (alias) @name)) @definition.interface
+  (#eq? @_keyword "defprotocol")
+  (#set! scope "."))
+
+(call
+  target: (identifier) @_defmodule
+  (arguments . (alias) @name)
+  (do_block
+    (call target: (identifier) @_defstruct) @definition.struct)
+  (#eq? @_defmodule "defmodule")
+  (#eq? @_defstruct "defstruct"))
+
+; A definition head is `name(args)` or a bare `name`, either maybe followed by a
+; `when` guard
+
+((call
+  target: (identifier) @_keyword
+  (arguments
+    .
+    [
+      (call target: (identifier) @name)
+      (identifier) @name
+      (binary_operator left: (call target: (identifier) @name))
+      (binary_operator left: (identifier) @name)
+    ])) @definition.function
+  (#any-of? @_keyword "def" "defp" "defdelegate" "defguard" "defguardp")
+  (#set! leaf))
+
+((call
+  target: (identifier) @_keyword
+  (arguments
+    .
+    [
+      (call target: (identifier) @name)
+      (identifier) @name
+      (binary_operator left: (call target: (identifier) @name))
+      (binary_operator left: (identifier) @name)
+    ])) @definition.macro
+  (#any-of? @_keyword "defmacro" "defmacrop")
+  (#set! leaf))
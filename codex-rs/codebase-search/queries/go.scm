@@ -0,0 +1,40 @@
+; Go symbols
+
+(function_declaration name: (identifier) @name) @definition.function
+
+; A method belongs to its receiver's type: `func (s *Server[T]) Start()` to `Server`
+(method_declaration
+  receiver: (parameter_list
+    (parameter_declaration
+      type: [
+        (type_identifier) @context
+        (pointer_type (type_identifier) @context)
+        (generic_type type: (type_identifier) @context)
+        (pointer_type (generic_type type: (type_identifier) @context))
+      ]))
+  name: (field_identifier) @name) @definition.method
+
+(method_declaration name: (field_identifier) @name) @definition.method
+
+(type_spec
+  name: (type_identifier) @name
+  type: (struct_type)) @definition.struct
+
+(type_spec
+  name: (type_identifier) @name
+  type: (interface_type)) @definition.interface
+
+(type_spec name: (type_identifier) @name) @definition.type
+
+(method_elem name: (field_identifier) @name) @definition.method
+
+; A `const` or `var` block is named after its first name
+
+(const_declaration
+  .
+  (const_spec . name: (identifier) @name)) @definition.constant
+
+(var_declaration [
+  (var_spec . name: (identifier) @name)
+  (var_spec_list . (var_spec . name: (identifier) @name))
+]) @definition.variable
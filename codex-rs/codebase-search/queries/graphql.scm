@@ -0,0 +1,45 @@
+; GraphQL symbols
+; Type definitions and extensions are symbols, and so is each field of the `Query`,
+; `Mutation` and `Subscription` root types, with the root type as context. Named
+; operations in documents are functions; anonymous ones have no name to search by.
+; A definition's description is part of its node, so its docs are embedded with it.
+
+([
+  (object_type_definition (name) @name)
+  (object_type_extension (name) @name)
+] @definition.type
+  (#any-of? @name "Query" "Mutation" "Subscription")
+  (#set! scope "."))
+
+([
+  (object_type_definition
+    (name) @_root
+    (fields_definition (field_definition (name) @name) @definition.method))
+  (object_type_extension
+    (name) @_root
+    (fields_definition (field_definition (name) @name) @definition.method))
+]
+  (#any-of? @_root "Query" "Mutation" "Subscription"))
+
+[
+  (object_type_definition (name) @name)
+  (object_type_extension (name) @name)
+  (input_object_type_definition (name) @name)
+  (input_object_type_extension (name) @name)
+  (union_type_definition (name) @name)
+  (union_type_extension (name) @name)
+  (scalar_type_definition (name) @name)
+  (scalar_type_extension (name) @name)
+] @definition.type
+
+[
+  (interface_type_definition (name) @name)
+  (interface_type_extension (name) @name)
+] @definition.interface
+
+[
+  (enum_type_definition (name) @name)
+  (enum_type_extension (name) @name)
+] @definition.enum
+
+(operation_definition (name) @name) @definition.function
@@ -0,0 +1,23 @@
+; Haskell symbols
+; A function's signature and equations are captured one by one and joined into a
+; single symbol afterwards; a lone signature is only kept in a class, where it
+; declares a method. Class and instance methods get the class or instance as
+; context, and an instance is named by its head, e.g. `Show Color`.
+
+(class name: (_) @name) @definition.trait
+
+(instance name: (_) @name (type_patterns) @name) @definition.impl
+
+[
+  (data_type name: (_) @name)
+  (newtype name: (_) @name)
+  (type_synomym name: (_) @name)
+  (type_family name: (_) @name)
+] @definition.type
+
+([
+  (function name: (_) @name)
+  (bind name: (_) @name)
+  (signature name: (_) @name)
+] @definition.function
+  (#set! leaf))
@@ -0,0 +1,51 @@
+; Terraform symbols
+; Only top-level blocks are symbols, named by their last label; nested blocks
+; (`lifecycle`, `ingress`, ...) stay part of their parent's chunk. A resource or data
+; source gets its type as context: `resource "aws_s3_bucket" "logs"` is `logs` in
+; `aws_s3_bucket`.
+
+(config_file
+  (body
+    (block
+      .
+      (identifier) @_type
+      .
+      (string_lit (template_literal) @context)
+      .
+      (string_lit (template_literal) @name)
+      .
+      (block_start)
+      (#any-of? @_type "resource" "data")) @definition.resource))
+
+(config_file
+  (body
+    (block
+      .
+      (identifier) @_type
+      .
+      (string_lit (template_literal) @name)
+      .
+      (block_start)
+      (#eq? @_type "module")) @definition.module))
+
+(config_file
+  (body
+    (block
+      .
+      (identifier) @_type
+      .
+      (string_lit (template_literal) @name)
+      .
+      (block_start)
+      (#eq? @_type "variable")) @definition.variable))
+
+(config_file
+  (body
+    (block
+      .
+      (identifier) @_type
+      .
+      (string_lit (template_literal) @name)
+      .
+      (block_start)
+      (#eq? @_type "output")) @definition.constant))
@@ -0,0 +1,32 @@
+; Java symbols
+; Members get their type's name as context, and the annotations on a declaration are
+; added to it. Fields are named by their first declarator: `int a, b;` is `a`.
+
+[
+  (class_declaration name: (identifier) @name)
+  (record_declaration name: (identifier) @name)
+] @definition.class
+
+[
+  (interface_declaration name: (identifier) @name)
+  (annotation_type_declaration name: (identifier) @name)
+] @definition.interface
+
+(enum_declaration name: (identifier) @name) @definition.enum
+
+[
+  (method_declaration name: (identifier) @name)
+  (constructor_declaration name: (identifier) @name)
+] @definition.method
+
+(constant_declaration
+  declarator: (variable_declarator name: (identifier) @name)) @definition.constant
+
+((field_declaration
+  (modifiers) @_modifiers
+  declarator: (variable_declarator name: (identifier) @name)) @definition.constant
+  (#match? @_modifiers "\\bstatic\\b")
+  (#match? @_modifiers "\\bfinal\\b"))
+
+(field_declaration
+  declarator: (variable_declarator name: (identifier) @name)) @definition.variable
@@ -0,0 +1,34 @@
+; JavaScript and JSX symbols, also used for TypeScript and TSX along with typescript.scm
+
+[
+  (function_declaration name: (identifier) @name)
+  (generator_function_declaration name: (identifier) @name)
+] @definition.function
+
+(class_declaration name: (_) @name) @definition.class
+
+(method_definition name: (_) @name) @definition.method
+
+; `const Button = () => ...` is how most components and helpers are written
+[
+  (lexical_declaration
+    (variable_declarator
+      name: (identifier) @name
+      value: [(arrow_function) (function_expression) (generator_function)]))
+  (variable_declaration
+    (variable_declarator
+      name: (identifier) @name
+      value: [(arrow_function) (function_expression) (generator_function)]))
+] @definition.function
+
+((export_statement
+  value: [(arrow_function) (function_expression) (generator_function)]) @definition.function
+  (#set! name "default"))
+
+; CommonJS `exports.name = ...` and `module.exports.name = ...`
+(assignment_expression
+  left: (member_expression
+    object: (_) @_object
+    property: (property_identifier) @name)
+  right: [(arrow_function) (function_expression) (generator_function)]
+  (#any-of? @_object "exports" "module.exports")) @definition.function
@@ -0,0 +1,16 @@
+; Protocol Buffers symbols
+; `package acme.users.v1;` scopes the declarations after it, and messages, enums and
+; services scope theirs, e.g. `acme.users.v1.UserProfile`.
+
+(package (full_ident) @name) @scope
+
+((message (message_name) @name) @definition.struct
+  (#set! scope "."))
+
+((enum (enum_name) @name) @definition.enum
+  (#set! scope "."))
+
+((service (service_name) @name) @definition.interface
+  (#set! scope "."))
+
+(rpc (rpc_name) @name) @definition.method
@@ -0,0 +1,43 @@
+; Python symbols
+; Functions nested in a class become methods.
+
+(function_definition name: (identifier) @name) @definition.function
+
+(class_definition name: (identifier) @name) @definition.class
+
+(type_alias_statement
+  left: (type [
+    (identifier) @name
+    (generic_type (identifier) @name)
+  ])) @definition.type
+
+; Module-level assignments: `X: TypeAlias = ...` is a type alias, UPPER_CASE names are
+; constants and the rest are variables
+
+(module
+  (expression_statement
+    (assignment
+      left: (identifier) @name
+      type: (type) @_annotation)) @definition.type
+  (#match? @_annotation "TypeAlias$"))
+
+(module
+  (expression_statement
+    (assignment left: (identifier) @name)) @definition.constant
+  (#match? @name "^[^a-z]*[A-Z][^a-z]*$"))
+
+(module
+  (expression_statement
+    (assignment left: (identifier) @name)) @definition.variable)
+
+; Annotated fields of a `@dataclass`
+
+(decorated_definition
+  (decorator) @_decorator
+  definition: (class_definition
+    body: (block
+      (expression_statement
+        (assignment
+          left: (identifier) @name
+          type: (type))) @definition.variable))
+  (#match? @_decorator "dataclass"))
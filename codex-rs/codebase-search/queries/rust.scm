@@ -0,0 +1,34 @@
+; Rust symbols
+; Functions nested in a struct, trait or impl become methods, and impls are named
+; `impl <type>` after the type without its path or generic arguments.
+
+(function_item name: (identifier) @name) @definition.function
+
+(function_signature_item name: (identifier) @name) @definition.function
+
+(struct_item name: (type_identifier) @name) @definition.struct
+
+(enum_item name: (type_identifier) @name) @definition.enum
+
+(trait_item name: (type_identifier) @name) @definition.trait
+
+(impl_item
+  type: [
+    (type_identifier) @name
+    (scoped_type_identifier name: (type_identifier) @name)
+    (generic_type type: (type_identifier) @name)
+    (generic_type type: (scoped_type_identifier name: (type_identifier) @name))
+  ]) @definition.impl
+
+(impl_item type: (_) @name) @definition.impl
+
+(associated_type name: (type_identifier) @name) @definition.type
+
+[
+  (const_item name: (identifier) @name)
+  (static_item name: (identifier) @name)
+] @definition.constant
+
+(mod_item name: (identifier) @name) @definition.module
+
+(macro_definition name: (identifier) @name) @definition.macro
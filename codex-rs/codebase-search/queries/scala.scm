@@ -0,0 +1,39 @@
+; Scala symbols
+; Objects, classes, traits and enums scope their members, e.g. `Billing.Invoice`,
+; and functions nested in them become methods. Definition bodies aren't searched, so
+; local vals and helper defs stay part of their definition's chunk.
+
+((object_definition name: (identifier) @name) @definition.module
+  (#set! scope "."))
+
+((class_definition name: (identifier) @name) @definition.class
+  (#set! scope "."))
+
+((trait_definition name: (identifier) @name) @definition.trait
+  (#set! scope "."))
+
+((enum_definition name: (identifier) @name) @definition.enum
+  (#set! scope "."))
+
+([
+  (function_definition name: (_) @name)
+  (function_declaration name: (_) @name)
+] @definition.function
+  (#set! leaf))
+
+; vals and vars are named by their pattern; `val (a, b) = ...` is skipped
+
+([
+  (val_definition pattern: (identifier) @name)
+  (val_declaration name: (_) @name)
+] @definition.constant
+  (#set! leaf))
+
+([
+  (var_definition pattern: (identifier) @name)
+  (var_declaration name: (_) @name)
+] @definition.variable
+  (#set! leaf))
+
+((type_definition name: (type_identifier) @name) @definition.type
+  (#set! leaf))
@@ -0,0 +1,15 @@
+; SQL schema objects
+; Tables, views and functions are named as written, including any schema
+; qualifier (`billing.invoices`). Nothing is declared inside a statement.
+
+((create_table (object_reference) @name) @definition.table
+  (#set! leaf))
+
+([
+  (create_view (object_reference) @name)
+  (create_materialized_view (object_reference) @name)
+] @definition.view
+  (#set! leaf))
+
+((create_function (object_reference) @name) @definition.function
+  (#set! leaf))
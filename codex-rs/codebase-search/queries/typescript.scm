@@ -0,0 +1,9 @@
+; TypeScript and TSX symbols beyond those in javascript.scm
+
+(abstract_class_declaration name: (_) @name) @definition.class
+
+(interface_declaration name: (_) @name) @definition.interface
+
+(enum_declaration name: (_) @name) @definition.enum
+
+(type_alias_declaration name: (_) @name) @definition.type
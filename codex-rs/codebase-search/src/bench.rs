@@ -0,0 +1,477 @@
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::Duration;
+use std::time::Instant;
+
+use qdrant_client::qdrant::CreateCollectionBuilder;
+use qdrant_client::qdrant::Distance;
+use qdrant_client::qdrant::PointId;
+use qdrant_client::qdrant::PointStruct;
+use qdrant_client::qdrant::SearchPointsBuilder;
+use qdrant_client::qdrant::UpsertPointsBuilder;
+use qdrant_client::qdrant::VectorParamsBuilder;
+use serde::Serialize;
+use tracing::info;
+use tracing::warn;
+
+use crate::chunker::CodeChunk;
+use crate::chunker::HierarchicalChunker;
+use crate::embedding::EmbeddedChunk;
+use crate::embedding::get_embedding_client;
+use crate::index_config::IndexConfig;
+use crate::symbol::Symbol;
+use crate::symbol::SymbolParser;
+use crate::vector_db::COLLECTION_PREFIX;
+use crate::vector_db::QDRANT_CLIENT;
+use crate::vector_db::UPSERT_BATCH_SIZE;
+use crate::vector_db::chunk_to_point;
+use crate::vector_db::generate_collection_id;
+use crate::walk_utils::is_supported_file_extension;
+use crate::walk_utils::walk_codebase_files;
+
+/// Queries timed by the search stage, chosen to hit code in most projects
+pub const DEFAULT_BENCH_QUERIES: &[&str] = &[
+    "error handling",
+    "parse configuration file",
+    "retry a failed network request",
+    "open a database connection",
+    "serialize a struct to json",
+    "spawn a background task",
+    "read a file from disk",
+    "command line argument parsing",
+];
+
+/// What `run_bench` measures
+#[derive(Debug, Clone)]
+pub struct BenchOptions {
+    /// Chunks sent to the embedding API; 0 skips the embedding and upsert stages
+    pub embed_chunks: usize,
+    /// Points written to a scratch collection, reusing the embedded vectors
+    pub upsert_points: usize,
+    /// Queries timed against the project's collection; empty skips the search stage
+    pub queries: Vec<String>,
+    /// Times each query's vector search is repeated
+    pub search_iterations: usize,
+    /// Results requested per search
+    pub search_limit: usize,
+}
+
+impl Default for BenchOptions {
+    fn default() -> Self {
+        Self {
+            embed_chunks: 64,
+            upsert_points: 1024,
+            queries: DEFAULT_BENCH_QUERIES
+                .iter()
+                .map(|q| q.to_string())
+                .collect(),
+            search_iterations: 5,
+            search_limit: 10,
+        }
+    }
+}
+
+/// Latency distribution of a repeated operation, in milliseconds
+#[derive(Debug, Clone, Serialize)]
+pub struct LatencyStats {
+    pub count: usize,
+    pub mean_ms: f64,
+    pub p50_ms: f64,
+    pub p95_ms: f64,
+    pub max_ms: f64,
+}
+
+impl LatencyStats {
+    /// `None` for an empty sample
+    pub fn from_samples(samples: &[Duration]) -> Option<Self> {
+        if samples.is_empty() {
+            return None;
+        }
+        let mut ms: Vec<f64> = samples.iter().map(|d| d.as_secs_f64() * 1000.0).collect();
+        ms.sort_by(f64::total_cmp);
+        // Nearest-rank percentile
+        let percentile =
+            |p: f64| ms[((p * ms.len() as f64).ceil() as usize).clamp(1, ms.len()) - 1];
+        Some(Self {
+            count: ms.len(),
+            mean_ms: ms.iter().sum::<f64>() / ms.len() as f64,
+            p50_ms: percentile(0.50),
+            p95_ms: percentile(0.95),
+            max_ms: ms[ms.len() - 1],
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ParseBench {
+    pub files: usize,
+    pub bytes: u64,
+    pub symbols: usize,
+    /// Files that failed to parse
+    pub errors: usize,
+    pub seconds: f64,
+    pub files_per_sec: f64,
+    pub mb_per_sec: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ChunkBench {
+    pub chunks: usize,
+    pub seconds: f64,
+    pub chunks_per_sec: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EmbeddingBench {
+    pub provider: String,
+    pub model: String,
+    pub batch_size: usize,
+    pub chunks: usize,
+    pub chunks_per_sec: f64,
+    pub batch_latency: Option<LatencyStats>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UpsertBench {
+    pub points: usize,
+    pub batch_size: usize,
+    pub seconds: f64,
+    pub points_per_sec: f64,
+    pub batch_latency: Option<LatencyStats>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchBench {
+    pub collection_id: String,
+    pub queries: usize,
+    /// Embedding each query once, uncached
+    pub query_embedding: Option<LatencyStats>,
+    /// Qdrant search alone, repeated `search_iterations` times per query
+    pub vector_search: Option<LatencyStats>,
+}
+
+/// Everything `run_bench` measured; stages that couldn't run are listed in `skipped`
+#[derive(Debug, Clone, Serialize)]
+pub struct BenchReport {
+    pub root_path: PathBuf,
+    pub started_at: chrono::DateTime<chrono::Utc>,
+    pub parse: ParseBench,
+    pub chunking: ChunkBench,
+    pub embedding: Option<EmbeddingBench>,
+    pub upsert: Option<UpsertBench>,
+    pub search: Option<SearchBench>,
+    pub skipped: Vec<SkippedStage>,
+}
+
+/// A stage `run_bench` couldn't measure
+#[derive(Debug, Clone, Serialize)]
+pub struct SkippedStage {
+    pub stage: &'static str,
+    pub reason: String,
+}
+
+impl SkippedStage {
+    fn new(stage: &'static str, reason: impl ToString) -> Self {
+        Self {
+            stage,
+            reason: reason.to_string(),
+        }
+    }
+}
+
+fn per_sec(count: f64, elapsed: Duration) -> f64 {
+    let seconds = elapsed.as_secs_f64();
+    if seconds > 0.0 { count / seconds } else { 0.0 }
+}
+
+/// Measure each stage of indexing and searching `root_path`
+/// Parsing and chunking always run, offline. Embedding and upserting need the
+/// embedding API and Qdrant, and upserts go to a scratch collection that is deleted
+/// afterwards; search needs the project to be indexed. A stage whose service is
+/// unavailable is skipped and reported rather than failing the run.
+pub async fn run_bench<P: AsRef<Path>>(
+    root_path: P,
+    options: &BenchOptions,
+) -> Result<BenchReport, anyhow::Error> {
+    let root_path = root_path.as_ref();
+    let started_at = chrono::Utc::now();
+    let config = IndexConfig::load(root_path)?;
+    let mut skipped = Vec::new();
+
+    let mut files = Vec::new();
+    walk_codebase_files(root_path, |path| {
+        if is_supported_file_extension(path) {
+            files.push(path.to_path_buf());
+        }
+        Ok(true)
+    })?;
+
+    info!("Benchmarking parsing of {} files", files.len());
+    let mut parser = SymbolParser::new()?;
+    let mut parsed: Vec<Vec<Symbol>> = Vec::with_capacity(files.len());
+    let mut bytes = 0;
+    let mut errors = 0;
+    let parse_started = Instant::now();
+    for file in &files {
+        bytes += std::fs::metadata(file).map(|m| m.len()).unwrap_or_default();
+        match parser.parse_file(file) {
+            Ok(symbols) => parsed.push(symbols),
+            Err(e) => {
+                warn!("Failed to parse {}: {e}", file.display());
+                errors += 1;
+            }
+        }
+    }
+    let parse_elapsed = parse_started.elapsed();
+    let parse = ParseBench {
+        files: files.len(),
+        bytes,
+        symbols: parsed.iter().map(Vec::len).sum(),
+        errors,
+        seconds: parse_elapsed.as_secs_f64(),
+        files_per_sec: per_sec(files.len() as f64, parse_elapsed),
+        mb_per_sec: per_sec(bytes as f64 / 1_000_000.0, parse_elapsed),
+    };
+
+    info!("Benchmarking chunking of {} symbols", parse.symbols);
+    let mut chunker = HierarchicalChunker::new(config.chunking_options())?;
+    let mut chunks: Vec<CodeChunk> = Vec::new();
+    let chunk_started = Instant::now();
+    for symbols in &parsed {
+        match chunker.chunk_symbols(symbols) {
+            Ok(file_chunks) => chunks.extend(file_chunks),
+            Err(e) => warn!("Failed to chunk symbols: {e}"),
+        }
+    }
+    let chunk_elapsed = chunk_started.elapsed();
+    let chunking = ChunkBench {
+        chunks: chunks.len(),
+        seconds: chunk_elapsed.as_secs_f64(),
+        chunks_per_sec: per_sec(chunks.len() as f64, chunk_elapsed),
+    };
+
+    let (embedding, embedded) = match bench_embedding(&chunks, options.embed_chunks).await {
+        Ok(Some((bench, embedded))) => (Some(bench), embedded),
+        Ok(None) => {
+            skipped.push(SkippedStage::new("embedding", "no chunks to embed"));
+            (None, Vec::new())
+        }
+        Err(e) => {
+            skipped.push(SkippedStage::new("embedding", e));
+            (None, Vec::new())
+        }
+    };
+
+    let upsert = if embedded.is_empty() || options.upsert_points == 0 {
+        skipped.push(SkippedStage::new("upsert", "no embedded chunks"));
+        None
+    } else {
+        match bench_upsert(root_path, &embedded, options.upsert_points).await {
+            Ok(bench) => Some(bench),
+            Err(e) => {
+                skipped.push(SkippedStage::new("upsert", e));
+                None
+            }
+        }
+    };
+
+    let search = if options.queries.is_empty() {
+        None
+    } else {
+        match bench_search(root_path, options).await {
+            Ok(bench) => Some(bench),
+            Err(e) => {
+                skipped.push(SkippedStage::new("search", e));
+                None
+            }
+        }
+    };
+
+    Ok(BenchReport {
+        root_path: root_path.to_path_buf(),
+        started_at,
+        parse,
+        chunking,
+        embedding,
+        upsert,
+        search,
+        skipped,
+    })
+}
+
+/// Embed up to `sample` chunks, timing each batch
+async fn bench_embedding(
+    chunks: &[CodeChunk],
+    sample: usize,
+) -> Result<Option<(EmbeddingBench, Vec<EmbeddedChunk>)>, anyhow::Error> {
+    let sample = &chunks[..sample.min(chunks.len())];
+    if sample.is_empty() {
+        return Ok(None);
+    }
+    let client = get_embedding_client()?;
+    info!("Benchmarking embedding of {} chunks", sample.len());
+
+    let mut batch_latencies = Vec::new();
+    let started = Instant::now();
+    let mut batch_started = started;
+    let embedded = client
+        .embed_chunks_with_progress(sample, |_| {
+            let now = Instant::now();
+            batch_latencies.push(now - batch_started);
+            batch_started = now;
+        })
+        .await?;
+    let elapsed = started.elapsed();
+
+    let config = client.config();
+    let bench = EmbeddingBench {
+        provider: config.provider.to_string(),
+        model: config.model.clone(),
+        batch_size: config.batch_size,
+        chunks: embedded.len(),
+        chunks_per_sec: per_sec(embedded.len() as f64, elapsed),
+        batch_latency: LatencyStats::from_samples(&batch_latencies),
+    };
+    Ok(Some((bench, embedded)))
+}
+
+/// Upsert `points` copies of the embedded chunks into a scratch collection
+async fn bench_upsert(
+    root_path: &Path,
+    embedded: &[EmbeddedChunk],
+    points: usize,
+) -> Result<UpsertBench, anyhow::Error> {
+    let dimension = embedded
+        .first()
+        .map(|chunk| chunk.embedding.len())
+        .unwrap_or_default();
+    let templates = embedded
+        .iter()
+        .map(|chunk| chunk_to_point(root_path, chunk.clone()).map(|point| point.point))
+        .collect::<Result<Vec<PointStruct>, _>>()?;
+    let collection_id = format!("{COLLECTION_PREFIX}bench_{}", uuid::Uuid::new_v4().simple());
+
+    QDRANT_CLIENT
+        .create_collection(
+            CreateCollectionBuilder::new(collection_id.clone())
+                .vectors_config(VectorParamsBuilder::new(dimension as u64, Distance::Cosine)),
+        )
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to create collection {collection_id}: {e}"))?;
+    info!("Benchmarking upserts of {points} points into {collection_id}");
+
+    let mut batch_latencies = Vec::new();
+    let started = Instant::now();
+    let mut result = Ok(());
+    let mut remaining = points;
+    while remaining > 0 {
+        let batch: Vec<PointStruct> = templates
+            .iter()
+            .cycle()
+            .take(remaining.min(UPSERT_BATCH_SIZE))
+            .map(|template| {
+                let mut point = template.clone();
+                point.id = Some(PointId::from(uuid::Uuid::new_v4().to_string()));
+                point
+            })
+            .collect();
+        remaining -= batch.len();
+        let batch_started = Instant::now();
+        if let Err(e) = QDRANT_CLIENT
+            .upsert_points(UpsertPointsBuilder::new(collection_id.as_str(), batch).wait(true))
+            .await
+        {
+            result = Err(anyhow::anyhow!(
+                "Failed to upsert into {collection_id}: {e}"
+            ));
+            break;
+        }
+        batch_latencies.push(batch_started.elapsed());
+    }
+    let elapsed = started.elapsed();
+
+    if let Err(e) = QDRANT_CLIENT
+        .delete_collection(collection_id.as_str())
+        .await
+    {
+        warn!("Failed to delete scratch collection {collection_id}: {e}");
+    }
+    result?;
+
+    Ok(UpsertBench {
+        points,
+        batch_size: UPSERT_BATCH_SIZE,
+        seconds: elapsed.as_secs_f64(),
+        points_per_sec: per_sec(points as f64, elapsed),
+        batch_latency: LatencyStats::from_samples(&batch_latencies),
+    })
+}
+
+/// Time query embedding and vector search against the project's collection
+async fn bench_search(
+    root_path: &Path,
+    options: &BenchOptions,
+) -> Result<SearchBench, anyhow::Error> {
+    let collection_id = generate_collection_id(root_path);
+    let exists = QDRANT_CLIENT
+        .collection_exists(collection_id.as_str())
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to check collection {collection_id}: {e}"))?;
+    if !exists {
+        anyhow::bail!(
+            "{} is not indexed; run index-codebase first",
+            root_path.display()
+        );
+    }
+    let client = get_embedding_client()?;
+    info!(
+        "Benchmarking {} queries against {collection_id}",
+        options.queries.len()
+    );
+
+    let mut embedding_latencies = Vec::new();
+    let mut search_latencies = Vec::new();
+    for query in &options.queries {
+        let started = Instant::now();
+        let vector = client.embed_query(query).await?;
+        embedding_latencies.push(started.elapsed());
+
+        for _ in 0..options.search_iterations {
+            let started = Instant::now();
+            QDRANT_CLIENT
+                .search_points(SearchPointsBuilder::new(
+                    collection_id.as_str(),
+                    vector.clone(),
+                    options.search_limit as u64,
+                ))
+                .await?;
+            search_latencies.push(started.elapsed());
+        }
+    }
+
+    Ok(SearchBench {
+        collection_id,
+        queries: options.queries.len(),
+        query_embedding: LatencyStats::from_samples(&embedding_latencies),
+        vector_search: LatencyStats::from_samples(&search_latencies),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::expect_used)]
+    use super::*;
+
+    #[test]
+    fn test_latency_percentiles() {
+        let samples: Vec<Duration> = (1..=20).map(Duration::from_millis).collect();
+        let stats = LatencyStats::from_samples(&samples).expect("non-empty sample");
+        assert_eq!(stats.count, 20);
+        assert_eq!(stats.p50_ms, 10.0);
+        assert_eq!(stats.p95_ms, 19.0);
+        assert_eq!(stats.max_ms, 20.0);
+        assert!((stats.mean_ms - 10.5).abs() < 1e-9);
+
+        assert!(LatencyStats::from_samples(&[]).is_none());
+    }
+}
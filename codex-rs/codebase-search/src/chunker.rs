@@ -7,7 +7,12 @@ use tracing::warn;
 
 use crate::symbol::SupportedLanguage;
 use crate::symbol::Symbol;
+use crate::symbol::SymbolKind;
+use crate::symbol::SymbolMetrics;
+use crate::symbol::SymbolModifiers;
 use crate::symbol::SymbolParser;
+use crate::symbol::TestFilter;
+use crate::symbol::symbol_id;
 
 /// Represents a chunk of code ready for embedding
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -26,6 +31,21 @@ pub struct CodeChunk {
     pub symbol_kind: String,
     /// Context information (e.g., containing class/module)
     pub context: Option<String>,
+    /// Declaration of the symbol without its body
+    #[serde(default)]
+    pub signature: String,
+    /// Visibility and modifiers of the symbol
+    #[serde(default)]
+    pub modifiers: SymbolModifiers,
+    /// Size and branching of the whole symbol, even when this chunk is a piece of it
+    #[serde(default)]
+    pub metrics: SymbolMetrics,
+    /// Whether the symbol is test code
+    #[serde(default)]
+    pub is_test: bool,
+    /// `Symbol::id` of the symbol, which the chunk's point ID is derived from
+    #[serde(default)]
+    pub symbol_id: String,
     /// Metadata about the chunking process
     pub chunk_metadata: ChunkMetadata,
 }
@@ -44,7 +64,7 @@ pub struct ChunkMetadata {
 }
 
 /// Configuration options for the chunking process
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ChunkingOptions {
     /// Maximum number of lines per chunk
     pub max_lines_per_chunk: usize,
@@ -54,6 +74,9 @@ pub struct ChunkingOptions {
     pub include_metadata: bool,
     /// Maximum recursion depth for hierarchical chunking
     pub max_recursion_depth: usize,
+    /// Whether test code is chunked alongside the rest, left out or chunked alone
+    #[serde(default)]
+    pub tests: TestFilter,
 }
 
 impl Default for ChunkingOptions {
@@ -63,6 +86,7 @@ impl Default for ChunkingOptions {
             min_lines_per_chunk: 5,
             include_metadata: true,
             max_recursion_depth: 5,
+            tests: TestFilter::Include,
         }
     }
 }
@@ -88,9 +112,21 @@ impl HierarchicalChunker {
             symbols.len()
         );
 
-        for symbol in symbols {
-            let chunks = self.chunk_symbol_recursive(symbol, 0)?;
-            all_chunks.extend(chunks);
+        for file_symbols in symbols.chunk_by(|a, b| a.file_path == b.file_path) {
+            let file_symbols: Vec<Symbol> = file_symbols
+                .iter()
+                .filter(|symbol| self.options.tests.keeps(symbol.is_test))
+                .cloned()
+                .collect();
+            all_chunks.extend(self.create_file_summary_chunk(&file_symbols));
+            // Imports are only embedded as part of their file's summary
+            for symbol in file_symbols
+                .iter()
+                .filter(|symbol| symbol.kind != SymbolKind::Import)
+            {
+                let chunks = self.chunk_symbol_recursive(symbol, 0)?;
+                all_chunks.extend(chunks);
+            }
         }
 
         info!(
@@ -214,7 +250,9 @@ impl HierarchicalChunker {
             .into_iter()
             .filter(|sub_sym| {
                 let sub_size = sub_sym.end_line - sub_sym.start_line + 1;
-                sub_size >= self.options.min_lines_per_chunk && sub_sym.name != symbol.name
+                sub_size >= self.options.min_lines_per_chunk
+                    && sub_sym.name != symbol.name
+                    && sub_sym.kind != SymbolKind::Import
             })
             .collect();
 
@@ -240,8 +278,6 @@ impl HierarchicalChunker {
 
     /// Determine if we should create a container chunk for organizational purposes
     fn should_create_container_chunk(&self, symbol: &Symbol, sub_symbols: &[Symbol]) -> bool {
-        use crate::symbol::SymbolKind;
-
         matches!(
             symbol.kind,
             SymbolKind::Impl | SymbolKind::Module | SymbolKind::Struct | SymbolKind::Trait
@@ -268,10 +304,10 @@ impl HierarchicalChunker {
                     .map(|s| s.name.as_str())
                     .collect::<Vec<_>>()
                     .join(", "),
-                self.extract_container_signature(symbol)
+                container_outline(symbol, sub_symbols)
             )
         } else {
-            self.extract_container_signature(symbol)
+            container_outline(symbol, sub_symbols)
         };
 
         CodeChunk {
@@ -282,6 +318,11 @@ impl HierarchicalChunker {
             symbol_name: symbol.name.clone(),
             symbol_kind: format!("{:?}", symbol.kind),
             context: symbol.context.clone(),
+            signature: symbol.signature.clone(),
+            modifiers: symbol.modifiers.clone(),
+            metrics: symbol.metrics,
+            is_test: symbol.is_test,
+            symbol_id: symbol.id.clone(),
             chunk_metadata: ChunkMetadata {
                 is_split: true,
                 original_size_lines: symbol.end_line - symbol.start_line + 1,
@@ -291,20 +332,67 @@ impl HierarchicalChunker {
         }
     }
 
-    /// Extract just the signature/header of a container symbol (without the full body)
-    fn extract_container_signature(&self, symbol: &Symbol) -> String {
-        // For now, just take the first few lines that likely contain the signature
-        let lines: Vec<&str> = symbol.content.lines().collect();
-        let signature_lines = std::cmp::min(10, lines.len());
-
-        let mut signature = lines[..signature_lines].join("\n");
-
-        // If we truncated, indicate it
-        if signature_lines < lines.len() {
-            signature.push_str("\n\n// ... (content continues) ...");
-        }
+    /// Summarize a file's imports and top-level symbols in one chunk, so its dependencies
+    /// are embedded alongside what it defines; `None` for files without imports
+    fn create_file_summary_chunk(&self, file_symbols: &[Symbol]) -> Option<CodeChunk> {
+        let imports: Vec<&Symbol> = file_symbols
+            .iter()
+            .filter(|symbol| symbol.kind == SymbolKind::Import)
+            .collect();
+        let (first, last) = (imports.first()?, imports.last()?);
+        let statements = imports
+            .iter()
+            .map(|import| import.content.as_str())
+            .collect::<Vec<_>>()
+            .join("\n");
+        let metrics = SymbolMetrics {
+            lines: last.end_line - first.start_line + 1,
+            tokens: statements.chars().count().div_ceil(4),
+            branches: 0,
+        };
+        let symbol_name = first
+            .file_path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let id = symbol_id(&SymbolKind::Import, &symbol_name, &statements);
+        let content = if self.options.include_metadata {
+            let defined: Vec<&str> = file_symbols
+                .iter()
+                .filter(|symbol| symbol.kind != SymbolKind::Import && symbol.context.is_none())
+                .map(|symbol| symbol.name.as_str())
+                .collect();
+            format!(
+                "// File: {}, Imports: {}\n// Defines: {}\n\n{}",
+                first.file_path.display(),
+                imports.len(),
+                defined.join(", "),
+                statements
+            )
+        } else {
+            statements
+        };
 
-        signature
+        Some(CodeChunk {
+            content,
+            file_path: first.file_path.clone(),
+            start_line: first.start_line,
+            end_line: last.end_line,
+            symbol_name,
+            symbol_kind: format!("{:?}", SymbolKind::Import),
+            context: None,
+            signature: String::new(),
+            modifiers: SymbolModifiers::default(),
+            metrics,
+            is_test: imports.iter().all(|import| import.is_test),
+            symbol_id: id,
+            chunk_metadata: ChunkMetadata {
+                is_split: false,
+                original_size_lines: last.end_line - first.start_line + 1,
+                chunk_depth: 0,
+                is_container: true,
+            },
+        })
     }
 
     /// Create a single chunk from a symbol
@@ -334,6 +422,11 @@ impl HierarchicalChunker {
             symbol_name: symbol.name.clone(),
             symbol_kind: format!("{:?}", symbol.kind),
             context: symbol.context.clone(),
+            signature: symbol.signature.clone(),
+            modifiers: symbol.modifiers.clone(),
+            metrics: symbol.metrics,
+            is_test: symbol.is_test,
+            symbol_id: symbol.id.clone(),
             chunk_metadata: ChunkMetadata {
                 is_split,
                 original_size_lines: symbol.end_line - symbol.start_line + 1,
@@ -344,7 +437,21 @@ impl HierarchicalChunker {
     }
 }
 
+/// A container's signature followed by the indented signatures of its sub-symbols,
+/// standing in for the container's body
+fn container_outline(symbol: &Symbol, sub_symbols: &[Symbol]) -> String {
+    let mut outline = symbol.signature.clone();
+    for sub_symbol in sub_symbols {
+        for line in sub_symbol.signature.lines() {
+            outline.push_str("\n    ");
+            outline.push_str(line.trim_start());
+        }
+    }
+    outline
+}
+
 /// Index a codebase and create chunks ready for embedding using hierarchical strategy
+#[cfg(feature = "native")]
 pub async fn chunk_codebase<P: AsRef<std::path::Path>>(
     root_path: P,
     chunking_options: ChunkingOptions,
@@ -362,16 +469,59 @@ pub async fn chunk_codebase<P: AsRef<std::path::Path>>(
     Ok(embedded_chunks)
 }
 
+#[cfg(feature = "native")]
 pub async fn chunk_codefile<P: AsRef<std::path::Path>>(
     file_path: P,
     chunking_options: ChunkingOptions,
+) -> Result<Vec<crate::embedding::EmbeddedChunk>, anyhow::Error> {
+    // Use global embedding client
+    let embedding_client = crate::embedding::get_embedding_client()?;
+    chunk_codefile_with_client(file_path, chunking_options, &embedding_client).await
+}
+
+/// Chunk a single file and embed the chunks with the given embedding client
+#[cfg(feature = "native")]
+pub async fn chunk_codefile_with_client<P: AsRef<std::path::Path>>(
+    file_path: P,
+    chunking_options: ChunkingOptions,
+    embedding_client: &crate::embedding::EmbeddingClient,
 ) -> Result<Vec<crate::embedding::EmbeddedChunk>, anyhow::Error> {
     let mut parser = SymbolParser::new()?;
     let symbols = parser.parse_file(&file_path)?;
     let mut chunker = HierarchicalChunker::new(chunking_options)?;
     let chunks = chunker.chunk_symbols(&symbols)?;
-    // Use global embedding client
-    let embedding_client = crate::embedding::get_embedding_client()?;
     let embedded_chunks = embedding_client.embed_chunks(&chunks).await?;
     Ok(embedded_chunks)
 }
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::expect_used)]
+    use super::*;
+
+    #[test]
+    fn test_imports_are_chunked_as_a_file_summary() {
+        let source = "use reqwest::Client;\nuse std::time::Duration;\n\nfn fetch() {}\n";
+        let symbols = SymbolParser::new()
+            .expect("Failed to create parser")
+            .parse_source("src/fetch.rs", source)
+            .expect("Failed to parse");
+        let chunks = HierarchicalChunker::new(ChunkingOptions::default())
+            .expect("Failed to create chunker")
+            .chunk_symbols(&symbols)
+            .expect("Failed to chunk");
+
+        let kinds: Vec<(&str, &str)> = chunks
+            .iter()
+            .map(|chunk| (chunk.symbol_kind.as_str(), chunk.symbol_name.as_str()))
+            .collect();
+        assert_eq!(kinds, vec![("Import", "fetch.rs"), ("Function", "fetch")]);
+        assert_eq!((chunks[0].start_line, chunks[0].end_line), (1, 2));
+        assert!(chunks[0].content.contains("// Defines: fetch"));
+        assert!(
+            chunks[0]
+                .content
+                .ends_with("use reqwest::Client;\nuse std::time::Duration;")
+        );
+    }
+}
@@ -0,0 +1,159 @@
+//! High-level facade over the indexing, search and watch entry points
+
+use std::path::Path;
+use std::path::PathBuf;
+
+use crate::embedding::configure_embedding;
+use crate::embedding::env_embedding_settings;
+use crate::file_watcher::FileWatcherBuilder;
+use crate::index_config::EmbeddingSettings;
+use crate::index_config::IndexConfig;
+use crate::index_config::QdrantSettings;
+use crate::indexer::IndexService;
+use crate::retriever::SearchFilter;
+use crate::retriever::SearchResult;
+use crate::retriever::search_codebase_filtered;
+use crate::vector_db::IndexStatus;
+use crate::vector_db::configure_qdrant;
+use crate::vector_db::env_qdrant_settings;
+use crate::vector_db::index_status;
+use crate::vector_db::init_session;
+use crate::vector_db::restore_session;
+
+/// Client settings for `CodebaseIndex::open`
+/// Fields set here override the project's `.codex/index.toml`, the user config and
+/// the `CODEX_*` environment variables, the same way CLI flags do.
+#[derive(Debug, Clone, Default)]
+pub struct Config {
+    pub embedding: EmbeddingSettings,
+    pub qdrant: QdrantSettings,
+}
+
+/// Options for `CodebaseIndex::search`
+#[derive(Debug, Clone)]
+pub struct SearchOptions {
+    pub limit: usize,
+    pub min_score: f32,
+    pub filter: SearchFilter,
+}
+
+impl Default for SearchOptions {
+    fn default() -> Self {
+        Self {
+            limit: 10,
+            min_score: 0.7,
+            filter: SearchFilter::default(),
+        }
+    }
+}
+
+/// The index of one project, for embedding codebase search in another program
+///
+/// ```no_run
+/// # async fn example() -> anyhow::Result<()> {
+/// use codebase_search::CodebaseIndex;
+/// use codebase_search::Config;
+/// use codebase_search::SearchOptions;
+///
+/// let index = CodebaseIndex::open("path/to/project", Config::default())?;
+/// index.update().await?;
+/// for result in index.search("where are sessions restored?", SearchOptions::default()).await? {
+///     println!("{}:{}", result.chunk.file_path.display(), result.chunk.start_line);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+///
+/// The embedding and Qdrant clients are shared by the whole process, so every index
+/// opened in it must resolve to the same client settings.
+#[derive(Debug, Clone)]
+pub struct CodebaseIndex {
+    root_path: PathBuf,
+}
+
+impl CodebaseIndex {
+    /// Open the project at `path` and install its client settings
+    /// `path` is canonicalized here, so later calls don't depend on the working directory.
+    pub fn open<P: AsRef<Path>>(path: P, config: Config) -> Result<Self, anyhow::Error> {
+        let path = path.as_ref();
+        let root_path = path
+            .canonicalize()
+            .map_err(|e| anyhow::anyhow!("Failed to open {}: {e}", path.display()))?;
+        let project = IndexConfig::load(&root_path)?;
+        configure_embedding(
+            project
+                .embedding
+                .overridden_by(env_embedding_settings())
+                .overridden_by(config.embedding),
+        )?;
+        configure_qdrant(
+            project
+                .qdrant
+                .overridden_by(env_qdrant_settings())
+                .overridden_by(config.qdrant),
+        )?;
+        Ok(Self { root_path })
+    }
+
+    /// Canonical root of the project
+    pub fn root_path(&self) -> &Path {
+        &self.root_path
+    }
+
+    /// Build the index from scratch, replacing any existing one
+    pub async fn index(&self) -> Result<(), anyhow::Error> {
+        init_session(&self.root_path).await
+    }
+
+    /// Reindex the files changed since the last run, building the index if there is none
+    pub async fn update(&self) -> Result<(), anyhow::Error> {
+        restore_session(&self.root_path).await
+    }
+
+    /// Chunks most similar to `query`, best first
+    pub async fn search(
+        &self,
+        query: &str,
+        options: SearchOptions,
+    ) -> Result<Vec<SearchResult>, anyhow::Error> {
+        search_codebase_filtered(
+            query.to_string(),
+            &self.root_path,
+            options.limit,
+            options.min_score,
+            &options.filter,
+        )
+        .await
+    }
+
+    /// Inspect the index without changing it
+    pub async fn status(&self) -> Result<IndexStatus, anyhow::Error> {
+        index_status(&self.root_path).await
+    }
+
+    /// Bring the index up to date, then start watching the project for changes
+    /// Drive the returned service with `IndexService::run` and stop it with
+    /// `IndexService::stop`.
+    pub async fn watch(&self) -> Result<IndexService, anyhow::Error> {
+        self.update().await?;
+        let mut watcher = FileWatcherBuilder::new().root_path(&self.root_path).build();
+        watcher.start()?;
+        IndexService::new(watcher)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn open_requires_an_existing_directory() {
+        let Err(e) = CodebaseIndex::open("/nonexistent/project", Config::default()) else {
+            panic!("opened a missing directory");
+        };
+        assert!(
+            e.to_string()
+                .starts_with("Failed to open /nonexistent/project")
+        );
+    }
+}
@@ -0,0 +1,209 @@
+use std::path::PathBuf;
+
+use serde::Serialize;
+
+use crate::retriever::SearchResult;
+
+/// Smallest remainder of the budget worth filling with a truncated chunk
+const MIN_TRUNCATED_TOKENS: usize = 64;
+
+/// Rough token count for budgeting, at about four characters per token
+pub fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(4)
+}
+
+/// A search result included in a built context, numbered as it's cited
+#[derive(Debug, Clone, Serialize)]
+pub struct ContextSource {
+    /// 1-based number the excerpt is labelled with, e.g. `[3]`
+    pub index: usize,
+    pub id: String,
+    pub file_path: PathBuf,
+    pub start_line: usize,
+    pub end_line: usize,
+    pub symbol_name: String,
+    pub score: f32,
+    /// Whether the excerpt was cut short to fit the budget
+    pub truncated: bool,
+}
+
+/// Retrieved code formatted as numbered excerpts for a language model prompt
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct BuiltContext {
+    pub text: String,
+    pub sources: Vec<ContextSource>,
+    /// Estimated tokens in `text`, summed per excerpt
+    pub tokens: usize,
+    /// Results left out because the budget ran out
+    pub omitted: usize,
+}
+
+/// Packs search results, best first, into a context that fits a token budget
+/// Results that don't fit are skipped so smaller, lower-ranked ones can still be used;
+/// the first one that doesn't fit is truncated at a line boundary if enough budget is left.
+#[derive(Debug, Clone)]
+pub struct ContextBuilder {
+    max_tokens: usize,
+}
+
+impl ContextBuilder {
+    pub fn new(max_tokens: usize) -> Self {
+        Self { max_tokens }
+    }
+
+    pub fn build(&self, results: &[SearchResult]) -> BuiltContext {
+        let mut context = BuiltContext::default();
+        for result in results {
+            let index = context.sources.len() + 1;
+            let header = excerpt_header(index, result);
+            let block = excerpt(&header, &result.chunk.content);
+            let remaining = self.max_tokens.saturating_sub(context.tokens);
+            let block_tokens = estimate_tokens(&block);
+
+            let (block, block_tokens, truncated) = if block_tokens <= remaining {
+                (block, block_tokens, false)
+            } else if remaining >= MIN_TRUNCATED_TOKENS {
+                match truncated_excerpt(&header, &result.chunk.content, remaining) {
+                    Some(block) => {
+                        let tokens = estimate_tokens(&block);
+                        (block, tokens, true)
+                    }
+                    None => {
+                        context.omitted += 1;
+                        continue;
+                    }
+                }
+            } else {
+                context.omitted += 1;
+                continue;
+            };
+
+            context.text.push_str(&block);
+            context.tokens += block_tokens;
+            context.sources.push(ContextSource {
+                index,
+                id: result.id.clone(),
+                file_path: result.chunk.file_path.clone(),
+                start_line: result.chunk.start_line,
+                end_line: result.chunk.end_line,
+                symbol_name: result.chunk.symbol_name.clone(),
+                score: result.score,
+                truncated,
+            });
+        }
+        context
+    }
+}
+
+fn excerpt_header(index: usize, result: &SearchResult) -> String {
+    format!(
+        "[{index}] {}:{}-{} ({} {}, score {:.2})",
+        result.chunk.file_path.display(),
+        result.chunk.start_line,
+        result.chunk.end_line,
+        result.chunk.symbol_kind,
+        result.chunk.symbol_name,
+        result.score
+    )
+}
+
+fn excerpt(header: &str, content: &str) -> String {
+    format!("{header}\n```\n{}\n```\n\n", content.trim_end())
+}
+
+/// The longest line-aligned prefix of `content` whose excerpt fits in `max_tokens`
+fn truncated_excerpt(header: &str, content: &str, max_tokens: usize) -> Option<String> {
+    const MARKER: &str = "// ... (truncated) ...";
+    let lines: Vec<&str> = content.lines().collect();
+    let fits = |count: usize| {
+        let block = excerpt(header, &format!("{}\n{MARKER}", lines[..count].join("\n")));
+        (estimate_tokens(&block) <= max_tokens).then_some(block)
+    };
+
+    // Binary search for the largest line count that fits
+    let (mut low, mut high) = (1, lines.len());
+    let mut best = None;
+    while low <= high {
+        let mid = low + (high - low) / 2;
+        match fits(mid) {
+            Some(block) => {
+                best = Some(block);
+                low = mid + 1;
+            }
+            None => high = mid - 1,
+        }
+    }
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunker::ChunkMetadata;
+    use crate::chunker::CodeChunk;
+
+    fn result(file_path: &str, lines: usize) -> SearchResult {
+        let content = (1..=lines)
+            .map(|line| format!("let value_{line} = compute({line});"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        SearchResult {
+            id: file_path.to_string(),
+            chunk: CodeChunk {
+                content,
+                file_path: PathBuf::from(file_path),
+                start_line: 1,
+                end_line: lines,
+                symbol_name: "compute_all".to_string(),
+                symbol_kind: "Function".to_string(),
+                context: None,
+                signature: String::new(),
+                modifiers: Default::default(),
+                metrics: Default::default(),
+                is_test: false,
+                symbol_id: String::new(),
+                chunk_metadata: ChunkMetadata {
+                    is_split: false,
+                    original_size_lines: lines,
+                    chunk_depth: 0,
+                    is_container: false,
+                },
+            },
+            score: 0.9,
+        }
+    }
+
+    #[test]
+    fn test_context_stays_within_budget() {
+        let results = vec![
+            result("src/small.rs", 5),
+            result("src/huge.rs", 400),
+            result("src/tiny.rs", 2),
+        ];
+        let context = ContextBuilder::new(300).build(&results);
+
+        assert!(context.tokens <= 300);
+        assert!(estimate_tokens(&context.text) <= context.tokens);
+        let files: Vec<&str> = context
+            .sources
+            .iter()
+            .map(|source| source.file_path.to_str().unwrap_or_default())
+            .collect();
+        assert_eq!(files, vec!["src/small.rs", "src/huge.rs"]);
+        assert!(!context.sources[0].truncated);
+        assert!(context.sources[1].truncated);
+        assert!(context.text.contains("[2] src/huge.rs:1-400"));
+        assert!(context.text.contains("(truncated)"));
+        assert_eq!(context.omitted, 1);
+    }
+
+    #[test]
+    fn test_results_past_the_budget_are_omitted() {
+        let results = vec![result("src/a.rs", 40), result("src/b.rs", 40)];
+        let context = ContextBuilder::new(60).build(&results);
+
+        assert!(context.tokens <= 60);
+        assert_eq!(context.sources.len(), 0);
+        assert_eq!(context.omitted, 2);
+    }
+}
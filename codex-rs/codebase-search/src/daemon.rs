@@ -0,0 +1,555 @@
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::PoisonError;
+use std::time::Duration;
+
+use mcp_types::JSONRPC_VERSION;
+use mcp_types::JSONRPCError;
+use mcp_types::JSONRPCErrorError;
+use mcp_types::JSONRPCMessage;
+use mcp_types::JSONRPCNotification;
+use mcp_types::JSONRPCRequest;
+use mcp_types::JSONRPCResponse;
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use serde_json::json;
+use tokio::io::AsyncBufReadExt;
+use tokio::io::AsyncRead;
+use tokio::io::AsyncWrite;
+use tokio::io::AsyncWriteExt;
+use tokio::io::BufReader;
+use tokio::sync::Notify;
+use tokio::sync::broadcast;
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+use tracing::debug;
+use tracing::info;
+use tracing::warn;
+
+use crate::retriever::SearchFilter;
+use crate::retriever::SimilarTo;
+use crate::retriever::find_similar_code;
+use crate::retriever::search_codebase_filtered;
+use crate::vector_db::index_status;
+use crate::vector_db::update_files;
+
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const INTERNAL_ERROR: i64 = -32603;
+
+/// Messages waiting to be written to one client
+const OUTGOING_CAPACITY: usize = 128;
+
+/// Index notifications kept for a client that is slow to read them
+const EVENT_CAPACITY: usize = 64;
+
+/// Quiet period after a `files_changed` notification before the files are reindexed,
+/// so a burst of saves becomes one update
+const UPDATE_DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// Params of `search`
+#[derive(Debug, Clone, Deserialize)]
+struct SearchParams {
+    query: String,
+    directory: Option<PathBuf>,
+    #[serde(default = "default_limit")]
+    limit: usize,
+    #[serde(default = "default_min_score")]
+    min_score: f32,
+    #[serde(flatten)]
+    filter: SearchFilter,
+}
+
+/// Params of `find_similar`: a `chunk_id` from a search result, or a `file` and `line`
+#[derive(Debug, Clone, Deserialize)]
+struct FindSimilarParams {
+    directory: Option<PathBuf>,
+    chunk_id: Option<String>,
+    /// Absolute, or relative to the codebase directory
+    file: Option<PathBuf>,
+    /// 1-based line within `file`
+    line: Option<usize>,
+    #[serde(default = "default_limit")]
+    limit: usize,
+    #[serde(default = "default_min_score")]
+    min_score: f32,
+    #[serde(flatten)]
+    filter: SearchFilter,
+}
+
+/// Params of `index_status`
+#[derive(Debug, Clone, Deserialize)]
+struct DirectoryParams {
+    directory: Option<PathBuf>,
+}
+
+/// Params of the `files_changed` notification; paths are absolute or relative to the
+/// codebase directory
+#[derive(Debug, Clone, Deserialize)]
+struct FilesChangedParams {
+    directory: Option<PathBuf>,
+    #[serde(default)]
+    changed: Vec<PathBuf>,
+    #[serde(default)]
+    deleted: Vec<PathBuf>,
+}
+
+fn default_limit() -> usize {
+    10
+}
+
+fn default_min_score() -> f32 {
+    0.3
+}
+
+/// Root-relative files an editor reported since the last update of a codebase
+#[derive(Debug, Default)]
+struct PendingFiles {
+    changed: BTreeSet<String>,
+    deleted: BTreeSet<String>,
+}
+
+/// A long-lived JSON-RPC 2.0 server for editor plugins
+///
+/// Messages are newline-delimited JSON over stdio or a Unix socket. Clients call
+/// `search`, `find_similar` and `index_status`, and send `files_changed` notifications
+/// as files are saved; the daemon reindexes those files in the background and
+/// notifies every client with `index_updated` or `index_failed`. All clients share
+/// the process's embedding client and Qdrant connection, so requests after the first
+/// skip connection setup.
+#[derive(Debug, Clone)]
+pub struct Daemon {
+    /// Codebase used by requests that don't name one; relative paths are resolved against it
+    default_directory: PathBuf,
+    pending: Arc<Mutex<BTreeMap<PathBuf, PendingFiles>>>,
+    pending_changed: Arc<Notify>,
+    events: broadcast::Sender<JSONRPCMessage>,
+    shutdown: CancellationToken,
+}
+
+impl Daemon {
+    pub fn new(default_directory: PathBuf) -> Self {
+        let (events, _) = broadcast::channel(EVENT_CAPACITY);
+        Self {
+            default_directory,
+            pending: Arc::default(),
+            pending_changed: Arc::default(),
+            events,
+            shutdown: CancellationToken::new(),
+        }
+    }
+
+    /// Cancelled by a `shutdown` request
+    pub fn shutdown_token(&self) -> CancellationToken {
+        self.shutdown.clone()
+    }
+
+    /// Serve one client on stdin and stdout until stdin closes or `shutdown` is called
+    pub async fn serve_stdio(self) -> Result<(), anyhow::Error> {
+        let updates = tokio::spawn(self.clone().run_updates());
+        info!(
+            "Codebase search daemon serving {} on stdio",
+            self.default_directory.display()
+        );
+        let served = self
+            .serve_connection(tokio::io::stdin(), tokio::io::stdout())
+            .await;
+        self.shutdown.cancel();
+        let _ = updates.await;
+        served
+    }
+
+    /// Serve any number of clients on a Unix socket until `shutdown` is called
+    /// A stale socket file left by a previous daemon is replaced.
+    #[cfg(unix)]
+    pub async fn serve_unix(self, socket_path: &Path) -> Result<(), anyhow::Error> {
+        if socket_path.exists() {
+            std::fs::remove_file(socket_path)?;
+        }
+        let listener = tokio::net::UnixListener::bind(socket_path)
+            .map_err(|e| anyhow::anyhow!("Failed to listen on {}: {e}", socket_path.display()))?;
+        info!(
+            "Codebase search daemon listening on {}",
+            socket_path.display()
+        );
+
+        let updates = tokio::spawn(self.clone().run_updates());
+        loop {
+            tokio::select! {
+                () = self.shutdown.cancelled() => break,
+                accepted = listener.accept() => {
+                    let (stream, _) = accepted?;
+                    let daemon = self.clone();
+                    tokio::spawn(async move {
+                        let (reader, writer) = stream.into_split();
+                        if let Err(e) = daemon.serve_connection(reader, writer).await {
+                            warn!("Daemon client disconnected: {e}");
+                        }
+                    });
+                }
+            }
+        }
+        let _ = updates.await;
+        let _ = std::fs::remove_file(socket_path);
+        Ok(())
+    }
+
+    /// Answer requests from one client, forwarding index notifications to it as well
+    async fn serve_connection<R, W>(&self, reader: R, mut writer: W) -> Result<(), anyhow::Error>
+    where
+        R: AsyncRead + Unpin,
+        W: AsyncWrite + Unpin + Send + 'static,
+    {
+        let (tx, mut rx) = mpsc::channel::<JSONRPCMessage>(OUTGOING_CAPACITY);
+        let mut events = self.events.subscribe();
+        let writer_task = tokio::spawn(async move {
+            loop {
+                let message = tokio::select! {
+                    message = rx.recv() => match message {
+                        Some(message) => message,
+                        None => break,
+                    },
+                    event = events.recv() => match event {
+                        Ok(event) => event,
+                        Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                            warn!("Dropped {skipped} index notifications for a slow client");
+                            continue;
+                        }
+                        Err(broadcast::error::RecvError::Closed) => break,
+                    },
+                };
+                let mut line = serde_json::to_string(&message)?;
+                line.push('\n');
+                writer.write_all(line.as_bytes()).await?;
+                writer.flush().await?;
+            }
+            Ok::<(), anyhow::Error>(())
+        });
+
+        let mut lines = BufReader::new(reader).lines();
+        loop {
+            let line = tokio::select! {
+                () = self.shutdown.cancelled() => break,
+                line = lines.next_line() => match line? {
+                    Some(line) => line,
+                    None => break,
+                },
+            };
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str::<JSONRPCMessage>(&line) {
+                Ok(JSONRPCMessage::Request(request)) => {
+                    let daemon = self.clone();
+                    let tx = tx.clone();
+                    tokio::spawn(async move {
+                        let _ = tx.send(daemon.handle_request(request).await).await;
+                    });
+                }
+                Ok(JSONRPCMessage::Notification(notification)) => {
+                    self.handle_notification(notification);
+                }
+                Ok(message) => debug!("Ignoring daemon message: {message:?}"),
+                Err(e) => warn!("Ignoring malformed daemon message: {e}"),
+            }
+        }
+
+        // Requests still in flight hold senders, so their responses are written first
+        drop(tx);
+        writer_task.await?
+    }
+
+    /// Answer one request with a response or an error
+    pub async fn handle_request(&self, request: JSONRPCRequest) -> JSONRPCMessage {
+        let JSONRPCRequest {
+            id, method, params, ..
+        } = request;
+        let result = match method.as_str() {
+            "search" => match parse_params(params) {
+                Ok(params) => self.search(params).await,
+                Err(error) => Err(error),
+            },
+            "find_similar" => match parse_params(params) {
+                Ok(params) => self.find_similar(params).await,
+                Err(error) => Err(error),
+            },
+            "index_status" => match parse_params::<DirectoryParams>(params) {
+                Ok(params) => {
+                    let directory = self.resolve_directory(params.directory.as_deref());
+                    index_status(&directory)
+                        .await
+                        .and_then(|status| Ok(serde_json::to_value(status)?))
+                        .map_err(internal_error)
+                }
+                Err(error) => Err(error),
+            },
+            "shutdown" => {
+                info!("Daemon shutting down on request");
+                self.shutdown.cancel();
+                Ok(json!({}))
+            }
+            _ => Err(JSONRPCErrorError {
+                code: METHOD_NOT_FOUND,
+                data: None,
+                message: format!("Unknown method: {method}"),
+            }),
+        };
+        match result {
+            Ok(result) => JSONRPCMessage::Response(JSONRPCResponse {
+                id,
+                jsonrpc: JSONRPC_VERSION.to_string(),
+                result,
+            }),
+            Err(error) => JSONRPCMessage::Error(JSONRPCError {
+                error,
+                id,
+                jsonrpc: JSONRPC_VERSION.to_string(),
+            }),
+        }
+    }
+
+    fn handle_notification(&self, notification: JSONRPCNotification) {
+        match notification.method.as_str() {
+            "files_changed" => match parse_params::<FilesChangedParams>(notification.params) {
+                Ok(params) => self.queue_files(params),
+                Err(error) => warn!("Ignoring files_changed: {}", error.message),
+            },
+            method => debug!("Ignoring unknown notification {method}"),
+        }
+    }
+
+    async fn search(&self, params: SearchParams) -> Result<serde_json::Value, JSONRPCErrorError> {
+        let directory = self.resolve_directory(params.directory.as_deref());
+        let results = search_codebase_filtered(
+            params.query,
+            &directory,
+            params.limit,
+            params.min_score,
+            &params.filter,
+        )
+        .await
+        .map_err(internal_error)?;
+        Ok(json!({ "results": results }))
+    }
+
+    async fn find_similar(
+        &self,
+        params: FindSimilarParams,
+    ) -> Result<serde_json::Value, JSONRPCErrorError> {
+        let directory = self.resolve_directory(params.directory.as_deref());
+        let target = match (params.chunk_id, params.file, params.line) {
+            (Some(chunk_id), None, None) => SimilarTo::Chunk(chunk_id),
+            (None, Some(file), Some(line)) => SimilarTo::Location {
+                file_path: relative_path(&directory, &file),
+                line,
+            },
+            _ => {
+                return Err(JSONRPCErrorError {
+                    code: INVALID_PARAMS,
+                    data: None,
+                    message: "find_similar takes either chunk_id, or file and line".to_string(),
+                });
+            }
+        };
+        let results = find_similar_code(
+            &target,
+            &directory,
+            params.limit,
+            params.min_score,
+            &params.filter,
+        )
+        .await
+        .map_err(internal_error)?;
+        Ok(json!({ "results": results }))
+    }
+
+    fn queue_files(&self, params: FilesChangedParams) {
+        let directory = self.resolve_directory(params.directory.as_deref());
+        {
+            let mut pending = self.pending.lock().unwrap_or_else(PoisonError::into_inner);
+            let files = pending.entry(directory.clone()).or_default();
+            for path in &params.changed {
+                let path = relative_path(&directory, path);
+                files.deleted.remove(&path);
+                files.changed.insert(path);
+            }
+            for path in &params.deleted {
+                let path = relative_path(&directory, path);
+                files.changed.remove(&path);
+                files.deleted.insert(path);
+            }
+        }
+        self.pending_changed.notify_one();
+    }
+
+    /// Apply queued file changes, one codebase at a time, until shutdown
+    async fn run_updates(self) {
+        loop {
+            tokio::select! {
+                () = self.shutdown.cancelled() => return,
+                () = self.pending_changed.notified() => {}
+            }
+            tokio::select! {
+                () = self.shutdown.cancelled() => return,
+                () = tokio::time::sleep(UPDATE_DEBOUNCE) => {}
+            }
+
+            let batches =
+                std::mem::take(&mut *self.pending.lock().unwrap_or_else(PoisonError::into_inner));
+            for (directory, files) in batches {
+                let changed: Vec<String> = files.changed.into_iter().collect();
+                let deleted: Vec<String> = files.deleted.into_iter().collect();
+                info!(
+                    "Reindexing {} changed and {} deleted files in {}",
+                    changed.len(),
+                    deleted.len(),
+                    directory.display()
+                );
+                let event = match update_files(&directory, &changed, &deleted).await {
+                    Ok(()) => notification(
+                        "index_updated",
+                        json!({
+                            "directory": directory,
+                            "changed": changed,
+                            "deleted": deleted,
+                        }),
+                    ),
+                    Err(e) => {
+                        warn!("Reindexing {} failed: {e}", directory.display());
+                        notification(
+                            "index_failed",
+                            json!({ "directory": directory, "error": e.to_string() }),
+                        )
+                    }
+                };
+                // Nobody may be connected to hear about it, which is fine
+                let _ = self.events.send(event);
+            }
+        }
+    }
+
+    /// Resolve a request's `directory` the same way the CLI resolves its arguments
+    fn resolve_directory(&self, directory: Option<&Path>) -> PathBuf {
+        let directory = match directory {
+            Some(directory) => self.default_directory.join(directory),
+            None => self.default_directory.clone(),
+        };
+        directory.canonicalize().unwrap_or(directory)
+    }
+}
+
+/// `path` relative to the codebase `root`, as file states and payloads store it
+fn relative_path(root: &Path, path: &Path) -> String {
+    let path = if path.is_absolute() {
+        path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+    } else {
+        root.join(path)
+    };
+    path.strip_prefix(root)
+        .unwrap_or(&path)
+        .to_string_lossy()
+        .to_string()
+}
+
+/// Deserialize request params, treating missing ones as `{}`
+fn parse_params<T: DeserializeOwned>(
+    params: Option<serde_json::Value>,
+) -> Result<T, JSONRPCErrorError> {
+    serde_json::from_value(params.unwrap_or_else(|| json!({}))).map_err(|e| JSONRPCErrorError {
+        code: INVALID_PARAMS,
+        data: None,
+        message: format!("Invalid params: {e}"),
+    })
+}
+
+fn internal_error(error: anyhow::Error) -> JSONRPCErrorError {
+    JSONRPCErrorError {
+        code: INTERNAL_ERROR,
+        data: None,
+        message: format!("{error:#}"),
+    }
+}
+
+fn notification(method: &str, params: serde_json::Value) -> JSONRPCMessage {
+    JSONRPCMessage::Notification(JSONRPCNotification {
+        jsonrpc: JSONRPC_VERSION.to_string(),
+        method: method.to_string(),
+        params: Some(params),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::expect_used)]
+    use super::*;
+    use mcp_types::RequestId;
+    use tempfile::TempDir;
+
+    fn request(method: &str, params: serde_json::Value) -> JSONRPCRequest {
+        JSONRPCRequest {
+            id: RequestId::Integer(7),
+            jsonrpc: JSONRPC_VERSION.to_string(),
+            method: method.to_string(),
+            params: Some(params),
+        }
+    }
+
+    fn error_code(message: JSONRPCMessage) -> i64 {
+        match message {
+            JSONRPCMessage::Error(error) => error.error.code,
+            other => panic!("expected an error, got {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rejects_bad_requests_and_queues_changed_files() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let root = temp_dir
+            .path()
+            .canonicalize()
+            .expect("Failed to resolve temp dir");
+        let daemon = Daemon::new(root.clone());
+
+        let ambiguous = request(
+            "find_similar",
+            json!({"chunk_id": "abc", "file": "src/lib.rs", "line": 3}),
+        );
+        assert_eq!(
+            error_code(daemon.handle_request(ambiguous).await),
+            INVALID_PARAMS
+        );
+        assert_eq!(
+            error_code(daemon.handle_request(request("search", json!({}))).await),
+            INVALID_PARAMS
+        );
+        assert_eq!(
+            error_code(daemon.handle_request(request("rename", json!({}))).await),
+            METHOD_NOT_FOUND
+        );
+
+        daemon.handle_notification(JSONRPCNotification {
+            jsonrpc: JSONRPC_VERSION.to_string(),
+            method: "files_changed".to_string(),
+            params: Some(json!({"changed": ["src/a.rs", root.join("src/b.rs")]})),
+        });
+        daemon.handle_notification(JSONRPCNotification {
+            jsonrpc: JSONRPC_VERSION.to_string(),
+            method: "files_changed".to_string(),
+            params: Some(json!({"deleted": ["src/a.rs"]})),
+        });
+        let pending = daemon.pending.lock().expect("pending lock poisoned");
+        let files = pending.get(&root).expect("changes should be queued");
+        assert_eq!(
+            files.changed.iter().collect::<Vec<_>>(),
+            [&"src/b.rs".to_string()]
+        );
+        assert_eq!(
+            files.deleted.iter().collect::<Vec<_>>(),
+            [&"src/a.rs".to_string()]
+        );
+    }
+}
@@ -0,0 +1,379 @@
+use std::path::Path;
+use std::path::PathBuf;
+
+use serde::Serialize;
+
+use crate::embedding::get_embedding_client;
+use crate::file_state::CodebaseState;
+use crate::file_state::IndexParameters;
+use crate::file_state::state_file_path;
+use crate::index_config::IndexConfig;
+use crate::symbol::SupportedLanguage;
+use crate::symbol::SymbolParser;
+use crate::vector_db::QDRANT_CLIENT;
+use crate::vector_db::generate_collection_id;
+
+/// A small snippet per supported extension, expected to yield at least one symbol
+const PARSER_SAMPLES: &[(&str, &str)] = &[
+    ("rs", "fn doctor() {}\n"),
+    ("py", "def doctor():\n    pass\n"),
+    ("go", "package main\n\nfunc doctor() {}\n"),
+    ("ts", "function doctor(): void {}\n"),
+    ("tsx", "const Doctor = () => <p />;\n"),
+    ("js", "function doctor() {}\n"),
+    ("mjs", "export function doctor() {}\n"),
+    ("jsx", "const Doctor = () => <p />;\n"),
+    ("java", "class Doctor {}\n"),
+    ("c", "void doctor(void) {}\n"),
+    ("h", "#define DOCTOR 1\n"),
+    ("cc", "void doctor() {}\n"),
+    ("cpp", "namespace doctor {}\n"),
+    ("hpp", "class Doctor {};\n"),
+    ("cs", "class Doctor { }\n"),
+    ("scala", "object Doctor\n"),
+    ("sc", "def doctor(): Unit = ()\n"),
+    ("ex", "defmodule Doctor do\nend\n"),
+    ("exs", "defmodule Doctor do\nend\n"),
+    ("hs", "doctor :: Int\ndoctor = 1\n"),
+    ("dart", "void doctor() {}\n"),
+    ("sql", "CREATE TABLE doctor (id INT);\n"),
+    ("proto", "syntax = \"proto3\";\nmessage Doctor {}\n"),
+    ("graphql", "type Doctor {\n  id: ID!\n}\n"),
+    ("gql", "query Doctor {\n  viewer\n}\n"),
+    ("tf", "variable \"doctor\" {}\n"),
+    ("yaml", "kind: ConfigMap\nmetadata:\n  name: doctor\n"),
+    ("yml", "kind: ConfigMap\nmetadata:\n  name: doctor\n"),
+];
+
+/// Outcome of a single check
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    Pass,
+    /// Works, but something is likely to cause trouble
+    Warn,
+    Fail,
+    /// Not run because an earlier check failed or doesn't apply
+    Skip,
+}
+
+/// Result of one `doctor` check, with a hint on how to fix it when it didn't pass
+#[derive(Debug, Clone, Serialize)]
+pub struct DoctorCheck {
+    pub name: &'static str,
+    pub status: CheckStatus,
+    pub detail: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hint: Option<String>,
+}
+
+impl DoctorCheck {
+    fn pass(name: &'static str, detail: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: CheckStatus::Pass,
+            detail: detail.into(),
+            hint: None,
+        }
+    }
+
+    fn skip(name: &'static str, detail: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: CheckStatus::Skip,
+            detail: detail.into(),
+            hint: None,
+        }
+    }
+
+    fn problem(
+        name: &'static str,
+        status: CheckStatus,
+        detail: impl Into<String>,
+        hint: impl Into<String>,
+    ) -> Self {
+        Self {
+            name,
+            status,
+            detail: detail.into(),
+            hint: Some(hint.into()),
+        }
+    }
+}
+
+/// Check everything indexing and search depend on
+/// Without `root_path` only the project-independent checks (configuration, Qdrant,
+/// embedding API, parsers) run.
+pub async fn run_checks(root_path: Option<&Path>) -> Vec<DoctorCheck> {
+    let mut checks = Vec::new();
+
+    let config = match root_path {
+        Some(root_path) => IndexConfig::load(root_path),
+        None => IndexConfig::load_user(),
+    };
+    let config = match config {
+        Ok(config) => {
+            checks.push(DoctorCheck::pass("config", "configuration files parsed"));
+            Some(config)
+        }
+        Err(e) => {
+            checks.push(DoctorCheck::problem(
+                "config",
+                CheckStatus::Fail,
+                e.to_string(),
+                "Fix the file named above; `codebase-search config init --force` rewrites it",
+            ));
+            None
+        }
+    };
+
+    let qdrant_ok = match QDRANT_CLIENT.health_check().await {
+        Ok(reply) => {
+            checks.push(DoctorCheck::pass(
+                "qdrant",
+                format!("Qdrant {} is reachable", reply.version),
+            ));
+            true
+        }
+        Err(e) => {
+            checks.push(DoctorCheck::problem(
+                "qdrant",
+                CheckStatus::Fail,
+                format!("Qdrant is unreachable: {e}"),
+                "Start Qdrant (e.g. `docker run -p 6334:6334 qdrant/qdrant`) or point \
+                 `[qdrant] url` / CODEX_QDRANT_URL at it, or set `[qdrant] managed = true`",
+            ));
+            false
+        }
+    };
+
+    let embedding_dimension = check_embedding(&mut checks).await;
+    checks.push(check_parsers());
+
+    let (Some(root_path), Some(config)) = (root_path, config) else {
+        return checks;
+    };
+
+    let state = check_state(&mut checks, root_path);
+    if !qdrant_ok {
+        checks.push(DoctorCheck::skip("collection", "Qdrant is unreachable"));
+        return checks;
+    }
+    checks.push(check_collection(root_path, &config, state.as_ref(), embedding_dimension).await);
+    checks
+}
+
+/// Embed a short text to prove credentials and reachability; returns the dimension
+async fn check_embedding(checks: &mut Vec<DoctorCheck>) -> Option<usize> {
+    let client = match get_embedding_client() {
+        Ok(client) => client,
+        Err(e) => {
+            checks.push(DoctorCheck::problem(
+                "embedding",
+                CheckStatus::Fail,
+                e.to_string(),
+                "Check the [embedding] settings and CODEX_EMBEDDING_* variables",
+            ));
+            return None;
+        }
+    };
+    let config = client.config();
+    match client.embed_query("codebase-search doctor").await {
+        Ok(vector) => {
+            checks.push(DoctorCheck::pass(
+                "embedding",
+                format!(
+                    "{} / {} returned {}-dimensional vectors",
+                    config.provider,
+                    config.model,
+                    vector.len()
+                ),
+            ));
+            Some(vector.len())
+        }
+        Err(e) => {
+            checks.push(DoctorCheck::problem(
+                "embedding",
+                CheckStatus::Fail,
+                format!("{} / {} failed: {e}", config.provider, config.model),
+                "Check the API key (CODEX_EMBEDDING_API_KEY), the API URL and network access; \
+                 `search-codebase --metadata-only` works without the embedding API",
+            ));
+            None
+        }
+    }
+}
+
+/// Parse a sample of every supported language
+fn check_parsers() -> DoctorCheck {
+    const NAME: &str = "parsers";
+    const HINT: &str = "The tree-sitter grammars are compiled into the binary; rebuild it";
+
+    let mut parser = match SymbolParser::new() {
+        Ok(parser) => parser,
+        Err(e) => return DoctorCheck::problem(NAME, CheckStatus::Fail, e.to_string(), HINT),
+    };
+
+    let mut broken = Vec::new();
+    for (extension, sample) in PARSER_SAMPLES {
+        let symbols = SupportedLanguage::from_extension(extension).and_then(|language| {
+            let tree = parser.parsers.get_mut(*extension)?.parse(sample, None)?;
+            let file_path = PathBuf::from(format!("doctor.{extension}"));
+            parser
+                .extract_symbols(&tree, sample, &file_path, &language)
+                .ok()
+        });
+        if symbols.is_none_or(|symbols| symbols.is_empty()) {
+            broken.push(*extension);
+        }
+    }
+
+    if broken.is_empty() {
+        let extensions: Vec<&str> = PARSER_SAMPLES.iter().map(|(ext, _)| *ext).collect();
+        DoctorCheck::pass(NAME, format!("parsed .{}", extensions.join(", .")))
+    } else {
+        DoctorCheck::problem(
+            NAME,
+            CheckStatus::Fail,
+            format!("no symbols extracted for .{}", broken.join(", .")),
+            HINT,
+        )
+    }
+}
+
+/// Load the project's state file, if there is one
+fn check_state(checks: &mut Vec<DoctorCheck>, root_path: &Path) -> Option<CodebaseState> {
+    const NAME: &str = "state";
+    let state_path = match state_file_path(root_path) {
+        Ok(state_path) => state_path,
+        Err(e) => {
+            checks.push(DoctorCheck::problem(
+                NAME,
+                CheckStatus::Fail,
+                e.to_string(),
+                "Check the [state] settings in .codex/index.toml",
+            ));
+            return None;
+        }
+    };
+    if !state_path.exists() {
+        checks.push(DoctorCheck::problem(
+            NAME,
+            CheckStatus::Warn,
+            format!("no state file at {}", state_path.display()),
+            "Run `codebase-search index-codebase` to index the project",
+        ));
+        return None;
+    }
+
+    match CodebaseState::from_file(&state_path) {
+        Ok(state) => {
+            checks.push(DoctorCheck::pass(
+                NAME,
+                format!(
+                    "{} tracks {} files",
+                    state_path.display(),
+                    state.file_states.len()
+                ),
+            ));
+            Some(state)
+        }
+        Err(e) => {
+            checks.push(DoctorCheck::problem(
+                NAME,
+                CheckStatus::Fail,
+                format!("{} is invalid: {e}", state_path.display()),
+                "Run `codebase-search clear-index` and index the project again",
+            ));
+            None
+        }
+    }
+}
+
+/// Compare the project's collection with its state and the current settings
+async fn check_collection(
+    root_path: &Path,
+    config: &IndexConfig,
+    state: Option<&CodebaseState>,
+    embedding_dimension: Option<usize>,
+) -> DoctorCheck {
+    const NAME: &str = "collection";
+    let collection_id = generate_collection_id(root_path);
+
+    let info = match QDRANT_CLIENT.collection_info(collection_id.as_str()).await {
+        Ok(response) => response.result,
+        Err(_) if state.is_none() => {
+            return DoctorCheck::skip(NAME, format!("{collection_id} doesn't exist yet"));
+        }
+        Err(e) => {
+            return DoctorCheck::problem(
+                NAME,
+                CheckStatus::Fail,
+                format!("{collection_id} is missing although a state file exists: {e}"),
+                "Run `codebase-search clear-index` and index the project again",
+            );
+        }
+    };
+    let Some(state) = state else {
+        return DoctorCheck::problem(
+            NAME,
+            CheckStatus::Warn,
+            format!("{collection_id} exists but there is no state file"),
+            "Run `codebase-search clear-index` and index the project again",
+        );
+    };
+
+    let points = info.and_then(|info| info.points_count).unwrap_or_default();
+    let tracked_chunks: usize = state
+        .file_states
+        .values()
+        .map(|file_state| file_state.chunk_ids.len())
+        .sum();
+    if tracked_chunks > 0 && points != tracked_chunks as u64 {
+        return DoctorCheck::problem(
+            NAME,
+            CheckStatus::Warn,
+            format!("{collection_id} has {points} points but the state tracks {tracked_chunks}"),
+            "Run `codebase-search state repair`",
+        );
+    }
+
+    if let (Some(saved), Ok(client)) = (&state.index_params, get_embedding_client()) {
+        let current = IndexParameters::new(client.config(), &config.chunking_options());
+        let differences = saved.differences(&current);
+        if !differences.is_empty() {
+            return DoctorCheck::problem(
+                NAME,
+                CheckStatus::Warn,
+                format!("index settings changed: {}", differences.join(", ")),
+                "Run `codebase-search index-codebase` to rebuild the index",
+            );
+        }
+        if let Some(dimension) =
+            embedding_dimension.filter(|dimension| *dimension != saved.dimension)
+        {
+            return DoctorCheck::problem(
+                NAME,
+                CheckStatus::Fail,
+                format!(
+                    "the embedding API returns {dimension}-dimensional vectors but the index holds {}",
+                    saved.dimension
+                ),
+                "Run `codebase-search clear-index` and index the project again",
+            );
+        }
+    }
+
+    DoctorCheck::pass(NAME, format!("{collection_id} holds {points} points"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parsers_check_passes() {
+        let check = check_parsers();
+        assert_eq!(check.status, CheckStatus::Pass, "{}", check.detail);
+    }
+}
@@ -1,21 +1,39 @@
 use crate::chunker::CodeChunk;
+use crate::index_config::EmbeddingSettings;
+use crate::index_config::IndexConfig;
+use crate::telemetry;
+use crate::telemetry::Operation;
 use anyhow::Result;
 use anyhow::anyhow;
 use reqwest::Client;
 use serde::Deserialize;
 use serde::Serialize;
 use std::collections::HashMap;
+use std::collections::VecDeque;
 use std::str::FromStr;
 use std::sync::Arc;
 use std::sync::LazyLock;
+use std::sync::OnceLock;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio::sync::OnceCell;
+use tokio::time::Instant;
+use tracing::debug;
 use tracing::error;
 use tracing::info;
+use tracing::instrument;
+use tracing::warn;
 
 pub const QDRANT_EMBEDDING_MODEL: &str = "Qwen/Qwen3-Embedding-8B";
 pub const QDRANT_EMBEDDING_DIMENSION: usize = 4096;
 
+/// Settings installed by `configure_embedding`, used instead of the user config and
+/// environment when the global client is created
+static CONFIGURED_SETTINGS: OnceLock<EmbeddingSettings> = OnceLock::new();
+
 /// Lazy-loaded global embedding client for interacting with embedding providers
-/// This client is configured based on environment variables or defaults to SiliconFlow
+/// Unless `configure_embedding` was called first, this client is configured from the
+/// user config file and environment variables, defaulting to SiliconFlow
 pub(crate) static EMBEDDING_CLIENT: LazyLock<Result<Arc<EmbeddingClient>, anyhow::Error>> =
     LazyLock::new(|| {
         let config = create_embedding_config();
@@ -34,58 +52,57 @@ pub(crate) fn get_embedding_client() -> Result<Arc<EmbeddingClient>, anyhow::Err
     }
 }
 
-/// Create embedding configuration from environment variables or defaults
-fn create_embedding_config() -> EmbeddingConfig {
-    let provider =
-        std::env::var("CODEX_EMBEDDING_PROVIDER").unwrap_or_else(|_| "siliconflow".to_string());
-
-    let (api_url, model) = match provider.as_str() {
-        "openai" => (
-            std::env::var("CODEX_EMBEDDING_API_URL")
-                .unwrap_or_else(|_| "https://api.openai.com/v1/embeddings".to_string()),
-            std::env::var("CODEX_EMBEDDING_MODEL")
-                .unwrap_or_else(|_| "text-embedding-3-large".to_string()),
-        ),
-        "cohere" => (
-            std::env::var("CODEX_EMBEDDING_API_URL")
-                .unwrap_or_else(|_| "https://api.cohere.ai/v1/embed".to_string()),
-            std::env::var("CODEX_EMBEDDING_MODEL")
-                .unwrap_or_else(|_| "embed-english-v3.0".to_string()),
-        ),
-        "siliconflow" | _ => (
-            std::env::var("CODEX_EMBEDDING_API_URL")
-                .unwrap_or_else(|_| "https://api.siliconflow.cn/v1/embeddings".to_string()),
-            std::env::var("CODEX_EMBEDDING_MODEL")
-                .unwrap_or_else(|_| "Qwen/Qwen3-Embedding-8B".to_string()),
-        ),
-    };
+/// Use `settings` as-is for the global embedding client
+/// Callers that layer config files, environment and flags themselves (like the CLI)
+/// install the result here. Must be called before the client is first used.
+/// Installing the same settings again is a no-op; different ones are an error.
+pub fn configure_embedding(settings: EmbeddingSettings) -> Result<()> {
+    match CONFIGURED_SETTINGS.set(settings) {
+        Ok(()) => Ok(()),
+        Err(settings) if CONFIGURED_SETTINGS.get() == Some(&settings) => Ok(()),
+        Err(_) => Err(anyhow!(
+            "Embedding client settings were already configured differently"
+        )),
+    }
+}
 
-    let api_key = std::env::var("CODEX_EMBEDDING_API_KEY")
-        .or_else(|_| std::env::var("OPENAI_API_KEY")) // Fallback for OpenAI
-        .unwrap_or_else(|_| {
-            // Default API key for SiliconFlow (from existing default config)
-            "sk-xzmxwlbvzdbgsgejawqjamccrifkwjabcpmmgenprsfudnpt".to_string()
-        });
-
-    let batch_size = std::env::var("CODEX_EMBEDDING_BATCH_SIZE")
-        .ok()
-        .and_then(|s| s.parse().ok())
-        .unwrap_or(10);
-
-    let timeout_seconds = std::env::var("CODEX_EMBEDDING_TIMEOUT")
-        .ok()
-        .and_then(|s| s.parse().ok())
-        .unwrap_or(30);
-
-    EmbeddingConfig {
-        provider,
-        api_url,
-        api_key,
-        model,
-        batch_size,
-        timeout_seconds,
-        additional_headers: HashMap::new(),
+/// Embedding settings from the `CODEX_EMBEDDING_*` environment variables
+pub fn env_embedding_settings() -> EmbeddingSettings {
+    fn var(name: &str) -> Option<String> {
+        std::env::var(name).ok()
     }
+    fn parsed<T: FromStr>(name: &str) -> Option<T> {
+        var(name).and_then(|s| s.parse().ok())
+    }
+    EmbeddingSettings {
+        provider: var("CODEX_EMBEDDING_PROVIDER"),
+        api_url: var("CODEX_EMBEDDING_API_URL"),
+        // Fallback for OpenAI
+        api_key: var("CODEX_EMBEDDING_API_KEY").or_else(|| var("OPENAI_API_KEY")),
+        model: var("CODEX_EMBEDDING_MODEL"),
+        batch_size: parsed("CODEX_EMBEDDING_BATCH_SIZE"),
+        timeout_secs: parsed("CODEX_EMBEDDING_TIMEOUT"),
+        query_requests_per_second: parsed("CODEX_EMBEDDING_QUERY_RPS"),
+        query_cache_size: parsed("CODEX_EMBEDDING_QUERY_CACHE_SIZE"),
+    }
+}
+
+/// Create embedding configuration from the configured settings, or from the user
+/// config file overridden by environment variables
+fn create_embedding_config() -> EmbeddingConfig {
+    let settings = match CONFIGURED_SETTINGS.get() {
+        Some(settings) => settings.clone(),
+        None => {
+            let user_settings = IndexConfig::load_user()
+                .map(|config| config.embedding)
+                .unwrap_or_else(|e| {
+                    warn!("Ignoring user config: {e}");
+                    EmbeddingSettings::default()
+                });
+            user_settings.overridden_by(env_embedding_settings())
+        }
+    };
+    EmbeddingConfig::from_settings(&settings)
 }
 
 /// Configuration for embedding model providers
@@ -103,10 +120,60 @@ pub struct EmbeddingConfig {
     pub batch_size: usize,
     /// Request timeout in seconds
     pub timeout_seconds: u64,
+    /// Maximum query embedding requests per second (0 disables rate limiting)
+    pub query_requests_per_second: u32,
+    /// Number of recent query embeddings kept for reuse (0 disables reuse)
+    pub query_cache_size: usize,
     /// Additional headers to include in requests
     pub additional_headers: HashMap<String, String>,
 }
 
+impl EmbeddingConfig {
+    /// Resolve settings, filling unset fields with the provider's defaults
+    pub fn from_settings(settings: &EmbeddingSettings) -> Self {
+        let provider = settings
+            .provider
+            .clone()
+            .unwrap_or_else(|| "siliconflow".to_string());
+
+        let (default_api_url, default_model) = match provider.as_str() {
+            "openai" => (
+                "https://api.openai.com/v1/embeddings",
+                "text-embedding-3-large",
+            ),
+            "cohere" => ("https://api.cohere.ai/v1/embed", "embed-english-v3.0"),
+            "siliconflow" | _ => (
+                "https://api.siliconflow.cn/v1/embeddings",
+                "Qwen/Qwen3-Embedding-8B",
+            ),
+        };
+
+        let defaults = Self::default();
+        Self {
+            api_url: settings
+                .api_url
+                .clone()
+                .unwrap_or_else(|| default_api_url.to_string()),
+            model: settings
+                .model
+                .clone()
+                .unwrap_or_else(|| default_model.to_string()),
+            // Default API key for SiliconFlow (from existing default config)
+            api_key: settings.api_key.clone().unwrap_or(defaults.api_key),
+            batch_size: settings.batch_size.unwrap_or(defaults.batch_size),
+            timeout_seconds: settings.timeout_secs.unwrap_or(defaults.timeout_seconds),
+            query_requests_per_second: settings
+                .query_requests_per_second
+                .unwrap_or(defaults.query_requests_per_second),
+            query_cache_size: settings
+                .query_cache_size
+                .unwrap_or(defaults.query_cache_size),
+            provider,
+            additional_headers: HashMap::new(),
+        }
+    }
+}
+
 impl Default for EmbeddingConfig {
     fn default() -> Self {
         Self {
@@ -117,6 +184,8 @@ impl Default for EmbeddingConfig {
             model: "Qwen/Qwen3-Embedding-8B".to_string(),
             batch_size: 10,
             timeout_seconds: 30,
+            query_requests_per_second: 5,
+            query_cache_size: 128,
             additional_headers: HashMap::new(),
         }
     }
@@ -164,10 +233,53 @@ pub struct EmbeddedChunk {
     pub created_at: chrono::DateTime<chrono::Utc>,
 }
 
+/// Query embeddings shared between callers, keyed by the query text
+/// Concurrent callers asking for the same query await the same cell, so only one
+/// request reaches the provider; completed cells are kept for reuse until evicted.
+#[derive(Default)]
+struct QueryEmbeddingCache {
+    entries: HashMap<String, Arc<OnceCell<Vec<f32>>>>,
+    /// Insertion order used to evict the oldest entries
+    order: VecDeque<String>,
+}
+
+impl QueryEmbeddingCache {
+    fn get_or_insert(&mut self, query: &str, capacity: usize) -> Arc<OnceCell<Vec<f32>>> {
+        if let Some(cell) = self.entries.get(query) {
+            return Arc::clone(cell);
+        }
+
+        let cell = Arc::new(OnceCell::new());
+        self.entries.insert(query.to_string(), Arc::clone(&cell));
+        self.order.push_back(query.to_string());
+
+        // Keep at least the in-flight entry so concurrent callers are deduplicated
+        while self.order.len() > capacity.max(1) {
+            if let Some(oldest) = self.order.pop_front() {
+                self.entries.remove(&oldest);
+            }
+        }
+
+        cell
+    }
+
+    fn remove(&mut self, query: &str) {
+        self.entries.remove(query);
+        self.order.retain(|q| q != query);
+    }
+}
+
 /// Main embedding client that handles communication with embedding providers
 pub struct EmbeddingClient {
     config: EmbeddingConfig,
     client: Client,
+    /// Request headers, built once from the configuration
+    headers: reqwest::header::HeaderMap,
+    query_cache: Mutex<QueryEmbeddingCache>,
+    /// Minimum spacing between query embedding requests, if rate limited
+    query_interval: Option<Duration>,
+    /// Earliest time the next query embedding request may be sent
+    next_query_at: Mutex<Instant>,
 }
 
 impl EmbeddingClient {
@@ -177,7 +289,39 @@ impl EmbeddingClient {
             .timeout(std::time::Duration::from_secs(config.timeout_seconds))
             .build()?;
 
-        Ok(Self { config, client })
+        let mut headers = reqwest::header::HeaderMap::new();
+        headers.insert(
+            "Authorization",
+            format!("Bearer {}", config.api_key).parse()?,
+        );
+        headers.insert("Content-Type", "application/json".parse()?);
+
+        // Add additional headers
+        for (key, value) in &config.additional_headers {
+            headers.insert(
+                key.parse::<reqwest::header::HeaderName>()?,
+                value.parse::<reqwest::header::HeaderValue>()?,
+            );
+        }
+
+        let query_interval = match config.query_requests_per_second {
+            0 => None,
+            rps => Some(Duration::from_secs_f64(1.0 / f64::from(rps))),
+        };
+
+        Ok(Self {
+            config,
+            client,
+            headers,
+            query_cache: Mutex::new(QueryEmbeddingCache::default()),
+            query_interval,
+            next_query_at: Mutex::new(Instant::now()),
+        })
+    }
+
+    /// The configuration this client was created with
+    pub fn config(&self) -> &EmbeddingConfig {
+        &self.config
     }
 
     /// Embed a single code chunk
@@ -198,6 +342,16 @@ impl EmbeddingClient {
 
     /// Embed multiple code chunks in batches
     pub async fn embed_chunks(&self, chunks: &[CodeChunk]) -> Result<Vec<EmbeddedChunk>> {
+        self.embed_chunks_with_progress(chunks, |_| {}).await
+    }
+
+    /// Embed multiple code chunks in batches, calling `on_batch` with the size of each
+    /// batch once it has been embedded
+    pub async fn embed_chunks_with_progress(
+        &self,
+        chunks: &[CodeChunk],
+        mut on_batch: impl FnMut(usize),
+    ) -> Result<Vec<EmbeddedChunk>> {
         if chunks.is_empty() {
             return Ok(vec![]);
         }
@@ -237,6 +391,8 @@ impl EmbeddingClient {
                     });
                 }
 
+                on_batch(current_batch.len());
+
                 // Reset for next batch
                 current_batch.clear();
                 batch_texts.clear();
@@ -248,42 +404,71 @@ impl EmbeddingClient {
     }
 
     /// Embed a query string for similarity search
+    /// Identical concurrent queries share a single provider request, recent results
+    /// are reused, and requests are paced by `query_requests_per_second`.
     pub async fn embed_query(&self, query: &str) -> Result<Vec<f32>> {
-        let embeddings = self.embed_texts(&[query.to_string()]).await?;
+        let cell = self
+            .query_cache
+            .lock()
+            .await
+            .get_or_insert(query, self.config.query_cache_size);
+
+        if cell.initialized() {
+            debug!("Reusing cached embedding for query '{}'", query);
+        }
 
-        if embeddings.is_empty() {
-            return Err(anyhow!("No embeddings returned for query"));
+        let embedding = cell
+            .get_or_try_init(|| async {
+                self.wait_for_query_slot().await;
+                let embeddings = self.embed_texts(&[query.to_string()]).await?;
+                embeddings
+                    .into_iter()
+                    .next()
+                    .ok_or_else(|| anyhow!("No embeddings returned for query"))
+            })
+            .await?
+            .clone();
+
+        if self.config.query_cache_size == 0 {
+            self.query_cache.lock().await.remove(query);
         }
 
-        Ok(embeddings[0].clone())
+        Ok(embedding)
+    }
+
+    /// Wait until the rate limit allows another query embedding request
+    async fn wait_for_query_slot(&self) {
+        let Some(interval) = self.query_interval else {
+            return;
+        };
+
+        let wait = {
+            let mut next_query_at = self.next_query_at.lock().await;
+            let now = Instant::now();
+            let slot = (*next_query_at).max(now);
+            *next_query_at = slot + interval;
+            slot - now
+        };
+
+        if !wait.is_zero() {
+            debug!("Rate limiting query embedding for {:?}", wait);
+            tokio::time::sleep(wait).await;
+        }
     }
 
     /// Send embedding request to the configured provider
+    #[instrument(name = "embed", skip_all, fields(model = %self.config.model, texts = texts.len()))]
     async fn embed_texts(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        let timer = telemetry::start(Operation::Embed);
         let request = EmbeddingRequest {
             model: self.config.model.clone(),
             input: texts.to_vec(),
         };
 
-        let mut headers = reqwest::header::HeaderMap::new();
-        headers.insert(
-            "Authorization",
-            format!("Bearer {}", self.config.api_key).parse()?,
-        );
-        headers.insert("Content-Type", "application/json".parse()?);
-
-        // Add additional headers
-        for (key, value) in &self.config.additional_headers {
-            headers.insert(
-                key.parse::<reqwest::header::HeaderName>()?,
-                value.parse::<reqwest::header::HeaderValue>()?,
-            );
-        }
-
         let response = self
             .client
             .post(&self.config.api_url)
-            .headers(headers)
+            .headers(self.headers.clone())
             .json(&request)
             .send()
             .await?;
@@ -300,10 +485,168 @@ impl EmbeddingClient {
         }
 
         let embedding_response: EmbeddingResponse = response.json().await?;
+        let tokens = embedding_response
+            .usage
+            .as_ref()
+            .and_then(|usage| usage.total_tokens.or(usage.prompt_tokens));
+        if let Some(tokens) = tokens {
+            telemetry::record_embedding_tokens(tokens as u64);
+        }
         // Sort embeddings by index to maintain order
         let mut embeddings: Vec<_> = embedding_response.data.into_iter().collect();
         embeddings.sort_by_key(|data| data.index);
 
+        timer.succeeded(texts.len() as u64);
         Ok(embeddings.into_iter().map(|data| data.embedding).collect())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::expect_used)]
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::Ordering;
+
+    /// Serve a provider that answers every request with one embedding per input,
+    /// counting the requests it receives
+    async fn mock_provider() -> (String, Arc<AtomicUsize>) {
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&requests);
+        let app = axum::Router::new().route(
+            "/embeddings",
+            axum::routing::post(move |axum::Json(request): axum::Json<serde_json::Value>| {
+                let counter = Arc::clone(&counter);
+                async move {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                    let inputs = request["input"].as_array().map_or(0, Vec::len);
+                    let data: Vec<_> = (0..inputs)
+                        .map(|index| serde_json::json!({"embedding": [1.0, 0.0], "index": index}))
+                        .collect();
+                    axum::Json(serde_json::json!({"data": data, "model": "mock"}))
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("Failed to bind mock provider");
+        let addr = listener.local_addr().expect("Failed to read address");
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        (format!("http://{addr}/embeddings"), requests)
+    }
+
+    fn client(
+        api_url: String,
+        query_requests_per_second: u32,
+        query_cache_size: usize,
+    ) -> EmbeddingClient {
+        EmbeddingClient::new(EmbeddingConfig {
+            api_url,
+            api_key: "test".to_string(),
+            query_requests_per_second,
+            query_cache_size,
+            ..EmbeddingConfig::default()
+        })
+        .expect("Failed to create client")
+    }
+
+    #[test]
+    fn query_cache_evicts_the_oldest_queries() {
+        let mut cache = QueryEmbeddingCache::default();
+        let first = cache.get_or_insert("first", 2);
+        assert!(Arc::ptr_eq(&first, &cache.get_or_insert("first", 2)));
+
+        cache.get_or_insert("second", 2);
+        cache.get_or_insert("third", 2);
+        assert!(!cache.entries.contains_key("first"));
+        assert_eq!(cache.order, ["second", "third"]);
+
+        // A zero capacity still keeps the in-flight entry
+        cache.get_or_insert("fourth", 0);
+        assert_eq!(cache.order, ["fourth"]);
+    }
+
+    #[tokio::test]
+    async fn identical_queries_share_one_provider_request() {
+        let (api_url, requests) = mock_provider().await;
+        let client = client(api_url, 0, 8);
+
+        let (a, b) = tokio::join!(client.embed_query("parse"), client.embed_query("parse"));
+        assert_eq!(a.expect("Failed to embed"), b.expect("Failed to embed"));
+        client.embed_query("parse").await.expect("Failed to embed");
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+
+        client
+            .embed_query("tokenize")
+            .await
+            .expect("Failed to embed");
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn a_zero_cache_size_embeds_every_query() {
+        let (api_url, requests) = mock_provider().await;
+        let client = client(api_url, 0, 0);
+
+        client.embed_query("parse").await.expect("Failed to embed");
+        client.embed_query("parse").await.expect("Failed to embed");
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn query_requests_are_paced_by_the_rate_limit() {
+        let (api_url, requests) = mock_provider().await;
+        let client = client(api_url, 10, 8);
+
+        let started = Instant::now();
+        for query in ["a", "b", "c"] {
+            client.embed_query(query).await.expect("Failed to embed");
+        }
+        // The first request goes out at once, the next two 100ms apart
+        assert!(started.elapsed() >= Duration::from_millis(200));
+        assert_eq!(requests.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn chunk_batches_are_reported_as_they_complete() {
+        let (api_url, requests) = mock_provider().await;
+        let client = EmbeddingClient::new(EmbeddingConfig {
+            api_url,
+            api_key: "test".to_string(),
+            batch_size: 2,
+            ..EmbeddingConfig::default()
+        })
+        .expect("Failed to create client");
+        let chunks: Vec<_> = (0..5)
+            .map(|i| CodeChunk {
+                content: format!("fn f{i}() {{}}"),
+                file_path: std::path::PathBuf::from("lib.rs"),
+                start_line: i,
+                end_line: i,
+                symbol_name: format!("f{i}"),
+                symbol_kind: "Function".to_string(),
+                context: None,
+                signature: String::new(),
+                modifiers: Default::default(),
+                metrics: Default::default(),
+                is_test: false,
+                symbol_id: String::new(),
+                chunk_metadata: crate::chunker::ChunkMetadata {
+                    is_split: false,
+                    original_size_lines: 1,
+                    chunk_depth: 0,
+                    is_container: false,
+                },
+            })
+            .collect();
+
+        let mut batches = Vec::new();
+        let embedded = client
+            .embed_chunks_with_progress(&chunks, |count| batches.push(count))
+            .await
+            .expect("Failed to embed chunks");
+        assert_eq!(embedded.len(), 5);
+        assert_eq!(batches, [2, 2, 1]);
+        assert_eq!(requests.load(Ordering::SeqCst), 3);
+    }
+}
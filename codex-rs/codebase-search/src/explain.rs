@@ -0,0 +1,241 @@
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::Result;
+use anyhow::anyhow;
+use reqwest::Client;
+use serde::Deserialize;
+use serde::Serialize;
+use tracing::debug;
+use tracing::error;
+
+use crate::context_builder::ContextBuilder;
+use crate::context_builder::ContextSource;
+use crate::index_config::ChatSettings;
+use crate::retriever::SearchFilter;
+use crate::retriever::search_codebase_filtered;
+
+/// Chat model used when none is configured
+pub const DEFAULT_CHAT_MODEL: &str = "gpt-4.1-mini";
+
+/// Token budget for the retrieved code when none is configured
+pub const DEFAULT_MAX_CONTEXT_TOKENS: usize = 6000;
+
+const DEFAULT_OPENAI_BASE_URL: &str = "https://api.openai.com/v1";
+
+const CHAT_TIMEOUT_SECS: u64 = 120;
+
+const SYSTEM_PROMPT: &str = "You answer questions about a codebase using only the numbered \
+excerpts you are given. Cite the excerpts you rely on inline as [n], using their numbers. \
+Mention file paths and symbol names where they help. If the excerpts don't contain the \
+answer, say so instead of guessing.";
+
+/// Chat settings from the `CODEX_CHAT_*` environment variables
+/// Like core, the API key falls back to `OPENAI_API_KEY`.
+pub fn env_chat_settings() -> ChatSettings {
+    fn var(name: &str) -> Option<String> {
+        std::env::var(name)
+            .ok()
+            .filter(|value| !value.trim().is_empty())
+    }
+    ChatSettings {
+        model: var("CODEX_CHAT_MODEL"),
+        api_url: var("CODEX_CHAT_API_URL"),
+        api_key: var("CODEX_CHAT_API_KEY").or_else(|| var("OPENAI_API_KEY")),
+        max_context_tokens: var("CODEX_CHAT_MAX_CONTEXT_TOKENS").and_then(|v| v.parse().ok()),
+    }
+}
+
+/// Fully resolved chat model configuration
+#[derive(Debug, Clone)]
+pub struct ChatConfig {
+    pub model: String,
+    pub api_url: String,
+    pub api_key: Option<String>,
+    pub max_context_tokens: usize,
+}
+
+impl ChatConfig {
+    /// Fill unset settings with defaults; the endpoint follows `OPENAI_BASE_URL` as core's
+    /// built-in OpenAI provider does
+    pub fn from_settings(settings: &ChatSettings) -> Self {
+        let api_url = settings.api_url.clone().unwrap_or_else(|| {
+            let base_url = std::env::var("OPENAI_BASE_URL")
+                .ok()
+                .filter(|value| !value.trim().is_empty())
+                .unwrap_or_else(|| DEFAULT_OPENAI_BASE_URL.to_string());
+            format!("{}/chat/completions", base_url.trim_end_matches('/'))
+        });
+        Self {
+            model: settings
+                .model
+                .clone()
+                .unwrap_or_else(|| DEFAULT_CHAT_MODEL.to_string()),
+            api_url,
+            api_key: settings.api_key.clone(),
+            max_context_tokens: settings
+                .max_context_tokens
+                .unwrap_or(DEFAULT_MAX_CONTEXT_TOKENS),
+        }
+    }
+}
+
+/// How much code to retrieve for a question
+#[derive(Debug, Clone)]
+pub struct ExplainOptions {
+    pub limit: usize,
+    pub min_score: f32,
+    pub filter: SearchFilter,
+}
+
+impl Default for ExplainOptions {
+    fn default() -> Self {
+        Self {
+            limit: 12,
+            min_score: 0.3,
+            filter: SearchFilter::default(),
+        }
+    }
+}
+
+/// An answer to a question about the code, with the excerpts it was based on
+#[derive(Debug, Clone, Serialize)]
+pub struct Explanation {
+    pub question: String,
+    pub model: String,
+    pub answer: String,
+    /// Excerpts sent to the model, numbered as the answer cites them
+    pub sources: Vec<ContextSource>,
+    pub context_tokens: usize,
+    /// Retrieved chunks that didn't fit the token budget
+    pub omitted_chunks: usize,
+}
+
+#[derive(Serialize)]
+struct ChatRequest<'a> {
+    model: &'a str,
+    messages: Vec<ChatMessage<'a>>,
+}
+
+#[derive(Serialize)]
+struct ChatMessage<'a> {
+    role: &'a str,
+    content: &'a str,
+}
+
+#[derive(Deserialize)]
+struct ChatResponse {
+    choices: Vec<ChatChoice>,
+}
+
+#[derive(Deserialize)]
+struct ChatChoice {
+    message: ChatResponseMessage,
+}
+
+#[derive(Deserialize)]
+struct ChatResponseMessage {
+    content: Option<String>,
+}
+
+/// Answer `question` from the indexed code under `root_path`
+/// The top chunks are retrieved semantically, packed into a token-budgeted context and
+/// sent to the chat model, which is asked to cite the excerpts it used.
+pub async fn explain<P: AsRef<Path>>(
+    question: &str,
+    root_path: P,
+    chat: &ChatConfig,
+    options: &ExplainOptions,
+) -> Result<Explanation> {
+    let api_key = chat.api_key.as_deref().ok_or_else(|| {
+        anyhow!("No API key for the chat model; set CODEX_CHAT_API_KEY or OPENAI_API_KEY")
+    })?;
+
+    let results = search_codebase_filtered(
+        question.to_string(),
+        root_path,
+        options.limit,
+        options.min_score,
+        &options.filter,
+    )
+    .await?;
+    if results.is_empty() {
+        return Err(anyhow!(
+            "No indexed code matched the question; check that the codebase is indexed or \
+             lower --min-score"
+        ));
+    }
+
+    let context = ContextBuilder::new(chat.max_context_tokens).build(&results);
+    if context.sources.is_empty() {
+        return Err(anyhow!(
+            "None of the matching code fits in {} context tokens",
+            chat.max_context_tokens
+        ));
+    }
+    debug!(
+        "Explaining with {} excerpts (~{} tokens, {} omitted)",
+        context.sources.len(),
+        context.tokens,
+        context.omitted
+    );
+
+    let prompt = format!("Question: {question}\n\nExcerpts:\n\n{}", context.text);
+    let answer = complete(chat, api_key, &prompt).await?;
+
+    Ok(Explanation {
+        question: question.to_string(),
+        model: chat.model.clone(),
+        answer,
+        sources: context.sources,
+        context_tokens: context.tokens,
+        omitted_chunks: context.omitted,
+    })
+}
+
+/// Send one user message to the chat completions endpoint and return the reply
+async fn complete(chat: &ChatConfig, api_key: &str, prompt: &str) -> Result<String> {
+    let client = Client::builder()
+        .timeout(Duration::from_secs(CHAT_TIMEOUT_SECS))
+        .build()?;
+    let request = ChatRequest {
+        model: &chat.model,
+        messages: vec![
+            ChatMessage {
+                role: "system",
+                content: SYSTEM_PROMPT,
+            },
+            ChatMessage {
+                role: "user",
+                content: prompt,
+            },
+        ],
+    };
+
+    let response = client
+        .post(&chat.api_url)
+        .bearer_auth(api_key)
+        .json(&request)
+        .send()
+        .await
+        .map_err(|e| anyhow!("Failed to reach the chat model at {}: {e}", chat.api_url))?;
+
+    let status = response.status();
+    if !status.is_success() {
+        let error_text = response.text().await.unwrap_or_default();
+        error!("Chat API error: {}", error_text);
+        return Err(anyhow!(
+            "Chat model request to {} failed (HTTP {status})",
+            chat.api_url
+        ));
+    }
+
+    let response: ChatResponse = response.json().await?;
+    response
+        .choices
+        .into_iter()
+        .next()
+        .and_then(|choice| choice.message.content)
+        .filter(|content| !content.trim().is_empty())
+        .ok_or_else(|| anyhow!("The chat model returned an empty answer"))
+}
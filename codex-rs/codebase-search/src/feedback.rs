@@ -0,0 +1,211 @@
+use std::collections::HashMap;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+
+use serde::Deserialize;
+use serde::Serialize;
+use tracing::debug;
+use tracing::warn;
+
+use crate::file_state::move_legacy_file;
+use crate::file_state::state_file_path;
+
+/// Feedback log name used by older versions, written to the project root
+pub const LEGACY_FEEDBACK_FILE_NAME: &str = ".rua.feedback.jsonl";
+
+/// Whether a returned search result was actually useful
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FeedbackRating {
+    Up,
+    Down,
+}
+
+impl std::str::FromStr for FeedbackRating {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "up" | "+" | "+1" | "good" => Ok(FeedbackRating::Up),
+            "down" | "-" | "-1" | "bad" => Ok(FeedbackRating::Down),
+            _ => Err(anyhow::anyhow!(
+                "Unknown feedback rating: {s}. Use 'up' or 'down'"
+            )),
+        }
+    }
+}
+
+/// A single relevance judgement for a search result, stored as one JSON line
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct FeedbackEntry {
+    /// The point ID of the chunk being rated (as shown in search results)
+    pub chunk_id: String,
+    pub rating: FeedbackRating,
+    /// The query that produced the result, if known
+    pub query: Option<String>,
+    /// Who gave the feedback (e.g. "cli", "agent")
+    pub source: Option<String>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl FeedbackEntry {
+    pub fn new(chunk_id: String, rating: FeedbackRating, query: Option<String>) -> Self {
+        Self {
+            chunk_id,
+            rating,
+            query,
+            source: None,
+            created_at: chrono::Utc::now(),
+        }
+    }
+}
+
+/// Aggregated feedback counts for a single chunk
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FeedbackCounts {
+    pub up: usize,
+    pub down: usize,
+}
+
+impl FeedbackCounts {
+    /// Net score in [-1.0, 1.0], usable as a ranking boost
+    pub fn net_score(&self) -> f32 {
+        let total = self.up + self.down;
+        if total == 0 {
+            return 0.0;
+        }
+        (self.up as f32 - self.down as f32) / total as f32
+    }
+}
+
+/// Path of the feedback log for a project, kept next to its state file
+/// (`.codex/index-state.feedback.jsonl` by default). A log left in the project root by
+/// an older version is moved there the first time it's looked up.
+pub fn feedback_file_path<P: AsRef<Path>>(root_path: P) -> Result<PathBuf, anyhow::Error> {
+    let root_path = root_path.as_ref();
+    let path = feedback_file_for_state(&state_file_path(root_path)?);
+    move_legacy_file(&root_path.join(LEGACY_FEEDBACK_FILE_NAME), &path)?;
+    Ok(path)
+}
+
+/// The feedback log kept next to the state file at `state_path`
+pub(crate) fn feedback_file_for_state(state_path: &Path) -> PathBuf {
+    state_path.with_extension("feedback.jsonl")
+}
+
+/// Append a feedback entry to the project's feedback log
+pub fn record_feedback<P: AsRef<Path>>(
+    root_path: P,
+    entry: &FeedbackEntry,
+) -> Result<(), anyhow::Error> {
+    let path = feedback_file_path(root_path)?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .map_err(|e| anyhow::anyhow!("Failed to open feedback log '{}': {e}", path.display()))?;
+
+    let line = serde_json::to_string(entry)?;
+    writeln!(file, "{line}")?;
+    debug!(
+        "Recorded {:?} feedback for chunk {}",
+        entry.rating, entry.chunk_id
+    );
+    Ok(())
+}
+
+/// Load every feedback entry recorded for a project
+/// Malformed lines are skipped so a partially written log never blocks searches
+pub fn load_feedback<P: AsRef<Path>>(root_path: P) -> Result<Vec<FeedbackEntry>, anyhow::Error> {
+    let path = feedback_file_path(root_path)?;
+    if !path.exists() {
+        return Ok(vec![]);
+    }
+
+    let content = std::fs::read_to_string(&path)
+        .map_err(|e| anyhow::anyhow!("Failed to read feedback log '{}': {e}", path.display()))?;
+
+    let mut entries = Vec::new();
+    for (line_no, line) in content.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str::<FeedbackEntry>(line) {
+            Ok(entry) => entries.push(entry),
+            Err(e) => warn!("Skipping malformed feedback line {}: {e}", line_no + 1),
+        }
+    }
+
+    Ok(entries)
+}
+
+/// Aggregate feedback entries into per-chunk counts
+pub fn summarize_feedback(entries: &[FeedbackEntry]) -> HashMap<String, FeedbackCounts> {
+    let mut summary: HashMap<String, FeedbackCounts> = HashMap::new();
+    for entry in entries {
+        let counts = summary.entry(entry.chunk_id.clone()).or_default();
+        match entry.rating {
+            FeedbackRating::Up => counts.up += 1,
+            FeedbackRating::Down => counts.down += 1,
+        }
+    }
+    summary
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::expect_used)]
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_record_and_summarize_feedback() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+
+        for rating in [FeedbackRating::Up, FeedbackRating::Up, FeedbackRating::Down] {
+            let entry = FeedbackEntry::new("chunk-a".to_string(), rating, None);
+            record_feedback(temp_dir.path(), &entry).expect("Failed to record feedback");
+        }
+        let entry = FeedbackEntry::new(
+            "chunk-b".to_string(),
+            FeedbackRating::Down,
+            Some("query".to_string()),
+        );
+        record_feedback(temp_dir.path(), &entry).expect("Failed to record feedback");
+
+        let entries = load_feedback(temp_dir.path()).expect("Failed to load feedback");
+        assert_eq!(entries.len(), 4);
+
+        let summary = summarize_feedback(&entries);
+        assert_eq!(summary["chunk-a"], FeedbackCounts { up: 2, down: 1 });
+        assert_eq!(summary["chunk-b"].net_score(), -1.0);
+        assert!(
+            temp_dir
+                .path()
+                .join(".codex/index-state.feedback.jsonl")
+                .is_file()
+        );
+    }
+
+    #[test]
+    fn legacy_feedback_moves_next_to_the_state_file() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let legacy_path = temp_dir.path().join(LEGACY_FEEDBACK_FILE_NAME);
+        let entry = FeedbackEntry::new("chunk-a".to_string(), FeedbackRating::Up, None);
+        let line = serde_json::to_string(&entry).expect("Failed to serialize feedback");
+        std::fs::write(&legacy_path, format!("{line}\n")).expect("Failed to write feedback");
+
+        let entries = load_feedback(temp_dir.path()).expect("Failed to load feedback");
+        assert_eq!(entries.len(), 1);
+        assert!(!legacy_path.exists());
+        assert_eq!(
+            feedback_file_path(temp_dir.path()).expect("Failed to locate feedback"),
+            temp_dir.path().join(".codex/index-state.feedback.jsonl")
+        );
+    }
+}
@@ -1,34 +1,404 @@
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
 use std::collections::HashMap;
+use std::io::Read;
+use std::path::Path;
+use std::path::PathBuf;
 
 use serde::Deserialize;
 use serde::Serialize;
+use sha2::Digest;
+use sha2::Sha256;
+use tracing::info;
+
+use crate::chunker::ChunkingOptions;
+use crate::embedding::EmbeddingConfig;
+use crate::embedding::QDRANT_EMBEDDING_DIMENSION;
+use crate::index_config::IndexConfig;
+use crate::index_config::StateLocation;
+use crate::vector_db::generate_collection_id;
+
+/// State file name used by older versions, written to the project root
+pub const LEGACY_STATE_FILE_NAME: &str = ".rua.index.json";
+
+/// State file name inside the project's `.codex/` directory
+pub const STATE_FILE_NAME: &str = "index-state.json";
+
+/// Collection ID → project root for every project indexed by this user, kept in the
+/// state cache directory so collections can be traced back to their projects
+const KNOWN_ROOTS_FILE_NAME: &str = "roots.json";
+
+/// Schema version written by this build; bump it (and add a step to
+/// `migrate_state`) whenever the on-disk layout of `CodebaseState` changes
+pub const STATE_VERSION: u32 = 6;
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct CodebaseState {
+    /// Schema version of the file this state was loaded from or will be written as
+    pub version: u32,
     pub file_states: HashMap<String, FileState>,
+    /// Commit the index was last compared against, for `change_detection = "git"`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub git: Option<GitBaseline>,
+    /// Settings the index was built with; missing in states written by older versions
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub index_params: Option<IndexParameters>,
+    /// `walk_utils::ignore_rules_hash` when the file set was last scanned
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ignore_rules_hash: Option<String>,
+}
+
+/// Embedding model and chunking settings an index was built with
+/// Vectors from different models, or chunks cut differently, can't be mixed in one
+/// collection, so a mismatch with the current settings calls for a full reindex.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct IndexParameters {
+    pub provider: String,
+    pub model: String,
+    pub dimension: usize,
+    pub chunking: ChunkingOptions,
+}
+
+impl IndexParameters {
+    pub fn new(embedding_config: &EmbeddingConfig, chunking: &ChunkingOptions) -> Self {
+        Self {
+            provider: embedding_config.provider.clone(),
+            model: embedding_config.model.clone(),
+            dimension: QDRANT_EMBEDDING_DIMENSION,
+            chunking: chunking.clone(),
+        }
+    }
+
+    /// Describe each setting that differs between these (saved) and `current` parameters
+    pub fn differences(&self, current: &IndexParameters) -> Vec<String> {
+        let mut differences = Vec::new();
+        if self.provider != current.provider {
+            differences.push(format!(
+                "provider {} -> {}",
+                self.provider, current.provider
+            ));
+        }
+        if self.model != current.model {
+            differences.push(format!("model {} -> {}", self.model, current.model));
+        }
+        if self.dimension != current.dimension {
+            differences.push(format!(
+                "dimension {} -> {}",
+                self.dimension, current.dimension
+            ));
+        }
+        if self.chunking != current.chunking {
+            differences.push("chunking options".to_string());
+        }
+        differences
+    }
+}
+
+/// What the index looked like relative to a git commit
+/// Every file outside `dirty_files` is indexed exactly as it is at `commit`, so the
+/// files to re-check later are those git reports as changed since `commit` plus
+/// `dirty_files`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct GitBaseline {
+    pub commit: String,
+    /// Files that differed from `commit` when it was recorded, or were reindexed since
+    pub dirty_files: BTreeSet<String>,
 }
 
 impl CodebaseState {
-    pub fn to_file(&self, file_path: Option<&str>) -> Result<(), anyhow::Error> {
-        let file_path = file_path.unwrap_or("./.rua.index.json");
+    pub fn new(file_states: HashMap<String, FileState>) -> Self {
+        Self {
+            version: STATE_VERSION,
+            file_states,
+            git: None,
+            index_params: None,
+            ignore_rules_hash: None,
+        }
+    }
+
+    /// Write the state to `file_path`, creating its parent directory if needed
+    pub fn to_file(&self, file_path: &Path) -> Result<(), anyhow::Error> {
+        if let Some(parent) = file_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
         let file_content = serde_json::to_string_pretty(self)?;
         std::fs::write(file_path, file_content)?;
         Ok(())
     }
 
-    pub fn from_file(file_path: Option<&str>) -> Result<Self, anyhow::Error> {
-        let file_path = file_path.unwrap_or("./.rua.index.json");
+    /// Read a state file, migrating older schema versions to `STATE_VERSION`
+    /// Files written by a newer version of the crate are rejected rather than
+    /// guessed at, since misreading them would corrupt incremental updates.
+    pub fn from_file(file_path: &Path) -> Result<Self, anyhow::Error> {
         let file_content = std::fs::read_to_string(file_path)?;
-        let codebase_state: CodebaseState = serde_json::from_str(&file_content)?;
+        let value: serde_json::Value = serde_json::from_str(&file_content)?;
+        let codebase_state = migrate_state(value)
+            .map_err(|e| anyhow::anyhow!("Invalid state file {}: {e}", file_path.display()))?;
         Ok(codebase_state)
     }
 }
 
+/// Bring a raw state document up to `STATE_VERSION`, one version at a time
+/// Files without a `version` field predate versioning and are treated as version 0.
+fn migrate_state(mut value: serde_json::Value) -> Result<CodebaseState, anyhow::Error> {
+    let object = value
+        .as_object_mut()
+        .ok_or_else(|| anyhow::anyhow!("expected a JSON object"))?;
+    let mut version = match object.get("version") {
+        None => 0,
+        Some(version) => version
+            .as_u64()
+            .and_then(|version| u32::try_from(version).ok())
+            .ok_or_else(|| anyhow::anyhow!("invalid version {version}"))?,
+    };
+    if version > STATE_VERSION {
+        anyhow::bail!(
+            "state version {version} is newer than supported version {STATE_VERSION}; \
+             upgrade codebase-search or remove the state file to reindex"
+        );
+    }
+
+    while version < STATE_VERSION {
+        match version {
+            // 0 -> 1: only the version field was added
+            0 => {}
+            // 1 -> 2: `content_md5` became `content_hash` plus the algorithm that produced it
+            1 => {
+                let file_states = object
+                    .get_mut("file_states")
+                    .and_then(serde_json::Value::as_object_mut)
+                    .ok_or_else(|| anyhow::anyhow!("missing file_states"))?;
+                for file_state in file_states.values_mut() {
+                    let Some(file_state) = file_state.as_object_mut() else {
+                        continue;
+                    };
+                    if let Some(hash) = file_state.remove("content_md5") {
+                        file_state.insert("content_hash".to_string(), hash);
+                    }
+                    file_state.insert("hash_algorithm".to_string(), serde_json::Value::from("md5"));
+                }
+            }
+            // 2 -> 3: `chunk_ids` was added; older entries default to empty (unknown)
+            2 => {}
+            // 3 -> 4: the optional `git` baseline was added
+            3 => {}
+            // 4 -> 5: the optional `index_params` were added
+            4 => {}
+            // 5 -> 6: the optional `ignore_rules_hash` was added
+            5 => {}
+            _ => unreachable!("no migration from state version {version}"),
+        }
+        version += 1;
+        info!("Migrated state file to version {version}");
+    }
+    object.insert("version".to_string(), serde_json::Value::from(version));
+
+    Ok(serde_json::from_value(value)?)
+}
+
+/// Overview of a project's saved state, as printed by `state show`
+#[derive(Debug, Clone, Serialize)]
+pub struct StateSummary {
+    pub state_path: PathBuf,
+    pub version: u32,
+    pub tracked_files: usize,
+    /// Chunk point IDs recorded across all files
+    pub tracked_chunks: usize,
+    /// Files indexed before chunk IDs were recorded
+    pub files_without_chunk_ids: usize,
+    /// When the state file was last written
+    pub last_indexed_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub index_params: Option<IndexParameters>,
+    pub git_commit: Option<String>,
+}
+
+/// Load a project's state file and summarize it
+pub fn summarize_state<P: AsRef<Path>>(root_path: P) -> Result<StateSummary, anyhow::Error> {
+    let state_path = state_file_path(root_path.as_ref())?;
+    let state = CodebaseState::from_file(&state_path).map_err(|e| {
+        anyhow::anyhow!(
+            "Failed to load state file {}: {e}. Run 'index-codebase' first",
+            state_path.display()
+        )
+    })?;
+    let last_indexed_at = std::fs::metadata(&state_path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .map(chrono::DateTime::<chrono::Utc>::from);
+
+    Ok(StateSummary {
+        version: state.version,
+        tracked_files: state.file_states.len(),
+        tracked_chunks: state
+            .file_states
+            .values()
+            .map(|file_state| file_state.chunk_ids.len())
+            .sum(),
+        files_without_chunk_ids: state
+            .file_states
+            .values()
+            .filter(|file_state| file_state.chunk_ids.is_empty())
+            .count(),
+        last_indexed_at,
+        index_params: state.index_params,
+        git_commit: state.git.map(|git| git.commit),
+        state_path,
+    })
+}
+
+/// Where the state file for a project lives, according to its `.codex/index.toml`
+/// By default that's `.codex/index-state.json` in the project; `[state] location =
+/// "cache"` keeps it in the user cache directory (keyed by collection ID) instead, and
+/// `[state] path` names an explicit file. A state file left in the project root by an
+/// older version is moved to the new location the first time it's looked up.
+pub fn state_file_path<P: AsRef<Path>>(root_path: P) -> Result<PathBuf, anyhow::Error> {
+    let root_path = root_path.as_ref();
+    let config = IndexConfig::load(root_path)?;
+
+    let path = match (config.state.path, config.state.location) {
+        (Some(path), _) => root_path.join(path),
+        (None, StateLocation::Project) => root_path.join(".codex").join(STATE_FILE_NAME),
+        (None, StateLocation::Cache) => {
+            state_cache_dir()?.join(format!("{}.json", generate_collection_id(root_path)))
+        }
+    };
+
+    move_legacy_file(&root_path.join(LEGACY_STATE_FILE_NAME), &path)?;
+    Ok(path)
+}
+
+/// Move a file an older version left at `legacy_path` to `path`, unless `path` exists
+pub(crate) fn move_legacy_file(legacy_path: &Path, path: &Path) -> Result<(), anyhow::Error> {
+    if legacy_path == path || !legacy_path.exists() || path.exists() {
+        return Ok(());
+    }
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    // rename fails across filesystems (e.g. into the cache dir), so fall back to copying
+    if std::fs::rename(legacy_path, path).is_err() {
+        std::fs::copy(legacy_path, path)?;
+        std::fs::remove_file(legacy_path)?;
+    }
+    info!("Moved {} to {}", legacy_path.display(), path.display());
+    Ok(())
+}
+
+/// Project roots indexed by this user, keyed by collection ID
+pub fn known_roots() -> Result<BTreeMap<String, PathBuf>, anyhow::Error> {
+    read_known_roots(&state_cache_dir()?.join(KNOWN_ROOTS_FILE_NAME))
+}
+
+fn read_known_roots(path: &Path) -> Result<BTreeMap<String, PathBuf>, anyhow::Error> {
+    if !path.exists() {
+        return Ok(BTreeMap::new());
+    }
+    let content = std::fs::read_to_string(path)?;
+    serde_json::from_str(&content)
+        .map_err(|e| anyhow::anyhow!("Failed to parse {}: {e}", path.display()))
+}
+
+/// Add or remove `root_path` in the known roots file at `path`, rewriting it only if
+/// it changed
+fn update_known_roots(path: &Path, root_path: &Path, remember: bool) -> Result<(), anyhow::Error> {
+    let mut roots = read_known_roots(path)?;
+    let collection_id = generate_collection_id(root_path);
+    let changed = if remember {
+        roots
+            .insert(collection_id, root_path.to_path_buf())
+            .as_deref()
+            != Some(root_path)
+    } else {
+        roots.remove(&collection_id).is_some()
+    };
+    if !changed {
+        return Ok(());
+    }
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, serde_json::to_string_pretty(&roots)?)?;
+    Ok(())
+}
+
+/// Record that `root_path` has a collection
+pub fn remember_root<P: AsRef<Path>>(root_path: P) -> Result<(), anyhow::Error> {
+    let path = state_cache_dir()?.join(KNOWN_ROOTS_FILE_NAME);
+    update_known_roots(&path, root_path.as_ref(), true)
+}
+
+/// Drop `root_path` from the known roots after its collection was deleted
+pub fn forget_root<P: AsRef<Path>>(root_path: P) -> Result<(), anyhow::Error> {
+    let path = state_cache_dir()?.join(KNOWN_ROOTS_FILE_NAME);
+    update_known_roots(&path, root_path.as_ref(), false)
+}
+
+/// Per-user cache directory for state files (`$XDG_CACHE_HOME/codex/codebase-search`)
+pub(crate) fn state_cache_dir() -> Result<PathBuf, anyhow::Error> {
+    let non_empty = |name: &str| std::env::var_os(name).filter(|value| !value.is_empty());
+    let cache_dir = non_empty("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| non_empty("HOME").map(|home| PathBuf::from(home).join(".cache")))
+        .or_else(|| non_empty("LOCALAPPDATA").map(PathBuf::from))
+        .ok_or_else(|| anyhow::anyhow!("Could not determine a cache directory for state files"))?;
+    Ok(cache_dir.join("codex").join("codebase-search"))
+}
+
+/// Hash used to detect content changes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HashAlgorithm {
+    #[default]
+    Sha256,
+    /// Used by state files written before version 2; still read so upgrading doesn't
+    /// reindex every file
+    Md5,
+}
+
+impl HashAlgorithm {
+    /// Hash a file's raw bytes, streaming so large files aren't loaded at once
+    pub fn hash_file(self, file_path: &Path) -> Result<String, anyhow::Error> {
+        let mut file = std::fs::File::open(file_path)
+            .map_err(|e| anyhow::anyhow!("Failed to open file '{}': {e}", file_path.display()))?;
+        let mut buffer = [0u8; 64 * 1024];
+        let mut read_chunk = |buffer: &mut [u8]| {
+            file.read(buffer)
+                .map_err(|e| anyhow::anyhow!("Failed to read file '{}': {e}", file_path.display()))
+        };
+
+        match self {
+            HashAlgorithm::Sha256 => {
+                let mut hasher = Sha256::new();
+                loop {
+                    let n = read_chunk(&mut buffer)?;
+                    if n == 0 {
+                        break;
+                    }
+                    hasher.update(&buffer[..n]);
+                }
+                Ok(format!("{:x}", hasher.finalize()))
+            }
+            HashAlgorithm::Md5 => {
+                let mut context = md5::Context::new();
+                loop {
+                    let n = read_chunk(&mut buffer)?;
+                    if n == 0 {
+                        break;
+                    }
+                    context.consume(&buffer[..n]);
+                }
+                Ok(format!("{:x}", context.finalize()))
+            }
+        }
+    }
+}
+
 /// FileState is used to track the state of a file
 /// if its not found -> new file
 /// if its found -> check if the content is the same
 ///     if the last_modified is different from the one in your file system -> maybe modified
-///         if the content_md5 is different -> definitely modified
+///         if the content_hash is different -> definitely modified
 ///     else -> unchanged
 /// use a set to track the files that are seen
 /// if the file is not in the set -> deleted
@@ -36,18 +406,216 @@ impl CodebaseState {
 /// we will use them to update the vector db
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
 pub struct FileState {
-    pub content_md5: String,
+    pub content_hash: String,
+    pub hash_algorithm: HashAlgorithm,
     pub last_modified: u64,
+    /// Qdrant point IDs of the chunks indexed for this file
+    /// Empty when unknown (the file was indexed by an older version or produced no
+    /// chunks), in which case its points are found by their `file_path` payload instead.
+    #[serde(default)]
+    pub chunk_ids: Vec<String>,
 }
 
 impl FileState {
-    pub fn new(file_path: String, last_modified: u64) -> Result<Self, anyhow::Error> {
-        let content = std::fs::read_to_string(&file_path)
-            .map_err(|e| anyhow::anyhow!("Failed to read file '{}': {}", file_path, e))?;
-        let content_md5 = format!("{:x}", md5::compute(content));
+    /// Hash the file's bytes, so non-UTF-8 files are tracked like any other
+    pub fn new(
+        file_path: &Path,
+        last_modified: u64,
+        hash_algorithm: HashAlgorithm,
+    ) -> Result<Self, anyhow::Error> {
         Ok(Self {
-            content_md5,
+            content_hash: hash_algorithm.hash_file(file_path)?,
+            hash_algorithm,
             last_modified,
+            chunk_ids: Vec::new(),
         })
     }
+
+    /// Whether `file_path`, described by this freshly computed state, still has the
+    /// content recorded in `saved`
+    /// When `saved` used another algorithm the file is re-hashed with that one, so
+    /// switching algorithms doesn't make every file look modified.
+    pub fn same_content(&self, saved: &FileState, file_path: &Path) -> bool {
+        if self.hash_algorithm == saved.hash_algorithm {
+            return self.content_hash == saved.content_hash;
+        }
+        saved
+            .hash_algorithm
+            .hash_file(file_path)
+            .is_ok_and(|hash| hash == saved.content_hash)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::expect_used)]
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_state_file_path_migrates_legacy_file() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let state = CodebaseState::new(HashMap::from([(
+            "src/lib.rs".to_string(),
+            FileState {
+                content_hash: "abc".to_string(),
+                hash_algorithm: HashAlgorithm::Sha256,
+                last_modified: 1,
+                chunk_ids: vec!["point-1".to_string()],
+            },
+        )]));
+        state
+            .to_file(&temp_dir.path().join(LEGACY_STATE_FILE_NAME))
+            .expect("Failed to write legacy state");
+
+        let path = state_file_path(temp_dir.path()).expect("Failed to resolve state path");
+        assert_eq!(path, temp_dir.path().join(".codex").join(STATE_FILE_NAME));
+        assert!(!temp_dir.path().join(LEGACY_STATE_FILE_NAME).exists());
+        let migrated = CodebaseState::from_file(&path).expect("Failed to read migrated state");
+        assert_eq!(migrated.file_states, state.file_states);
+    }
+
+    #[test]
+    fn test_from_file_migrates_and_rejects_versions() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let path = temp_dir.path().join(STATE_FILE_NAME);
+
+        std::fs::write(
+            &path,
+            r#"{"file_states":{"a.rs":{"content_md5":"abc","last_modified":1}}}"#,
+        )
+        .expect("Failed to write unversioned state");
+        let state = CodebaseState::from_file(&path).expect("Failed to migrate state");
+        assert_eq!(state.version, STATE_VERSION);
+        let file_state = &state.file_states["a.rs"];
+        assert_eq!(file_state.content_hash, "abc");
+        assert_eq!(file_state.hash_algorithm, HashAlgorithm::Md5);
+        assert!(file_state.chunk_ids.is_empty());
+
+        std::fs::write(
+            &path,
+            format!(r#"{{"version":{},"file_states":{{}}}}"#, STATE_VERSION + 1),
+        )
+        .expect("Failed to write future state");
+        assert!(CodebaseState::from_file(&path).is_err());
+    }
+
+    #[test]
+    fn test_same_content_across_hash_algorithms() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let path = temp_dir.path().join("latin1.py");
+        std::fs::write(&path, b"name = '\xe9t\xe9'\n").expect("Failed to write file");
+
+        let current = FileState::new(&path, 1, HashAlgorithm::Sha256).expect("Failed to hash");
+        let legacy = FileState::new(&path, 1, HashAlgorithm::Md5).expect("Failed to hash");
+        assert_ne!(current.content_hash, legacy.content_hash);
+        assert!(current.same_content(&legacy, &path));
+
+        let stale = FileState {
+            content_hash: "0".repeat(32),
+            ..legacy
+        };
+        assert!(!current.same_content(&stale, &path));
+    }
+
+    #[test]
+    fn index_parameters_describe_each_difference() {
+        let embedding_config = EmbeddingConfig::default();
+        let saved = IndexParameters::new(&embedding_config, &ChunkingOptions::default());
+        assert!(saved.differences(&saved.clone()).is_empty());
+
+        let current = IndexParameters::new(
+            &EmbeddingConfig {
+                model: "other-model".to_string(),
+                ..embedding_config
+            },
+            &ChunkingOptions {
+                max_lines_per_chunk: 50,
+                ..ChunkingOptions::default()
+            },
+        );
+        assert_eq!(
+            saved.differences(&current),
+            [
+                format!("model {} -> other-model", saved.model),
+                "chunking options".to_string(),
+            ]
+        );
+
+        // The parameters survive a round trip through the state file
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let path = temp_dir.path().join(STATE_FILE_NAME);
+        let mut state = CodebaseState::new(HashMap::new());
+        state.index_params = Some(current.clone());
+        state.to_file(&path).expect("Failed to write state");
+        let loaded = CodebaseState::from_file(&path).expect("Failed to read state");
+        assert_eq!(loaded.index_params, Some(current));
+    }
+
+    #[test]
+    fn state_summaries_count_files_and_chunks() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let error = summarize_state(temp_dir.path())
+            .expect_err("A project without a state file has nothing to show");
+        assert!(
+            error.to_string().contains("Run 'index-codebase' first"),
+            "{error}"
+        );
+
+        let file_state = |chunk_ids: &[&str]| FileState {
+            content_hash: "abc".to_string(),
+            hash_algorithm: HashAlgorithm::Sha256,
+            last_modified: 1,
+            chunk_ids: chunk_ids.iter().map(ToString::to_string).collect(),
+        };
+        let mut state = CodebaseState::new(HashMap::from([
+            ("a.rs".to_string(), file_state(&["a-1", "a-2"])),
+            ("b.rs".to_string(), file_state(&["b-1"])),
+            ("legacy.rs".to_string(), file_state(&[])),
+        ]));
+        state.git = Some(GitBaseline {
+            commit: "0123abc".to_string(),
+            dirty_files: BTreeSet::new(),
+        });
+        let state_path = state_file_path(temp_dir.path()).expect("Failed to locate state");
+        state.to_file(&state_path).expect("Failed to write state");
+
+        let summary = summarize_state(temp_dir.path()).expect("Failed to summarize state");
+        assert_eq!(summary.state_path, state_path);
+        assert_eq!(summary.version, STATE_VERSION);
+        assert_eq!(summary.tracked_files, 3);
+        assert_eq!(summary.tracked_chunks, 3);
+        assert_eq!(summary.files_without_chunk_ids, 1);
+        assert!(summary.last_indexed_at.is_some());
+        assert_eq!(summary.git_commit.as_deref(), Some("0123abc"));
+    }
+
+    #[test]
+    fn known_roots_are_remembered_and_forgotten() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let path = temp_dir.path().join("cache").join(KNOWN_ROOTS_FILE_NAME);
+        let project = Path::new("/work/project");
+        assert!(
+            read_known_roots(&path)
+                .expect("Failed to read roots")
+                .is_empty()
+        );
+
+        update_known_roots(&path, project, true).expect("Failed to remember root");
+        let roots = read_known_roots(&path).expect("Failed to read roots");
+        assert_eq!(
+            roots,
+            BTreeMap::from([(generate_collection_id(project), project.to_path_buf())])
+        );
+
+        update_known_roots(&path, project, false).expect("Failed to forget root");
+        assert!(
+            read_known_roots(&path)
+                .expect("Failed to read roots")
+                .is_empty()
+        );
+
+        std::fs::write(&path, "not json").expect("Failed to corrupt roots");
+        assert!(read_known_roots(&path).is_err());
+    }
 }
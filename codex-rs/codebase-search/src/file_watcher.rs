@@ -1,19 +1,236 @@
-use tokio::sync::mpsc::{self, UnboundedReceiver};
-use notify::{Config, Event, RecommendedWatcher, RecursiveMode, Watcher};
-use std::path::{Path, PathBuf};
+use notify::Config;
+use notify::Event;
+use notify::EventKind;
+use notify::PollWatcher;
+use notify::RecommendedWatcher;
+use notify::RecursiveMode;
+use notify::Watcher;
+use notify::event::CreateKind;
+use notify::event::Flag;
+use notify::event::ModifyKind;
+use notify::event::RemoveKind;
+use notify::event::RenameMode;
+use serde::Deserialize;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+use tokio::sync::Notify;
+use tokio::sync::mpsc;
+use tokio::sync::mpsc::Receiver;
+use tokio::sync::mpsc::error::TrySendError;
+use tokio::time::Instant;
+use tokio_util::sync::CancellationToken;
+use tracing::debug;
 use tracing::info;
+use tracing::warn;
+
+use crate::walk_utils::CodebaseIgnoreMatcher;
+use crate::walk_utils::is_ignore_file;
+use crate::walk_utils::walk_codebase_files;
 
 /// Represents a file change event that needs to be processed
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum FileChangeEvent {
     /// File was created or modified
     FileChanged(PathBuf),
     /// File was deleted
     FileDeleted(PathBuf),
+    /// File was renamed or moved; index it as a delete of `from` plus an add of `to`
+    FileRenamed { from: PathBuf, to: PathBuf },
     /// Directory was created
     DirCreated(PathBuf),
     /// Directory was deleted
     DirDeleted(PathBuf),
+    /// Too many changes to track one by one (e.g. a branch checkout); everything under
+    /// the path should be diffed against the index in a single pass
+    Rescan(PathBuf),
+}
+
+impl FileChangeEvent {
+    /// The path this change leaves behind (the new path for renames)
+    pub fn path(&self) -> &Path {
+        match self {
+            FileChangeEvent::FileChanged(path)
+            | FileChangeEvent::FileDeleted(path)
+            | FileChangeEvent::DirCreated(path)
+            | FileChangeEvent::DirDeleted(path)
+            | FileChangeEvent::Rescan(path) => path,
+            FileChangeEvent::FileRenamed { to, .. } => to,
+        }
+    }
+
+    /// Translate a raw notify event into semantic change events
+    /// Access and metadata-only events produce nothing. Rename events carrying both
+    /// paths become `FileRenamed`; half-renames (only the old or only the new name
+    /// reported) become a delete or a change respectively.
+    pub fn from_notify_event(event: &Event) -> Vec<FileChangeEvent> {
+        let changed = |path: &PathBuf| {
+            if path.is_dir() {
+                FileChangeEvent::DirCreated(path.clone())
+            } else {
+                FileChangeEvent::FileChanged(path.clone())
+            }
+        };
+        // Best guess when the event doesn't say what happened to the path
+        let existing_or_deleted = |path: &PathBuf| {
+            if path.exists() {
+                changed(path)
+            } else {
+                FileChangeEvent::FileDeleted(path.clone())
+            }
+        };
+
+        match event.kind {
+            EventKind::Create(CreateKind::Folder) => event
+                .paths
+                .iter()
+                .map(|path| FileChangeEvent::DirCreated(path.clone()))
+                .collect(),
+            EventKind::Create(_) => event.paths.iter().map(changed).collect(),
+            EventKind::Remove(RemoveKind::Folder) => event
+                .paths
+                .iter()
+                .map(|path| FileChangeEvent::DirDeleted(path.clone()))
+                .collect(),
+            EventKind::Remove(_) => event
+                .paths
+                .iter()
+                .map(|path| FileChangeEvent::FileDeleted(path.clone()))
+                .collect(),
+            EventKind::Modify(ModifyKind::Name(RenameMode::Both)) => match event.paths.as_slice() {
+                [from, to] => vec![FileChangeEvent::FileRenamed {
+                    from: from.clone(),
+                    to: to.clone(),
+                }],
+                // One side was filtered out (e.g. moved in from an ignored directory)
+                paths => paths.iter().map(existing_or_deleted).collect(),
+            },
+            EventKind::Modify(ModifyKind::Name(RenameMode::From)) => event
+                .paths
+                .iter()
+                .map(|path| FileChangeEvent::FileDeleted(path.clone()))
+                .collect(),
+            EventKind::Modify(ModifyKind::Name(RenameMode::To)) => {
+                event.paths.iter().map(changed).collect()
+            }
+            EventKind::Modify(ModifyKind::Name(_)) => {
+                event.paths.iter().map(existing_or_deleted).collect()
+            }
+            EventKind::Modify(ModifyKind::Metadata(_)) | EventKind::Access(_) => vec![],
+            EventKind::Modify(_) | EventKind::Any | EventKind::Other => {
+                event.paths.iter().map(existing_or_deleted).collect()
+            }
+        }
+    }
+
+    /// Combine a pending change for a path with a newer change for the same path
+    fn coalesce(self, newer: FileChangeEvent) -> FileChangeEvent {
+        match (self, newer) {
+            // A pending rescan already covers anything that happens afterwards
+            (FileChangeEvent::Rescan(path), _) | (_, FileChangeEvent::Rescan(path)) => {
+                FileChangeEvent::Rescan(path)
+            }
+            // Edits after a rename still need the old path removed
+            (FileChangeEvent::FileRenamed { from, to }, FileChangeEvent::FileChanged(path))
+                if path == to =>
+            {
+                FileChangeEvent::FileRenamed { from, to }
+            }
+            // Renamed and then deleted: only the original path was ever indexed
+            (FileChangeEvent::FileRenamed { from, to }, FileChangeEvent::FileDeleted(path))
+                if path == to =>
+            {
+                FileChangeEvent::FileDeleted(from)
+            }
+            (_, newer) => newer,
+        }
+    }
+}
+
+/// How the watcher learns about file system changes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WatcherBackend {
+    /// Use native OS events, or polling when the root is on a filesystem that doesn't
+    /// deliver them (NFS, SMB, some container volumes) or the native watcher fails
+    #[default]
+    Auto,
+    /// Native OS events (inotify, FSEvents, ReadDirectoryChangesW)
+    Native,
+    /// Periodically scan the tree and compare modification times
+    Poll,
+}
+
+impl std::str::FromStr for WatcherBackend {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "auto" => Ok(WatcherBackend::Auto),
+            "native" => Ok(WatcherBackend::Native),
+            "poll" => Ok(WatcherBackend::Poll),
+            _ => Err(anyhow::anyhow!(
+                "Unknown watcher backend: {s}. Use 'auto', 'native' or 'poll'"
+            )),
+        }
+    }
+}
+
+/// Filesystem types (as named in /proc/mounts) that don't deliver native change events
+/// for every modification, typically because the change happened on another machine
+const POLL_ONLY_FILESYSTEMS: &[&str] = &[
+    "nfs",
+    "nfs4",
+    "cifs",
+    "smb3",
+    "smbfs",
+    "9p",
+    "vboxsf",
+    "virtiofs",
+    "fuse.sshfs",
+    "fuse.grpcfuse",
+];
+
+/// Name of the filesystem type `path` lives on, if it's one that needs polling
+pub fn poll_only_filesystem(path: &Path) -> Option<String> {
+    filesystem_type(path).filter(|fs_type| POLL_ONLY_FILESYSTEMS.contains(&fs_type.as_str()))
+}
+
+/// Filesystem type of the mount containing `path`, read from /proc/self/mounts
+#[cfg(target_os = "linux")]
+fn filesystem_type(path: &Path) -> Option<String> {
+    let path = path.canonicalize().ok()?;
+    let mounts = std::fs::read_to_string("/proc/self/mounts").ok()?;
+
+    // The longest matching mount point wins; later entries shadow earlier ones
+    mounts
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let _device = fields.next()?;
+            let mount_point = fields.next()?;
+            let fs_type = fields.next()?;
+            // Whitespace in mount points is octal-escaped
+            let mount_point = mount_point
+                .replace("\\040", " ")
+                .replace("\\011", "\t")
+                .replace("\\012", "\n")
+                .replace("\\134", "\\");
+            Some((PathBuf::from(mount_point), fs_type))
+        })
+        .filter(|(mount_point, _)| path.starts_with(mount_point))
+        .max_by_key(|(mount_point, _)| mount_point.components().count())
+        .map(|(_, fs_type)| fs_type.to_string())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn filesystem_type(_path: &Path) -> Option<String> {
+    None
 }
 
 /// Configuration for the file watcher
@@ -29,6 +246,16 @@ pub struct FileWatcherConfig {
     pub file_extensions: Vec<String>,
     /// Directories to ignore
     pub ignore_dirs: Vec<String>,
+    /// Apply the indexer's ignore rules (`.gitignore`, `.ignore`, `.codexignore`, built-in
+    /// build/cache directories and hidden files) so only indexable paths are reported
+    pub respect_ignore_files: bool,
+    /// Once more paths than this are pending, stop tracking them individually and
+    /// report a single `Rescan` of the root instead
+    pub max_pending_changes: usize,
+    /// Which watcher implementation to use
+    pub backend: WatcherBackend,
+    /// How often the polling backend rescans the tree (in milliseconds)
+    pub poll_interval: u64,
 }
 
 impl Default for FileWatcherConfig {
@@ -46,6 +273,10 @@ impl Default for FileWatcherConfig {
                 "build".to_string(),
                 "dist".to_string(),
             ],
+            respect_ignore_files: true,
+            max_pending_changes: 1000,
+            backend: WatcherBackend::Auto,
+            poll_interval: 2000,
         }
     }
 }
@@ -53,79 +284,631 @@ impl Default for FileWatcherConfig {
 /// The main file watcher that monitors file system changes
 pub struct FileWatcher {
     config: FileWatcherConfig,
+    /// The underlying notify watcher, kept alive between `watch` calls
+    watcher: Option<Box<dyn Watcher + Send>>,
+    /// The backend actually in use once started (`Native` or `Poll`)
+    active_backend: Option<WatcherBackend>,
+    rx: Option<EventReceiver>,
+    /// Set by the notify callback when the event channel is full and events were dropped
+    overflowed: Arc<AtomicBool>,
+    /// Whether a `Rescan` is pending, in which case individual events are not tracked
+    rescan_pending: bool,
+    /// Runtime counters reported by `status`
+    metrics: WatcherMetrics,
+    /// Paths with events that haven't been released in a batch yet
+    pending: HashMap<PathBuf, PendingChange>,
+    /// The indexer's ignore rules, loaded when the watcher starts
+    ignore_matcher: Option<CodebaseIgnoreMatcher>,
+    /// Cancelled to stop the watcher; shared with whoever holds a `shutdown_token`
+    shutdown: CancellationToken,
+    /// Suspends batching while set; shared with whoever holds a `pause_handle`
+    pause: PauseHandle,
+    /// Whether changes were seen (and dropped) while paused, requiring a catch-up rescan
+    missed_while_paused: bool,
+}
+
+/// Suspends and resumes a `FileWatcher` from another task
+/// While paused the watcher keeps draining OS events but doesn't report them; on resume
+/// a single `Rescan` of the root is reported if anything changed in the meantime, so
+/// the index catches up with one diff instead of replaying every event.
+#[derive(Debug, Clone, Default)]
+pub struct PauseHandle {
+    paused: Arc<AtomicBool>,
+    resumed: Arc<Notify>,
+}
+
+impl PauseHandle {
+    /// Stop reporting changes until `resume` is called
+    pub fn pause(&self) {
+        if !self.paused.swap(true, Ordering::SeqCst) {
+            info!("file watcher paused");
+        }
+    }
+
+    /// Resume reporting changes, starting with a catch-up rescan if needed
+    pub fn resume(&self) {
+        if self.paused.swap(false, Ordering::SeqCst) {
+            info!("file watcher resumed");
+            self.resumed.notify_one();
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::SeqCst)
+    }
+}
+
+/// Counters behind `FileWatcherStatus`
+#[derive(Debug, Clone, Default)]
+struct WatcherMetrics {
+    watched_files: usize,
+    events_received: u64,
+    events_processed: u64,
+    batches_released: u64,
+    changes_released: u64,
+    last_event_at: Option<chrono::DateTime<chrono::Utc>>,
+    last_batch_at: Option<chrono::DateTime<chrono::Utc>>,
+    last_reindex_at: Option<chrono::DateTime<chrono::Utc>>,
 }
 
+/// Debounce bookkeeping for a single path
+#[derive(Debug, Clone)]
+struct PendingChange {
+    /// The coalesced change for this path so far
+    change: FileChangeEvent,
+    /// When the first event for this path in the current batch arrived
+    first_seen: Instant,
+    /// When the most recent event for this path arrived
+    last_seen: Instant,
+}
+
+/// Raw notify results as delivered by the OS watcher callback
+type EventReceiver = Receiver<notify::Result<Event>>;
+
+/// A path held back this long is released even if events keep arriving, so a file
+/// that is rewritten continuously still gets reindexed (as a multiple of the debounce delay)
+const MAX_DEBOUNCE_FACTOR: u32 = 10;
+
+/// Raw events buffered between the OS watcher and the debouncer; anything beyond this
+/// is dropped and turned into a rescan, so an event storm can't grow memory unbounded
+const EVENT_CHANNEL_CAPACITY: usize = 4096;
+
 impl FileWatcher {
     /// Create a new file watcher
     pub fn new(config: FileWatcherConfig) -> Self {
-        Self { config }
+        Self::with_shutdown_token(config, CancellationToken::new())
+    }
+
+    /// Create a new file watcher that stops when `shutdown` is cancelled
+    pub fn with_shutdown_token(config: FileWatcherConfig, shutdown: CancellationToken) -> Self {
+        Self {
+            config,
+            watcher: None,
+            active_backend: None,
+            rx: None,
+            overflowed: Arc::new(AtomicBool::new(false)),
+            rescan_pending: false,
+            metrics: WatcherMetrics::default(),
+            pending: HashMap::new(),
+            ignore_matcher: None,
+            shutdown,
+            pause: PauseHandle::default(),
+            missed_while_paused: false,
+        }
+    }
+
+    /// Root path being watched
+    pub fn root_path(&self) -> &Path {
+        &self.config.root_path
+    }
+
+    /// A token that stops this watcher when cancelled
+    /// Clone it into a signal handler or another task; the same token can also be
+    /// used to abandon work driven by the watcher, such as an in-flight reindex.
+    pub fn shutdown_token(&self) -> CancellationToken {
+        self.shutdown.clone()
+    }
+
+    /// Stop watching: pending `watch`/`next_batch` calls return and the OS watch is released
+    pub fn stop(&self) {
+        self.shutdown.cancel();
     }
 
+    /// Whether the watcher has been stopped
+    pub fn is_stopped(&self) -> bool {
+        self.shutdown.is_cancelled()
+    }
+
+    /// A handle for pausing and resuming this watcher from another task
+    pub fn pause_handle(&self) -> PauseHandle {
+        self.pause.clone()
+    }
+
+    /// Stop reporting changes (e.g. during a build or rebase) until `resume` is called
+    pub fn pause(&self) {
+        self.pause.pause();
+    }
+
+    /// Resume reporting changes; anything missed while paused is reported as a `Rescan`
+    pub fn resume(&self) {
+        self.pause.resume();
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.pause.is_paused()
+    }
+
+    /// Drain events until the watcher is resumed, then queue a catch-up rescan if
+    /// anything changed; returns false if the watcher was stopped instead
+    async fn wait_while_paused(&mut self) -> notify::Result<bool> {
+        if !self.pending.is_empty() {
+            self.pending.clear();
+            self.rescan_pending = false;
+            self.missed_while_paused = true;
+        }
+
+        let pause = self.pause.clone();
+        while pause.is_paused() {
+            tokio::select! {
+                _ = pause.resumed.notified() => {}
+                event = self.next_event() => {
+                    if event?.is_none() {
+                        return Ok(false);
+                    }
+                    self.missed_while_paused = true;
+                }
+            }
+        }
+
+        if std::mem::take(&mut self.missed_while_paused) {
+            debug!("Changes were missed while paused; queueing a rescan");
+            self.record_rescan(Instant::now());
+        }
+        Ok(true)
+    }
+
+    /// Drop the OS watch and any changes that were still being debounced
+    fn release(&mut self) {
+        if self.watcher.take().is_some() {
+            info!("stopped watching {:?}", self.config.root_path);
+        }
+        self.active_backend = None;
+        self.rx = None;
+        self.pending.clear();
+        self.rescan_pending = false;
+    }
+
+    /// Create and register a notify watcher of the given backend (`Native` or `Poll`)
     fn async_watcher(
         &self,
-    ) -> notify::Result<(RecommendedWatcher, UnboundedReceiver<notify::Result<Event>>)> {
-        let (mut tx, rx) = mpsc::unbounded_channel();
+        backend: WatcherBackend,
+    ) -> notify::Result<(Box<dyn Watcher + Send>, EventReceiver)> {
+        let (tx, rx) = mpsc::channel(EVENT_CHANNEL_CAPACITY);
+        let overflowed = Arc::clone(&self.overflowed);
+        let handler = move |res| {
+            if let Err(TrySendError::Full(_)) = tx.try_send(res) {
+                overflowed.store(true, Ordering::SeqCst);
+            }
+        };
 
-        let watcher = RecommendedWatcher::new(
-            move |res| {
-                let _ = tx.send(res);
-            },        
-            Config::default(),
-        )?;
+        let mut watcher: Box<dyn Watcher + Send> = match backend {
+            WatcherBackend::Poll => {
+                let config = Config::default()
+                    .with_poll_interval(Duration::from_millis(self.config.poll_interval));
+                Box::new(PollWatcher::new(handler, config)?)
+            }
+            WatcherBackend::Auto | WatcherBackend::Native => {
+                Box::new(RecommendedWatcher::new(handler, Config::default())?)
+            }
+        };
+        watcher.watch(&self.config.root_path, self.config.recursive)?;
 
         Ok((watcher, rx))
     }
 
-    /// Start watching for file changes
-    pub async fn watch(&mut self) -> notify::Result<Event> {
+    /// Register the underlying watcher if it isn't running yet
+    /// Called implicitly by `watch`; call it up front to avoid missing events that
+    /// happen before the first `watch` call.
+    pub fn start(&mut self) -> notify::Result<()> {
+        if self.is_stopped() {
+            return Err(notify::Error::generic("file watcher has been stopped"));
+        }
+        if self.watcher.is_some() {
+            return Ok(());
+        }
+
         let root_path = self.config.root_path.clone();
+        if self.config.respect_ignore_files {
+            self.ignore_matcher = Some(CodebaseIgnoreMatcher::new(&root_path));
+        }
+        self.refresh_watched_files();
+
+        let backend = match self.config.backend {
+            WatcherBackend::Auto => match poll_only_filesystem(&root_path) {
+                Some(fs_type) => {
+                    info!("{root_path:?} is on {fs_type}, which doesn't report changes; polling");
+                    WatcherBackend::Poll
+                }
+                None => WatcherBackend::Native,
+            },
+            backend => backend,
+        };
+
+        let (watcher, rx, backend) = match self.async_watcher(backend) {
+            Ok((watcher, rx)) => (watcher, rx, backend),
+            // e.g. the inotify watch limit was reached on a large tree
+            Err(e)
+                if self.config.backend == WatcherBackend::Auto
+                    && backend == WatcherBackend::Native =>
+            {
+                warn!("Native file watcher failed ({e}); falling back to polling");
+                let (watcher, rx) = self.async_watcher(WatcherBackend::Poll)?;
+                (watcher, rx, WatcherBackend::Poll)
+            }
+            Err(e) => return Err(e),
+        };
+        info!(
+            "watching for file changes under {:?} ({:?})...",
+            root_path, backend
+        );
 
-        let (mut watcher, mut rx) = self.async_watcher()?;
-        watcher.watch(&root_path, self.config.recursive)?;
-        info!("watching for file changes under {:?}...", root_path);
+        self.watcher = Some(watcher);
+        self.active_backend = Some(backend);
+        self.rx = Some(rx);
+        Ok(())
+    }
+
+    /// Wait for the next file change event that isn't ignored
+    /// The underlying watcher stays registered between calls, so events that arrive
+    /// while the caller is busy are queued rather than lost. Returns an error once the
+    /// watcher has been stopped.
+    pub async fn watch(&mut self) -> notify::Result<Event> {
+        self.next_event()
+            .await?
+            .ok_or_else(|| notify::Error::generic("file watcher has been stopped"))
+    }
+
+    /// Wait for the next event that isn't ignored, or None once the watcher is stopped
+    async fn next_event(&mut self) -> notify::Result<Option<Event>> {
+        if self.is_stopped() {
+            self.release();
+            return Ok(None);
+        }
+        self.start()?;
+
+        loop {
+            // Dropped events can't be replayed, so ask for a rescan instead
+            if self.overflowed.swap(false, Ordering::SeqCst) {
+                warn!("File watcher event buffer overflowed; requesting a rescan");
+                return Ok(Some(Event::new(EventKind::Other).set_flag(Flag::Rescan)));
+            }
+
+            let Some(rx) = self.rx.as_mut() else {
+                return Err(notify::Error::generic("file watcher is not running"));
+            };
+
+            let res = tokio::select! {
+                biased;
+                _ = self.shutdown.cancelled() => break,
+                res = rx.recv() => res,
+            };
+            let Some(res) = res else {
+                return Err(notify::Error::generic(
+                    "file watcher channel closed unexpectedly",
+                ));
+            };
+            self.metrics.events_received += 1;
+            self.metrics.last_event_at = Some(chrono::Utc::now());
 
-        while let Some(res) = rx.recv().await {
             match res {
                 Ok(event) => {
+                    // Changed ignore rules can include or exclude any file in the tree
+                    if self.changes_ignore_rules(&event) {
+                        info!("Ignore rules changed; reloading and requesting a rescan");
+                        self.reload_ignore_rules();
+                        return Ok(Some(Event::new(EventKind::Other).set_flag(Flag::Rescan)));
+                    }
+
                     // Filter out events for paths that should be ignored
                     let mut filtered_paths = Vec::new();
                     for path in &event.paths {
-                        if !Self::should_ignore_path(path, &self.config) {
+                        if !Self::should_ignore_path(
+                            path,
+                            &self.config,
+                            self.ignore_matcher.as_ref(),
+                        ) {
                             filtered_paths.push(path.clone());
                         }
                     }
-                    
-                    // Only return the event if there are paths that aren't ignored
-                    if !filtered_paths.is_empty() {
+
+                    // Only return the event if there are paths that aren't ignored;
+                    // rescan notices apply to the whole tree and always get through
+                    if !filtered_paths.is_empty() || event.need_rescan() {
                         let filtered_event = Event {
                             kind: event.kind,
                             paths: filtered_paths,
                             attrs: event.attrs,
                         };
-                        return Ok(filtered_event);
+                        return Ok(Some(filtered_event));
                     }
                     // If all paths were filtered out, continue waiting for the next event
-                },
+                }
                 Err(err) => return Err(err),
             }
         }
 
-        unreachable!("file watcher channel should not close while watcher is alive");
+        self.release();
+        Ok(None)
+    }
+
+    /// Wait for the next debounced batch of file changes
+    /// Each path is held back until no event for it has arrived for `debounce_delay`
+    /// milliseconds, and repeated events for the same path are coalesced into a single
+    /// `FileChangeEvent`, so an editor save or a formatter run produces one entry per
+    /// file rather than dozens of events. Paths that are still receiving events stay
+    /// pending for a later batch. Returns None once the watcher has been stopped;
+    /// changes still being debounced at that point are dropped.
+    pub async fn next_batch(&mut self) -> notify::Result<Option<Vec<FileChangeEvent>>> {
+        let debounce = Duration::from_millis(self.config.debounce_delay);
+        let max_delay = debounce * MAX_DEBOUNCE_FACTOR;
+
+        loop {
+            if self.is_paused() && !self.wait_while_paused().await? {
+                return Ok(None);
+            }
+
+            if self.pending.is_empty() {
+                let Some(event) = self.next_event().await? else {
+                    return Ok(None);
+                };
+                self.record_event(&event);
+                continue;
+            }
+
+            // Sleep until the earliest pending path becomes ready, unless more events arrive
+            let now = Instant::now();
+            let deadline = self
+                .pending
+                .values()
+                .map(|pending| (pending.last_seen + debounce).min(pending.first_seen + max_delay))
+                .min()
+                .unwrap_or(now);
+
+            if deadline > now {
+                match tokio::time::timeout_at(deadline, self.next_event()).await {
+                    Ok(event) => {
+                        let Some(event) = event? else {
+                            return Ok(None);
+                        };
+                        self.record_event(&event);
+                        continue;
+                    }
+                    Err(_) => {
+                        // Debounce window elapsed without new events
+                    }
+                }
+            }
+
+            let now = Instant::now();
+            let ready: Vec<PathBuf> = self
+                .pending
+                .iter()
+                .filter(|(_, pending)| {
+                    pending.last_seen + debounce <= now || pending.first_seen + max_delay <= now
+                })
+                .map(|(path, _)| path.clone())
+                .collect();
+
+            if ready.is_empty() || self.is_paused() {
+                continue;
+            }
+
+            let mut batch: Vec<FileChangeEvent> = ready
+                .iter()
+                .filter_map(|path| self.pending.remove(path))
+                .map(|pending| pending.change)
+                .collect();
+            batch.sort_by(|a, b| a.path().cmp(b.path()));
+            self.rescan_pending = self
+                .pending
+                .values()
+                .any(|pending| matches!(pending.change, FileChangeEvent::Rescan(_)));
+            if batch
+                .iter()
+                .any(|change| matches!(change, FileChangeEvent::Rescan(_)))
+            {
+                self.refresh_watched_files();
+            }
+
+            self.metrics.batches_released += 1;
+            self.metrics.changes_released += batch.len() as u64;
+            self.metrics.last_batch_at = Some(chrono::Utc::now());
+            debug!("Releasing batch of {} file changes", batch.len());
+            return Ok(Some(batch));
+        }
+    }
+
+    /// Record an event as pending changes, refreshing their debounce timers
+    fn record_event(&mut self, event: &Event) {
+        if self.is_paused() {
+            // Picked up by the rescan queued on resume
+            self.missed_while_paused = true;
+            return;
+        }
+
+        let now = Instant::now();
+        self.metrics.events_processed += 1;
+        // While a rescan is pending, further events only extend its debounce window
+        if event.need_rescan() || self.rescan_pending {
+            self.record_rescan(now);
+            return;
+        }
+
+        for change in FileChangeEvent::from_notify_event(event) {
+            let mut change = change;
+            let mut first_seen = now;
+
+            // A rename moves any pending change from the old path to the new one
+            let renamed_from = match &change {
+                FileChangeEvent::FileRenamed { from, .. } => self.pending.remove(from),
+                _ => None,
+            };
+            if let Some(previous) = renamed_from {
+                first_seen = previous.first_seen;
+                // Chained renames (a -> b -> c) collapse into a single a -> c rename
+                if let (
+                    FileChangeEvent::FileRenamed { from: original, .. },
+                    FileChangeEvent::FileRenamed { to, .. },
+                ) = (previous.change, change.clone())
+                {
+                    change = FileChangeEvent::FileRenamed { from: original, to };
+                }
+            }
+
+            let path = change.path().to_path_buf();
+            let pending = match self.pending.remove(&path) {
+                Some(previous) => PendingChange {
+                    change: previous.change.coalesce(change),
+                    first_seen: previous.first_seen.min(first_seen),
+                    last_seen: now,
+                },
+                None => PendingChange {
+                    change,
+                    first_seen,
+                    last_seen: now,
+                },
+            };
+            self.pending.insert(path, pending);
+        }
+
+        if self.pending.len() > self.config.max_pending_changes {
+            warn!(
+                "More than {} paths changed at once; falling back to a full rescan",
+                self.config.max_pending_changes
+            );
+            self.record_rescan(now);
+        }
+    }
+
+    /// Snapshot of the watcher's runtime metrics
+    pub fn status(&self) -> FileWatcherStatus {
+        FileWatcherStatus {
+            watched_files: self.metrics.watched_files,
+            root_path: self.config.root_path.clone(),
+            is_running: self.watcher.is_some() && !self.is_stopped(),
+            is_paused: self.is_paused(),
+            backend: self.active_backend,
+            events_received: self.metrics.events_received,
+            events_processed: self.metrics.events_processed,
+            queued_events: self.rx.as_ref().map_or(0, |rx| rx.len()),
+            pending_changes: self.pending.len(),
+            rescan_pending: self.rescan_pending,
+            batches_released: self.metrics.batches_released,
+            changes_released: self.metrics.changes_released,
+            last_event_at: self.metrics.last_event_at,
+            last_batch_at: self.metrics.last_batch_at,
+            last_reindex_at: self.metrics.last_reindex_at,
+        }
+    }
+
+    /// Record that the batches released so far have been applied to the index
+    /// The watcher doesn't index anything itself; whoever consumes its batches calls
+    /// this so `status` can report when the index was last brought up to date.
+    pub fn mark_reindexed(&mut self) {
+        self.metrics.last_reindex_at = Some(chrono::Utc::now());
+    }
+
+    /// Re-read `.gitignore`/`.ignore`/`.codexignore` files and recount watched files
+    /// Called automatically when one of them changes.
+    pub fn reload_ignore_rules(&mut self) {
+        if self.config.respect_ignore_files {
+            self.ignore_matcher = Some(CodebaseIgnoreMatcher::new(&self.config.root_path));
+        }
+        self.refresh_watched_files();
+    }
+
+    /// Whether an event edits an ignore file that is itself in effect
+    fn changes_ignore_rules(&self, event: &Event) -> bool {
+        let Some(matcher) = self.ignore_matcher.as_ref() else {
+            return false;
+        };
+        // Git and editors read these files constantly; only writes matter
+        let is_write = matches!(
+            event.kind,
+            EventKind::Create(_)
+                | EventKind::Remove(_)
+                | EventKind::Modify(ModifyKind::Data(_) | ModifyKind::Name(_) | ModifyKind::Any)
+                | EventKind::Any
+        );
+        // An ignore file inside an ignored directory has no effect
+        is_write
+            && event.paths.iter().any(|path| {
+                is_ignore_file(path)
+                    && path
+                        .parent()
+                        .is_some_and(|dir| !matcher.is_ignored(dir, true))
+            })
     }
 
-    /// Check if a path should be ignored based on configuration
-    fn should_ignore_path(path: &Path, config: &FileWatcherConfig) -> bool {
+    /// Count the files under the root that pass the watcher's filters
+    fn refresh_watched_files(&mut self) {
+        let mut watched_files = 0;
+        let result = walk_codebase_files(&self.config.root_path, |path| {
+            if !Self::should_ignore_path(path, &self.config, self.ignore_matcher.as_ref()) {
+                watched_files += 1;
+            }
+            Ok(true)
+        });
+        match result {
+            Ok(()) => self.metrics.watched_files = watched_files,
+            Err(e) => debug!("Failed to count watched files: {}", e),
+        }
+    }
+
+    /// Replace every pending change with a single rescan of the root
+    fn record_rescan(&mut self, now: Instant) {
+        let first_seen = self
+            .pending
+            .values()
+            .map(|pending| pending.first_seen)
+            .min()
+            .unwrap_or(now);
+        self.pending.clear();
+
+        let root_path = self.config.root_path.clone();
+        self.pending.insert(
+            root_path.clone(),
+            PendingChange {
+                change: FileChangeEvent::Rescan(root_path),
+                first_seen,
+                last_seen: now,
+            },
+        );
+        self.rescan_pending = true;
+    }
+
+    /// Check if a path should be ignored based on configuration and the indexer's ignore rules
+    fn should_ignore_path(
+        path: &Path,
+        config: &FileWatcherConfig,
+        ignore_matcher: Option<&CodebaseIgnoreMatcher>,
+    ) -> bool {
         // Check if any parent directory is in the ignore list
-        for component in path.components() {
-            if let std::path::Component::Normal(name) = component {
+        let in_ignored_dir = path.components().any(|component| {
+            matches!(component, std::path::Component::Normal(name)
                 if config
                     .ignore_dirs
                     .iter()
-                    .any(|ignore| ignore == &name.to_string_lossy())
-                {
-                    return true;
-                }
-            }
+                    .any(|ignore| ignore == &name.to_string_lossy()))
+        });
+        if in_ignored_dir {
+            return true;
+        }
+
+        // Deleted paths no longer exist, so they're matched as files; rules for their
+        // parent directories still apply
+        if ignore_matcher.is_some_and(|matcher| matcher.is_ignored(path, path.is_dir())) {
+            return true;
         }
 
         // Check file extensions if specified
@@ -145,21 +928,47 @@ impl FileWatcher {
 }
 
 /// Status information about the file watcher
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct FileWatcherStatus {
+    /// Files under the root that pass the filters, counted at start and after each rescan
     pub watched_files: usize,
     pub root_path: PathBuf,
+    /// Whether the OS watch is registered and the watcher hasn't been stopped
+    pub is_running: bool,
+    /// Whether reporting is suspended via `pause`
+    pub is_paused: bool,
+    /// The backend in use (`Native` or `Poll`), once started
+    pub backend: Option<WatcherBackend>,
+    /// Raw events delivered by the OS watcher, including ignored ones
+    pub events_received: u64,
+    /// Events that passed the filters and were fed to the debouncer
+    pub events_processed: u64,
+    /// Raw events waiting in the channel
+    pub queued_events: usize,
+    /// Paths being debounced that haven't been released in a batch yet
+    pub pending_changes: usize,
+    /// Whether an event storm collapsed the pending changes into a rescan
+    pub rescan_pending: bool,
+    pub batches_released: u64,
+    pub changes_released: u64,
+    pub last_event_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub last_batch_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// When a consumer last reported applying released batches (see `mark_reindexed`)
+    pub last_reindex_at: Option<chrono::DateTime<chrono::Utc>>,
 }
 
 /// Builder pattern for creating FileWatcher instances
+#[derive(Default)]
 pub struct FileWatcherBuilder {
     config: FileWatcherConfig,
+    shutdown: Option<CancellationToken>,
 }
 
 impl FileWatcherBuilder {
     pub fn new() -> Self {
         Self {
             config: FileWatcherConfig::default(),
+            shutdown: None,
         }
     }
 
@@ -188,16 +997,45 @@ impl FileWatcherBuilder {
         self
     }
 
+    pub fn backend(mut self, backend: WatcherBackend) -> Self {
+        self.config.backend = backend;
+        self
+    }
+
+    pub fn poll_interval(mut self, interval_ms: u64) -> Self {
+        self.config.poll_interval = interval_ms;
+        self
+    }
+
+    pub fn max_pending_changes(mut self, max: usize) -> Self {
+        self.config.max_pending_changes = max;
+        self
+    }
+
+    pub fn respect_ignore_files(mut self, respect: bool) -> Self {
+        self.config.respect_ignore_files = respect;
+        self
+    }
+
+    /// Stop the watcher when `token` is cancelled (e.g. by the host application)
+    pub fn shutdown_token(mut self, token: CancellationToken) -> Self {
+        self.shutdown = Some(token);
+        self
+    }
+
     pub fn build(self) -> FileWatcher {
-        FileWatcher::new(self.config)
+        match self.shutdown {
+            Some(shutdown) => FileWatcher::with_shutdown_token(self.config, shutdown),
+            None => FileWatcher::new(self.config),
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
+    #![allow(clippy::expect_used)]
     use super::*;
     use std::fs;
-    use std::time::Duration;
     use tempfile::TempDir;
     use tokio::time::timeout;
 
@@ -210,10 +1048,13 @@ mod tests {
         let temp_path = temp_dir.path();
 
         // Create a file watcher for the temp directory
-        let mut config = FileWatcherConfig::default();
-        config.root_path = temp_path.to_path_buf();
+        let config = FileWatcherConfig {
+            root_path: temp_path.to_path_buf(),
+            ..Default::default()
+        };
 
         let mut watcher = FileWatcher::new(config);
+        watcher.start().expect("Failed to start watcher");
 
         // Give the watcher a moment to start
         tokio::time::sleep(Duration::from_millis(1000)).await;
@@ -227,14 +1068,15 @@ mod tests {
 
         // build watcher with temp root
         loop {
-          let event = tokio::time::timeout(Duration::from_secs(3), watcher.watch()).await
-              .expect("timeout")
-              .expect("watch error");
-          if event.paths.iter().any(|p| p.ends_with("test.txt")) {
-            assert!(matches!(event.kind, notify::EventKind::Create(_)));
-            break;
-          }
-          // otherwise continue to get the next event
+            let event = tokio::time::timeout(Duration::from_secs(3), watcher.watch())
+                .await
+                .expect("timeout")
+                .expect("watch error");
+            if event.paths.iter().any(|p| p.ends_with("test.txt")) {
+                assert!(matches!(event.kind, notify::EventKind::Create(_)));
+                break;
+            }
+            // otherwise continue to get the next event
         }
 
         // Cleanup happens automatically when TempDir is dropped
@@ -252,6 +1094,7 @@ mod tests {
             recursive: RecursiveMode::Recursive,
             file_extensions: vec!["txt".to_string()],
             ignore_dirs: vec!["ignored".to_string()],
+            ..FileWatcherConfig::default()
         };
 
         let mut watcher = FileWatcher::new(config);
@@ -265,7 +1108,7 @@ mod tests {
         // Create ignored directory
         let ignored_dir = temp_path.join("ignored");
         fs::create_dir(&ignored_dir).expect("Failed to create ignored dir");
-        
+
         // Create file in ignored directory (this should be filtered out)
         let ignored_file = ignored_dir.join("test.txt");
         fs::write(&ignored_file, "ignored content").expect("Failed to write ignored file");
@@ -294,4 +1137,261 @@ mod tests {
             Err(_) => panic!("Timeout waiting for file event"),
         }
     }
+
+    #[tokio::test]
+    async fn test_file_watcher_batches_repeated_writes() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let temp_path = temp_dir.path();
+
+        let mut watcher = FileWatcherBuilder::new()
+            .root_path(temp_path)
+            .debounce_delay(200)
+            .build();
+        watcher.start().expect("Failed to start watcher");
+
+        // Several writes in quick succession, as an editor save or formatter would produce
+        let test_file = temp_path.join("batched.rs");
+        for i in 0..5 {
+            fs::write(&test_file, format!("fn f{i}() {{}}")).expect("Failed to write test file");
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+
+        let batch = timeout(Duration::from_secs(3), watcher.next_batch())
+            .await
+            .expect("timeout")
+            .expect("watch error")
+            .expect("watcher stopped");
+
+        let matching: Vec<_> = batch
+            .iter()
+            .filter(|change| change.path().ends_with("batched.rs"))
+            .collect();
+        assert_eq!(matching.len(), 1);
+        assert!(matches!(matching[0], FileChangeEvent::FileChanged(_)));
+
+        let status = watcher.status();
+        assert!(status.is_running);
+        assert!(status.events_processed >= 1);
+        assert!(status.events_received >= status.events_processed);
+        assert_eq!(status.batches_released, 1);
+        assert_eq!(status.changes_released, batch.len() as u64);
+        assert!(status.last_reindex_at.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_file_watcher_respects_gitignore() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let temp_path = temp_dir.path();
+        fs::write(temp_path.join(".gitignore"), "*.log\ngenerated/\n")
+            .expect("Failed to write .gitignore");
+        fs::create_dir(temp_path.join("generated")).expect("Failed to create generated dir");
+
+        let mut watcher = FileWatcherBuilder::new()
+            .root_path(temp_path)
+            .debounce_delay(100)
+            .build();
+        watcher.start().expect("Failed to start watcher");
+
+        // Everything the indexer would skip is filtered out before batching
+        fs::write(temp_path.join("debug.log"), "noise").expect("Failed to write log file");
+        fs::write(
+            temp_path.join("generated").join("out.rs"),
+            "fn generated() {}",
+        )
+        .expect("Failed to write generated file");
+        fs::write(temp_path.join("lib.rs"), "fn lib() {}").expect("Failed to write lib file");
+
+        let batch = timeout(Duration::from_secs(3), watcher.next_batch())
+            .await
+            .expect("Timeout waiting for file batch")
+            .expect("Watcher error")
+            .expect("Watcher stopped");
+        assert!(!batch.is_empty());
+        for change in &batch {
+            assert!(
+                change.path().ends_with("lib.rs"),
+                "unexpected change: {change:?}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_file_watcher_collapses_event_storm_into_rescan() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let temp_path = temp_dir.path();
+
+        let mut watcher = FileWatcherBuilder::new()
+            .root_path(temp_path)
+            .debounce_delay(200)
+            .max_pending_changes(5)
+            .build();
+        watcher.start().expect("Failed to start watcher");
+
+        for i in 0..20 {
+            fs::write(temp_path.join(format!("file_{i}.rs")), "fn f() {}")
+                .expect("Failed to write test file");
+        }
+
+        let batch = timeout(Duration::from_secs(5), watcher.next_batch())
+            .await
+            .expect("Timeout waiting for file batch")
+            .expect("Watcher error")
+            .expect("Watcher stopped");
+        assert_eq!(
+            batch,
+            vec![FileChangeEvent::Rescan(temp_path.to_path_buf())]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_poll_backend_detects_changes() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let temp_path = temp_dir.path();
+
+        let mut watcher = FileWatcherBuilder::new()
+            .root_path(temp_path)
+            .debounce_delay(100)
+            .backend(WatcherBackend::Poll)
+            .poll_interval(100)
+            .build();
+        watcher.start().expect("Failed to start watcher");
+        assert_eq!(watcher.status().backend, Some(WatcherBackend::Poll));
+
+        fs::write(temp_path.join("polled.rs"), "fn polled() {}").expect("Failed to write file");
+
+        let batch = timeout(Duration::from_secs(5), watcher.next_batch())
+            .await
+            .expect("Timeout waiting for file batch")
+            .expect("Watcher error")
+            .expect("Watcher stopped");
+        assert!(
+            batch
+                .iter()
+                .any(|change| change.path().ends_with("polled.rs"))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_file_watcher_rescans_after_resume() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let temp_path = temp_dir.path();
+
+        let mut watcher = FileWatcherBuilder::new()
+            .root_path(temp_path)
+            .debounce_delay(100)
+            .build();
+        watcher.start().expect("Failed to start watcher");
+
+        let pause = watcher.pause_handle();
+        pause.pause();
+        for i in 0..3 {
+            fs::write(temp_path.join(format!("paused_{i}.rs")), "fn f() {}")
+                .expect("Failed to write test file");
+        }
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(300)).await;
+            pause.resume();
+        });
+
+        let batch = timeout(Duration::from_secs(3), watcher.next_batch())
+            .await
+            .expect("Timeout waiting for file batch")
+            .expect("Watcher error")
+            .expect("Watcher stopped");
+        assert_eq!(
+            batch,
+            vec![FileChangeEvent::Rescan(temp_path.to_path_buf())]
+        );
+        assert!(!watcher.is_paused());
+    }
+
+    #[tokio::test]
+    async fn test_ignore_file_change_requests_rescan() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let temp_path = temp_dir.path();
+
+        let mut watcher = FileWatcherBuilder::new()
+            .root_path(temp_path)
+            .debounce_delay(100)
+            .build();
+        watcher.start().expect("Failed to start watcher");
+
+        fs::write(temp_path.join(".gitignore"), "*.log\n").expect("Failed to write .gitignore");
+
+        let batch = timeout(Duration::from_secs(3), watcher.next_batch())
+            .await
+            .expect("Timeout waiting for file batch")
+            .expect("Watcher error")
+            .expect("Watcher stopped");
+        assert_eq!(
+            batch,
+            vec![FileChangeEvent::Rescan(temp_path.to_path_buf())]
+        );
+
+        // The reloaded rules apply to later events
+        fs::write(temp_path.join("debug.log"), "noise").expect("Failed to write log file");
+        fs::write(temp_path.join("lib.rs"), "fn lib() {}").expect("Failed to write lib file");
+        let batch = timeout(Duration::from_secs(3), watcher.next_batch())
+            .await
+            .expect("Timeout waiting for file batch")
+            .expect("Watcher error")
+            .expect("Watcher stopped");
+        assert!(batch.iter().all(|change| change.path().ends_with("lib.rs")));
+    }
+
+    #[tokio::test]
+    async fn test_file_watcher_stops_on_shutdown() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let shutdown = CancellationToken::new();
+        let mut watcher = FileWatcherBuilder::new()
+            .root_path(temp_dir.path())
+            .debounce_delay(100)
+            .shutdown_token(shutdown.clone())
+            .build();
+        watcher.start().expect("Failed to start watcher");
+
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            shutdown.cancel();
+        });
+
+        let batch = timeout(Duration::from_secs(3), watcher.next_batch())
+            .await
+            .expect("Watcher did not stop")
+            .expect("Watcher error");
+        assert!(batch.is_none());
+        assert!(watcher.is_stopped());
+        assert!(watcher.watch().await.is_err());
+    }
+
+    #[test]
+    fn test_translate_rename_event() {
+        let event = Event::new(EventKind::Modify(ModifyKind::Name(RenameMode::Both)))
+            .add_path(PathBuf::from("/nonexistent/old.rs"))
+            .add_path(PathBuf::from("/nonexistent/new.rs"));
+
+        assert_eq!(
+            FileChangeEvent::from_notify_event(&event),
+            vec![FileChangeEvent::FileRenamed {
+                from: PathBuf::from("/nonexistent/old.rs"),
+                to: PathBuf::from("/nonexistent/new.rs"),
+            }]
+        );
+
+        // Editing the new path keeps the rename; deleting it leaves only the old path
+        let renamed = FileChangeEvent::FileRenamed {
+            from: PathBuf::from("old.rs"),
+            to: PathBuf::from("new.rs"),
+        };
+        assert_eq!(
+            renamed
+                .clone()
+                .coalesce(FileChangeEvent::FileChanged(PathBuf::from("new.rs"))),
+            renamed
+        );
+        assert_eq!(
+            renamed.coalesce(FileChangeEvent::FileDeleted(PathBuf::from("new.rs"))),
+            FileChangeEvent::FileDeleted(PathBuf::from("old.rs"))
+        );
+    }
 }
@@ -0,0 +1,135 @@
+use std::collections::BTreeSet;
+use std::path::Path;
+use std::process::Command;
+
+use crate::file_state::GitBaseline;
+
+/// Files that differ from a commit, as reported by git
+/// Paths are relative to the project root and limited to files beneath it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GitChanges {
+    /// Added, modified or untracked files
+    pub changed: Vec<String>,
+    pub deleted: Vec<String>,
+}
+
+/// Run git in `root_path` and return its stdout
+fn git(root_path: &Path, args: &[&str]) -> Result<Vec<u8>, anyhow::Error> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(root_path)
+        .args(args)
+        .output()
+        .map_err(|e| anyhow::anyhow!("Failed to run git: {e}"))?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(output.stdout)
+}
+
+/// Split NUL-separated git output into paths
+fn split_nul(output: &[u8]) -> impl Iterator<Item = String> + '_ {
+    output
+        .split(|byte| *byte == 0)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| String::from_utf8_lossy(entry).into_owned())
+}
+
+/// The commit currently checked out
+pub fn head_commit(root_path: &Path) -> Result<String, anyhow::Error> {
+    let output = git(root_path, &["rev-parse", "HEAD"])?;
+    Ok(String::from_utf8_lossy(&output).trim().to_string())
+}
+
+/// Compare the working tree, including untracked files that aren't ignored, with `commit`
+pub fn changed_since(root_path: &Path, commit: &str) -> Result<GitChanges, anyhow::Error> {
+    let mut changes = GitChanges::default();
+
+    // `-z --name-status` prints `<status>\0<path>\0` pairs; `--no-renames` reports a
+    // rename as a delete plus an add so every entry has exactly one path
+    let diff = git(
+        root_path,
+        &[
+            "diff",
+            "--relative",
+            "--name-status",
+            "--no-renames",
+            "-z",
+            commit,
+            "--",
+        ],
+    )?;
+    let mut entries = split_nul(&diff);
+    while let (Some(status), Some(path)) = (entries.next(), entries.next()) {
+        if status.starts_with('D') {
+            changes.deleted.push(path);
+        } else {
+            changes.changed.push(path);
+        }
+    }
+
+    let untracked = git(
+        root_path,
+        &["ls-files", "--others", "--exclude-standard", "-z"],
+    )?;
+    changes.changed.extend(split_nul(&untracked));
+
+    Ok(changes)
+}
+
+/// Record HEAD and the files that currently differ from it
+pub fn snapshot(root_path: &Path) -> Result<GitBaseline, anyhow::Error> {
+    let commit = head_commit(root_path)?;
+    let changes = changed_since(root_path, &commit)?;
+    Ok(GitBaseline {
+        commit,
+        dirty_files: changes
+            .changed
+            .into_iter()
+            .chain(changes.deleted)
+            .collect::<BTreeSet<_>>(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::expect_used)]
+    use super::*;
+    use tempfile::TempDir;
+
+    fn run_git(root_path: &Path, args: &[&str]) {
+        git(root_path, args).expect("git command failed");
+    }
+
+    #[test]
+    fn test_changed_since_reports_worktree_changes() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let root = temp_dir.path();
+        run_git(root, &["init", "-q"]);
+        run_git(root, &["config", "user.email", "test@example.com"]);
+        run_git(root, &["config", "user.name", "Test"]);
+        std::fs::write(root.join("kept.rs"), "fn kept() {}\n").expect("write");
+        std::fs::write(root.join("edited.rs"), "fn edited() {}\n").expect("write");
+        std::fs::write(root.join("removed.rs"), "fn removed() {}\n").expect("write");
+        run_git(root, &["add", "."]);
+        run_git(root, &["commit", "-q", "-m", "init"]);
+        let commit = head_commit(root).expect("Failed to read HEAD");
+
+        std::fs::write(root.join("edited.rs"), "fn edited() { todo!() }\n").expect("write");
+        std::fs::remove_file(root.join("removed.rs")).expect("remove");
+        std::fs::write(root.join("new.rs"), "fn new() {}\n").expect("write");
+
+        let mut changes = changed_since(root, &commit).expect("Failed to diff");
+        changes.changed.sort();
+        assert_eq!(changes.changed, vec!["edited.rs", "new.rs"]);
+        assert_eq!(changes.deleted, vec!["removed.rs"]);
+
+        let baseline = snapshot(root).expect("Failed to snapshot");
+        assert_eq!(baseline.commit, commit);
+        assert_eq!(baseline.dirty_files.len(), 3);
+    }
+}
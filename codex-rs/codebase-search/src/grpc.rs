@@ -0,0 +1,613 @@
+//! gRPC service for remote indexing and search (`grpc` feature)
+//! A central machine hosts the index of one or more projects for a team, keeps it up
+//! to date with file watchers, and thin clients connect with `CodebaseSearchClient`.
+//! The protocol is defined in `proto/codebase_search.proto`; the messages below
+//! mirror it field for field, and `build.rs` generates the server and client.
+//! `GrpcService::with_team` serves the projects of a `TeamConfig` to token holders.
+
+use std::collections::HashSet;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::PoisonError;
+
+use futures::Stream;
+use tokio::sync::broadcast;
+use tokio::sync::mpsc::UnboundedReceiver;
+use tokio_util::sync::CancellationToken;
+use tonic::Request;
+use tonic::Response;
+use tonic::Status;
+use tracing::info;
+use tracing::warn;
+
+use crate::file_watcher::FileWatcherBuilder;
+use crate::indexer::IndexService;
+use crate::indexer::WorkspaceIndexService;
+use crate::indexer::WorkspaceUpdate;
+use crate::indexer::index_lock;
+use crate::retriever::SearchFilter;
+use crate::retriever::search_codebase_filtered;
+use crate::team::AccessError;
+use crate::team::Role;
+use crate::team::TeamConfig;
+use crate::team::bearer_token;
+use crate::vector_db::IndexStatus;
+use crate::vector_db::index_status;
+use crate::vector_db::init_session;
+use crate::vector_db::restore_session;
+
+include!(concat!(
+    env!("OUT_DIR"),
+    "/codebase_search.v1.CodebaseSearch.rs"
+));
+
+pub use codebase_search_client::CodebaseSearchClient;
+pub use codebase_search_server::CodebaseSearch;
+pub use codebase_search_server::CodebaseSearchServer;
+
+/// Port `codebase-search grpc` listens on unless told otherwise
+pub const DEFAULT_PORT: u16 = 50051;
+
+const DEFAULT_LIMIT: u32 = 10;
+const DEFAULT_MIN_SCORE: f32 = 0.7;
+
+/// Index updates kept for a `Watch` subscriber that falls behind
+const EVENT_BUFFER: usize = 256;
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct IndexRequest {
+    #[prost(string, tag = "1")]
+    pub directory: String,
+    #[prost(bool, tag = "2")]
+    pub force: bool,
+    #[prost(string, tag = "3")]
+    pub project: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct SearchRequest {
+    #[prost(string, tag = "1")]
+    pub query: String,
+    #[prost(string, tag = "2")]
+    pub directory: String,
+    #[prost(uint32, optional, tag = "3")]
+    pub limit: Option<u32>,
+    #[prost(float, optional, tag = "4")]
+    pub min_score: Option<f32>,
+    #[prost(string, repeated, tag = "5")]
+    pub kinds: Vec<String>,
+    #[prost(string, repeated, tag = "6")]
+    pub paths: Vec<String>,
+    #[prost(string, repeated, tag = "7")]
+    pub exclude_paths: Vec<String>,
+    #[prost(string, repeated, tag = "8")]
+    pub languages: Vec<String>,
+    #[prost(bool, tag = "9")]
+    pub exclude_tests: bool,
+    #[prost(string, tag = "10")]
+    pub project: String,
+    #[prost(bool, tag = "11")]
+    pub public_only: bool,
+    #[prost(uint32, optional, tag = "12")]
+    pub min_lines: Option<u32>,
+    #[prost(uint32, optional, tag = "13")]
+    pub min_branches: Option<u32>,
+    #[prost(bool, tag = "14")]
+    pub only_tests: bool,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct SearchResponse {
+    #[prost(message, repeated, tag = "1")]
+    pub results: Vec<SearchResult>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct SearchResult {
+    #[prost(string, tag = "1")]
+    pub id: String,
+    #[prost(float, tag = "2")]
+    pub score: f32,
+    #[prost(string, tag = "3")]
+    pub file_path: String,
+    #[prost(uint32, tag = "4")]
+    pub start_line: u32,
+    #[prost(uint32, tag = "5")]
+    pub end_line: u32,
+    #[prost(string, tag = "6")]
+    pub symbol_name: String,
+    #[prost(string, tag = "7")]
+    pub symbol_kind: String,
+    #[prost(string, tag = "8")]
+    pub content: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct StatusRequest {
+    #[prost(string, tag = "1")]
+    pub directory: String,
+    #[prost(string, tag = "2")]
+    pub project: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct StatusResponse {
+    #[prost(string, tag = "1")]
+    pub root_path: String,
+    #[prost(bool, tag = "2")]
+    pub indexed: bool,
+    #[prost(string, tag = "3")]
+    pub collection_id: String,
+    #[prost(uint64, optional, tag = "4")]
+    pub point_count: Option<u64>,
+    #[prost(string, optional, tag = "5")]
+    pub collection_error: Option<String>,
+    #[prost(uint64, tag = "6")]
+    pub tracked_files: u64,
+    #[prost(uint64, tag = "7")]
+    pub tracked_chunks: u64,
+    #[prost(int64, optional, tag = "8")]
+    pub last_indexed_at: Option<i64>,
+    #[prost(string, optional, tag = "9")]
+    pub git_commit: Option<String>,
+    #[prost(string, repeated, tag = "10")]
+    pub pending_added: Vec<String>,
+    #[prost(string, repeated, tag = "11")]
+    pub pending_modified: Vec<String>,
+    #[prost(string, repeated, tag = "12")]
+    pub pending_deleted: Vec<String>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct WatchRequest {
+    #[prost(string, tag = "1")]
+    pub directory: String,
+    #[prost(string, tag = "2")]
+    pub project: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct IndexEvent {
+    #[prost(string, tag = "1")]
+    pub root_path: String,
+    #[prost(uint64, tag = "2")]
+    pub changes: u64,
+    #[prost(uint64, tag = "3")]
+    pub added: u64,
+    #[prost(uint64, tag = "4")]
+    pub modified: u64,
+    #[prost(uint64, tag = "5")]
+    pub deleted: u64,
+    #[prost(string, optional, tag = "6")]
+    pub error: Option<String>,
+}
+
+impl From<crate::retriever::SearchResult> for SearchResult {
+    fn from(result: crate::retriever::SearchResult) -> Self {
+        let chunk = result.chunk;
+        Self {
+            id: result.id,
+            score: result.score,
+            file_path: chunk.file_path.to_string_lossy().into_owned(),
+            start_line: u32::try_from(chunk.start_line).unwrap_or(u32::MAX),
+            end_line: u32::try_from(chunk.end_line).unwrap_or(u32::MAX),
+            symbol_name: chunk.symbol_name,
+            symbol_kind: chunk.symbol_kind,
+            content: chunk.content,
+        }
+    }
+}
+
+impl From<IndexStatus> for StatusResponse {
+    fn from(status: IndexStatus) -> Self {
+        let state = status.state;
+        let pending = status.pending.unwrap_or_default();
+        Self {
+            root_path: status.root_path.to_string_lossy().into_owned(),
+            indexed: status.indexed,
+            collection_id: status.collection_id,
+            point_count: status.point_count,
+            collection_error: status.collection_error,
+            tracked_files: state.as_ref().map_or(0, |state| state.tracked_files as u64),
+            tracked_chunks: state
+                .as_ref()
+                .map_or(0, |state| state.tracked_chunks as u64),
+            last_indexed_at: state
+                .as_ref()
+                .and_then(|state| state.last_indexed_at)
+                .map(|time| time.timestamp()),
+            git_commit: state.and_then(|state| state.git_commit),
+            pending_added: pending.added,
+            pending_modified: pending.modified,
+            pending_deleted: pending.deleted,
+        }
+    }
+}
+
+impl From<WorkspaceUpdate> for IndexEvent {
+    fn from(update: WorkspaceUpdate) -> Self {
+        let (result, error) = match update.result {
+            Ok(result) => (result, None),
+            Err(e) => (Default::default(), Some(e.to_string())),
+        };
+        Self {
+            root_path: update.root_path.to_string_lossy().into_owned(),
+            changes: update.changes as u64,
+            added: result.added as u64,
+            modified: result.modified as u64,
+            deleted: result.deleted as u64,
+            error,
+        }
+    }
+}
+
+/// The `CodebaseSearch` service for a fixed set of projects
+/// Requests can only name those projects, so clients can't index or read anything
+/// else on the server.
+#[derive(Debug, Clone)]
+pub struct GrpcService {
+    roots: Arc<Vec<PathBuf>>,
+    /// Projects with an index run in flight; a second `Index` for one is rejected
+    indexing: Arc<Mutex<HashSet<PathBuf>>>,
+    events: broadcast::Sender<IndexEvent>,
+    /// Projects and tokens when serving a team; requests then name a project
+    team: Option<Arc<TeamConfig>>,
+}
+
+/// Removes a project from `GrpcService::indexing` when its index run ends
+struct IndexingGuard {
+    indexing: Arc<Mutex<HashSet<PathBuf>>>,
+    directory: PathBuf,
+}
+
+impl Drop for IndexingGuard {
+    fn drop(&mut self) {
+        self.indexing
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(&self.directory);
+    }
+}
+
+impl GrpcService {
+    /// Serve the projects at `roots`
+    pub fn new(roots: Vec<PathBuf>) -> Self {
+        let roots = roots
+            .into_iter()
+            .map(|root| canonical_directory(&root))
+            .collect();
+        let (events, _) = broadcast::channel(EVENT_BUFFER);
+        Self {
+            roots: Arc::new(roots),
+            indexing: Arc::default(),
+            events,
+            team: None,
+        }
+    }
+
+    /// Serve the projects of `team`, to callers holding one of its tokens
+    pub fn with_team(team: TeamConfig) -> Self {
+        let roots = team
+            .projects()
+            .iter()
+            .map(|project| project.path.clone())
+            .collect();
+        Self {
+            team: Some(Arc::new(team)),
+            ..Self::new(roots)
+        }
+    }
+
+    /// Canonical roots of the served projects
+    pub fn roots(&self) -> &[PathBuf] {
+        &self.roots
+    }
+
+    /// Send a workspace's updates to `Watch` subscribers until the stream ends
+    pub async fn publish(&self, mut updates: UnboundedReceiver<WorkspaceUpdate>) {
+        while let Some(update) = updates.recv().await {
+            // Nobody listening is fine
+            let _ = self.events.send(update.into());
+        }
+    }
+
+    /// The served project a request names; an empty directory means the only one
+    fn resolve(&self, directory: &str) -> Result<PathBuf, Status> {
+        if directory.is_empty() {
+            return match self.roots.as_slice() {
+                [root] => Ok(root.clone()),
+                _ => Err(Status::invalid_argument(
+                    "directory is required when the server hosts several projects",
+                )),
+            };
+        }
+        let directory = canonical_directory(Path::new(directory));
+        if self.roots.contains(&directory) {
+            Ok(directory)
+        } else {
+            Err(Status::not_found(format!(
+                "{} is not hosted by this server",
+                directory.display()
+            )))
+        }
+    }
+
+    /// The project a request may act on as `role`: with a team, the named `project`
+    /// if the request's token allows it, otherwise the hosted `directory`
+    fn authorize<T>(
+        &self,
+        request: &Request<T>,
+        project: &str,
+        directory: &str,
+        role: Role,
+    ) -> Result<PathBuf, Status> {
+        let Some(team) = &self.team else {
+            return self.resolve(directory);
+        };
+        let project = Some(project).filter(|project| !project.is_empty());
+        team.authorize(request_token(request), project, role)
+            .map(|project| project.path.clone())
+            .map_err(access_denied)
+    }
+}
+
+/// The API token sent as `authorization: Bearer <token>` metadata
+fn request_token<T>(request: &Request<T>) -> Option<&str> {
+    request
+        .metadata()
+        .get("authorization")
+        .and_then(|value| value.to_str().ok())
+        .and_then(bearer_token)
+}
+
+fn access_denied(error: AccessError) -> Status {
+    let message = error.to_string();
+    match error {
+        AccessError::Unauthenticated => Status::unauthenticated(message),
+        AccessError::Forbidden(_) => Status::permission_denied(message),
+        AccessError::UnknownProject(_) => Status::not_found(message),
+        AccessError::ProjectRequired => Status::invalid_argument(message),
+    }
+}
+
+/// Resolve a requested directory the same way the CLI does
+fn canonical_directory(directory: &Path) -> PathBuf {
+    directory
+        .canonicalize()
+        .unwrap_or_else(|_| directory.to_path_buf())
+}
+
+fn internal(error: anyhow::Error) -> Status {
+    Status::internal(error.to_string())
+}
+
+#[tonic::async_trait]
+impl CodebaseSearch for GrpcService {
+    async fn index(
+        &self,
+        request: Request<IndexRequest>,
+    ) -> Result<Response<StatusResponse>, Status> {
+        let directory = self.authorize(
+            &request,
+            &request.get_ref().project,
+            &request.get_ref().directory,
+            Role::Maintainer,
+        )?;
+        let request = request.into_inner();
+        let inserted = self
+            .indexing
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(directory.clone());
+        if !inserted {
+            return Err(Status::aborted(format!(
+                "{} is already being indexed",
+                directory.display()
+            )));
+        }
+        let _guard = IndexingGuard {
+            indexing: self.indexing.clone(),
+            directory: directory.clone(),
+        };
+
+        // Waits for this server's watcher if it is updating the same index
+        let lock = index_lock(&directory);
+        let _lock = lock.lock().await;
+
+        info!("Indexing {} on request", directory.display());
+        let result = if request.force {
+            init_session(&directory).await
+        } else {
+            restore_session(&directory).await
+        };
+        result.map_err(|e| {
+            warn!("Indexing {} failed: {e}", directory.display());
+            internal(e)
+        })?;
+        let status = index_status(&directory).await.map_err(internal)?;
+        Ok(Response::new(status.into()))
+    }
+
+    async fn search(
+        &self,
+        request: Request<SearchRequest>,
+    ) -> Result<Response<SearchResponse>, Status> {
+        let directory = self.authorize(
+            &request,
+            &request.get_ref().project,
+            &request.get_ref().directory,
+            Role::Reader,
+        )?;
+        let request = request.into_inner();
+        let filter = SearchFilter {
+            kinds: request.kinds,
+            paths: request.paths,
+            exclude_paths: request.exclude_paths,
+            languages: request.languages,
+            exclude_tests: request.exclude_tests,
+            only_tests: request.only_tests,
+            public_only: request.public_only,
+            min_lines: request.min_lines.map(|lines| lines as usize),
+            min_branches: request.min_branches.map(|branches| branches as usize),
+        };
+        let results = search_codebase_filtered(
+            request.query,
+            &directory,
+            request.limit.unwrap_or(DEFAULT_LIMIT) as usize,
+            request.min_score.unwrap_or(DEFAULT_MIN_SCORE),
+            &filter,
+        )
+        .await
+        .map_err(internal)?;
+        Ok(Response::new(SearchResponse {
+            results: results.into_iter().map(SearchResult::from).collect(),
+        }))
+    }
+
+    async fn status(
+        &self,
+        request: Request<StatusRequest>,
+    ) -> Result<Response<StatusResponse>, Status> {
+        let directory = self.authorize(
+            &request,
+            &request.get_ref().project,
+            &request.get_ref().directory,
+            Role::Reader,
+        )?;
+        let status = index_status(&directory).await.map_err(internal)?;
+        Ok(Response::new(status.into()))
+    }
+
+    type WatchStream = Pin<Box<dyn Stream<Item = Result<IndexEvent, Status>> + Send>>;
+
+    async fn watch(
+        &self,
+        request: Request<WatchRequest>,
+    ) -> Result<Response<Self::WatchStream>, Status> {
+        let WatchRequest { directory, project } = request.get_ref();
+        // Roots whose updates are streamed; None streams every hosted project
+        let roots = match &self.team {
+            Some(team) if project.is_empty() => Some(
+                team.readable_roots(request_token(&request))
+                    .map_err(access_denied)?,
+            ),
+            _ if project.is_empty() && directory.is_empty() => None,
+            _ => Some(vec![self.authorize(
+                &request,
+                project,
+                directory,
+                Role::Reader,
+            )?]),
+        };
+        let roots: Option<Vec<String>> = roots.map(|roots| {
+            roots
+                .iter()
+                .map(|root| root.to_string_lossy().into_owned())
+                .collect()
+        });
+
+        let events = self.events.subscribe();
+        let stream = futures::stream::unfold(events, move |mut events| {
+            let roots = roots.clone();
+            async move {
+                loop {
+                    match events.recv().await {
+                        Ok(event) => {
+                            if roots
+                                .as_ref()
+                                .is_none_or(|roots| roots.contains(&event.root_path))
+                            {
+                                return Some((Ok(event), events));
+                            }
+                        }
+                        Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                            warn!("Watch subscriber fell behind, skipped {skipped} updates");
+                        }
+                        Err(broadcast::error::RecvError::Closed) => return None,
+                    }
+                }
+            }
+        });
+        Ok(Response::new(Box::pin(stream)))
+    }
+}
+
+/// Bring every project of `service` up to date, then serve them on `addr`, reindexing
+/// changed files as they're saved, until `shutdown` is cancelled
+pub async fn serve(
+    addr: SocketAddr,
+    service: GrpcService,
+    shutdown: CancellationToken,
+) -> Result<(), anyhow::Error> {
+    let mut services = Vec::new();
+    for root in service.roots() {
+        info!("Bringing index up to date: {}", root.display());
+        restore_session(root).await?;
+
+        let mut watcher = FileWatcherBuilder::new()
+            .root_path(root)
+            .shutdown_token(shutdown.clone())
+            .build();
+        watcher.start()?;
+        services.push(IndexService::new(watcher)?);
+    }
+    let updates = WorkspaceIndexService::new(services)?.spawn();
+    tokio::spawn({
+        let service = service.clone();
+        async move { service.publish(updates).await }
+    });
+
+    info!("Serving codebase search over gRPC on {addr}");
+    tonic::transport::Server::builder()
+        .add_service(CodebaseSearchServer::new(service))
+        .serve_with_shutdown(addr, shutdown.cancelled_owned())
+        .await
+        .map_err(|e| anyhow::anyhow!("gRPC server error: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::expect_used)]
+    use super::*;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn requests_only_reach_hosted_projects() {
+        let hosted = TempDir::new().expect("Failed to create temp dir");
+        let other = TempDir::new().expect("Failed to create temp dir");
+        let service = GrpcService::new(vec![hosted.path().to_path_buf()]);
+        let root = hosted
+            .path()
+            .canonicalize()
+            .expect("Failed to canonicalize");
+
+        assert_eq!(service.resolve("").expect("Default project"), root);
+        assert_eq!(
+            service
+                .resolve(&hosted.path().to_string_lossy())
+                .expect("Hosted project"),
+            root
+        );
+        let status = service
+            .status(Request::new(StatusRequest {
+                directory: other.path().to_string_lossy().into_owned(),
+                project: String::new(),
+            }))
+            .await
+            .expect_err("Other directories are not served");
+        assert_eq!(status.code(), tonic::Code::NotFound);
+
+        let two_projects = GrpcService::new(vec![
+            hosted.path().to_path_buf(),
+            other.path().to_path_buf(),
+        ]);
+        let status = two_projects
+            .resolve("")
+            .expect_err("Ambiguous without a directory");
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+    }
+}
@@ -0,0 +1,440 @@
+use std::fs::File;
+use std::io::BufRead;
+use std::io::BufReader;
+use std::io::Read;
+use std::path::Path;
+use std::path::PathBuf;
+
+use qdrant_client::Payload;
+use qdrant_client::qdrant::CreateCollectionBuilder;
+use qdrant_client::qdrant::Distance;
+use qdrant_client::qdrant::PointId;
+use qdrant_client::qdrant::PointStruct;
+use qdrant_client::qdrant::ScrollPointsBuilder;
+use qdrant_client::qdrant::UpsertPointsBuilder;
+use qdrant_client::qdrant::VectorParamsBuilder;
+use qdrant_client::qdrant::vector_output::Vector;
+use serde::Deserialize;
+use serde::Serialize;
+use tracing::info;
+use tracing::warn;
+
+use crate::embedding::get_embedding_client;
+use crate::file_state::CodebaseState;
+use crate::file_state::IndexParameters;
+use crate::file_state::remember_root;
+use crate::file_state::state_file_path;
+use crate::index_config::IndexConfig;
+use crate::progress::IndexPhase;
+use crate::progress::IndexProgress;
+use crate::progress::ProgressFn;
+use crate::retriever::point_id_to_string;
+use crate::vector_db::QDRANT_CLIENT;
+use crate::vector_db::cleanup_collection;
+use crate::vector_db::collection_dimension;
+use crate::vector_db::create_payload_indexes;
+use crate::vector_db::generate_collection_id;
+
+/// Bumped whenever the archive layout changes incompatibly
+const ARCHIVE_VERSION: u32 = 1;
+const MANIFEST_ENTRY: &str = "manifest.json";
+const STATE_ENTRY: &str = "state.json";
+/// Points are stored as numbered NDJSON pages under this directory
+const POINTS_DIR: &str = "points";
+const EXPORT_PAGE_SIZE: u32 = 256;
+const ZSTD_LEVEL: i32 = 3;
+
+/// First entry of an index archive, describing what follows
+#[derive(Debug, Clone, Deserialize, Serialize)]
+struct ArchiveManifest {
+    version: u32,
+    /// Collection the archive was exported from
+    collection_id: String,
+    exported_at: chrono::DateTime<chrono::Utc>,
+    points: u64,
+    dimension: u64,
+}
+
+/// A point as stored in an archive; payload file paths are root-relative, so points
+/// can be imported under any project root
+#[derive(Debug, Clone, Deserialize, Serialize)]
+struct ArchivedPoint {
+    id: String,
+    vector: Vec<f32>,
+    payload: serde_json::Map<String, serde_json::Value>,
+}
+
+impl ArchivedPoint {
+    fn into_point(self) -> Result<PointStruct, anyhow::Error> {
+        let id = match self.id.parse::<u64>() {
+            Ok(num) => PointId::from(num),
+            Err(_) => PointId::from(self.id),
+        };
+        let payload = Payload::try_from(serde_json::Value::Object(self.payload))
+            .map_err(|e| anyhow::anyhow!("Invalid point payload: {e}"))?;
+        Ok(PointStruct::new(id, self.vector, payload))
+    }
+}
+
+/// What `export_index` wrote
+#[derive(Debug, Clone)]
+pub struct ExportedIndex {
+    pub collection_id: String,
+    pub archive_path: PathBuf,
+    pub points: u64,
+    pub tracked_files: usize,
+}
+
+/// What `import_index` restored
+#[derive(Debug, Clone)]
+pub struct ImportedIndex {
+    pub collection_id: String,
+    /// Collection the archive was exported from
+    pub source_collection_id: String,
+    pub points: u64,
+    pub tracked_files: usize,
+    /// Index settings of the archive that differ from the current configuration, in
+    /// which case searches embed queries differently from the imported chunks
+    pub mismatched_settings: Vec<String>,
+}
+
+/// Write a project's collection and state file to a zstd-compressed tar archive
+/// The archive can be imported under another root, e.g. an index built in CI.
+pub async fn export_index<P: AsRef<Path>>(
+    root_path: P,
+    archive_path: &Path,
+) -> Result<ExportedIndex, anyhow::Error> {
+    let root_path = root_path.as_ref();
+    let collection_id = generate_collection_id(root_path);
+
+    let state_path = state_file_path(root_path)?;
+    if !state_path.is_file() {
+        anyhow::bail!(
+            "{} is not indexed (no state file at {}); run index-codebase first",
+            root_path.display(),
+            state_path.display()
+        );
+    }
+    let state = CodebaseState::from_file(&state_path)?;
+
+    let info = QDRANT_CLIENT
+        .collection_info(collection_id.as_str())
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to read collection {collection_id}: {e}"))?
+        .result
+        .ok_or_else(|| anyhow::anyhow!("Collection {collection_id} has no info"))?;
+    let dimension = collection_dimension(&info).ok_or_else(|| {
+        anyhow::anyhow!("Collection {collection_id} doesn't use a single unnamed vector")
+    })?;
+    let manifest = ArchiveManifest {
+        version: ARCHIVE_VERSION,
+        collection_id: collection_id.clone(),
+        exported_at: chrono::Utc::now(),
+        points: info.points_count.unwrap_or_default(),
+        dimension,
+    };
+
+    let file = File::create(archive_path)
+        .map_err(|e| anyhow::anyhow!("Failed to create {}: {e}", archive_path.display()))?;
+    let mut archive = tar::Builder::new(zstd::Encoder::new(file, ZSTD_LEVEL)?);
+    append_entry(
+        &mut archive,
+        MANIFEST_ENTRY,
+        &serde_json::to_vec_pretty(&manifest)?,
+    )?;
+    append_entry(
+        &mut archive,
+        STATE_ENTRY,
+        &serde_json::to_vec_pretty(&state)?,
+    )?;
+
+    let mut points = 0;
+    let mut page = 0;
+    let mut offset = None;
+    loop {
+        let mut request = ScrollPointsBuilder::new(collection_id.as_str())
+            .limit(EXPORT_PAGE_SIZE)
+            .with_payload(true)
+            .with_vectors(true);
+        if let Some(offset) = offset.take() {
+            request = request.offset(offset);
+        }
+        let response = QDRANT_CLIENT
+            .scroll(request)
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to read collection {collection_id}: {e}"))?;
+
+        let mut lines = Vec::new();
+        for point in response.result {
+            let vector = match point.vectors.and_then(|vectors| vectors.get_vector()) {
+                Some(Vector::Dense(dense)) => dense.data,
+                _ => anyhow::bail!("Point in {collection_id} has no dense vector"),
+            };
+            let archived = ArchivedPoint {
+                id: point_id_to_string(point.id),
+                vector,
+                payload: Payload::from(point.payload).into(),
+            };
+            serde_json::to_writer(&mut lines, &archived)?;
+            lines.push(b'\n');
+            points += 1;
+        }
+        if !lines.is_empty() {
+            append_entry(
+                &mut archive,
+                &format!("{POINTS_DIR}/{page:06}.ndjson"),
+                &lines,
+            )?;
+            page += 1;
+        }
+
+        match response.next_page_offset {
+            Some(next) => offset = Some(next),
+            None => break,
+        }
+    }
+    archive.into_inner()?.finish()?;
+
+    info!(
+        "Exported {points} points from {collection_id} to {}",
+        archive_path.display()
+    );
+    Ok(ExportedIndex {
+        collection_id,
+        archive_path: archive_path.to_path_buf(),
+        points,
+        tracked_files: state.file_states.len(),
+    })
+}
+
+fn append_entry<W: std::io::Write>(
+    archive: &mut tar::Builder<W>,
+    path: &str,
+    data: &[u8],
+) -> Result<(), anyhow::Error> {
+    let mut header = tar::Header::new_gnu();
+    header.set_size(data.len() as u64);
+    header.set_mode(0o644);
+    header.set_mtime(chrono::Utc::now().timestamp().max(0) as u64);
+    archive.append_data(&mut header, path, data)?;
+    Ok(())
+}
+
+/// Restore an archive written by `export_index` as the index of `root_path`
+/// Files changed since the export are picked up by the next incremental update. An
+/// existing index is only replaced when `replace` is set.
+pub async fn import_index<P: AsRef<Path>>(
+    archive_path: &Path,
+    root_path: P,
+    replace: bool,
+    progress: &ProgressFn<'_>,
+) -> Result<ImportedIndex, anyhow::Error> {
+    let root_path = root_path.as_ref();
+    let collection_id = generate_collection_id(root_path);
+    let state_path = state_file_path(root_path)?;
+
+    let collection_exists = QDRANT_CLIENT
+        .collection_exists(collection_id.as_str())
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to check collection {collection_id}: {e}"))?;
+    if !replace && (collection_exists || state_path.is_file()) {
+        anyhow::bail!(
+            "{} is already indexed; pass --force to replace its index",
+            root_path.display()
+        );
+    }
+
+    let file = File::open(archive_path)
+        .map_err(|e| anyhow::anyhow!("Failed to open {}: {e}", archive_path.display()))?;
+    let mut archive = tar::Archive::new(zstd::Decoder::new(file)?);
+    let mut entries = archive.entries()?;
+
+    let manifest: ArchiveManifest = read_json_entry(entries.next(), MANIFEST_ENTRY)?;
+    if manifest.version != ARCHIVE_VERSION {
+        anyhow::bail!(
+            "Archive version {} is not supported (expected {ARCHIVE_VERSION})",
+            manifest.version
+        );
+    }
+    let state: CodebaseState = read_json_entry(entries.next(), STATE_ENTRY)?;
+
+    if collection_exists {
+        QDRANT_CLIENT
+            .delete_collection(collection_id.as_str())
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to delete collection {collection_id}: {e}"))?;
+        info!("Deleted collection {collection_id} before import");
+    }
+    QDRANT_CLIENT
+        .create_collection(
+            CreateCollectionBuilder::new(collection_id.clone()).vectors_config(
+                VectorParamsBuilder::new(manifest.dimension, Distance::Cosine),
+            ),
+        )
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to create collection {collection_id}: {e}"))?;
+    if let Err(e) = create_payload_indexes(&collection_id).await {
+        cleanup_collection(&collection_id, &e.to_string()).await;
+        return Err(e);
+    }
+
+    progress(IndexProgress::Started {
+        phase: IndexPhase::Upserting,
+        total: manifest.points,
+    });
+    let mut points = 0;
+    for entry in entries {
+        let result = match entry {
+            Ok(entry) => upsert_page(&collection_id, entry).await,
+            Err(e) => Err(e.into()),
+        };
+        match result {
+            Ok(count) => {
+                points += count;
+                progress(IndexProgress::Advanced {
+                    phase: IndexPhase::Upserting,
+                    count,
+                });
+            }
+            Err(e) => {
+                let error_msg = format!("Failed to import {}: {e}", archive_path.display());
+                cleanup_collection(&collection_id, &error_msg).await;
+                return Err(anyhow::anyhow!(error_msg));
+            }
+        }
+    }
+    progress(IndexProgress::Finished {
+        phase: IndexPhase::Upserting,
+    });
+
+    if let Err(e) = state.to_file(&state_path) {
+        let error_msg = format!("Failed to save state file: {e}");
+        cleanup_collection(&collection_id, &error_msg).await;
+        return Err(anyhow::anyhow!(error_msg));
+    }
+    if let Err(e) = remember_root(root_path) {
+        warn!("Failed to record project root for {collection_id}: {e}");
+    }
+
+    let mismatched_settings = match (&state.index_params, get_embedding_client()) {
+        (Some(saved), Ok(client)) => {
+            let chunking = IndexConfig::load(root_path)?.chunking_options();
+            saved.differences(&IndexParameters::new(client.config(), &chunking))
+        }
+        _ => Vec::new(),
+    };
+
+    info!(
+        "Imported {points} points from {} into {collection_id}",
+        archive_path.display()
+    );
+    Ok(ImportedIndex {
+        collection_id,
+        source_collection_id: manifest.collection_id,
+        points,
+        tracked_files: state.file_states.len(),
+        mismatched_settings,
+    })
+}
+
+fn read_json_entry<R: Read, T: serde::de::DeserializeOwned>(
+    entry: Option<std::io::Result<tar::Entry<'_, R>>>,
+    expected: &str,
+) -> Result<T, anyhow::Error> {
+    let entry = entry.ok_or_else(|| anyhow::anyhow!("Archive is missing {expected}"))??;
+    let path = entry.path()?.to_string_lossy().to_string();
+    if path != expected {
+        anyhow::bail!("Expected {expected} in archive, found {path}");
+    }
+    serde_json::from_reader(entry).map_err(|e| anyhow::anyhow!("Invalid {expected}: {e}"))
+}
+
+/// Upsert one NDJSON page of points; returns how many it held
+async fn upsert_page<R: Read>(
+    collection_id: &str,
+    entry: tar::Entry<'_, R>,
+) -> Result<u64, anyhow::Error> {
+    let path = entry.path()?.to_string_lossy().to_string();
+    if !path.starts_with(POINTS_DIR) {
+        warn!("Skipping unexpected archive entry {path}");
+        return Ok(0);
+    }
+
+    let mut points = Vec::new();
+    for line in BufReader::new(entry).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let archived: ArchivedPoint = serde_json::from_str(&line)
+            .map_err(|e| anyhow::anyhow!("Invalid point in {path}: {e}"))?;
+        points.push(archived.into_point()?);
+    }
+    let count = points.len() as u64;
+    if !points.is_empty() {
+        QDRANT_CLIENT
+            .upsert_points(UpsertPointsBuilder::new(collection_id, points))
+            .await?;
+    }
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::expect_used)]
+    use super::*;
+
+    #[test]
+    fn test_archive_entries_round_trip() {
+        let manifest = ArchiveManifest {
+            version: ARCHIVE_VERSION,
+            collection_id: "rua_0123456789abcdef".to_string(),
+            exported_at: chrono::Utc::now(),
+            points: 1,
+            dimension: 3,
+        };
+        let point = ArchivedPoint {
+            id: "1".to_string(),
+            vector: vec![0.1, 0.2, 0.3],
+            payload: serde_json::json!({"file_path": "src/lib.rs", "start_line": 1})
+                .as_object()
+                .cloned()
+                .expect("payload is an object"),
+        };
+
+        let mut archive =
+            tar::Builder::new(zstd::Encoder::new(Vec::new(), ZSTD_LEVEL).expect("encoder"));
+        append_entry(
+            &mut archive,
+            MANIFEST_ENTRY,
+            &serde_json::to_vec(&manifest).expect("serialize manifest"),
+        )
+        .expect("append manifest");
+        append_entry(
+            &mut archive,
+            "points/000000.ndjson",
+            &serde_json::to_vec(&point).expect("serialize point"),
+        )
+        .expect("append points");
+        let bytes = archive
+            .into_inner()
+            .expect("finish tar")
+            .finish()
+            .expect("finish zstd");
+
+        let mut archive = tar::Archive::new(zstd::Decoder::new(bytes.as_slice()).expect("decoder"));
+        let mut entries = archive.entries().expect("entries");
+        let read: ArchiveManifest =
+            read_json_entry(entries.next(), MANIFEST_ENTRY).expect("read manifest");
+        assert_eq!(read.collection_id, manifest.collection_id);
+        assert_eq!(read.dimension, 3);
+
+        let error = read_json_entry::<_, CodebaseState>(entries.next(), STATE_ENTRY)
+            .expect_err("points entry is not the state");
+        assert!(error.to_string().contains("found points/000000.ndjson"));
+
+        let point = point.into_point().expect("convert point");
+        assert_eq!(point_id_to_string(point.id), "1");
+        assert!(point.payload.contains_key("file_path"));
+    }
+}
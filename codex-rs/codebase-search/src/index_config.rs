@@ -0,0 +1,530 @@
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::path::Path;
+use std::path::PathBuf;
+
+use serde::Deserialize;
+use serde::Serialize;
+use tracing::debug;
+
+use crate::chunker::ChunkingOptions;
+use crate::file_state::HashAlgorithm;
+use crate::symbol::TestFilter;
+
+/// Project-level configuration file, relative to the project root
+pub const INDEX_CONFIG_FILE: &str = ".codex/index.toml";
+
+/// User-level configuration file, relative to the user config directory
+pub const USER_CONFIG_FILE: &str = "codex/codebase-search.toml";
+
+/// Commented starting point written by `codebase-search config init`
+pub const CONFIG_TEMPLATE: &str = r#"# codebase-search configuration
+# The user config (~/.config/codex/codebase-search.toml) is read first and a project's
+# .codex/index.toml is layered over it; environment variables and command-line flags
+# override both. Uncomment what you need.
+
+[embedding]
+# provider = "siliconflow"        # or "openai", "cohere"
+# model = "Qwen/Qwen3-Embedding-8B"
+# api_url = "https://api.siliconflow.cn/v1/embeddings"
+# api_key = "..."                 # prefer the user config or CODEX_EMBEDDING_API_KEY
+# batch_size = 10
+# timeout_secs = 30
+
+[qdrant]
+# url = "http://localhost:6334"
+# managed = true                  # start a local Qdrant in .codex/qdrant instead
+# binary = "/usr/local/bin/qdrant" # for managed mode; PATH or a download otherwise
+
+[chat]                            # used by `explain`
+# model = "gpt-4.1-mini"
+# api_url = "https://api.openai.com/v1/chat/completions"
+# api_key = "..."                 # prefer CODEX_CHAT_API_KEY or OPENAI_API_KEY
+# max_context_tokens = 6000
+
+[chunking]
+# max_lines = 200
+# min_lines = 5
+# include_metadata = true
+# max_depth = 5
+# tests = "exclude"               # leave test code out, or "only" to index nothing else
+
+[ignore]
+# patterns = ["generated/", "*.pb.go"]
+
+[manifests]
+# yaml = true                     # index .yaml/.yml documents by kind and metadata.name
+
+# [[languages]]                   # a grammar built with `tree-sitter build`
+# name = "Lua"
+# extensions = ["lua"]
+# library = "/usr/local/lib/tree-sitter/lua.so"
+# symbols = { function_declaration = "function" }
+
+[state]
+# location = "project"            # or "cache"
+# hash = "sha256"                 # or "md5"
+# change_detection = "hash"       # or "git"
+"#;
+
+/// Settings read from the user's `~/.config/codex/codebase-search.toml` overlaid with
+/// the project's `.codex/index.toml`
+/// Every section is optional; missing files mean all defaults.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct IndexConfig {
+    pub embedding: EmbeddingSettings,
+    pub qdrant: QdrantSettings,
+    pub chat: ChatSettings,
+    pub chunking: ChunkingSettings,
+    pub ignore: IgnoreSettings,
+    pub manifests: ManifestSettings,
+    /// Grammars loaded at startup, beyond the built-in languages
+    pub languages: Vec<LanguageSettings>,
+    pub hooks: HooksConfig,
+    pub state: StateConfig,
+}
+
+/// Embedding provider settings; unset fields fall back to the provider's defaults
+/// The `CODEX_EMBEDDING_*` environment variables take precedence over these.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct EmbeddingSettings {
+    /// `"siliconflow"`, `"openai"` or `"cohere"`
+    pub provider: Option<String>,
+    pub api_url: Option<String>,
+    pub api_key: Option<String>,
+    pub model: Option<String>,
+    pub batch_size: Option<usize>,
+    pub timeout_secs: Option<u64>,
+    pub query_requests_per_second: Option<u32>,
+    pub query_cache_size: Option<usize>,
+}
+
+impl EmbeddingSettings {
+    /// Fields set in `overrides` replace the ones here
+    pub fn overridden_by(self, overrides: Self) -> Self {
+        Self {
+            provider: overrides.provider.or(self.provider),
+            api_url: overrides.api_url.or(self.api_url),
+            api_key: overrides.api_key.or(self.api_key),
+            model: overrides.model.or(self.model),
+            batch_size: overrides.batch_size.or(self.batch_size),
+            timeout_secs: overrides.timeout_secs.or(self.timeout_secs),
+            query_requests_per_second: overrides
+                .query_requests_per_second
+                .or(self.query_requests_per_second),
+            query_cache_size: overrides.query_cache_size.or(self.query_cache_size),
+        }
+    }
+}
+
+/// Vector database connection settings
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct QdrantSettings {
+    /// gRPC endpoint, `http://localhost:6334` when unset
+    pub url: Option<String>,
+    /// Start a local Qdrant with its data in `.codex/qdrant` instead of connecting to
+    /// `url` (see `managed_qdrant`)
+    pub managed: Option<bool>,
+    /// Qdrant executable for the managed instance; found on PATH or downloaded when unset
+    pub binary: Option<PathBuf>,
+}
+
+impl QdrantSettings {
+    /// Fields set in `overrides` replace the ones here
+    pub fn overridden_by(self, overrides: Self) -> Self {
+        Self {
+            url: overrides.url.or(self.url),
+            managed: overrides.managed.or(self.managed),
+            binary: overrides.binary.or(self.binary),
+        }
+    }
+}
+
+/// Chat model used to answer questions about the code; OpenAI's chat completions API
+/// when unset. The `CODEX_CHAT_*` environment variables take precedence over these.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ChatSettings {
+    pub model: Option<String>,
+    /// Full chat completions endpoint
+    pub api_url: Option<String>,
+    pub api_key: Option<String>,
+    /// Budget for the retrieved code sent along with a question
+    pub max_context_tokens: Option<usize>,
+}
+
+impl ChatSettings {
+    /// Fields set in `overrides` replace the ones here
+    pub fn overridden_by(self, overrides: Self) -> Self {
+        Self {
+            model: overrides.model.or(self.model),
+            api_url: overrides.api_url.or(self.api_url),
+            api_key: overrides.api_key.or(self.api_key),
+            max_context_tokens: overrides.max_context_tokens.or(self.max_context_tokens),
+        }
+    }
+}
+
+/// Chunking settings; unset fields keep the `ChunkingOptions` defaults
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ChunkingSettings {
+    pub max_lines: Option<usize>,
+    pub min_lines: Option<usize>,
+    pub include_metadata: Option<bool>,
+    pub max_depth: Option<usize>,
+    /// `exclude` leaves test code out of the index and `only` indexes nothing else
+    pub tests: Option<TestFilter>,
+}
+
+impl ChunkingSettings {
+    /// Fields set in `overrides` replace the ones here
+    pub fn overridden_by(self, overrides: Self) -> Self {
+        Self {
+            max_lines: overrides.max_lines.or(self.max_lines),
+            min_lines: overrides.min_lines.or(self.min_lines),
+            include_metadata: overrides.include_metadata.or(self.include_metadata),
+            max_depth: overrides.max_depth.or(self.max_depth),
+            tests: overrides.tests.or(self.tests),
+        }
+    }
+
+    /// Chunking options with these settings applied over the defaults
+    pub fn options(&self) -> ChunkingOptions {
+        let defaults = ChunkingOptions::default();
+        ChunkingOptions {
+            max_lines_per_chunk: self.max_lines.unwrap_or(defaults.max_lines_per_chunk),
+            min_lines_per_chunk: self.min_lines.unwrap_or(defaults.min_lines_per_chunk),
+            include_metadata: self.include_metadata.unwrap_or(defaults.include_metadata),
+            max_recursion_depth: self.max_depth.unwrap_or(defaults.max_recursion_depth),
+            tests: self.tests.unwrap_or(defaults.tests),
+        }
+    }
+}
+
+/// Extra paths to leave out of the index
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct IgnoreSettings {
+    /// Gitignore-style patterns, applied on top of the built-in ones and ignore files
+    pub patterns: Vec<String>,
+}
+
+/// Configuration files indexed alongside the code, off by default
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ManifestSettings {
+    /// Index `.yaml`/`.yml` files, one symbol per document with a `kind` and
+    /// `metadata.name` (Kubernetes manifests and the like)
+    pub yaml: bool,
+}
+
+/// A tree-sitter grammar built as a shared library, and how to extract its symbols
+/// (see `language_registry`)
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct LanguageSettings {
+    pub name: String,
+    /// File extensions, without the leading dot
+    pub extensions: Vec<String>,
+    /// Built with `tree-sitter build`
+    pub library: PathBuf,
+    /// Function returning the grammar; `tree_sitter_<name>` when unset
+    pub entry_point: Option<String>,
+    /// Field holding each symbol's name; `name` when unset
+    pub name_field: Option<String>,
+    /// Symbol kind (`function`, `class`, ...) of each node kind that declares one
+    #[serde(default)]
+    pub symbols: BTreeMap<String, String>,
+}
+
+/// Where the incremental indexing state is kept
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct StateConfig {
+    pub location: StateLocation,
+    /// Explicit state file path, relative to the project root; overrides `location`
+    pub path: Option<PathBuf>,
+    /// Content hash used for change detection (`"sha256"` or `"md5"`)
+    pub hash: HashAlgorithm,
+    /// How `restore_session` finds files changed since the last run
+    pub change_detection: ChangeDetection,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeDetection {
+    /// Stat every file and hash those whose mtime moved
+    #[default]
+    Hash,
+    /// Ask git which files differ from the last indexed commit; falls back to `Hash`
+    /// outside a git repository
+    Git,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StateLocation {
+    /// `.codex/index-state.json` inside the project
+    #[default]
+    Project,
+    /// The user cache directory, keyed by collection ID, leaving the project untouched
+    Cache,
+}
+
+/// Commands to run in response to indexing events
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct HooksConfig {
+    /// Run after every incremental reindex that changed the index
+    pub post_reindex: Vec<HookCommand>,
+    /// Notified when the index is updated or an update fails
+    pub webhooks: Vec<WebhookConfig>,
+}
+
+/// An external command run by a hook
+///
+/// ```toml
+/// [[hooks.post_reindex]]
+/// command = ["touch", ".codex/index.stamp"]
+/// timeout_secs = 5
+/// ```
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct HookCommand {
+    /// Program and arguments; run directly, not through a shell
+    pub command: Vec<String>,
+    /// Kill the command if it runs longer than this
+    #[serde(default = "default_hook_timeout_secs")]
+    pub timeout_secs: u64,
+}
+
+fn default_hook_timeout_secs() -> u64 {
+    30
+}
+
+/// An HTTP endpoint that receives index notifications (see `notifications`)
+///
+/// ```toml
+/// [[hooks.webhooks]]
+/// url = "https://bots.example.com/index-events"
+/// events = ["failed"]
+/// headers = { Authorization = "Bearer ..." }
+/// ```
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct WebhookConfig {
+    /// Receives each notification as a JSON `POST`
+    pub url: String,
+    /// Events to send (`reindexed`, `indexed`, `failed`); every event when empty
+    #[serde(default)]
+    pub events: Vec<String>,
+    /// Extra request headers, e.g. for authentication
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    /// Give up on a delivery that takes longer than this
+    #[serde(default = "default_webhook_timeout_secs")]
+    pub timeout_secs: u64,
+}
+
+fn default_webhook_timeout_secs() -> u64 {
+    10
+}
+
+impl IndexConfig {
+    /// Path of the config file for a project
+    pub fn file_path<P: AsRef<Path>>(root_path: P) -> PathBuf {
+        root_path.as_ref().join(INDEX_CONFIG_FILE)
+    }
+
+    /// Path of the user-level config file (`$XDG_CONFIG_HOME/codex/codebase-search.toml`)
+    pub fn user_file_path() -> Option<PathBuf> {
+        let non_empty = |name: &str| std::env::var_os(name).filter(|value| !value.is_empty());
+        non_empty("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| non_empty("HOME").map(|home| PathBuf::from(home).join(".config")))
+            .or_else(|| non_empty("APPDATA").map(PathBuf::from))
+            .map(|config_dir| config_dir.join(USER_CONFIG_FILE))
+    }
+
+    /// Files `load` reads for a project, lowest precedence first
+    pub fn layer_paths<P: AsRef<Path>>(root_path: P) -> Vec<PathBuf> {
+        Self::user_file_path()
+            .into_iter()
+            .chain(std::iter::once(Self::file_path(root_path)))
+            .collect()
+    }
+
+    /// Load the project's config over the user's, falling back to defaults for files
+    /// that don't exist
+    pub fn load<P: AsRef<Path>>(root_path: P) -> Result<Self, anyhow::Error> {
+        Self::load_layers(&Self::layer_paths(root_path))
+    }
+
+    /// Load the user-level config alone, for settings that aren't tied to a project
+    pub fn load_user() -> Result<Self, anyhow::Error> {
+        Self::load_layers(Self::user_file_path().as_slice())
+    }
+
+    /// Merge config files, later files overriding earlier ones table by table
+    fn load_layers(paths: &[PathBuf]) -> Result<Self, anyhow::Error> {
+        let mut merged = toml::Table::new();
+        for path in paths {
+            if !path.exists() {
+                debug!("No config at {}, skipping", path.display());
+                continue;
+            }
+
+            let content = std::fs::read_to_string(path)
+                .map_err(|e| anyhow::anyhow!("Failed to read {}: {e}", path.display()))?;
+            // Deserialize each file on its own first so errors name the file at fault
+            toml::from_str::<Self>(&content)
+                .map_err(|e| anyhow::anyhow!("Failed to parse {}: {e}", path.display()))?;
+            let layer = toml::from_str::<toml::Table>(&content)
+                .map_err(|e| anyhow::anyhow!("Failed to parse {}: {e}", path.display()))?;
+            merge_tables(&mut merged, layer);
+        }
+
+        toml::Value::Table(merged)
+            .try_into()
+            .map_err(|e| anyhow::anyhow!("Invalid configuration: {e}"))
+    }
+
+    /// Chunking options for this project
+    pub fn chunking_options(&self) -> ChunkingOptions {
+        self.chunking.options()
+    }
+}
+
+/// Overlay `overlay` onto `base`: nested tables merge key by key, other values
+/// (including arrays such as `hooks.post_reindex`) are replaced
+fn merge_tables(base: &mut toml::Table, overlay: toml::Table) {
+    for (key, value) in overlay {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(existing)), toml::Value::Table(value)) => {
+                merge_tables(existing, value);
+            }
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::expect_used)]
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_load_post_reindex_hooks() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        assert!(
+            IndexConfig::load(temp_dir.path())
+                .expect("Failed to load default config")
+                .hooks
+                .post_reindex
+                .is_empty()
+        );
+
+        std::fs::create_dir(temp_dir.path().join(".codex")).expect("Failed to create .codex");
+        std::fs::write(
+            IndexConfig::file_path(temp_dir.path()),
+            r#"
+[[hooks.post_reindex]]
+command = ["touch", "stamp"]
+
+[[hooks.post_reindex]]
+command = ["notify-send", "index updated"]
+timeout_secs = 5
+
+[[hooks.webhooks]]
+url = "http://localhost:9000/index"
+events = ["failed"]
+"#,
+        )
+        .expect("Failed to write config");
+
+        let config = IndexConfig::load(temp_dir.path()).expect("Failed to load config");
+        let hooks = &config.hooks.post_reindex;
+        assert_eq!(hooks.len(), 2);
+        assert_eq!(hooks[0].command, vec!["touch", "stamp"]);
+        assert_eq!(hooks[0].timeout_secs, 30);
+        assert_eq!(hooks[1].timeout_secs, 5);
+        let webhooks = &config.hooks.webhooks;
+        assert_eq!(webhooks.len(), 1);
+        assert_eq!(webhooks[0].events, vec!["failed"]);
+        assert_eq!(webhooks[0].timeout_secs, 10);
+    }
+
+    #[test]
+    fn test_config_template_parses() {
+        let config: IndexConfig = toml::from_str(CONFIG_TEMPLATE).expect("Invalid template");
+        assert_eq!(config.chunking_options(), ChunkingOptions::default());
+        assert!(config.embedding.provider.is_none());
+    }
+
+    #[test]
+    fn test_project_config_overrides_user_config() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let user_path = temp_dir.path().join("user.toml");
+        let project_path = temp_dir.path().join("project.toml");
+        std::fs::write(
+            &user_path,
+            r#"
+[embedding]
+provider = "openai"
+model = "text-embedding-3-small"
+
+[qdrant]
+url = "http://qdrant.internal:6334"
+
+[chunking]
+max_lines = 120
+"#,
+        )
+        .expect("Failed to write user config");
+        std::fs::write(
+            &project_path,
+            r#"
+[embedding]
+model = "text-embedding-3-large"
+
+[chunking]
+min_lines = 3
+
+[ignore]
+patterns = ["generated/"]
+"#,
+        )
+        .expect("Failed to write project config");
+
+        let config = IndexConfig::load_layers(&[
+            user_path,
+            project_path,
+            temp_dir.path().join("missing.toml"),
+        ])
+        .expect("Failed to load config");
+        assert_eq!(config.embedding.provider.as_deref(), Some("openai"));
+        assert_eq!(
+            config.embedding.model.as_deref(),
+            Some("text-embedding-3-large")
+        );
+        assert_eq!(
+            config.qdrant.url.as_deref(),
+            Some("http://qdrant.internal:6334")
+        );
+        assert_eq!(config.ignore.patterns, vec!["generated/"]);
+        assert!(!config.manifests.yaml);
+
+        let options = config.chunking_options();
+        assert_eq!(options.max_lines_per_chunk, 120);
+        assert_eq!(options.min_lines_per_chunk, 3);
+        assert_eq!(
+            options.max_recursion_depth,
+            ChunkingOptions::default().max_recursion_depth
+        );
+    }
+}
@@ -0,0 +1,353 @@
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::path::Path;
+use std::path::PathBuf;
+
+use qdrant_client::qdrant::ScrollPointsBuilder;
+use serde::Serialize;
+
+use crate::chunker::CodeChunk;
+use crate::file_state::CodebaseState;
+use crate::file_state::StateSummary;
+use crate::file_state::state_file_path;
+use crate::file_state::summarize_state;
+use crate::retriever::chunk_from_payload;
+use crate::symbol::SupportedLanguage;
+use crate::vector_db::QDRANT_CLIENT;
+use crate::vector_db::generate_collection_id;
+
+/// Number of points fetched per scroll request while gathering stats
+const STATS_SCROLL_PAGE_SIZE: u32 = 256;
+
+/// Files listed under `largest_files`
+const LARGEST_FILES: usize = 10;
+
+/// Upper bounds (inclusive, in lines) of the chunk size buckets; the last bucket is open
+const SIZE_BUCKETS: &[usize] = &[10, 25, 50, 100, 200];
+
+/// Chunks and symbols indexed for one language
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct LanguageStats {
+    pub language: String,
+    pub files: usize,
+    pub chunks: usize,
+    /// Distinct symbols by file, name and start line
+    pub symbols: usize,
+}
+
+/// Chunks whose line count falls in `min_lines..=max_lines`
+#[derive(Debug, Clone, Serialize)]
+pub struct SizeBucket {
+    pub min_lines: usize,
+    /// None for the last, open-ended bucket
+    pub max_lines: Option<usize>,
+    pub chunks: usize,
+}
+
+/// A file ranked by how much of the index it takes up
+#[derive(Debug, Clone, Serialize)]
+pub struct FileStats {
+    pub file_path: String,
+    pub chunks: usize,
+    /// Lines covered by its chunks, counting overlapping containers once per chunk
+    pub chunk_lines: usize,
+}
+
+/// What `stats` reports about a project's index
+#[derive(Debug, Clone, Serialize)]
+pub struct IndexStats {
+    pub root_path: PathBuf,
+    pub collection_id: String,
+    pub chunks: usize,
+    pub files: usize,
+    pub symbols: usize,
+    /// Bytes of chunk content stored in the payloads
+    pub content_bytes: usize,
+    pub languages: Vec<LanguageStats>,
+    /// Chunk count per symbol kind, most common first
+    pub kinds: Vec<(String, usize)>,
+    pub sizes: Vec<SizeBucket>,
+    pub mean_chunk_lines: f64,
+    pub max_chunk_lines: usize,
+    pub largest_files: Vec<FileStats>,
+    /// Chunks cut from a symbol too large to embed whole
+    pub split_chunks: usize,
+    /// Signature-only chunks standing in for a split container (impl, class, ...)
+    pub container_chunks: usize,
+    pub split_ratio: f64,
+    pub container_ratio: f64,
+    /// None when the project has no state file
+    pub state: Option<StateSummary>,
+    /// Tracked files with no points in the collection
+    pub files_without_points: Vec<String>,
+}
+
+/// Summarize a project's index from its collection payloads and state file
+/// Nothing is re-parsed, so the numbers describe what was indexed, not the working tree.
+pub async fn index_stats<P: AsRef<Path>>(root_path: P) -> Result<IndexStats, anyhow::Error> {
+    let root_path = root_path.as_ref();
+    let collection_id = generate_collection_id(root_path);
+
+    let exists = QDRANT_CLIENT
+        .collection_exists(collection_id.as_str())
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to check collection {collection_id}: {e}"))?;
+    if !exists {
+        anyhow::bail!(
+            "{} is not indexed (no collection {collection_id}). Run 'index-codebase' first",
+            root_path.display()
+        );
+    }
+
+    let mut chunks = Vec::new();
+    let mut offset = None;
+    loop {
+        let mut request = ScrollPointsBuilder::new(collection_id.as_str())
+            .limit(STATS_SCROLL_PAGE_SIZE)
+            .with_payload(true)
+            .with_vectors(false);
+        if let Some(offset) = offset.take() {
+            request = request.offset(offset);
+        }
+
+        let response = QDRANT_CLIENT
+            .scroll(request)
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to read collection {collection_id}: {e}"))?;
+        for point in response.result {
+            chunks.push(chunk_from_payload(&point.payload)?);
+        }
+
+        match response.next_page_offset {
+            Some(next) => offset = Some(next),
+            None => break,
+        }
+    }
+
+    let state_path = state_file_path(root_path)?;
+    let (state, tracked_files) = if state_path.exists() {
+        let tracked_files = CodebaseState::from_file(&state_path)?
+            .file_states
+            .into_keys()
+            .collect();
+        (Some(summarize_state(root_path)?), tracked_files)
+    } else {
+        (None, Vec::new())
+    };
+
+    let mut stats = aggregate(&chunks, &tracked_files);
+    stats.root_path = root_path.to_path_buf();
+    stats.collection_id = collection_id;
+    stats.state = state;
+    Ok(stats)
+}
+
+/// Language name for a root-relative path, or its extension for unsupported ones
+fn language_of(file_path: &Path) -> String {
+    let extension = file_path
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    match SupportedLanguage::from_extension(&extension) {
+        Some(language) => language.name(),
+        None if extension.is_empty() => "unknown".to_string(),
+        None => extension,
+    }
+}
+
+/// Ratio of `part` to `whole`, 0 for an empty index
+fn ratio(part: usize, whole: usize) -> f64 {
+    if whole == 0 {
+        0.0
+    } else {
+        part as f64 / whole as f64
+    }
+}
+
+fn aggregate(chunks: &[CodeChunk], tracked_files: &[String]) -> IndexStats {
+    let mut languages: BTreeMap<String, LanguageStats> = BTreeMap::new();
+    let mut language_files: HashSet<(String, &Path)> = HashSet::new();
+    let mut symbols: HashSet<(&Path, &str, usize)> = HashSet::new();
+    let mut kinds: HashMap<&str, usize> = HashMap::new();
+    let mut files: HashMap<&Path, FileStats> = HashMap::new();
+    let mut sizes: Vec<SizeBucket> = SIZE_BUCKETS
+        .iter()
+        .enumerate()
+        .map(|(i, &max_lines)| SizeBucket {
+            min_lines: if i == 0 { 1 } else { SIZE_BUCKETS[i - 1] + 1 },
+            max_lines: Some(max_lines),
+            chunks: 0,
+        })
+        .collect();
+    sizes.push(SizeBucket {
+        min_lines: SIZE_BUCKETS.last().map_or(1, |max| max + 1),
+        max_lines: None,
+        chunks: 0,
+    });
+
+    let mut total_lines = 0;
+    let mut max_chunk_lines = 0;
+    let mut content_bytes = 0;
+    let mut split_chunks = 0;
+    let mut container_chunks = 0;
+    for chunk in chunks {
+        let file_path = chunk.file_path.as_path();
+        let language = language_of(file_path);
+        let new_symbol = symbols.insert((file_path, &chunk.symbol_name, chunk.start_line));
+        let new_file = language_files.insert((language.clone(), file_path));
+        let entry = languages
+            .entry(language.clone())
+            .or_insert_with(|| LanguageStats {
+                language,
+                ..Default::default()
+            });
+        entry.chunks += 1;
+        entry.files += usize::from(new_file);
+        entry.symbols += usize::from(new_symbol);
+
+        *kinds.entry(&chunk.symbol_kind).or_default() += 1;
+
+        let lines = chunk.end_line.saturating_sub(chunk.start_line) + 1;
+        total_lines += lines;
+        max_chunk_lines = max_chunk_lines.max(lines);
+        content_bytes += chunk.content.len();
+        let bucket = SIZE_BUCKETS
+            .iter()
+            .position(|&max_lines| lines <= max_lines)
+            .unwrap_or(SIZE_BUCKETS.len());
+        sizes[bucket].chunks += 1;
+
+        if chunk.chunk_metadata.is_container {
+            container_chunks += 1;
+        } else if chunk.chunk_metadata.is_split {
+            split_chunks += 1;
+        }
+
+        let file = files.entry(file_path).or_insert_with(|| FileStats {
+            file_path: file_path.to_string_lossy().to_string(),
+            chunks: 0,
+            chunk_lines: 0,
+        });
+        file.chunks += 1;
+        file.chunk_lines += lines;
+    }
+
+    let mut kinds: Vec<(String, usize)> = kinds
+        .into_iter()
+        .map(|(kind, count)| (kind.to_string(), count))
+        .collect();
+    kinds.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+    let mut languages: Vec<LanguageStats> = languages.into_values().collect();
+    languages.sort_by_key(|language| std::cmp::Reverse(language.chunks));
+
+    let indexed_files: HashSet<String> =
+        files.values().map(|file| file.file_path.clone()).collect();
+    let mut files_without_points: Vec<String> = tracked_files
+        .iter()
+        .filter(|file_path| !indexed_files.contains(*file_path))
+        .cloned()
+        .collect();
+    files_without_points.sort();
+
+    let file_count = files.len();
+    let mut largest_files: Vec<FileStats> = files.into_values().collect();
+    largest_files.sort_by(|a, b| {
+        b.chunk_lines
+            .cmp(&a.chunk_lines)
+            .then_with(|| b.chunks.cmp(&a.chunks))
+            .then_with(|| a.file_path.cmp(&b.file_path))
+    });
+    largest_files.truncate(LARGEST_FILES);
+
+    IndexStats {
+        root_path: PathBuf::new(),
+        collection_id: String::new(),
+        chunks: chunks.len(),
+        files: file_count,
+        symbols: symbols.len(),
+        content_bytes,
+        languages,
+        kinds,
+        sizes,
+        mean_chunk_lines: ratio(total_lines, chunks.len()),
+        max_chunk_lines,
+        largest_files,
+        split_chunks,
+        container_chunks,
+        split_ratio: ratio(split_chunks, chunks.len()),
+        container_ratio: ratio(container_chunks, chunks.len()),
+        state: None,
+        files_without_points,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunker::ChunkMetadata;
+
+    fn chunk(
+        file_path: &str,
+        symbol_kind: &str,
+        lines: (usize, usize),
+        is_split: bool,
+    ) -> CodeChunk {
+        CodeChunk {
+            content: "x".repeat(10),
+            file_path: PathBuf::from(file_path),
+            start_line: lines.0,
+            end_line: lines.1,
+            symbol_name: format!("{symbol_kind}_{}", lines.0),
+            symbol_kind: symbol_kind.to_string(),
+            context: None,
+            signature: String::new(),
+            modifiers: Default::default(),
+            metrics: Default::default(),
+            is_test: false,
+            symbol_id: String::new(),
+            chunk_metadata: ChunkMetadata {
+                is_split,
+                original_size_lines: lines.1 - lines.0 + 1,
+                chunk_depth: usize::from(is_split),
+                is_container: false,
+            },
+        }
+    }
+
+    #[test]
+    fn test_aggregate_counts_languages_kinds_and_sizes() {
+        let mut container = chunk("src/lib.rs", "Impl", (1, 300), true);
+        container.chunk_metadata.is_container = true;
+        let chunks = vec![
+            container,
+            chunk("src/lib.rs", "Method", (2, 40), true),
+            chunk("src/lib.rs", "Function", (310, 315), false),
+            chunk("tools/gen.py", "Function", (1, 60), false),
+        ];
+        let tracked = vec![
+            "src/lib.rs".to_string(),
+            "tools/gen.py".to_string(),
+            "src/empty.rs".to_string(),
+        ];
+
+        let stats = aggregate(&chunks, &tracked);
+
+        assert_eq!(stats.chunks, 4);
+        assert_eq!(stats.files, 2);
+        assert_eq!(stats.content_bytes, 40);
+        assert_eq!(stats.languages[0].language, "rust");
+        assert_eq!(stats.languages[0].files, 1);
+        assert_eq!(stats.languages[0].chunks, 3);
+        assert_eq!(stats.languages[1].language, "python");
+        assert_eq!(stats.kinds[0], ("Function".to_string(), 2));
+        let size_counts: Vec<usize> = stats.sizes.iter().map(|bucket| bucket.chunks).collect();
+        assert_eq!(size_counts, vec![1, 0, 1, 1, 0, 1]);
+        assert_eq!(stats.max_chunk_lines, 300);
+        assert_eq!(stats.container_chunks, 1);
+        assert_eq!(stats.split_chunks, 1);
+        assert_eq!(stats.largest_files[0].file_path, "src/lib.rs");
+        assert_eq!(stats.files_without_points, vec!["src/empty.rs".to_string()]);
+    }
+}
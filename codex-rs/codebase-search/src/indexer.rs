@@ -0,0 +1,719 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::LazyLock;
+use std::sync::Mutex;
+use std::sync::PoisonError;
+use std::time::Duration;
+use std::time::SystemTime;
+
+use qdrant_client::Qdrant;
+use tokio::sync::mpsc;
+use tokio::sync::mpsc::UnboundedReceiver;
+use tokio::sync::mpsc::UnboundedSender;
+use tokio_util::sync::CancellationToken;
+use tracing::debug;
+use tracing::info;
+use tracing::warn;
+
+use crate::chunker::ChunkingOptions;
+use crate::embedding::EmbeddingClient;
+use crate::embedding::get_embedding_client;
+use crate::file_state::CodebaseState;
+use crate::file_state::HashAlgorithm;
+use crate::file_state::IndexParameters;
+use crate::file_state::state_file_path;
+use crate::file_watcher::FileChangeEvent;
+use crate::file_watcher::FileWatcher;
+use crate::file_watcher::FileWatcherStatus;
+use crate::file_watcher::PauseHandle;
+use crate::index_config::HookCommand;
+use crate::index_config::IndexConfig;
+use crate::index_config::WebhookConfig;
+use crate::notifications::IndexNotification;
+use crate::notifications::send_webhooks;
+use crate::progress::no_progress;
+use crate::vector_db::QDRANT_CLIENT;
+use crate::vector_db::apply_file_changes;
+use crate::vector_db::classify_file_changes;
+use crate::vector_db::collect_supported_file_states;
+use crate::vector_db::diff_file_states;
+use crate::vector_db::generate_collection_id;
+use crate::walk_utils::ignore_rules_hash;
+use crate::walk_utils::is_supported_file_extension;
+use crate::walk_utils::walk_codebase_files;
+
+/// Summary of what a batch of file changes did to the index
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct IndexUpdate {
+    pub added: usize,
+    pub modified: usize,
+    pub deleted: usize,
+}
+
+impl IndexUpdate {
+    /// Whether the batch touched the index at all
+    pub fn is_empty(&self) -> bool {
+        self.added == 0 && self.modified == 0 && self.deleted == 0
+    }
+}
+
+/// Callback invoked with the project root after each reindex that changed the index
+pub type ReindexCallback = Box<dyn Fn(&Path, &IndexUpdate) + Send + Sync>;
+
+static INDEX_LOCKS: LazyLock<Mutex<HashMap<PathBuf, Arc<tokio::sync::Mutex<()>>>>> =
+    LazyLock::new(Mutex::default);
+
+/// Lock serializing updates to the index of `root_path` within the process
+/// `IndexService` holds it while applying a batch and the servers while reindexing on
+/// request, so a watcher and a request never write the same collection and state
+/// file at once.
+pub fn index_lock(root_path: &Path) -> Arc<tokio::sync::Mutex<()>> {
+    INDEX_LOCKS
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .entry(root_path.to_path_buf())
+        .or_default()
+        .clone()
+}
+
+/// Keeps a project's index in sync with the file system while it is being edited
+/// The service owns the file watcher, the chunking options, the embedding client and
+/// the vector store, and keeps the saved file states in memory so each batch only
+/// hashes, chunks and embeds the files that actually changed. The project must already
+/// be indexed (see `vector_db::restore_session`).
+pub struct IndexService {
+    root_path: PathBuf,
+    collection_id: String,
+    watcher: FileWatcher,
+    chunking_options: ChunkingOptions,
+    embedding_client: Arc<EmbeddingClient>,
+    qdrant: Arc<Qdrant>,
+    /// Where the file states are persisted (see `file_state::state_file_path`)
+    state_path: PathBuf,
+    /// Mirror of the state file, written back after every applied batch
+    state: CodebaseState,
+    /// Modification time of the state file when `state` last matched it
+    state_modified: Option<SystemTime>,
+    /// `[state] hash` from `.codex/index.toml`
+    hash_algorithm: HashAlgorithm,
+    /// Commands from `[[hooks.post_reindex]]` in `.codex/index.toml`
+    post_reindex_hooks: Vec<HookCommand>,
+    post_reindex_callbacks: Vec<ReindexCallback>,
+    /// `[[hooks.webhooks]]` from `.codex/index.toml`, told about updates and failures
+    webhooks: Vec<WebhookConfig>,
+}
+
+impl IndexService {
+    /// Create a service for the project the watcher is watching
+    pub fn new(watcher: FileWatcher) -> Result<Self, anyhow::Error> {
+        let root_path = watcher.root_path().to_path_buf();
+        let state_path = state_file_path(&root_path)?;
+        let state = CodebaseState::from_file(&state_path).map_err(|e| {
+            anyhow::anyhow!(
+                "Failed to load state file for {}: {e}. Run 'index-codebase' first",
+                root_path.display()
+            )
+        })?;
+        let config = IndexConfig::load(&root_path)?;
+
+        Ok(Self {
+            collection_id: generate_collection_id(&root_path),
+            root_path,
+            watcher,
+            chunking_options: config.chunking_options(),
+            embedding_client: get_embedding_client()?,
+            qdrant: QDRANT_CLIENT.clone(),
+            state_modified: modified_time(&state_path),
+            state_path,
+            state,
+            hash_algorithm: config.state.hash,
+            post_reindex_hooks: config.hooks.post_reindex,
+            post_reindex_callbacks: Vec::new(),
+            webhooks: config.hooks.webhooks,
+        })
+    }
+
+    /// Call `callback` after each reindex that changed the index
+    /// Callbacks run after the configured post-reindex hook commands.
+    pub fn on_reindex<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(&Path, &IndexUpdate) + Send + Sync + 'static,
+    {
+        self.post_reindex_callbacks.push(Box::new(callback));
+        self
+    }
+
+    /// Use custom chunking options for reindexed files
+    pub fn with_chunking_options(mut self, chunking_options: ChunkingOptions) -> Self {
+        self.chunking_options = chunking_options;
+        self
+    }
+
+    /// Root path of the project being indexed
+    pub fn root_path(&self) -> &Path {
+        &self.root_path
+    }
+
+    /// Token that stops the service (and its watcher) when cancelled
+    pub fn shutdown_token(&self) -> CancellationToken {
+        self.watcher.shutdown_token()
+    }
+
+    /// Stop the service; `run` returns once any in-flight update is abandoned
+    pub fn stop(&self) {
+        self.watcher.stop();
+    }
+
+    /// Handle for suspending indexing during builds, rebases and other bulk churn
+    /// Changes made while paused are caught up with a single diff on resume.
+    pub fn pause_handle(&self) -> PauseHandle {
+        self.watcher.pause_handle()
+    }
+
+    /// Runtime metrics of the underlying watcher
+    pub fn status(&self) -> FileWatcherStatus {
+        self.watcher.status()
+    }
+
+    /// Wait for the next debounced batch of changes, or None once the service is stopped
+    pub async fn next_batch(&mut self) -> Result<Option<Vec<FileChangeEvent>>, anyhow::Error> {
+        Ok(self.watcher.next_batch().await?)
+    }
+
+    /// Refuse to add vectors from a different model or chunking setup to the collection
+    fn check_index_parameters(&self) -> Result<(), anyhow::Error> {
+        let Some(saved_params) = &self.state.index_params else {
+            return Ok(());
+        };
+        let current = IndexParameters::new(self.embedding_client.config(), &self.chunking_options);
+        let differences = saved_params.differences(&current);
+        if !differences.is_empty() {
+            anyhow::bail!(
+                "Index for {} was built with different settings ({}); run 'index-codebase' \
+                 to rebuild it",
+                self.root_path.display(),
+                differences.join(", ")
+            );
+        }
+        Ok(())
+    }
+
+    /// Apply a batch of file changes to the index and persist the updated file states
+    /// A `Rescan` in the batch (sent after an event storm such as a branch checkout)
+    /// replaces the per-file updates with one diff of the whole project against the
+    /// saved state, so only files whose content really changed are re-embedded.
+    /// Configured webhooks are notified of updates that changed the index and of failures.
+    pub async fn apply_changes(
+        &mut self,
+        changes: &[FileChangeEvent],
+    ) -> Result<IndexUpdate, anyhow::Error> {
+        let lock = index_lock(&self.root_path);
+        let _guard = lock.lock().await;
+        let result = match self.reload_state_if_changed() {
+            Ok(()) => self.update_index(changes).await,
+            Err(e) => Err(e),
+        };
+        if let Some(notification) = IndexNotification::for_update(&self.root_path, &result) {
+            send_webhooks(&self.webhooks, &notification);
+        }
+        result
+    }
+
+    async fn update_index(
+        &mut self,
+        changes: &[FileChangeEvent],
+    ) -> Result<IndexUpdate, anyhow::Error> {
+        self.check_index_parameters()?;
+        let rescan = changes
+            .iter()
+            .any(|change| matches!(change, FileChangeEvent::Rescan(_)));
+        let mut rules_moved = false;
+        let mut changes = if rescan {
+            info!("Rescanning {} for changes", self.root_path.display());
+            // Rescans follow ignore-file edits, so the eligible file set may have moved
+            let rules_hash = ignore_rules_hash(&self.root_path);
+            if self.state.ignore_rules_hash.as_ref() != Some(&rules_hash) {
+                self.state.ignore_rules_hash = Some(rules_hash);
+                rules_moved = true;
+            }
+            let current_file_states = collect_supported_file_states(
+                &self.root_path,
+                self.hash_algorithm,
+                Some(&self.state),
+            )?;
+            diff_file_states(&self.root_path, &self.state, &current_file_states)
+        } else {
+            let (changed_files, deleted_files) = self.collect_paths(changes)?;
+            classify_file_changes(
+                &self.root_path,
+                &self.state,
+                &changed_files,
+                &deleted_files,
+                self.hash_algorithm,
+            )
+        };
+        if changes.is_empty() {
+            debug!("No indexable changes in batch");
+            if !changes.new_states.is_empty() || rules_moved {
+                changes.apply_to(&mut self.state);
+                self.save_state()?;
+            }
+            self.watcher.mark_reindexed();
+            return Ok(IndexUpdate::default());
+        }
+
+        let update = IndexUpdate {
+            added: changes.added.len(),
+            modified: changes.modified.len(),
+            deleted: changes.deleted.len(),
+        };
+        info!(
+            "Updating index - Added: {}, Modified: {}, Deleted: {}",
+            update.added, update.modified, update.deleted
+        );
+
+        let chunk_ids = apply_file_changes(
+            &self.qdrant,
+            &self.embedding_client,
+            &self.chunking_options,
+            &self.root_path,
+            &self.collection_id,
+            &self.state,
+            &changes.added,
+            &changes.modified,
+            &changes.deleted,
+            &no_progress,
+        )
+        .await?;
+
+        changes.record_chunk_ids(chunk_ids);
+        changes.apply_to(&mut self.state);
+        self.save_state()?;
+        self.watcher.mark_reindexed();
+
+        run_post_reindex_hooks(&self.post_reindex_hooks, &self.root_path, &update).await;
+        for callback in &self.post_reindex_callbacks {
+            callback(&self.root_path, &update);
+        }
+
+        Ok(update)
+    }
+
+    /// Apply changes as they arrive until the service is stopped
+    /// A failed batch is logged and skipped; its files are picked up again the next time
+    /// they change or when the session is restored.
+    pub async fn run(&mut self) -> Result<(), anyhow::Error> {
+        let shutdown = self.shutdown_token();
+        while let Some(batch) = self.next_batch().await? {
+            // An interrupted update leaves the state file untouched, so the next run redoes it
+            tokio::select! {
+                result = self.apply_changes(&batch) => {
+                    if let Err(e) = result {
+                        warn!("Incremental update failed: {e}");
+                    }
+                }
+                _ = shutdown.cancelled() => {
+                    info!("Index update interrupted by shutdown");
+                    break;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Pick up a state file rewritten by someone else (e.g. a reindex requested from a
+    /// server), so this service doesn't write stale states back over it
+    fn reload_state_if_changed(&mut self) -> Result<(), anyhow::Error> {
+        let modified = modified_time(&self.state_path);
+        if modified != self.state_modified {
+            debug!(
+                "State file for {} changed, reloading",
+                self.root_path.display()
+            );
+            self.state = CodebaseState::from_file(&self.state_path)?;
+            self.state_modified = modified;
+        }
+        Ok(())
+    }
+
+    fn save_state(&mut self) -> Result<(), anyhow::Error> {
+        self.state.to_file(&self.state_path)?;
+        self.state_modified = modified_time(&self.state_path);
+        Ok(())
+    }
+
+    /// Split watcher events into root-relative changed and deleted paths
+    fn collect_paths(
+        &self,
+        changes: &[FileChangeEvent],
+    ) -> Result<(Vec<String>, Vec<String>), anyhow::Error> {
+        let relative_path = |path: &Path| {
+            path.strip_prefix(&self.root_path)
+                .unwrap_or(path)
+                .to_string_lossy()
+                .to_string()
+        };
+
+        let mut changed_files = Vec::new();
+        let mut deleted_files = Vec::new();
+        for change in changes {
+            match change {
+                FileChangeEvent::FileChanged(path) => {
+                    if is_supported_file_extension(path) {
+                        changed_files.push(relative_path(path));
+                    }
+                }
+                FileChangeEvent::FileDeleted(path) => {
+                    if is_supported_file_extension(path) {
+                        deleted_files.push(relative_path(path));
+                    }
+                }
+                FileChangeEvent::FileRenamed { from, to } => {
+                    if is_supported_file_extension(from) {
+                        deleted_files.push(relative_path(from));
+                    }
+                    if is_supported_file_extension(to) {
+                        changed_files.push(relative_path(to));
+                    }
+                }
+                FileChangeEvent::DirCreated(path) => {
+                    // Files moved in with a directory don't get their own events
+                    walk_codebase_files(path, |file| {
+                        if is_supported_file_extension(file) {
+                            changed_files.push(relative_path(file));
+                        }
+                        Ok(true)
+                    })?;
+                }
+                FileChangeEvent::DirDeleted(path) => {
+                    // Every tracked file under a deleted directory is dropped
+                    deleted_files.push(relative_path(path));
+                }
+                // Handled by `apply_changes` as a full diff
+                FileChangeEvent::Rescan(_) => {}
+            }
+        }
+
+        Ok((changed_files, deleted_files))
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+/// Run the configured post-reindex commands in the project root
+/// Hook failures are logged but never fail the reindex. Each command gets the
+/// update summary in `CODEX_INDEX_ROOT`, `CODEX_INDEX_ADDED`, `CODEX_INDEX_MODIFIED`
+/// and `CODEX_INDEX_DELETED`.
+async fn run_post_reindex_hooks(hooks: &[HookCommand], root_path: &Path, update: &IndexUpdate) {
+    for hook in hooks {
+        let Some((program, args)) = hook.command.split_first() else {
+            warn!("Skipping post-reindex hook with an empty command");
+            continue;
+        };
+
+        let child = tokio::process::Command::new(program)
+            .args(args)
+            .current_dir(root_path)
+            .env("CODEX_INDEX_ROOT", root_path)
+            .env("CODEX_INDEX_ADDED", update.added.to_string())
+            .env("CODEX_INDEX_MODIFIED", update.modified.to_string())
+            .env("CODEX_INDEX_DELETED", update.deleted.to_string())
+            .stdin(std::process::Stdio::null())
+            .kill_on_drop(true)
+            .status();
+
+        match tokio::time::timeout(Duration::from_secs(hook.timeout_secs), child).await {
+            Ok(Ok(status)) if status.success() => {
+                debug!("Post-reindex hook {:?} finished", hook.command);
+            }
+            Ok(Ok(status)) => {
+                warn!("Post-reindex hook {:?} exited with {status}", hook.command)
+            }
+            Ok(Err(e)) => warn!("Failed to run post-reindex hook {:?}: {e}", hook.command),
+            Err(_) => warn!(
+                "Post-reindex hook {:?} timed out after {}s",
+                hook.command, hook.timeout_secs
+            ),
+        }
+    }
+}
+
+/// The outcome of one batch of changes in a multi-root workspace
+#[derive(Debug)]
+pub struct WorkspaceUpdate {
+    /// The root the changes came from
+    pub root_path: PathBuf,
+    /// Number of changes in the batch, as reported by the watcher
+    pub changes: usize,
+    pub result: Result<IndexUpdate, anyhow::Error>,
+}
+
+/// Keeps several project roots in sync at once (e.g. a multi-repo checkout)
+/// Each root keeps its own watcher, collection and state file; their batches are
+/// processed concurrently and reported through one stream tagged with the root.
+pub struct WorkspaceIndexService {
+    services: Vec<IndexService>,
+}
+
+impl WorkspaceIndexService {
+    /// Combine per-root services; roots must not be nested inside each other
+    pub fn new(services: Vec<IndexService>) -> Result<Self, anyhow::Error> {
+        for (i, service) in services.iter().enumerate() {
+            for other in services.iter().skip(i + 1) {
+                if service.root_path().starts_with(other.root_path())
+                    || other.root_path().starts_with(service.root_path())
+                {
+                    return Err(anyhow::anyhow!(
+                        "Workspace roots overlap: {} and {}",
+                        service.root_path().display(),
+                        other.root_path().display()
+                    ));
+                }
+            }
+        }
+        Ok(Self { services })
+    }
+
+    /// Root paths in the workspace
+    pub fn root_paths(&self) -> Vec<PathBuf> {
+        self.services
+            .iter()
+            .map(|service| service.root_path().to_path_buf())
+            .collect()
+    }
+
+    /// Start processing every root in its own task and return the merged update stream
+    /// The stream ends once every root's watcher has stopped (see `IndexService::stop`).
+    pub fn spawn(self) -> UnboundedReceiver<WorkspaceUpdate> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        for service in self.services {
+            tokio::spawn(Self::run_root(service, tx.clone()));
+        }
+        rx
+    }
+
+    async fn run_root(mut service: IndexService, tx: UnboundedSender<WorkspaceUpdate>) {
+        let root_path = service.root_path().to_path_buf();
+        let shutdown = service.shutdown_token();
+        loop {
+            let batch = match service.next_batch().await {
+                Ok(Some(batch)) => batch,
+                Ok(None) => break,
+                Err(e) => {
+                    let _ = tx.send(WorkspaceUpdate {
+                        root_path: root_path.clone(),
+                        changes: 0,
+                        result: Err(e),
+                    });
+                    break;
+                }
+            };
+
+            // An interrupted update leaves the state file untouched, so the next run redoes it
+            let result = tokio::select! {
+                result = service.apply_changes(&batch) => result,
+                _ = shutdown.cancelled() => break,
+            };
+            let update = WorkspaceUpdate {
+                root_path: root_path.clone(),
+                changes: batch.len(),
+                result,
+            };
+            if tx.send(update).is_err() {
+                // Nobody is listening any more
+                service.stop();
+                break;
+            }
+        }
+        info!("Stopped indexing {}", root_path.display());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::expect_used)]
+    use super::*;
+    use crate::embedding::EmbeddingConfig;
+    use crate::file_watcher::FileWatcherConfig;
+    use tempfile::TempDir;
+
+    /// A project whose state file already records `files`, as after `index-codebase`
+    fn indexed_project(files: &[(&str, &str)]) -> TempDir {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        for (path, content) in files {
+            let path = temp_dir.path().join(path);
+            std::fs::create_dir_all(path.parent().expect("Files have a parent"))
+                .expect("Failed to create dir");
+            std::fs::write(path, content).expect("Failed to write file");
+        }
+        let file_states =
+            collect_supported_file_states(temp_dir.path(), HashAlgorithm::Sha256, None)
+                .expect("Failed to collect states");
+        CodebaseState::new(file_states)
+            .to_file(&state_file_path(temp_dir.path()).expect("Failed to locate state"))
+            .expect("Failed to write state");
+        temp_dir
+    }
+
+    fn service(root_path: &Path) -> IndexService {
+        IndexService::new(FileWatcher::new(FileWatcherConfig {
+            root_path: root_path.to_path_buf(),
+            debounce_delay: 50,
+            ..FileWatcherConfig::default()
+        }))
+        .expect("Failed to create service")
+    }
+
+    #[test]
+    fn a_service_needs_an_indexed_project() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let watcher = FileWatcher::new(FileWatcherConfig {
+            root_path: temp_dir.path().to_path_buf(),
+            ..FileWatcherConfig::default()
+        });
+
+        let error = IndexService::new(watcher)
+            .err()
+            .expect("A project without a state file can't be updated");
+        assert!(
+            error.to_string().contains("Run 'index-codebase' first"),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn watcher_events_become_root_relative_paths() {
+        let project = indexed_project(&[("moved/b.go", "package b\n")]);
+        let root = project.path();
+        let service = service(root);
+
+        let (changed, deleted) = service
+            .collect_paths(&[
+                FileChangeEvent::FileChanged(root.join("a.rs")),
+                FileChangeEvent::FileChanged(root.join("notes.unknown")),
+                FileChangeEvent::FileRenamed {
+                    from: root.join("old.rs"),
+                    to: root.join("new.py"),
+                },
+                FileChangeEvent::DirCreated(root.join("moved")),
+                FileChangeEvent::DirDeleted(root.join("gone")),
+            ])
+            .expect("Failed to collect paths");
+
+        let moved = Path::new("moved")
+            .join("b.go")
+            .to_string_lossy()
+            .to_string();
+        assert_eq!(changed, ["a.rs", "new.py", moved.as_str()]);
+        assert_eq!(deleted, ["old.rs", "gone"]);
+    }
+
+    #[tokio::test]
+    async fn unchanged_files_leave_the_index_untouched() {
+        let project = indexed_project(&[("a.rs", "fn a() {}\n")]);
+        let root = project.path();
+        let mut service = service(root);
+
+        // Rewriting the same content and deleting an untracked file need no vectors, so
+        // the batch completes without a Qdrant server
+        std::fs::write(root.join("a.rs"), "fn a() {}\n").expect("Failed to rewrite file");
+        let update = service
+            .apply_changes(&[
+                FileChangeEvent::FileChanged(root.join("a.rs")),
+                FileChangeEvent::FileDeleted(root.join("untracked.rs")),
+            ])
+            .await
+            .expect("Failed to apply changes");
+        assert!(update.is_empty());
+    }
+
+    #[test]
+    fn workspace_roots_must_not_overlap() {
+        let outer = indexed_project(&[("inner/a.rs", "fn a() {}\n")]);
+        let inner = outer.path().join("inner");
+        CodebaseState::new(HashMap::new())
+            .to_file(&state_file_path(&inner).expect("Failed to locate state"))
+            .expect("Failed to write state");
+
+        let error = WorkspaceIndexService::new(vec![service(outer.path()), service(&inner)])
+            .err()
+            .expect("Nested roots would index the same files twice");
+        assert!(
+            error.to_string().contains("Workspace roots overlap"),
+            "{error}"
+        );
+
+        let other = indexed_project(&[]);
+        let workspace =
+            WorkspaceIndexService::new(vec![service(outer.path()), service(other.path())])
+                .expect("Disjoint roots should combine");
+        assert_eq!(
+            workspace.root_paths(),
+            [outer.path().to_path_buf(), other.path().to_path_buf()]
+        );
+    }
+
+    #[tokio::test]
+    async fn the_workspace_reports_each_root_until_every_root_stops() {
+        let first = indexed_project(&[("a.rs", "fn a() {}\n")]);
+        let second = indexed_project(&[("b.rs", "fn b() {}\n")]);
+        let services = vec![service(first.path()), service(second.path())];
+        let shutdown: Vec<_> = services.iter().map(IndexService::shutdown_token).collect();
+        let mut updates = WorkspaceIndexService::new(services)
+            .expect("Failed to create workspace")
+            .spawn();
+
+        // Give the watchers a moment to start
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        std::fs::write(second.path().join("b.rs"), "fn b() {}\n").expect("Failed to rewrite file");
+        let update = tokio::time::timeout(Duration::from_secs(5), updates.recv())
+            .await
+            .expect("Timed out waiting for an update")
+            .expect("The stream ended early");
+        assert_eq!(update.root_path, second.path());
+        assert!(update.result.expect("Failed to apply changes").is_empty());
+
+        for token in shutdown {
+            token.cancel();
+        }
+        let end = tokio::time::timeout(Duration::from_secs(5), updates.recv())
+            .await
+            .expect("Timed out waiting for the stream to end");
+        assert!(end.is_none());
+    }
+
+    #[tokio::test]
+    async fn batches_are_refused_when_the_index_settings_changed() {
+        let project = indexed_project(&[("a.rs", "fn a() {}\n")]);
+        let root = project.path();
+        let state_path = state_file_path(root).expect("Failed to locate state");
+        let mut state = CodebaseState::from_file(&state_path).expect("Failed to read state");
+        let embedding_config = get_embedding_client()
+            .expect("Failed to create embedding client")
+            .config()
+            .clone();
+        state.index_params = Some(IndexParameters::new(
+            &EmbeddingConfig {
+                model: "another-model".to_string(),
+                ..embedding_config
+            },
+            &ChunkingOptions::default(),
+        ));
+        state.to_file(&state_path).expect("Failed to write state");
+
+        let error = service(root)
+            .apply_changes(&[FileChangeEvent::FileChanged(root.join("a.rs"))])
+            .await
+            .expect_err("Vectors from another model can't join the collection");
+        assert!(
+            error.to_string().contains("model another-model ->"),
+            "{error}"
+        );
+    }
+}
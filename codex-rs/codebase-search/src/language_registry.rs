@@ -0,0 +1,463 @@
+//! Languages the parser knows, keyed by file extension
+//! The grammars compiled into the crate are always registered. More can be added
+//! without rebuilding it: build the grammar as a shared library with the tree-sitter
+//! CLI (`tree-sitter build -o lua.so`) and describe it under `[[languages]]` in the
+//! config:
+//!
+//! ```toml
+//! [[languages]]
+//! name = "Lua"
+//! extensions = ["lua"]
+//! library = "/usr/local/lib/tree-sitter/lua.so"
+//! # entry_point = "tree_sitter_lua"            # tree_sitter_<name> by default
+//! # name_field = "name"                        # field holding each symbol's name
+//!
+//! [languages.symbols]                          # node kind = symbol kind
+//! function_declaration = "function"
+//! ```
+//!
+//! A symbol nested in another gets the outer symbol's name as context. Built-in
+//! languages stay `SupportedLanguage` variants with a compiled-in grammar and symbol
+//! query; only languages configured this way are `SupportedLanguage::External`. The
+//! registry is process-wide: `configure_languages` installs it before the first file is parsed,
+//! and the built-in languages alone are used otherwise.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::OnceLock;
+
+use crate::symbol::SupportedLanguage;
+use crate::symbol::SymbolKind;
+
+/// A grammar loaded at runtime and the rules for extracting its symbols
+#[derive(Debug)]
+pub struct ExternalLanguage {
+    pub name: String,
+    /// Lowercase, without the leading dot
+    pub extensions: Vec<String>,
+    pub grammar: tree_sitter::Language,
+    /// Field holding the name of a symbol's node; the first `identifier` child is used
+    /// when the node has no such field
+    pub name_field: String,
+    /// Symbol kind declared by each node kind
+    pub symbols: HashMap<String, SymbolKind>,
+}
+
+/// A registered language and how `codebase-search languages` describes it
+#[derive(Debug, Clone)]
+pub struct LanguageEntry {
+    pub language: SupportedLanguage,
+    pub display_name: String,
+    /// Emoji shown before the name, padded to two columns
+    pub icon: &'static str,
+    /// What gets extracted, e.g. `Functions, Methods, Classes`
+    pub summary: String,
+}
+
+/// Every language that can be parsed, in display order
+#[derive(Debug, Clone)]
+pub struct LanguageRegistry {
+    entries: Vec<LanguageEntry>,
+}
+
+/// Installed by `configure_languages`, or the built-in languages on first use
+static REGISTRY: OnceLock<LanguageRegistry> = OnceLock::new();
+
+/// Use `registry` for every parser and file walk in this process
+/// Must be called before the registry is first used.
+pub fn configure_languages(registry: LanguageRegistry) -> Result<(), anyhow::Error> {
+    REGISTRY
+        .set(registry)
+        .map_err(|_| anyhow::anyhow!("Languages were already configured"))
+}
+
+impl LanguageRegistry {
+    /// The configured registry, or the built-in languages when none was configured
+    pub fn global() -> &'static LanguageRegistry {
+        REGISTRY.get_or_init(LanguageRegistry::builtin)
+    }
+
+    /// The languages compiled into the crate
+    pub fn builtin() -> Self {
+        let entry = |language, display_name: &str, icon, summary: &str| LanguageEntry {
+            language,
+            display_name: display_name.to_string(),
+            icon,
+            summary: summary.to_string(),
+        };
+        let entries = vec![
+            entry(
+                SupportedLanguage::Rust,
+                "Rust",
+                "🦀",
+                "Functions, Methods, Structs, Enums, Traits, Impls, Modules, Constants, Macros, Imports",
+            ),
+            entry(
+                SupportedLanguage::Python,
+                "Python",
+                "🐍",
+                "Functions, Methods, Classes, Constants, Variables, Type aliases, Dataclass fields, Imports",
+            ),
+            entry(
+                SupportedLanguage::Go,
+                "Go",
+                "🐹",
+                "Functions, Methods, Types (structs/interfaces), Interface methods, Constants, Variables, Imports",
+            ),
+            entry(
+                SupportedLanguage::TypeScript,
+                "TypeScript",
+                "🟦",
+                "Functions, Methods, Classes, Interfaces, Enums, Type aliases, Imports",
+            ),
+            entry(
+                SupportedLanguage::JavaScript,
+                "JavaScript",
+                "🟨",
+                "Functions (including arrow-function consts and exports), Methods, Classes, Imports",
+            ),
+            entry(
+                SupportedLanguage::Java,
+                "Java",
+                "☕",
+                "Classes, Interfaces, Enums, Records, Methods, Fields, Constants, Imports",
+            ),
+            entry(
+                SupportedLanguage::C,
+                "C",
+                "🔩",
+                "Functions, Structs, Unions, Enums, Typedefs, Macros, Imports",
+            ),
+            entry(
+                SupportedLanguage::Cpp,
+                "C++",
+                "➕",
+                "Namespaces, Classes, Structs, Methods, Templates, Functions, Enums, Type aliases, Imports",
+            ),
+            entry(
+                SupportedLanguage::CSharp,
+                "C#",
+                "#️⃣ ",
+                "Namespaces, Classes, Records, Structs, Interfaces, Methods, Properties, Enums, Imports",
+            ),
+            entry(
+                SupportedLanguage::Scala,
+                "Scala",
+                "🔺",
+                "Objects, Classes, Traits, Enums, Defs, Vals, Vars, Type aliases, Imports",
+            ),
+            entry(
+                SupportedLanguage::Elixir,
+                "Elixir",
+                "💧",
+                "Modules, Protocols, Functions, Macros, Structs",
+            ),
+            entry(
+                SupportedLanguage::Haskell,
+                "Haskell",
+                "λ ",
+                "Functions, Data types, Newtypes, Type synonyms, Type classes, Instances, Imports",
+            ),
+            entry(
+                SupportedLanguage::Dart,
+                "Dart",
+                "🎯",
+                "Classes, Mixins, Extensions, Enums, Methods, Functions, Imports",
+            ),
+            entry(
+                SupportedLanguage::Sql,
+                "SQL",
+                "🗄️ ",
+                "Tables, Views, Materialized views, Functions, Procedures",
+            ),
+            entry(
+                SupportedLanguage::Protobuf,
+                "Protocol Buffers",
+                "📜",
+                "Messages, Enums, Services, RPCs, Imports",
+            ),
+            entry(
+                SupportedLanguage::GraphQl,
+                "GraphQL",
+                "🕸️ ",
+                "Types, Interfaces, Enums, Inputs, Queries, Mutations, Subscriptions, Operations",
+            ),
+            entry(
+                SupportedLanguage::Terraform,
+                "Terraform",
+                "🌍",
+                "Resources, Data sources, Modules, Variables, Outputs",
+            ),
+            entry(
+                SupportedLanguage::Yaml,
+                "YAML",
+                "☸️ ",
+                "Documents with a kind and metadata.name, e.g. Kubernetes manifests \
+                 (only indexed with [manifests] yaml = true)",
+            ),
+        ];
+        Self { entries }
+    }
+
+    /// Add a language loaded at runtime
+    /// Its extensions must not already belong to another language.
+    pub fn register(&mut self, language: ExternalLanguage) -> Result<(), anyhow::Error> {
+        if let Some((extension, entry)) = language
+            .extensions
+            .iter()
+            .find_map(|extension| self.by_extension(extension).map(|entry| (extension, entry)))
+        {
+            anyhow::bail!(
+                "Language {}: .{extension} is already handled by {}",
+                language.name,
+                entry.display_name
+            );
+        }
+        let mut kinds: Vec<String> = language
+            .symbols
+            .values()
+            .map(|kind| format!("{kind:?}"))
+            .collect();
+        kinds.sort();
+        kinds.dedup();
+        self.entries.push(LanguageEntry {
+            display_name: language.name.clone(),
+            icon: "🔌",
+            summary: kinds.join(", "),
+            language: SupportedLanguage::External(Arc::new(language)),
+        });
+        Ok(())
+    }
+
+    /// Registered languages, built-in ones first
+    pub fn languages(&self) -> &[LanguageEntry] {
+        &self.entries
+    }
+
+    /// The language of files with extension `extension` (without the dot)
+    pub fn by_extension(&self, extension: &str) -> Option<&LanguageEntry> {
+        let extension = extension.to_lowercase();
+        self.entries
+            .iter()
+            .find(|entry| entry.language.extensions().contains(&extension.as_str()))
+    }
+
+    /// The language named `name`, ignoring case (`rust`, `c++`, `protocol buffers`, ...)
+    pub fn by_name(&self, name: &str) -> Option<&LanguageEntry> {
+        self.entries
+            .iter()
+            .find(|entry| entry.display_name.eq_ignore_ascii_case(name))
+    }
+
+    /// Whether files like `path` can be parsed
+    pub fn supports(&self, path: &Path) -> bool {
+        path.extension()
+            .and_then(|extension| extension.to_str())
+            .is_some_and(|extension| self.by_extension(extension).is_some())
+    }
+
+    /// Lowercase names of the registered languages, for error messages
+    pub fn names(&self) -> Vec<String> {
+        self.entries
+            .iter()
+            .map(|entry| entry.display_name.to_lowercase())
+            .collect()
+    }
+}
+
+#[cfg(feature = "native")]
+impl LanguageRegistry {
+    /// The built-in languages plus the `[[languages]]` of a config
+    pub fn with_configured(
+        languages: &[crate::index_config::LanguageSettings],
+    ) -> Result<Self, anyhow::Error> {
+        let mut registry = Self::builtin();
+        for settings in languages {
+            registry.register(ExternalLanguage::load(settings)?)?;
+        }
+        Ok(registry)
+    }
+}
+
+#[cfg(feature = "native")]
+impl ExternalLanguage {
+    /// Load the grammar `settings` describe from its shared library
+    pub fn load(settings: &crate::index_config::LanguageSettings) -> Result<Self, anyhow::Error> {
+        let mut symbols = HashMap::new();
+        for (node_kind, kind_name) in &settings.symbols {
+            let kind = SymbolKind::from_name(kind_name).ok_or_else(|| {
+                anyhow::anyhow!(
+                    "Language {}: unknown symbol kind {kind_name} for {node_kind}",
+                    settings.name
+                )
+            })?;
+            symbols.insert(node_kind.clone(), kind);
+        }
+        if settings.extensions.is_empty() {
+            anyhow::bail!(
+                "Language {}: at least one extension is required",
+                settings.name
+            );
+        }
+
+        let entry_point = match &settings.entry_point {
+            Some(entry_point) => entry_point.clone(),
+            None => format!(
+                "tree_sitter_{}",
+                settings.name.to_lowercase().replace(['-', ' '], "_")
+            ),
+        };
+        let grammar = load_grammar(&settings.library, &entry_point)
+            .map_err(|e| anyhow::anyhow!("Language {}: {e}", settings.name))?;
+        // Catches grammars generated for an incompatible tree-sitter ABI
+        tree_sitter::Parser::new()
+            .set_language(&grammar)
+            .map_err(|e| anyhow::anyhow!("Language {}: {e}", settings.name))?;
+
+        Ok(Self {
+            name: settings.name.clone(),
+            extensions: settings
+                .extensions
+                .iter()
+                .map(|extension| extension.trim_start_matches('.').to_lowercase())
+                .collect(),
+            grammar,
+            name_field: settings
+                .name_field
+                .clone()
+                .unwrap_or_else(|| "name".to_string()),
+            symbols,
+        })
+    }
+}
+
+/// Call `entry_point` in the shared library at `library` for its grammar
+/// The library stays loaded for the life of the process, since the grammar lives in it.
+#[cfg(all(feature = "native", unix))]
+fn load_grammar(library: &Path, entry_point: &str) -> Result<tree_sitter::Language, anyhow::Error> {
+    use std::ffi::CStr;
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let dl_error = || {
+        // SAFETY: dlerror returns null or a NUL-terminated message owned by libc
+        let message = unsafe { libc::dlerror() };
+        if message.is_null() {
+            "unknown error".to_string()
+        } else {
+            // SAFETY: checked for null above
+            unsafe { CStr::from_ptr(message) }
+                .to_string_lossy()
+                .into_owned()
+        }
+    };
+
+    let path = CString::new(library.as_os_str().as_bytes())?;
+    // SAFETY: `path` is NUL-terminated; loading runs the library's initializers, which
+    // grammars built by the tree-sitter CLI don't have
+    let handle = unsafe { libc::dlopen(path.as_ptr(), libc::RTLD_NOW | libc::RTLD_LOCAL) };
+    if handle.is_null() {
+        anyhow::bail!("Failed to load {}: {}", library.display(), dl_error());
+    }
+    let symbol = CString::new(entry_point)?;
+    // SAFETY: `handle` is a library opened above and `symbol` is NUL-terminated
+    let function = unsafe { libc::dlsym(handle, symbol.as_ptr()) };
+    if function.is_null() {
+        anyhow::bail!("{} has no {entry_point}: {}", library.display(), dl_error());
+    }
+    // SAFETY: a grammar's entry point takes no arguments and returns its TSLanguage
+    let language_fn = unsafe {
+        let function: unsafe extern "C" fn() -> *const () = std::mem::transmute(function);
+        tree_sitter_language::LanguageFn::from_raw(function)
+    };
+    Ok(tree_sitter::Language::new(language_fn))
+}
+
+#[cfg(all(feature = "native", not(unix)))]
+fn load_grammar(
+    library: &Path,
+    _entry_point: &str,
+) -> Result<tree_sitter::Language, anyhow::Error> {
+    anyhow::bail!(
+        "Loading {}: external grammars are only supported on Unix",
+        library.display()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::expect_used)]
+    use super::*;
+
+    fn external(name: &str, extensions: &[&str]) -> ExternalLanguage {
+        ExternalLanguage {
+            name: name.to_string(),
+            extensions: extensions
+                .iter()
+                .map(|extension| extension.to_string())
+                .collect(),
+            grammar: SupportedLanguage::Rust.tree_sitter_language(),
+            name_field: "name".to_string(),
+            symbols: HashMap::from([("function_item".to_string(), SymbolKind::Function)]),
+        }
+    }
+
+    #[test]
+    fn registered_languages_are_found_by_extension_and_name() {
+        let mut registry = LanguageRegistry::builtin();
+        assert_eq!(
+            registry
+                .by_extension("TSX")
+                .map(|entry| entry.display_name.as_str()),
+            Some("TypeScript")
+        );
+        assert!(registry.by_name("c++").is_some());
+        assert!(!registry.supports(Path::new("init.lua")));
+
+        registry
+            .register(external("Lua", &["lua"]))
+            .expect("lua is free");
+        assert!(registry.supports(Path::new("init.lua")));
+        let entry = registry.by_name("lua").expect("Lua is registered");
+        assert_eq!(entry.summary, "Function");
+        assert!(matches!(entry.language, SupportedLanguage::External(_)));
+
+        let error = registry
+            .register(external("Rusty", &["rs"]))
+            .expect_err(".rs belongs to Rust");
+        assert!(error.to_string().contains("Rust"), "{error}");
+    }
+
+    #[cfg(all(feature = "native", unix))]
+    #[test]
+    fn missing_grammar_libraries_are_reported() {
+        let error = load_grammar(Path::new("/nonexistent/lua.so"), "tree_sitter_lua")
+            .expect_err("there is no library to load");
+        assert!(
+            error
+                .to_string()
+                .starts_with("Failed to load /nonexistent/lua.so: "),
+            "{error}"
+        );
+    }
+
+    #[cfg(all(feature = "native", any(target_os = "linux", target_os = "macos")))]
+    #[test]
+    fn libraries_without_the_entry_point_are_reported() {
+        // A library every process has loaded, which holds no grammar
+        let library = if cfg!(target_os = "linux") {
+            "libc.so.6"
+        } else {
+            "/usr/lib/libSystem.B.dylib"
+        };
+        let error =
+            load_grammar(Path::new(library), "tree_sitter_lua").expect_err("libc has no grammar");
+        assert!(
+            error
+                .to_string()
+                .starts_with(&format!("{library} has no tree_sitter_lua: ")),
+            "{error}"
+        );
+    }
+}
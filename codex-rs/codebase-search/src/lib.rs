@@ -1,8 +1,72 @@
+//! Semantic code search: tree-sitter symbol extraction, hierarchical chunking,
+//! embedding and a Qdrant-backed index
+//!
+//! Only `symbol`, `language_registry`, `chunker` and `wasm` are built without the
+//! default `native` feature, so the parser and chunker can be compiled to wasm32.
+
+#[cfg(feature = "native")]
+pub mod bench;
 pub mod chunker;
+#[cfg(feature = "native")]
+mod codebase_index;
+#[cfg(feature = "native")]
+pub mod context_builder;
+#[cfg(feature = "native")]
+pub mod daemon;
+#[cfg(feature = "native")]
+pub mod doctor;
+#[cfg(feature = "native")]
 pub mod embedding;
+#[cfg(feature = "native")]
+pub mod explain;
+#[cfg(feature = "native")]
+pub mod feedback;
+#[cfg(feature = "native")]
 pub mod file_state;
+#[cfg(feature = "native")]
 pub mod file_watcher;
+#[cfg(feature = "native")]
+pub mod git_changes;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+#[cfg(feature = "native")]
+pub mod index_archive;
+#[cfg(feature = "native")]
+pub mod index_config;
+#[cfg(feature = "native")]
+pub mod index_stats;
+#[cfg(feature = "native")]
+pub mod indexer;
+pub mod language_registry;
+#[cfg(feature = "native")]
+pub mod managed_qdrant;
+#[cfg(feature = "native")]
+pub mod mcp_server;
+#[cfg(feature = "native")]
+pub mod notifications;
+#[cfg(feature = "native")]
+pub mod progress;
+#[cfg(feature = "native")]
 pub mod retriever;
+#[cfg(feature = "native")]
+pub mod server;
 pub mod symbol;
+mod symbol_query;
+#[cfg(feature = "native")]
+pub mod team;
+#[cfg(feature = "native")]
+pub mod telemetry;
+#[cfg(feature = "native")]
+pub mod tui;
+#[cfg(feature = "native")]
 pub mod vector_db;
+#[cfg(feature = "native")]
 pub mod walk_utils;
+pub mod wasm;
+
+#[cfg(feature = "native")]
+pub use codebase_index::CodebaseIndex;
+#[cfg(feature = "native")]
+pub use codebase_index::Config;
+#[cfg(feature = "native")]
+pub use codebase_index::SearchOptions;
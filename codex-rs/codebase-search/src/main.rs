@@ -1,15 +1,63 @@
 use anyhow::Result;
+use clap::CommandFactory;
 use clap::Parser;
 use clap::Subcommand;
-use codebase_search::chunker::ChunkingOptions;
+use clap::ValueEnum;
+use codebase_search::bench::BenchOptions;
 use codebase_search::chunker::chunk_codebase;
+use codebase_search::embedding::EmbeddingConfig;
+use codebase_search::embedding::configure_embedding;
+use codebase_search::embedding::env_embedding_settings;
+use codebase_search::explain::ExplainOptions;
+use codebase_search::explain::env_chat_settings;
+use codebase_search::index_config::ChatSettings;
+use codebase_search::index_config::ChunkingSettings;
+use codebase_search::index_config::EmbeddingSettings;
+use codebase_search::index_config::IndexConfig;
+use codebase_search::index_config::QdrantSettings;
+use codebase_search::language_registry::LanguageRegistry;
+use codebase_search::language_registry::configure_languages;
+use codebase_search::managed_qdrant::ManagedQdrant;
+use codebase_search::managed_qdrant::managed_data_dir;
+use codebase_search::progress::IndexPhase;
+use codebase_search::progress::IndexProgress;
+use codebase_search::retriever::MetadataQuery;
+use codebase_search::retriever::SearchFilter;
 use codebase_search::symbol::SymbolKind;
+use codebase_search::symbol::SymbolModifiers;
 use codebase_search::symbol::SymbolParser;
-use codebase_search::symbol::parse_codebase;
+use codebase_search::symbol::TestFilter;
+use codebase_search::symbol::parse_codebase_with_stats;
+#[cfg(feature = "otel")]
+use codebase_search::telemetry::Telemetry;
+use codebase_search::vector_db::DEFAULT_QDRANT_URL;
+use codebase_search::vector_db::configure_qdrant;
+use codebase_search::vector_db::env_qdrant_settings;
+use codebase_search::vector_db::init_session_with_progress;
 use codebase_search::vector_db::restore_session;
+use codebase_search::vector_db::restore_session_with_progress;
+use indicatif::MultiProgress;
+use indicatif::ProgressBar;
+use indicatif::ProgressDrawTarget;
+use indicatif::ProgressStyle;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::Path;
 use std::path::PathBuf;
+use std::process::ExitCode;
+use std::sync::LazyLock;
+use std::sync::Mutex;
+use std::sync::PoisonError;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
 use tracing::info;
 use tracing::warn;
+use tracing_subscriber::Layer;
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::fmt::writer::BoxMakeWriter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
 
 /// A CLI tool for parsing and analyzing codebase symbols
 #[derive(Parser)]
@@ -23,6 +71,242 @@ struct Cli {
     /// Enable verbose logging
     #[arg(short, long)]
     verbose: bool,
+
+    /// Suppress status messages and progress bars, and only log warnings and errors
+    #[arg(long, global = true)]
+    quiet: bool,
+
+    /// Log line format on stderr
+    #[arg(long, global = true, value_enum, default_value_t = LogFormat::Pretty)]
+    log_format: LogFormat,
+
+    /// Output format for commands that print results
+    #[arg(short = 'f', long, global = true, value_enum, default_value_t = OutputFormat::Text)]
+    format: OutputFormat,
+
+    /// Qdrant URL, overriding config files and CODEX_QDRANT_URL
+    #[arg(long, global = true, value_name = "URL")]
+    qdrant_url: Option<String>,
+
+    /// Start a local Qdrant with its data in the project's .codex/qdrant instead of
+    /// connecting to one, like `[qdrant] managed = true`
+    #[arg(long, global = true)]
+    managed_qdrant: bool,
+
+    /// Embedding provider (siliconflow, openai, cohere), overriding config files and
+    /// CODEX_EMBEDDING_PROVIDER
+    #[arg(long, global = true, value_name = "PROVIDER")]
+    embedding_provider: Option<String>,
+
+    /// Embedding model, overriding config files and CODEX_EMBEDDING_MODEL
+    #[arg(long, global = true, value_name = "MODEL")]
+    embedding_model: Option<String>,
+
+    /// Export traces and metrics over OTLP/gRPC to this collector; also enabled by
+    /// OTEL_EXPORTER_OTLP_ENDPOINT
+    #[cfg(feature = "otel")]
+    #[arg(long, global = true, value_name = "URL")]
+    otlp_endpoint: Option<String>,
+}
+
+impl Cli {
+    /// `--verbose` wins over `--quiet`, which keeps only warnings and errors
+    fn log_level(&self) -> tracing::Level {
+        if self.verbose {
+            tracing::Level::DEBUG
+        } else if self.quiet {
+            tracing::Level::WARN
+        } else {
+            tracing::Level::INFO
+        }
+    }
+
+    /// Client settings from config files, then the environment, then flags
+    fn client_settings(&self, config: &IndexConfig) -> (EmbeddingSettings, QdrantSettings) {
+        let embedding = config
+            .embedding
+            .clone()
+            .overridden_by(env_embedding_settings())
+            .overridden_by(EmbeddingSettings {
+                provider: self.embedding_provider.clone(),
+                model: self.embedding_model.clone(),
+                ..Default::default()
+            });
+        let qdrant = config
+            .qdrant
+            .clone()
+            .overridden_by(env_qdrant_settings())
+            .overridden_by(QdrantSettings {
+                url: self.qdrant_url.clone(),
+                managed: self.managed_qdrant.then_some(true),
+                ..Default::default()
+            });
+        (embedding, qdrant)
+    }
+}
+
+/// How commands print their results
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum OutputFormat {
+    /// Human-readable output
+    Text,
+    /// A single pretty-printed JSON document
+    Json,
+    /// One compact JSON object per line (per symbol, chunk, result, ...)
+    Ndjson,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum LogFormat {
+    /// Human-readable lines
+    Pretty,
+    /// One JSON object per event, for log pipelines
+    Json,
+}
+
+/// Why a command failed, and the process exit code that reports it
+/// Clap already exits with 2 for usage errors, so the specific kinds start at 3.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum FailureKind {
+    Other,
+    Config,
+    QdrantUnreachable,
+    EmbeddingAuth,
+    NotIndexed,
+    NoResults,
+}
+
+impl FailureKind {
+    fn exit_code(self) -> u8 {
+        match self {
+            FailureKind::Other => 1,
+            FailureKind::Config => 3,
+            FailureKind::QdrantUnreachable => 4,
+            FailureKind::EmbeddingAuth => 5,
+            FailureKind::NotIndexed => 6,
+            FailureKind::NoResults => 7,
+        }
+    }
+
+    /// Classify an error, by an explicit kind anywhere in its chain or else by the
+    /// messages the library and clients use for these failures
+    fn of(error: &anyhow::Error) -> Self {
+        // `downcast_ref` also sees kinds attached with `context`, which `chain` only
+        // yields wrapped in their context layer
+        let explicit = error
+            .downcast_ref::<FailureKind>()
+            .or_else(|| error.chain().find_map(|e| e.downcast_ref::<FailureKind>()));
+        if let Some(kind) = explicit {
+            return *kind;
+        }
+
+        let message = format!("{error:#}");
+        let mentions = |markers: &[&str]| markers.iter().any(|marker| message.contains(marker));
+        if mentions(&["Failed to connect to", "tonic::transport::Error"]) {
+            FailureKind::QdrantUnreachable
+        } else if mentions(&["status: 401", "status: 403"]) {
+            FailureKind::EmbeddingAuth
+        } else if mentions(&[
+            "Run 'index-codebase' first",
+            "run index-codebase first",
+            "Not found: Collection",
+        ]) {
+            FailureKind::NotIndexed
+        } else if mentions(&["Invalid configuration"]) {
+            FailureKind::Config
+        } else {
+            FailureKind::Other
+        }
+    }
+}
+
+impl std::fmt::Display for FailureKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let description = match self {
+            FailureKind::Other => "Command failed",
+            FailureKind::Config => "Invalid configuration",
+            FailureKind::QdrantUnreachable => "Qdrant is unreachable",
+            FailureKind::EmbeddingAuth => "The embedding API rejected the credentials",
+            FailureKind::NotIndexed => "The codebase is not indexed",
+            FailureKind::NoResults => "No results",
+        };
+        f.write_str(description)
+    }
+}
+
+impl std::error::Error for FailureKind {}
+
+/// Set by `--quiet`; status messages go through `status!` so they can be dropped
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+/// `println!` for progress and hint messages that aren't part of a command's result
+macro_rules! status {
+    ($($arg:tt)*) => {
+        if !QUIET.load(Ordering::Relaxed) {
+            println!($($arg)*);
+        }
+    };
+}
+
+/// Progress bars currently on screen; logs are written through `ProgressAwareWriter`
+/// so they appear above the bars instead of tearing through them
+static PROGRESS: LazyLock<MultiProgress> = LazyLock::new(MultiProgress::new);
+
+/// Log writer that suspends the progress bars while a line is written to stderr
+struct ProgressAwareWriter;
+
+impl Write for ProgressAwareWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        PROGRESS.suspend(|| std::io::stderr().write(buf))
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        std::io::stderr().flush()
+    }
+}
+
+/// One progress bar per indexing phase, driven by `IndexProgress` events
+#[derive(Default)]
+struct IndexProgressBars {
+    bars: Mutex<HashMap<IndexPhase, ProgressBar>>,
+}
+
+impl IndexProgressBars {
+    fn update(&self, event: IndexProgress) {
+        let mut bars = self.bars.lock().unwrap_or_else(PoisonError::into_inner);
+        match event {
+            IndexProgress::Started { phase, total } => {
+                if total == 0 {
+                    return;
+                }
+                let unit = match phase {
+                    IndexPhase::Chunking => "files",
+                    IndexPhase::Embedding | IndexPhase::Upserting => "chunks",
+                };
+                let style = ProgressStyle::with_template(
+                    "{prefix:>10} [{elapsed_precise}] {bar:40.cyan/blue} {pos}/{len} {msg} (ETA {eta})",
+                )
+                .unwrap_or_else(|_| ProgressStyle::default_bar())
+                .progress_chars("=> ");
+                let bar = PROGRESS.add(ProgressBar::new(total));
+                bar.set_style(style);
+                bar.set_prefix(phase.label());
+                bar.set_message(unit);
+                bars.insert(phase, bar);
+            }
+            IndexProgress::Advanced { phase, count } => {
+                if let Some(bar) = bars.get(&phase) {
+                    bar.inc(count);
+                }
+            }
+            IndexProgress::Finished { phase } => {
+                if let Some(bar) = bars.remove(&phase) {
+                    bar.finish();
+                }
+            }
+        }
+    }
 }
 
 #[derive(Subcommand)]
@@ -33,9 +317,9 @@ enum Commands {
         #[arg(value_name = "FILE")]
         file_path: PathBuf,
 
-        /// Output format (json, pretty, summary)
-        #[arg(short = 'f', long, default_value = "pretty")]
-        format: String,
+        /// Print symbol counts instead of listing every symbol (text output)
+        #[arg(long)]
+        summary: bool,
     },
     /// Parse an entire codebase directory
     ParseCodebase {
@@ -43,9 +327,9 @@ enum Commands {
         #[arg(value_name = "DIRECTORY")]
         directory: PathBuf,
 
-        /// Output format (json, pretty, summary)
-        #[arg(short = 'f', long, default_value = "summary")]
-        format: String,
+        /// List every symbol instead of a summary (text output)
+        #[arg(long)]
+        list: bool,
 
         /// Filter by symbol kind (function, struct, class, etc.)
         #[arg(short = 'k', long)]
@@ -54,6 +338,18 @@ enum Commands {
         /// Filter by file extension
         #[arg(short = 'e', long)]
         extension_filter: Option<String>,
+
+        /// Report how many symbols were extracted twice and collapsed into one
+        #[arg(long)]
+        dedupe: bool,
+
+        /// Leave out test code: tests, test modules and everything in test files
+        #[arg(long, conflicts_with = "only_tests")]
+        exclude_tests: bool,
+
+        /// Only list test code
+        #[arg(long)]
+        only_tests: bool,
     },
     /// Chunk a codebase for embedding (extract symbols and create chunks)
     ChunkCodebase {
@@ -61,41 +357,82 @@ enum Commands {
         #[arg(value_name = "DIRECTORY")]
         directory: PathBuf,
 
-        /// Output format (json, pretty, summary)
-        #[arg(short = 'f', long, default_value = "summary")]
-        format: String,
+        /// List every chunk instead of a summary (text output)
+        #[arg(long)]
+        list: bool,
 
-        /// Maximum lines per chunk
-        #[arg(long, default_value = "200")]
-        max_lines: usize,
+        /// Maximum lines per chunk [default: from config, or 200]
+        #[arg(long)]
+        max_lines: Option<usize>,
 
-        /// Minimum lines per chunk
-        #[arg(long, default_value = "5")]
-        min_lines: usize,
+        /// Minimum lines per chunk [default: from config, or 5]
+        #[arg(long)]
+        min_lines: Option<usize>,
 
-        /// Include metadata in chunk content
+        /// Include metadata in chunk content (on unless the config turns it off)
         #[arg(long)]
         include_metadata: bool,
 
-        /// Maximum recursion depth for hierarchical chunking
-        #[arg(long, default_value = "5")]
-        max_depth: usize,
+        /// Maximum recursion depth for hierarchical chunking [default: from config, or 5]
+        #[arg(long)]
+        max_depth: Option<usize>,
+
+        /// Leave test code out [default: from config's `[chunking] tests`]
+        #[arg(long, conflicts_with = "only_tests")]
+        exclude_tests: bool,
+
+        /// Only chunk test code
+        #[arg(long)]
+        only_tests: bool,
     },
     /// Initialize or update codebase index in vector database (automatically detects changes)
     IndexCodebase {
         /// Path to the codebase directory
         #[arg(value_name = "DIRECTORY")]
         directory: PathBuf,
+
+        /// Ignore the state file and rebuild the whole index from scratch
+        #[arg(long)]
+        force: bool,
+    },
+    /// Watch a codebase and keep its index up to date as files change
+    Watch {
+        /// Paths to the codebase directories (several roots are watched together)
+        #[arg(value_name = "DIRECTORY", required = true)]
+        directories: Vec<PathBuf>,
+
+        /// Quiet period in milliseconds to wait for further changes before reindexing
+        #[arg(long, default_value = "1000")]
+        debounce_ms: u64,
+
+        /// How to detect changes: auto, native or poll (auto polls on network filesystems)
+        #[arg(long, default_value = "auto")]
+        backend: String,
+
+        /// Interval in milliseconds between scans when polling
+        #[arg(long, default_value = "2000")]
+        poll_interval_ms: u64,
+
+        /// Stream index updates and failures as server-sent events on
+        /// `http://ADDR/events`
+        #[arg(long, value_name = "ADDR")]
+        events_addr: Option<std::net::SocketAddr>,
     },
     /// Search the indexed codebase using semantic similarity
     SearchCodebase {
-        /// Search query
-        #[arg(value_name = "QUERY")]
-        query: String,
+        /// Search query (optional with --interactive, where it is run first)
+        #[arg(value_name = "QUERY", required_unless_present = "interactive")]
+        query: Option<String>,
 
         /// Path to the codebase directory (for collection identification)
-        #[arg(value_name = "DIRECTORY")]
-        directory: PathBuf,
+        #[arg(value_name = "DIRECTORY", required_unless_present = "interactive")]
+        directory: Option<PathBuf>,
+
+        /// Read queries from stdin in a loop, keeping the clients connected between them
+        /// Queries accept inline `kind:KIND` and `path:GLOB` filters. A single
+        /// positional argument is taken as the directory.
+        #[arg(short = 'i', long, conflicts_with = "metadata_only")]
+        interactive: bool,
 
         /// Number of results to return
         #[arg(short = 'n', long, default_value = "10")]
@@ -104,298 +441,2110 @@ enum Commands {
         /// Minimum similarity score (0.0 to 1.0)
         #[arg(long, default_value = "0.7")]
         min_score: f32,
+
+        /// Match the query against symbol names only, without calling the embedding API
+        #[arg(long)]
+        metadata_only: bool,
+
+        /// Only return these symbol kinds (function, struct, class, etc.)
+        #[arg(long, value_delimiter = ',')]
+        kind: Vec<String>,
+
+        /// Only return files matching this glob (`src/**`), or containing it if it has
+        /// no glob characters; repeatable
+        #[arg(long)]
+        path: Vec<String>,
+
+        /// Skip files matching this glob or containing this string; repeatable
+        #[arg(long)]
+        exclude: Vec<String>,
+
+        /// Only return these languages (rust, python, go, typescript, javascript, java, c, c++, csharp, scala, elixir, haskell, dart, sql, protobuf, graphql, terraform, yaml)
+        #[arg(long, value_delimiter = ',')]
+        lang: Vec<String>,
+
+        /// Skip test code: tests, test modules, and test directories and files
+        #[arg(long, visible_alias = "exclude-tests", conflicts_with = "only_tests")]
+        no_tests: bool,
+
+        /// Only return test code
+        #[arg(long)]
+        only_tests: bool,
+
+        /// Only return symbols declared public (`pub`, `export`, `public`, ...)
+        #[arg(long)]
+        public_only: bool,
+
+        /// Skip symbols shorter than this many lines, e.g. trivial getters
+        #[arg(long)]
+        min_lines: Option<usize>,
+
+        /// Skip symbols with fewer decision points (conditionals, loops, cases) than this
+        #[arg(long)]
+        min_branches: Option<usize>,
+    },
+    /// Record whether a search result was useful (relevance feedback)
+    Feedback {
+        /// Path to the codebase directory
+        #[arg(value_name = "DIRECTORY")]
+        directory: PathBuf,
+
+        /// Chunk ID shown in search results
+        #[arg(value_name = "CHUNK_ID", required_unless_present = "summary")]
+        chunk_id: Option<String>,
+
+        /// Rating for the chunk (up, down)
+        #[arg(short = 'r', long, default_value = "up")]
+        rating: String,
+
+        /// The query that returned the chunk
+        #[arg(short = 'q', long)]
+        query: Option<String>,
+
+        /// Print aggregated feedback per chunk instead of recording
+        #[arg(long)]
+        summary: bool,
+    },
+    /// Show whether a codebase is indexed and what has changed since
+    Status {
+        /// Path to the codebase directory
+        #[arg(value_name = "DIRECTORY")]
+        directory: PathBuf,
+    },
+    /// Summarize what's in a codebase's index: languages, kinds, chunk sizes, largest files
+    Stats {
+        /// Path to the codebase directory
+        #[arg(value_name = "DIRECTORY")]
+        directory: PathBuf,
+    },
+    /// List the collections this tool created on the Qdrant instance
+    ListCollections,
+    /// Delete a codebase's collection and local state to start over
+    ClearIndex {
+        /// Path to the codebase directory
+        #[arg(value_name = "DIRECTORY")]
+        directory: PathBuf,
+
+        /// Don't ask for confirmation
+        #[arg(short = 'y', long)]
+        yes: bool,
+    },
+    /// Answer a question about a codebase with a chat model, citing the code it used
+    Explain {
+        /// The question, e.g. "how does incremental indexing work"
+        #[arg(value_name = "QUESTION")]
+        question: String,
+
+        /// Path to the indexed codebase directory
+        #[arg(value_name = "DIRECTORY")]
+        directory: PathBuf,
+
+        /// Number of chunks to retrieve
+        #[arg(short = 'n', long, default_value_t = ExplainOptions::default().limit)]
+        limit: usize,
+
+        /// Minimum similarity score (0.0 to 1.0)
+        #[arg(long, default_value_t = ExplainOptions::default().min_score)]
+        min_score: f32,
+
+        /// Token budget for the code sent to the model, overriding `[chat]` in config files
+        #[arg(long)]
+        max_context_tokens: Option<usize>,
+
+        /// Chat model, overriding config files and CODEX_CHAT_MODEL
+        #[arg(long, value_name = "MODEL")]
+        chat_model: Option<String>,
+    },
+    /// Search a codebase interactively as you type, previewing and opening results
+    Tui {
+        /// Path to the indexed codebase directory
+        #[arg(value_name = "DIRECTORY")]
+        directory: PathBuf,
+
+        /// Maximum number of results per search
+        #[arg(short = 'n', long, default_value = "20")]
+        limit: usize,
+
+        /// Minimum similarity score (0.0 to 1.0)
+        #[arg(long, default_value = "0.5")]
+        min_score: f32,
+    },
+    /// Measure parse, chunk, embedding, upsert and search performance on a codebase
+    Bench {
+        /// Path to the codebase directory
+        #[arg(value_name = "DIRECTORY")]
+        directory: PathBuf,
+
+        /// Chunks sent to the embedding API
+        #[arg(long, default_value_t = BenchOptions::default().embed_chunks)]
+        embed_chunks: usize,
+
+        /// Points written to a scratch collection
+        #[arg(long, default_value_t = BenchOptions::default().upsert_points)]
+        upsert_points: usize,
+
+        /// Query to time instead of the built-in set; repeatable
+        #[arg(long = "query", value_name = "QUERY")]
+        queries: Vec<String>,
+
+        /// Times each query's vector search is repeated
+        #[arg(long, default_value_t = BenchOptions::default().search_iterations)]
+        search_iterations: usize,
+
+        /// Only measure parsing and chunking, without the embedding API or Qdrant
+        #[arg(long)]
+        offline: bool,
+
+        /// Also write the JSON report to this file
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Write a codebase's index and state file to a compressed archive
+    ExportIndex {
+        /// Path to the indexed codebase directory
+        #[arg(value_name = "DIRECTORY")]
+        directory: PathBuf,
+
+        /// Archive to write
+        #[arg(short, long, default_value = "index.tar.zst")]
+        output: PathBuf,
+    },
+    /// Restore an archive written by export-index as a codebase's index
+    ImportIndex {
+        /// Archive written by export-index
+        #[arg(value_name = "ARCHIVE")]
+        archive: PathBuf,
+
+        /// Path to the codebase directory the index belongs to
+        #[arg(value_name = "DIRECTORY")]
+        directory: PathBuf,
+
+        /// Replace the codebase's existing index
+        #[arg(long)]
+        force: bool,
+    },
+    /// Run a JSON-RPC daemon for editor plugins on stdio, or on a Unix socket
+    ///
+    /// Methods: `search`, `find_similar`, `index_status` and `shutdown`; send
+    /// `files_changed` notifications as files are saved to keep the index current.
+    Daemon {
+        /// Codebase used by requests that don't name a directory
+        #[arg(value_name = "DIRECTORY", default_value = ".")]
+        directory: PathBuf,
+
+        /// Listen on this Unix socket instead of stdio, serving any number of clients
+        #[arg(long, value_name = "PATH")]
+        socket: Option<PathBuf>,
+    },
+    /// Serve search, indexing and status to MCP clients over stdin and stdout
+    ///
+    /// Offers the `search_code`, `index_codebase` and `index_status` tools, e.g. for an
+    /// `[mcp_servers]` entry running `codebase-search mcp /path/to/repo`.
+    Mcp {
+        /// Codebase used by tool calls that don't name a directory
+        #[arg(value_name = "DIRECTORY", default_value = ".")]
+        directory: PathBuf,
+    },
+    /// Serve search, indexing and status over an HTTP JSON API
+    Serve {
+        /// Port to listen on
+        #[arg(long, default_value_t = codebase_search::server::DEFAULT_PORT)]
+        port: u16,
+
+        /// Address to bind to
+        #[arg(long, default_value = "127.0.0.1")]
+        host: std::net::IpAddr,
+
+        /// Serve only the projects in this team config, to holders of its API tokens
+        #[arg(long, value_name = "PATH")]
+        team_config: Option<PathBuf>,
+    },
+    /// Host the index of one or more projects for remote clients over gRPC
+    ///
+    /// Each project is brought up to date and watched for changes. Clients can only
+    /// index, search and watch the projects named here.
+    #[cfg(feature = "grpc")]
+    Grpc {
+        /// Projects to host
+        #[arg(
+            value_name = "DIRECTORY",
+            default_value = ".",
+            num_args = 1..,
+            conflicts_with = "team_config"
+        )]
+        directories: Vec<PathBuf>,
+
+        /// Host the projects in this team config instead, to holders of its API tokens
+        #[arg(long, value_name = "PATH")]
+        team_config: Option<PathBuf>,
+
+        /// Port to listen on
+        #[arg(long, default_value_t = codebase_search::grpc::DEFAULT_PORT)]
+        port: u16,
+
+        /// Address to bind to
+        #[arg(long, default_value = "127.0.0.1")]
+        host: std::net::IpAddr,
+    },
+    /// Inspect or repair the saved index state of a codebase
+    State {
+        #[command(subcommand)]
+        action: StateCommand,
+    },
+    /// Check Qdrant, the embedding API, parsers and a codebase's index for problems
+    Doctor {
+        /// Codebase whose state file and collection are checked too
+        #[arg(value_name = "DIRECTORY")]
+        directory: Option<PathBuf>,
+    },
+    /// Show or create configuration files
+    Config {
+        #[command(subcommand)]
+        action: ConfigCommand,
     },
     /// Show supported languages and file extensions
     Languages,
+    /// Print a shell completion script
+    ///
+    /// e.g. `codebase-search completions bash > ~/.local/share/bash-completion/completions/codebase-search`
+    Completions {
+        /// Shell to generate completions for
+        #[arg(value_enum)]
+        shell: clap_complete::Shell,
+    },
+}
+
+impl Commands {
+    /// Project whose `.codex/index.toml` applies to this command, if any
+    fn project_root(&self) -> Option<&Path> {
+        match self {
+            Commands::ParseCodebase { directory, .. }
+            | Commands::ChunkCodebase { directory, .. }
+            | Commands::IndexCodebase { directory, .. }
+            | Commands::Feedback { directory, .. }
+            | Commands::Status { directory }
+            | Commands::Stats { directory }
+            | Commands::ClearIndex { directory, .. }
+            | Commands::Tui { directory, .. }
+            | Commands::Explain { directory, .. }
+            | Commands::Bench { directory, .. }
+            | Commands::Mcp { directory }
+            | Commands::Daemon { directory, .. }
+            | Commands::ExportIndex { directory, .. }
+            | Commands::ImportIndex { directory, .. }
+            | Commands::State {
+                action: StateCommand::Show { directory } | StateCommand::Repair { directory },
+            }
+            | Commands::Config {
+                action: ConfigCommand::Show { directory },
+            } => Some(directory),
+            Commands::SearchCodebase {
+                query, directory, ..
+            } => Some(search_directory(query, directory)),
+            Commands::Doctor { directory } => directory.as_deref(),
+            // Settings are shared by every watched root; the first one's apply
+            Commands::Watch { directories, .. } => directories.first().map(PathBuf::as_path),
+            #[cfg(feature = "grpc")]
+            Commands::Grpc { directories, .. } => directories.first().map(PathBuf::as_path),
+            Commands::ParseFile { .. }
+            | Commands::ListCollections
+            | Commands::Serve { .. }
+            | Commands::Config {
+                action: ConfigCommand::Init { .. },
+            }
+            | Commands::Languages
+            | Commands::Completions { .. } => None,
+        }
+    }
+
+    /// Whether the command talks to Qdrant, and so needs a managed instance started
+    fn uses_qdrant(&self) -> bool {
+        !matches!(
+            self,
+            Commands::ParseFile { .. }
+                | Commands::ParseCodebase { .. }
+                | Commands::ChunkCodebase { .. }
+                | Commands::Feedback { .. }
+                | Commands::State {
+                    action: StateCommand::Show { .. }
+                }
+                | Commands::Config { .. }
+                | Commands::Languages
+                | Commands::Completions { .. }
+        )
+    }
+
+    /// Whether the command should still run when the config files can't be loaded,
+    /// because it reports or repairs them
+    fn tolerates_bad_config(&self) -> bool {
+        matches!(
+            self,
+            Commands::Doctor { .. }
+                | Commands::Config {
+                    action: ConfigCommand::Init { .. }
+                }
+        )
+    }
+}
+
+#[derive(Subcommand)]
+enum ConfigCommand {
+    /// Print the effective configuration and the files it was read from
+    Show {
+        /// Project whose `.codex/index.toml` is layered over the user config
+        #[arg(value_name = "DIRECTORY", default_value = ".")]
+        directory: PathBuf,
+    },
+    /// Write a commented configuration template
+    Init {
+        /// Project to create `.codex/index.toml` in
+        #[arg(value_name = "DIRECTORY", default_value = ".")]
+        directory: PathBuf,
+
+        /// Create the user config (~/.config/codex/codebase-search.toml) instead
+        #[arg(long)]
+        user: bool,
+
+        /// Overwrite an existing file
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum StateCommand {
+    /// Print a summary of the state file (files tracked, last index time, model)
+    Show {
+        /// Path to the codebase directory
+        #[arg(value_name = "DIRECTORY")]
+        directory: PathBuf,
+    },
+    /// Reconcile the state file with the vector database collection
+    Repair {
+        /// Path to the codebase directory
+        #[arg(value_name = "DIRECTORY")]
+        directory: PathBuf,
+    },
 }
 
 #[tokio::main]
-async fn main() -> Result<()> {
+async fn main() -> ExitCode {
     let cli = Cli::parse();
+    let format = cli.format;
+    match run(cli).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            let kind = FailureKind::of(&e);
+            report_failure(&e, kind, format);
+            ExitCode::from(kind.exit_code())
+        }
+    }
+}
+
+async fn run(cli: Cli) -> Result<()> {
+    // Needs neither logging nor configuration
+    if let Commands::Completions { shell } = cli.command {
+        clap_complete::generate(
+            shell,
+            &mut Cli::command(),
+            "codebase-search",
+            &mut std::io::stdout(),
+        );
+        return Ok(());
+    }
+
+    QUIET.store(cli.quiet, Ordering::Relaxed);
+    if cli.quiet {
+        PROGRESS.set_draw_target(ProgressDrawTarget::hidden());
+    }
 
     // Initialize logging
-    let log_level = if cli.verbose {
-        tracing::Level::DEBUG
+    let log_level = cli.log_level();
+
+    // Logs go to stderr so they never interleave with JSON on stdout; the TUI owns the
+    // terminal, so its logs are dropped rather than drawn over the interface
+    let log_writer = if matches!(cli.command, Commands::Tui { .. }) {
+        BoxMakeWriter::new(std::io::sink)
     } else {
-        tracing::Level::INFO
+        BoxMakeWriter::new(|| ProgressAwareWriter)
+    };
+    let fmt_layer = tracing_subscriber::fmt::layer().with_writer(log_writer);
+    let fmt_layer = match cli.log_format {
+        LogFormat::Pretty => fmt_layer.boxed(),
+        LogFormat::Json => fmt_layer.json().boxed(),
     };
 
-    tracing_subscriber::fmt().with_max_level(log_level).init();
+    // Kept alive until `run` returns, so spans and metrics are flushed on exit
+    #[cfg(feature = "otel")]
+    let telemetry = match cli.otlp_endpoint.as_deref() {
+        Some(endpoint) => Some(Telemetry::init_otlp(Some(endpoint))?),
+        None if std::env::var_os("OTEL_EXPORTER_OTLP_ENDPOINT").is_some() => {
+            Some(Telemetry::init_otlp(None)?)
+        }
+        None => None,
+    };
+    #[cfg(feature = "otel")]
+    let telemetry_layer = telemetry.as_ref().map(|telemetry| telemetry.layer());
+    #[cfg(not(feature = "otel"))]
+    let telemetry_layer = None::<tracing_subscriber::layer::Identity>;
+
+    tracing_subscriber::registry()
+        .with(fmt_layer.with_filter(LevelFilter::from_level(log_level)))
+        .with(telemetry_layer)
+        .init();
+
+    // Config files, environment and flags are layered once here and installed before
+    // any client is created
+    let config = match cli.command.project_root() {
+        Some(root_path) => IndexConfig::load(root_path),
+        None => IndexConfig::load_user(),
+    };
+    let config = match config {
+        Ok(config) => config,
+        Err(e) if cli.command.tolerates_bad_config() => {
+            warn!("Using default settings: {e}");
+            IndexConfig::default()
+        }
+        Err(e) => return Err(e.context(FailureKind::Config)),
+    };
+    let languages = LanguageRegistry::with_configured(&config.languages)
+        .map_err(|e| e.context(FailureKind::Config))?;
+    configure_languages(languages)?;
+    let (embedding_settings, qdrant_settings) = cli.client_settings(&config);
+    configure_embedding(embedding_settings.clone())?;
+    // Held until `run` returns, so a Qdrant started here is stopped on the way out
+    let managed_qdrant = if qdrant_settings.managed == Some(true) && cli.command.uses_qdrant() {
+        let data_dir = managed_data_dir(cli.command.project_root())?;
+        let managed = ManagedQdrant::start(&data_dir, qdrant_settings.binary.as_deref())
+            .await
+            .map_err(|e| e.context(FailureKind::QdrantUnreachable))?;
+        Some(managed)
+    } else {
+        None
+    };
+    let qdrant_settings = match &managed_qdrant {
+        Some(managed) => QdrantSettings {
+            url: Some(managed.url().to_string()),
+            ..qdrant_settings
+        },
+        None => qdrant_settings,
+    };
+    configure_qdrant(qdrant_settings.clone())?;
 
+    let format = cli.format;
     match cli.command {
-        Commands::ParseFile { file_path, format } => {
-            parse_single_file(file_path, &format)?;
+        Commands::ParseFile { file_path, summary } => {
+            parse_single_file(file_path, format, summary)?;
         }
         Commands::ParseCodebase {
             directory,
-            format,
+            list,
             kind_filter,
             extension_filter,
+            dedupe,
+            exclude_tests,
+            only_tests,
         } => {
-            parse_codebase_directory(directory, &format, kind_filter, extension_filter)?;
+            parse_codebase_directory(
+                directory,
+                format,
+                list,
+                kind_filter,
+                extension_filter,
+                dedupe,
+                TestFilter::from_flags(exclude_tests, only_tests),
+            )?;
         }
         Commands::ChunkCodebase {
             directory,
-            format,
+            list,
             max_lines,
             min_lines,
             include_metadata,
             max_depth,
+            exclude_tests,
+            only_tests,
         } => {
-            chunk_codebase_command(
-                directory,
-                &format,
+            let tests = match (exclude_tests, only_tests) {
+                (false, false) => None,
+                flags => Some(TestFilter::from_flags(flags.0, flags.1)),
+            };
+            let chunking = ChunkingSettings {
                 max_lines,
                 min_lines,
-                include_metadata,
+                include_metadata: include_metadata.then_some(true),
                 max_depth,
+                tests,
+            };
+            chunk_codebase_command(directory, format, list, chunking).await?;
+        }
+        Commands::IndexCodebase { directory, force } => {
+            index_codebase_command(directory, force).await?;
+        }
+        Commands::Watch {
+            directories,
+            debounce_ms,
+            backend,
+            poll_interval_ms,
+            events_addr,
+        } => {
+            watch_command(
+                directories,
+                debounce_ms,
+                &backend,
+                poll_interval_ms,
+                events_addr,
             )
             .await?;
         }
-        Commands::IndexCodebase { directory } => {
-            index_codebase_command(directory).await?;
-        }
         Commands::SearchCodebase {
             query,
             directory,
+            interactive,
             limit,
             min_score,
+            metadata_only,
+            kind,
+            path,
+            exclude,
+            lang,
+            no_tests,
+            only_tests,
+            public_only,
+            min_lines,
+            min_branches,
+        } => {
+            let filter = SearchFilter {
+                kinds: kind,
+                paths: path,
+                exclude_paths: exclude,
+                languages: lang,
+                exclude_tests: no_tests,
+                only_tests,
+                public_only,
+                min_lines,
+                min_branches,
+            };
+            let root_path = search_directory(&query, &directory).to_path_buf();
+            // A query is only given alongside a directory; without --interactive clap
+            // requires both
+            let query = directory.and(query);
+            if interactive {
+                search_repl_command(query, root_path, limit, min_score, filter, format).await?;
+                return Ok(());
+            }
+            let query = query.unwrap_or_default();
+            let directory = root_path;
+            if metadata_only {
+                let metadata_query = MetadataQuery {
+                    name: Some(query),
+                    filter,
+                    ..Default::default()
+                };
+                metadata_search_command(metadata_query, directory, limit, format).await?;
+            } else {
+                search_codebase_command(query, directory, limit, min_score, &filter, format)
+                    .await?;
+            }
+        }
+        Commands::Feedback {
+            directory,
+            chunk_id,
+            rating,
+            query,
+            summary,
         } => {
-            search_codebase_command(query, directory, limit, min_score).await?;
+            feedback_command(directory, chunk_id, &rating, query, summary)?;
         }
-        Commands::Languages => {
-            show_supported_languages();
+        Commands::Status { directory } => {
+            status_command(directory, format).await?;
         }
+        Commands::Stats { directory } => {
+            stats_command(directory, format).await?;
+        }
+        Commands::ListCollections => {
+            list_collections_command(format).await?;
+        }
+        Commands::ClearIndex { directory, yes } => {
+            clear_index_command(directory, yes).await?;
+        }
+        Commands::Explain {
+            question,
+            directory,
+            limit,
+            min_score,
+            max_context_tokens,
+            chat_model,
+        } => {
+            let chat_settings = config
+                .chat
+                .clone()
+                .overridden_by(env_chat_settings())
+                .overridden_by(ChatSettings {
+                    model: chat_model,
+                    max_context_tokens,
+                    ..Default::default()
+                });
+            let options = ExplainOptions {
+                limit,
+                min_score,
+                ..Default::default()
+            };
+            explain_command(question, directory, &chat_settings, &options, format).await?;
+        }
+        Commands::Tui {
+            directory,
+            limit,
+            min_score,
+        } => {
+            let canonical_directory = directory
+                .canonicalize()
+                .unwrap_or_else(|_| directory.clone());
+            codebase_search::tui::run(&canonical_directory, limit, min_score).await?;
+        }
+        Commands::Bench {
+            directory,
+            embed_chunks,
+            upsert_points,
+            queries,
+            search_iterations,
+            offline,
+            output,
+        } => {
+            let mut options = BenchOptions {
+                embed_chunks,
+                upsert_points,
+                search_iterations,
+                ..Default::default()
+            };
+            if !queries.is_empty() {
+                options.queries = queries;
+            }
+            if offline {
+                options.embed_chunks = 0;
+                options.upsert_points = 0;
+                options.queries.clear();
+            }
+            bench_command(directory, options, output, format).await?;
+        }
+        Commands::ExportIndex { directory, output } => {
+            export_index_command(directory, output).await?;
+        }
+        Commands::ImportIndex {
+            archive,
+            directory,
+            force,
+        } => {
+            import_index_command(archive, directory, force).await?;
+        }
+        Commands::Daemon { directory, socket } => {
+            daemon_command(directory, socket).await?;
+        }
+        Commands::Mcp { directory } => {
+            let directory = directory.canonicalize().unwrap_or(directory);
+            codebase_search::mcp_server::McpServer::new(directory)
+                .serve_stdio()
+                .await?;
+        }
+        Commands::Serve {
+            port,
+            host,
+            team_config,
+        } => {
+            serve_command(host, port, team_config).await?;
+        }
+        #[cfg(feature = "grpc")]
+        Commands::Grpc {
+            directories,
+            port,
+            host,
+            team_config,
+        } => {
+            grpc_command(directories, team_config, host, port).await?;
+        }
+        Commands::State { action } => match action {
+            StateCommand::Show { directory } => state_show_command(directory, format)?,
+            StateCommand::Repair { directory } => state_repair_command(directory).await?,
+        },
+        Commands::Doctor { directory } => {
+            doctor_command(directory, format).await?;
+        }
+        Commands::Config { action } => match action {
+            ConfigCommand::Show { directory } => {
+                config_show_command(
+                    &directory,
+                    config,
+                    embedding_settings,
+                    qdrant_settings,
+                    format,
+                )?;
+            }
+            ConfigCommand::Init {
+                directory,
+                user,
+                force,
+            } => config_init_command(&directory, user, force)?,
+        },
+        Commands::Languages => {
+            show_supported_languages();
+        }
+        Commands::Completions { .. } => {}
+    }
+
+    Ok(())
+}
+
+/// Print a failure the way `format` asks for: the error chain on stderr for text, or an
+/// envelope with the failure kind on stdout for JSON. `NoResults` prints nothing more,
+/// since the command has already printed its (empty) results.
+fn report_failure(error: &anyhow::Error, kind: FailureKind, format: OutputFormat) {
+    if kind == FailureKind::NoResults {
+        return;
+    }
+    if format == OutputFormat::Text {
+        eprintln!("Error: {error:?}");
+        return;
+    }
+
+    if let Err(e) = print_json(&failure_envelope(error, kind), format) {
+        eprintln!("Error: {error:?} (and failed to print it as JSON: {e})");
+    }
+}
+
+/// `{"error": {...}}` document describing a failure for JSON output
+fn failure_envelope(error: &anyhow::Error, kind: FailureKind) -> serde_json::Value {
+    serde_json::json!({
+        "error": {
+            "kind": kind,
+            "exit_code": kind.exit_code(),
+            "message": error.to_string(),
+            "causes": error.chain().skip(1).map(ToString::to_string).collect::<Vec<_>>(),
+        }
+    })
+}
+
+/// Print `value` as one JSON document; `ndjson` puts it on a single line
+fn print_json<T: Serialize>(value: &T, format: OutputFormat) -> Result<()> {
+    println!("{}", json_document(value, format)?);
+    Ok(())
+}
+
+/// Print `items` as a JSON array, or as one JSON object per line for `ndjson`
+fn print_json_items<T: Serialize>(items: &[T], format: OutputFormat) -> Result<()> {
+    for line in json_item_lines(items, format)? {
+        println!("{line}");
+    }
+    Ok(())
+}
+
+fn json_document<T: Serialize>(value: &T, format: OutputFormat) -> Result<String> {
+    Ok(match format {
+        OutputFormat::Ndjson => serde_json::to_string(value)?,
+        _ => serde_json::to_string_pretty(value)?,
+    })
+}
+
+fn json_item_lines<T: Serialize>(items: &[T], format: OutputFormat) -> Result<Vec<String>> {
+    match format {
+        OutputFormat::Ndjson => items
+            .iter()
+            .map(|item| Ok(serde_json::to_string(item)?))
+            .collect(),
+        _ => Ok(vec![json_document(&items, format)?]),
+    }
+}
+
+fn parse_single_file(file_path: PathBuf, format: OutputFormat, summary: bool) -> Result<()> {
+    info!("Parsing file: {}", file_path.display());
+
+    let mut parser = SymbolParser::new()?;
+    let symbols = parser
+        .parse_file(&file_path)
+        .map_err(|e| anyhow::anyhow!("Failed to parse file '{}': {}", file_path.display(), e))?;
+
+    match format {
+        OutputFormat::Json | OutputFormat::Ndjson => print_json_items(&symbols, format)?,
+        OutputFormat::Text if summary => print_symbols_summary(&symbols, Some(&file_path)),
+        OutputFormat::Text => {
+            println!("=== Symbols in {} ===", file_path.display());
+            print_symbols_pretty(&symbols);
+        }
+    }
+
+    Ok(())
+}
+
+fn parse_codebase_directory(
+    directory: PathBuf,
+    format: OutputFormat,
+    list: bool,
+    kind_filter: Option<String>,
+    extension_filter: Option<String>,
+    dedupe: bool,
+    tests: TestFilter,
+) -> Result<()> {
+    info!("Parsing codebase: {}", directory.display());
+
+    let (symbols, stats) = parse_codebase_with_stats(&directory)?;
+
+    // Apply filters
+    let filtered_symbols: Vec<_> = symbols
+        .into_iter()
+        .filter(|symbol| {
+            if !tests.keeps(symbol.is_test) {
+                return false;
+            }
+
+            // Filter by kind if specified
+            if let Some(ref kind_str) = kind_filter {
+                let kind_matches = match SymbolKind::from_name(kind_str) {
+                    Some(kind) => symbol.kind == kind,
+                    None => {
+                        warn!("Unknown symbol kind filter: {kind_str}");
+                        true
+                    }
+                };
+                if !kind_matches {
+                    return false;
+                }
+            }
+
+            // Filter by extension if specified
+            if let Some(ref ext) = extension_filter {
+                if let Some(file_ext) = symbol.file_path.extension().and_then(|e| e.to_str()) {
+                    if file_ext != ext {
+                        return false;
+                    }
+                }
+            }
+
+            true
+        })
+        .collect();
+
+    match format {
+        OutputFormat::Json | OutputFormat::Ndjson => print_json_items(&filtered_symbols, format)?,
+        OutputFormat::Text if list => {
+            println!("=== Symbols in {} ===", directory.display());
+            print_symbols_pretty(&filtered_symbols);
+        }
+        OutputFormat::Text => print_symbols_summary(&filtered_symbols, None),
+    }
+    if dedupe {
+        let line = format!(
+            "Duplicate symbols collapsed: {}",
+            stats.duplicates_collapsed
+        );
+        // Keep JSON output parseable
+        match format {
+            OutputFormat::Text => println!("\n{line}"),
+            OutputFormat::Json | OutputFormat::Ndjson => eprintln!("{line}"),
+        }
+    }
+
+    Ok(())
+}
+
+async fn chunk_codebase_command(
+    directory: PathBuf,
+    format: OutputFormat,
+    list: bool,
+    chunking: ChunkingSettings,
+) -> Result<()> {
+    info!("Chunking codebase: {}", directory.display());
+
+    // Flags override the project's `[chunking]` settings
+    let chunking_options = IndexConfig::load(&directory)?
+        .chunking
+        .overridden_by(chunking)
+        .options();
+
+    let embedded_chunks = chunk_codebase(&directory, chunking_options).await?;
+    let chunks: Vec<_> = embedded_chunks.into_iter().map(|ec| ec.chunk).collect();
+
+    match format {
+        OutputFormat::Json | OutputFormat::Ndjson => print_json_items(&chunks, format)?,
+        OutputFormat::Text if list => {
+            println!("=== Chunks in {} ===", directory.display());
+            print_chunks_pretty(&chunks);
+        }
+        OutputFormat::Text => print_chunks_summary(&chunks),
+    }
+
+    Ok(())
+}
+
+async fn index_codebase_command(directory: PathBuf, force: bool) -> Result<()> {
+    // Canonicalize the directory path to convert relative paths to absolute paths
+    let canonical_directory = directory
+        .canonicalize()
+        .unwrap_or_else(|_| directory.clone());
+
+    status!("🔍 Analyzing codebase: {}", canonical_directory.display());
+    if force {
+        status!("♻️  Ignoring the saved state and rebuilding the whole index.");
+    } else {
+        status!(
+            "📊 This will automatically detect and process only changed files for optimal performance."
+        );
+    }
+
+    // restore_session intelligently handles both initial indexing and incremental updates,
+    // while init_session always rebuilds and rewrites the state; each phase gets a
+    // progress bar (hidden when stderr isn't a terminal)
+    let bars = IndexProgressBars::default();
+    let progress = |event| bars.update(event);
+    if force {
+        init_session_with_progress(&canonical_directory, &progress).await?;
+    } else {
+        restore_session_with_progress(&canonical_directory, &progress).await?;
+    }
+
+    status!("✅ Codebase indexed successfully into vector database!");
+    status!(
+        "🗂️  Collection available for: {}",
+        canonical_directory.display()
+    );
+    status!("💡 Use 'search-codebase' command to query the indexed code.");
+    Ok(())
+}
+
+async fn watch_command(
+    directories: Vec<PathBuf>,
+    debounce_ms: u64,
+    backend: &str,
+    poll_interval_ms: u64,
+    events_addr: Option<std::net::SocketAddr>,
+) -> Result<()> {
+    use codebase_search::file_watcher::FileWatcherBuilder;
+    use codebase_search::file_watcher::WatcherBackend;
+    use codebase_search::indexer::IndexService;
+    use codebase_search::indexer::WorkspaceIndexService;
+    use codebase_search::notifications::IndexNotification;
+
+    let backend: WatcherBackend = backend.parse()?;
+
+    // Ctrl-C stops every watcher and any reindex that is in flight
+    let shutdown = tokio_util::sync::CancellationToken::new();
+    tokio::spawn({
+        let shutdown = shutdown.clone();
+        async move {
+            if tokio::signal::ctrl_c().await.is_ok() {
+                shutdown.cancel();
+            }
+        }
+    });
+
+    let mut services = Vec::new();
+    for directory in &directories {
+        // Canonicalize the directory path to convert relative paths to absolute paths
+        let canonical_directory = directory
+            .canonicalize()
+            .unwrap_or_else(|_| directory.clone());
+
+        // Catch up on anything that changed while we weren't watching
+        status!(
+            "🔍 Bringing index up to date: {}",
+            canonical_directory.display()
+        );
+        restore_session(&canonical_directory).await?;
+
+        let mut watcher = FileWatcherBuilder::new()
+            .root_path(&canonical_directory)
+            .debounce_delay(debounce_ms)
+            .backend(backend)
+            .poll_interval(poll_interval_ms)
+            .shutdown_token(shutdown.clone())
+            .build();
+        watcher.start()?;
+        if watcher.status().backend == Some(WatcherBackend::Poll) {
+            status!(
+                "⏱️  Polling {} for changes every {poll_interval_ms}ms",
+                canonical_directory.display()
+            );
+        }
+        services.push(IndexService::new(watcher)?);
+    }
+
+    let workspace = WorkspaceIndexService::new(services)?;
+    let roots = workspace.root_paths();
+    for root in &roots {
+        status!("👀 Watching {} for changes", root.display());
+    }
+    let (events, _) = tokio::sync::broadcast::channel(256);
+    if let Some(addr) = events_addr {
+        let listener =
+            codebase_search::server::serve_events(addr, events.clone(), shutdown.clone());
+        tokio::spawn(async move {
+            if let Err(e) = listener.await {
+                warn!("Event stream stopped: {e}");
+            }
+        });
+        status!("📡 Streaming index events on http://{addr}/events");
+    }
+    status!("   (Ctrl-C to stop)");
+
+    // Batches from every root arrive on one stream, tagged with their root
+    let multi_root = roots.len() > 1;
+    let mut updates = workspace.spawn();
+    while let Some(update) = updates.recv().await {
+        if let Some(notification) = IndexNotification::for_update(&update.root_path, &update.result)
+        {
+            // Nobody may be subscribed
+            let _ = events.send(notification);
+        }
+        let prefix = if multi_root {
+            format!("[{}] ", update.root_path.display())
+        } else {
+            String::new()
+        };
+        match update.result {
+            Ok(result) if result.is_empty() => {}
+            Ok(result) => status!(
+                "✅ {prefix}Index updated: {} added, {} modified, {} deleted",
+                result.added,
+                result.modified,
+                result.deleted
+            ),
+            Err(e) => eprintln!("❌ {prefix}Incremental update failed: {e}"),
+        }
+    }
+
+    if shutdown.is_cancelled() {
+        status!("👋 Stopped watching");
+    }
+    Ok(())
+}
+
+/// Directory searched by `search-codebase`
+/// With --interactive a lone positional, which clap assigns to the query, names the
+/// directory, and with none the current directory is searched.
+fn search_directory<'a>(query: &'a Option<String>, directory: &'a Option<PathBuf>) -> &'a Path {
+    match (query, directory) {
+        (_, Some(directory)) => directory,
+        (Some(query), None) => Path::new(query),
+        (None, None) => Path::new("."),
+    }
+}
+
+/// Read queries from stdin until EOF or `:quit`, running each against the same clients
+async fn search_repl_command(
+    initial_query: Option<String>,
+    directory: PathBuf,
+    limit: usize,
+    min_score: f32,
+    base_filter: SearchFilter,
+    format: OutputFormat,
+) -> Result<()> {
+    use codebase_search::retriever::search_codebase_filtered;
+    use tokio::io::AsyncBufReadExt;
+
+    let canonical_directory = directory
+        .canonicalize()
+        .unwrap_or_else(|_| directory.clone());
+    // The embedding and Qdrant clients are created by the first query and reused by
+    // every later one
+    let text = format == OutputFormat::Text;
+    if text {
+        println!(
+            "🔍 Interactive search in {} (limit {limit}, min score {min_score:.2})",
+            canonical_directory.display()
+        );
+        println!("💡 Filter with kind:KIND and path:GLOB; :quit or Ctrl-D exits.");
+    }
+
+    let mut lines = tokio::io::BufReader::new(tokio::io::stdin()).lines();
+    let mut pending = initial_query;
+    loop {
+        let line = match pending.take() {
+            Some(line) => line,
+            None => {
+                if text {
+                    print!("search> ");
+                    std::io::stdout().flush()?;
+                }
+                match lines.next_line().await? {
+                    Some(line) => line,
+                    None => break,
+                }
+            }
+        };
+        let line = line.trim();
+        match line {
+            "" => continue,
+            ":q" | ":quit" | ":exit" => break,
+            ":h" | ":help" => {
+                println!("Type a query, optionally with filters:");
+                println!("  kind:function,method   only these symbol kinds");
+                println!("  path:src/**            only files matching the glob");
+                println!("  exclude:generated      skip files matching or containing this");
+                println!("  lang:rust,python       only these languages");
+                println!("  :quit                  exit");
+                continue;
+            }
+            _ => {}
+        }
+
+        let (query, filter) = SearchFilter::parse_inline(line);
+        let filter = filter.with_defaults(&base_filter);
+        if query.is_empty() {
+            eprintln!("❌ Filters need a query to go with them");
+            continue;
+        }
+        let started = std::time::Instant::now();
+        let results =
+            match search_codebase_filtered(query, &canonical_directory, limit, min_score, &filter)
+                .await
+            {
+                Ok(results) => results,
+                Err(e) => {
+                    eprintln!("❌ Search failed: {e}");
+                    continue;
+                }
+            };
+
+        if !text {
+            print_json_items(&results, format)?;
+            continue;
+        }
+        if results.is_empty() {
+            println!("❌ No results ({} ms)", started.elapsed().as_millis());
+            continue;
+        }
+        for (i, result) in results.iter().enumerate() {
+            print_search_result(i + 1, result);
+        }
+        println!(
+            "🎯 {} results ({} ms)",
+            results.len(),
+            started.elapsed().as_millis()
+        );
+    }
+    Ok(())
+}
+
+async fn search_codebase_command(
+    query: String,
+    directory: PathBuf,
+    limit: usize,
+    min_score: f32,
+    filter: &SearchFilter,
+    format: OutputFormat,
+) -> Result<()> {
+    use codebase_search::retriever::search_codebase_filtered;
+
+    // Canonicalize the directory path to convert relative paths to absolute paths
+    let canonical_directory = directory
+        .canonicalize()
+        .unwrap_or_else(|_| directory.clone());
+
+    info!("Searching indexed codebase for query: {}", query);
+
+    if format != OutputFormat::Text {
+        let results =
+            search_codebase_filtered(query, &canonical_directory, limit, min_score, filter).await?;
+        print_json_items(&results, format)?;
+        return no_results_unless(!results.is_empty());
+    }
+    if plain_output() {
+        let results =
+            search_codebase_filtered(query, &canonical_directory, limit, min_score, filter).await?;
+        print_plain_results(&directory, &results);
+        return no_results_unless(!results.is_empty());
+    }
+
+    status!("🔍 Searching codebase for: \"{query}\"");
+    status!("🎯 Limit: {limit}, Min score: {min_score:.2}");
+    status!();
+
+    match search_codebase_filtered(query, &canonical_directory, limit, min_score, filter).await {
+        Ok(results) => {
+            if results.is_empty() {
+                println!("❌ No results found matching your query.");
+                status!("💡 Try:");
+                status!("   - Using different keywords");
+                status!("   - Lowering the minimum score (current: {min_score:.2})");
+                status!("   - Checking if the codebase is indexed with 'index-codebase' command");
+                return Err(FailureKind::NoResults.into());
+            } else {
+                status!("✅ Found {} results:", results.len());
+                status!();
+
+                for (i, result) in results.iter().enumerate() {
+                    print_search_result(i + 1, result);
+                    if i < results.len() - 1 {
+                        println!("{}", "─".repeat(80));
+                    }
+                }
+
+                status!();
+                status!(
+                    "🎯 Search completed. Showing {} results with score >= {:.2}",
+                    results.len(),
+                    min_score
+                );
+            }
+        }
+        Err(e) => {
+            eprintln!("❌ Search failed: {e}");
+            eprintln!("💡 Make sure:");
+            eprintln!("   - Qdrant is running on localhost:6334");
+            eprintln!("   - The codebase is indexed (run 'index-codebase' first)");
+            eprintln!("   - The directory path is correct");
+            eprintln!("   - Or use --metadata-only to search without the embedding API");
+            return Err(e);
+        }
+    }
+
+    Ok(())
+}
+
+/// `FailureKind::NoResults` unless `found`, so scripts can tell an empty search by its exit code
+fn no_results_unless(found: bool) -> Result<()> {
+    if found {
+        Ok(())
+    } else {
+        Err(FailureKind::NoResults.into())
+    }
+}
+
+async fn metadata_search_command(
+    query: MetadataQuery,
+    directory: PathBuf,
+    limit: usize,
+    format: OutputFormat,
+) -> Result<()> {
+    use codebase_search::retriever::search_codebase_metadata;
+
+    // Canonicalize the directory path to convert relative paths to absolute paths
+    let canonical_directory = directory
+        .canonicalize()
+        .unwrap_or_else(|_| directory.clone());
+
+    info!("Metadata search for: {:?}", query);
+
+    if format != OutputFormat::Text {
+        let results = search_codebase_metadata(query, &canonical_directory, limit).await?;
+        print_json_items(&results, format)?;
+        return no_results_unless(!results.is_empty());
+    }
+    if plain_output() {
+        let results = search_codebase_metadata(query, &canonical_directory, limit).await?;
+        print_plain_results(&directory, &results);
+        return no_results_unless(!results.is_empty());
+    }
+
+    status!(
+        "🔍 Searching symbol metadata for: \"{}\"",
+        query.name.as_deref().unwrap_or_default()
+    );
+    status!("🎯 Limit: {limit} (metadata only, no embeddings)");
+    status!();
+
+    match search_codebase_metadata(query, &canonical_directory, limit).await {
+        Ok(results) => {
+            if results.is_empty() {
+                println!("❌ No symbols found matching your query.");
+                status!("💡 Try a shorter name fragment or drop the --kind/--path filters.");
+                return Err(FailureKind::NoResults.into());
+            } else {
+                status!("✅ Found {} results:", results.len());
+                status!();
+
+                for (i, result) in results.iter().enumerate() {
+                    print_search_result(i + 1, result);
+                    if i < results.len() - 1 {
+                        println!("{}", "─".repeat(80));
+                    }
+                }
+            }
+        }
+        Err(e) => {
+            eprintln!("❌ Metadata search failed: {e}");
+            eprintln!("💡 Make sure:");
+            eprintln!("   - Qdrant is running on localhost:6334");
+            eprintln!("   - The codebase is indexed (run 'index-codebase' first)");
+            return Err(e);
+        }
+    }
+
+    Ok(())
+}
+
+async fn stats_command(directory: PathBuf, format: OutputFormat) -> Result<()> {
+    use codebase_search::index_stats::index_stats;
+
+    let canonical_directory = directory
+        .canonicalize()
+        .unwrap_or_else(|_| directory.clone());
+    let stats = index_stats(&canonical_directory).await?;
+    if format != OutputFormat::Text {
+        return print_json(&stats, format);
+    }
+
+    println!("=== Index Stats ===");
+    println!("Codebase: {}", stats.root_path.display());
+    println!("Collection: {}", stats.collection_id);
+    println!(
+        "Chunks: {} from {} symbols in {} files ({:.1} MiB of content)",
+        stats.chunks,
+        stats.symbols,
+        stats.files,
+        stats.content_bytes as f64 / (1024.0 * 1024.0)
+    );
+    if let Some(state) = &stats.state {
+        println!("Files tracked: {}", state.tracked_files);
+        if let Some(at) = state.last_indexed_at {
+            println!("Last indexed: {}", at.format("%Y-%m-%d %H:%M:%S UTC"));
+        }
+    }
+    if !stats.files_without_points.is_empty() {
+        println!(
+            "Tracked files without chunks: {}",
+            stats.files_without_points.len()
+        );
+    }
+
+    println!("\n📋 By Language:");
+    for language in &stats.languages {
+        println!(
+            "   {}: {} files, {} symbols, {} chunks",
+            language.language, language.files, language.symbols, language.chunks
+        );
+    }
+
+    println!("\n🏷️  By Symbol Kind:");
+    for (kind, count) in &stats.kinds {
+        println!("   {kind}: {count}");
+    }
+
+    println!(
+        "\n📏 Chunk Sizes (mean {:.1} lines, max {}):",
+        stats.mean_chunk_lines, stats.max_chunk_lines
+    );
+    for bucket in &stats.sizes {
+        let range = match bucket.max_lines {
+            Some(max_lines) => format!("{}-{max_lines}", bucket.min_lines),
+            None => format!("{}+", bucket.min_lines),
+        };
+        println!("   {range:>8} lines: {}", bucket.chunks);
+    }
+
+    println!("\n✂️  Splitting:");
+    println!(
+        "   Split chunks: {} ({:.1}%)",
+        stats.split_chunks,
+        stats.split_ratio * 100.0
+    );
+    println!(
+        "   Container chunks: {} ({:.1}%)",
+        stats.container_chunks,
+        stats.container_ratio * 100.0
+    );
+
+    if !stats.largest_files.is_empty() {
+        println!("\n📁 Largest files:");
+        for file in &stats.largest_files {
+            println!(
+                "   {} - {} chunks, {} lines",
+                file.file_path, file.chunks, file.chunk_lines
+            );
+        }
+    }
+    Ok(())
+}
+
+async fn status_command(directory: PathBuf, format: OutputFormat) -> Result<()> {
+    use codebase_search::vector_db::index_status;
+
+    let canonical_directory = directory
+        .canonicalize()
+        .unwrap_or_else(|_| directory.clone());
+    let status = index_status(&canonical_directory).await?;
+
+    match format {
+        OutputFormat::Json | OutputFormat::Ndjson => print_json(&status, format)?,
+        OutputFormat::Text => {
+            println!("=== Index Status ===");
+            println!("Codebase: {}", status.root_path.display());
+            println!(
+                "Indexed: {}",
+                if status.indexed { "✅ yes" } else { "❌ no" }
+            );
+            println!("Collection: {}", status.collection_id);
+            match (status.point_count, &status.collection_error) {
+                (Some(count), _) => println!("Points: {count}"),
+                (None, Some(error)) => println!("Points: unavailable ({error})"),
+                (None, None) => println!("Points: unavailable"),
+            }
+
+            let Some(state) = status.state else {
+                println!("State: none (run 'index-codebase' first)");
+                return Ok(());
+            };
+            println!("Files tracked: {}", state.tracked_files);
+            match state.last_indexed_at {
+                Some(at) => println!("Last indexed: {}", at.format("%Y-%m-%d %H:%M:%S UTC")),
+                None => println!("Last indexed: unknown"),
+            }
+            match state.index_params {
+                Some(params) => println!("Embedding: {} / {}", params.provider, params.model),
+                None => println!("Embedding: unknown"),
+            }
+
+            if let Some(pending) = status.pending {
+                if pending.is_empty() {
+                    println!("Pending changes: none");
+                } else {
+                    println!(
+                        "Pending changes: {} added, {} modified, {} deleted",
+                        pending.added.len(),
+                        pending.modified.len(),
+                        pending.deleted.len()
+                    );
+                    for file_path in &pending.added {
+                        println!("   + {file_path}");
+                    }
+                    for file_path in &pending.modified {
+                        println!("   ~ {file_path}");
+                    }
+                    for file_path in &pending.deleted {
+                        println!("   - {file_path}");
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn list_collections_command(format: OutputFormat) -> Result<()> {
+    use codebase_search::vector_db::list_collections;
+
+    let collections = list_collections().await?;
+
+    match format {
+        OutputFormat::Json | OutputFormat::Ndjson => print_json_items(&collections, format)?,
+        OutputFormat::Text => {
+            if collections.is_empty() {
+                println!("No collections found.");
+                return Ok(());
+            }
+            println!("=== Collections ({}) ===", collections.len());
+            for collection in &collections {
+                println!("\n📦 {}", collection.name);
+                match &collection.root_path {
+                    Some(root_path) => println!("   Codebase: {}", root_path.display()),
+                    None => println!("   Codebase: unknown"),
+                }
+                if let Some(error) = &collection.error {
+                    println!("   Details unavailable: {error}");
+                    continue;
+                }
+                if let Some(status) = &collection.status {
+                    println!("   Status: {status}");
+                }
+                match collection.points_count {
+                    Some(count) => println!("   Points: {count}"),
+                    None => println!("   Points: unknown"),
+                }
+                if let Some(bytes) = collection.vector_bytes {
+                    println!("   Vectors: {:.1} MiB", bytes as f64 / (1024.0 * 1024.0));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn clear_index_command(directory: PathBuf, yes: bool) -> Result<()> {
+    use codebase_search::vector_db::clear_index;
+    use std::io::IsTerminal;
+    use std::io::Write;
+
+    let canonical_directory = directory
+        .canonicalize()
+        .unwrap_or_else(|_| directory.clone());
+
+    if !yes {
+        if !std::io::stdin().is_terminal() {
+            return Err(anyhow::anyhow!(
+                "Refusing to clear the index without confirmation; pass --yes"
+            ));
+        }
+        print!(
+            "🗑️  Delete the index, state and feedback for {}? [y/N] ",
+            canonical_directory.display()
+        );
+        std::io::stdout().flush()?;
+        let mut answer = String::new();
+        std::io::stdin().read_line(&mut answer)?;
+        if !matches!(answer.trim().to_lowercase().as_str(), "y" | "yes") {
+            println!("Aborted.");
+            return Ok(());
+        }
+    }
+
+    let cleared = clear_index(&canonical_directory).await?;
+    if cleared.collection_deleted {
+        status!("✅ Deleted collection {}", cleared.collection_id);
+    } else {
+        status!("ℹ️  Collection {} did not exist", cleared.collection_id);
+    }
+    for path in &cleared.removed_files {
+        status!("✅ Removed {}", path.display());
+    }
+    status!("💡 Run 'index-codebase' to build a fresh index.");
+    Ok(())
+}
+
+async fn explain_command(
+    question: String,
+    directory: PathBuf,
+    chat_settings: &ChatSettings,
+    options: &ExplainOptions,
+    format: OutputFormat,
+) -> Result<()> {
+    use codebase_search::explain::ChatConfig;
+    use codebase_search::explain::explain;
+
+    let canonical_directory = directory
+        .canonicalize()
+        .unwrap_or_else(|_| directory.clone());
+    let chat = ChatConfig::from_settings(chat_settings);
+    if format == OutputFormat::Text {
+        status!(
+            "🤔 Asking {} about {}",
+            chat.model,
+            canonical_directory.display()
+        );
+    }
+
+    let explanation = explain(&question, &canonical_directory, &chat, options).await?;
+    if format != OutputFormat::Text {
+        return print_json(&explanation, format);
+    }
+
+    println!();
+    println!("{}", explanation.answer.trim());
+    println!();
+    println!("📚 Sources:");
+    for source in &explanation.sources {
+        println!(
+            "   [{}] {}:{}-{} {}{}",
+            source.index,
+            source.file_path.display(),
+            source.start_line,
+            source.end_line,
+            source.symbol_name,
+            if source.truncated { " (truncated)" } else { "" }
+        );
+    }
+    if explanation.omitted_chunks > 0 {
+        status!(
+            "💡 {} more matching chunks didn't fit in the context; raise --max-context-tokens to include them.",
+            explanation.omitted_chunks
+        );
+    }
+    Ok(())
+}
+
+async fn bench_command(
+    directory: PathBuf,
+    options: BenchOptions,
+    output: Option<PathBuf>,
+    format: OutputFormat,
+) -> Result<()> {
+    use codebase_search::bench::LatencyStats;
+    use codebase_search::bench::run_bench;
+
+    let canonical_directory = directory
+        .canonicalize()
+        .unwrap_or_else(|_| directory.clone());
+    if format == OutputFormat::Text {
+        status!("⏱️  Benchmarking {}", canonical_directory.display());
+    }
+
+    let report = run_bench(&canonical_directory, &options).await?;
+    if let Some(output) = &output {
+        std::fs::write(output, serde_json::to_string_pretty(&report)?)
+            .map_err(|e| anyhow::anyhow!("Failed to write {}: {e}", output.display()))?;
+    }
+    if format != OutputFormat::Text {
+        return print_json(&report, format);
+    }
+
+    let latency = |stats: &Option<LatencyStats>| match stats {
+        Some(stats) => format!(
+            "p50 {:.1} ms, p95 {:.1} ms, max {:.1} ms",
+            stats.p50_ms, stats.p95_ms, stats.max_ms
+        ),
+        None => "n/a".to_string(),
+    };
+    let parse = &report.parse;
+    println!(
+        "📄 Parse:     {} files, {} symbols in {:.2}s ({:.0} files/s, {:.2} MB/s)",
+        parse.files, parse.symbols, parse.seconds, parse.files_per_sec, parse.mb_per_sec
+    );
+    let chunking = &report.chunking;
+    println!(
+        "✂️  Chunking:  {} chunks in {:.2}s ({:.0} chunks/s)",
+        chunking.chunks, chunking.seconds, chunking.chunks_per_sec
+    );
+    if let Some(embedding) = &report.embedding {
+        println!(
+            "🧠 Embedding: {} chunks with {} ({:.1} chunks/s); per batch of {}: {}",
+            embedding.chunks,
+            embedding.model,
+            embedding.chunks_per_sec,
+            embedding.batch_size,
+            latency(&embedding.batch_latency)
+        );
+    }
+    if let Some(upsert) = &report.upsert {
+        println!(
+            "💾 Upsert:    {} points in {:.2}s ({:.0} points/s); per batch of {}: {}",
+            upsert.points,
+            upsert.seconds,
+            upsert.points_per_sec,
+            upsert.batch_size,
+            latency(&upsert.batch_latency)
+        );
+    }
+    if let Some(search) = &report.search {
+        println!(
+            "🔍 Search:    {} queries; embedding {}; vector search {}",
+            search.queries,
+            latency(&search.query_embedding),
+            latency(&search.vector_search)
+        );
+    }
+    for skipped in &report.skipped {
+        println!("⏭️  Skipped {}: {}", skipped.stage, skipped.reason);
     }
+    match &output {
+        Some(output) => status!("📝 Report written to {}", output.display()),
+        None => status!("💡 Use --format json or --output FILE for a report to compare runs."),
+    }
+    Ok(())
+}
+
+async fn export_index_command(directory: PathBuf, output: PathBuf) -> Result<()> {
+    use codebase_search::index_archive::export_index;
+
+    let canonical_directory = directory
+        .canonicalize()
+        .unwrap_or_else(|_| directory.clone());
 
+    status!("📦 Exporting index of {}", canonical_directory.display());
+    let exported = export_index(&canonical_directory, &output).await?;
+    status!(
+        "✅ Wrote {} points and the state of {} files from {} to {}",
+        exported.points,
+        exported.tracked_files,
+        exported.collection_id,
+        exported.archive_path.display()
+    );
+    status!("💡 Run 'import-index' on another machine to use it without reindexing.");
     Ok(())
 }
 
-fn parse_single_file(file_path: PathBuf, format: &str) -> Result<()> {
-    info!("Parsing file: {}", file_path.display());
+async fn import_index_command(archive: PathBuf, directory: PathBuf, force: bool) -> Result<()> {
+    use codebase_search::index_archive::import_index;
 
-    let mut parser = SymbolParser::new()?;
-    let symbols = parser
-        .parse_file(&file_path)
-        .map_err(|e| anyhow::anyhow!("Failed to parse file '{}': {}", file_path.display(), e))?;
+    let canonical_directory = directory
+        .canonicalize()
+        .unwrap_or_else(|_| directory.clone());
 
-    match format {
-        "json" => {
-            let json = serde_json::to_string_pretty(&symbols)?;
-            println!("{json}");
-        }
-        "pretty" => {
-            println!("=== Symbols in {} ===", file_path.display());
-            print_symbols_pretty(&symbols);
-        }
-        "summary" => {
-            print_symbols_summary(&symbols, Some(&file_path));
-        }
-        _ => {
-            return Err(anyhow::anyhow!(
-                "Unsupported format: {format}. Use 'json', 'pretty', or 'summary'"
-            ));
-        }
+    status!(
+        "📦 Importing {} into {}",
+        archive.display(),
+        canonical_directory.display()
+    );
+    let bars = IndexProgressBars::default();
+    let imported = import_index(&archive, &canonical_directory, force, &|event| {
+        bars.update(event)
+    })
+    .await?;
+    status!(
+        "✅ Imported {} points and the state of {} files into {} (exported from {})",
+        imported.points,
+        imported.tracked_files,
+        imported.collection_id,
+        imported.source_collection_id
+    );
+    if !imported.mismatched_settings.is_empty() {
+        println!(
+            "⚠️  The archive was built with different settings: {}",
+            imported.mismatched_settings.join(", ")
+        );
+        println!("   Searches may be poor until 'index-codebase' rebuilds the index.");
     }
-
+    status!("💡 Run 'index-codebase' to pick up files changed since the export.");
     Ok(())
 }
 
-fn parse_codebase_directory(
-    directory: PathBuf,
-    format: &str,
-    kind_filter: Option<String>,
-    extension_filter: Option<String>,
+async fn serve_command(
+    host: std::net::IpAddr,
+    port: u16,
+    team_config: Option<PathBuf>,
 ) -> Result<()> {
-    info!("Parsing codebase: {}", directory.display());
-
-    let symbols = parse_codebase(&directory)?;
-
-    // Apply filters
-    let filtered_symbols: Vec<_> = symbols
-        .into_iter()
-        .filter(|symbol| {
-            // Filter by kind if specified
-            if let Some(ref kind_str) = kind_filter {
-                let kind_matches = match kind_str.to_lowercase().as_str() {
-                    "function" => matches!(symbol.kind, SymbolKind::Function),
-                    "method" => matches!(symbol.kind, SymbolKind::Method),
-                    "struct" => matches!(symbol.kind, SymbolKind::Struct),
-                    "class" => matches!(symbol.kind, SymbolKind::Class),
-                    "enum" => matches!(symbol.kind, SymbolKind::Enum),
-                    "trait" => matches!(symbol.kind, SymbolKind::Trait),
-                    "interface" => matches!(symbol.kind, SymbolKind::Interface),
-                    "impl" => matches!(symbol.kind, SymbolKind::Impl),
-                    "module" => matches!(symbol.kind, SymbolKind::Module),
-                    "constant" => matches!(symbol.kind, SymbolKind::Constant),
-                    "variable" => matches!(symbol.kind, SymbolKind::Variable),
-                    "type" => matches!(symbol.kind, SymbolKind::Type),
-                    _ => {
-                        warn!("Unknown symbol kind filter: {kind_str}");
-                        true
-                    }
-                };
-                if !kind_matches {
-                    return false;
-                }
+    let team = team_config
+        .map(codebase_search::team::TeamConfig::load)
+        .transpose()?;
+    let shutdown = tokio_util::sync::CancellationToken::new();
+    tokio::spawn({
+        let shutdown = shutdown.clone();
+        async move {
+            if tokio::signal::ctrl_c().await.is_ok() {
+                shutdown.cancel();
             }
+        }
+    });
 
-            // Filter by extension if specified
-            if let Some(ref ext) = extension_filter {
-                if let Some(file_ext) = symbol.file_path.extension().and_then(|e| e.to_str()) {
-                    if file_ext != ext {
-                        return false;
-                    }
-                }
+    let addr = std::net::SocketAddr::new(host, port);
+    if let Some(team) = team {
+        for project in team.projects() {
+            status!("🔍 Hosting {} ({})", project.name, project.path.display());
+        }
+        status!("🌐 Serving team codebase search on http://{addr} (Ctrl-C to stop)");
+        status!("   Authorization: Bearer <token>");
+        status!("   GET  /search?query=...&project=...");
+        status!("   POST /index   {{\"project\": \"...\"}}");
+        status!("   GET  /status?project=...");
+        status!("   GET  /events[?project=...]");
+        return codebase_search::server::serve_team(addr, team, shutdown).await;
+    }
+    status!("🌐 Serving codebase search on http://{addr} (Ctrl-C to stop)");
+    status!("   GET  /search?query=...&directory=...");
+    status!("   POST /index   {{\"directory\": \"...\"}}");
+    status!("   GET  /status?directory=...");
+    status!("   GET  /events[?directory=...]");
+    codebase_search::server::serve(addr, shutdown).await
+}
+
+#[cfg(feature = "grpc")]
+async fn grpc_command(
+    directories: Vec<PathBuf>,
+    team_config: Option<PathBuf>,
+    host: std::net::IpAddr,
+    port: u16,
+) -> Result<()> {
+    let service = match team_config {
+        Some(path) => codebase_search::grpc::GrpcService::with_team(
+            codebase_search::team::TeamConfig::load(path)?,
+        ),
+        None => codebase_search::grpc::GrpcService::new(directories),
+    };
+    let shutdown = tokio_util::sync::CancellationToken::new();
+    tokio::spawn({
+        let shutdown = shutdown.clone();
+        async move {
+            if tokio::signal::ctrl_c().await.is_ok() {
+                shutdown.cancel();
             }
+        }
+    });
 
-            true
-        })
-        .collect();
+    let addr = std::net::SocketAddr::new(host, port);
+    for root in service.roots() {
+        status!("🔍 Hosting {}", root.display());
+    }
+    status!("🌐 Serving codebase search over gRPC on {addr} (Ctrl-C to stop)");
+    codebase_search::grpc::serve(addr, service, shutdown).await
+}
 
-    match format {
-        "json" => {
-            let json = serde_json::to_string_pretty(&filtered_symbols)?;
-            println!("{json}");
-        }
-        "pretty" => {
-            println!("=== Symbols in {} ===", directory.display());
-            print_symbols_pretty(&filtered_symbols);
+async fn daemon_command(directory: PathBuf, socket: Option<PathBuf>) -> Result<()> {
+    let directory = directory.canonicalize().unwrap_or(directory);
+    let daemon = codebase_search::daemon::Daemon::new(directory);
+    let shutdown = daemon.shutdown_token();
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            shutdown.cancel();
         }
-        "summary" => {
-            print_symbols_summary(&filtered_symbols, None);
+    });
+
+    match socket {
+        #[cfg(unix)]
+        Some(socket) => daemon.serve_unix(&socket).await,
+        #[cfg(not(unix))]
+        Some(_) => Err(anyhow::anyhow!(
+            "--socket needs Unix domain sockets; use stdio on this platform"
+        )),
+        None => daemon.serve_stdio().await,
+    }
+}
+
+async fn doctor_command(directory: Option<PathBuf>, format: OutputFormat) -> Result<()> {
+    use codebase_search::doctor::CheckStatus;
+    use codebase_search::doctor::run_checks;
+
+    let canonical_directory = directory.map(|directory| {
+        directory
+            .canonicalize()
+            .unwrap_or_else(|_| directory.clone())
+    });
+    if format == OutputFormat::Text {
+        match &canonical_directory {
+            Some(directory) => status!("🩺 Checking codebase-search for {}", directory.display()),
+            None => status!("🩺 Checking codebase-search"),
         }
-        _ => {
-            return Err(anyhow::anyhow!(
-                "Unsupported format: {format}. Use 'json', 'pretty', or 'summary'"
-            ));
+        status!();
+    }
+
+    let checks = run_checks(canonical_directory.as_deref()).await;
+    if format == OutputFormat::Text {
+        for check in &checks {
+            let icon = match check.status {
+                CheckStatus::Pass => "✅",
+                CheckStatus::Warn => "⚠️ ",
+                CheckStatus::Fail => "❌",
+                CheckStatus::Skip => "⏭️ ",
+            };
+            println!("{icon} {}: {}", check.name, check.detail);
+            if let Some(hint) = &check.hint {
+                status!("   💡 {hint}");
+            }
         }
+    } else {
+        print_json_items(&checks, format)?;
     }
 
+    let failed = checks
+        .iter()
+        .filter(|check| check.status == CheckStatus::Fail)
+        .count();
+    if failed > 0 {
+        return Err(anyhow::anyhow!("{failed} check(s) failed"));
+    }
     Ok(())
 }
 
-async fn chunk_codebase_command(
-    directory: PathBuf,
-    format: &str,
-    max_lines: usize,
-    min_lines: usize,
-    include_metadata: bool,
-    max_depth: usize,
+fn config_show_command(
+    directory: &Path,
+    config: IndexConfig,
+    embedding_settings: EmbeddingSettings,
+    qdrant_settings: QdrantSettings,
+    format: OutputFormat,
 ) -> Result<()> {
-    info!("Chunking codebase: {}", directory.display());
-
-    let chunking_options = ChunkingOptions {
-        max_lines_per_chunk: max_lines,
-        min_lines_per_chunk: min_lines,
-        include_metadata,
-        max_recursion_depth: max_depth,
+    // Fill in every default so the output shows what would actually be used
+    let embedding = EmbeddingConfig::from_settings(&embedding_settings);
+    let masked_key = format!("{}…", embedding.api_key.chars().take(4).collect::<String>());
+    let chunking = config.chunking_options();
+    let chat = codebase_search::explain::ChatConfig::from_settings(
+        &config.chat.clone().overridden_by(env_chat_settings()),
+    );
+    let effective = IndexConfig {
+        embedding: EmbeddingSettings {
+            provider: Some(embedding.provider),
+            api_url: Some(embedding.api_url),
+            api_key: Some(masked_key),
+            model: Some(embedding.model),
+            batch_size: Some(embedding.batch_size),
+            timeout_secs: Some(embedding.timeout_seconds),
+            query_requests_per_second: Some(embedding.query_requests_per_second),
+            query_cache_size: Some(embedding.query_cache_size),
+        },
+        qdrant: QdrantSettings {
+            url: Some(
+                qdrant_settings
+                    .url
+                    .unwrap_or_else(|| DEFAULT_QDRANT_URL.to_string()),
+            ),
+            managed: Some(qdrant_settings.managed.unwrap_or(false)),
+            binary: qdrant_settings.binary,
+        },
+        chat: ChatSettings {
+            model: Some(chat.model),
+            api_url: Some(chat.api_url),
+            api_key: chat
+                .api_key
+                .map(|key| format!("{}…", key.chars().take(4).collect::<String>())),
+            max_context_tokens: Some(chat.max_context_tokens),
+        },
+        chunking: ChunkingSettings {
+            max_lines: Some(chunking.max_lines_per_chunk),
+            min_lines: Some(chunking.min_lines_per_chunk),
+            include_metadata: Some(chunking.include_metadata),
+            max_depth: Some(chunking.max_recursion_depth),
+            tests: Some(chunking.tests),
+        },
+        ..config
     };
 
-    let embedded_chunks = chunk_codebase(&directory, chunking_options).await?;
-    let chunks: Vec<_> = embedded_chunks.into_iter().map(|ec| ec.chunk).collect();
+    if format != OutputFormat::Text {
+        return print_json(&effective, format);
+    }
 
-    match format {
-        "json" => {
-            let json = serde_json::to_string_pretty(&chunks)?;
-            println!("{json}");
-        }
-        "pretty" => {
-            println!("=== Chunks in {} ===", directory.display());
-            print_chunks_pretty(&chunks);
-        }
-        "summary" => {
-            print_chunks_summary(&chunks);
-        }
-        _ => {
-            return Err(anyhow::anyhow!(
-                "Unsupported format: {format}. Use 'json', 'pretty', or 'summary'"
-            ));
-        }
+    println!("# Layers, lowest precedence first (environment variables and flags override all):");
+    for path in IndexConfig::layer_paths(directory) {
+        let found = if path.is_file() { "" } else { " (not found)" };
+        println!("#   {}{found}", path.display());
+    }
+    println!();
+    print!("{}", toml::to_string_pretty(&effective)?);
+    Ok(())
+}
+
+fn config_init_command(directory: &Path, user: bool, force: bool) -> Result<()> {
+    use codebase_search::index_config::CONFIG_TEMPLATE;
+
+    let path = if user {
+        IndexConfig::user_file_path()
+            .ok_or_else(|| anyhow::anyhow!("Could not determine the user config directory"))?
+    } else {
+        IndexConfig::file_path(directory)
+    };
+    if path.exists() && !force {
+        return Err(anyhow::anyhow!(
+            "{} already exists; pass --force to overwrite it",
+            path.display()
+        ));
     }
 
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(&path, CONFIG_TEMPLATE)?;
+    status!("✅ Wrote {}", path.display());
     Ok(())
 }
 
-async fn index_codebase_command(directory: PathBuf) -> Result<()> {
-    // Canonicalize the directory path to convert relative paths to absolute paths
+fn state_show_command(directory: PathBuf, format: OutputFormat) -> Result<()> {
+    use codebase_search::file_state::summarize_state;
+
     let canonical_directory = directory
         .canonicalize()
         .unwrap_or_else(|_| directory.clone());
+    let summary = summarize_state(&canonical_directory)?;
+    if format != OutputFormat::Text {
+        return print_json(&summary, format);
+    }
 
-    println!("🔍 Analyzing codebase: {}", canonical_directory.display());
-    println!(
-        "📊 This will automatically detect and process only changed files for optimal performance."
-    );
+    println!("=== Index State ===");
+    println!("Codebase: {}", canonical_directory.display());
+    println!("State file: {}", summary.state_path.display());
+    println!("Schema version: {}", summary.version);
+    println!("Files tracked: {}", summary.tracked_files);
+    println!("Chunks tracked: {}", summary.tracked_chunks);
+    if summary.files_without_chunk_ids > 0 {
+        println!(
+            "Files without chunk IDs: {} (indexed by an older version)",
+            summary.files_without_chunk_ids
+        );
+    }
+    match summary.last_indexed_at {
+        Some(at) => println!("Last indexed: {}", at.format("%Y-%m-%d %H:%M:%S UTC")),
+        None => println!("Last indexed: unknown"),
+    }
+    match summary.index_params {
+        Some(params) => {
+            println!("Embedding: {} / {}", params.provider, params.model);
+            println!("Dimension: {}", params.dimension);
+            println!(
+                "Chunking: {}-{} lines, depth {}",
+                params.chunking.min_lines_per_chunk,
+                params.chunking.max_lines_per_chunk,
+                params.chunking.max_recursion_depth
+            );
+        }
+        None => println!("Embedding: unknown (recorded on the next index run)"),
+    }
+    if let Some(commit) = summary.git_commit {
+        println!("Git baseline: {commit}");
+    }
+    Ok(())
+}
 
-    // restore_session intelligently handles both initial indexing and incremental updates
-    restore_session(&canonical_directory).await?;
+async fn state_repair_command(directory: PathBuf) -> Result<()> {
+    use codebase_search::vector_db::repair_state;
 
-    println!("✅ Codebase indexed successfully into vector database!");
-    println!(
-        "🗂️  Collection available for: {}",
+    let canonical_directory = directory
+        .canonicalize()
+        .unwrap_or_else(|_| directory.clone());
+    status!(
+        "🔧 Repairing index state: {}",
         canonical_directory.display()
     );
-    println!("💡 Use 'search-codebase' command to query the indexed code.");
+
+    let repair = repair_state(&canonical_directory).await?;
+
+    println!("✅ State reconciled with the collection");
+    println!("   Orphaned points removed: {}", repair.orphaned_points);
+    println!("   Files re-indexed: {}", repair.reindexed_files.len());
+    for file_path in &repair.reindexed_files {
+        println!("      + {file_path}");
+    }
+    println!("   Missing files dropped: {}", repair.dropped_files.len());
+    for file_path in &repair.dropped_files {
+        println!("      - {file_path}");
+    }
+    println!("   Chunk IDs refreshed: {} files", repair.refreshed_files);
     Ok(())
 }
 
-async fn search_codebase_command(
-    query: String,
+fn feedback_command(
     directory: PathBuf,
-    limit: usize,
-    min_score: f32,
+    chunk_id: Option<String>,
+    rating: &str,
+    query: Option<String>,
+    summary: bool,
 ) -> Result<()> {
-    use codebase_search::retriever::search_codebase;
+    use codebase_search::feedback::FeedbackEntry;
+    use codebase_search::feedback::FeedbackRating;
+    use codebase_search::feedback::load_feedback;
+    use codebase_search::feedback::record_feedback;
+    use codebase_search::feedback::summarize_feedback;
 
-    // Canonicalize the directory path to convert relative paths to absolute paths
     let canonical_directory = directory
         .canonicalize()
         .unwrap_or_else(|_| directory.clone());
 
-    info!("Searching indexed codebase for query: {}", query);
-
-    println!("🔍 Searching codebase for: \"{query}\"");
-    println!("🎯 Limit: {limit}, Min score: {min_score:.2}");
-    println!();
-
-    match search_codebase(query, &canonical_directory, limit, min_score).await {
-        Ok(results) => {
-            if results.is_empty() {
-                println!("❌ No results found matching your query.");
-                println!("💡 Try:");
-                println!("   - Using different keywords");
-                println!("   - Lowering the minimum score (current: {min_score:.2})");
-                println!("   - Checking if the codebase is indexed with 'index-codebase' command");
-            } else {
-                println!("✅ Found {} results:", results.len());
-                println!();
-
-                for (i, result) in results.iter().enumerate() {
-                    print_search_result(i + 1, result);
-                    if i < results.len() - 1 {
-                        println!("{}", "─".repeat(80));
-                    }
-                }
-
-                println!();
-                println!(
-                    "🎯 Search completed. Showing {} results with score >= {:.2}",
-                    results.len(),
-                    min_score
-                );
-            }
+    if summary {
+        let entries = load_feedback(&canonical_directory)?;
+        if entries.is_empty() {
+            println!(
+                "❌ No feedback recorded for {}",
+                canonical_directory.display()
+            );
+            return Ok(());
         }
-        Err(e) => {
-            eprintln!("❌ Search failed: {e}");
-            eprintln!("💡 Make sure:");
-            eprintln!("   - Qdrant is running on localhost:6334");
-            eprintln!("   - The codebase is indexed (run 'index-codebase' first)");
-            eprintln!("   - The directory path is correct");
-            return Err(e);
+
+        let mut counts: Vec<_> = summarize_feedback(&entries).into_iter().collect();
+        counts.sort_by(|a, b| {
+            b.1.net_score()
+                .partial_cmp(&a.1.net_score())
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+
+        println!("=== Feedback Summary ===");
+        println!("Total judgements: {}", entries.len());
+        println!("Chunks rated: {}", counts.len());
+        println!();
+        for (chunk_id, count) in counts {
+            println!(
+                "   {chunk_id}  👍 {}  👎 {}  (net {:+.2})",
+                count.up,
+                count.down,
+                count.net_score()
+            );
         }
+        return Ok(());
     }
 
+    let chunk_id =
+        chunk_id.ok_or_else(|| anyhow::anyhow!("A CHUNK_ID is required to record feedback"))?;
+    let rating: FeedbackRating = rating.parse()?;
+
+    let mut entry = FeedbackEntry::new(chunk_id, rating, query);
+    entry.source = Some("cli".to_string());
+    record_feedback(&canonical_directory, &entry)?;
+
+    let emoji = match rating {
+        FeedbackRating::Up => "👍",
+        FeedbackRating::Down => "👎",
+    };
+    status!("{emoji} Recorded feedback for chunk {}", entry.chunk_id);
     Ok(())
 }
 
@@ -430,6 +2579,12 @@ fn print_symbols_pretty(symbols: &[codebase_search::symbol::Symbol]) {
                 SymbolKind::Constant => "🔒",
                 SymbolKind::Variable => "📊",
                 SymbolKind::Type => "🏷️",
+                SymbolKind::Macro => "🧩",
+                SymbolKind::Property => "🔑",
+                SymbolKind::Table => "🗃️",
+                SymbolKind::View => "🪟",
+                SymbolKind::Resource => "☁️",
+                SymbolKind::Import => "📥",
             };
 
             let context_info = symbol
@@ -439,9 +2594,10 @@ fn print_symbols_pretty(symbols: &[codebase_search::symbol::Symbol]) {
                 .unwrap_or_default();
 
             println!(
-                "   {kind_emoji} {} {:?} at {}:{}-{}:{}{context_info}",
+                "   {kind_emoji} {} {:?}{} at {}:{}-{}:{}{context_info}",
                 symbol.name,
                 symbol.kind,
+                modifiers_label(&symbol.modifiers),
                 symbol.start_line,
                 symbol.start_column,
                 symbol.end_line,
@@ -451,6 +2607,22 @@ fn print_symbols_pretty(symbols: &[codebase_search::symbol::Symbol]) {
     }
 }
 
+/// Visibility and modifiers as a ` [public async]` suffix, empty when none are known
+fn modifiers_label(modifiers: &SymbolModifiers) -> String {
+    let labels: Vec<&str> = modifiers
+        .visibility
+        .map(|visibility| visibility.as_str())
+        .into_iter()
+        .chain(modifiers.is_async.then_some("async"))
+        .chain(modifiers.is_static.then_some("static"))
+        .collect();
+    if labels.is_empty() {
+        String::new()
+    } else {
+        format!(" [{}]", labels.join(" "))
+    }
+}
+
 fn print_chunks_pretty(chunks: &[codebase_search::chunker::CodeChunk]) {
     use std::collections::HashMap;
 
@@ -485,17 +2657,20 @@ fn print_chunks_pretty(chunks: &[codebase_search::chunker::CodeChunk]) {
                 _ => "📄",
             };
 
-            let content_preview = if chunk.content.len() > 100 {
+            let content_preview = if !chunk.signature.is_empty() {
+                chunk.signature.clone()
+            } else if chunk.content.len() > 100 {
                 format!("{}...", &chunk.content[..100])
             } else {
                 chunk.content.clone()
             };
 
             println!(
-                "   {} {} ({}) at {}:{} (depth: {}, {} lines) - {}",
+                "   {} {} ({}){} at {}:{} (depth: {}, {} lines) - {}",
                 kind_emoji,
                 chunk.symbol_name,
                 chunk.symbol_kind,
+                modifiers_label(&chunk.modifiers),
                 chunk.start_line,
                 chunk.end_line,
                 chunk.chunk_metadata.chunk_depth,
@@ -609,14 +2784,21 @@ fn print_symbols_summary(
 
 fn show_supported_languages() {
     println!("=== Supported Languages ===");
-    println!("🦀 Rust (.rs)");
-    println!("   - Functions, Methods, Structs, Enums, Traits, Impls, Modules, Constants");
-
-    println!("🐍 Python (.py)");
-    println!("   - Functions, Methods, Classes");
-
-    println!("🐹 Go (.go)");
-    println!("   - Functions, Methods, Types (structs/interfaces), Constants, Variables");
+    for entry in LanguageRegistry::global().languages() {
+        let extensions: Vec<String> = entry
+            .language
+            .extensions()
+            .iter()
+            .map(|extension| format!(".{extension}"))
+            .collect();
+        println!(
+            "{} {} ({})",
+            entry.icon,
+            entry.display_name,
+            extensions.join(", ")
+        );
+        println!("   - {}", entry.summary);
+    }
 
     println!("\n=== Usage Examples ===");
     println!("Parse a single Rust file:");
@@ -637,13 +2819,59 @@ fn show_supported_languages() {
     println!("\nIndex a codebase into vector database (with smart incremental updates):");
     println!("  codebase-search index-codebase .");
 
+    println!("\nKeep the index fresh while you edit:");
+    println!("  codebase-search watch .");
+
     println!("\nSearch the indexed codebase:");
     println!("  codebase-search search-codebase \"authentication logic\" . --limit 5");
 
+    println!("\nSearch symbol names without the embedding API:");
+    println!("  codebase-search search-codebase parse . --metadata-only --kind function");
+
+    println!("\nRate a search result (use the 🆔 shown in results):");
+    println!(
+        "  codebase-search feedback . <CHUNK_ID> --rating up --query \"authentication logic\""
+    );
+
     println!("\nOutput as JSON:");
     println!("  codebase-search parse-file src/lib.rs --format json");
 }
 
+/// Whether text results should be printed as plain `path:line:score:symbol` lines: stdout
+/// is piped (into fzf, grep, ...) or `NO_COLOR` asks for undecorated output
+fn plain_output() -> bool {
+    use std::io::IsTerminal;
+
+    !std::io::stdout().is_terminal() || std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty())
+}
+
+/// One `path:line:score:symbol` line per result, with paths relative to the directory as
+/// it was given so they open from the current directory
+fn print_plain_results(directory: &Path, results: &[codebase_search::retriever::SearchResult]) {
+    for result in results {
+        println!("{}", plain_result_line(directory, result));
+    }
+}
+
+fn plain_result_line(
+    directory: &Path,
+    result: &codebase_search::retriever::SearchResult,
+) -> String {
+    let chunk = &result.chunk;
+    let path = if directory == Path::new(".") {
+        chunk.file_path.clone()
+    } else {
+        directory.join(&chunk.file_path)
+    };
+    format!(
+        "{}:{}:{:.3}:{}",
+        path.display(),
+        chunk.start_line,
+        result.score,
+        chunk.symbol_name
+    )
+}
+
 fn print_search_result(index: usize, result: &codebase_search::retriever::SearchResult) {
     let chunk = &result.chunk;
 
@@ -665,8 +2893,13 @@ fn print_search_result(index: usize, result: &codebase_search::retriever::Search
 
     // Header with result index, symbol info, and score
     println!(
-        "{}. {} {} {} (Score: {:.3})",
-        index, kind_emoji, chunk.symbol_kind, chunk.symbol_name, result.score
+        "{}. {} {} {}{} (Score: {:.3})",
+        index,
+        kind_emoji,
+        chunk.symbol_kind,
+        chunk.symbol_name,
+        modifiers_label(&chunk.modifiers),
+        result.score
     );
 
     // File and location info
@@ -682,6 +2915,11 @@ fn print_search_result(index: usize, result: &codebase_search::retriever::Search
         println!("   🗂️  Context: {context}");
     }
 
+    // Chunk ID for relevance feedback
+    if !result.id.is_empty() {
+        println!("   🆔 {}", result.id);
+    }
+
     // Additional metadata
     println!(
         "   📊 Chunk: depth {}, {} lines{}",
@@ -694,6 +2932,17 @@ fn print_search_result(index: usize, result: &codebase_search::retriever::Search
         }
     );
 
+    // Prefer the declaration; chunks indexed before signatures were recorded fall back
+    // to the first few lines of content
+    if !chunk.signature.is_empty() {
+        println!("   📝 Signature:");
+        for line in chunk.signature.lines() {
+            println!("      {line}");
+        }
+        println!();
+        return;
+    }
+
     // Content preview (limit to first few lines and max characters)
     let content_lines: Vec<&str> = chunk.content.lines().collect();
     let preview_lines = if content_lines.len() > 5 {
@@ -721,3 +2970,179 @@ fn print_search_result(index: usize, result: &codebase_search::retriever::Search
 
     println!();
 }
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::expect_used)]
+    use super::*;
+
+    fn parse(args: &[&str]) -> Cli {
+        Cli::try_parse_from(std::iter::once("codebase-search").chain(args.iter().copied()))
+            .expect("Failed to parse arguments")
+    }
+
+    #[test]
+    fn the_output_format_applies_to_every_subcommand() {
+        assert_eq!(parse(&["status", "."]).format, OutputFormat::Text);
+        assert_eq!(
+            parse(&["status", ".", "--format", "ndjson"]).format,
+            OutputFormat::Ndjson
+        );
+        assert_eq!(
+            parse(&["-f", "json", "list-collections"]).format,
+            OutputFormat::Json
+        );
+        assert!(Cli::try_parse_from(["codebase-search", "status", ".", "-f", "xml"]).is_err());
+    }
+
+    #[test]
+    fn ndjson_prints_one_compact_object_per_item() {
+        let items = [
+            serde_json::json!({"name": "a"}),
+            serde_json::json!({"name": "b"}),
+        ];
+
+        let lines = json_item_lines(&items, OutputFormat::Ndjson).expect("Failed to render");
+        assert_eq!(lines, [r#"{"name":"a"}"#, r#"{"name":"b"}"#]);
+        assert!(
+            json_item_lines::<serde_json::Value>(&[], OutputFormat::Ndjson)
+                .expect("Failed to render")
+                .is_empty()
+        );
+
+        let lines = json_item_lines(&items, OutputFormat::Json).expect("Failed to render");
+        assert_eq!(lines.len(), 1);
+        let parsed: serde_json::Value = serde_json::from_str(&lines[0]).expect("Invalid JSON");
+        assert_eq!(parsed, serde_json::json!([{"name": "a"}, {"name": "b"}]));
+
+        let document = json_document(&items[0], OutputFormat::Ndjson).expect("Failed to render");
+        assert_eq!(document, r#"{"name":"a"}"#);
+    }
+
+    #[test]
+    fn quiet_and_log_format_are_global_flags() {
+        let cli = parse(&["status", "."]);
+        assert!(!cli.quiet);
+        assert_eq!(cli.log_format, LogFormat::Pretty);
+        assert_eq!(cli.log_level(), tracing::Level::INFO);
+
+        let cli = parse(&["status", ".", "--quiet", "--log-format", "json"]);
+        assert!(cli.quiet);
+        assert_eq!(cli.log_format, LogFormat::Json);
+        assert_eq!(cli.log_level(), tracing::Level::WARN);
+
+        assert_eq!(
+            parse(&["-v", "status", ".", "--quiet"]).log_level(),
+            tracing::Level::DEBUG
+        );
+    }
+
+    #[test]
+    fn index_codebase_rebuilds_only_when_forced() {
+        let force = |args: &[&str]| match parse(args).command {
+            Commands::IndexCodebase { force, .. } => force,
+            _ => panic!("Expected index-codebase"),
+        };
+        assert!(!force(&["index-codebase", "."]));
+        assert!(force(&["index-codebase", ".", "--force"]));
+    }
+
+    #[test]
+    fn failures_are_classified_into_exit_codes() {
+        let kind = |error: anyhow::Error| FailureKind::of(&error);
+
+        // An explicit kind anywhere in the chain wins over the message
+        let tagged = anyhow::anyhow!("Failed to connect to Qdrant").context(FailureKind::Config);
+        assert_eq!(
+            kind(tagged.context("while loading settings")),
+            FailureKind::Config
+        );
+        let wrapped = anyhow::Error::new(FailureKind::NoResults).context("search failed");
+        assert_eq!(kind(wrapped), FailureKind::NoResults);
+
+        assert_eq!(
+            kind(anyhow::anyhow!(
+                "Failed to connect to http://localhost:6334"
+            )),
+            FailureKind::QdrantUnreachable
+        );
+        assert_eq!(
+            kind(anyhow::anyhow!(
+                "Embedding API request failed with status: 401 Unauthorized"
+            )),
+            FailureKind::EmbeddingAuth
+        );
+        assert_eq!(
+            kind(anyhow::anyhow!(
+                "Failed to load state file: missing. Run 'index-codebase' first"
+            )),
+            FailureKind::NotIndexed
+        );
+        assert_eq!(kind(anyhow::anyhow!("disk full")), FailureKind::Other);
+
+        let codes = [
+            FailureKind::Other,
+            FailureKind::Config,
+            FailureKind::QdrantUnreachable,
+            FailureKind::EmbeddingAuth,
+            FailureKind::NotIndexed,
+            FailureKind::NoResults,
+        ]
+        .map(FailureKind::exit_code);
+        assert_eq!(codes, [1, 3, 4, 5, 6, 7]);
+    }
+
+    #[test]
+    fn json_failures_carry_the_kind_and_causes() {
+        let error = anyhow::anyhow!("connection refused").context("Failed to connect to Qdrant");
+        let envelope = failure_envelope(&error, FailureKind::QdrantUnreachable);
+        assert_eq!(
+            envelope,
+            serde_json::json!({
+                "error": {
+                    "kind": "qdrant_unreachable",
+                    "exit_code": 4,
+                    "message": "Failed to connect to Qdrant",
+                    "causes": ["connection refused"],
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn plain_results_open_from_the_current_directory() {
+        let result = codebase_search::retriever::SearchResult {
+            id: "point".to_string(),
+            chunk: codebase_search::chunker::CodeChunk {
+                content: "fn parse() {}".to_string(),
+                file_path: PathBuf::from("src/parser.rs"),
+                start_line: 42,
+                end_line: 42,
+                symbol_name: "parse".to_string(),
+                symbol_kind: "Function".to_string(),
+                context: None,
+                signature: String::new(),
+                modifiers: Default::default(),
+                metrics: Default::default(),
+                is_test: false,
+                symbol_id: String::new(),
+                chunk_metadata: codebase_search::chunker::ChunkMetadata {
+                    is_split: false,
+                    original_size_lines: 1,
+                    chunk_depth: 0,
+                    is_container: false,
+                },
+            },
+            score: 0.87654,
+        };
+
+        assert_eq!(
+            plain_result_line(Path::new("."), &result),
+            "src/parser.rs:42:0.877:parse"
+        );
+        assert_eq!(
+            plain_result_line(Path::new("../repo"), &result),
+            "../repo/src/parser.rs:42:0.877:parse"
+        );
+    }
+}
@@ -0,0 +1,365 @@
+//! A local Qdrant started on first use, so no Qdrant server has to be set up
+//! With `[qdrant] managed = true` (or `--managed-qdrant`) the CLI runs `qdrant` from
+//! `[qdrant] binary` or PATH, downloading release `QDRANT_VERSION` into the user cache
+//! when neither has one. Its storage lives in the project's `.codex/qdrant`, and it is
+//! stopped when the `ManagedQdrant` that started it is dropped. Other processes using
+//! the same data directory meanwhile connect to the running instance.
+
+use std::fs::File;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process::Child;
+use std::process::Command;
+use std::process::Stdio;
+use std::time::Duration;
+use std::time::Instant;
+
+use qdrant_client::Qdrant;
+use serde::Deserialize;
+use serde::Serialize;
+use sha2::Digest;
+use sha2::Sha256;
+use tracing::info;
+use tracing::warn;
+
+use crate::file_state::state_cache_dir;
+
+/// Qdrant release downloaded when no binary is configured or on PATH
+pub const QDRANT_VERSION: &str = "1.15.0";
+
+/// Data directory of a project's managed Qdrant, relative to the project root
+pub const MANAGED_QDRANT_DIR: &str = ".codex/qdrant";
+
+/// Records the running instance in the data directory, for other processes to reuse
+const INSTANCE_FILE: &str = "instance.json";
+
+/// Longest to wait for a started Qdrant to answer health checks
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Longest to wait for Qdrant to flush and exit before killing it
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Instance {
+    pid: u32,
+    grpc_port: u16,
+}
+
+/// A running managed Qdrant
+/// Dropping the handle that started the process shuts it down.
+#[derive(Debug)]
+pub struct ManagedQdrant {
+    url: String,
+    data_dir: PathBuf,
+    /// None when another process started the instance
+    child: Option<Child>,
+}
+
+/// Data directory for the managed Qdrant of `root_path`, or a per-user one for
+/// commands that aren't tied to a project
+pub fn managed_data_dir(root_path: Option<&Path>) -> Result<PathBuf, anyhow::Error> {
+    match root_path {
+        Some(root_path) => Ok(root_path.join(MANAGED_QDRANT_DIR)),
+        None => Ok(state_cache_dir()?.join("qdrant-data")),
+    }
+}
+
+impl ManagedQdrant {
+    /// Connect to the instance running on `data_dir`, or start one
+    /// `binary` overrides the lookup on PATH and the download.
+    pub async fn start(data_dir: &Path, binary: Option<&Path>) -> Result<Self, anyhow::Error> {
+        std::fs::create_dir_all(data_dir)
+            .map_err(|e| anyhow::anyhow!("Failed to create {}: {e}", data_dir.display()))?;
+        // Qdrant runs from the data directory, so its storage paths must be absolute
+        let data_dir = &data_dir.canonicalize()?;
+        let instance_path = data_dir.join(INSTANCE_FILE);
+        if let Some(instance) = read_instance(&instance_path) {
+            let url = grpc_url(instance.grpc_port);
+            if is_healthy(&url).await {
+                info!("Using managed Qdrant at {url} (pid {})", instance.pid);
+                return Ok(Self {
+                    url,
+                    data_dir: data_dir.to_path_buf(),
+                    child: None,
+                });
+            }
+        }
+
+        let binary = match binary {
+            Some(binary) => binary.to_path_buf(),
+            None => find_or_download_binary().await?,
+        };
+        let grpc_port = free_port()?;
+        let http_port = free_port()?;
+        let log_path = data_dir.join("qdrant.log");
+        let log = File::create(&log_path)
+            .map_err(|e| anyhow::anyhow!("Failed to create {}: {e}", log_path.display()))?;
+
+        info!(
+            "Starting managed Qdrant from {} on port {grpc_port}",
+            binary.display()
+        );
+        let child = Command::new(&binary)
+            .current_dir(data_dir)
+            .env("QDRANT__STORAGE__STORAGE_PATH", data_dir.join("storage"))
+            .env(
+                "QDRANT__STORAGE__SNAPSHOTS_PATH",
+                data_dir.join("snapshots"),
+            )
+            .env("QDRANT__SERVICE__HOST", "127.0.0.1")
+            .env("QDRANT__SERVICE__GRPC_PORT", grpc_port.to_string())
+            .env("QDRANT__SERVICE__HTTP_PORT", http_port.to_string())
+            .env("QDRANT__TELEMETRY_DISABLED", "true")
+            .stdin(Stdio::null())
+            .stdout(log.try_clone()?)
+            .stderr(log)
+            .spawn()
+            .map_err(|e| anyhow::anyhow!("Failed to start {}: {e}", binary.display()))?;
+        let instance = Instance {
+            pid: child.id(),
+            grpc_port,
+        };
+        let mut managed = Self {
+            url: grpc_url(grpc_port),
+            data_dir: data_dir.to_path_buf(),
+            child: Some(child),
+        };
+        std::fs::write(&instance_path, serde_json::to_string(&instance)?)?;
+
+        let deadline = Instant::now() + STARTUP_TIMEOUT;
+        loop {
+            let exited = match managed.child.as_mut() {
+                Some(child) => child.try_wait()?,
+                None => None,
+            };
+            if let Some(status) = exited {
+                anyhow::bail!(
+                    "Managed Qdrant exited with {status}; see {}",
+                    log_path.display()
+                );
+            }
+            if is_healthy(&managed.url).await {
+                return Ok(managed);
+            }
+            if Instant::now() > deadline {
+                anyhow::bail!(
+                    "Managed Qdrant didn't become ready within {}s; see {}",
+                    STARTUP_TIMEOUT.as_secs(),
+                    log_path.display()
+                );
+            }
+            tokio::time::sleep(Duration::from_millis(250)).await;
+        }
+    }
+
+    /// gRPC URL of the instance, for `QdrantSettings::url`
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Whether this handle started the instance, and so stops it when dropped
+    pub fn is_owner(&self) -> bool {
+        self.child.is_some()
+    }
+}
+
+impl Drop for ManagedQdrant {
+    fn drop(&mut self) {
+        let Some(mut child) = self.child.take() else {
+            return;
+        };
+        info!("Stopping managed Qdrant");
+        terminate(&mut child);
+        let deadline = Instant::now() + SHUTDOWN_TIMEOUT;
+        loop {
+            match child.try_wait() {
+                Ok(Some(_)) => break,
+                Ok(None) if Instant::now() < deadline => {
+                    std::thread::sleep(Duration::from_millis(100));
+                }
+                _ => {
+                    warn!("Managed Qdrant didn't stop in time, killing it");
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    break;
+                }
+            }
+        }
+        let _ = std::fs::remove_file(self.data_dir.join(INSTANCE_FILE));
+    }
+}
+
+/// Ask Qdrant to flush its storage and exit
+#[cfg(unix)]
+fn terminate(child: &mut Child) {
+    // SAFETY: sending a signal to our own child process has no memory-safety effects
+    let result = unsafe { libc::kill(child.id() as libc::pid_t, libc::SIGTERM) };
+    if result != 0 {
+        let _ = child.kill();
+    }
+}
+
+#[cfg(not(unix))]
+fn terminate(child: &mut Child) {
+    let _ = child.kill();
+}
+
+fn grpc_url(port: u16) -> String {
+    format!("http://127.0.0.1:{port}")
+}
+
+fn read_instance(path: &Path) -> Option<Instance> {
+    let content = std::fs::read_to_string(path).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+async fn is_healthy(url: &str) -> bool {
+    let Ok(client) = Qdrant::from_url(url)
+        .timeout(Duration::from_secs(2))
+        .connect_timeout(Duration::from_secs(1))
+        .skip_compatibility_check()
+        .build()
+    else {
+        return false;
+    };
+    client.health_check().await.is_ok()
+}
+
+/// A port that was free a moment ago
+fn free_port() -> Result<u16, anyhow::Error> {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+    Ok(listener.local_addr()?.port())
+}
+
+/// `qdrant` on PATH, else the cached download of `QDRANT_VERSION`
+async fn find_or_download_binary() -> Result<PathBuf, anyhow::Error> {
+    let name = if cfg!(windows) {
+        "qdrant.exe"
+    } else {
+        "qdrant"
+    };
+    let on_path = std::env::var_os("PATH").and_then(|paths| {
+        std::env::split_paths(&paths)
+            .map(|dir| dir.join(name))
+            .find(|path| path.is_file())
+    });
+    if let Some(path) = on_path {
+        return Ok(path);
+    }
+
+    let cached = state_cache_dir()?
+        .join(format!("qdrant-{QDRANT_VERSION}"))
+        .join(name);
+    if !cached.is_file() {
+        download_release(&cached).await?;
+    }
+    Ok(cached)
+}
+
+/// Release archive for this platform and the SHA-256 it is pinned to
+/// The digests belong to the `QDRANT_VERSION` assets and change with it; an empty one
+/// has not been pinned yet, so no download can pass verification against it.
+fn release_asset() -> Result<(&'static str, &'static str), anyhow::Error> {
+    match (std::env::consts::OS, std::env::consts::ARCH) {
+        ("linux", "x86_64") => Ok(("qdrant-x86_64-unknown-linux-musl.tar.gz", "")),
+        ("linux", "aarch64") => Ok(("qdrant-aarch64-unknown-linux-musl.tar.gz", "")),
+        ("macos", "x86_64") => Ok(("qdrant-x86_64-apple-darwin.tar.gz", "")),
+        ("macos", "aarch64") => Ok(("qdrant-aarch64-apple-darwin.tar.gz", "")),
+        (os, arch) => Err(anyhow::anyhow!(
+            "No Qdrant download for {os}/{arch}; put qdrant on PATH or set [qdrant] binary"
+        )),
+    }
+}
+
+/// Check a downloaded archive against its pinned SHA-256
+fn verify_sha256(archive: &[u8], expected: &str, url: &str) -> Result<(), anyhow::Error> {
+    if expected.is_empty() {
+        return Err(anyhow::anyhow!(
+            "No checksum is pinned for {url}; put qdrant on PATH or set [qdrant] binary"
+        ));
+    }
+    let actual = format!("{:x}", Sha256::digest(archive));
+    if !actual.eq_ignore_ascii_case(expected) {
+        return Err(anyhow::anyhow!(
+            "Checksum mismatch for {url}: expected SHA-256 {expected}, got {actual}"
+        ));
+    }
+    Ok(())
+}
+
+/// Download the `QDRANT_VERSION` release and unpack its binary to `target`
+/// The archive is checked against its pinned SHA-256 before anything is unpacked.
+async fn download_release(target: &Path) -> Result<(), anyhow::Error> {
+    let (asset, sha256) = release_asset()?;
+    let url =
+        format!("https://github.com/qdrant/qdrant/releases/download/v{QDRANT_VERSION}/{asset}");
+    info!("Downloading Qdrant {QDRANT_VERSION} from {url}");
+    let archive = reqwest::get(&url)
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| anyhow::anyhow!("Failed to download {url}: {e}"))?
+        .bytes()
+        .await?;
+    verify_sha256(&archive, sha256, &url)?;
+
+    let target = target.to_path_buf();
+    tokio::task::spawn_blocking(move || {
+        if let Some(dir) = target.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        // Unpacked beside the target and renamed, so an interrupted download never
+        // leaves a truncated binary behind
+        let partial = target.with_extension("partial");
+        let mut entries = tar::Archive::new(flate2::read::GzDecoder::new(archive.as_ref()));
+        for entry in entries.entries()? {
+            let mut entry = entry?;
+            if entry.path()?.file_name() == target.file_name() {
+                entry.unpack(&partial)?;
+                std::fs::rename(&partial, &target)?;
+                return Ok(());
+            }
+        }
+        Err(anyhow::anyhow!("{url} contains no qdrant binary"))
+    })
+    .await?
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::expect_used)]
+    use super::*;
+    use tempfile::TempDir;
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn start_reports_a_binary_that_exits() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let data_dir = temp_dir.path().join(MANAGED_QDRANT_DIR);
+
+        let error = ManagedQdrant::start(&data_dir, Some(Path::new("false")))
+            .await
+            .expect_err("A binary that exits can't be a running Qdrant");
+        assert!(error.to_string().contains("qdrant.log"), "{error}");
+        assert!(data_dir.join("qdrant.log").is_file());
+    }
+
+    #[test]
+    fn downloads_must_match_the_pinned_sha256() {
+        let url = "https://example.com/qdrant.tar.gz";
+        // SHA-256 of "abc"
+        let digest = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+
+        verify_sha256(b"abc", digest, url).expect("Matching archive should verify");
+        verify_sha256(b"abc", &digest.to_uppercase(), url)
+            .expect("Digests should compare case-insensitively");
+
+        let error = verify_sha256(b"abd", digest, url).expect_err("A changed archive must fail");
+        assert!(error.to_string().contains("Checksum mismatch"), "{error}");
+
+        let error = verify_sha256(b"abc", "", url).expect_err("An unpinned asset must fail");
+        assert!(
+            error.to_string().contains("No checksum is pinned"),
+            "{error}"
+        );
+    }
+}
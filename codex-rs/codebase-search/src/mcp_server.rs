@@ -0,0 +1,585 @@
+use std::collections::HashSet;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::PoisonError;
+
+use mcp_types::CallToolRequestParams;
+use mcp_types::CallToolResult;
+use mcp_types::ContentBlock;
+use mcp_types::Implementation;
+use mcp_types::InitializeRequestParams;
+use mcp_types::InitializeResult;
+use mcp_types::JSONRPC_VERSION;
+use mcp_types::JSONRPCError;
+use mcp_types::JSONRPCErrorError;
+use mcp_types::JSONRPCMessage;
+use mcp_types::JSONRPCRequest;
+use mcp_types::JSONRPCResponse;
+use mcp_types::ListToolsResult;
+use mcp_types::ServerCapabilities;
+use mcp_types::ServerCapabilitiesTools;
+use mcp_types::TextContent;
+use mcp_types::Tool;
+use mcp_types::ToolAnnotations;
+use mcp_types::ToolInputSchema;
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use serde_json::json;
+use tokio::io::AsyncBufReadExt;
+use tokio::io::AsyncWriteExt;
+use tokio::io::BufReader;
+use tokio::sync::mpsc;
+use tracing::debug;
+use tracing::info;
+use tracing::warn;
+
+use crate::retriever::SearchFilter;
+use crate::retriever::SearchResult;
+use crate::retriever::search_codebase_filtered;
+use crate::vector_db::IndexStatus;
+use crate::vector_db::index_status;
+use crate::vector_db::init_session;
+use crate::vector_db::restore_session;
+
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+/// Responses waiting to be written to stdout
+const OUTGOING_CAPACITY: usize = 128;
+
+/// Lines of each result shown in `search_code` text output
+const MAX_SNIPPET_LINES: usize = 40;
+
+/// Arguments of the `search_code` tool
+#[derive(Debug, Clone, Deserialize)]
+struct SearchCodeArgs {
+    query: String,
+    directory: Option<PathBuf>,
+    #[serde(default = "default_limit")]
+    limit: usize,
+    #[serde(default = "default_min_score")]
+    min_score: f32,
+    #[serde(flatten)]
+    filter: SearchFilter,
+}
+
+fn default_limit() -> usize {
+    10
+}
+
+fn default_min_score() -> f32 {
+    0.3
+}
+
+/// Arguments of the `index_codebase` tool
+#[derive(Debug, Clone, Deserialize)]
+struct IndexCodebaseArgs {
+    directory: Option<PathBuf>,
+    #[serde(default)]
+    force: bool,
+}
+
+/// Arguments of the `index_status` tool
+#[derive(Debug, Clone, Deserialize)]
+struct IndexStatusArgs {
+    directory: Option<PathBuf>,
+}
+
+/// Serves `search_code`, `index_codebase` and `index_status` to an MCP client
+/// Requests are handled concurrently, so a search can run while a codebase is indexing.
+#[derive(Debug, Clone)]
+pub struct McpServer {
+    /// Codebase used by tool calls that don't name one; relative paths are resolved against it
+    default_directory: PathBuf,
+    /// Codebases with an index run in flight; a second `index_codebase` for one is rejected
+    indexing: Arc<Mutex<HashSet<PathBuf>>>,
+}
+
+/// Removes a codebase from `McpServer::indexing` when its index run ends
+struct IndexingGuard {
+    indexing: Arc<Mutex<HashSet<PathBuf>>>,
+    directory: PathBuf,
+}
+
+impl Drop for IndexingGuard {
+    fn drop(&mut self) {
+        self.indexing
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(&self.directory);
+    }
+}
+
+impl McpServer {
+    pub fn new(default_directory: PathBuf) -> Self {
+        Self {
+            default_directory,
+            indexing: Arc::default(),
+        }
+    }
+
+    /// Serve newline-delimited JSON-RPC on stdin and stdout until stdin closes
+    pub async fn serve_stdio(self) -> Result<(), anyhow::Error> {
+        let (tx, mut rx) = mpsc::channel::<JSONRPCMessage>(OUTGOING_CAPACITY);
+        let writer = tokio::spawn(async move {
+            let mut stdout = tokio::io::stdout();
+            while let Some(message) = rx.recv().await {
+                let mut line = serde_json::to_string(&message)?;
+                line.push('\n');
+                stdout.write_all(line.as_bytes()).await?;
+                stdout.flush().await?;
+            }
+            Ok::<(), anyhow::Error>(())
+        });
+
+        info!(
+            "Serving codebase search over MCP for {}",
+            self.default_directory.display()
+        );
+        let mut lines = BufReader::new(tokio::io::stdin()).lines();
+        while let Some(line) = lines.next_line().await? {
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str::<JSONRPCMessage>(&line) {
+                Ok(JSONRPCMessage::Request(request)) => {
+                    let server = self.clone();
+                    let tx = tx.clone();
+                    tokio::spawn(async move {
+                        let _ = tx.send(server.handle_request(request).await).await;
+                    });
+                }
+                // The server sends no requests, so the only messages that aren't
+                // requests are notifications like `notifications/initialized`
+                Ok(message) => debug!("Ignoring MCP message: {message:?}"),
+                Err(e) => warn!("Ignoring malformed MCP message: {e}"),
+            }
+        }
+
+        debug!("stdin closed, stopping the MCP server");
+        drop(tx);
+        writer.await?
+    }
+
+    /// Answer one JSON-RPC request with a response or an error
+    pub async fn handle_request(&self, request: JSONRPCRequest) -> JSONRPCMessage {
+        let JSONRPCRequest {
+            id, method, params, ..
+        } = request;
+        let result = match method.as_str() {
+            "initialize" => {
+                parse_params::<InitializeRequestParams>(params).map(|params| initialize(&params))
+            }
+            "ping" => Ok(json!({})),
+            "tools/list" => Ok(list_tools()),
+            "tools/call" => match parse_params::<CallToolRequestParams>(params) {
+                Ok(params) => self.call_tool(params).await,
+                Err(error) => Err(error),
+            },
+            _ => Err(JSONRPCErrorError {
+                code: METHOD_NOT_FOUND,
+                data: None,
+                message: format!("Unknown method: {method}"),
+            }),
+        };
+        match result {
+            Ok(result) => JSONRPCMessage::Response(JSONRPCResponse {
+                id,
+                jsonrpc: JSONRPC_VERSION.to_string(),
+                result,
+            }),
+            Err(error) => JSONRPCMessage::Error(JSONRPCError {
+                error,
+                id,
+                jsonrpc: JSONRPC_VERSION.to_string(),
+            }),
+        }
+    }
+
+    /// Run a tool; failures of the tool itself are reported in the result with `isError`
+    async fn call_tool(
+        &self,
+        params: CallToolRequestParams,
+    ) -> Result<serde_json::Value, JSONRPCErrorError> {
+        let arguments = params.arguments;
+        let outcome = match params.name.as_str() {
+            "search_code" => self.search_code(parse_params(arguments)?).await,
+            "index_codebase" => self.index_codebase(parse_params(arguments)?).await,
+            "index_status" => self.index_status(parse_params(arguments)?).await,
+            name => {
+                return Err(JSONRPCErrorError {
+                    code: INVALID_PARAMS,
+                    data: None,
+                    message: format!("Unknown tool: {name}"),
+                });
+            }
+        };
+        let result = match outcome {
+            Ok((text, structured)) => CallToolResult {
+                content: vec![text_content(text)],
+                is_error: None,
+                structured_content: Some(structured),
+            },
+            Err(e) => CallToolResult {
+                content: vec![text_content(format!("{e:#}"))],
+                is_error: Some(true),
+                structured_content: None,
+            },
+        };
+        Ok(serde_json::to_value(result).unwrap_or_default())
+    }
+
+    async fn search_code(
+        &self,
+        args: SearchCodeArgs,
+    ) -> Result<(String, serde_json::Value), anyhow::Error> {
+        let directory = self.resolve_directory(args.directory.as_deref());
+        let results = search_codebase_filtered(
+            args.query.clone(),
+            &directory,
+            args.limit,
+            args.min_score,
+            &args.filter,
+        )
+        .await?;
+        let text = format_results(&args.query, &results);
+        Ok((text, json!({ "results": results })))
+    }
+
+    async fn index_codebase(
+        &self,
+        args: IndexCodebaseArgs,
+    ) -> Result<(String, serde_json::Value), anyhow::Error> {
+        let directory = self.resolve_directory(args.directory.as_deref());
+        let inserted = self
+            .indexing
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(directory.clone());
+        if !inserted {
+            return Err(anyhow::anyhow!(
+                "{} is already being indexed",
+                directory.display()
+            ));
+        }
+        let _guard = IndexingGuard {
+            indexing: self.indexing.clone(),
+            directory: directory.clone(),
+        };
+
+        info!("Indexing {} for an MCP client", directory.display());
+        if args.force {
+            init_session(&directory).await?;
+        } else {
+            restore_session(&directory).await?;
+        }
+        let status = index_status(&directory).await?;
+        let text = format!(
+            "Indexed {}. {}",
+            directory.display(),
+            describe_status(&status)
+        );
+        Ok((text, serde_json::to_value(&status)?))
+    }
+
+    async fn index_status(
+        &self,
+        args: IndexStatusArgs,
+    ) -> Result<(String, serde_json::Value), anyhow::Error> {
+        let directory = self.resolve_directory(args.directory.as_deref());
+        let status = index_status(&directory).await?;
+        Ok((describe_status(&status), serde_json::to_value(&status)?))
+    }
+
+    /// Resolve a tool's `directory` argument the same way the CLI resolves its arguments
+    fn resolve_directory(&self, directory: Option<&Path>) -> PathBuf {
+        let directory = match directory {
+            Some(directory) => self.default_directory.join(directory),
+            None => self.default_directory.clone(),
+        };
+        directory.canonicalize().unwrap_or(directory)
+    }
+}
+
+fn initialize(params: &InitializeRequestParams) -> serde_json::Value {
+    let result = InitializeResult {
+        capabilities: ServerCapabilities {
+            completions: None,
+            experimental: None,
+            logging: None,
+            prompts: None,
+            resources: None,
+            tools: Some(ServerCapabilitiesTools {
+                list_changed: Some(false),
+            }),
+        },
+        instructions: Some(
+            "Semantic search over indexed codebases. Call index_status to check whether a \
+             codebase is indexed, index_codebase to build or refresh its index, and \
+             search_code to find code by meaning."
+                .to_string(),
+        ),
+        protocol_version: params.protocol_version.clone(),
+        server_info: Implementation {
+            name: "codebase-search".to_string(),
+            title: Some("Codebase Search".to_string()),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+        },
+    };
+    serde_json::to_value(result).unwrap_or_default()
+}
+
+fn list_tools() -> serde_json::Value {
+    let directory = json!({
+        "type": "string",
+        "description": "Codebase directory; relative paths are resolved against the server's directory, which is used when omitted"
+    });
+    let string_list = |description: &str| json!({"type": "array", "items": {"type": "string"}, "description": description});
+    let annotations = |read_only: bool| ToolAnnotations {
+        destructive_hint: Some(false),
+        idempotent_hint: Some(true),
+        open_world_hint: Some(false),
+        read_only_hint: Some(read_only),
+        title: None,
+    };
+    let tools = vec![
+        Tool {
+            annotations: Some(annotations(true)),
+            description: Some(
+                "Find code by meaning in an indexed codebase, best match first. Results name \
+                 the file, line range and symbol of each matching chunk."
+                    .to_string(),
+            ),
+            input_schema: ToolInputSchema {
+                properties: Some(json!({
+                    "query": {"type": "string", "description": "What the code does, e.g. \"retry failed HTTP requests\""},
+                    "directory": directory,
+                    "limit": {"type": "integer", "description": "Maximum number of results (default 10)"},
+                    "min_score": {"type": "number", "description": "Minimum similarity score from 0.0 to 1.0 (default 0.3)"},
+                    "kinds": string_list("Only return these symbol kinds, e.g. function or struct"),
+                    "paths": string_list("Only return files matching these globs, e.g. src/**"),
+                    "exclude_paths": string_list("Skip files matching these globs"),
+                    "languages": string_list("Only return these languages, e.g. rust or python"),
+                    "exclude_tests": {"type": "boolean", "description": "Skip test code"},
+                    "only_tests": {"type": "boolean", "description": "Only return test code"},
+                    "public_only": {"type": "boolean", "description": "Only return symbols declared public"},
+                    "min_lines": {"type": "integer", "description": "Skip symbols shorter than this many lines, e.g. trivial getters"},
+                    "min_branches": {"type": "integer", "description": "Skip symbols with fewer conditionals, loops and cases than this"}
+                })),
+                required: Some(vec!["query".to_string()]),
+                r#type: "object".to_string(),
+            },
+            name: "search_code".to_string(),
+            output_schema: None,
+            title: Some("Search code".to_string()),
+        },
+        Tool {
+            annotations: Some(annotations(false)),
+            description: Some(
+                "Build the semantic index of a codebase, or update it with the files that \
+                 changed since it was last indexed."
+                    .to_string(),
+            ),
+            input_schema: ToolInputSchema {
+                properties: Some(json!({
+                    "directory": directory,
+                    "force": {"type": "boolean", "description": "Ignore the saved state and rebuild the whole index"}
+                })),
+                required: None,
+                r#type: "object".to_string(),
+            },
+            name: "index_codebase".to_string(),
+            output_schema: None,
+            title: Some("Index codebase".to_string()),
+        },
+        Tool {
+            annotations: Some(annotations(true)),
+            description: Some(
+                "Show whether a codebase is indexed, how many chunks its index holds and which \
+                 files changed since it was last indexed."
+                    .to_string(),
+            ),
+            input_schema: ToolInputSchema {
+                properties: Some(json!({ "directory": directory })),
+                required: None,
+                r#type: "object".to_string(),
+            },
+            name: "index_status".to_string(),
+            output_schema: None,
+            title: Some("Index status".to_string()),
+        },
+    ];
+    serde_json::to_value(ListToolsResult {
+        next_cursor: None,
+        tools,
+    })
+    .unwrap_or_default()
+}
+
+/// Deserialize request params or tool arguments, treating missing ones as `{}`
+fn parse_params<T: DeserializeOwned>(
+    params: Option<serde_json::Value>,
+) -> Result<T, JSONRPCErrorError> {
+    serde_json::from_value(params.unwrap_or_else(|| json!({}))).map_err(|e| JSONRPCErrorError {
+        code: INVALID_PARAMS,
+        data: None,
+        message: format!("Invalid params: {e}"),
+    })
+}
+
+fn text_content(text: String) -> ContentBlock {
+    ContentBlock::TextContent(TextContent {
+        annotations: None,
+        text,
+        r#type: "text".to_string(),
+    })
+}
+
+fn format_results(query: &str, results: &[SearchResult]) -> String {
+    if results.is_empty() {
+        return format!(
+            "No indexed code matched \"{query}\". Check that the codebase is indexed, or \
+             rephrase the query or lower min_score."
+        );
+    }
+    let mut text = format!(
+        "Found {} matching chunks (best match first):\n",
+        results.len()
+    );
+    for result in results {
+        let chunk = &result.chunk;
+        text.push_str(&format!(
+            "\n{}:{}-{} {} {} (score: {:.2})\n",
+            chunk.file_path.display(),
+            chunk.start_line,
+            chunk.end_line,
+            chunk.symbol_kind,
+            chunk.symbol_name,
+            result.score
+        ));
+        let lines: Vec<&str> = chunk.content.lines().collect();
+        text.push_str(&lines[..lines.len().min(MAX_SNIPPET_LINES)].join("\n"));
+        if lines.len() > MAX_SNIPPET_LINES {
+            text.push_str(&format!(
+                "\n... ({} more lines)",
+                lines.len() - MAX_SNIPPET_LINES
+            ));
+        }
+        text.push('\n');
+    }
+    text
+}
+
+fn describe_status(status: &IndexStatus) -> String {
+    let mut text = match (&status.state, status.point_count) {
+        (Some(state), Some(points)) if status.indexed => format!(
+            "{} is indexed: {points} chunks from {} files.",
+            status.root_path.display(),
+            state.tracked_files
+        ),
+        (Some(_), _) => format!(
+            "{} has saved index state but its collection is unavailable ({}); run index_codebase with force to rebuild it.",
+            status.root_path.display(),
+            status
+                .collection_error
+                .as_deref()
+                .unwrap_or("the collection is missing")
+        ),
+        (None, _) => format!(
+            "{} is not indexed; run index_codebase first.",
+            status.root_path.display()
+        ),
+    };
+    if let Some(pending) = status
+        .pending
+        .as_ref()
+        .filter(|pending| !pending.is_empty())
+    {
+        text.push_str(&format!(
+            " {} added, {} modified and {} deleted files are not reflected in the index yet.",
+            pending.added.len(),
+            pending.modified.len(),
+            pending.deleted.len()
+        ));
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::expect_used)]
+    use super::*;
+    use mcp_types::RequestId;
+    use tempfile::TempDir;
+
+    fn request(method: &str, params: serde_json::Value) -> JSONRPCRequest {
+        JSONRPCRequest {
+            id: RequestId::Integer(1),
+            jsonrpc: JSONRPC_VERSION.to_string(),
+            method: method.to_string(),
+            params: Some(params),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_lists_tools_and_rejects_bad_calls() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let server = McpServer::new(temp_dir.path().to_path_buf());
+
+        let JSONRPCMessage::Response(response) = server
+            .handle_request(request(
+                "initialize",
+                json!({
+                    "capabilities": {},
+                    "clientInfo": {"name": "test", "version": "1.0"},
+                    "protocolVersion": "2025-06-18"
+                }),
+            ))
+            .await
+        else {
+            panic!("initialize failed");
+        };
+        assert_eq!(response.result["protocolVersion"], "2025-06-18");
+        assert_eq!(response.result["serverInfo"]["name"], "codebase-search");
+
+        let JSONRPCMessage::Response(response) = server
+            .handle_request(request("tools/list", json!({})))
+            .await
+        else {
+            panic!("tools/list failed");
+        };
+        let names: Vec<&str> = response.result["tools"]
+            .as_array()
+            .expect("tools should be a list")
+            .iter()
+            .filter_map(|tool| tool["name"].as_str())
+            .collect();
+        assert_eq!(names, ["search_code", "index_codebase", "index_status"]);
+
+        let JSONRPCMessage::Error(error) = server
+            .handle_request(request(
+                "tools/call",
+                json!({"name": "search_code", "arguments": {"limit": 5}}),
+            ))
+            .await
+        else {
+            panic!("search_code without a query should be rejected");
+        };
+        assert_eq!(error.error.code, INVALID_PARAMS);
+        assert!(
+            error.error.message.contains("query"),
+            "{}",
+            error.error.message
+        );
+
+        let JSONRPCMessage::Error(error) = server
+            .handle_request(request("resources/list", json!({})))
+            .await
+        else {
+            panic!("unsupported methods should be rejected");
+        };
+        assert_eq!(error.error.code, METHOD_NOT_FOUND);
+    }
+}
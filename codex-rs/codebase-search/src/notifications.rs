@@ -0,0 +1,160 @@
+//! Notifications about index freshness, for bots and dashboards that react to it
+//! `IndexService` and the HTTP server's `/index` post them to the `[[hooks.webhooks]]`
+//! configured for the project, and `serve` and `watch --events-addr` stream them as
+//! server-sent events.
+
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::LazyLock;
+use std::time::Duration;
+
+use chrono::DateTime;
+use chrono::Utc;
+use reqwest::Client;
+use serde::Deserialize;
+use serde::Serialize;
+use tracing::debug;
+use tracing::warn;
+
+use crate::index_config::WebhookConfig;
+use crate::indexer::IndexUpdate;
+
+/// Shared by every delivery so connections to the same endpoint are reused
+static WEBHOOK_CLIENT: LazyLock<Client> = LazyLock::new(Client::new);
+
+/// Something that happened to a project's index
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IndexNotification {
+    pub root_path: PathBuf,
+    pub timestamp: DateTime<Utc>,
+    #[serde(flatten)]
+    pub event: IndexEvent,
+}
+
+/// What happened, tagged by `event` in JSON
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum IndexEvent {
+    /// Changed files were reindexed by a watcher
+    Reindexed {
+        added: usize,
+        modified: usize,
+        deleted: usize,
+    },
+    /// The project was brought up to date on request
+    Indexed {
+        tracked_files: usize,
+        tracked_chunks: usize,
+    },
+    /// An index run failed; the index stays stale until a later run succeeds
+    Failed { error: String },
+}
+
+impl IndexNotification {
+    /// A notification about `root_path` stamped with the current time
+    pub fn new(root_path: &Path, event: IndexEvent) -> Self {
+        Self {
+            root_path: root_path.to_path_buf(),
+            timestamp: Utc::now(),
+            event,
+        }
+    }
+
+    /// The notification for a watcher batch, or None when it didn't change the index
+    pub fn for_update(
+        root_path: &Path,
+        result: &Result<IndexUpdate, anyhow::Error>,
+    ) -> Option<Self> {
+        let event = match result {
+            Ok(update) if update.is_empty() => return None,
+            Ok(update) => IndexEvent::Reindexed {
+                added: update.added,
+                modified: update.modified,
+                deleted: update.deleted,
+            },
+            Err(e) => IndexEvent::Failed {
+                error: e.to_string(),
+            },
+        };
+        Some(Self::new(root_path, event))
+    }
+
+    /// Name of the event, as in the `event` field
+    pub fn event_name(&self) -> &'static str {
+        match self.event {
+            IndexEvent::Reindexed { .. } => "reindexed",
+            IndexEvent::Indexed { .. } => "indexed",
+            IndexEvent::Failed { .. } => "failed",
+        }
+    }
+}
+
+/// POST `notification` as JSON to every webhook subscribed to its event
+/// Deliveries run in the background; failures are logged and never affect indexing.
+pub fn send_webhooks(webhooks: &[WebhookConfig], notification: &IndexNotification) {
+    let event = notification.event_name();
+    for webhook in webhooks {
+        if !webhook.events.is_empty() && !webhook.events.iter().any(|name| name == event) {
+            continue;
+        }
+        tokio::spawn(deliver(webhook.clone(), notification.clone()));
+    }
+}
+
+async fn deliver(webhook: WebhookConfig, notification: IndexNotification) {
+    let mut request = WEBHOOK_CLIENT
+        .post(&webhook.url)
+        .timeout(Duration::from_secs(webhook.timeout_secs))
+        .json(&notification);
+    for (name, value) in &webhook.headers {
+        request = request.header(name, value);
+    }
+    match request
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+    {
+        Ok(_) => debug!(
+            "Delivered {} notification to {}",
+            notification.event_name(),
+            webhook.url
+        ),
+        Err(e) => warn!(
+            "Failed to deliver {} notification to {}: {e}",
+            notification.event_name(),
+            webhook.url
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::expect_used)]
+    use super::*;
+
+    #[test]
+    fn notifications_serialize_flat_with_an_event_tag() {
+        let root = Path::new("/repo");
+        assert!(IndexNotification::for_update(root, &Ok(IndexUpdate::default())).is_none());
+
+        let update = IndexUpdate {
+            added: 1,
+            modified: 2,
+            deleted: 0,
+        };
+        let notification =
+            IndexNotification::for_update(root, &Ok(update)).expect("Expected a notification");
+        let json = serde_json::to_value(&notification).expect("Failed to serialize");
+        assert_eq!(json["event"], "reindexed");
+        assert_eq!(json["root_path"], "/repo");
+        assert_eq!(json["added"], 1);
+        assert_eq!(json["modified"], 2);
+
+        let failed = IndexNotification::for_update(root, &Err(anyhow::anyhow!("Qdrant is down")))
+            .expect("Expected a notification");
+        assert_eq!(failed.event_name(), "failed");
+        let json = serde_json::to_string(&failed).expect("Failed to serialize");
+        let parsed: IndexNotification = serde_json::from_str(&json).expect("Failed to parse");
+        assert_eq!(parsed, failed);
+    }
+}
@@ -0,0 +1,45 @@
+/// Stage of an indexing run
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IndexPhase {
+    /// Parsing files into symbols and cutting them into chunks
+    Chunking,
+    /// Sending chunks to the embedding provider
+    Embedding,
+    /// Writing points to the vector database
+    Upserting,
+}
+
+impl IndexPhase {
+    pub fn label(&self) -> &'static str {
+        match self {
+            IndexPhase::Chunking => "Chunking",
+            IndexPhase::Embedding => "Embedding",
+            IndexPhase::Upserting => "Upserting",
+        }
+    }
+}
+
+/// Reported as an indexing run moves through its phases
+/// Work is counted in files while chunking and in chunks while embedding and upserting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndexProgress {
+    /// `phase` started with `total` units of work
+    Started {
+        phase: IndexPhase,
+        total: u64,
+    },
+    /// `count` more units of `phase` are done
+    Advanced {
+        phase: IndexPhase,
+        count: u64,
+    },
+    Finished {
+        phase: IndexPhase,
+    },
+}
+
+/// Receives progress events while indexing
+pub type ProgressFn<'a> = dyn Fn(IndexProgress) + Send + Sync + 'a;
+
+/// Progress sink for callers that don't report progress
+pub fn no_progress(_: IndexProgress) {}
@@ -1,18 +1,41 @@
+use globset::GlobBuilder;
+use globset::GlobSet;
+use globset::GlobSetBuilder;
+use qdrant_client::qdrant::Condition;
+use qdrant_client::qdrant::Filter;
+use qdrant_client::qdrant::GetPointsBuilder;
+use qdrant_client::qdrant::PointId;
+use qdrant_client::qdrant::RecommendPointsBuilder;
+use qdrant_client::qdrant::ScrollPointsBuilder;
 use qdrant_client::qdrant::SearchParamsBuilder;
 use qdrant_client::qdrant::SearchPointsBuilder;
 use qdrant_client::qdrant::Value as QdrantValue;
+use qdrant_client::qdrant::point_id::PointIdOptions;
+use serde::Deserialize;
+use serde::Serialize;
 use tracing::info;
+use tracing::instrument;
 
 use crate::chunker::ChunkMetadata;
 use crate::chunker::CodeChunk;
+use crate::language_registry::LanguageRegistry;
+use crate::symbol::SupportedLanguage;
+use crate::symbol::SymbolKind;
+use crate::symbol::SymbolMetrics;
+use crate::symbol::SymbolModifiers;
+use crate::symbol::Visibility;
+use crate::telemetry;
+use crate::telemetry::Operation;
 use crate::vector_db::QDRANT_CLIENT;
 use crate::vector_db::generate_collection_id;
 use std::path::Path;
 use std::path::PathBuf;
 
 /// A search result containing the code chunk and its similarity score
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct SearchResult {
+    /// The Qdrant point ID of the chunk, used to reference it in relevance feedback
+    pub id: String,
     pub chunk: CodeChunk,
     pub score: f32,
 }
@@ -24,6 +47,277 @@ pub async fn search_codebase<P: AsRef<Path>>(
     limit: usize,
     min_score: f32,
 ) -> Result<Vec<SearchResult>, anyhow::Error> {
+    search_codebase_filtered(query, root_path, limit, min_score, &SearchFilter::default()).await
+}
+
+/// Candidates fetched per requested result when a filter may discard some of them
+const FILTERED_SEARCH_OVERSAMPLE: usize = 10;
+
+/// Directory names whose contents count as test code
+const TEST_DIRECTORIES: &[&str] = &["test", "tests", "__tests__", "testdata", "spec"];
+
+/// Restricts which chunks a search returns
+/// Path patterns are globs over the root-relative file path (`src/**`); one without a
+/// `/` matches at any depth, like a gitignore pattern, and one without any glob
+/// characters matches every path containing it.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct SearchFilter {
+    /// Symbol kinds to keep (e.g. "function"), compared case-insensitively; empty keeps all
+    pub kinds: Vec<String>,
+    /// Path patterns to keep; empty keeps all
+    pub paths: Vec<String>,
+    /// Path patterns to drop
+    pub exclude_paths: Vec<String>,
+    /// Languages to keep, by name or extension (`rust`, `py`); empty keeps all
+    pub languages: Vec<String>,
+    /// Drop test code: symbols tagged as tests at extraction, files in test directories
+    /// or named like tests, and `test_*` symbols
+    pub exclude_tests: bool,
+    /// Keep only test code, as judged by `exclude_tests`
+    pub only_tests: bool,
+    /// Keep only symbols declared public; chunks whose visibility is unknown are dropped
+    pub public_only: bool,
+    /// Drop symbols shorter than this many lines, e.g. getters and one-line wrappers
+    pub min_lines: Option<usize>,
+    /// Drop symbols with fewer decision points (conditionals, loops, cases) than this
+    pub min_branches: Option<usize>,
+}
+
+impl SearchFilter {
+    pub fn is_empty(&self) -> bool {
+        self.kinds.is_empty()
+            && self.paths.is_empty()
+            && self.exclude_paths.is_empty()
+            && self.languages.is_empty()
+            && !self.exclude_tests
+            && !self.only_tests
+            && !self.public_only
+            && self.min_lines.is_none()
+            && self.min_branches.is_none()
+    }
+
+    /// Split `kind:`, `path:`, `exclude:` and `lang:` terms out of a query, returning
+    /// the remaining query text and the filter they describe
+    /// Every term accepts comma-separated values (`kind:function,method`); any other
+    /// term, including `std::io`, is left in the query.
+    pub fn parse_inline(query: &str) -> (String, SearchFilter) {
+        let mut filter = SearchFilter::default();
+        let mut text = Vec::new();
+        for term in query.split_whitespace() {
+            let Some((key, values)) = term.split_once(':') else {
+                text.push(term);
+                continue;
+            };
+            let target = match key {
+                "kind" => &mut filter.kinds,
+                "path" => &mut filter.paths,
+                "exclude" => &mut filter.exclude_paths,
+                "lang" => &mut filter.languages,
+                _ => {
+                    text.push(term);
+                    continue;
+                }
+            };
+            target.extend(
+                values
+                    .split(',')
+                    .filter(|value| !value.is_empty())
+                    .map(str::to_string),
+            );
+        }
+        (text.join(" "), filter)
+    }
+
+    /// Fill every criterion this filter leaves open from `defaults`
+    /// Exclusions accumulate, so inline terms can narrow command-line flags.
+    pub fn with_defaults(mut self, defaults: &SearchFilter) -> SearchFilter {
+        if self.kinds.is_empty() {
+            self.kinds = defaults.kinds.clone();
+        }
+        if self.paths.is_empty() {
+            self.paths = defaults.paths.clone();
+        }
+        if self.languages.is_empty() {
+            self.languages = defaults.languages.clone();
+        }
+        self.exclude_paths
+            .extend(defaults.exclude_paths.iter().cloned());
+        self.exclude_tests |= defaults.exclude_tests;
+        self.only_tests |= defaults.only_tests;
+        self.public_only |= defaults.public_only;
+        self.min_lines = self.min_lines.or(defaults.min_lines);
+        self.min_branches = self.min_branches.or(defaults.min_branches);
+        self
+    }
+
+    fn compile(&self) -> Result<CompiledFilter, anyhow::Error> {
+        let mut extensions = Vec::new();
+        for language in &self.languages {
+            let language = SupportedLanguage::from_name(language).ok_or_else(|| {
+                anyhow::anyhow!(
+                    "Unknown language {language}; expected one of {}",
+                    LanguageRegistry::global().names().join(", ")
+                )
+            })?;
+            extensions.extend(language.extensions().iter().map(|ext| ext.to_string()));
+        }
+        Ok(CompiledFilter {
+            kinds: self.kinds.iter().map(|kind| kind.to_lowercase()).collect(),
+            paths: PathPatterns::new(&self.paths)?,
+            exclude_paths: PathPatterns::new(&self.exclude_paths)?,
+            extensions,
+            exclude_tests: self.exclude_tests,
+            only_tests: self.only_tests,
+            public_only: self.public_only,
+            min_lines: self.min_lines.unwrap_or(0),
+            min_branches: self.min_branches.unwrap_or(0),
+        })
+    }
+}
+
+/// Compiled path patterns of a `SearchFilter`
+struct PathPatterns {
+    globs: GlobSet,
+    substrings: Vec<String>,
+}
+
+impl PathPatterns {
+    /// `None` when there are no patterns
+    fn new(patterns: &[String]) -> Result<Option<Self>, anyhow::Error> {
+        if patterns.is_empty() {
+            return Ok(None);
+        }
+        let mut globs = GlobSetBuilder::new();
+        let mut substrings = Vec::new();
+        for pattern in patterns {
+            if !pattern.contains(['*', '?', '[', '{']) {
+                substrings.push(pattern.clone());
+                continue;
+            }
+            let pattern = if pattern.contains('/') {
+                pattern.trim_start_matches('/').to_string()
+            } else {
+                format!("**/{pattern}")
+            };
+            let glob = GlobBuilder::new(&pattern)
+                .literal_separator(true)
+                .build()
+                .map_err(|e| anyhow::anyhow!("Invalid path filter {pattern}: {e}"))?;
+            globs.add(glob);
+        }
+        Ok(Some(Self {
+            globs: globs.build()?,
+            substrings,
+        }))
+    }
+
+    fn is_match(&self, path: &Path) -> bool {
+        let path_string = path.to_string_lossy();
+        self.globs.is_match(path)
+            || self
+                .substrings
+                .iter()
+                .any(|substring| path_string.contains(substring.as_str()))
+    }
+}
+
+/// A `SearchFilter` ready to test chunks against
+struct CompiledFilter {
+    kinds: Vec<String>,
+    paths: Option<PathPatterns>,
+    exclude_paths: Option<PathPatterns>,
+    extensions: Vec<String>,
+    exclude_tests: bool,
+    only_tests: bool,
+    public_only: bool,
+    min_lines: usize,
+    min_branches: usize,
+}
+
+impl CompiledFilter {
+    fn matches(&self, chunk: &CodeChunk) -> bool {
+        let path = chunk.file_path.as_path();
+        if !self.kinds.is_empty() && !self.kinds.contains(&chunk.symbol_kind.to_lowercase()) {
+            return false;
+        }
+        if !self.extensions.is_empty() {
+            let extension = path.extension().and_then(|ext| ext.to_str());
+            if !extension
+                .is_some_and(|extension| self.extensions.iter().any(|allowed| allowed == extension))
+            {
+                return false;
+            }
+        }
+        if (self.exclude_tests || self.only_tests) && is_test_chunk(chunk) != self.only_tests {
+            return false;
+        }
+        if self.public_only && chunk.modifiers.visibility != Some(Visibility::Public) {
+            return false;
+        }
+        if chunk.metrics.lines < self.min_lines || chunk.metrics.branches < self.min_branches {
+            return false;
+        }
+        if self
+            .exclude_paths
+            .as_ref()
+            .is_some_and(|patterns| patterns.is_match(path))
+        {
+            return false;
+        }
+        self.paths
+            .as_ref()
+            .is_none_or(|patterns| patterns.is_match(path))
+    }
+}
+
+/// Whether a chunk is test code: tagged as such at extraction, or looking like it
+/// by its path and symbol name
+fn is_test_chunk(chunk: &CodeChunk) -> bool {
+    if chunk.is_test {
+        return true;
+    }
+    let path = chunk.file_path.as_path();
+    let in_test_directory = path.parent().is_some_and(|parent| {
+        parent.components().any(|component| {
+            TEST_DIRECTORIES.contains(&component.as_os_str().to_string_lossy().as_ref())
+        })
+    });
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy())
+        .unwrap_or_default();
+    let test_file = stem.starts_with("test_")
+        || stem.ends_with("_test")
+        || stem.ends_with("_tests")
+        || stem == "conftest";
+    let test_symbol = chunk.symbol_name.starts_with("test_")
+        || (chunk.symbol_kind == "Module" && chunk.symbol_name == "tests");
+    in_test_directory || test_file || test_symbol
+}
+
+/// `search_codebase`, returning only chunks that pass `filter`
+/// Filters are applied to an oversampled candidate set, so a very selective filter
+/// can return fewer than `limit` results even when more matches exist.
+#[instrument(
+    name = "search",
+    skip_all,
+    fields(root = %root_path.as_ref().display(), limit, min_score)
+)]
+pub async fn search_codebase_filtered<P: AsRef<Path>>(
+    query: String,
+    root_path: P,
+    limit: usize,
+    min_score: f32,
+    filter: &SearchFilter,
+) -> Result<Vec<SearchResult>, anyhow::Error> {
+    let timer = telemetry::start(Operation::Search);
+    let compiled_filter = filter.compile()?;
+    let candidates = if filter.is_empty() {
+        limit
+    } else {
+        limit.saturating_mul(FILTERED_SEARCH_OVERSAMPLE)
+    };
     let collection_id = generate_collection_id(root_path.as_ref());
     info!("Searching collection: {}", collection_id);
 
@@ -40,7 +334,7 @@ pub async fn search_codebase<P: AsRef<Path>>(
     let search_response = QDRANT_CLIENT
         .clone()
         .search_points(
-            SearchPointsBuilder::new(collection_id.as_str(), query_vector, limit as u64)
+            SearchPointsBuilder::new(collection_id.as_str(), query_vector, candidates as u64)
                 .with_payload(true)
                 .params(SearchParamsBuilder::default()),
         )
@@ -59,60 +353,416 @@ pub async fn search_codebase<P: AsRef<Path>>(
             continue;
         }
 
-        let payload = scored_point.payload;
-
-        // Extract fields from payload with proper error handling
-        let file_path = extract_string_field(&payload, "file_path")?;
-        let start_line = extract_u64_field(&payload, "start_line")? as usize;
-        let end_line = extract_u64_field(&payload, "end_line")? as usize;
-        let symbol_name = extract_string_field(&payload, "symbol_name")?;
-        let symbol_kind = extract_string_field(&payload, "symbol_kind")?;
-        let content = extract_string_field(&payload, "content")?;
-
-        // Optional fields
-        let context = extract_optional_string_field(&payload, "context");
-
-        // Extract chunk metadata
-        let is_container = extract_optional_bool_field(&payload, "is_container").unwrap_or(false);
-        let original_size_lines = extract_optional_u64_field(&payload, "original_size_lines")
-            .map(|v| v as usize)
-            .unwrap_or(end_line - start_line + 1);
-        let is_split = extract_optional_bool_field(&payload, "is_split").unwrap_or(false);
-        let chunk_depth = extract_optional_u64_field(&payload, "chunk_depth")
-            .map(|v| v as usize)
-            .unwrap_or(0);
-
-        let chunk_metadata = ChunkMetadata {
-            is_container,
-            original_size_lines,
-            is_split,
-            chunk_depth,
-        };
+        let id = point_id_to_string(scored_point.id);
+        let chunk = chunk_from_payload(&scored_point.payload)?;
+        if !compiled_filter.matches(&chunk) {
+            continue;
+        }
+        results.push(SearchResult { id, chunk, score });
+    }
 
-        let chunk = CodeChunk {
-            content,
-            file_path: PathBuf::from(file_path),
-            start_line,
-            end_line,
-            symbol_name,
-            symbol_kind,
-            context,
-            chunk_metadata,
-        };
+    // Sort by score descending
+    results.sort_by(|a, b| {
+        b.score
+            .partial_cmp(&a.score)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    results.truncate(limit);
 
-        results.push(SearchResult { chunk, score });
+    timer.succeeded(results.len() as u64);
+    Ok(results)
+}
+
+/// The code `find_similar_code` looks for neighbours of
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SimilarTo {
+    /// An indexed chunk, by the ID search results report
+    Chunk(String),
+    /// The innermost chunk covering a 1-based line of a root-relative file
+    Location { file_path: String, line: usize },
+}
+
+/// Find the indexed chunks closest to an already indexed chunk
+/// The chunk's stored embedding is reused, so this never calls the embedding API.
+/// Chunks overlapping the target in the same file (its containers and the pieces
+/// it was split into) are left out, since they trivially resemble it.
+#[instrument(
+    name = "find_similar",
+    skip_all,
+    fields(root = %root_path.as_ref().display(), limit, min_score)
+)]
+pub async fn find_similar_code<P: AsRef<Path>>(
+    target: &SimilarTo,
+    root_path: P,
+    limit: usize,
+    min_score: f32,
+    filter: &SearchFilter,
+) -> Result<Vec<SearchResult>, anyhow::Error> {
+    let timer = telemetry::start(Operation::FindSimilar);
+    let compiled_filter = filter.compile()?;
+    let collection_id = generate_collection_id(root_path.as_ref());
+    let (target_id, target_chunk) = match target {
+        SimilarTo::Chunk(id) => indexed_chunk(&collection_id, id).await?,
+        SimilarTo::Location { file_path, line } => {
+            chunk_at_location(&collection_id, file_path, *line).await?
+        }
+    };
+    info!(
+        "Finding code similar to {}:{}-{} in collection {}",
+        target_chunk.file_path.display(),
+        target_chunk.start_line,
+        target_chunk.end_line,
+        collection_id
+    );
+
+    let candidates = limit.saturating_mul(FILTERED_SEARCH_OVERSAMPLE);
+    let response = QDRANT_CLIENT
+        .recommend(
+            RecommendPointsBuilder::new(collection_id.as_str(), candidates as u64)
+                .add_positive(target_id)
+                .with_payload(true)
+                .score_threshold(min_score),
+        )
+        .await?;
+
+    let mut results = Vec::new();
+    for scored_point in response.result {
+        let chunk = chunk_from_payload(&scored_point.payload)?;
+        let overlaps_target = chunk.file_path == target_chunk.file_path
+            && chunk.start_line <= target_chunk.end_line
+            && target_chunk.start_line <= chunk.end_line;
+        if overlaps_target || !compiled_filter.matches(&chunk) {
+            continue;
+        }
+        results.push(SearchResult {
+            id: point_id_to_string(scored_point.id),
+            chunk,
+            score: scored_point.score,
+        });
+        if results.len() == limit {
+            break;
+        }
     }
+    timer.succeeded(results.len() as u64);
+    Ok(results)
+}
 
-    // Sort by score descending
+/// Load one indexed chunk by point ID
+async fn indexed_chunk(
+    collection_id: &str,
+    id: &str,
+) -> Result<(String, CodeChunk), anyhow::Error> {
+    let response = QDRANT_CLIENT
+        .get_points(
+            GetPointsBuilder::new(collection_id, vec![PointId::from(id)]).with_payload(true),
+        )
+        .await?;
+    let point = response
+        .result
+        .into_iter()
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("No indexed chunk has ID {id}"))?;
+    Ok((id.to_string(), chunk_from_payload(&point.payload)?))
+}
+
+/// The smallest indexed chunk of `file_path` that covers `line`
+async fn chunk_at_location(
+    collection_id: &str,
+    file_path: &str,
+    line: usize,
+) -> Result<(String, CodeChunk), anyhow::Error> {
+    let mut best: Option<(String, CodeChunk)> = None;
+    let mut offset = None;
+    loop {
+        let mut request = ScrollPointsBuilder::new(collection_id)
+            .filter(Filter::must([Condition::matches(
+                "file_path",
+                file_path.to_string(),
+            )]))
+            .limit(METADATA_SCROLL_PAGE_SIZE)
+            .with_payload(true)
+            .with_vectors(false);
+        if let Some(offset) = offset.take() {
+            request = request.offset(offset);
+        }
+        let response = QDRANT_CLIENT.scroll(request).await?;
+
+        for point in response.result {
+            let chunk = chunk_from_payload(&point.payload)?;
+            if !(chunk.start_line..=chunk.end_line).contains(&line) {
+                continue;
+            }
+            let span = chunk.end_line - chunk.start_line;
+            if best
+                .as_ref()
+                .is_none_or(|(_, best)| span < best.end_line - best.start_line)
+            {
+                best = Some((point_id_to_string(point.id), chunk));
+            }
+        }
+
+        match response.next_page_offset {
+            Some(next) => offset = Some(next),
+            None => break,
+        }
+    }
+    best.ok_or_else(|| anyhow::anyhow!("No indexed chunk covers {file_path}:{line}"))
+}
+
+/// Filters for a metadata-only search over the stored payload fields
+#[derive(Debug, Clone, Default)]
+pub struct MetadataQuery {
+    /// Case-insensitive substring matched against symbol names
+    pub name: Option<String>,
+    /// Symbol kind to match (e.g. "function", "Struct"), compared case-insensitively
+    pub kind: Option<String>,
+    /// Case-insensitive substring matched against the relative file path
+    pub path: Option<String>,
+    /// Further restrictions, as for semantic search
+    pub filter: SearchFilter,
+}
+
+/// Search codebase using only payload metadata (symbol name, kind, path)
+/// This never calls the embedding API, so it works offline and without an API key.
+/// Scores are heuristic: exact name matches rank above prefix matches, which rank
+/// above plain substring matches.
+pub async fn search_codebase_metadata<P: AsRef<Path>>(
+    query: MetadataQuery,
+    root_path: P,
+    limit: usize,
+) -> Result<Vec<SearchResult>, anyhow::Error> {
+    let collection_id = generate_collection_id(root_path.as_ref());
+    info!("Metadata search in collection: {}", collection_id);
+
+    let name = query
+        .name
+        .map(|n| n.to_lowercase())
+        .filter(|n| !n.is_empty());
+    let compiled_filter = query.filter.compile()?;
+    let kind = query.kind.map(|k| k.to_lowercase());
+    let path = query
+        .path
+        .map(|p| p.to_lowercase())
+        .filter(|p| !p.is_empty());
+    let score = |chunk: &CodeChunk| {
+        compiled_filter
+            .matches(chunk)
+            .then(|| metadata_score(chunk, name.as_deref(), kind.as_deref(), path.as_deref()))
+            .flatten()
+    };
+
+    // The name index finds every name that starts with the query. Names that only
+    // contain it score lower, so a full scroll is only needed when too few start with it.
+    let mut results = Vec::new();
+    if let Some(name) = &name {
+        let filter = metadata_filter(Some(name), kind.as_deref());
+        results = scroll_metadata_matches(&collection_id, filter, &score).await?;
+    }
+    let prefix_matches = results
+        .iter()
+        .filter(|result| result.score > SUBSTRING_NAME_SCORE)
+        .count();
+    if name.is_none() || prefix_matches < limit {
+        let filter = metadata_filter(None, kind.as_deref());
+        results = scroll_metadata_matches(&collection_id, filter, &score).await?;
+    }
+
+    info!("Found {} metadata matches", results.len());
+
+    // Sort by score descending, then by location for a stable order
     results.sort_by(|a, b| {
         b.score
             .partial_cmp(&a.score)
             .unwrap_or(std::cmp::Ordering::Equal)
+            .then_with(|| a.chunk.file_path.cmp(&b.chunk.file_path))
+            .then_with(|| a.chunk.start_line.cmp(&b.chunk.start_line))
     });
+    results.truncate(limit);
+
+    Ok(results)
+}
+
+/// Number of points fetched per scroll request during metadata search
+const METADATA_SCROLL_PAGE_SIZE: u32 = 256;
+
+/// Score of a name that contains the query without starting with it
+const SUBSTRING_NAME_SCORE: f32 = 0.6;
+
+/// Scroll the points `filter` selects, keeping those `score` accepts
+async fn scroll_metadata_matches(
+    collection_id: &str,
+    filter: Option<Filter>,
+    score: &(dyn Fn(&CodeChunk) -> Option<f32> + Sync),
+) -> Result<Vec<SearchResult>, anyhow::Error> {
+    let mut results = Vec::new();
+    let mut offset = None;
+    loop {
+        let mut request = ScrollPointsBuilder::new(collection_id)
+            .limit(METADATA_SCROLL_PAGE_SIZE)
+            .with_payload(true)
+            .with_vectors(false);
+        if let Some(filter) = &filter {
+            request = request.filter(filter.clone());
+        }
+        if let Some(offset) = offset.take() {
+            request = request.offset(offset);
+        }
 
+        let response = QDRANT_CLIENT.scroll(request).await?;
+        for point in response.result {
+            let chunk = chunk_from_payload(&point.payload)?;
+            if let Some(score) = score(&chunk) {
+                results.push(SearchResult {
+                    id: point_id_to_string(point.id),
+                    chunk,
+                    score,
+                });
+            }
+        }
+
+        match response.next_page_offset {
+            Some(next) => offset = Some(next),
+            None => break,
+        }
+    }
     Ok(results)
 }
 
+/// Payload conditions narrowing a metadata search by its lowercase name and kind, or
+/// `None` to scroll every point
+/// A kind is matched by the name it's stored under (`function` is `Function`). A name is
+/// a full-text match over the prefix index, which finds the points where each word of the
+/// query starts a word of the name; substrings are checked by `metadata_score`.
+fn metadata_filter(name: Option<&str>, kind: Option<&str>) -> Option<Filter> {
+    let mut conditions = Vec::new();
+    if let Some(name) = name {
+        conditions.push(Condition::matches_text("symbol_name", name));
+    }
+    if let Some(kind) = kind {
+        let stored = SymbolKind::from_name(kind)
+            .map_or_else(|| kind.to_string(), |kind| format!("{kind:?}"));
+        conditions.push(Condition::matches("symbol_kind", stored));
+    }
+    (!conditions.is_empty()).then(|| Filter::must(conditions))
+}
+
+/// Score a chunk against a metadata query's lowercase name, kind and path substrings,
+/// or None if it doesn't match
+fn metadata_score(
+    chunk: &CodeChunk,
+    name: Option<&str>,
+    kind: Option<&str>,
+    path: Option<&str>,
+) -> Option<f32> {
+    if kind.is_some_and(|kind| chunk.symbol_kind.to_lowercase() != kind) {
+        return None;
+    }
+    if path.is_some_and(|path| {
+        !chunk
+            .file_path
+            .to_string_lossy()
+            .to_lowercase()
+            .contains(path)
+    }) {
+        return None;
+    }
+    match name {
+        Some(name) => metadata_name_score(&chunk.symbol_name, name),
+        None => Some(1.0),
+    }
+}
+
+/// Score a symbol name against a lowercase query, or None if it doesn't match
+fn metadata_name_score(symbol_name: &str, query: &str) -> Option<f32> {
+    let symbol_name = symbol_name.to_lowercase();
+    if symbol_name == query {
+        Some(1.0)
+    } else if symbol_name.starts_with(query) {
+        Some(0.8)
+    } else if symbol_name.contains(query) {
+        Some(SUBSTRING_NAME_SCORE)
+    } else {
+        None
+    }
+}
+
+/// Render a Qdrant point ID as a string (UUID or number)
+pub(crate) fn point_id_to_string(id: Option<PointId>) -> String {
+    match id.and_then(|id| id.point_id_options) {
+        Some(PointIdOptions::Uuid(uuid)) => uuid,
+        Some(PointIdOptions::Num(num)) => num.to_string(),
+        None => String::new(),
+    }
+}
+
+/// Rebuild a CodeChunk from the payload stored alongside a Qdrant point
+pub(crate) fn chunk_from_payload(
+    payload: &std::collections::HashMap<String, QdrantValue>,
+) -> Result<CodeChunk, anyhow::Error> {
+    // Extract fields from payload with proper error handling
+    let file_path = extract_string_field(payload, "file_path")?;
+    let start_line = extract_u64_field(payload, "start_line")? as usize;
+    let end_line = extract_u64_field(payload, "end_line")? as usize;
+    let symbol_name = extract_string_field(payload, "symbol_name")?;
+    let symbol_kind = extract_string_field(payload, "symbol_kind")?;
+    let content = extract_string_field(payload, "content")?;
+
+    // Optional fields
+    let context = extract_optional_string_field(payload, "context");
+    let signature = extract_optional_string_field(payload, "signature").unwrap_or_default();
+    let modifiers = SymbolModifiers {
+        visibility: extract_optional_string_field(payload, "visibility")
+            .and_then(|visibility| Visibility::from_name(&visibility)),
+        is_async: extract_optional_bool_field(payload, "is_async").unwrap_or(false),
+        is_static: extract_optional_bool_field(payload, "is_static").unwrap_or(false),
+    };
+    // Chunks indexed before metrics were stored count no branches
+    let metrics = SymbolMetrics {
+        lines: extract_optional_u64_field(payload, "line_count")
+            .map_or(end_line - start_line + 1, |lines| lines as usize),
+        tokens: extract_optional_u64_field(payload, "token_estimate").map_or_else(
+            || content.chars().count().div_ceil(4),
+            |tokens| tokens as usize,
+        ),
+        branches: extract_optional_u64_field(payload, "branch_count").unwrap_or(0) as usize,
+    };
+    let is_test = extract_optional_bool_field(payload, "is_test").unwrap_or(false);
+    let symbol_id = extract_optional_string_field(payload, "symbol_id").unwrap_or_default();
+
+    // Extract chunk metadata
+    let is_container = extract_optional_bool_field(payload, "is_container").unwrap_or(false);
+    let original_size_lines = extract_optional_u64_field(payload, "original_size_lines")
+        .map(|v| v as usize)
+        .unwrap_or(end_line - start_line + 1);
+    let is_split = extract_optional_bool_field(payload, "is_split").unwrap_or(false);
+    let chunk_depth = extract_optional_u64_field(payload, "chunk_depth")
+        .map(|v| v as usize)
+        .unwrap_or(0);
+
+    let chunk_metadata = ChunkMetadata {
+        is_container,
+        original_size_lines,
+        is_split,
+        chunk_depth,
+    };
+
+    Ok(CodeChunk {
+        content,
+        file_path: PathBuf::from(file_path),
+        start_line,
+        end_line,
+        symbol_name,
+        symbol_kind,
+        context,
+        signature,
+        modifiers,
+        metrics,
+        is_test,
+        symbol_id,
+        chunk_metadata,
+    })
+}
+
 /// Helper function to extract string field from Qdrant payload
 fn extract_string_field(
     payload: &std::collections::HashMap<String, QdrantValue>,
@@ -183,3 +833,184 @@ fn extract_optional_bool_field(
         _ => None,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::expect_used)]
+    use super::*;
+
+    fn chunk(file_path: &str, symbol_kind: &str) -> CodeChunk {
+        CodeChunk {
+            content: String::new(),
+            file_path: PathBuf::from(file_path),
+            start_line: 1,
+            end_line: 1,
+            symbol_name: "doctor".to_string(),
+            symbol_kind: symbol_kind.to_string(),
+            context: None,
+            signature: String::new(),
+            modifiers: Default::default(),
+            metrics: Default::default(),
+            is_test: false,
+            symbol_id: String::new(),
+            chunk_metadata: ChunkMetadata {
+                is_split: false,
+                original_size_lines: 1,
+                chunk_depth: 0,
+                is_container: false,
+            },
+        }
+    }
+
+    #[test]
+    fn test_parse_inline_filter() {
+        let (text, filter) =
+            SearchFilter::parse_inline("retry std::io errors kind:function,Method path:src/**");
+        assert_eq!(text, "retry std::io errors");
+        assert_eq!(filter.kinds, vec!["function", "Method"]);
+        assert_eq!(filter.paths, vec!["src/**"]);
+
+        let (text, filter) = SearchFilter::parse_inline("plain query");
+        assert_eq!(text, "plain query");
+        assert!(filter.is_empty());
+    }
+
+    #[test]
+    fn test_filter_matches() {
+        let (_, filter) = SearchFilter::parse_inline("kind:function path:src/** path:*.py");
+        let compiled = filter.compile().expect("Failed to compile filter");
+        assert!(compiled.matches(&chunk("src/net/retry.rs", "Function")));
+        assert!(compiled.matches(&chunk("scripts/tools/gen.py", "function")));
+        assert!(!compiled.matches(&chunk("tests/retry.rs", "Function")));
+        assert!(!compiled.matches(&chunk("src/net/retry.rs", "Struct")));
+    }
+
+    #[test]
+    fn test_filter_excludes_languages_and_tests() {
+        let filter = SearchFilter {
+            exclude_paths: vec!["generated".to_string()],
+            languages: vec!["rust".to_string(), "py".to_string()],
+            exclude_tests: true,
+            ..Default::default()
+        };
+        let compiled = filter.compile().expect("Failed to compile filter");
+        assert!(compiled.matches(&chunk("src/lib.rs", "Function")));
+        assert!(compiled.matches(&chunk("tools/gen.py", "Function")));
+        assert!(!compiled.matches(&chunk("cmd/main.go", "Function")));
+        assert!(!compiled.matches(&chunk("src/generated/api.rs", "Function")));
+        assert!(!compiled.matches(&chunk("tests/retry.rs", "Function")));
+        assert!(!compiled.matches(&chunk("tools/test_gen.py", "Function")));
+
+        let mut test_fn = chunk("src/lib.rs", "Function");
+        test_fn.symbol_name = "test_retry".to_string();
+        assert!(!compiled.matches(&test_fn));
+        let mut tagged = chunk("src/lib.rs", "Function");
+        tagged.is_test = true;
+        assert!(!compiled.matches(&tagged));
+
+        let only_tests = SearchFilter {
+            only_tests: true,
+            ..Default::default()
+        };
+        let compiled = only_tests.compile().expect("Failed to compile filter");
+        assert!(compiled.matches(&tagged));
+        assert!(compiled.matches(&chunk("tests/retry.rs", "Function")));
+        assert!(!compiled.matches(&chunk("src/lib.rs", "Function")));
+
+        let public_only = SearchFilter {
+            public_only: true,
+            ..Default::default()
+        };
+        let compiled = public_only.compile().expect("Failed to compile filter");
+        let mut public_fn = chunk("src/lib.rs", "Function");
+        public_fn.modifiers.visibility = Some(Visibility::Public);
+        assert!(compiled.matches(&public_fn));
+        public_fn.modifiers.visibility = Some(Visibility::Internal);
+        assert!(!compiled.matches(&public_fn));
+        assert!(!compiled.matches(&chunk("src/lib.rs", "Function")));
+
+        let nontrivial = SearchFilter {
+            min_lines: Some(3),
+            min_branches: Some(1),
+            ..Default::default()
+        };
+        let compiled = nontrivial.compile().expect("Failed to compile filter");
+        let mut getter = chunk("src/lib.rs", "Method");
+        getter.metrics = SymbolMetrics {
+            lines: 3,
+            tokens: 12,
+            branches: 0,
+        };
+        assert!(!compiled.matches(&getter));
+        getter.metrics.branches = 2;
+        assert!(compiled.matches(&getter));
+
+        let unknown = SearchFilter {
+            languages: vec!["cobol".to_string()],
+            ..Default::default()
+        };
+        assert!(unknown.compile().is_err());
+    }
+
+    #[test]
+    fn test_inline_filter_overrides_defaults() {
+        let defaults = SearchFilter {
+            kinds: vec!["struct".to_string()],
+            exclude_paths: vec!["vendor/**".to_string()],
+            exclude_tests: true,
+            ..Default::default()
+        };
+        let (_, inline) = SearchFilter::parse_inline("parser kind:function exclude:gen");
+        let filter = inline.with_defaults(&defaults);
+        assert_eq!(filter.kinds, vec!["function"]);
+        assert_eq!(filter.exclude_paths, vec!["gen", "vendor/**"]);
+        assert!(filter.exclude_tests);
+    }
+
+    #[test]
+    fn metadata_filters_narrow_by_name_and_kind_in_qdrant() {
+        assert_eq!(metadata_filter(None, None), None);
+        assert_eq!(
+            metadata_filter(Some("parse_file"), Some("function")),
+            Some(Filter::must([
+                Condition::matches_text("symbol_name", "parse_file"),
+                Condition::matches("symbol_kind", "Function".to_string()),
+            ]))
+        );
+        // A kind no symbol has is matched as given, so nothing is scrolled
+        assert_eq!(
+            metadata_filter(None, Some("widget")),
+            Some(Filter::must([Condition::matches(
+                "symbol_kind",
+                "widget".to_string()
+            )]))
+        );
+    }
+
+    #[test]
+    fn metadata_names_and_paths_match_mid_word() {
+        let mut parser = chunk("src/retriever.rs", "Struct");
+        parser.symbol_name = "FileParser".to_string();
+        assert_eq!(metadata_score(&parser, Some("arse"), None, None), Some(0.6));
+        assert_eq!(
+            metadata_score(&parser, Some("parser"), None, None),
+            Some(0.6)
+        );
+        assert_eq!(
+            metadata_score(&parser, Some("filepa"), None, None),
+            Some(0.8)
+        );
+        assert_eq!(
+            metadata_score(&parser, Some("fileparser"), None, None),
+            Some(1.0)
+        );
+        assert_eq!(metadata_score(&parser, Some("parsing"), None, None), None);
+
+        assert_eq!(
+            metadata_score(&parser, None, Some("struct"), Some("c/retr")),
+            Some(1.0)
+        );
+        assert_eq!(metadata_score(&parser, None, Some("function"), None), None);
+        assert_eq!(metadata_score(&parser, None, None, Some("lib")), None);
+    }
+}
@@ -0,0 +1,567 @@
+use std::collections::HashSet;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::PoisonError;
+
+use axum::Json;
+use axum::Router;
+use axum::extract::Query;
+use axum::extract::State;
+use axum::http::HeaderMap;
+use axum::http::StatusCode;
+use axum::http::header::AUTHORIZATION;
+use axum::response::IntoResponse;
+use axum::response::Response;
+use axum::response::sse::Event;
+use axum::response::sse::KeepAlive;
+use axum::response::sse::Sse;
+use axum::routing::get;
+use axum::routing::post;
+use futures::Stream;
+use serde::Deserialize;
+use serde::Serialize;
+use tokio::sync::broadcast;
+use tokio_util::sync::CancellationToken;
+use tracing::info;
+use tracing::warn;
+
+use crate::file_state::StateSummary;
+use crate::file_state::summarize_state;
+use crate::index_config::IndexConfig;
+use crate::indexer::index_lock;
+use crate::notifications::IndexEvent;
+use crate::notifications::IndexNotification;
+use crate::notifications::send_webhooks;
+use crate::retriever::MetadataQuery;
+use crate::retriever::SearchResult;
+use crate::retriever::search_codebase;
+use crate::retriever::search_codebase_metadata;
+use crate::team::AccessError;
+use crate::team::Role;
+use crate::team::TeamConfig;
+use crate::team::bearer_token;
+use crate::vector_db::restore_session;
+
+/// Port `codebase-search serve` listens on unless told otherwise
+pub const DEFAULT_PORT: u16 = 8080;
+
+/// Notifications kept for `/events` subscribers that fall behind
+const EVENT_BUFFER: usize = 256;
+
+/// Query parameters for `GET /search`
+#[derive(Debug, Clone, Deserialize)]
+pub struct SearchRequest {
+    pub query: String,
+    /// Codebase whose collection is searched
+    #[serde(default)]
+    pub directory: PathBuf,
+    /// Project to search instead of `directory`, on a team server
+    pub project: Option<String>,
+    #[serde(default = "default_limit")]
+    pub limit: usize,
+    #[serde(default = "default_min_score")]
+    pub min_score: f32,
+    /// Match symbol names from the payload instead of embedding the query
+    #[serde(default)]
+    pub metadata_only: bool,
+    pub kind: Option<String>,
+    pub path: Option<String>,
+}
+
+fn default_limit() -> usize {
+    10
+}
+
+fn default_min_score() -> f32 {
+    0.7
+}
+
+/// Body of `POST /index` and query parameters of `GET /status`
+#[derive(Debug, Clone, Deserialize)]
+pub struct DirectoryRequest {
+    #[serde(default)]
+    pub directory: PathBuf,
+    /// Project to use instead of `directory`, on a team server
+    pub project: Option<String>,
+}
+
+/// Query parameters for `GET /events`
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct EventsRequest {
+    /// Only stream notifications about this codebase
+    pub directory: Option<PathBuf>,
+    /// Only stream notifications about this project, on a team server
+    pub project: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchResponse {
+    pub results: Vec<SearchResult>,
+}
+
+/// Error body returned by every endpoint
+#[derive(Debug, Clone, Serialize)]
+pub struct ErrorResponse {
+    pub error: String,
+}
+
+/// An error mapped to an HTTP status with a JSON body
+struct ApiError {
+    status: StatusCode,
+    message: String,
+}
+
+impl ApiError {
+    fn internal(error: anyhow::Error) -> Self {
+        Self {
+            status: StatusCode::INTERNAL_SERVER_ERROR,
+            message: error.to_string(),
+        }
+    }
+}
+
+impl From<AccessError> for ApiError {
+    fn from(error: AccessError) -> Self {
+        let status = match error {
+            AccessError::Unauthenticated => StatusCode::UNAUTHORIZED,
+            AccessError::Forbidden(_) => StatusCode::FORBIDDEN,
+            AccessError::UnknownProject(_) => StatusCode::NOT_FOUND,
+            AccessError::ProjectRequired => StatusCode::BAD_REQUEST,
+        };
+        Self {
+            status,
+            message: error.to_string(),
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (
+            self.status,
+            Json(ErrorResponse {
+                error: self.message,
+            }),
+        )
+            .into_response()
+    }
+}
+
+/// Shared between requests
+#[derive(Debug, Clone)]
+struct ServerState {
+    /// Codebases with an index run in flight; a second `/index` for one is rejected
+    indexing: Arc<Mutex<HashSet<PathBuf>>>,
+    /// Streamed to `/events` subscribers
+    events: broadcast::Sender<IndexNotification>,
+    /// Projects and tokens on a team server; any directory may be used without one
+    team: Option<Arc<TeamConfig>>,
+}
+
+impl ServerState {
+    fn new(events: broadcast::Sender<IndexNotification>, team: Option<Arc<TeamConfig>>) -> Self {
+        Self {
+            indexing: Arc::default(),
+            events,
+            team,
+        }
+    }
+
+    /// The codebase a request is about, once the caller may act on it as `role`
+    fn resolve(
+        &self,
+        headers: &HeaderMap,
+        project: Option<&str>,
+        directory: &Path,
+        role: Role,
+    ) -> Result<PathBuf, ApiError> {
+        let Some(team) = &self.team else {
+            if directory.as_os_str().is_empty() {
+                return Err(ApiError {
+                    status: StatusCode::BAD_REQUEST,
+                    message: "directory is required".to_string(),
+                });
+            }
+            return Ok(canonical_directory(directory));
+        };
+        let project = team.authorize(request_token(headers), project, role)?;
+        Ok(project.path.clone())
+    }
+}
+
+/// The API token sent as `Authorization: Bearer <token>`
+fn request_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(bearer_token)
+}
+
+/// Removes a codebase from `ServerState::indexing` when its index run ends
+struct IndexingGuard {
+    indexing: Arc<Mutex<HashSet<PathBuf>>>,
+    directory: PathBuf,
+}
+
+impl Drop for IndexingGuard {
+    fn drop(&mut self) {
+        self.indexing
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(&self.directory);
+    }
+}
+
+/// Resolve a requested directory the same way the CLI does
+fn canonical_directory(directory: &Path) -> PathBuf {
+    directory
+        .canonicalize()
+        .unwrap_or_else(|_| directory.to_path_buf())
+}
+
+/// Build the HTTP API
+/// - `GET /search?query=..&directory=..[&limit=..&min_score=..&metadata_only=true]`
+/// - `POST /index` with `{"directory": ".."}` indexes (or incrementally updates) a codebase
+/// - `GET /status?directory=..` summarizes the saved index state
+/// - `GET /events[?directory=..]` streams the outcome of `/index` runs as server-sent events
+pub fn router() -> Router {
+    api_router(None)
+}
+
+/// `router` for a team: requests name a project from `team` with `project` instead of
+/// a directory, and need a token whose role allows the request (see `team`)
+pub fn team_router(team: TeamConfig) -> Router {
+    api_router(Some(Arc::new(team)))
+}
+
+fn api_router(team: Option<Arc<TeamConfig>>) -> Router {
+    let (events, _) = broadcast::channel(EVENT_BUFFER);
+    Router::new()
+        .route("/search", get(search))
+        .route("/index", post(index))
+        .route("/status", get(status))
+        .route("/events", get(stream_events))
+        .with_state(ServerState::new(events, team))
+}
+
+/// Just `GET /events`, streaming notifications published to `events` elsewhere
+/// (e.g. by a watcher)
+pub fn events_router(events: broadcast::Sender<IndexNotification>) -> Router {
+    Router::new()
+        .route("/events", get(stream_events))
+        .with_state(ServerState::new(events, None))
+}
+
+/// Serve the HTTP API on `addr` until `shutdown` is cancelled
+pub async fn serve(addr: SocketAddr, shutdown: CancellationToken) -> Result<(), anyhow::Error> {
+    serve_router(addr, router(), "codebase search", shutdown).await
+}
+
+/// Serve the HTTP API for `team` on `addr` until `shutdown` is cancelled
+pub async fn serve_team(
+    addr: SocketAddr,
+    team: TeamConfig,
+    shutdown: CancellationToken,
+) -> Result<(), anyhow::Error> {
+    serve_router(addr, team_router(team), "team codebase search", shutdown).await
+}
+
+/// Serve `GET /events` for `events` on `addr` until `shutdown` is cancelled
+pub async fn serve_events(
+    addr: SocketAddr,
+    events: broadcast::Sender<IndexNotification>,
+    shutdown: CancellationToken,
+) -> Result<(), anyhow::Error> {
+    serve_router(addr, events_router(events), "index events", shutdown).await
+}
+
+async fn serve_router(
+    addr: SocketAddr,
+    router: Router,
+    description: &str,
+    shutdown: CancellationToken,
+) -> Result<(), anyhow::Error> {
+    let listener = tokio::net::TcpListener::bind(addr)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to listen on {addr}: {e}"))?;
+    info!("Serving {description} on http://{}", listener.local_addr()?);
+
+    axum::serve(listener, router)
+        .with_graceful_shutdown(async move { shutdown.cancelled().await })
+        .await
+        .map_err(|e| anyhow::anyhow!("Server error: {e}"))
+}
+
+async fn search(
+    State(state): State<ServerState>,
+    headers: HeaderMap,
+    Query(request): Query<SearchRequest>,
+) -> Result<Json<SearchResponse>, ApiError> {
+    let directory = state.resolve(
+        &headers,
+        request.project.as_deref(),
+        &request.directory,
+        Role::Reader,
+    )?;
+    let results = if request.metadata_only {
+        let query = MetadataQuery {
+            name: Some(request.query),
+            kind: request.kind,
+            path: request.path,
+            ..Default::default()
+        };
+        search_codebase_metadata(query, &directory, request.limit).await
+    } else {
+        search_codebase(request.query, &directory, request.limit, request.min_score).await
+    };
+    let results = results.map_err(ApiError::internal)?;
+    Ok(Json(SearchResponse { results }))
+}
+
+async fn index(
+    State(state): State<ServerState>,
+    headers: HeaderMap,
+    Json(request): Json<DirectoryRequest>,
+) -> Result<Json<StateSummary>, ApiError> {
+    let directory = state.resolve(
+        &headers,
+        request.project.as_deref(),
+        &request.directory,
+        Role::Maintainer,
+    )?;
+    let inserted = state
+        .indexing
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .insert(directory.clone());
+    if !inserted {
+        return Err(ApiError {
+            status: StatusCode::CONFLICT,
+            message: format!("{} is already being indexed", directory.display()),
+        });
+    }
+    let _guard = IndexingGuard {
+        indexing: state.indexing.clone(),
+        directory: directory.clone(),
+    };
+
+    // Waits for a watcher in this process that is updating the same index
+    let lock = index_lock(&directory);
+    let _lock = lock.lock().await;
+
+    info!("Indexing {} on request", directory.display());
+    let result = match restore_session(&directory).await {
+        Ok(()) => summarize_state(&directory),
+        Err(e) => {
+            warn!("Indexing {} failed: {e}", directory.display());
+            Err(e)
+        }
+    };
+    let event = match &result {
+        Ok(summary) => IndexEvent::Indexed {
+            tracked_files: summary.tracked_files,
+            tracked_chunks: summary.tracked_chunks,
+        },
+        Err(e) => IndexEvent::Failed {
+            error: e.to_string(),
+        },
+    };
+    publish(
+        &state,
+        &directory,
+        IndexNotification::new(&directory, event),
+    );
+    result.map(Json).map_err(ApiError::internal)
+}
+
+/// Send a notification to `/events` subscribers and the project's webhooks
+fn publish(state: &ServerState, directory: &Path, notification: IndexNotification) {
+    match IndexConfig::load(directory) {
+        Ok(config) => send_webhooks(&config.hooks.webhooks, &notification),
+        Err(e) => warn!("Not notifying webhooks for {}: {e}", directory.display()),
+    }
+    // Nobody may be subscribed
+    let _ = state.events.send(notification);
+}
+
+async fn stream_events(
+    State(state): State<ServerState>,
+    headers: HeaderMap,
+    Query(request): Query<EventsRequest>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, ApiError> {
+    // Roots whose notifications are streamed; None streams every root
+    let roots = match (&state.team, request.project, request.directory) {
+        (Some(team), None, _) => Some(team.readable_roots(request_token(&headers))?),
+        (_, project, directory) if project.is_some() || directory.is_some() => {
+            let directory = directory.unwrap_or_default();
+            Some(vec![state.resolve(
+                &headers,
+                project.as_deref(),
+                &directory,
+                Role::Reader,
+            )?])
+        }
+        _ => None,
+    };
+    let events = state.events.subscribe();
+    let stream = futures::stream::unfold(events, move |mut events| {
+        let roots = roots.clone();
+        async move {
+            loop {
+                match events.recv().await {
+                    Ok(notification) => {
+                        if roots
+                            .as_ref()
+                            .is_some_and(|roots| !roots.contains(&notification.root_path))
+                        {
+                            continue;
+                        }
+                        match Event::default()
+                            .event(notification.event_name())
+                            .json_data(&notification)
+                        {
+                            Ok(event) => return Some((Ok(event), events)),
+                            Err(e) => warn!("Failed to encode notification: {e}"),
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        warn!("Event subscriber fell behind, skipped {skipped} notifications");
+                    }
+                    Err(broadcast::error::RecvError::Closed) => return None,
+                }
+            }
+        }
+    });
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
+async fn status(
+    State(state): State<ServerState>,
+    headers: HeaderMap,
+    Query(request): Query<DirectoryRequest>,
+) -> Result<Json<StateSummary>, ApiError> {
+    let directory = state.resolve(
+        &headers,
+        request.project.as_deref(),
+        &request.directory,
+        Role::Reader,
+    )?;
+    summarize_state(&directory).map(Json).map_err(|e| ApiError {
+        status: StatusCode::NOT_FOUND,
+        message: e.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::expect_used)]
+    use super::*;
+    use crate::file_state::CodebaseState;
+    use crate::file_state::FileState;
+    use crate::file_state::HashAlgorithm;
+    use crate::file_state::state_file_path;
+    use std::collections::HashMap;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_status_endpoint() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let shutdown = CancellationToken::new();
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("Failed to bind");
+        let addr = listener.local_addr().expect("Failed to read address");
+        tokio::spawn({
+            let shutdown = shutdown.clone();
+            async move {
+                axum::serve(listener, router())
+                    .with_graceful_shutdown(async move { shutdown.cancelled().await })
+                    .await
+            }
+        });
+
+        let client = reqwest::Client::new();
+        let url = format!("http://{addr}/status");
+        let missing = client
+            .get(&url)
+            .query(&[("directory", temp_dir.path())])
+            .send()
+            .await
+            .expect("Request failed");
+        assert_eq!(missing.status().as_u16(), 404);
+
+        CodebaseState::new(HashMap::from([(
+            "main.py".to_string(),
+            FileState {
+                content_hash: "abc".to_string(),
+                hash_algorithm: HashAlgorithm::Sha256,
+                last_modified: 1,
+                chunk_ids: vec!["point-1".to_string(), "point-2".to_string()],
+            },
+        )]))
+        .to_file(&state_file_path(temp_dir.path()).expect("Failed to resolve state path"))
+        .expect("Failed to write state");
+
+        let summary: serde_json::Value = client
+            .get(&url)
+            .query(&[("directory", temp_dir.path())])
+            .send()
+            .await
+            .expect("Request failed")
+            .json()
+            .await
+            .expect("Invalid JSON");
+        assert_eq!(summary["tracked_files"], 1);
+        assert_eq!(summary["tracked_chunks"], 2);
+
+        shutdown.cancel();
+    }
+
+    #[test]
+    fn team_requests_need_a_token_with_the_right_role() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let config_path = temp_dir.path().join("team.toml");
+        std::fs::write(
+            &config_path,
+            format!(
+                "[[projects]]\nname = \"repo\"\npath = {:?}\n\n\
+                 [[tokens]]\nname = \"agent\"\ntoken = \"secret\"\nrole = \"reader\"\n",
+                temp_dir.path()
+            ),
+        )
+        .expect("Failed to write team config");
+        let team = TeamConfig::load(&config_path).expect("Valid team config");
+        let (events, _) = broadcast::channel(EVENT_BUFFER);
+        let state = ServerState::new(events, Some(Arc::new(team)));
+
+        let mut headers = HeaderMap::new();
+        let status = |headers: &HeaderMap, role| {
+            state
+                .resolve(headers, None, Path::new(""), role)
+                .map_err(|error| error.status)
+        };
+        assert_eq!(
+            status(&headers, Role::Reader),
+            Err(StatusCode::UNAUTHORIZED)
+        );
+        headers.insert(
+            AUTHORIZATION,
+            "Bearer secret".parse().expect("Valid header"),
+        );
+        assert_eq!(
+            status(&headers, Role::Reader),
+            Ok(temp_dir
+                .path()
+                .canonicalize()
+                .expect("Failed to canonicalize"))
+        );
+        assert_eq!(
+            status(&headers, Role::Maintainer),
+            Err(StatusCode::FORBIDDEN)
+        );
+    }
+}
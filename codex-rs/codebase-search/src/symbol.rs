@@ -1,23 +1,44 @@
 use serde::Deserialize;
 use serde::Serialize;
+use std::cell::Cell;
 use std::collections::HashMap;
+use std::collections::HashSet;
+#[cfg(feature = "native")]
 use std::fs;
 use std::path::Path;
 use std::path::PathBuf;
+use std::sync::Arc;
+#[cfg(feature = "native")]
 use std::time::UNIX_EPOCH;
 use tracing::debug;
+#[cfg(feature = "native")]
 use tracing::info;
+#[cfg(feature = "native")]
 use tracing::warn;
 
 use tree_sitter::Node;
 use tree_sitter::Parser;
 
+use crate::language_registry::ExternalLanguage;
+use crate::language_registry::LanguageRegistry;
+
+#[cfg(feature = "native")]
 use crate::walk_utils::is_supported_file_extension;
+#[cfg(feature = "native")]
 use crate::walk_utils::walk_codebase_files;
 use tree_sitter::Tree;
 
+use crate::symbol_query::Definition;
+use crate::symbol_query::SymbolQuery;
+
+#[cfg(feature = "native")]
 use crate::file_state::CodebaseState;
+#[cfg(feature = "native")]
 use crate::file_state::FileState;
+#[cfg(feature = "native")]
+use crate::file_state::state_file_path;
+#[cfg(feature = "native")]
+use crate::index_config::IndexConfig;
 
 /// Represents a code symbol that can be indexed for semantic search
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -40,6 +61,110 @@ pub struct Symbol {
     pub end_column: usize,
     /// Additional context (e.g., class name for methods)
     pub context: Option<String>,
+    /// Generic type parameters with their constraints (`T any`, `K comparable`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub type_parameters: Vec<String>,
+    /// The declaration up to where the body starts (`pub fn parse(input: &str) -> Ast`),
+    /// or the first line for symbols without a body
+    #[serde(default)]
+    pub signature: String,
+    /// Visibility and modifiers, filled in once the whole file has been walked
+    #[serde(default)]
+    pub modifiers: SymbolModifiers,
+    /// Size and branching, filled in with the modifiers
+    #[serde(default)]
+    pub metrics: SymbolMetrics,
+    /// Whether this is test code: a test, something in a test file or module, or a
+    /// test helper nested in one
+    #[serde(default)]
+    pub is_test: bool,
+    /// Stable ID from the kind, qualified name and whitespace-normalized content, so
+    /// edits elsewhere in the file leave it unchanged
+    #[serde(default)]
+    pub id: String,
+}
+
+/// Which symbols to keep, by whether they're test code
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TestFilter {
+    #[default]
+    Include,
+    Exclude,
+    Only,
+}
+
+impl TestFilter {
+    /// `--exclude-tests` and `--only-tests`, which can't both be set
+    pub fn from_flags(exclude_tests: bool, only_tests: bool) -> Self {
+        match (exclude_tests, only_tests) {
+            (true, _) => TestFilter::Exclude,
+            (false, true) => TestFilter::Only,
+            (false, false) => TestFilter::Include,
+        }
+    }
+
+    pub fn keeps(&self, is_test: bool) -> bool {
+        match self {
+            TestFilter::Include => true,
+            TestFilter::Exclude => !is_test,
+            TestFilter::Only => is_test,
+        }
+    }
+}
+
+/// How big and how branchy a symbol is, so trivial ones (getters, re-exports) can be
+/// told apart from real logic
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SymbolMetrics {
+    pub lines: usize,
+    /// Rough token count, at about four characters per token
+    pub tokens: usize,
+    /// Decision points in the body: conditionals, loops, match arms and cases, catch
+    /// clauses and `&&`/`||`, the cyclomatic complexity minus one
+    pub branches: usize,
+}
+
+/// Declared visibility of a symbol, in its language's terms
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum Visibility {
+    Public,
+    Protected,
+    /// Visible within its crate, package or assembly (`pub(crate)`, Java's package
+    /// default, C# `internal`)
+    Internal,
+    Private,
+}
+
+impl Visibility {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Visibility::Public => "public",
+            Visibility::Protected => "protected",
+            Visibility::Internal => "internal",
+            Visibility::Private => "private",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "public" => Some(Visibility::Public),
+            "protected" => Some(Visibility::Protected),
+            "internal" => Some(Visibility::Internal),
+            "private" => Some(Visibility::Private),
+            _ => None,
+        }
+    }
+}
+
+/// Visibility and modifiers of a symbol's declaration
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SymbolModifiers {
+    /// `None` where the language has no such notion or leaves it to the enclosing scope
+    pub visibility: Option<Visibility>,
+    pub is_async: bool,
+    pub is_static: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
@@ -56,6 +181,20 @@ pub enum SymbolKind {
     Method,
     Interface,
     Type,
+    /// A preprocessor macro
+    Macro,
+    /// A property with accessors, e.g. C# `get; set;`
+    Property,
+    /// A SQL table
+    Table,
+    /// A SQL view or materialized view
+    View,
+    /// An infrastructure object: a Terraform resource or data source, or a Kubernetes
+    /// style manifest
+    Resource,
+    /// An import of another module or package (`use`, `import`, `#include`), named
+    /// after what it imports
+    Import,
 }
 
 /// Supported programming languages for parsing
@@ -65,78 +204,217 @@ pub enum SupportedLanguage {
     Rust,
     Python,
     Go,
+    /// `.ts` and `.tsx`, each parsed with its own grammar
+    TypeScript,
+    /// `.js`, `.mjs` and `.jsx`; the grammar includes JSX
+    JavaScript,
+    Java,
+    /// `.c` and `.h`
+    C,
+    /// `.cc`, `.cpp` and `.hpp`
+    Cpp,
+    CSharp,
+    /// `.scala` and `.sc`
+    Scala,
+    /// `.ex` and `.exs`
+    Elixir,
+    Haskell,
+    Dart,
+    Sql,
+    Protobuf,
+    /// `.graphql` and `.gql`
+    GraphQl,
+    /// Terraform's `.tf`, parsed with the HCL grammar
+    Terraform,
+    /// `.yaml` and `.yml`; only walked when `[manifests] yaml` is set
+    Yaml,
+    /// A grammar loaded at runtime (see `language_registry`)
+    External(Arc<ExternalLanguage>),
+}
+
+impl SymbolKind {
+    /// Parse a lowercase kind name as used by `--kind` filters and language configs
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "function" => Some(SymbolKind::Function),
+            "method" => Some(SymbolKind::Method),
+            "struct" => Some(SymbolKind::Struct),
+            "class" => Some(SymbolKind::Class),
+            "enum" => Some(SymbolKind::Enum),
+            "trait" => Some(SymbolKind::Trait),
+            "interface" => Some(SymbolKind::Interface),
+            "impl" => Some(SymbolKind::Impl),
+            "module" => Some(SymbolKind::Module),
+            "constant" => Some(SymbolKind::Constant),
+            "variable" => Some(SymbolKind::Variable),
+            "type" => Some(SymbolKind::Type),
+            "macro" => Some(SymbolKind::Macro),
+            "property" => Some(SymbolKind::Property),
+            "table" => Some(SymbolKind::Table),
+            "view" => Some(SymbolKind::View),
+            "resource" => Some(SymbolKind::Resource),
+            "import" => Some(SymbolKind::Import),
+            _ => None,
+        }
+    }
 }
 
 impl SupportedLanguage {
+    /// The registered language of files with extension `ext`
     pub fn from_extension(ext: &str) -> Option<Self> {
-        match ext.to_lowercase().as_str() {
-            "rs" => Some(SupportedLanguage::Rust),
-            "py" => Some(SupportedLanguage::Python),
-            "go" => Some(SupportedLanguage::Go),
-            _ => None,
+        LanguageRegistry::global()
+            .by_extension(ext)
+            .map(|entry| entry.language.clone())
+    }
+
+    /// Look a language up by name or extension (`rust`, `rs`, `golang`, ...)
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "rust" => Some(SupportedLanguage::Rust),
+            "python" => Some(SupportedLanguage::Python),
+            "go" | "golang" => Some(SupportedLanguage::Go),
+            "typescript" => Some(SupportedLanguage::TypeScript),
+            "javascript" => Some(SupportedLanguage::JavaScript),
+            "c++" => Some(SupportedLanguage::Cpp),
+            "csharp" | "c#" => Some(SupportedLanguage::CSharp),
+            "protobuf" => Some(SupportedLanguage::Protobuf),
+            "terraform" | "hcl" => Some(SupportedLanguage::Terraform),
+            other => match LanguageRegistry::global().by_name(other) {
+                Some(entry) => Some(entry.language.clone()),
+                None => Self::from_extension(other),
+            },
+        }
+    }
+
+    /// Lowercase name, e.g. `rust` or the `name` of an external language
+    pub fn name(&self) -> String {
+        match self {
+            SupportedLanguage::External(language) => language.name.to_lowercase(),
+            builtin => format!("{builtin:?}").to_lowercase(),
         }
     }
 
+    /// Grammar for the language; TSX files use `tree_sitter_typescript::LANGUAGE_TSX`
+    /// instead of the TypeScript one returned here
     pub fn tree_sitter_language(&self) -> tree_sitter::Language {
         match self {
             SupportedLanguage::Rust => tree_sitter_rust::LANGUAGE.into(),
             SupportedLanguage::Python => tree_sitter_python::LANGUAGE.into(),
             SupportedLanguage::Go => tree_sitter_go::LANGUAGE.into(),
+            SupportedLanguage::TypeScript => tree_sitter_typescript::LANGUAGE_TYPESCRIPT.into(),
+            SupportedLanguage::JavaScript => tree_sitter_javascript::LANGUAGE.into(),
+            SupportedLanguage::Java => tree_sitter_java::LANGUAGE.into(),
+            SupportedLanguage::C => tree_sitter_c::LANGUAGE.into(),
+            SupportedLanguage::Cpp => tree_sitter_cpp::LANGUAGE.into(),
+            SupportedLanguage::CSharp => tree_sitter_c_sharp::LANGUAGE.into(),
+            SupportedLanguage::Scala => tree_sitter_scala::LANGUAGE.into(),
+            SupportedLanguage::Elixir => tree_sitter_elixir::LANGUAGE.into(),
+            SupportedLanguage::Haskell => tree_sitter_haskell::LANGUAGE.into(),
+            SupportedLanguage::Dart => tree_sitter_dart::LANGUAGE.into(),
+            SupportedLanguage::Sql => tree_sitter_sequel::LANGUAGE.into(),
+            SupportedLanguage::Protobuf => tree_sitter_proto::LANGUAGE.into(),
+            SupportedLanguage::GraphQl => tree_sitter_graphql::LANGUAGE.into(),
+            SupportedLanguage::Terraform => tree_sitter_hcl::LANGUAGE.into(),
+            SupportedLanguage::Yaml => tree_sitter_yaml::LANGUAGE.into(),
+            SupportedLanguage::External(language) => language.grammar.clone(),
+        }
+    }
+
+    /// Grammar for files with extension `extension` of this language
+    pub fn tree_sitter_language_for(&self, extension: &str) -> tree_sitter::Language {
+        match (self, extension) {
+            (SupportedLanguage::TypeScript, "tsx") => tree_sitter_typescript::LANGUAGE_TSX.into(),
+            _ => self.tree_sitter_language(),
         }
     }
 
     /// Get the file extensions supported by this language
-    pub fn extensions(&self) -> &'static [&'static str] {
-        match self {
+    pub fn extensions(&self) -> Vec<&str> {
+        let builtin: &[&str] = match self {
             SupportedLanguage::Rust => &["rs"],
             SupportedLanguage::Python => &["py"],
             SupportedLanguage::Go => &["go"],
-        }
+            SupportedLanguage::TypeScript => &["ts", "tsx"],
+            SupportedLanguage::JavaScript => &["js", "mjs", "jsx"],
+            SupportedLanguage::Java => &["java"],
+            SupportedLanguage::C => &["c", "h"],
+            SupportedLanguage::Cpp => &["cc", "cpp", "hpp"],
+            SupportedLanguage::CSharp => &["cs"],
+            SupportedLanguage::Scala => &["scala", "sc"],
+            SupportedLanguage::Elixir => &["ex", "exs"],
+            SupportedLanguage::Haskell => &["hs"],
+            SupportedLanguage::Dart => &["dart"],
+            SupportedLanguage::Sql => &["sql"],
+            SupportedLanguage::Protobuf => &["proto"],
+            SupportedLanguage::GraphQl => &["graphql", "gql"],
+            SupportedLanguage::Terraform => &["tf"],
+            SupportedLanguage::Yaml => &["yaml", "yml"],
+            SupportedLanguage::External(language) => {
+                return language.extensions.iter().map(String::as_str).collect();
+            }
+        };
+        builtin.to_vec()
     }
 }
 
 /// Parser for extracting symbols from source code using tree-sitter
 pub struct SymbolParser {
     pub parsers: HashMap<String, Parser>,
+    /// Symbol queries by extension, for the languages described by one
+    queries: HashMap<String, Arc<SymbolQuery>>,
+    /// Duplicate symbols dropped by `extract_symbols` so far
+    duplicates: Cell<usize>,
 }
 
 impl SymbolParser {
+    /// A parser for every extension in the language registry
     pub fn new() -> Result<Self, anyhow::Error> {
         let mut parsers = HashMap::new();
+        let mut queries = HashMap::new();
+        for entry in LanguageRegistry::global().languages() {
+            for extension in entry.language.extensions() {
+                let grammar = entry.language.tree_sitter_language_for(extension);
+                if let Some(query) = SymbolQuery::for_language(&entry.language, &grammar)? {
+                    queries.insert(extension.to_string(), query);
+                }
+                let mut parser = Parser::new();
+                match parser.set_language(&grammar) {
+                    Ok(_) => (),
+                    Err(e) => {
+                        return Err(anyhow::anyhow!(
+                            "Failed to set {} language: {e}",
+                            entry.display_name
+                        ));
+                    }
+                };
+                parsers.insert(extension.to_string(), parser);
+            }
+        }
 
-        // Initialize Rust parser
-        let mut rust_parser = Parser::new();
-        match rust_parser.set_language(&SupportedLanguage::Rust.tree_sitter_language()) {
-            Ok(_) => (),
-            Err(e) => return Err(anyhow::anyhow!("Failed to set language: {e}")),
-        };
-        parsers.insert("rs".to_string(), rust_parser);
-
-        // Initialize Python parser
-        let mut python_parser = Parser::new();
-        match python_parser.set_language(&SupportedLanguage::Python.tree_sitter_language()) {
-            Ok(_) => (),
-            Err(e) => return Err(anyhow::anyhow!("Failed to set Python language: {e}")),
-        };
-        parsers.insert("py".to_string(), python_parser);
-
-        // Initialize Go parser
-        let mut go_parser = Parser::new();
-        match go_parser.set_language(&SupportedLanguage::Go.tree_sitter_language()) {
-            Ok(_) => (),
-            Err(e) => return Err(anyhow::anyhow!("Failed to set Go language: {e}")),
-        };
-        parsers.insert("go".to_string(), go_parser);
-
-        Ok(SymbolParser { parsers })
+        Ok(SymbolParser {
+            parsers,
+            queries,
+            duplicates: Cell::new(0),
+        })
     }
 
     /// Parse a single file and extract all symbols
+    #[cfg(feature = "native")]
     pub fn parse_file<P: AsRef<Path>>(
         &mut self,
         file_path: P,
     ) -> Result<Vec<Symbol>, anyhow::Error> {
         let content = fs::read_to_string(file_path.as_ref())?;
+        self.parse_source(file_path, &content)
+    }
+
+    /// Extract all symbols from `content`, the source of `file_path`
+    /// Nothing is read from disk; the language is picked by the path's extension.
+    pub fn parse_source<P: AsRef<Path>>(
+        &mut self,
+        file_path: P,
+        content: &str,
+    ) -> Result<Vec<Symbol>, anyhow::Error> {
         let extension = file_path
             .as_ref()
             .extension()
@@ -152,10 +430,10 @@ impl SymbolParser {
             .ok_or_else(|| anyhow::anyhow!("No parser available for extension: {extension}"))?;
 
         let tree = parser
-            .parse(&content, None)
+            .parse(content, None)
             .ok_or_else(|| anyhow::anyhow!("Failed to parse file"))?;
 
-        let symbols = self.extract_symbols(&tree, &content, file_path.as_ref(), &language)?;
+        let symbols = self.extract_symbols(&tree, content, file_path.as_ref(), &language)?;
 
         debug!(
             "Extracted {} symbols from {}",
@@ -165,7 +443,13 @@ impl SymbolParser {
         Ok(symbols)
     }
 
+    /// How many duplicate symbols this parser has collapsed
+    pub fn duplicates_collapsed(&self) -> usize {
+        self.duplicates.get()
+    }
+
     /// Extract symbols from a parsed tree
+    /// A symbol extracted twice (same span, kind and name) is only kept once.
     pub fn extract_symbols(
         &self,
         tree: &Tree,
@@ -176,700 +460,1025 @@ impl SymbolParser {
         let mut symbols = Vec::new();
         let root_node = tree.root_node();
 
+        let import_kinds = import_kinds(language);
+        if !import_kinds.is_empty() {
+            self.traverse_imports(root_node, import_kinds, source, file_path, &mut symbols)?;
+        }
+
         match language {
-            SupportedLanguage::Rust => {
-                self.extract_rust_symbols(root_node, source, file_path, &mut symbols)?;
+            SupportedLanguage::Yaml => {
+                self.extract_yaml_manifests(root_node, source, file_path, &mut symbols)?;
             }
-            SupportedLanguage::Python => {
-                self.extract_python_symbols(root_node, source, file_path, &mut symbols)?;
+            SupportedLanguage::External(language) => {
+                self.traverse_external_node(
+                    root_node,
+                    language,
+                    source,
+                    file_path,
+                    &mut symbols,
+                    None,
+                )?;
             }
-            SupportedLanguage::Go => {
-                self.extract_go_symbols(root_node, source, file_path, &mut symbols)?;
+            _ => {
+                let extension = file_path
+                    .extension()
+                    .and_then(|ext| ext.to_str())
+                    .unwrap_or("");
+                let query = self.queries.get(extension).ok_or_else(|| {
+                    anyhow::anyhow!("No symbol query available for extension: {extension}")
+                })?;
+                self.extract_query_symbols(
+                    query,
+                    root_node,
+                    source,
+                    file_path,
+                    language,
+                    &mut symbols,
+                )?;
             }
         }
 
+        let collapsed = dedupe_symbols(&mut symbols);
+        if collapsed > 0 {
+            debug!(
+                "Collapsed {collapsed} duplicate symbols in {}",
+                file_path.display()
+            );
+            self.duplicates.set(self.duplicates.get() + collapsed);
+        }
+
+        let lines: Vec<&str> = source.lines().collect();
+        for symbol in &mut symbols {
+            symbol.modifiers = declared_modifiers(language, symbol, &lines);
+            symbol.signature = declaration_signature(root_node, symbol, source);
+            symbol.metrics = symbol_metrics(root_node, symbol);
+        }
+        if matches!(language, SupportedLanguage::Rust) {
+            inherit_trait_visibility(&mut symbols);
+        }
+        mark_test_code(language, file_path, &mut symbols, &lines);
+        assign_symbol_ids(&mut symbols);
+
         Ok(symbols)
     }
 
-    /// Extract symbols from Rust code
-    fn extract_rust_symbols(
+    /// Build a symbol for each definition `query` finds under `root`
+    fn extract_query_symbols(
         &self,
-        node: Node,
+        query: &SymbolQuery,
+        root: Node,
         source: &str,
         file_path: &Path,
+        language: &SupportedLanguage,
         symbols: &mut Vec<Symbol>,
     ) -> Result<(), anyhow::Error> {
-        self.traverse_rust_node(node, source, file_path, symbols, None)?;
+        let mut spans = Vec::new();
+        for definition in query.definitions(root, source)? {
+            let (first, last) = definition_span(language, definition.node);
+            spans.push((first, last, definition));
+        }
+        if matches!(language, SupportedLanguage::Haskell) {
+            spans = join_haskell_equations(spans);
+        }
+        for (first, last, definition) in spans {
+            let mut symbol = self.span_symbol(
+                first,
+                last,
+                definition.name,
+                definition.kind,
+                source,
+                file_path,
+                &definition.context,
+            )?;
+            refine_query_symbol(language, definition.node, source, &mut symbol)?;
+            symbols.push(symbol);
+        }
         Ok(())
     }
 
-    fn extract_python_symbols(
+    /// Build a symbol spanning `first` through `last`, for declarations split over
+    /// several sibling nodes
+    #[allow(clippy::too_many_arguments)]
+    fn span_symbol(
+        &self,
+        first: Node,
+        last: Node,
+        name: String,
+        kind: SymbolKind,
+        source: &str,
+        file_path: &Path,
+        context: &Option<String>,
+    ) -> Result<Symbol, anyhow::Error> {
+        let content = source
+            .get(first.start_byte()..last.end_byte())
+            .ok_or_else(|| anyhow::anyhow!("Declaration out of range"))?;
+        let start_pos = first.start_position();
+        let end_pos = last.end_position();
+
+        Ok(Symbol {
+            name,
+            kind,
+            content: content.to_string(),
+            file_path: file_path.to_path_buf(),
+            start_line: start_pos.row + 1,
+            end_line: end_pos.row + 1,
+            start_column: start_pos.column,
+            end_column: end_pos.column,
+            context: context.clone(),
+            type_parameters: Vec::new(),
+            signature: String::new(),
+            modifiers: SymbolModifiers::default(),
+            metrics: SymbolMetrics::default(),
+            is_test: false,
+            id: String::new(),
+        })
+    }
+
+    /// Record every import statement of `kinds` under `node` as an `Import` symbol
+    fn traverse_imports(
         &self,
         node: Node,
+        kinds: &[&str],
         source: &str,
         file_path: &Path,
         symbols: &mut Vec<Symbol>,
     ) -> Result<(), anyhow::Error> {
-        self.traverse_python_node(node, source, file_path, symbols, None)?;
+        if kinds.contains(&node.kind()) {
+            let name = import_name(node, source)?;
+            if !name.is_empty() {
+                symbols.push(self.span_symbol(
+                    node,
+                    node,
+                    name,
+                    SymbolKind::Import,
+                    source,
+                    file_path,
+                    &None,
+                )?);
+            }
+            return Ok(());
+        }
+        for child in node.named_children(&mut node.walk()) {
+            self.traverse_imports(child, kinds, source, file_path, symbols)?;
+        }
         Ok(())
     }
 
-    fn extract_go_symbols(
+    /// Extract the documents of a YAML stream that have a `kind` and `metadata.name`
+    /// Each becomes a resource named by `metadata.name` with the kind as context, so
+    /// `kind: Deployment` / `name: api` is `api` in `Deployment`. Other documents have
+    /// nothing to name them by and are skipped.
+    fn extract_yaml_manifests(
         &self,
-        node: Node,
+        root: Node,
         source: &str,
         file_path: &Path,
         symbols: &mut Vec<Symbol>,
     ) -> Result<(), anyhow::Error> {
-        self.traverse_go_node(node, source, file_path, symbols, None)?;
+        for document in root.children(&mut root.walk()) {
+            if document.kind() != "document" {
+                continue;
+            }
+            let Some(mapping) = yaml_block_mapping(document) else {
+                continue;
+            };
+            let kind = yaml_mapping_value(mapping, "kind", source)?;
+            let name = match yaml_mapping_value(mapping, "metadata", source)?
+                .and_then(yaml_block_mapping)
+            {
+                Some(metadata) => yaml_mapping_value(metadata, "name", source)?,
+                None => None,
+            };
+            let (Some(kind), Some(name)) = (kind, name) else {
+                continue;
+            };
+            symbols.push(self.span_symbol(
+                document,
+                document,
+                yaml_scalar(name, source)?,
+                SymbolKind::Resource,
+                source,
+                file_path,
+                &Some(yaml_scalar(kind, source)?),
+            )?);
+        }
         Ok(())
     }
 
-    /// Recursively traverse Rust AST nodes to find symbols
-    fn traverse_rust_node(
+    /// Recursively traverse the AST of an external language, following its symbol rules
+    /// Symbols nested in another get its dotted path as context.
+    fn traverse_external_node(
         &self,
         node: Node,
+        language: &ExternalLanguage,
         source: &str,
         file_path: &Path,
         symbols: &mut Vec<Symbol>,
         context: Option<String>,
     ) -> Result<(), anyhow::Error> {
-        match node.kind() {
-            "function_item" => {
-                if let Some(symbol) =
-                    self.extract_rust_function(node, source, file_path, &context)?
-                {
-                    symbols.push(symbol);
-                }
-            }
-            "struct_item" => {
-                if let Some(symbol) = self.extract_rust_struct(node, source, file_path, &context)? {
-                    let struct_name = symbol.name.clone();
-                    symbols.push(symbol);
-
-                    // For struct implementations, pass the struct name as context
-                    for child in node.children(&mut node.walk()) {
-                        self.traverse_rust_node(
-                            child,
-                            source,
-                            file_path,
-                            symbols,
-                            Some(struct_name.clone()),
-                        )?;
-                    }
-                    return Ok(());
-                }
-            }
-            "enum_item" => {
-                if let Some(symbol) = self.extract_rust_enum(node, source, file_path, &context)? {
-                    symbols.push(symbol);
-                }
-            }
-            "trait_item" => {
-                if let Some(symbol) = self.extract_rust_trait(node, source, file_path, &context)? {
-                    symbols.push(symbol);
-                }
+        let mut child_context = context.clone();
+        if let Some(kind) = language.symbols.get(node.kind()) {
+            let name = match node.child_by_field_name(&language.name_field) {
+                Some(name_node) => Some(name_node.utf8_text(source.as_bytes())?.to_string()),
+                None => self.find_child_text(node, "identifier", source)?,
+            };
+            if let Some(name) = name {
+                symbols.push(self.span_symbol(
+                    node,
+                    node,
+                    name.clone(),
+                    kind.clone(),
+                    source,
+                    file_path,
+                    &context,
+                )?);
+                child_context = Some(match &context {
+                    Some(context) => format!("{context}.{name}"),
+                    None => name,
+                });
             }
-            "impl_item" => {
-                if let Some(symbol) = self.extract_rust_impl(node, source, file_path, &context)? {
-                    let impl_context = Some(symbol.name.clone());
-                    symbols.push(symbol);
-
-                    // Extract methods from impl block
-                    for child in node.children(&mut node.walk()) {
-                        self.traverse_rust_node(
-                            child,
-                            source,
-                            file_path,
-                            symbols,
-                            impl_context.clone(),
-                        )?;
-                    }
-                    return Ok(());
-                }
-            }
-            "const_item" | "static_item" => {
-                if let Some(symbol) =
-                    self.extract_rust_constant(node, source, file_path, &context)?
-                {
-                    symbols.push(symbol);
-                }
-            }
-            "mod_item" => {
-                if let Some(symbol) = self.extract_rust_module(node, source, file_path, &context)? {
-                    symbols.push(symbol);
-                }
-            }
-            _ => {}
         }
 
         // Continue traversing child nodes
         for child in node.children(&mut node.walk()) {
-            self.traverse_rust_node(child, source, file_path, symbols, context.clone())?;
+            self.traverse_external_node(
+                child,
+                language,
+                source,
+                file_path,
+                symbols,
+                child_context.clone(),
+            )?;
         }
 
         Ok(())
     }
 
-    /// Extract function symbol from Rust code
-    fn extract_rust_function(
+    /// Helper function to find text content of a child node with specific kind
+    fn find_child_text(
         &self,
         node: Node,
+        kind: &str,
         source: &str,
-        file_path: &Path,
-        context: &Option<String>,
-    ) -> Result<Option<Symbol>, anyhow::Error> {
-        // Find function name
-        let name = self
-            .find_child_text(node, "identifier", source)?
-            .ok_or_else(|| anyhow::anyhow!("Function missing name"))?;
-
-        let content = node.utf8_text(source.as_bytes())?;
-        let start_pos = node.start_position();
-        let end_pos = node.end_position();
-
-        let kind = if context.is_some() {
-            SymbolKind::Method
-        } else {
-            SymbolKind::Function
-        };
-
-        Ok(Some(Symbol {
-            name,
-            kind,
-            content: content.to_string(),
-            file_path: file_path.to_path_buf(),
-            start_line: start_pos.row + 1,
-            end_line: end_pos.row + 1,
-            start_column: start_pos.column,
-            end_column: end_pos.column,
-            context: context.clone(),
-        }))
+    ) -> Result<Option<String>, anyhow::Error> {
+        for child in node.children(&mut node.walk()) {
+            if child.kind() == kind {
+                let text = child.utf8_text(source.as_bytes())?;
+                return Ok(Some(text.to_string()));
+            }
+        }
+        Ok(None)
     }
+}
 
-    /// Extract struct symbol from Rust code
-    fn extract_rust_struct(
-        &self,
-        node: Node,
-        source: &str,
-        file_path: &Path,
-        context: &Option<String>,
-    ) -> Result<Option<Symbol>, anyhow::Error> {
-        let name = self
-            .find_child_text(node, "type_identifier", source)?
-            .ok_or_else(|| anyhow::anyhow!("Struct missing name"))?;
+/// Type parameters of a generic Go function or type, one per name: `[K comparable, V any]`
+/// and `[K, V any]` give `K comparable`/`V any` and `K any`/`V any`
+fn go_type_parameters(node: Node, source: &str) -> Result<Vec<String>, anyhow::Error> {
+    let Some(list) = node.child_by_field_name("type_parameters") else {
+        return Ok(Vec::new());
+    };
+    let mut parameters = Vec::new();
+    for declaration in list.named_children(&mut list.walk()) {
+        let constraint = match declaration.child_by_field_name("type") {
+            Some(constraint) => constraint.utf8_text(source.as_bytes())?,
+            None => continue,
+        };
+        for name in declaration.children_by_field_name("name", &mut declaration.walk()) {
+            parameters.push(format!(
+                "{} {constraint}",
+                name.utf8_text(source.as_bytes())?
+            ));
+        }
+    }
+    Ok(parameters)
+}
 
-        let content = node.utf8_text(source.as_bytes())?;
-        let start_pos = node.start_position();
-        let end_pos = node.end_position();
+/// Drop symbols with the same span, kind and name as an earlier one, which a traversal
+/// that visits a node both as a container's member and as a child produces; declarators
+/// sharing a declaration's span keep their own names. Returns how many were dropped.
+pub fn dedupe_symbols(symbols: &mut Vec<Symbol>) -> usize {
+    let before = symbols.len();
+    let mut seen = HashSet::new();
+    symbols.retain(|symbol| {
+        seen.insert((
+            symbol.start_line,
+            symbol.start_column,
+            symbol.end_line,
+            symbol.end_column,
+            symbol.kind.clone(),
+            symbol.name.clone(),
+        ))
+    });
+    before - symbols.len()
+}
 
-        Ok(Some(Symbol {
-            name,
-            kind: SymbolKind::Struct,
-            content: content.to_string(),
-            file_path: file_path.to_path_buf(),
-            start_line: start_pos.row + 1,
-            end_line: end_pos.row + 1,
-            start_column: start_pos.column,
-            end_column: end_pos.column,
-            context: context.clone(),
-        }))
+/// What a symbol query can't express: functions nested in a Rust or Python container
+/// are methods, Rust impls are named `impl <type>` and procedural macros after how
+/// they're invoked, and generic Go declarations carry their type parameters
+fn refine_query_symbol(
+    language: &SupportedLanguage,
+    node: Node,
+    source: &str,
+    symbol: &mut Symbol,
+) -> Result<(), anyhow::Error> {
+    match language {
+        SupportedLanguage::Rust if symbol.kind == SymbolKind::Impl => {
+            symbol.name = format!("impl {}", symbol.name);
+        }
+        SupportedLanguage::Rust | SupportedLanguage::Python
+            if symbol.kind == SymbolKind::Function =>
+        {
+            let macro_name = match node.kind() {
+                "function_item" => rust_proc_macro_name(node, &symbol.name, source)?,
+                _ => None,
+            };
+            if let Some(name) = macro_name {
+                symbol.name = name;
+                symbol.kind = SymbolKind::Macro;
+            } else if symbol.context.is_some() {
+                symbol.kind = SymbolKind::Method;
+            }
+        }
+        SupportedLanguage::Scala | SupportedLanguage::Haskell | SupportedLanguage::Dart
+            if symbol.kind == SymbolKind::Function && symbol.context.is_some() =>
+        {
+            symbol.kind = SymbolKind::Method;
+        }
+        SupportedLanguage::Cpp if symbol.kind == SymbolKind::Function => {
+            // `void Client::send()` is a method of `Client` defined out of line
+            if let Some((scope, name)) = symbol.name.rsplit_once("::") {
+                symbol.context = Some(cpp_scope(&symbol.context, scope));
+                symbol.name = name.to_string();
+                symbol.kind = SymbolKind::Method;
+            } else if node
+                .parent()
+                .is_some_and(|parent| parent.kind() == "field_declaration_list")
+            {
+                symbol.kind = SymbolKind::Method;
+            }
+        }
+        SupportedLanguage::Java => {
+            symbol.context = java_annotated_context(node, symbol.context.take(), source)?;
+        }
+        // Modules are named in full, and a struct after its module
+        SupportedLanguage::Elixir => match (&symbol.kind, &symbol.context) {
+            (SymbolKind::Module | SymbolKind::Interface, Some(context)) => {
+                symbol.name = format!("{context}.{}", symbol.name);
+            }
+            (SymbolKind::Struct, Some(context)) => symbol.name = context.clone(),
+            _ => {}
+        },
+        SupportedLanguage::Go => symbol.type_parameters = go_type_parameters(node, source)?,
+        _ => {}
     }
+    Ok(())
+}
 
-    /// Extract enum symbol from Rust code
-    fn extract_rust_enum(
-        &self,
-        node: Node,
-        source: &str,
-        file_path: &Path,
-        context: &Option<String>,
-    ) -> Result<Option<Symbol>, anyhow::Error> {
-        let name = self
-            .find_child_text(node, "type_identifier", source)?
-            .ok_or_else(|| anyhow::anyhow!("Enum missing name"))?;
+/// The first and last node of a definition's symbol
+/// C++ templates span their `template <...>` header, and Dart functions the body the
+/// grammar puts beside their signature.
+fn definition_span<'tree>(
+    language: &SupportedLanguage,
+    node: Node<'tree>,
+) -> (Node<'tree>, Node<'tree>) {
+    match language {
+        SupportedLanguage::Cpp => match node.parent() {
+            Some(parent) if parent.kind() == "template_declaration" => (parent, parent),
+            _ => (node, node),
+        },
+        SupportedLanguage::Dart => match node.next_sibling() {
+            Some(body) if body.kind() == "function_body" => (node, body),
+            _ => (node, node),
+        },
+        _ => (node, node),
+    }
+}
 
-        let content = node.utf8_text(source.as_bytes())?;
-        let start_pos = node.start_position();
-        let end_pos = node.end_position();
+/// Join each Haskell function's signature and consecutive equations into one span
+/// A lone signature is only kept in a class, where it declares a method.
+fn join_haskell_equations<'tree>(
+    spans: Vec<(Node<'tree>, Node<'tree>, Definition<'tree>)>,
+) -> Vec<(Node<'tree>, Node<'tree>, Definition<'tree>)> {
+    let mut joined: Vec<(Node, Node, Definition)> = Vec::new();
+    for (first, last, definition) in spans {
+        match joined.last_mut() {
+            Some((_, joined_last, previous))
+                if previous.kind == SymbolKind::Function
+                    && previous.name == definition.name
+                    && previous.node.parent() == definition.node.parent() =>
+            {
+                *joined_last = last;
+            }
+            _ => joined.push((first, last, definition)),
+        }
+    }
+    joined.retain(|(first, last, _)| {
+        first != last
+            || first.kind() != "signature"
+            || first
+                .parent()
+                .is_some_and(|parent| parent.kind() == "class_declarations")
+    });
+    joined
+}
 
-        Ok(Some(Symbol {
-            name,
-            kind: SymbolKind::Enum,
-            content: content.to_string(),
-            file_path: file_path.to_path_buf(),
-            start_line: start_pos.row + 1,
-            end_line: end_pos.row + 1,
-            start_column: start_pos.column,
-            end_column: end_pos.column,
-            context: context.clone(),
-        }))
+/// `context` followed by the annotations on a Java declaration, e.g.
+/// `UserController @GetMapping("/{id}")`
+fn java_annotated_context(
+    node: Node,
+    context: Option<String>,
+    source: &str,
+) -> Result<Option<String>, anyhow::Error> {
+    let mut parts: Vec<String> = context.into_iter().collect();
+    for child in node.children(&mut node.walk()) {
+        if child.kind() != "modifiers" {
+            continue;
+        }
+        for modifier in child.children(&mut child.walk()) {
+            if matches!(modifier.kind(), "annotation" | "marker_annotation") {
+                parts.push(modifier.utf8_text(source.as_bytes())?.to_string());
+            }
+        }
     }
+    Ok((!parts.is_empty()).then(|| parts.join(" ")))
+}
 
-    /// Extract trait symbol from Rust code
-    fn extract_rust_trait(
-        &self,
-        node: Node,
-        source: &str,
-        file_path: &Path,
-        context: &Option<String>,
-    ) -> Result<Option<Symbol>, anyhow::Error> {
-        let name = self
-            .find_child_text(node, "type_identifier", source)?
-            .ok_or_else(|| anyhow::anyhow!("Trait missing name"))?;
+/// `name` nested in the C++ scope `context`
+fn cpp_scope(context: &Option<String>, name: &str) -> String {
+    match context {
+        Some(context) => format!("{context}::{name}"),
+        None => name.to_string(),
+    }
+}
 
-        let content = node.utf8_text(source.as_bytes())?;
-        let start_pos = node.start_position();
-        let end_pos = node.end_position();
+/// The macro a Rust function defines when it's marked `#[proc_macro]`,
+/// `#[proc_macro_attribute]` or `#[proc_macro_derive(Name)]`, looking back through the
+/// attributes (and comments) right before it
+fn rust_proc_macro_name(
+    node: Node,
+    function_name: &str,
+    source: &str,
+) -> Result<Option<String>, anyhow::Error> {
+    let mut sibling = node.prev_named_sibling();
+    while let Some(attribute) = sibling {
+        match attribute.kind() {
+            "attribute_item" => {}
+            "line_comment" | "block_comment" => {
+                sibling = attribute.prev_named_sibling();
+                continue;
+            }
+            _ => break,
+        }
+        let text = attribute.utf8_text(source.as_bytes())?;
+        let inner = text.trim_start_matches("#[").trim_end_matches(']').trim();
+        if inner == "proc_macro" || inner == "proc_macro_attribute" {
+            return Ok(Some(function_name.to_string()));
+        }
+        if let Some(arguments) = inner.strip_prefix("proc_macro_derive(") {
+            let derive = arguments
+                .split([',', ')'])
+                .next()
+                .unwrap_or_default()
+                .trim();
+            return Ok(Some(derive.to_string()));
+        }
+        sibling = attribute.prev_named_sibling();
+    }
+    Ok(None)
+}
 
-        Ok(Some(Symbol {
-            name,
-            kind: SymbolKind::Trait,
-            content: content.to_string(),
-            file_path: file_path.to_path_buf(),
-            start_line: start_pos.row + 1,
-            end_line: end_pos.row + 1,
-            start_column: start_pos.column,
-            end_column: end_pos.column,
-            context: context.clone(),
-        }))
+/// The block mapping a YAML document or block node holds, if any
+fn yaml_block_mapping(node: Node) -> Option<Node> {
+    match node.kind() {
+        "block_mapping" => Some(node),
+        "document" | "block_node" => node
+            .named_children(&mut node.walk())
+            .find_map(yaml_block_mapping),
+        _ => None,
     }
+}
 
-    /// Extract impl symbol from Rust code
-    fn extract_rust_impl(
-        &self,
-        node: Node,
-        source: &str,
-        file_path: &Path,
-        context: &Option<String>,
-    ) -> Result<Option<Symbol>, anyhow::Error> {
-        // Find the type being implemented
-        let name = self
-            .find_child_text(node, "type_identifier", source)?
-            .unwrap_or_else(|| "impl".to_string());
-
-        let content = node.utf8_text(source.as_bytes())?;
-        let start_pos = node.start_position();
-        let end_pos = node.end_position();
-
-        Ok(Some(Symbol {
-            name: format!("impl {name}"),
-            kind: SymbolKind::Impl,
-            content: content.to_string(),
-            file_path: file_path.to_path_buf(),
-            start_line: start_pos.row + 1,
-            end_line: end_pos.row + 1,
-            start_column: start_pos.column,
-            end_column: end_pos.column,
-            context: context.clone(),
-        }))
+/// The value of `key` in a YAML block mapping
+fn yaml_mapping_value<'tree>(
+    mapping: Node<'tree>,
+    key: &str,
+    source: &str,
+) -> Result<Option<Node<'tree>>, anyhow::Error> {
+    for pair in mapping.named_children(&mut mapping.walk()) {
+        let (Some(pair_key), Some(value)) = (
+            pair.child_by_field_name("key"),
+            pair.child_by_field_name("value"),
+        ) else {
+            continue;
+        };
+        if yaml_scalar(pair_key, source)? == key {
+            return Ok(Some(value));
+        }
     }
+    Ok(None)
+}
 
-    /// Extract constant symbol from Rust code
-    fn extract_rust_constant(
-        &self,
-        node: Node,
-        source: &str,
-        file_path: &Path,
-        context: &Option<String>,
-    ) -> Result<Option<Symbol>, anyhow::Error> {
-        let name = self
-            .find_child_text(node, "identifier", source)?
-            .ok_or_else(|| anyhow::anyhow!("Constant missing name"))?;
+/// Text of a YAML scalar without its quotes
+fn yaml_scalar(node: Node, source: &str) -> Result<String, anyhow::Error> {
+    let text = node.utf8_text(source.as_bytes())?.trim();
+    Ok(text.trim_matches(|c| c == '"' || c == '\'').to_string())
+}
 
-        let content = node.utf8_text(source.as_bytes())?;
-        let start_pos = node.start_position();
-        let end_pos = node.end_position();
+/// Work out a symbol's visibility and modifiers from the keywords of its declaration
+/// Keywords are read from the declaration up to the symbol's name, along with whatever
+/// precedes it on its first line (TypeScript's `export`) and Python decorators;
+/// languages without a notion of visibility are left at the defaults.
+fn declared_modifiers(
+    language: &SupportedLanguage,
+    symbol: &Symbol,
+    lines: &[&str],
+) -> SymbolModifiers {
+    if symbol.kind == SymbolKind::Impl {
+        return SymbolModifiers::default();
+    }
+    let first_line = symbol.start_line.saturating_sub(1);
+    let line_prefix = lines
+        .get(first_line)
+        .and_then(|line| line.get(..symbol.start_column))
+        .unwrap_or_default();
+    let body_start = symbol.content.find('{').unwrap_or(symbol.content.len());
+    let name_start = word_position(&symbol.content[..body_start], &symbol.name)
+        .or_else(|| symbol.content.find('('))
+        .unwrap_or(body_start)
+        .min(body_start);
+    let mut keywords: Vec<&str> = words(line_prefix)
+        .chain(words(&symbol.content[..name_start]))
+        .collect();
+    if matches!(language, SupportedLanguage::Python) {
+        let decorators = lines[..first_line.min(lines.len())]
+            .iter()
+            .rev()
+            .take_while(|line| line.trim_start().starts_with('@'));
+        keywords.extend(decorators.flat_map(|line| words(line)));
+    }
+    let has = |keyword: &str| keywords.contains(&keyword);
+    // `foo = async () =>` and Dart's `foo() async {` put the keyword after the name
+    let async_after_name = matches!(
+        language,
+        SupportedLanguage::TypeScript | SupportedLanguage::JavaScript | SupportedLanguage::Dart
+    ) && words(&symbol.content[name_start..body_start])
+        .any(|word| word == "async");
+
+    let name = symbol.name.rsplit(['.', ':']).next().unwrap_or_default();
+    let underscore_private = if name.starts_with('_') && !name.ends_with("__") {
+        Visibility::Private
+    } else {
+        Visibility::Public
+    };
+    let visibility = match language {
+        SupportedLanguage::Rust => Some(rust_visibility(&keywords)),
+        SupportedLanguage::Go => Some(if name.starts_with(char::is_uppercase) {
+            Visibility::Public
+        } else {
+            Visibility::Private
+        }),
+        SupportedLanguage::Python | SupportedLanguage::Dart => Some(underscore_private),
+        SupportedLanguage::Java => explicit_visibility(&keywords).or(Some(Visibility::Internal)),
+        SupportedLanguage::CSharp => {
+            explicit_visibility(&keywords).or(Some(if symbol.context.is_some() {
+                Visibility::Private
+            } else {
+                Visibility::Internal
+            }))
+        }
+        SupportedLanguage::TypeScript | SupportedLanguage::JavaScript => {
+            explicit_visibility(&keywords).or(Some(
+                if name.starts_with('#') || (symbol.context.is_none() && !has("export")) {
+                    Visibility::Private
+                } else {
+                    Visibility::Public
+                },
+            ))
+        }
+        SupportedLanguage::Scala => explicit_visibility(&keywords).or(Some(Visibility::Public)),
+        SupportedLanguage::Elixir => Some(if has("defp") || has("defmacrop") || has("defguardp") {
+            Visibility::Private
+        } else {
+            Visibility::Public
+        }),
+        // C++ access specifiers label whole sections, so only file-local `static` is known
+        SupportedLanguage::C | SupportedLanguage::Cpp if symbol.context.is_none() => {
+            if has("static") {
+                Some(Visibility::Private)
+            } else if matches!(language, SupportedLanguage::C) {
+                Some(Visibility::Public)
+            } else {
+                None
+            }
+        }
+        _ => None,
+    };
+    SymbolModifiers {
+        visibility,
+        is_async: has("async") || async_after_name,
+        is_static: has("static") || has("staticmethod"),
+    }
+}
 
-        Ok(Some(Symbol {
-            name,
-            kind: SymbolKind::Constant,
-            content: content.to_string(),
-            file_path: file_path.to_path_buf(),
-            start_line: start_pos.row + 1,
-            end_line: end_pos.row + 1,
-            start_column: start_pos.column,
-            end_column: end_pos.column,
-            context: context.clone(),
-        }))
+/// Node kinds of import statements; Go's are the specs of a grouped `import (...)`
+fn import_kinds(language: &SupportedLanguage) -> &'static [&'static str] {
+    match language {
+        SupportedLanguage::Rust => &["use_declaration", "extern_crate_declaration"],
+        SupportedLanguage::Python => &["import_statement", "import_from_statement"],
+        SupportedLanguage::Go => &["import_spec"],
+        SupportedLanguage::TypeScript | SupportedLanguage::JavaScript => &["import_statement"],
+        SupportedLanguage::Java | SupportedLanguage::Scala => &["import_declaration"],
+        SupportedLanguage::C | SupportedLanguage::Cpp => &["preproc_include"],
+        SupportedLanguage::CSharp => &["using_directive"],
+        SupportedLanguage::Haskell | SupportedLanguage::Protobuf => &["import"],
+        SupportedLanguage::Dart => &["import_or_export"],
+        _ => &[],
     }
+}
 
-    /// Extract module symbol from Rust code
-    fn extract_rust_module(
-        &self,
-        node: Node,
-        source: &str,
-        file_path: &Path,
-        context: &Option<String>,
-    ) -> Result<Option<Symbol>, anyhow::Error> {
-        let name = self
-            .find_child_text(node, "identifier", source)?
-            .ok_or_else(|| anyhow::anyhow!("Module missing name"))?;
-
-        let content = node.utf8_text(source.as_bytes())?;
-        let start_pos = node.start_position();
-        let end_pos = node.end_position();
-
-        Ok(Some(Symbol {
-            name,
-            kind: SymbolKind::Module,
-            content: content.to_string(),
-            file_path: file_path.to_path_buf(),
-            start_line: start_pos.row + 1,
-            end_line: end_pos.row + 1,
-            start_column: start_pos.column,
-            end_column: end_pos.column,
-            context: context.clone(),
-        }))
-    }
-
-    /// Recursively traverse Python AST nodes to find symbols
-    fn traverse_python_node(
-        &self,
-        node: Node,
-        source: &str,
-        file_path: &Path,
-        symbols: &mut Vec<Symbol>,
-        context: Option<String>,
-    ) -> Result<(), anyhow::Error> {
-        match node.kind() {
-            "function_definition" => {
-                if let Some(symbol) =
-                    self.extract_python_function(node, source, file_path, &context)?
-                {
-                    symbols.push(symbol);
-                }
-            }
-            "class_definition" => {
-                if let Some(symbol) =
-                    self.extract_python_class(node, source, file_path, &context)?
-                {
-                    let class_name = symbol.name.clone();
-                    symbols.push(symbol);
-
-                    // For class methods, pass the class name as context
-                    for child in node.children(&mut node.walk()) {
-                        self.traverse_python_node(
-                            child,
-                            source,
-                            file_path,
-                            symbols,
-                            Some(class_name.clone()),
-                        )?;
-                    }
-                    return Ok(());
+/// What an import statement imports: its module or path field where the grammar has
+/// one, otherwise its text without the leading keywords
+fn import_name(node: Node, source: &str) -> Result<String, anyhow::Error> {
+    const KEYWORDS: &[&str] = &[
+        "use", "pub", "extern", "crate", "import", "static", "using", "global", "export",
+    ];
+    let target = ["argument", "module_name", "path", "source", "module"]
+        .iter()
+        .find_map(|field| node.child_by_field_name(field));
+    let text = match target {
+        Some(target) => target.utf8_text(source.as_bytes())?,
+        None => {
+            let mut text = node.utf8_text(source.as_bytes())?.trim();
+            while let Some((word, rest)) = text.split_once(char::is_whitespace) {
+                if !KEYWORDS.contains(&word) {
+                    break;
                 }
+                text = rest.trim_start();
             }
-            _ => {}
-        }
-
-        // Continue traversing child nodes
-        for child in node.children(&mut node.walk()) {
-            self.traverse_python_node(child, source, file_path, symbols, context.clone())?;
+            text
         }
+    };
+    let text = text.trim().trim_end_matches(';');
+    // Keep only the path of `'package:http/http.dart' as http`
+    let name = match text.strip_prefix(['"', '\'']) {
+        Some(quoted) => quoted.split(['"', '\'']).next().unwrap_or_default(),
+        None => text.trim_matches(|c| matches!(c, '"' | '<' | '>')),
+    };
+    Ok(name.split_whitespace().collect::<Vec<_>>().join(" "))
+}
 
-        Ok(())
-    }
+/// Text of a symbol's declaration before its body, found in the tree; symbols without
+/// a body fall back to their first line
+fn declaration_signature(root: Node, symbol: &Symbol, source: &str) -> String {
+    let start = tree_sitter::Point::new(symbol.start_line - 1, symbol.start_column);
+    let end = tree_sitter::Point::new(symbol.end_line - 1, symbol.end_column);
+    let signature = root
+        .descendant_for_point_range(start, end)
+        .and_then(|node| {
+            if node.start_position() == start {
+                return body_node(node, 2).map(|body| (node.start_byte(), body));
+            }
+            // Symbols spanning several siblings (a Dart signature and its body, Haskell
+            // equations) are covered by their parent
+            let in_range: Vec<Node> = node
+                .named_children(&mut node.walk())
+                .filter(|child| child.start_position() >= start && child.end_position() <= end)
+                .collect();
+            let first = in_range.first()?;
+            let body = in_range
+                .iter()
+                .find(|child| is_body_kind(child.kind()))
+                .copied()
+                .or_else(|| body_node(*first, 2))?;
+            Some((first.start_byte(), body))
+        })
+        .and_then(|(start_byte, body)| source.get(start_byte..body.start_byte()))
+        .map(str::trim_end)
+        .filter(|signature| !signature.is_empty());
+    signature
+        .or_else(|| symbol.content.lines().next().map(str::trim_end))
+        .unwrap_or_default()
+        .to_string()
+}
 
-    /// Extract function symbol from Python code
-    fn extract_python_function(
-        &self,
-        node: Node,
-        source: &str,
-        file_path: &Path,
-        context: &Option<String>,
-    ) -> Result<Option<Symbol>, anyhow::Error> {
-        // Find function name
-        let name = self
-            .find_child_text(node, "identifier", source)?
-            .ok_or_else(|| anyhow::anyhow!("Python function missing name"))?;
-
-        let content = node.utf8_text(source.as_bytes())?;
-        let start_pos = node.start_position();
-        let end_pos = node.end_position();
-
-        let kind = if context.is_some() {
-            SymbolKind::Method
-        } else {
-            SymbolKind::Function
+/// Give each symbol its `id`; identical declarations in one file (same kind,
+/// qualified name and content) are told apart by their order
+fn assign_symbol_ids(symbols: &mut [Symbol]) {
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    for symbol in symbols {
+        let qualified_name = match &symbol.context {
+            Some(context) => format!("{context}::{}", symbol.name),
+            None => symbol.name.clone(),
         };
-
-        Ok(Some(Symbol {
-            name,
-            kind,
-            content: content.to_string(),
-            file_path: file_path.to_path_buf(),
-            start_line: start_pos.row + 1,
-            end_line: end_pos.row + 1,
-            start_column: start_pos.column,
-            end_column: end_pos.column,
-            context: context.clone(),
-        }))
+        let id = symbol_id(&symbol.kind, &qualified_name, &symbol.content);
+        let repeats = seen.entry(id.clone()).or_default();
+        symbol.id = match *repeats {
+            0 => id,
+            n => symbol_id(
+                &symbol.kind,
+                &format!("{qualified_name}#{n}"),
+                &symbol.content,
+            ),
+        };
+        *repeats += 1;
     }
+}
 
-    /// Extract class symbol from Python code
-    fn extract_python_class(
-        &self,
-        node: Node,
-        source: &str,
-        file_path: &Path,
-        context: &Option<String>,
-    ) -> Result<Option<Symbol>, anyhow::Error> {
-        let name = self
-            .find_child_text(node, "identifier", source)?
-            .ok_or_else(|| anyhow::anyhow!("Python class missing name"))?;
-
-        let content = node.utf8_text(source.as_bytes())?;
-        let start_pos = node.start_position();
-        let end_pos = node.end_position();
+/// ID of a `kind` symbol named `qualified_name` (`Context::name`) with source `content`
+/// Whitespace is collapsed before hashing, so reindenting or moving the symbol keeps its
+/// ID. The hash is FNV-1a, which unlike `DefaultHasher` is the same on every build.
+pub fn symbol_id(kind: &SymbolKind, qualified_name: &str, content: &str) -> String {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    let mut write = |bytes: &[u8]| {
+        for byte in bytes {
+            hash = (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3);
+        }
+    };
+    write(format!("{kind:?}").as_bytes());
+    write(&[0]);
+    write(qualified_name.as_bytes());
+    write(&[0]);
+    for (i, word) in content.split_whitespace().enumerate() {
+        if i > 0 {
+            write(b" ");
+        }
+        write(word.as_bytes());
+    }
+    format!("{hash:016x}")
+}
 
-        Ok(Some(Symbol {
-            name,
-            kind: SymbolKind::Class,
-            content: content.to_string(),
-            file_path: file_path.to_path_buf(),
-            start_line: start_pos.row + 1,
-            end_line: end_pos.row + 1,
-            start_column: start_pos.column,
-            end_column: end_pos.column,
-            context: context.clone(),
-        }))
+/// Mark every symbol of a test file, every test declaration and everything nested in
+/// one (a `#[cfg(test)]` module's items, a test class's helpers) as test code
+fn mark_test_code(
+    language: &SupportedLanguage,
+    file_path: &Path,
+    symbols: &mut [Symbol],
+    lines: &[&str],
+) {
+    let test_file = is_test_file(language, file_path);
+    let test_spans: Vec<(usize, usize)> = symbols
+        .iter()
+        .filter(|symbol| is_test_declaration(language, symbol, lines))
+        .map(|symbol| (symbol.start_line, symbol.end_line))
+        .collect();
+    for symbol in symbols {
+        symbol.is_test = test_file
+            || test_spans
+                .iter()
+                .any(|&(start, end)| start <= symbol.start_line && symbol.end_line <= end);
     }
+}
 
-    /// Recursively traverse Go AST nodes to find symbols
-    fn traverse_go_node(
-        &self,
-        node: Node,
-        source: &str,
-        file_path: &Path,
-        symbols: &mut Vec<Symbol>,
-        context: Option<String>,
-    ) -> Result<(), anyhow::Error> {
-        match node.kind() {
-            "function_declaration" => {
-                if let Some(symbol) = self.extract_go_function(node, source, file_path, &context)? {
-                    symbols.push(symbol);
-                }
-            }
-            "method_declaration" => {
-                if let Some(symbol) = self.extract_go_method(node, source, file_path, &context)? {
-                    symbols.push(symbol);
-                }
-            }
-            "type_declaration" => {
-                // Go type declarations can contain structs, interfaces, etc.
-                for child in node.children(&mut node.walk()) {
-                    if child.kind() == "type_spec" {
-                        if let Some(symbol) =
-                            self.extract_go_type(child, source, file_path, &context)?
-                        {
-                            symbols.push(symbol);
-                        }
-                    }
-                }
-            }
-            "const_declaration" | "var_declaration" => {
-                if let Some(symbol) = self.extract_go_variable(node, source, file_path, &context)? {
-                    symbols.push(symbol);
-                }
-            }
-            _ => {}
+/// Whether `path` is named like a test file in `language`: `_test.go`, `test_*.py`,
+/// `*.test.ts`, `*.spec.js`, `FooTest.java`, ...
+fn is_test_file(language: &SupportedLanguage, path: &Path) -> bool {
+    let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+        return false;
+    };
+    let stem = name.split('.').next().unwrap_or_default();
+    match language {
+        SupportedLanguage::Go => name.ends_with("_test.go"),
+        SupportedLanguage::Python => {
+            stem.starts_with("test_") || stem.ends_with("_test") || stem == "conftest"
         }
-
-        // Continue traversing child nodes
-        for child in node.children(&mut node.walk()) {
-            self.traverse_go_node(child, source, file_path, symbols, context.clone())?;
+        SupportedLanguage::JavaScript | SupportedLanguage::TypeScript => {
+            name.contains(".test.") || name.contains(".spec.")
         }
-
-        Ok(())
+        SupportedLanguage::Java | SupportedLanguage::CSharp | SupportedLanguage::Scala => {
+            stem.ends_with("Test") || stem.ends_with("Tests") || stem.ends_with("Spec")
+        }
+        SupportedLanguage::Elixir | SupportedLanguage::Dart => stem.ends_with("_test"),
+        _ => false,
     }
+}
 
-    /// Extract function symbol from Go code
-    fn extract_go_function(
-        &self,
-        node: Node,
-        source: &str,
-        file_path: &Path,
-        context: &Option<String>,
-    ) -> Result<Option<Symbol>, anyhow::Error> {
-        // Find function name
-        let name = self
-            .find_child_text(node, "identifier", source)?
-            .ok_or_else(|| anyhow::anyhow!("Go function missing name"))?;
-
-        let content = node.utf8_text(source.as_bytes())?;
-        let start_pos = node.start_position();
-        let end_pos = node.end_position();
-
-        Ok(Some(Symbol {
-            name,
-            kind: SymbolKind::Function,
-            content: content.to_string(),
-            file_path: file_path.to_path_buf(),
-            start_line: start_pos.row + 1,
-            end_line: end_pos.row + 1,
-            start_column: start_pos.column,
-            end_column: end_pos.column,
-            context: context.clone(),
-        }))
+/// Whether `symbol` is declared as a test: Rust `#[test]`-like attributes and
+/// `#[cfg(test)]`, Python `test_*` functions and `Test*` classes, Java `@Test` and C#
+/// `[Test]`/`[Fact]` attributes
+fn is_test_declaration(language: &SupportedLanguage, symbol: &Symbol, lines: &[&str]) -> bool {
+    match language {
+        SupportedLanguage::Rust => {
+            let first_line = symbol.start_line.saturating_sub(1).min(lines.len());
+            lines[..first_line]
+                .iter()
+                .map(|line| line.trim())
+                .rev()
+                .take_while(|line| line.starts_with("#[") || line.starts_with("//"))
+                .filter_map(|line| line.strip_prefix("#[")?.strip_suffix(']'))
+                .any(|attribute| {
+                    let path = attribute.split('(').next().unwrap_or_default().trim();
+                    attribute == "cfg(test)"
+                        || path == "test"
+                        || path.ends_with("::test")
+                        || path == "rstest"
+                        || path == "test_case"
+                })
+        }
+        SupportedLanguage::Python => match symbol.kind {
+            SymbolKind::Function | SymbolKind::Method => symbol.name.starts_with("test"),
+            SymbolKind::Class => symbol.name.starts_with("Test"),
+            _ => false,
+        },
+        SupportedLanguage::Java => {
+            symbol.signature.contains("@Test") || symbol.signature.contains("@ParameterizedTest")
+        }
+        SupportedLanguage::CSharp => ["[Test", "[Fact", "[Theory", "[TestMethod"]
+            .iter()
+            .any(|attribute| symbol.signature.contains(attribute)),
+        _ => false,
     }
+}
 
-    /// Extract method symbol from Go code
-    fn extract_go_method(
-        &self,
-        node: Node,
-        source: &str,
-        file_path: &Path,
-        context: &Option<String>,
-    ) -> Result<Option<Symbol>, anyhow::Error> {
-        // Find method name
-        let name = self
-            .find_child_text(node, "field_identifier", source)?
-            .or_else(|| {
-                self.find_child_text(node, "identifier", source)
-                    .unwrap_or(None)
-            })
-            .ok_or_else(|| anyhow::anyhow!("Go method missing name"))?;
-
-        let content = node.utf8_text(source.as_bytes())?;
-        let start_pos = node.start_position();
-        let end_pos = node.end_position();
-
-        // Try to extract receiver type for context
-        let receiver_context = self.extract_go_receiver_type(node, source)?;
-        let final_context = receiver_context.or_else(|| context.clone());
-
-        Ok(Some(Symbol {
-            name,
-            kind: SymbolKind::Method,
-            content: content.to_string(),
-            file_path: file_path.to_path_buf(),
-            start_line: start_pos.row + 1,
-            end_line: end_pos.row + 1,
-            start_column: start_pos.column,
-            end_column: end_pos.column,
-            context: final_context,
-        }))
+/// Size and branch count of `symbol`
+fn symbol_metrics(root: Node, symbol: &Symbol) -> SymbolMetrics {
+    let start = tree_sitter::Point::new(symbol.start_line - 1, symbol.start_column);
+    let end = tree_sitter::Point::new(symbol.end_line - 1, symbol.end_column);
+    let branches = match root.descendant_for_point_range(start, end) {
+        Some(node) if node.start_position() == start => count_branches(node),
+        // Symbols spanning several siblings are covered by their parent
+        Some(node) => node
+            .children(&mut node.walk())
+            .filter(|child| child.start_position() >= start && child.end_position() <= end)
+            .map(count_branches)
+            .sum(),
+        None => 0,
+    };
+    SymbolMetrics {
+        lines: symbol.end_line - symbol.start_line + 1,
+        tokens: symbol.content.chars().count().div_ceil(4),
+        branches,
     }
+}
 
-    /// Extract type symbol from Go code (struct, interface, etc.)
-    fn extract_go_type(
-        &self,
-        node: Node,
-        source: &str,
-        file_path: &Path,
-        context: &Option<String>,
-    ) -> Result<Option<Symbol>, anyhow::Error> {
-        // Find type name
-        let name = self
-            .find_child_text(node, "type_identifier", source)?
-            .ok_or_else(|| anyhow::anyhow!("Go type missing name"))?;
-
-        let content = node.utf8_text(source.as_bytes())?;
-        let start_pos = node.start_position();
-        let end_pos = node.end_position();
-
-        // Determine the kind based on the type
-        let kind = if content.contains("struct") {
-            SymbolKind::Struct
-        } else if content.contains("interface") {
-            SymbolKind::Interface
-        } else {
-            SymbolKind::Type
-        };
+/// Decision points in `node` and its descendants
+fn count_branches(node: Node) -> usize {
+    let nested: usize = node.children(&mut node.walk()).map(count_branches).sum();
+    nested + usize::from(is_branch(node))
+}
 
-        Ok(Some(Symbol {
-            name,
-            kind,
-            content: content.to_string(),
-            file_path: file_path.to_path_buf(),
-            start_line: start_pos.row + 1,
-            end_line: end_pos.row + 1,
-            start_column: start_pos.column,
-            end_column: end_pos.column,
-            context: context.clone(),
-        }))
+/// Node kinds that are decision points in one of the built-in grammars
+const BRANCH_KINDS: &[&str] = &[
+    "if_expression",
+    "if_statement",
+    "elif_clause",
+    "else_if_clause",
+    "conditional_expression",
+    "ternary_expression",
+    "for_expression",
+    "for_statement",
+    "for_in_statement",
+    "enhanced_for_statement",
+    "foreach_statement",
+    "while_expression",
+    "while_statement",
+    "do_statement",
+    "loop_expression",
+    "match_arm",
+    "case_clause",
+    "switch_case",
+    "switch_section",
+    "switch_block_statement_group",
+    "expression_case",
+    "type_case",
+    "communication_case",
+    "case_statement",
+    "catch_clause",
+    "except_clause",
+    "rescue",
+    "guard",
+    "boolean_operator",
+];
+
+/// Whether `node` is a decision point: a `BRANCH_KINDS` node or a `&&`/`||` expression
+fn is_branch(node: Node) -> bool {
+    if BRANCH_KINDS.contains(&node.kind()) {
+        return true;
     }
+    node.kind() == "binary_expression"
+        && node
+            .child_by_field_name("operator")
+            .is_some_and(|operator| matches!(operator.kind(), "&&" | "||"))
+}
 
-    /// Extract variable/constant symbol from Go code
-    fn extract_go_variable(
-        &self,
-        node: Node,
-        source: &str,
-        file_path: &Path,
-        context: &Option<String>,
-    ) -> Result<Option<Symbol>, anyhow::Error> {
-        // Find variable name - could be in a var_spec or const_spec child
-        let mut name = None;
-        for child in node.children(&mut node.walk()) {
-            if child.kind() == "var_spec" || child.kind() == "const_spec" {
-                name = self.find_child_text(child, "identifier", source)?;
-                if name.is_some() {
-                    break;
-                }
-            }
-        }
+/// The body of a declaration: its `body` field or a block-like child, looking through
+/// up to `depth` levels of declarators, function values and type definitions
+/// (`const f = () => {}`, Go's `type T struct {}`)
+fn body_node(node: Node, depth: usize) -> Option<Node> {
+    if let Some(body) = node.child_by_field_name("body") {
+        return Some(body);
+    }
+    let children: Vec<Node> = node.named_children(&mut node.walk()).collect();
+    if let Some(body) = children.iter().find(|child| is_body_kind(child.kind())) {
+        return Some(*body);
+    }
+    if depth == 0 {
+        return None;
+    }
+    children
+        .into_iter()
+        .filter(|child| {
+            let kind = child.kind();
+            kind.contains("declarator")
+                || kind.contains("function")
+                || kind.contains("arrow")
+                || kind.contains("class")
+                || kind.ends_with("_type")
+        })
+        .find_map(|child| body_node(child, depth - 1))
+}
 
-        let name = name.ok_or(anyhow::anyhow!("Go variable/constant missing name"))?;
-        let content = node.utf8_text(source.as_bytes())?;
-        let start_pos = node.start_position();
-        let end_pos = node.end_position();
+fn is_body_kind(kind: &str) -> bool {
+    kind.ends_with("body")
+        || kind.ends_with("block")
+        || matches!(
+            kind,
+            "declaration_list"
+                | "field_declaration_list"
+                | "enum_variant_list"
+                | "compound_statement"
+                | "statement_block"
+        )
+}
 
-        let kind = if node.kind() == "const_declaration" {
-            SymbolKind::Constant
-        } else {
-            SymbolKind::Variable
+/// Items declared inside a Rust trait have no visibility of their own; they're as
+/// visible as the trait
+fn inherit_trait_visibility(symbols: &mut [Symbol]) {
+    let traits: Vec<(String, usize, usize, Option<Visibility>)> = symbols
+        .iter()
+        .filter(|symbol| symbol.kind == SymbolKind::Trait)
+        .map(|symbol| {
+            (
+                symbol.name.clone(),
+                symbol.start_line,
+                symbol.end_line,
+                symbol.modifiers.visibility,
+            )
+        })
+        .collect();
+    for symbol in symbols.iter_mut() {
+        let Some(context) = symbol.context.as_deref() else {
+            continue;
         };
-
-        Ok(Some(Symbol {
-            name,
-            kind,
-            content: content.to_string(),
-            file_path: file_path.to_path_buf(),
-            start_line: start_pos.row + 1,
-            end_line: end_pos.row + 1,
-            start_column: start_pos.column,
-            end_column: end_pos.column,
-            context: context.clone(),
-        }))
+        let enclosing = traits.iter().find(|(name, start, end, _)| {
+            name == context
+                && *start <= symbol.start_line
+                && symbol.end_line <= *end
+                && symbol.kind != SymbolKind::Trait
+        });
+        if let Some((_, _, _, visibility)) = enclosing {
+            symbol.modifiers.visibility = *visibility;
+        }
     }
+}
 
-    /// Extract receiver type from Go method declaration
-    fn extract_go_receiver_type(
-        &self,
-        node: Node,
-        source: &str,
-    ) -> Result<Option<String>, anyhow::Error> {
-        for child in node.children(&mut node.walk()) {
-            if child.kind() == "parameter_list" {
-                // This is likely the receiver
-                if let Some(receiver_type) =
-                    self.find_child_text(child, "type_identifier", source)?
-                {
-                    return Ok(Some(receiver_type));
-                }
-            }
-        }
-        Ok(None)
+/// Rust visibility from the keywords before a name: `pub(crate)`, `pub(super)` and
+/// `pub(in path)` are internal, `pub(self)` is private
+fn rust_visibility(keywords: &[&str]) -> Visibility {
+    let Some(index) = keywords.iter().position(|keyword| *keyword == "pub") else {
+        return Visibility::Private;
+    };
+    match keywords.get(index + 1) {
+        Some(&"crate" | &"super" | &"in") => Visibility::Internal,
+        Some(&"self") => Visibility::Private,
+        _ => Visibility::Public,
     }
+}
 
-    /// Helper function to find text content of a child node with specific kind
-    fn find_child_text(
-        &self,
-        node: Node,
-        kind: &str,
-        source: &str,
-    ) -> Result<Option<String>, anyhow::Error> {
-        for child in node.children(&mut node.walk()) {
-            if child.kind() == kind {
-                let text = child.utf8_text(source.as_bytes())?;
-                return Ok(Some(text.to_string()));
-            }
-        }
-        Ok(None)
+/// The first access modifier keyword, for languages that spell visibility out
+fn explicit_visibility(keywords: &[&str]) -> Option<Visibility> {
+    keywords.iter().find_map(|keyword| match *keyword {
+        "public" => Some(Visibility::Public),
+        "protected" => Some(Visibility::Protected),
+        "internal" => Some(Visibility::Internal),
+        "private" => Some(Visibility::Private),
+        _ => None,
+    })
+}
+
+/// Identifier-like words of `text`
+fn words(text: &str) -> impl Iterator<Item = &str> {
+    text.split(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .filter(|word| !word.is_empty())
+}
+
+/// Byte offset of the first occurrence of `word` in `text` that isn't part of a longer
+/// identifier
+fn word_position(text: &str, word: &str) -> Option<usize> {
+    if word.is_empty() {
+        return None;
     }
+    let is_ident = |c: char| c.is_alphanumeric() || c == '_';
+    text.match_indices(word)
+        .map(|(index, _)| index)
+        .find(|&index| {
+            let before = text[..index].chars().next_back();
+            let after = text[index + word.len()..].chars().next();
+            !before.is_some_and(is_ident) && !after.is_some_and(is_ident)
+        })
 }
 
 /// Helper function to extract file metadata (last modified time)
+#[cfg(feature = "native")]
 pub fn get_file_metadata(path: &Path) -> Result<u64, anyhow::Error> {
     let metadata = fs::metadata(path)
         .map_err(|e| anyhow::anyhow!("Failed to get metadata for '{}': {}", path.display(), e))?;
@@ -897,10 +1506,28 @@ pub fn get_file_metadata(path: &Path) -> Result<u64, anyhow::Error> {
 }
 
 /// Index a codebase by walking through directories and extracting symbols
+#[cfg(feature = "native")]
 pub fn parse_codebase<P: AsRef<Path>>(root_path: P) -> Result<Vec<Symbol>, anyhow::Error> {
+    parse_codebase_with_stats(root_path).map(|(symbols, _)| symbols)
+}
+
+/// What `parse_codebase_with_stats` did besides extracting symbols
+#[cfg(feature = "native")]
+#[derive(Debug, Clone, Default)]
+pub struct ParseStats {
+    /// Symbols extracted twice and kept once
+    pub duplicates_collapsed: usize,
+}
+
+/// `parse_codebase`, also reporting `ParseStats`
+#[cfg(feature = "native")]
+pub fn parse_codebase_with_stats<P: AsRef<Path>>(
+    root_path: P,
+) -> Result<(Vec<Symbol>, ParseStats), anyhow::Error> {
     let mut parser = SymbolParser::new()?;
     let mut all_symbols = Vec::new();
     let mut file_state_map = HashMap::new();
+    let hash_algorithm = IndexConfig::load(root_path.as_ref())?.state.hash;
 
     info!(
         "Starting codebase indexing at: {}",
@@ -922,14 +1549,13 @@ pub fn parse_codebase<P: AsRef<Path>>(root_path: P) -> Result<Vec<Symbol>, anyho
             }
         };
 
-        let file_state = FileState::new(path.to_string_lossy().to_string(), last_modified)
-            .map_err(|e| {
-                anyhow::anyhow!(
-                    "Failed to create file state for '{}': {}",
-                    path.display(),
-                    e
-                )
-            })?;
+        let file_state = FileState::new(path, last_modified, hash_algorithm).map_err(|e| {
+            anyhow::anyhow!(
+                "Failed to create file state for '{}': {}",
+                path.display(),
+                e
+            )
+        })?;
         file_state_map.insert(path.to_string_lossy().to_string(), file_state);
 
         let extension = path.extension().and_then(|ext| ext.to_str()).unwrap_or("");
@@ -953,16 +1579,1370 @@ pub fn parse_codebase<P: AsRef<Path>>(root_path: P) -> Result<Vec<Symbol>, anyho
         Ok(true) // Continue walking
     })?;
 
-    let codebase_state = CodebaseState {
-        file_states: file_state_map,
-    };
-    codebase_state
-        .to_file(None)
-        .map_err(|e| anyhow::anyhow!("Failed to save codebase state to index.json: {}", e))?;
+    let codebase_state = CodebaseState::new(file_state_map);
+    state_file_path(root_path.as_ref())
+        .and_then(|path| codebase_state.to_file(&path))
+        .map_err(|e| anyhow::anyhow!("Failed to save codebase state: {}", e))?;
 
+    let stats = ParseStats {
+        duplicates_collapsed: parser.duplicates_collapsed(),
+    };
     info!(
-        "Indexing complete. Total symbols extracted: {}",
-        all_symbols.len()
+        "Indexing complete. Total symbols extracted: {} ({} duplicates collapsed)",
+        all_symbols.len(),
+        stats.duplicates_collapsed
     );
-    Ok(all_symbols)
+    Ok((all_symbols, stats))
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::expect_used)]
+    use super::*;
+
+    fn names_and_kinds(file_path: &str, source: &str) -> Vec<(String, SymbolKind)> {
+        parse(file_path, source)
+            .into_iter()
+            .map(|symbol| (symbol.name, symbol.kind))
+            .collect()
+    }
+
+    fn names_kinds_and_contexts(
+        file_path: &str,
+        source: &str,
+    ) -> Vec<(String, SymbolKind, Option<String>)> {
+        parse(file_path, source)
+            .into_iter()
+            .map(|symbol| (symbol.name, symbol.kind, symbol.context))
+            .collect()
+    }
+
+    fn parse(file_path: &str, source: &str) -> Vec<Symbol> {
+        let mut parser = SymbolParser::new().expect("Failed to create parser");
+        parser
+            .parse_source(file_path, source)
+            .expect("Failed to parse")
+    }
+
+    #[test]
+    fn go_interface_methods_and_type_parameters_are_extracted() {
+        let source = r#"
+package cache
+
+type Store[K comparable, V any] interface {
+    Get(key K) (V, bool)
+    Put(key K, value V)
+}
+
+type Entry[K, V any] struct {
+    Key   K
+    Value V
+}
+
+func Map[T, U any](items []T, f func(T) U) []U {
+    return nil
+}
+"#;
+        let symbols = parse("cache.go", source);
+        let summary: Vec<(&str, &SymbolKind, Option<&str>, Vec<&str>)> = symbols
+            .iter()
+            .map(|symbol| {
+                (
+                    symbol.name.as_str(),
+                    &symbol.kind,
+                    symbol.context.as_deref(),
+                    symbol.type_parameters.iter().map(String::as_str).collect(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (
+                    "Store",
+                    &SymbolKind::Interface,
+                    None,
+                    vec!["K comparable", "V any"]
+                ),
+                ("Get", &SymbolKind::Method, Some("Store"), vec![]),
+                ("Put", &SymbolKind::Method, Some("Store"), vec![]),
+                ("Entry", &SymbolKind::Struct, None, vec!["K any", "V any"]),
+                ("Map", &SymbolKind::Function, None, vec!["T any", "U any"]),
+            ]
+        );
+    }
+
+    #[test]
+    fn metrics_count_lines_and_branches() {
+        let source = r#"
+fn classify(n: i32) -> &'static str {
+    if n < 0 || n > 100 {
+        return "out of range";
+    }
+    match n % 2 {
+        0 => "even",
+        _ => "odd",
+    }
+}
+
+fn id(n: i32) -> i32 { n }
+"#;
+        let symbols = parse("classify.rs", source);
+        let metrics: Vec<(&str, usize, usize)> = symbols
+            .iter()
+            .map(|symbol| {
+                (
+                    symbol.name.as_str(),
+                    symbol.metrics.lines,
+                    symbol.metrics.branches,
+                )
+            })
+            .collect();
+        // `if`, `||` and two match arms
+        assert_eq!(metrics, vec![("classify", 9, 4), ("id", 1, 0)]);
+        assert!(symbols[0].metrics.tokens > symbols[1].metrics.tokens);
+    }
+
+    #[test]
+    fn duplicate_symbols_are_collapsed() {
+        let mut symbols = parse(
+            "lib.rs",
+            "struct Point { x: i32 }\nconst ORIGIN: i32 = 0;\n",
+        );
+        let mut twice = symbols.clone();
+        twice.extend(symbols.clone());
+        // A second declarator of the same declaration shares its span but not its name
+        let mut declarator = symbols[1].clone();
+        declarator.name = "ZERO".to_string();
+        twice.push(declarator.clone());
+
+        assert_eq!(dedupe_symbols(&mut twice), 2);
+        symbols.push(declarator);
+        let names = |symbols: &[Symbol]| -> Vec<String> {
+            symbols.iter().map(|symbol| symbol.name.clone()).collect()
+        };
+        assert_eq!(names(&twice), names(&symbols));
+    }
+
+    #[test]
+    fn symbol_ids_ignore_position_and_whitespace() {
+        let ids = |source: &str| -> Vec<String> {
+            parse("lib.rs", source)
+                .into_iter()
+                .map(|symbol| symbol.id)
+                .collect()
+        };
+        let before = ids("fn parse() { 1 }\n\nfn render() {}\n");
+        let after = ids("// header\n\nfn parse() {\n    1\n}\n\nfn render() {}\n");
+        assert_eq!(before, after);
+        assert_ne!(before, ids("fn parse() { 2 }\n\nfn render() {}\n")[..]);
+
+        let twins = ids("#[cfg(unix)]\nfn open() {}\n#[cfg(windows)]\nfn open() {}\n");
+        assert_eq!(twins.len(), 2);
+        assert_ne!(twins[0], twins[1]);
+    }
+
+    #[test]
+    fn test_code_is_tagged() {
+        let rust = r#"
+pub fn retry() {}
+
+#[cfg(test)]
+mod tests {
+    fn fixture() {}
+
+    #[test]
+    fn retries() {}
+}
+"#;
+        let tests: Vec<(String, bool)> = parse("lib.rs", rust)
+            .into_iter()
+            .map(|symbol| (symbol.name, symbol.is_test))
+            .collect();
+        assert_eq!(
+            tests,
+            vec![
+                ("retry".to_string(), false),
+                ("tests".to_string(), true),
+                ("fixture".to_string(), true),
+                ("retries".to_string(), true),
+            ]
+        );
+
+        let go = "package cache\n\nfunc helper() {}\n";
+        assert!(
+            parse("cache_test.go", go)
+                .iter()
+                .all(|symbol| symbol.is_test)
+        );
+        assert!(!parse("cache.go", go).iter().any(|symbol| symbol.is_test));
+    }
+
+    #[test]
+    fn go_methods_take_their_receiver_type_as_context() {
+        let source = r#"
+package server
+
+var (
+    hits   = 0
+    misses = 0
+)
+
+type Server[T any] struct{ state T }
+
+func (s Server[T]) Name() string { return "" }
+
+func (s *Server[T]) Start() {}
+
+func (Config) Validate() error { return nil }
+
+func (c *Config) Load() {}
+
+func main() {}
+"#;
+        assert_eq!(
+            names_kinds_and_contexts("server.go", source),
+            vec![
+                ("hits".to_string(), SymbolKind::Variable, None),
+                ("Server".to_string(), SymbolKind::Struct, None),
+                (
+                    "Name".to_string(),
+                    SymbolKind::Method,
+                    Some("Server".to_string())
+                ),
+                (
+                    "Start".to_string(),
+                    SymbolKind::Method,
+                    Some("Server".to_string())
+                ),
+                (
+                    "Validate".to_string(),
+                    SymbolKind::Method,
+                    Some("Config".to_string())
+                ),
+                (
+                    "Load".to_string(),
+                    SymbolKind::Method,
+                    Some("Config".to_string())
+                ),
+                ("main".to_string(), SymbolKind::Function, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn rust_impls_are_named_after_the_implementing_type() {
+        let source = r#"
+impl<T> fmt::Display for Wrapper<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        todo!()
+    }
+}
+
+impl crate::config::Settings {
+    fn load() -> Self {
+        todo!()
+    }
+}
+
+impl Default for Mode {
+    fn default() -> Self {
+        Mode::Fast
+    }
+}
+"#;
+        assert_eq!(
+            names_kinds_and_contexts("wrapper.rs", source),
+            vec![
+                ("impl Wrapper".to_string(), SymbolKind::Impl, None),
+                (
+                    "fmt".to_string(),
+                    SymbolKind::Method,
+                    Some("Wrapper".to_string())
+                ),
+                ("impl Settings".to_string(), SymbolKind::Impl, None),
+                (
+                    "load".to_string(),
+                    SymbolKind::Method,
+                    Some("Settings".to_string())
+                ),
+                ("impl Mode".to_string(), SymbolKind::Impl, None),
+                (
+                    "default".to_string(),
+                    SymbolKind::Method,
+                    Some("Mode".to_string())
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn python_assignments_and_type_aliases_are_extracted() {
+        let source = r#"
+from dataclasses import dataclass
+from typing import TypeAlias
+
+MAX_RETRIES = 3
+default_timeout = 30.0
+UserId: TypeAlias = int
+type Pair[T] = tuple[T, T]
+
+@dataclass(frozen=True)
+class Config:
+    """Client settings"""
+    host: str
+    port: int = 8080
+
+class Client:
+    retries: int = 0
+
+    def connect(self):
+        attempts = 0
+"#;
+        let symbols = parse("config.py", source);
+        let summary: Vec<(&str, &SymbolKind, Option<&str>)> = symbols
+            .iter()
+            .map(|symbol| {
+                (
+                    symbol.name.as_str(),
+                    &symbol.kind,
+                    symbol.context.as_deref(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("dataclasses", &SymbolKind::Import, None),
+                ("typing", &SymbolKind::Import, None),
+                ("MAX_RETRIES", &SymbolKind::Constant, None),
+                ("default_timeout", &SymbolKind::Variable, None),
+                ("UserId", &SymbolKind::Type, None),
+                ("Pair", &SymbolKind::Type, None),
+                ("Config", &SymbolKind::Class, None),
+                ("host", &SymbolKind::Variable, Some("Config")),
+                ("port", &SymbolKind::Variable, Some("Config")),
+                ("Client", &SymbolKind::Class, None),
+                ("connect", &SymbolKind::Method, Some("Client")),
+            ]
+        );
+    }
+
+    #[test]
+    fn rust_trait_items_carry_the_trait_as_context() {
+        let source = r#"
+pub trait Storage {
+    type Key;
+    const CAPACITY: usize;
+
+    fn get(&self, key: &Self::Key) -> Option<Vec<u8>>;
+
+    fn contains(&self, key: &Self::Key) -> bool {
+        self.get(key).is_some()
+    }
+}
+"#;
+        let symbols = parse("storage.rs", source);
+        let summary: Vec<(&str, &SymbolKind, Option<&str>, Option<Visibility>)> = symbols
+            .iter()
+            .map(|symbol| {
+                (
+                    symbol.name.as_str(),
+                    &symbol.kind,
+                    symbol.context.as_deref(),
+                    symbol.modifiers.visibility,
+                )
+            })
+            .collect();
+        let public = Some(Visibility::Public);
+        assert_eq!(
+            summary,
+            vec![
+                ("Storage", &SymbolKind::Trait, None, public),
+                ("Key", &SymbolKind::Type, Some("Storage"), public),
+                ("CAPACITY", &SymbolKind::Constant, Some("Storage"), public),
+                ("get", &SymbolKind::Method, Some("Storage"), public),
+                ("contains", &SymbolKind::Method, Some("Storage"), public),
+            ]
+        );
+        assert_eq!(
+            symbols[3].signature,
+            "fn get(&self, key: &Self::Key) -> Option<Vec<u8>>;"
+        );
+    }
+
+    #[test]
+    fn rust_macros_are_extracted() {
+        let source = r#"
+macro_rules! hashmap {
+    ($($key:expr => $value:expr),*) => {{ std::collections::HashMap::new() }};
+}
+
+#[proc_macro_derive(Builder, attributes(builder))]
+pub fn derive_builder(input: TokenStream) -> TokenStream {
+    input
+}
+
+/// Times the annotated function
+#[proc_macro_attribute]
+pub fn timed(attr: TokenStream, item: TokenStream) -> TokenStream {
+    item
+}
+
+#[inline]
+fn helper() {}
+"#;
+        assert_eq!(
+            names_and_kinds("lib.rs", source),
+            vec![
+                ("hashmap".to_string(), SymbolKind::Macro),
+                ("Builder".to_string(), SymbolKind::Macro),
+                ("timed".to_string(), SymbolKind::Macro),
+                ("helper".to_string(), SymbolKind::Function),
+            ]
+        );
+    }
+
+    #[test]
+    fn signatures_stop_at_the_body() {
+        let signatures = |file_path: &str, source: &str| {
+            parse(file_path, source)
+                .into_iter()
+                .map(|symbol| symbol.signature)
+                .collect::<Vec<_>>()
+        };
+
+        let rust = r#"
+pub fn merge<T>(left: Vec<T>, right: Vec<T>) -> Vec<T>
+where
+    T: Ord,
+{
+    left
+}
+const LIMIT: usize = 10;
+"#;
+        assert_eq!(
+            signatures("merge.rs", rust),
+            vec![
+                "pub fn merge<T>(left: Vec<T>, right: Vec<T>) -> Vec<T>\nwhere\n    T: Ord,",
+                "const LIMIT: usize = 10;",
+            ]
+        );
+
+        let python = r#"
+class Cache(Base):
+    def get(self, key: str) -> str:
+        return key
+"#;
+        assert_eq!(
+            signatures("cache.py", python),
+            vec!["class Cache(Base):", "def get(self, key: str) -> str:"]
+        );
+
+        let typescript = "export const load = async (id: string): Promise<User> => {\n    return fetch(id);\n};\n";
+        assert_eq!(
+            signatures("load.ts", typescript),
+            vec!["const load = async (id: string): Promise<User> =>"]
+        );
+    }
+
+    #[test]
+    fn visibility_and_modifiers_are_captured() {
+        let modifiers = |file_path: &str, source: &str| {
+            parse(file_path, source)
+                .into_iter()
+                .map(|symbol| (symbol.name, symbol.modifiers))
+                .collect::<Vec<_>>()
+        };
+        let modifier = |visibility, is_async, is_static| SymbolModifiers {
+            visibility: Some(visibility),
+            is_async,
+            is_static,
+        };
+
+        let rust = r#"
+pub async fn fetch(path: &'static str) {}
+pub(crate) fn helper() {}
+fn private() {}
+"#;
+        assert_eq!(
+            modifiers("lib.rs", rust),
+            vec![
+                (
+                    "fetch".to_string(),
+                    modifier(Visibility::Public, true, false)
+                ),
+                (
+                    "helper".to_string(),
+                    modifier(Visibility::Internal, false, false)
+                ),
+                (
+                    "private".to_string(),
+                    modifier(Visibility::Private, false, false)
+                ),
+            ]
+        );
+
+        let python = r#"
+class Client:
+    @staticmethod
+    def build():
+        pass
+
+    async def _send(self):
+        pass
+"#;
+        assert_eq!(
+            modifiers("client.py", python),
+            vec![
+                (
+                    "Client".to_string(),
+                    modifier(Visibility::Public, false, false)
+                ),
+                (
+                    "build".to_string(),
+                    modifier(Visibility::Public, false, true)
+                ),
+                (
+                    "_send".to_string(),
+                    modifier(Visibility::Private, true, false)
+                ),
+            ]
+        );
+
+        let typescript = r#"
+export class Store {
+    private static cache() {}
+    async load() {}
+}
+
+function local() {}
+export const save = async (value: string) => value;
+"#;
+        assert_eq!(
+            modifiers("store.ts", typescript),
+            vec![
+                (
+                    "Store".to_string(),
+                    modifier(Visibility::Public, false, false)
+                ),
+                (
+                    "cache".to_string(),
+                    modifier(Visibility::Private, false, true)
+                ),
+                (
+                    "load".to_string(),
+                    modifier(Visibility::Public, true, false)
+                ),
+                (
+                    "local".to_string(),
+                    modifier(Visibility::Private, false, false)
+                ),
+                (
+                    "save".to_string(),
+                    modifier(Visibility::Public, true, false)
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn typescript_and_tsx_symbols_are_extracted() {
+        let source = r#"
+export interface User {
+    id: number;
+}
+
+export type UserId = User["id"];
+
+enum Role {
+    Admin,
+    Guest,
+}
+
+export function loadUser(id: UserId): User {
+    return { id };
+}
+
+export class UserStore {
+    private users: User[] = [];
+
+    add(user: User): void {
+        this.users.push(user);
+    }
+}
+
+const formatUser = (user: User) => `#${user.id}`;
+const LIMIT = 10;
+"#;
+        assert_eq!(
+            names_and_kinds("user.ts", source),
+            vec![
+                ("User".to_string(), SymbolKind::Interface),
+                ("UserId".to_string(), SymbolKind::Type),
+                ("Role".to_string(), SymbolKind::Enum),
+                ("loadUser".to_string(), SymbolKind::Function),
+                ("UserStore".to_string(), SymbolKind::Class),
+                ("add".to_string(), SymbolKind::Method),
+                ("formatUser".to_string(), SymbolKind::Function),
+            ]
+        );
+
+        let component = r#"
+export const Greeting = ({ name }: { name: string }) => <p>Hello {name}</p>;
+
+export default function App() {
+    return <Greeting name="team" />;
+}
+"#;
+        assert_eq!(
+            names_and_kinds("App.tsx", component),
+            vec![
+                ("Greeting".to_string(), SymbolKind::Function),
+                ("App".to_string(), SymbolKind::Function),
+            ]
+        );
+    }
+
+    #[test]
+    fn javascript_and_jsx_symbols_are_extracted() {
+        let source = r#"
+import { api } from "./api.mjs";
+
+export async function fetchCart(id) {
+    return api.get(`/carts/${id}`);
+}
+
+class Cart {
+    total() {
+        return 0;
+    }
+}
+
+export const addItem = async (cart, item) => cart.items.push(item);
+exports.removeItem = function (cart, index) {
+    cart.items.splice(index, 1);
+};
+module.exports.clear = (cart) => {
+    cart.items = [];
+};
+const TAX = 0.2;
+"#;
+        assert_eq!(
+            names_and_kinds("cart.js", source),
+            vec![
+                ("./api.mjs".to_string(), SymbolKind::Import),
+                ("fetchCart".to_string(), SymbolKind::Function),
+                ("Cart".to_string(), SymbolKind::Class),
+                ("total".to_string(), SymbolKind::Method),
+                ("addItem".to_string(), SymbolKind::Function),
+                ("removeItem".to_string(), SymbolKind::Function),
+                ("clear".to_string(), SymbolKind::Function),
+            ]
+        );
+
+        let component =
+            "export default ({ items }) => <ul>{items.map((item) => <li>{item}</li>)}</ul>;\n";
+        assert_eq!(
+            names_and_kinds("List.jsx", component),
+            vec![("default".to_string(), SymbolKind::Function)]
+        );
+        assert_eq!(
+            names_and_kinds("util.mjs", "export function noop() {}\n"),
+            vec![("noop".to_string(), SymbolKind::Function)]
+        );
+    }
+
+    #[test]
+    fn java_symbols_carry_their_type_and_annotations_as_context() {
+        let source = r#"
+package com.example.users;
+
+@RestController
+@RequestMapping("/users")
+public class UserController {
+    private static final int PAGE_SIZE = 20;
+    private final UserService service;
+
+    public UserController(UserService service) {
+        this.service = service;
+    }
+
+    @GetMapping("/{id}")
+    public User get(@PathVariable long id) {
+        return service.find(id);
+    }
+
+    enum Status { ACTIVE, DISABLED }
+}
+
+interface UserService {
+    User find(long id);
+}
+"#;
+        let symbols = parse("UserController.java", source);
+        let summary: Vec<(&str, &SymbolKind, Option<&str>)> = symbols
+            .iter()
+            .map(|symbol| {
+                (
+                    symbol.name.as_str(),
+                    &symbol.kind,
+                    symbol.context.as_deref(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (
+                    "UserController",
+                    &SymbolKind::Class,
+                    Some("@RestController @RequestMapping(\"/users\")")
+                ),
+                ("PAGE_SIZE", &SymbolKind::Constant, Some("UserController")),
+                ("service", &SymbolKind::Variable, Some("UserController")),
+                (
+                    "UserController",
+                    &SymbolKind::Method,
+                    Some("UserController")
+                ),
+                (
+                    "get",
+                    &SymbolKind::Method,
+                    Some("UserController @GetMapping(\"/{id}\")")
+                ),
+                ("Status", &SymbolKind::Enum, Some("UserController")),
+                ("UserService", &SymbolKind::Interface, None),
+                ("find", &SymbolKind::Method, Some("UserService")),
+            ]
+        );
+    }
+
+    #[test]
+    fn c_definitions_and_macros_are_extracted() {
+        let source = r#"
+#include <stdint.h>
+
+#define MAX_SENSORS 8
+#define CLAMP(x, lo, hi) ((x) < (lo) ? (lo) : (x) > (hi) ? (hi) : (x))
+
+struct sensor {
+    uint8_t id;
+    int16_t reading;
+};
+
+typedef struct {
+    struct sensor sensors[MAX_SENSORS];
+} board_t;
+
+typedef void (*irq_handler)(int);
+
+enum mode { MODE_IDLE, MODE_RUN };
+
+int read_sensor(struct sensor *s);
+
+static int16_t *latest_reading(struct sensor *s) {
+    return &s->reading;
+}
+"#;
+        assert_eq!(
+            names_and_kinds("board.c", source),
+            vec![
+                ("stdint.h".to_string(), SymbolKind::Import),
+                ("MAX_SENSORS".to_string(), SymbolKind::Macro),
+                ("CLAMP".to_string(), SymbolKind::Macro),
+                ("sensor".to_string(), SymbolKind::Struct),
+                ("board_t".to_string(), SymbolKind::Type),
+                ("irq_handler".to_string(), SymbolKind::Type),
+                ("mode".to_string(), SymbolKind::Enum),
+                ("latest_reading".to_string(), SymbolKind::Function),
+            ]
+        );
+        assert_eq!(
+            names_and_kinds("board.h", "#define BOARD_H\n"),
+            vec![("BOARD_H".to_string(), SymbolKind::Macro)]
+        );
+    }
+
+    #[test]
+    fn cpp_symbols_carry_namespace_and_class_context() {
+        let source = r#"
+namespace net {
+namespace http {
+
+template <typename Body>
+class Client {
+public:
+    explicit Client(std::string host) : host_(std::move(host)) {}
+
+    Response send(const Request<Body>& request) {
+        return transport_.send(request);
+    }
+
+private:
+    std::string host_;
+};
+
+enum class Method { Get, Post };
+
+using Headers = std::map<std::string, std::string>;
+
+}  // namespace http
+
+bool Socket::connect(int port) {
+    return port > 0;
+}
+
+template <typename T>
+T clamp(T value, T lo, T hi) {
+    return value < lo ? lo : value > hi ? hi : value;
+}
+
+}  // namespace net
+
+int main() {
+    return 0;
+}
+"#;
+        let symbols = parse("client.cpp", source);
+        let summary: Vec<(&str, &SymbolKind, Option<&str>)> = symbols
+            .iter()
+            .map(|symbol| {
+                (
+                    symbol.name.as_str(),
+                    &symbol.kind,
+                    symbol.context.as_deref(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("net", &SymbolKind::Module, None),
+                ("http", &SymbolKind::Module, Some("net")),
+                ("Client", &SymbolKind::Class, Some("net::http")),
+                ("Client", &SymbolKind::Method, Some("net::http::Client")),
+                ("send", &SymbolKind::Method, Some("net::http::Client")),
+                ("Method", &SymbolKind::Enum, Some("net::http")),
+                ("Headers", &SymbolKind::Type, Some("net::http")),
+                ("connect", &SymbolKind::Method, Some("net::Socket")),
+                ("clamp", &SymbolKind::Function, Some("net")),
+                ("main", &SymbolKind::Function, None),
+            ]
+        );
+        let client = &symbols[2];
+        assert!(client.content.starts_with("template <typename Body>"));
+    }
+
+    #[test]
+    fn csharp_symbols_carry_namespace_and_type_context() {
+        let source = r#"
+using System;
+
+namespace Shop.Orders;
+
+public record OrderPlaced(Guid Id, decimal Total);
+
+public interface IOrderService
+{
+    Order Find(Guid id);
+}
+
+public class OrderService : IOrderService
+{
+    public int Count { get; private set; }
+
+    public OrderService(IRepository repository) { }
+
+    public Order Find(Guid id)
+    {
+        bool Matches(Order order) => order.Id == id;
+        return repository.All().First(Matches);
+    }
+
+    public enum State { Open, Closed }
+}
+"#;
+        let symbols = parse("OrderService.cs", source);
+        let summary: Vec<(&str, &SymbolKind, Option<&str>)> = symbols
+            .iter()
+            .map(|symbol| {
+                (
+                    symbol.name.as_str(),
+                    &symbol.kind,
+                    symbol.context.as_deref(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("System", &SymbolKind::Import, None),
+                ("Shop.Orders", &SymbolKind::Module, None),
+                ("OrderPlaced", &SymbolKind::Class, Some("Shop.Orders")),
+                ("IOrderService", &SymbolKind::Interface, Some("Shop.Orders")),
+                (
+                    "Find",
+                    &SymbolKind::Method,
+                    Some("Shop.Orders.IOrderService")
+                ),
+                ("OrderService", &SymbolKind::Class, Some("Shop.Orders")),
+                (
+                    "Count",
+                    &SymbolKind::Property,
+                    Some("Shop.Orders.OrderService")
+                ),
+                (
+                    "OrderService",
+                    &SymbolKind::Method,
+                    Some("Shop.Orders.OrderService")
+                ),
+                (
+                    "Find",
+                    &SymbolKind::Method,
+                    Some("Shop.Orders.OrderService")
+                ),
+                (
+                    "Matches",
+                    &SymbolKind::Function,
+                    Some("Shop.Orders.OrderService")
+                ),
+                ("State", &SymbolKind::Enum, Some("Shop.Orders.OrderService")),
+            ]
+        );
+
+        let block_scoped = "namespace Shop { class Cart { void Clear() { } } }\n";
+        let symbols = parse("Cart.cs", block_scoped);
+        assert_eq!(symbols[2].name, "Clear");
+        assert_eq!(symbols[2].context.as_deref(), Some("Shop.Cart"));
+    }
+
+    #[test]
+    fn elixir_definitions_carry_their_module_as_context() {
+        let source = r#"
+defmodule MyAppWeb.UserController do
+  use MyAppWeb, :controller
+
+  defmodule Params do
+    defstruct [:name, :email]
+  end
+
+  def index(conn, _params) do
+    render(conn, :index, users: Accounts.list_users())
+  end
+
+  def show(conn, %{"id" => id}) when is_binary(id), do: render(conn, :show)
+
+  defp authorize do
+    :ok
+  end
+
+  defmacro admin_only(do: block) do
+    quote do: unquote(block)
+  end
+end
+
+defprotocol Greeter do
+  def greet(person)
+end
+"#;
+        let symbols = parse("user_controller.ex", source);
+        let summary: Vec<(&str, &SymbolKind, Option<&str>)> = symbols
+            .iter()
+            .map(|symbol| {
+                (
+                    symbol.name.as_str(),
+                    &symbol.kind,
+                    symbol.context.as_deref(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("MyAppWeb.UserController", &SymbolKind::Module, None),
+                (
+                    "MyAppWeb.UserController.Params",
+                    &SymbolKind::Module,
+                    Some("MyAppWeb.UserController")
+                ),
+                (
+                    "MyAppWeb.UserController.Params",
+                    &SymbolKind::Struct,
+                    Some("MyAppWeb.UserController.Params")
+                ),
+                (
+                    "index",
+                    &SymbolKind::Function,
+                    Some("MyAppWeb.UserController")
+                ),
+                (
+                    "show",
+                    &SymbolKind::Function,
+                    Some("MyAppWeb.UserController")
+                ),
+                (
+                    "authorize",
+                    &SymbolKind::Function,
+                    Some("MyAppWeb.UserController")
+                ),
+                (
+                    "admin_only",
+                    &SymbolKind::Macro,
+                    Some("MyAppWeb.UserController")
+                ),
+                ("Greeter", &SymbolKind::Interface, None),
+                ("greet", &SymbolKind::Function, Some("Greeter")),
+            ]
+        );
+    }
+
+    #[test]
+    fn scala_symbols_carry_their_enclosing_type_as_context() {
+        let source = r#"
+package geometry
+
+trait Shape {
+  def area: Double
+}
+
+case class Circle(r: Double) extends Shape {
+  val pi = 3.14
+  def area: Double = pi * r * r
+}
+
+object Shapes {
+  val unitCircle: Circle = Circle(1)
+  def describe(shape: Shape): String = s"area ${shape.area}"
+}
+"#;
+        assert_eq!(
+            names_kinds_and_contexts("Shapes.scala", source),
+            vec![
+                ("Shape".to_string(), SymbolKind::Trait, None),
+                (
+                    "area".to_string(),
+                    SymbolKind::Method,
+                    Some("Shape".to_string())
+                ),
+                ("Circle".to_string(), SymbolKind::Class, None),
+                (
+                    "pi".to_string(),
+                    SymbolKind::Constant,
+                    Some("Circle".to_string())
+                ),
+                (
+                    "area".to_string(),
+                    SymbolKind::Method,
+                    Some("Circle".to_string())
+                ),
+                ("Shapes".to_string(), SymbolKind::Module, None),
+                (
+                    "unitCircle".to_string(),
+                    SymbolKind::Constant,
+                    Some("Shapes".to_string())
+                ),
+                (
+                    "describe".to_string(),
+                    SymbolKind::Method,
+                    Some("Shapes".to_string())
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn haskell_functions_types_classes_and_instances_are_extracted() {
+        let source = r#"
+module Shapes where
+
+data Shape = Circle Double | Square Double
+
+newtype Name = Name String
+
+type Area = Double
+
+class HasArea a where
+  area :: a -> Area
+
+instance HasArea Shape where
+  area (Circle r) = 3.14 * r * r
+  area (Square s) = s * s
+
+perimeter :: Shape -> Double
+perimeter (Circle r) = 2 * 3.14 * r
+perimeter (Square s) = 4 * s
+"#;
+        assert_eq!(
+            names_kinds_and_contexts("Shapes.hs", source),
+            vec![
+                ("Shape".to_string(), SymbolKind::Type, None),
+                ("Name".to_string(), SymbolKind::Type, None),
+                ("Area".to_string(), SymbolKind::Type, None),
+                ("HasArea".to_string(), SymbolKind::Trait, None),
+                (
+                    "area".to_string(),
+                    SymbolKind::Method,
+                    Some("HasArea".to_string())
+                ),
+                ("HasArea Shape".to_string(), SymbolKind::Impl, None),
+                (
+                    "area".to_string(),
+                    SymbolKind::Method,
+                    Some("HasArea Shape".to_string())
+                ),
+                ("perimeter".to_string(), SymbolKind::Function, None),
+            ]
+        );
+        // A function's signature and equations make up one symbol
+        let perimeter = parse("Shapes.hs", source)
+            .into_iter()
+            .find(|symbol| symbol.name == "perimeter")
+            .expect("perimeter is extracted");
+        assert_eq!((perimeter.start_line, perimeter.end_line), (17, 19));
+    }
+
+    #[test]
+    fn dart_classes_mixins_methods_and_functions_are_extracted() {
+        let source = r#"
+import 'package:flutter/widgets.dart';
+
+mixin Logging {
+  void log(String message) {
+    print(message);
+  }
+}
+
+class Counter extends StatelessWidget with Logging {
+  int get doubled => 2;
+
+  Widget build(BuildContext context) {
+    return Text('count');
+  }
+}
+
+enum Mode { light, dark }
+
+int twice(int x) {
+  return x * 2;
+}
+"#;
+        assert_eq!(
+            names_kinds_and_contexts("counter.dart", source),
+            vec![
+                (
+                    "package:flutter/widgets.dart".to_string(),
+                    SymbolKind::Import,
+                    None
+                ),
+                ("Logging".to_string(), SymbolKind::Trait, None),
+                (
+                    "log".to_string(),
+                    SymbolKind::Method,
+                    Some("Logging".to_string())
+                ),
+                ("Counter".to_string(), SymbolKind::Class, None),
+                (
+                    "doubled".to_string(),
+                    SymbolKind::Method,
+                    Some("Counter".to_string())
+                ),
+                (
+                    "build".to_string(),
+                    SymbolKind::Method,
+                    Some("Counter".to_string())
+                ),
+                ("Mode".to_string(), SymbolKind::Enum, None),
+                ("twice".to_string(), SymbolKind::Function, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn sql_tables_views_and_functions_are_extracted() {
+        let source = r#"
+CREATE TABLE billing.invoices (
+  id INT PRIMARY KEY,
+  total NUMERIC
+);
+
+CREATE VIEW open_invoices AS SELECT * FROM billing.invoices WHERE total > 0;
+
+CREATE MATERIALIZED VIEW invoice_totals AS SELECT sum(total) FROM billing.invoices;
+
+CREATE FUNCTION invoice_count() RETURNS INT AS $$ SELECT count(*) FROM billing.invoices $$ LANGUAGE sql;
+
+INSERT INTO billing.invoices VALUES (1, 10);
+"#;
+        assert_eq!(
+            names_and_kinds("schema.sql", source),
+            vec![
+                ("billing.invoices".to_string(), SymbolKind::Table),
+                ("open_invoices".to_string(), SymbolKind::View),
+                ("invoice_totals".to_string(), SymbolKind::View),
+                ("invoice_count".to_string(), SymbolKind::Function),
+            ]
+        );
+    }
+
+    #[test]
+    fn proto_messages_services_and_rpcs_carry_the_package_as_context() {
+        let source = r#"
+syntax = "proto3";
+
+package acme.users.v1;
+
+import "google/protobuf/timestamp.proto";
+
+message UserProfile {
+  string name = 1;
+
+  message Address {
+    string city = 1;
+  }
+
+  enum Status {
+    ACTIVE = 0;
+  }
+}
+
+service UserService {
+  rpc GetProfile(UserProfile) returns (UserProfile);
+}
+"#;
+        let package = |context: &str| Some(format!("acme.users.v1{context}"));
+        assert_eq!(
+            names_kinds_and_contexts("user.proto", source),
+            vec![
+                (
+                    "google/protobuf/timestamp.proto".to_string(),
+                    SymbolKind::Import,
+                    None
+                ),
+                ("UserProfile".to_string(), SymbolKind::Struct, package("")),
+                (
+                    "Address".to_string(),
+                    SymbolKind::Struct,
+                    package(".UserProfile")
+                ),
+                (
+                    "Status".to_string(),
+                    SymbolKind::Enum,
+                    package(".UserProfile")
+                ),
+                (
+                    "UserService".to_string(),
+                    SymbolKind::Interface,
+                    package("")
+                ),
+                (
+                    "GetProfile".to_string(),
+                    SymbolKind::Method,
+                    package(".UserService")
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn graphql_types_and_operations_keep_their_descriptions() {
+        let source = r#""""A registered user"""
+type User {
+  id: ID!
+  name: String
+}
+
+interface Node {
+  id: ID!
+}
+
+enum Role {
+  ADMIN
+  MEMBER
+}
+
+input RenameInput {
+  name: String!
+}
+
+type Query {
+  "Look a user up by ID"
+  user(id: ID!): User
+}
+
+type Mutation {
+  rename(id: ID!, input: RenameInput!): User
+}
+"#;
+        assert_eq!(
+            names_kinds_and_contexts("schema.graphql", source),
+            vec![
+                ("User".to_string(), SymbolKind::Type, None),
+                ("Node".to_string(), SymbolKind::Interface, None),
+                ("Role".to_string(), SymbolKind::Enum, None),
+                ("RenameInput".to_string(), SymbolKind::Type, None),
+                ("Query".to_string(), SymbolKind::Type, None),
+                (
+                    "user".to_string(),
+                    SymbolKind::Method,
+                    Some("Query".to_string())
+                ),
+                ("Mutation".to_string(), SymbolKind::Type, None),
+                (
+                    "rename".to_string(),
+                    SymbolKind::Method,
+                    Some("Mutation".to_string())
+                ),
+            ]
+        );
+        let symbols = parse("schema.gql", source);
+        assert!(
+            symbols[0]
+                .content
+                .starts_with("\"\"\"A registered user\"\"\"")
+        );
+        assert!(symbols[5].content.starts_with("\"Look a user up by ID\""));
+    }
+
+    #[test]
+    fn terraform_blocks_are_extracted_by_label() {
+        let source = r#"
+variable "region" {
+  default = "us-east-1"
+}
+
+resource "aws_s3_bucket" "logs" {
+  bucket = "logs"
+}
+
+data "aws_iam_policy_document" "read" {
+}
+
+module "network" {
+  source = "./network"
+}
+
+output "bucket_arn" {
+  value = aws_s3_bucket.logs.arn
+}
+"#;
+        assert_eq!(
+            names_kinds_and_contexts("main.tf", source),
+            vec![
+                ("region".to_string(), SymbolKind::Variable, None),
+                (
+                    "logs".to_string(),
+                    SymbolKind::Resource,
+                    Some("aws_s3_bucket".to_string())
+                ),
+                (
+                    "read".to_string(),
+                    SymbolKind::Resource,
+                    Some("aws_iam_policy_document".to_string())
+                ),
+                ("network".to_string(), SymbolKind::Module, None),
+                ("bucket_arn".to_string(), SymbolKind::Constant, None),
+            ]
+        );
+    }
 }
@@ -0,0 +1,341 @@
+//! Declarative symbol extraction with tree-sitter queries
+//!
+//! Each language's query in `queries/` captures a definition as `@definition.<kind>`,
+//! with `<kind>` named as in `--kind` filters, and its name as `@name`. A pattern
+//! without a `@name` is named by its `(#set! name "...")`, and one with several joins
+//! them with spaces. Definitions nested in a class, struct, enum, trait, impl or
+//! interface get that container's name as context, unless the pattern captures one
+//! as `@context`. When several patterns match the same node the first one wins, so
+//! specific patterns go before general ones, and a node matched more than once by
+//! it is named by its first match.
+//!
+//! Patterns can also set:
+//! - `(#set! scope "::")`: the definition is a container whatever its kind, and its
+//!   members' context is the enclosing context and its name joined by the
+//!   separator, e.g. `net::http::Client`.
+//! - `(#set! leaf)`: definitions inside this one are left to its chunk.
+//!
+//! A pattern that captures a statement as `@scope` rather than a definition makes
+//! its `@name` the context of the declarations after it in the same parent, as a
+//! Protobuf `package` or C# `namespace Shop;` does.
+
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::sync::LazyLock;
+use std::sync::Mutex;
+
+use tree_sitter::Language;
+use tree_sitter::Node;
+use tree_sitter::Query;
+use tree_sitter::QueryCursor;
+use tree_sitter::StreamingIterator;
+
+use crate::symbol::SupportedLanguage;
+use crate::symbol::SymbolKind;
+
+/// Compiled queries by grammar, shared by every parser in the process since
+/// compiling them costs far more than parsing a file
+static QUERIES: LazyLock<Mutex<HashMap<Language, Arc<SymbolQuery>>>> =
+    LazyLock::new(Default::default);
+
+/// A compiled symbol query for one grammar
+pub(crate) struct SymbolQuery {
+    query: Query,
+    /// Symbol kind of each `@definition.<kind>` capture, by capture index
+    kinds: Vec<Option<SymbolKind>>,
+    name: Option<u32>,
+    context: Option<u32>,
+    scope: Option<u32>,
+    /// `#set!` properties of each pattern, by pattern index
+    settings: Vec<PatternSettings>,
+}
+
+/// The `#set!` properties a pattern can have
+#[derive(Default)]
+struct PatternSettings {
+    name: Option<String>,
+    separator: Option<String>,
+    leaf: bool,
+}
+
+/// A definition a query matched
+pub(crate) struct Definition<'tree> {
+    pub node: Node<'tree>,
+    pub name: String,
+    pub kind: SymbolKind,
+    pub context: Option<String>,
+}
+
+/// A match before duplicates are dropped and contexts are resolved; a `@scope`
+/// match has no kind and covers the rest of its parent
+struct Candidate<'tree> {
+    pattern: usize,
+    node: Node<'tree>,
+    start: usize,
+    end: usize,
+    name: String,
+    name_start: usize,
+    kind: Option<SymbolKind>,
+    context: Option<String>,
+}
+
+/// A definition or `@scope` enclosing the candidates that follow it
+struct Container {
+    end: usize,
+    /// Context of its members, `None` for a leaf
+    path: Option<String>,
+}
+
+impl SymbolQuery {
+    /// The query for `language`'s grammar `grammar`, if its symbols are described by one
+    /// Each grammar's query is compiled once per process.
+    pub(crate) fn for_language(
+        language: &SupportedLanguage,
+        grammar: &Language,
+    ) -> Result<Option<Arc<Self>>, anyhow::Error> {
+        let source = match language {
+            SupportedLanguage::Rust => include_str!("../queries/rust.scm").to_string(),
+            SupportedLanguage::Java => include_str!("../queries/java.scm").to_string(),
+            SupportedLanguage::C => include_str!("../queries/c.scm").to_string(),
+            SupportedLanguage::Cpp => include_str!("../queries/cpp.scm").to_string(),
+            SupportedLanguage::CSharp => include_str!("../queries/csharp.scm").to_string(),
+            SupportedLanguage::Scala => include_str!("../queries/scala.scm").to_string(),
+            SupportedLanguage::Elixir => include_str!("../queries/elixir.scm").to_string(),
+            SupportedLanguage::Haskell => include_str!("../queries/haskell.scm").to_string(),
+            SupportedLanguage::Dart => include_str!("../queries/dart.scm").to_string(),
+            SupportedLanguage::Sql => include_str!("../queries/sql.scm").to_string(),
+            SupportedLanguage::Protobuf => include_str!("../queries/proto.scm").to_string(),
+            SupportedLanguage::GraphQl => include_str!("../queries/graphql.scm").to_string(),
+            SupportedLanguage::Terraform => include_str!("../queries/hcl.scm").to_string(),
+            SupportedLanguage::Python => include_str!("../queries/python.scm").to_string(),
+            SupportedLanguage::Go => include_str!("../queries/go.scm").to_string(),
+            SupportedLanguage::JavaScript => include_str!("../queries/javascript.scm").to_string(),
+            SupportedLanguage::TypeScript => format!(
+                "{}\n{}",
+                include_str!("../queries/javascript.scm"),
+                include_str!("../queries/typescript.scm")
+            ),
+            _ => return Ok(None),
+        };
+        let mut queries = QUERIES
+            .lock()
+            .map_err(|_| anyhow::anyhow!("Symbol query cache poisoned"))?;
+        if let Some(query) = queries.get(grammar) {
+            return Ok(Some(Arc::clone(query)));
+        }
+        let query = Self::new(grammar, &source)
+            .map(Arc::new)
+            .map_err(|e| anyhow::anyhow!("Invalid {} symbol query: {e}", language.name()))?;
+        queries.insert(grammar.clone(), Arc::clone(&query));
+        Ok(Some(query))
+    }
+
+    /// Compile `source` for `grammar`
+    pub(crate) fn new(grammar: &Language, source: &str) -> Result<Self, anyhow::Error> {
+        let query = Query::new(grammar, source)?;
+        let mut kinds = Vec::new();
+        for capture in query.capture_names() {
+            let kind = match capture.strip_prefix("definition.") {
+                Some(kind) => Some(
+                    SymbolKind::from_name(kind)
+                        .ok_or_else(|| anyhow::anyhow!("unknown symbol kind @{capture}"))?,
+                ),
+                None => None,
+            };
+            kinds.push(kind);
+        }
+        let mut settings = Vec::new();
+        for pattern in 0..query.pattern_count() {
+            let mut pattern_settings = PatternSettings::default();
+            for property in query.property_settings(pattern) {
+                let value = property.value.as_deref().map(str::to_string);
+                match &*property.key {
+                    "name" => pattern_settings.name = value,
+                    "scope" => pattern_settings.separator = value,
+                    "leaf" => pattern_settings.leaf = true,
+                    key => anyhow::bail!("unknown property {key} in pattern {pattern}"),
+                }
+            }
+            settings.push(pattern_settings);
+        }
+        Ok(Self {
+            name: query.capture_index_for_name("name"),
+            context: query.capture_index_for_name("context"),
+            scope: query.capture_index_for_name("scope"),
+            settings,
+            kinds,
+            query,
+        })
+    }
+
+    /// Every definition under `root`, in source order with containers before their
+    /// members
+    pub(crate) fn definitions<'tree>(
+        &self,
+        root: Node<'tree>,
+        source: &str,
+    ) -> Result<Vec<Definition<'tree>>, anyhow::Error> {
+        let mut candidates = Vec::new();
+        let mut cursor = QueryCursor::new();
+        let mut matches = cursor.matches(&self.query, root, source.as_bytes());
+        while let Some(found) = matches.next() {
+            let settings = &self.settings[found.pattern_index];
+            let mut definition = None;
+            let mut scope = None;
+            let mut names = Vec::new();
+            let mut context = None;
+            for capture in found.captures {
+                if let Some(Some(kind)) = self.kinds.get(capture.index as usize) {
+                    definition = Some((capture.node, kind.clone()));
+                } else if Some(capture.index) == self.name {
+                    names.push(capture.node);
+                } else if Some(capture.index) == self.context {
+                    context = Some(capture.node.utf8_text(source.as_bytes())?.to_string());
+                } else if Some(capture.index) == self.scope {
+                    scope = Some(capture.node);
+                }
+            }
+            let (name, name_start) = match names.first() {
+                Some(first) => {
+                    let mut parts = Vec::new();
+                    for name in &names {
+                        parts.extend(name.utf8_text(source.as_bytes())?.split_whitespace());
+                    }
+                    (parts.join(" "), first.start_byte())
+                }
+                None => match (&settings.name, definition.as_ref()) {
+                    (Some(name), Some((node, _))) => (name.clone(), node.start_byte()),
+                    _ => continue,
+                },
+            };
+            let candidate = match (definition, scope) {
+                (Some((node, kind)), _) => Candidate {
+                    pattern: found.pattern_index,
+                    node,
+                    start: node.start_byte(),
+                    end: node.end_byte(),
+                    name,
+                    name_start,
+                    kind: Some(kind),
+                    context,
+                },
+                (None, Some(node)) => Candidate {
+                    pattern: found.pattern_index,
+                    node,
+                    start: node.end_byte(),
+                    end: node
+                        .parent()
+                        .map_or(node.end_byte(), |parent| parent.end_byte()),
+                    name,
+                    name_start,
+                    kind: None,
+                    context,
+                },
+                (None, None) => continue,
+            };
+            candidates.push(candidate);
+        }
+
+        candidates.sort_by_key(|candidate| {
+            (
+                candidate.start,
+                Reverse(candidate.end),
+                candidate.name_start,
+                candidate.pattern,
+            )
+        });
+        let mut first_patterns = HashMap::new();
+        for candidate in candidates
+            .iter()
+            .filter(|candidate| candidate.kind.is_some())
+        {
+            first_patterns
+                .entry(candidate.node.id())
+                .and_modify(|pattern: &mut usize| *pattern = (*pattern).min(candidate.pattern))
+                .or_insert(candidate.pattern);
+        }
+        let mut seen = HashSet::new();
+        candidates.retain(|candidate| {
+            candidate.kind.is_none()
+                || (first_patterns.get(&candidate.node.id()) == Some(&candidate.pattern)
+                    && seen.insert(candidate.node.id()))
+        });
+
+        // Containers enclosing the current candidate, innermost last
+        let mut containers: Vec<Container> = Vec::new();
+        let mut definitions = Vec::new();
+        for candidate in candidates {
+            while containers.last().is_some_and(|container| {
+                container.end < candidate.end || container.end <= candidate.start
+            }) {
+                containers.pop();
+            }
+            let outer = match containers.last() {
+                // Nothing inside a leaf is a definition of its own
+                Some(Container { path: None, .. }) => continue,
+                Some(container) => container.path.clone(),
+                None => None,
+            };
+            self.push_candidate(candidate, outer, &mut containers, &mut definitions);
+        }
+        Ok(definitions)
+    }
+
+    /// Resolve `candidate`'s context given that of its container, `outer`, and record
+    /// it as a definition, a container or both
+    fn push_candidate<'tree>(
+        &self,
+        candidate: Candidate<'tree>,
+        outer: Option<String>,
+        containers: &mut Vec<Container>,
+        definitions: &mut Vec<Definition<'tree>>,
+    ) {
+        let Some(kind) = candidate.kind else {
+            containers.push(Container {
+                end: candidate.end,
+                path: Some(candidate.name),
+            });
+            return;
+        };
+        let settings = &self.settings[candidate.pattern];
+        let context = candidate.context.or(outer);
+        if settings.leaf {
+            containers.push(Container {
+                end: candidate.end,
+                path: None,
+            });
+        } else if settings.separator.is_some() || provides_context(&kind) {
+            let path = match (&settings.separator, &context) {
+                (Some(separator), Some(context)) => {
+                    format!("{context}{separator}{}", candidate.name)
+                }
+                _ => candidate.name.clone(),
+            };
+            containers.push(Container {
+                end: candidate.end,
+                path: Some(path),
+            });
+        }
+        definitions.push(Definition {
+            node: candidate.node,
+            name: candidate.name,
+            kind,
+            context,
+        });
+    }
+}
+
+/// Whether definitions nested in one of `kind` belong to it
+fn provides_context(kind: &SymbolKind) -> bool {
+    matches!(
+        kind,
+        SymbolKind::Class
+            | SymbolKind::Struct
+            | SymbolKind::Enum
+            | SymbolKind::Trait
+            | SymbolKind::Impl
+            | SymbolKind::Interface
+    )
+}
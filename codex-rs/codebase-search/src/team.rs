@@ -0,0 +1,355 @@
+//! Shared index access for a team (`serve --team-config` and `grpc --team-config`)
+//! The team config names each hosted project (its namespace) and lists the API tokens
+//! that may use them:
+//!
+//! ```toml
+//! [[projects]]
+//! name = "monorepo"
+//! path = "/srv/checkouts/monorepo"
+//!
+//! [[tokens]]
+//! name = "ci"
+//! token_sha256 = "<sha256 of the token, hex>"   # or token = "..."
+//! role = "maintainer"
+//!
+//! [[tokens]]
+//! name = "agents"
+//! token = "..."
+//! role = "reader"
+//! projects = ["monorepo"]                        # every project when omitted
+//! ```
+//!
+//! Readers can search and read status and events; maintainers can also reindex.
+//! Clients send their token as `Authorization: Bearer <token>`.
+
+use std::collections::HashSet;
+use std::path::Path;
+use std::path::PathBuf;
+
+use serde::Deserialize;
+use sha2::Digest;
+use sha2::Sha256;
+
+/// What a token may do
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    /// Search, status and events
+    Reader,
+    /// Everything a reader can do, plus reindexing
+    Maintainer,
+}
+
+/// A hosted project, addressed by `name` in requests
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct TeamProject {
+    pub name: String,
+    pub path: PathBuf,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TeamFile {
+    projects: Vec<TeamProject>,
+    #[serde(default)]
+    tokens: Vec<TokenEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TokenEntry {
+    /// Shown in logs instead of the token
+    name: String,
+    token: Option<String>,
+    token_sha256: Option<String>,
+    role: Role,
+    #[serde(default)]
+    projects: Vec<String>,
+}
+
+#[derive(Debug)]
+struct ApiToken {
+    name: String,
+    /// Lowercase hex SHA-256 of the token
+    sha256: String,
+    role: Role,
+    /// Project names the token may use; every project when empty
+    projects: Vec<String>,
+}
+
+/// Why a request was refused
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AccessError {
+    /// No token, or one the config doesn't list
+    Unauthenticated,
+    /// A valid token without access to the project or operation
+    Forbidden(String),
+    UnknownProject(String),
+    /// The server hosts several projects and the request named none
+    ProjectRequired,
+}
+
+impl std::fmt::Display for AccessError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AccessError::Unauthenticated => f.write_str("a valid API token is required"),
+            AccessError::Forbidden(message) => f.write_str(message),
+            AccessError::UnknownProject(name) => write!(f, "no project is named {name}"),
+            AccessError::ProjectRequired => {
+                f.write_str("project is required when the server hosts several projects")
+            }
+        }
+    }
+}
+
+impl std::error::Error for AccessError {}
+
+/// Projects and API tokens of a team server
+#[derive(Debug)]
+pub struct TeamConfig {
+    projects: Vec<TeamProject>,
+    tokens: Vec<ApiToken>,
+}
+
+impl TeamConfig {
+    /// Read a team config file; project paths are canonicalized
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, anyhow::Error> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("Failed to read {}: {e}", path.display()))?;
+        let mut config = Self::parse(&content)
+            .map_err(|e| anyhow::anyhow!("Invalid team config {}: {e}", path.display()))?;
+        for project in &mut config.projects {
+            project.path = project.path.canonicalize().map_err(|e| {
+                anyhow::anyhow!(
+                    "Project {} at {}: {e}",
+                    project.name,
+                    project.path.display()
+                )
+            })?;
+        }
+        Ok(config)
+    }
+
+    fn parse(content: &str) -> Result<Self, anyhow::Error> {
+        let file: TeamFile = toml::from_str(content)?;
+        if file.projects.is_empty() {
+            anyhow::bail!("at least one project is required");
+        }
+        let mut names = HashSet::new();
+        for project in &file.projects {
+            if !names.insert(project.name.as_str()) {
+                anyhow::bail!("project {} is listed twice", project.name);
+            }
+        }
+
+        let mut tokens = Vec::new();
+        for entry in file.tokens {
+            let sha256 = match (entry.token, entry.token_sha256) {
+                (Some(token), None) => sha256_hex(&token),
+                (None, Some(hash)) => {
+                    let hash = hash.to_ascii_lowercase();
+                    if hash.len() != 64 || !hash.chars().all(|c| c.is_ascii_hexdigit()) {
+                        anyhow::bail!("token {}: token_sha256 must be 64 hex digits", entry.name);
+                    }
+                    hash
+                }
+                _ => anyhow::bail!(
+                    "token {}: set exactly one of token and token_sha256",
+                    entry.name
+                ),
+            };
+            if let Some(unknown) = entry
+                .projects
+                .iter()
+                .find(|name| !names.contains(name.as_str()))
+            {
+                anyhow::bail!("token {}: no project is named {unknown}", entry.name);
+            }
+            tokens.push(ApiToken {
+                name: entry.name,
+                sha256,
+                role: entry.role,
+                projects: entry.projects,
+            });
+        }
+        Ok(Self {
+            projects: file.projects,
+            tokens,
+        })
+    }
+
+    /// Hosted projects, in config order
+    pub fn projects(&self) -> &[TeamProject] {
+        &self.projects
+    }
+
+    /// The project `project` names (or the only one when None), if `token` may act on
+    /// it as `role`
+    pub fn authorize(
+        &self,
+        token: Option<&str>,
+        project: Option<&str>,
+        role: Role,
+    ) -> Result<&TeamProject, AccessError> {
+        let token = self.token(token)?;
+        let project = match project.filter(|name| !name.is_empty()) {
+            Some(name) => self
+                .projects
+                .iter()
+                .find(|project| project.name == name)
+                .ok_or_else(|| AccessError::UnknownProject(name.to_string()))?,
+            None => match self.projects.as_slice() {
+                [project] => project,
+                _ => return Err(AccessError::ProjectRequired),
+            },
+        };
+        if !token.allows(&project.name) {
+            return Err(AccessError::Forbidden(format!(
+                "token {} has no access to project {}",
+                token.name, project.name
+            )));
+        }
+        if token.role < role {
+            return Err(AccessError::Forbidden(format!(
+                "token {} is not a maintainer of project {}",
+                token.name, project.name
+            )));
+        }
+        Ok(project)
+    }
+
+    /// Roots of every project `token` may read
+    pub fn readable_roots(&self, token: Option<&str>) -> Result<Vec<PathBuf>, AccessError> {
+        let token = self.token(token)?;
+        Ok(self
+            .projects
+            .iter()
+            .filter(|project| token.allows(&project.name))
+            .map(|project| project.path.clone())
+            .collect())
+    }
+
+    fn token(&self, token: Option<&str>) -> Result<&ApiToken, AccessError> {
+        let hash = sha256_hex(token.ok_or(AccessError::Unauthenticated)?);
+        self.tokens
+            .iter()
+            .find(|candidate| constant_time_eq(candidate.sha256.as_bytes(), hash.as_bytes()))
+            .ok_or(AccessError::Unauthenticated)
+    }
+}
+
+impl ApiToken {
+    fn allows(&self, project: &str) -> bool {
+        self.projects.is_empty() || self.projects.iter().any(|name| name == project)
+    }
+}
+
+/// The token in an `Authorization: Bearer <token>` header value
+pub fn bearer_token(header: &str) -> Option<&str> {
+    header
+        .strip_prefix("Bearer ")
+        .map(str::trim)
+        .filter(|token| !token.is_empty())
+}
+
+fn sha256_hex(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
+/// Compare without returning early, so timing doesn't reveal how much of a token matched
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::expect_used)]
+    use super::*;
+
+    #[test]
+    fn tokens_are_scoped_to_projects_and_roles() {
+        let config = TeamConfig::parse(&format!(
+            r#"
+[[projects]]
+name = "monorepo"
+path = "/srv/monorepo"
+
+[[projects]]
+name = "docs"
+path = "/srv/docs"
+
+[[tokens]]
+name = "ci"
+token_sha256 = "{}"
+role = "maintainer"
+
+[[tokens]]
+name = "agents"
+token = "reader-secret"
+role = "reader"
+projects = ["monorepo"]
+"#,
+            sha256_hex("ci-secret")
+        ))
+        .expect("Valid team config");
+
+        assert_eq!(
+            config
+                .authorize(Some("ci-secret"), Some("docs"), Role::Maintainer)
+                .expect("Maintainer of every project")
+                .path,
+            PathBuf::from("/srv/docs")
+        );
+        assert!(
+            config
+                .authorize(Some("reader-secret"), Some("monorepo"), Role::Reader)
+                .is_ok()
+        );
+        assert!(matches!(
+            config.authorize(Some("reader-secret"), Some("monorepo"), Role::Maintainer),
+            Err(AccessError::Forbidden(_))
+        ));
+        assert!(matches!(
+            config.authorize(Some("reader-secret"), Some("docs"), Role::Reader),
+            Err(AccessError::Forbidden(_))
+        ));
+        assert_eq!(
+            config.authorize(Some("wrong"), Some("docs"), Role::Reader),
+            Err(AccessError::Unauthenticated)
+        );
+        assert_eq!(
+            config.authorize(None, Some("docs"), Role::Reader),
+            Err(AccessError::Unauthenticated)
+        );
+        assert_eq!(
+            config.authorize(Some("ci-secret"), None, Role::Reader),
+            Err(AccessError::ProjectRequired)
+        );
+        assert_eq!(
+            config.readable_roots(Some("reader-secret")),
+            Ok(vec![PathBuf::from("/srv/monorepo")])
+        );
+        assert_eq!(bearer_token("Bearer ci-secret"), Some("ci-secret"));
+        assert_eq!(bearer_token("Basic ci-secret"), None);
+    }
+
+    #[test]
+    fn invalid_team_configs_are_rejected() {
+        let project = "[[projects]]\nname = \"a\"\npath = \"/a\"\n";
+        assert!(TeamConfig::parse("projects = []").is_err());
+        assert!(TeamConfig::parse(&format!("{project}{project}")).is_err());
+        assert!(
+            TeamConfig::parse(&format!(
+                "{project}[[tokens]]\nname = \"t\"\nrole = \"reader\"\n"
+            ))
+            .is_err()
+        );
+        assert!(
+            TeamConfig::parse(&format!(
+                "{project}[[tokens]]\nname = \"t\"\ntoken = \"x\"\nrole = \"reader\"\nprojects = [\"b\"]\n"
+            ))
+            .is_err()
+        );
+    }
+}
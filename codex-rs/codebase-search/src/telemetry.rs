@@ -0,0 +1,378 @@
+//! Spans and metrics for indexing, embedding, upserts and search
+//! Each operation runs in a `tracing` span and is timed with `start`. With the `otel`
+//! feature, `Telemetry` exports the spans and these metrics over OTLP/gRPC:
+//! - `codebase_search.operations`: operations finished, by `operation` and `outcome`
+//! - `codebase_search.operation.duration`: their latency in seconds
+//! - `codebase_search.items`: files indexed, texts embedded, points upserted and
+//!   results returned, by `operation`
+//! - `codebase_search.embedding.tokens`: tokens the embedding provider billed
+//!
+//! Without the feature, recording is a no-op.
+
+use std::time::Instant;
+
+#[cfg(feature = "otel")]
+use opentelemetry::KeyValue;
+#[cfg(feature = "otel")]
+use opentelemetry::metrics::Counter;
+#[cfg(feature = "otel")]
+use opentelemetry::metrics::Histogram;
+#[cfg(feature = "otel")]
+use opentelemetry::metrics::Meter;
+#[cfg(feature = "otel")]
+use opentelemetry::trace::TracerProvider;
+#[cfg(feature = "otel")]
+use opentelemetry_otlp::WithExportConfig;
+#[cfg(feature = "otel")]
+use opentelemetry_sdk::Resource;
+#[cfg(feature = "otel")]
+use opentelemetry_sdk::metrics::SdkMeterProvider;
+#[cfg(feature = "otel")]
+use opentelemetry_sdk::trace::SdkTracerProvider;
+#[cfg(feature = "otel")]
+use std::sync::LazyLock;
+#[cfg(feature = "otel")]
+use tracing::warn;
+
+/// Name reported as the OpenTelemetry service and instrumentation scope
+#[cfg(feature = "otel")]
+const SERVICE_NAME: &str = "codebase-search";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Operation {
+    /// Indexing a project from scratch
+    FullIndex,
+    /// Reindexing a set of changed files
+    IncrementalIndex,
+    Embed,
+    Upsert,
+    Search,
+    FindSimilar,
+}
+
+#[cfg(feature = "otel")]
+impl Operation {
+    fn as_str(self) -> &'static str {
+        match self {
+            Operation::FullIndex => "index_full",
+            Operation::IncrementalIndex => "index_incremental",
+            Operation::Embed => "embed",
+            Operation::Upsert => "upsert",
+            Operation::Search => "search",
+            Operation::FindSimilar => "find_similar",
+        }
+    }
+}
+
+/// Times an operation from `start`
+/// Dropping it without calling `succeeded` (e.g. on an early `?` return) records
+/// the operation as failed.
+#[must_use]
+pub(crate) struct OperationTimer {
+    operation: Operation,
+    started: Instant,
+    finished: bool,
+}
+
+/// Start timing `operation`
+pub(crate) fn start(operation: Operation) -> OperationTimer {
+    OperationTimer {
+        operation,
+        started: Instant::now(),
+        finished: false,
+    }
+}
+
+impl OperationTimer {
+    /// Record the operation as successful, having handled `items` items
+    pub(crate) fn succeeded(mut self, items: u64) {
+        self.finished = true;
+        record(self.operation, self.started, true, items);
+    }
+}
+
+impl Drop for OperationTimer {
+    fn drop(&mut self) {
+        if !self.finished {
+            record(self.operation, self.started, false, 0);
+        }
+    }
+}
+
+/// Record tokens billed by the embedding provider
+#[cfg(feature = "otel")]
+pub(crate) fn record_embedding_tokens(tokens: u64) {
+    INSTRUMENTS.tokens.add(tokens, &[]);
+}
+
+#[cfg(not(feature = "otel"))]
+pub(crate) fn record_embedding_tokens(_tokens: u64) {}
+
+#[cfg(feature = "otel")]
+fn record(operation: Operation, started: Instant, ok: bool, items: u64) {
+    INSTRUMENTS.record(operation, started, ok, items);
+}
+
+#[cfg(not(feature = "otel"))]
+fn record(_operation: Operation, _started: Instant, _ok: bool, _items: u64) {}
+
+#[cfg(feature = "otel")]
+struct Instruments {
+    operations: Counter<u64>,
+    duration: Histogram<f64>,
+    items: Counter<u64>,
+    tokens: Counter<u64>,
+}
+
+#[cfg(feature = "otel")]
+impl Instruments {
+    fn new(meter: &Meter) -> Self {
+        Self {
+            operations: meter
+                .u64_counter("codebase_search.operations")
+                .with_description("Indexing, embedding, upsert and search operations finished")
+                .build(),
+            duration: meter
+                .f64_histogram("codebase_search.operation.duration")
+                .with_description("Latency of indexing, embedding, upsert and search operations")
+                .with_unit("s")
+                .build(),
+            items: meter
+                .u64_counter("codebase_search.items")
+                .with_description(
+                    "Files indexed, texts embedded, points upserted and results returned",
+                )
+                .build(),
+            tokens: meter
+                .u64_counter("codebase_search.embedding.tokens")
+                .with_description("Tokens billed by the embedding provider")
+                .build(),
+        }
+    }
+
+    fn record(&self, operation: Operation, started: Instant, ok: bool, items: u64) {
+        let operation = KeyValue::new("operation", operation.as_str());
+        let attributes = [
+            operation.clone(),
+            KeyValue::new("outcome", if ok { "ok" } else { "error" }),
+        ];
+        self.operations.add(1, &attributes);
+        self.duration
+            .record(started.elapsed().as_secs_f64(), &attributes);
+        if items > 0 {
+            self.items.add(items, &[operation]);
+        }
+    }
+}
+
+/// Created from the global meter provider on first use, so `Telemetry::init_otlp`
+/// must run before any operation is recorded
+#[cfg(feature = "otel")]
+static INSTRUMENTS: LazyLock<Instruments> =
+    LazyLock::new(|| Instruments::new(&opentelemetry::global::meter(SERVICE_NAME)));
+
+/// OTLP export of spans and metrics; flushed and shut down when dropped
+#[cfg(feature = "otel")]
+pub struct Telemetry {
+    tracer_provider: SdkTracerProvider,
+    meter_provider: SdkMeterProvider,
+}
+
+#[cfg(feature = "otel")]
+impl Telemetry {
+    /// Export over OTLP/gRPC to `endpoint`, or to the collector named by the standard
+    /// `OTEL_EXPORTER_OTLP_*` environment variables when None
+    /// Must be called inside a Tokio runtime, before any operation is recorded.
+    pub fn init_otlp(endpoint: Option<&str>) -> Result<Self, anyhow::Error> {
+        let mut span_exporter = opentelemetry_otlp::SpanExporter::builder().with_tonic();
+        let mut metric_exporter = opentelemetry_otlp::MetricExporter::builder().with_tonic();
+        if let Some(endpoint) = endpoint {
+            span_exporter = span_exporter.with_endpoint(endpoint);
+            metric_exporter = metric_exporter.with_endpoint(endpoint);
+        }
+        let span_exporter = span_exporter
+            .build()
+            .map_err(|e| anyhow::anyhow!("Failed to create OTLP span exporter: {e}"))?;
+        let metric_exporter = metric_exporter
+            .build()
+            .map_err(|e| anyhow::anyhow!("Failed to create OTLP metric exporter: {e}"))?;
+
+        let resource = Resource::builder().with_service_name(SERVICE_NAME).build();
+        let tracer_provider = SdkTracerProvider::builder()
+            .with_batch_exporter(span_exporter)
+            .with_resource(resource.clone())
+            .build();
+        let meter_provider = SdkMeterProvider::builder()
+            .with_periodic_exporter(metric_exporter)
+            .with_resource(resource)
+            .build();
+        opentelemetry::global::set_meter_provider(meter_provider.clone());
+        Ok(Self {
+            tracer_provider,
+            meter_provider,
+        })
+    }
+
+    /// Layer exporting this crate's spans (at INFO and above) as OpenTelemetry spans
+    pub fn layer<S>(&self) -> impl tracing_subscriber::Layer<S>
+    where
+        S: tracing::Subscriber + for<'span> tracing_subscriber::registry::LookupSpan<'span>,
+    {
+        use tracing_subscriber::Layer;
+
+        tracing_opentelemetry::layer()
+            .with_tracer(self.tracer_provider.tracer(SERVICE_NAME))
+            .with_filter(
+                tracing_subscriber::filter::Targets::new()
+                    .with_target("codebase_search", tracing::Level::INFO),
+            )
+    }
+}
+
+#[cfg(feature = "otel")]
+impl Drop for Telemetry {
+    fn drop(&mut self) {
+        if let Err(e) = self.tracer_provider.shutdown() {
+            warn!("Failed to flush OpenTelemetry spans: {e}");
+        }
+        if let Err(e) = self.meter_provider.shutdown() {
+            warn!("Failed to flush OpenTelemetry metrics: {e}");
+        }
+    }
+}
+
+#[cfg(all(test, feature = "otel"))]
+mod tests {
+    #![allow(clippy::expect_used)]
+
+    use super::*;
+    use opentelemetry::metrics::MeterProvider;
+    use opentelemetry_sdk::error::OTelSdkResult;
+    use opentelemetry_sdk::metrics::Temporality;
+    use opentelemetry_sdk::metrics::data::AggregatedMetrics;
+    use opentelemetry_sdk::metrics::data::MetricData;
+    use opentelemetry_sdk::metrics::data::ResourceMetrics;
+    use opentelemetry_sdk::metrics::exporter::PushMetricExporter;
+    use std::collections::BTreeMap;
+    use std::sync::Arc;
+    use std::sync::Mutex;
+    use std::time::Duration;
+
+    /// Exported values by `metric{attribute=value,...}`: sums for counters and
+    /// sample counts for histograms
+    #[derive(Clone, Default)]
+    struct Exported(Arc<Mutex<BTreeMap<String, u64>>>);
+
+    impl PushMetricExporter for Exported {
+        async fn export(&self, metrics: &ResourceMetrics) -> OTelSdkResult {
+            let mut exported = self.0.lock().expect("exported metrics");
+            for metric in metrics.scope_metrics().flat_map(|scope| scope.metrics()) {
+                match metric.data() {
+                    AggregatedMetrics::U64(MetricData::Sum(sum)) => {
+                        for point in sum.data_points() {
+                            exported.insert(key(metric.name(), point.attributes()), point.value());
+                        }
+                    }
+                    AggregatedMetrics::F64(MetricData::Histogram(histogram)) => {
+                        for point in histogram.data_points() {
+                            exported.insert(key(metric.name(), point.attributes()), point.count());
+                        }
+                    }
+                    other => panic!("unexpected metric data {other:?}"),
+                }
+            }
+            Ok(())
+        }
+
+        fn force_flush(&self) -> OTelSdkResult {
+            Ok(())
+        }
+
+        fn shutdown_with_timeout(&self, _timeout: Duration) -> OTelSdkResult {
+            Ok(())
+        }
+
+        fn temporality(&self) -> Temporality {
+            Temporality::Cumulative
+        }
+    }
+
+    fn key<'a>(name: &str, attributes: impl Iterator<Item = &'a KeyValue>) -> String {
+        let mut attributes: Vec<String> = attributes
+            .map(|kv| format!("{}={}", kv.key, kv.value))
+            .collect();
+        attributes.sort();
+        format!("{name}{{{}}}", attributes.join(","))
+    }
+
+    fn instruments() -> (Instruments, SdkMeterProvider, Exported) {
+        let exported = Exported::default();
+        let provider = SdkMeterProvider::builder()
+            .with_periodic_exporter(exported.clone())
+            .build();
+        let instruments = Instruments::new(&provider.meter(SERVICE_NAME));
+        (instruments, provider, exported)
+    }
+
+    #[test]
+    fn operations_are_counted_by_outcome() {
+        let (instruments, provider, exported) = instruments();
+        instruments.record(Operation::Search, Instant::now(), true, 3);
+        instruments.record(Operation::Search, Instant::now(), true, 2);
+        instruments.record(Operation::FullIndex, Instant::now(), false, 0);
+        instruments.tokens.add(42, &[]);
+        provider.force_flush().expect("flush metrics");
+
+        let exported = exported.0.lock().expect("exported metrics").clone();
+        assert_eq!(
+            exported,
+            BTreeMap::from([
+                ("codebase_search.embedding.tokens{}".to_string(), 42),
+                ("codebase_search.items{operation=search}".to_string(), 5),
+                (
+                    "codebase_search.operation.duration{operation=index_full,outcome=error}"
+                        .to_string(),
+                    1
+                ),
+                (
+                    "codebase_search.operation.duration{operation=search,outcome=ok}".to_string(),
+                    2
+                ),
+                (
+                    "codebase_search.operations{operation=index_full,outcome=error}".to_string(),
+                    1
+                ),
+                (
+                    "codebase_search.operations{operation=search,outcome=ok}".to_string(),
+                    2
+                ),
+            ])
+        );
+    }
+
+    #[test]
+    fn operation_names_are_stable() {
+        let names: Vec<&str> = [
+            Operation::FullIndex,
+            Operation::IncrementalIndex,
+            Operation::Embed,
+            Operation::Upsert,
+            Operation::Search,
+            Operation::FindSimilar,
+        ]
+        .into_iter()
+        .map(Operation::as_str)
+        .collect();
+        assert_eq!(
+            names,
+            [
+                "index_full",
+                "index_incremental",
+                "embed",
+                "upsert",
+                "search",
+                "find_similar"
+            ]
+        );
+    }
+}
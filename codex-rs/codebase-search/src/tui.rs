@@ -0,0 +1,585 @@
+use std::ops::Range;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::LazyLock;
+use std::time::Duration;
+use std::time::Instant;
+
+use crossterm::event::Event;
+use crossterm::event::EventStream;
+use crossterm::event::KeyCode;
+use crossterm::event::KeyEvent;
+use crossterm::event::KeyEventKind;
+use crossterm::event::KeyModifiers;
+use crossterm::execute;
+use crossterm::terminal::EnterAlternateScreen;
+use crossterm::terminal::enable_raw_mode;
+use futures::StreamExt;
+use ratatui::DefaultTerminal;
+use ratatui::Frame;
+use ratatui::layout::Constraint;
+use ratatui::layout::Layout;
+use ratatui::style::Color;
+use ratatui::style::Modifier;
+use ratatui::style::Style;
+use ratatui::text::Line;
+use ratatui::text::Span;
+use ratatui::widgets::Block;
+use ratatui::widgets::List;
+use ratatui::widgets::ListItem;
+use ratatui::widgets::ListState;
+use ratatui::widgets::Paragraph;
+use tokio::sync::mpsc;
+
+use crate::retriever::SearchResult;
+use crate::retriever::search_codebase;
+use crate::symbol::SymbolParser;
+use crate::vector_db::QDRANT_CLIENT;
+
+/// How long the query has to stay unchanged before it is searched
+const SEARCH_DEBOUNCE: Duration = Duration::from_millis(300);
+/// Lines of surrounding code shown above and below a result in the preview
+const PREVIEW_CONTEXT_LINES: usize = 3;
+const PREVIEW_PAGE_LINES: u16 = 10;
+const TICK: Duration = Duration::from_millis(50);
+
+/// A finished search, tagged with the sequence number it was started with
+type SearchResponse = (u64, Result<Vec<SearchResult>, String>);
+
+/// What the event loop should do after a key press
+enum Action {
+    None,
+    Quit,
+    Open { file: PathBuf, line: usize },
+}
+
+/// The selected result's code, highlighted and numbered
+struct Preview {
+    result_id: String,
+    title: String,
+    lines: Vec<Line<'static>>,
+}
+
+struct App {
+    root_path: PathBuf,
+    limit: usize,
+    min_score: f32,
+    query: String,
+    /// When the query last changed, if it hasn't been searched for since
+    edited_at: Option<Instant>,
+    /// Sequence number of the latest search; responses to older ones are dropped
+    search_seq: u64,
+    results: Vec<SearchResult>,
+    list_state: ListState,
+    preview: Option<Preview>,
+    preview_scroll: u16,
+    status: Line<'static>,
+    /// Used for highlighting; previews fall back to plain text without it
+    parser: Option<SymbolParser>,
+}
+
+/// Run the interactive search UI for an indexed codebase until the user quits
+/// The query is searched as it is typed; Enter opens the selected result in
+/// `$VISUAL`/`$EDITOR` at its first line.
+pub async fn run<P: AsRef<Path>>(
+    root_path: P,
+    limit: usize,
+    min_score: f32,
+) -> Result<(), anyhow::Error> {
+    let mut app = App {
+        root_path: root_path.as_ref().to_path_buf(),
+        limit,
+        min_score,
+        query: String::new(),
+        edited_at: None,
+        search_seq: 0,
+        results: Vec::new(),
+        list_state: ListState::default(),
+        preview: None,
+        preview_scroll: 0,
+        status: Line::from("Type to search"),
+        parser: SymbolParser::new().ok(),
+    };
+
+    // The Qdrant client prints a warning to stdout when it can't reach the server;
+    // create it before the terminal is taken over
+    LazyLock::force(&QDRANT_CLIENT);
+    let mut terminal = ratatui::try_init()?;
+    let result = event_loop(&mut terminal, &mut app).await;
+    ratatui::restore();
+    result
+}
+
+async fn event_loop(terminal: &mut DefaultTerminal, app: &mut App) -> Result<(), anyhow::Error> {
+    let (tx, mut rx) = mpsc::unbounded_channel::<SearchResponse>();
+    let mut events = EventStream::new();
+    let mut tick = tokio::time::interval(TICK);
+
+    loop {
+        app.refresh_preview();
+        terminal.draw(|frame| app.draw(frame))?;
+
+        let action = tokio::select! {
+            event = events.next() => match event {
+                Some(Ok(Event::Key(key))) => app.handle_key(key),
+                Some(Ok(_)) => Action::None,
+                Some(Err(e)) => return Err(e.into()),
+                None => Action::Quit,
+            },
+            Some((seq, response)) = rx.recv() => {
+                app.receive(seq, response);
+                Action::None
+            }
+            _ = tick.tick() => Action::None,
+        };
+
+        match action {
+            Action::None => {}
+            Action::Quit => return Ok(()),
+            Action::Open { file, line } => {
+                // The event reader would otherwise compete with the editor for input
+                drop(events);
+                app.status = open_in_editor(terminal, &file, line)?;
+                events = EventStream::new();
+            }
+        }
+
+        if let Some((seq, query)) = app.take_due_query() {
+            let tx = tx.clone();
+            let root_path = app.root_path.clone();
+            let (limit, min_score) = (app.limit, app.min_score);
+            tokio::spawn(async move {
+                let response = search_codebase(query, &root_path, limit, min_score)
+                    .await
+                    .map_err(|e| e.to_string());
+                let _ = tx.send((seq, response));
+            });
+        }
+    }
+}
+
+impl App {
+    fn handle_key(&mut self, key: KeyEvent) -> Action {
+        if key.kind == KeyEventKind::Release {
+            return Action::None;
+        }
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Esc => return Action::Quit,
+            KeyCode::Char('c') if ctrl => return Action::Quit,
+            KeyCode::Enter => {
+                if let Some(result) = self.selected() {
+                    return Action::Open {
+                        file: self.root_path.join(&result.chunk.file_path),
+                        line: result.chunk.start_line,
+                    };
+                }
+            }
+            KeyCode::Up => self.select_offset(-1),
+            KeyCode::Char('p') if ctrl => self.select_offset(-1),
+            KeyCode::Down => self.select_offset(1),
+            KeyCode::Char('n') if ctrl => self.select_offset(1),
+            KeyCode::PageUp => {
+                self.preview_scroll = self.preview_scroll.saturating_sub(PREVIEW_PAGE_LINES);
+            }
+            KeyCode::PageDown => {
+                self.preview_scroll = self.preview_scroll.saturating_add(PREVIEW_PAGE_LINES);
+            }
+            KeyCode::Char('u') if ctrl => {
+                self.query.clear();
+                self.edited_at = Some(Instant::now());
+            }
+            KeyCode::Backspace => {
+                self.query.pop();
+                self.edited_at = Some(Instant::now());
+            }
+            KeyCode::Char(c) if !ctrl => {
+                self.query.push(c);
+                self.edited_at = Some(Instant::now());
+            }
+            _ => {}
+        }
+        Action::None
+    }
+
+    fn selected(&self) -> Option<&SearchResult> {
+        self.list_state
+            .selected()
+            .and_then(|index| self.results.get(index))
+    }
+
+    fn select_offset(&mut self, offset: isize) {
+        if self.results.is_empty() {
+            return;
+        }
+        let current = self.list_state.selected().unwrap_or_default();
+        let next = current
+            .saturating_add_signed(offset)
+            .min(self.results.len() - 1);
+        self.list_state.select(Some(next));
+        self.preview_scroll = 0;
+    }
+
+    /// The query to search for, once it has been left alone for `SEARCH_DEBOUNCE`
+    fn take_due_query(&mut self) -> Option<(u64, String)> {
+        if self
+            .edited_at
+            .is_none_or(|edited_at| edited_at.elapsed() < SEARCH_DEBOUNCE)
+        {
+            return None;
+        }
+        self.edited_at = None;
+        self.search_seq += 1;
+
+        let query = self.query.trim();
+        if query.is_empty() {
+            self.results.clear();
+            self.list_state.select(None);
+            self.status = Line::from("Type to search");
+            return None;
+        }
+        self.status = Line::from("Searching…");
+        Some((self.search_seq, query.to_string()))
+    }
+
+    fn receive(&mut self, seq: u64, response: Result<Vec<SearchResult>, String>) {
+        if seq != self.search_seq {
+            return;
+        }
+        match response {
+            Ok(results) => {
+                self.status = Line::from(format!("{} results", results.len()));
+                self.list_state
+                    .select(if results.is_empty() { None } else { Some(0) });
+                self.results = results;
+                self.preview_scroll = 0;
+            }
+            Err(e) => {
+                self.status = Line::styled(format!("Search failed: {e}"), Color::Red);
+            }
+        }
+    }
+
+    /// Rebuild the preview when the selection moved to another result
+    fn refresh_preview(&mut self) {
+        let Some(result) = self.selected() else {
+            self.preview = None;
+            return;
+        };
+        if self
+            .preview
+            .as_ref()
+            .is_some_and(|preview| preview.result_id == result.id)
+        {
+            return;
+        }
+
+        let chunk = &result.chunk;
+        let title = format!(
+            " {}:{}-{} ",
+            chunk.file_path.display(),
+            chunk.start_line,
+            chunk.end_line
+        );
+        let result_id = result.id.clone();
+        let chunk_lines = chunk.start_line..chunk.end_line + 1;
+        let extension = chunk
+            .file_path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .unwrap_or_default()
+            .to_string();
+        let content = chunk.content.clone();
+
+        let lines = match std::fs::read_to_string(self.root_path.join(&chunk.file_path)) {
+            Ok(source) => {
+                let total = source.lines().count();
+                let first = chunk_lines
+                    .start
+                    .saturating_sub(PREVIEW_CONTEXT_LINES)
+                    .max(1);
+                let last = (chunk_lines.end - 1 + PREVIEW_CONTEXT_LINES).min(total);
+                let highlighted =
+                    highlight_lines(self.parser.as_mut(), &extension, &source, first - 1..last);
+                highlighted
+                    .into_iter()
+                    .zip(first..)
+                    .map(|(spans, number)| {
+                        let gutter_style = if chunk_lines.contains(&number) {
+                            Style::new().fg(Color::Yellow)
+                        } else {
+                            Style::new().fg(Color::DarkGray)
+                        };
+                        let mut line = vec![Span::styled(format!("{number:>5} │ "), gutter_style)];
+                        line.extend(spans);
+                        Line::from(line)
+                    })
+                    .collect()
+            }
+            // The file moved or was deleted since indexing; show what was indexed
+            Err(_) => content
+                .lines()
+                .map(|line| Line::from(line.to_string()))
+                .collect(),
+        };
+
+        self.preview = Some(Preview {
+            result_id,
+            title,
+            lines,
+        });
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [query_area, body_area, status_area] = Layout::vertical([
+            Constraint::Length(3),
+            Constraint::Min(1),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+        let [list_area, preview_area] =
+            Layout::horizontal([Constraint::Percentage(40), Constraint::Percentage(60)])
+                .areas(body_area);
+
+        let query = Span::raw(self.query.clone());
+        let cursor_x = query_area.x + 1 + query.width() as u16;
+        frame.render_widget(
+            Paragraph::new(query).block(Block::bordered().title(" Query ")),
+            query_area,
+        );
+        frame.set_cursor_position((cursor_x, query_area.y + 1));
+
+        let items = self.results.iter().map(|result| {
+            let chunk = &result.chunk;
+            ListItem::new(vec![
+                Line::from(vec![
+                    Span::styled(format!("{:.3} ", result.score), Color::Green),
+                    Span::styled(format!("{} ", chunk.symbol_kind), Color::Cyan),
+                    Span::raw(chunk.symbol_name.clone()),
+                ]),
+                Line::styled(
+                    format!("      {}:{}", chunk.file_path.display(), chunk.start_line),
+                    Color::DarkGray,
+                ),
+            ])
+        });
+        let list = List::new(items)
+            .block(Block::bordered().title(" Results "))
+            .highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(list, list_area, &mut self.list_state);
+
+        let (title, lines) = match &self.preview {
+            Some(preview) => (preview.title.clone(), preview.lines.clone()),
+            None => (" Preview ".to_string(), Vec::new()),
+        };
+        frame.render_widget(
+            Paragraph::new(lines)
+                .block(Block::bordered().title(title))
+                .scroll((self.preview_scroll, 0)),
+            preview_area,
+        );
+
+        let mut status = vec![Span::styled(
+            "↑/↓ select · Enter open · PgUp/PgDn scroll · Ctrl-U clear · Esc quit │ ",
+            Color::DarkGray,
+        )];
+        status.extend(self.status.spans.iter().cloned());
+        frame.render_widget(Line::from(status), status_area);
+    }
+}
+
+/// Suspend the UI, open `file` at `line` in the user's editor and return a status line
+fn open_in_editor(
+    terminal: &mut DefaultTerminal,
+    file: &Path,
+    line: usize,
+) -> Result<Line<'static>, anyhow::Error> {
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .unwrap_or_default();
+    let Some((program, args)) = editor_command(&editor, file, line) else {
+        return Ok(Line::styled(
+            "Set $VISUAL or $EDITOR to open results",
+            Color::Yellow,
+        ));
+    };
+
+    ratatui::restore();
+    let status = std::process::Command::new(&program).args(&args).status();
+    enable_raw_mode()?;
+    execute!(std::io::stdout(), EnterAlternateScreen)?;
+    terminal.clear()?;
+
+    Ok(match status {
+        Ok(status) if status.success() => Line::from(format!("Opened {}:{line}", file.display())),
+        Ok(status) => Line::styled(format!("{program} exited with {status}"), Color::Red),
+        Err(e) => Line::styled(format!("Failed to run {program}: {e}"), Color::Red),
+    })
+}
+
+/// Program and arguments that open `file` at `line` with `editor`, which may carry
+/// its own arguments (e.g. `code --wait`)
+fn editor_command(editor: &str, file: &Path, line: usize) -> Option<(String, Vec<String>)> {
+    let mut words = editor.split_whitespace().map(str::to_string);
+    let program = words.next()?;
+    let mut args: Vec<String> = words.collect();
+
+    let name = Path::new(&program)
+        .file_stem()
+        .and_then(|name| name.to_str())
+        .unwrap_or_default();
+    let location = format!("{}:{line}", file.display());
+    match name {
+        "code" | "code-insiders" | "codium" | "cursor" => {
+            args.extend(["-g".to_string(), location]);
+        }
+        "subl" | "hx" | "helix" | "zed" => args.push(location),
+        // vi, vim, nvim, nano, emacs, micro, kak and most others accept `+LINE FILE`
+        _ => args.extend([format!("+{line}"), file.display().to_string()]),
+    }
+    Some((program, args))
+}
+
+/// Style for a syntax node, or `None` to style its children individually
+fn node_style(node: &tree_sitter::Node) -> Option<Style> {
+    let kind = node.kind();
+    if kind.contains("comment") {
+        return Some(
+            Style::new()
+                .fg(Color::DarkGray)
+                .add_modifier(Modifier::ITALIC),
+        );
+    }
+    if kind.contains("string") || kind == "char_literal" || kind == "rune_literal" {
+        return Some(Style::new().fg(Color::Green));
+    }
+    if kind == "type_identifier" || kind == "primitive_type" {
+        return Some(Style::new().fg(Color::Cyan));
+    }
+    if kind.contains("integer")
+        || kind.contains("float")
+        || matches!(kind, "int_literal" | "true" | "false" | "none" | "nil")
+    {
+        return Some(Style::new().fg(Color::Yellow));
+    }
+    // Keywords are the anonymous nodes spelled like words (`fn`, `def`, `func`, ...)
+    if !node.is_named() && kind.chars().all(|c| c.is_ascii_alphabetic() || c == '_') {
+        return Some(Style::new().fg(Color::Magenta));
+    }
+    None
+}
+
+/// Highlight the 0-based `lines` of `source` by parsing it with the parser for
+/// `extension`; one list of spans per line, unstyled if the language isn't supported
+fn highlight_lines(
+    parser: Option<&mut SymbolParser>,
+    extension: &str,
+    source: &str,
+    lines: Range<usize>,
+) -> Vec<Vec<Span<'static>>> {
+    let mut line_ranges = Vec::new();
+    let mut offset = 0;
+    for line in source.split_inclusive('\n') {
+        let content = line.trim_end_matches(['\n', '\r']);
+        line_ranges.push(offset..offset + content.len());
+        offset += line.len();
+    }
+    let Some(line_ranges) = line_ranges.get(lines) else {
+        return Vec::new();
+    };
+    let (Some(first), Some(last)) = (line_ranges.first(), line_ranges.last()) else {
+        return Vec::new();
+    };
+    let visible = first.start..last.end;
+
+    let mut styles = vec![Style::new(); source.len()];
+    let tree = parser
+        .and_then(|parser| parser.parsers.get_mut(extension))
+        .and_then(|parser| parser.parse(source, None));
+    if let Some(tree) = tree {
+        let mut stack = vec![tree.root_node()];
+        while let Some(node) = stack.pop() {
+            if node.end_byte() <= visible.start || node.start_byte() >= visible.end {
+                continue;
+            }
+            match node_style(&node) {
+                Some(style) => styles[node.byte_range()].fill(style),
+                None => {
+                    let mut cursor = node.walk();
+                    stack.extend(node.children(&mut cursor));
+                }
+            }
+        }
+    }
+
+    line_ranges
+        .iter()
+        .map(|range| {
+            let mut spans = Vec::new();
+            let mut start = range.start;
+            for end in range.start + 1..=range.end {
+                let boundary = end == range.end || styles[end] != styles[start];
+                if boundary && source.is_char_boundary(end) {
+                    let text = source[start..end].replace('\t', "    ");
+                    spans.push(Span::styled(text, styles[start]));
+                    start = end;
+                }
+            }
+            spans
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::expect_used)]
+    use super::*;
+
+    #[test]
+    fn test_editor_command() {
+        let file = Path::new("/repo/src/lib.rs");
+        assert_eq!(
+            editor_command("nvim", file, 12),
+            Some((
+                "nvim".to_string(),
+                vec!["+12".to_string(), "/repo/src/lib.rs".to_string()]
+            ))
+        );
+        assert_eq!(
+            editor_command("code --wait", file, 12),
+            Some((
+                "code".to_string(),
+                vec![
+                    "--wait".to_string(),
+                    "-g".to_string(),
+                    "/repo/src/lib.rs:12".to_string()
+                ]
+            ))
+        );
+        assert_eq!(editor_command("  ", file, 12), None);
+    }
+
+    #[test]
+    fn test_highlight_lines() {
+        let mut parser = SymbolParser::new().expect("Failed to create parser");
+        let source = "// doc\nfn main() {\n    let s = \"hi\";\n}\n";
+        let lines = highlight_lines(Some(&mut parser), "rs", source, 1..3);
+        assert_eq!(lines.len(), 2);
+
+        let style_of = |line: &[Span<'static>], text: &str| {
+            line.iter()
+                .find(|span| span.content == text)
+                .map(|span| span.style)
+        };
+        assert_eq!(
+            style_of(&lines[0], "fn"),
+            Some(Style::new().fg(Color::Magenta))
+        );
+        assert_eq!(
+            style_of(&lines[1], "\"hi\""),
+            Some(Style::new().fg(Color::Green))
+        );
+        let text: String = lines[1].iter().map(|span| span.content.as_ref()).collect();
+        assert_eq!(text, "    let s = \"hi\";");
+    }
+}
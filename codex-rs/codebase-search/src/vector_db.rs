@@ -1,51 +1,88 @@
+use crate::walk_utils::CodebaseIgnoreMatcher;
+use crate::walk_utils::ignore_rules_hash;
 use crate::walk_utils::is_supported_file_extension;
 use crate::walk_utils::walk_codebase_files;
 use std::collections::HashMap;
-use std::collections::HashSet;
 use std::fs;
 use std::path::Path;
+use std::path::PathBuf;
 use std::sync::Arc;
 use std::sync::LazyLock;
+use std::sync::OnceLock;
 use tracing::debug;
 use tracing::info;
+use tracing::instrument;
 use tracing::warn;
 
+use serde::Serialize;
 use serde_json::json;
 
 use crate::chunker::ChunkingOptions;
-use crate::chunker::chunk_codebase;
-use crate::chunker::chunk_codefile;
+use crate::chunker::CodeChunk;
+use crate::chunker::HierarchicalChunker;
+use crate::embedding::EmbeddedChunk;
+use crate::embedding::EmbeddingClient;
 use crate::embedding::QDRANT_EMBEDDING_DIMENSION;
+use crate::embedding::get_embedding_client;
+use crate::feedback::LEGACY_FEEDBACK_FILE_NAME;
+use crate::feedback::feedback_file_for_state;
 use crate::file_state::CodebaseState;
 use crate::file_state::FileState;
+use crate::file_state::GitBaseline;
+use crate::file_state::HashAlgorithm;
+use crate::file_state::IndexParameters;
+use crate::file_state::LEGACY_STATE_FILE_NAME;
+use crate::file_state::StateSummary;
+use crate::file_state::forget_root;
+use crate::file_state::known_roots;
+use crate::file_state::remember_root;
+use crate::file_state::state_file_path;
+use crate::file_state::summarize_state;
+use crate::git_changes;
+use crate::git_changes::GitChanges;
+use crate::index_config::ChangeDetection;
+use crate::index_config::IndexConfig;
+use crate::index_config::QdrantSettings;
+use crate::progress::IndexPhase;
+use crate::progress::IndexProgress;
+use crate::progress::ProgressFn;
+use crate::progress::no_progress;
+use crate::retriever::point_id_to_string;
+use crate::symbol::SymbolParser;
 use crate::symbol::get_file_metadata;
+use crate::telemetry;
+use crate::telemetry::Operation;
 use qdrant_client::Payload;
 use qdrant_client::Qdrant;
+use qdrant_client::qdrant::CollectionInfo;
+use qdrant_client::qdrant::CollectionStatus;
 use qdrant_client::qdrant::Condition;
 use qdrant_client::qdrant::CreateCollectionBuilder;
+use qdrant_client::qdrant::CreateFieldIndexCollectionBuilder;
 use qdrant_client::qdrant::DeletePointsBuilder;
 use qdrant_client::qdrant::Distance;
+use qdrant_client::qdrant::FieldType;
 use qdrant_client::qdrant::Filter;
+use qdrant_client::qdrant::PayloadIncludeSelector;
 use qdrant_client::qdrant::PointStruct;
+use qdrant_client::qdrant::ScrollPointsBuilder;
+use qdrant_client::qdrant::TextIndexParamsBuilder;
+use qdrant_client::qdrant::TokenizerType;
 use qdrant_client::qdrant::UpsertPointsBuilder;
 use qdrant_client::qdrant::VectorParamsBuilder;
+use qdrant_client::qdrant::vectors_config::Config as VectorsConfigKind;
 use sha2::Digest;
 use sha2::Sha256;
 
-/// Generate a deterministic point ID from file path and chunk position
-/// This ensures we can properly upsert points for the same chunk across updates
+/// Generate a deterministic point ID from a chunk's file path and symbol ID
+/// Symbol IDs don't depend on line numbers, so an edit elsewhere in the file keeps
+/// the point ID and the chunk is upserted in place.
 /// Returns a deterministic UUID-v5-like string that Qdrant accepts
-fn generate_point_id(
-    file_path: &str,
-    start_line: usize,
-    end_line: usize,
-    symbol_name: &str,
-) -> String {
+fn generate_point_id(file_path: &str, symbol_id: &str) -> String {
     let mut hasher = Sha256::new();
     hasher.update(file_path.as_bytes());
-    hasher.update(start_line.to_string().as_bytes());
-    hasher.update(end_line.to_string().as_bytes());
-    hasher.update(symbol_name.as_bytes());
+    hasher.update([0]);
+    hasher.update(symbol_id.as_bytes());
     let hash = hasher.finalize();
 
     // Format as a UUID-like string that Qdrant will accept
@@ -62,14 +99,68 @@ fn generate_point_id(
 }
 
 pub(crate) static QDRANT_CLIENT: LazyLock<Arc<Qdrant>> =
-    LazyLock::new(|| match Qdrant::from_url("http://localhost:6334").build() {
+    LazyLock::new(|| match Qdrant::from_url(&qdrant_url()).build() {
         Ok(client) => Arc::new(client),
         Err(e) => panic!("Failed to create Qdrant client: {e}"),
     });
 
+/// Qdrant endpoint used when no config file or environment variable names one
+pub const DEFAULT_QDRANT_URL: &str = "http://localhost:6334";
+
+/// Settings installed by `configure_qdrant`, used instead of the user config and
+/// environment when the global client is created
+static CONFIGURED_QDRANT: OnceLock<QdrantSettings> = OnceLock::new();
+
+/// Use `settings` as-is for the global Qdrant client
+/// Must be called before the client is first used. Installing the same settings
+/// again is a no-op; different ones are an error.
+pub fn configure_qdrant(settings: QdrantSettings) -> Result<(), anyhow::Error> {
+    match CONFIGURED_QDRANT.set(settings) {
+        Ok(()) => Ok(()),
+        Err(settings) if CONFIGURED_QDRANT.get() == Some(&settings) => Ok(()),
+        Err(_) => Err(anyhow::anyhow!(
+            "Qdrant client settings were already configured differently"
+        )),
+    }
+}
+
+/// Qdrant settings from the `CODEX_QDRANT_URL` and `CODEX_QDRANT_MANAGED` environment
+/// variables
+pub fn env_qdrant_settings() -> QdrantSettings {
+    QdrantSettings {
+        url: std::env::var("CODEX_QDRANT_URL").ok(),
+        managed: std::env::var("CODEX_QDRANT_MANAGED")
+            .ok()
+            .map(|value| matches!(value.as_str(), "1" | "true")),
+        binary: None,
+    }
+}
+
+/// The configured Qdrant URL, or the user config's overridden by the environment
+fn qdrant_url() -> String {
+    let settings = match CONFIGURED_QDRANT.get() {
+        Some(settings) => settings.clone(),
+        None => {
+            let user_settings = IndexConfig::load_user()
+                .map(|config| config.qdrant)
+                .unwrap_or_else(|e| {
+                    warn!("Ignoring user config: {e}");
+                    QdrantSettings::default()
+                });
+            user_settings.overridden_by(env_qdrant_settings())
+        }
+    };
+    settings
+        .url
+        .unwrap_or_else(|| DEFAULT_QDRANT_URL.to_string())
+}
+
 /// Generate a unique collection ID from a root path using SHA-256 hashing
 /// This creates a deterministic, unique identifier that's safe for use as a collection name
 /// The collection ID will be the same for the same root path across different sessions
+/// Prefix of every collection created by this crate
+pub const COLLECTION_PREFIX: &str = "rua_";
+
 pub(crate) fn generate_collection_id<P: AsRef<Path>>(root_path: P) -> String {
     let mut hasher = Sha256::new();
 
@@ -80,12 +171,12 @@ pub(crate) fn generate_collection_id<P: AsRef<Path>>(root_path: P) -> String {
 
     // Take the first 16 characters of the hash to keep it reasonably short
     // while still maintaining uniqueness
-    format!("rua_{}", &hash_str[..16])
+    format!("{COLLECTION_PREFIX}{}", &hash_str[..16])
 }
 
 /// Helper function to clean up a collection when operations fail
 /// This is used by both init_session and restore_session
-async fn cleanup_collection(collection_id: &str, reason: &str) {
+pub(crate) async fn cleanup_collection(collection_id: &str, reason: &str) {
     warn!("Cleaning up collection {collection_id} due to error: {reason}");
     if let Err(cleanup_err) = QDRANT_CLIENT.delete_collection(collection_id).await {
         warn!("Failed to cleanup collection {collection_id} after error: {cleanup_err}");
@@ -94,9 +185,72 @@ async fn cleanup_collection(collection_id: &str, reason: &str) {
     }
 }
 
-// New helper to collect supported file states under a root path
-fn collect_supported_file_states<P: AsRef<Path>>(
+/// Index the payload fields metadata search filters on: the symbol kind as a keyword,
+/// and the symbol name as lowercase words with their prefixes
+/// Creating an index that already exists leaves it as it is.
+pub(crate) async fn create_payload_indexes(collection_id: &str) -> Result<(), anyhow::Error> {
+    QDRANT_CLIENT
+        .create_field_index(
+            CreateFieldIndexCollectionBuilder::new(
+                collection_id,
+                "symbol_kind",
+                FieldType::Keyword,
+            )
+            .wait(true),
+        )
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to index symbol_kind in {collection_id}: {e}"))?;
+    QDRANT_CLIENT
+        .create_field_index(
+            CreateFieldIndexCollectionBuilder::new(collection_id, "symbol_name", FieldType::Text)
+                .field_index_params(
+                    TextIndexParamsBuilder::new(TokenizerType::Prefix).lowercase(true),
+                )
+                .wait(true),
+        )
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to index symbol_name in {collection_id}: {e}"))?;
+    Ok(())
+}
+
+/// Fields `create_payload_indexes` indexes
+const PAYLOAD_INDEX_FIELDS: [&str; 2] = ["symbol_kind", "symbol_name"];
+
+/// Index the payload fields of a collection created before the indexes existed
+/// A failure, e.g. on a read-only collection, is only logged so the session still opens.
+async fn ensure_payload_indexes(collection_id: &str) {
+    let schema = match QDRANT_CLIENT.collection_info(collection_id).await {
+        Ok(response) => response
+            .result
+            .map(|info| info.payload_schema)
+            .unwrap_or_default(),
+        Err(e) => {
+            warn!("Failed to read the payload indexes of {collection_id}: {e}");
+            return;
+        }
+    };
+    if !missing_payload_indexes(&schema) {
+        return;
+    }
+    info!("Creating payload indexes for {collection_id}");
+    if let Err(e) = create_payload_indexes(collection_id).await {
+        warn!("{e}");
+    }
+}
+
+fn missing_payload_indexes<V>(schema: &HashMap<String, V>) -> bool {
+    PAYLOAD_INDEX_FIELDS
+        .iter()
+        .any(|field| !schema.contains_key(*field))
+}
+
+/// Collect the state of every supported file under a root path
+/// Files whose mtime matches their entry in `previous` reuse that entry instead of being
+/// re-hashed, so a no-op scan of a large project only has to stat each file.
+pub(crate) fn collect_supported_file_states<P: AsRef<Path>>(
     root_path: P,
+    hash_algorithm: HashAlgorithm,
+    previous: Option<&CodebaseState>,
 ) -> Result<HashMap<String, FileState>, anyhow::Error> {
     let mut file_states = HashMap::new();
     let root_path = root_path.as_ref();
@@ -122,14 +276,19 @@ fn collect_supported_file_states<P: AsRef<Path>>(
             }
         };
 
-        let file_state = FileState::new(path.to_string_lossy().to_string(), last_modified)
-            .map_err(|e| {
-                anyhow::anyhow!(
-                    "Failed to create file state for '{}': {}",
-                    path.display(),
-                    e
-                )
-            })?;
+        let saved = previous.and_then(|state| state.file_states.get(&file_path_str));
+        if let Some(saved) = saved.filter(|saved| saved.last_modified == last_modified) {
+            file_states.insert(file_path_str, saved.clone());
+            return Ok(true); // Continue walking
+        }
+
+        let file_state = FileState::new(path, last_modified, hash_algorithm).map_err(|e| {
+            anyhow::anyhow!(
+                "Failed to create file state for '{}': {}",
+                path.display(),
+                e
+            )
+        })?;
 
         file_states.insert(file_path_str, file_state);
         Ok(true) // Continue walking
@@ -138,6 +297,85 @@ fn collect_supported_file_states<P: AsRef<Path>>(
     Ok(file_states)
 }
 
+/// A Qdrant point together with the file it was chunked from
+pub(crate) struct ChunkPoint {
+    pub(crate) file_path: String,
+    pub(crate) point_id: String,
+    pub(crate) point: PointStruct,
+}
+
+/// Split converted chunks into the points to upsert and the point IDs of each file
+fn group_points_by_file(
+    chunk_points: Vec<ChunkPoint>,
+) -> (Vec<PointStruct>, HashMap<String, Vec<String>>) {
+    let mut points = Vec::with_capacity(chunk_points.len());
+    let mut chunk_ids: HashMap<String, Vec<String>> = HashMap::new();
+    for chunk_point in chunk_points {
+        chunk_ids
+            .entry(chunk_point.file_path)
+            .or_default()
+            .push(chunk_point.point_id);
+        points.push(chunk_point.point);
+    }
+    for ids in chunk_ids.values_mut() {
+        ids.sort();
+        ids.dedup();
+    }
+    (points, chunk_ids)
+}
+
+/// Convert an embedded chunk into a Qdrant point, keyed by its root-relative file path
+pub(crate) fn chunk_to_point(
+    root_path: &Path,
+    chunk: EmbeddedChunk,
+) -> Result<ChunkPoint, anyhow::Error> {
+    let file_path_relative = chunk
+        .chunk
+        .file_path
+        .strip_prefix(root_path)
+        .unwrap_or(&chunk.chunk.file_path)
+        .to_string_lossy()
+        .to_string();
+
+    let payload = Payload::try_from(json!({
+        "file_path": file_path_relative.clone(),
+        "start_line": chunk.chunk.start_line,
+        "end_line": chunk.chunk.end_line,
+        "symbol_name": chunk.chunk.symbol_name.clone(),
+        "symbol_kind": chunk.chunk.symbol_kind.clone(),
+        "is_container": chunk.chunk.chunk_metadata.is_container,
+        "original_size_lines": chunk.chunk.chunk_metadata.original_size_lines,
+        "is_split": chunk.chunk.chunk_metadata.is_split,
+        "chunk_depth": chunk.chunk.chunk_metadata.chunk_depth,
+        "context": chunk.chunk.context.clone(),
+        "signature": chunk.chunk.signature.clone(),
+        "visibility": chunk.chunk.modifiers.visibility.map(|visibility| visibility.as_str()),
+        "is_async": chunk.chunk.modifiers.is_async,
+        "is_static": chunk.chunk.modifiers.is_static,
+        "line_count": chunk.chunk.metrics.lines,
+        "token_estimate": chunk.chunk.metrics.tokens,
+        "branch_count": chunk.chunk.metrics.branches,
+        "is_test": chunk.chunk.is_test,
+        "symbol_id": chunk.chunk.symbol_id.clone(),
+        "content": chunk.chunk.content.clone(),
+    }))
+    .map_err(|e| {
+        anyhow::anyhow!(
+            "Failed to convert chunk to payload for file {}: {}",
+            file_path_relative,
+            e
+        )
+    })?;
+
+    let point_id = generate_point_id(&file_path_relative, &chunk.chunk.symbol_id);
+
+    Ok(ChunkPoint {
+        file_path: file_path_relative,
+        point: PointStruct::new(point_id.clone(), chunk.embedding, payload),
+        point_id,
+    })
+}
+
 /// init_vector_db creates a new collection for the first time launched a project
 /// it will generate a unique collection id based on the root path
 /// for each project, we maintain a state file to track the modification of the project
@@ -153,6 +391,16 @@ fn collect_supported_file_states<P: AsRef<Path>>(
 ///     }
 /// }
 pub async fn init_session<P: AsRef<Path>>(root_path: P) -> Result<(), anyhow::Error> {
+    init_session_with_progress(root_path, &no_progress).await
+}
+
+/// `init_session`, reporting chunking, embedding and upserting progress to `progress`
+#[instrument(name = "index", skip_all, fields(root = %root_path.as_ref().display(), mode = "full"))]
+pub async fn init_session_with_progress<P: AsRef<Path>>(
+    root_path: P,
+    progress: &ProgressFn<'_>,
+) -> Result<(), anyhow::Error> {
+    let timer = telemetry::start(Operation::FullIndex);
     let collection_id = generate_collection_id(root_path.as_ref());
 
     // Check if collection already exists and delete it if it does
@@ -198,10 +446,47 @@ pub async fn init_session<P: AsRef<Path>>(root_path: P) -> Result<(), anyhow::Er
     // From this point on, if anything fails, we need to clean up the collection
     let collection_id_for_cleanup = collection_id.clone();
 
+    if let Err(e) = create_payload_indexes(&collection_id).await {
+        cleanup_collection(&collection_id_for_cleanup, &e.to_string()).await;
+        return Err(e);
+    }
+
     // Index the project
-    let opts = ChunkingOptions::default();
-    let chunks = match chunk_codebase(root_path.as_ref(), opts).await {
-        Ok(chunks) => chunks,
+    let config = match IndexConfig::load(root_path.as_ref()) {
+        Ok(config) => config,
+        Err(e) => {
+            cleanup_collection(&collection_id_for_cleanup, &e.to_string()).await;
+            return Err(e);
+        }
+    };
+    let opts = config.chunking_options();
+    let embedding_client = match get_embedding_client() {
+        Ok(client) => client,
+        Err(e) => {
+            cleanup_collection(&collection_id_for_cleanup, &e.to_string()).await;
+            return Err(e);
+        }
+    };
+    let index_params = IndexParameters::new(embedding_client.config(), &opts);
+
+    // The file states double as the list of files to index
+    let file_states =
+        match collect_supported_file_states(root_path.as_ref(), config.state.hash, None) {
+            Ok(states) => states,
+            Err(e) => {
+                let error_msg = format!("Failed to collect file states: {e}");
+                cleanup_collection(&collection_id_for_cleanup, &error_msg).await;
+                return Err(anyhow::anyhow!(error_msg));
+            }
+        };
+    let mut files = file_states.keys().cloned().collect::<Vec<_>>();
+    files.sort();
+
+    let code_chunks = match chunk_files(root_path.as_ref(), &files, &opts, progress) {
+        Ok(chunked) => chunked
+            .into_iter()
+            .flat_map(|(_, chunks)| chunks)
+            .collect::<Vec<_>>(),
         Err(e) => {
             let error_msg = format!("Failed to chunk codebase: {e}");
             cleanup_collection(&collection_id_for_cleanup, &error_msg).await;
@@ -209,50 +494,38 @@ pub async fn init_session<P: AsRef<Path>>(root_path: P) -> Result<(), anyhow::Er
         }
     };
 
+    progress(IndexProgress::Started {
+        phase: IndexPhase::Embedding,
+        total: code_chunks.len() as u64,
+    });
+    let chunks = match embedding_client
+        .embed_chunks_with_progress(&code_chunks, |count| {
+            progress(IndexProgress::Advanced {
+                phase: IndexPhase::Embedding,
+                count: count as u64,
+            })
+        })
+        .await
+    {
+        Ok(chunks) => chunks,
+        Err(e) => {
+            let error_msg = format!("Failed to embed codebase: {e}");
+            cleanup_collection(&collection_id_for_cleanup, &error_msg).await;
+            return Err(anyhow::anyhow!(error_msg));
+        }
+    };
+    progress(IndexProgress::Finished {
+        phase: IndexPhase::Embedding,
+    });
+
     // Convert chunks to points with metadata
     let points = chunks
         .into_iter()
-        .map(|chunk| {
-            let file_path_relative = chunk
-                .chunk
-                .file_path
-                .strip_prefix(root_path.as_ref())
-                .unwrap_or(&chunk.chunk.file_path)
-                .to_string_lossy()
-                .to_string();
-
-            let payload = match Payload::try_from(json!({
-                "file_path": file_path_relative.clone(),
-                "start_line": chunk.chunk.start_line,
-                "end_line": chunk.chunk.end_line,
-                "symbol_name": chunk.chunk.symbol_name.clone(),
-                "symbol_kind": chunk.chunk.symbol_kind.clone(),
-                "is_container": chunk.chunk.chunk_metadata.is_container,
-                "original_size_lines": chunk.chunk.chunk_metadata.original_size_lines,
-                "is_split": chunk.chunk.chunk_metadata.is_split,
-                "chunk_depth": chunk.chunk.chunk_metadata.chunk_depth,
-                "context": chunk.chunk.context.clone(),
-                "content": chunk.chunk.content.clone(),
-            })) {
-                Ok(payload) => payload,
-                Err(e) => {
-                    return Err(anyhow::anyhow!("Failed to convert chunk to payload: {}", e));
-                }
-            };
-
-            let point_id = generate_point_id(
-                &file_path_relative,
-                chunk.chunk.start_line,
-                chunk.chunk.end_line,
-                &chunk.chunk.symbol_name,
-            );
-
-            Ok(PointStruct::new(point_id, chunk.embedding, payload))
-        })
+        .map(|chunk| chunk_to_point(root_path.as_ref(), chunk))
         .collect::<Result<Vec<_>, anyhow::Error>>();
 
-    let points = match points {
-        Ok(points) => points,
+    let (points, chunk_ids) = match points {
+        Ok(chunk_points) => group_points_by_file(chunk_points),
         Err(e) => {
             cleanup_collection(&collection_id_for_cleanup, &e.to_string()).await;
             return Err(e);
@@ -260,43 +533,36 @@ pub async fn init_session<P: AsRef<Path>>(root_path: P) -> Result<(), anyhow::Er
     };
 
     // Save the chunks to the vector db
-    if let Err(e) = QDRANT_CLIENT
-        .upsert_points(UpsertPointsBuilder::new(collection_id.clone(), points))
-        .await
+    if let Err(e) =
+        upsert_points_with_progress(&QDRANT_CLIENT, &collection_id, points, progress).await
     {
         let error_msg = format!("Failed to upsert points to collection {collection_id}: {e}");
         cleanup_collection(&collection_id_for_cleanup, &error_msg).await;
         return Err(anyhow::anyhow!(error_msg));
     }
 
-    // Save the state file - this should be done before changing directory
-    let file_states = match collect_supported_file_states(root_path.as_ref()) {
-        Ok(states) => states,
-        Err(e) => {
-            let error_msg = format!("Failed to collect file states: {e}");
-            cleanup_collection(&collection_id_for_cleanup, &error_msg).await;
-            return Err(anyhow::anyhow!(error_msg));
+    // Save the state file
+    let mut state = CodebaseState::new(file_states);
+    for (file_path, ids) in chunk_ids {
+        if let Some(file_state) = state.file_states.get_mut(&file_path) {
+            file_state.chunk_ids = ids;
         }
-    };
-
-    // Change to the target directory
-    if let Err(e) = std::env::set_current_dir(root_path.as_ref()) {
-        let error_msg = format!(
-            "Failed to change directory to {}: {e}",
-            root_path.as_ref().display()
-        );
-        cleanup_collection(&collection_id_for_cleanup, &error_msg).await;
-        return Err(anyhow::anyhow!(error_msg));
     }
-
-    let state = CodebaseState { file_states };
-    if let Err(e) = state.to_file(None) {
+    state.index_params = Some(index_params);
+    state.ignore_rules_hash = Some(ignore_rules_hash(root_path.as_ref()));
+    refresh_git_baseline(root_path.as_ref(), &config, &mut state);
+    let saved = state_file_path(root_path.as_ref()).and_then(|path| state.to_file(&path));
+    if let Err(e) = saved {
         let error_msg = format!("Failed to save state file: {e}");
         cleanup_collection(&collection_id_for_cleanup, &error_msg).await;
         return Err(anyhow::anyhow!(error_msg));
     }
 
+    if let Err(e) = remember_root(root_path.as_ref()) {
+        warn!("Failed to record project root for {collection_id}: {e}");
+    }
     info!("Successfully initialized session with collection: {collection_id}");
+    timer.succeeded(state.file_states.len() as u64);
     Ok(())
 }
 
@@ -305,7 +571,15 @@ pub async fn init_session<P: AsRef<Path>>(root_path: P) -> Result<(), anyhow::Er
 /// if the content hash is different, it will update the vector db
 /// if the content hash is the same, it will skip the update
 pub async fn restore_session<P: AsRef<Path>>(root_path: P) -> Result<(), anyhow::Error> {
-    let index_file_path = root_path.as_ref().join(".rua.index.json");
+    restore_session_with_progress(root_path, &no_progress).await
+}
+
+/// `restore_session`, reporting chunking, embedding and upserting progress to `progress`
+pub async fn restore_session_with_progress<P: AsRef<Path>>(
+    root_path: P,
+    progress: &ProgressFn<'_>,
+) -> Result<(), anyhow::Error> {
+    let index_file_path = state_file_path(root_path.as_ref())?;
     info!("looking for index file at {}", index_file_path.display());
 
     match fs::exists(&index_file_path) {
@@ -313,219 +587,83 @@ pub async fn restore_session<P: AsRef<Path>>(root_path: P) -> Result<(), anyhow:
             info!("Found existing index file, checking for changes...");
 
             // 1. Load the existing state
-            std::env::set_current_dir(root_path.as_ref())?;
-            let saved_state = CodebaseState::from_file(None)?;
-
-            // 2. Discover current files and build current state
-            let current_file_states = collect_supported_file_states(root_path.as_ref())?;
-            let seen_files: HashSet<String> = current_file_states.keys().cloned().collect();
-
-            // 3. Compare states and categorize files
-            let mut added_files = Vec::new();
-            let mut modified_files = Vec::new();
-            let mut deleted_files = Vec::new();
-
-            // Find added and modified files
-            for (file_path, current_state) in &current_file_states {
-                match saved_state.file_states.get(file_path) {
-                    Some(saved_state) => {
-                        // File existed before, check if modified
-                        if current_state.content_md5 != saved_state.content_md5 {
-                            debug!("File modified: {file_path}");
-                            modified_files.push(file_path.clone());
-                        }
-                    }
-                    None => {
-                        // New file
-                        debug!("File added: {file_path}");
-                        added_files.push(file_path.clone());
-                    }
+            let saved_state = CodebaseState::from_file(&index_file_path)?;
+            let config = IndexConfig::load(root_path.as_ref())?;
+            let chunking_options = config.chunking_options();
+            let embedding_client = get_embedding_client()?;
+            let index_params = IndexParameters::new(embedding_client.config(), &chunking_options);
+            if let Some(saved_params) = &saved_state.index_params {
+                let differences = saved_params.differences(&index_params);
+                if !differences.is_empty() {
+                    warn!(
+                        "Index was built with different settings ({}), rebuilding it",
+                        differences.join(", ")
+                    );
+                    return init_session_with_progress(root_path, progress).await;
                 }
             }
 
-            // Find deleted files
-            for file_path in saved_state.file_states.keys() {
-                if !seen_files.contains(file_path) {
-                    debug!("File deleted: {file_path}");
-                    deleted_files.push(file_path.clone());
-                }
-            }
+            // 2. Find the files that changed since the last run
+            let rules_hash = ignore_rules_hash(root_path.as_ref());
+            let mut changes =
+                detect_changes(root_path.as_ref(), &saved_state, &config, &rules_hash)?;
 
             info!(
                 "Changes detected - Added: {}, Modified: {}, Deleted: {}",
-                added_files.len(),
-                modified_files.len(),
-                deleted_files.len()
-            );
-            info!(
-                "Using collection: {}",
-                generate_collection_id(root_path.as_ref()).as_str()
+                changes.added.len(),
+                changes.modified.len(),
+                changes.deleted.len()
             );
+            let collection_id = generate_collection_id(root_path.as_ref());
+            info!("Using collection: {}", collection_id.as_str());
+            ensure_payload_indexes(&collection_id).await;
 
-            // 4. Update vector database if there are changes
-            if !added_files.is_empty() || !modified_files.is_empty() || !deleted_files.is_empty() {
-                // Handle file deletions - remove points for deleted and modified files
-                let files_to_delete: Vec<String> = deleted_files
-                    .iter()
-                    .chain(modified_files.iter())
-                    .cloned()
-                    .collect();
-
-                if !files_to_delete.is_empty() {
-                    debug!(
-                        "Removing points for {} files (deleted: {}, modified: {})",
-                        files_to_delete.len(),
-                        deleted_files.len(),
-                        modified_files.len()
-                    );
-
-                    // Create filter to match points with any of the file paths to delete
-                    let conditions: Vec<Condition> = files_to_delete
-                        .iter()
-                        .map(|file_path| Condition::matches("file_path", file_path.clone()))
-                        .collect();
-
-                    let filter = Filter::should(conditions);
-
-                    // Delete all points matching this filter in a single operation
-                    QDRANT_CLIENT
-                        .delete_points(
-                            DeletePointsBuilder::new(
-                                generate_collection_id(root_path.as_ref()).as_str(),
-                            )
-                            .points(filter),
-                        )
-                        .await
-                        .map_err(|e| {
-                            anyhow::anyhow!(
-                                "Failed to delete points for {} files: {}",
-                                files_to_delete.len(),
-                                e
-                            )
-                        })?;
-                    info!(
-                        "Deleted points for {} files (deleted: {}, modified: {})",
-                        files_to_delete.len(),
-                        deleted_files.len(),
-                        modified_files.len()
-                    );
-                }
-
-                // Process added and modified files - chunk and insert new content
-                let files_to_process: Vec<String> = added_files
-                    .iter()
-                    .chain(modified_files.iter())
-                    .cloned()
-                    .collect();
-
-                if !files_to_process.is_empty() {
-                    info!(
-                        "Processing {} files for insertion (added: {}, modified: {})",
-                        files_to_process.len(),
-                        added_files.len(),
-                        modified_files.len()
-                    );
-
-                    let opts = ChunkingOptions::default();
-                    let mut all_chunks = Vec::new();
-
-                    // Process each file individually
-                    for file_path in &files_to_process {
-                        let full_file_path = root_path.as_ref().join(file_path);
-
-                        match chunk_codefile(&full_file_path, opts.clone()).await {
-                            Ok(mut chunks) => {
-                                debug!("Generated {} chunks for file: {}", chunks.len(), file_path);
-                                all_chunks.append(&mut chunks);
-                            }
-                            Err(e) => {
-                                warn!("Failed to chunk file {}: {}", file_path, e);
-                                continue;
-                            }
-                        }
-                    }
-
-                    info!(
-                        "Generated {} chunks for {} files",
-                        all_chunks.len(),
-                        files_to_process.len()
-                    );
-
-                    if !all_chunks.is_empty() {
-                        // Convert chunks to points with metadata
-                        let mut points = Vec::new();
-                        for chunk in all_chunks {
-                            let file_path_relative = chunk
-                                .chunk
-                                .file_path
-                                .strip_prefix(root_path.as_ref())
-                                .unwrap_or(&chunk.chunk.file_path)
-                                .to_string_lossy()
-                                .to_string();
-
-                            let payload = match Payload::try_from(json!({
-                                "file_path": file_path_relative.clone(),
-                                "start_line": chunk.chunk.start_line,
-                                "end_line": chunk.chunk.end_line,
-                                "symbol_name": chunk.chunk.symbol_name.clone(),
-                                "symbol_kind": chunk.chunk.symbol_kind.clone(),
-                                "is_container": chunk.chunk.chunk_metadata.is_container,
-                                "original_size_lines": chunk.chunk.chunk_metadata.original_size_lines,
-                                "is_split": chunk.chunk.chunk_metadata.is_split,
-                                "chunk_depth": chunk.chunk.chunk_metadata.chunk_depth,
-                                "context": chunk.chunk.context.clone(),
-                                "content": chunk.chunk.content.clone(),
-                            })) {
-                                Ok(payload) => payload,
-                                Err(e) => {
-                                    warn!(
-                                        "Failed to convert chunk to payload for file {}: {}",
-                                        file_path_relative, e
-                                    );
-                                    continue;
-                                }
-                            };
-
-                            let point_id = generate_point_id(
-                                &file_path_relative,
-                                chunk.chunk.start_line,
-                                chunk.chunk.end_line,
-                                &chunk.chunk.symbol_name,
-                            );
-
-                            points.push(PointStruct::new(point_id, chunk.embedding, payload));
-                        }
-
-                        // Upsert points (this will automatically update existing points with same ID)
-                        QDRANT_CLIENT
-                            .upsert_points(UpsertPointsBuilder::new(
-                                generate_collection_id(root_path.as_ref()).as_str(),
-                                points,
-                            ))
-                            .await?;
-
-                        info!(
-                            "Successfully inserted points for {} files (added: {}, modified: {})",
-                            files_to_process.len(),
-                            added_files.len(),
-                            modified_files.len()
-                        );
-                    }
-                }
-
-                // 5. Save the updated state file
-                let new_state = CodebaseState {
-                    file_states: current_file_states,
-                };
-                new_state.to_file(None)?;
-                info!("Updated state file with current file states");
+            // 3. Update vector database if there are changes
+            // (new_states may also just carry moved mtimes, recorded so the next run
+            // can skip hashing those files)
+            let state_changed = !changes.is_empty()
+                || !changes.new_states.is_empty()
+                || saved_state.index_params.is_none()
+                || saved_state.ignore_rules_hash.as_ref() != Some(&rules_hash);
+            if !changes.is_empty() {
+                let chunk_ids = apply_file_changes(
+                    &QDRANT_CLIENT,
+                    &embedding_client,
+                    &chunking_options,
+                    root_path.as_ref(),
+                    &collection_id,
+                    &saved_state,
+                    &changes.added,
+                    &changes.modified,
+                    &changes.deleted,
+                    progress,
+                )
+                .await?;
+                changes.record_chunk_ids(chunk_ids);
             } else {
                 info!("No changes detected, vector database is up to date");
             }
+
+            // 4. Save the updated state file
+            let mut new_state = saved_state;
+            changes.apply_to(&mut new_state);
+            // States from older versions adopt the current settings rather than forcing
+            // a reindex on upgrade
+            new_state.index_params = Some(index_params);
+            new_state.ignore_rules_hash = Some(rules_hash);
+            let baseline_moved = refresh_git_baseline(root_path.as_ref(), &config, &mut new_state);
+            if state_changed || baseline_moved {
+                new_state.to_file(&index_file_path)?;
+                info!("Updated state file with current file states");
+            }
+            // Collections created before roots were recorded get picked up here
+            if let Err(e) = remember_root(root_path.as_ref()) {
+                warn!("Failed to record project root: {e}");
+            }
         }
         Ok(false) => {
             info!("No existing index file found, initializing new session...");
-            init_session(root_path).await?;
+            init_session_with_progress(root_path, progress).await?;
         }
         Err(e) => {
             return Err(anyhow::anyhow!(
@@ -536,3 +674,1389 @@ pub async fn restore_session<P: AsRef<Path>>(root_path: P) -> Result<(), anyhow:
     }
     Ok(())
 }
+
+/// Points are written in batches of this size so large runs can report progress
+pub(crate) const UPSERT_BATCH_SIZE: usize = 256;
+
+/// Parse and chunk root-relative `files`, reporting `Chunking` progress per file
+/// Files that fail to parse are skipped with a warning.
+fn chunk_files(
+    root_path: &Path,
+    files: &[String],
+    chunking_options: &ChunkingOptions,
+    progress: &ProgressFn<'_>,
+) -> Result<Vec<(String, Vec<CodeChunk>)>, anyhow::Error> {
+    progress(IndexProgress::Started {
+        phase: IndexPhase::Chunking,
+        total: files.len() as u64,
+    });
+    let mut parser = SymbolParser::new()?;
+    let mut chunker = HierarchicalChunker::new(chunking_options.clone())?;
+    let mut chunked = Vec::with_capacity(files.len());
+    for file_path in files {
+        let chunks = parser
+            .parse_file(root_path.join(file_path))
+            .and_then(|symbols| chunker.chunk_symbols(&symbols));
+        match chunks {
+            Ok(chunks) => {
+                debug!("Generated {} chunks for file: {}", chunks.len(), file_path);
+                chunked.push((file_path.clone(), chunks));
+            }
+            Err(e) => warn!("Failed to chunk file {}: {}", file_path, e),
+        }
+        progress(IndexProgress::Advanced {
+            phase: IndexPhase::Chunking,
+            count: 1,
+        });
+    }
+    progress(IndexProgress::Finished {
+        phase: IndexPhase::Chunking,
+    });
+    Ok(chunked)
+}
+
+/// Upsert `points` in batches, reporting `Upserting` progress per batch
+#[instrument(name = "upsert", skip_all, fields(collection = collection_id, points = points.len()))]
+async fn upsert_points_with_progress(
+    qdrant: &Qdrant,
+    collection_id: &str,
+    points: Vec<PointStruct>,
+    progress: &ProgressFn<'_>,
+) -> Result<(), anyhow::Error> {
+    let timer = telemetry::start(Operation::Upsert);
+    let total = points.len() as u64;
+    progress(IndexProgress::Started {
+        phase: IndexPhase::Upserting,
+        total: points.len() as u64,
+    });
+    let mut points = points.into_iter();
+    loop {
+        let batch = points.by_ref().take(UPSERT_BATCH_SIZE).collect::<Vec<_>>();
+        if batch.is_empty() {
+            break;
+        }
+        let count = batch.len() as u64;
+        qdrant
+            .upsert_points(UpsertPointsBuilder::new(collection_id, batch))
+            .await?;
+        progress(IndexProgress::Advanced {
+            phase: IndexPhase::Upserting,
+            count,
+        });
+    }
+    progress(IndexProgress::Finished {
+        phase: IndexPhase::Upserting,
+    });
+    timer.succeeded(total);
+    Ok(())
+}
+
+/// Remove stale points and index new content for a set of changed files
+/// Points for deleted files are removed first, by the IDs recorded in `saved_state`
+/// where known, then added and modified files are re-chunked, embedded and upserted.
+/// Unchanged symbols of a modified file keep their point IDs and are upserted in place,
+/// so only the recorded points left over are removed afterwards; modified files without
+/// recorded IDs are cleared up front. Paths are relative to `root_path`. Returns the
+/// point IDs now indexed for each added or modified file.
+#[allow(clippy::too_many_arguments)]
+#[instrument(
+    name = "index",
+    skip_all,
+    fields(
+        root = %root_path.display(),
+        mode = "incremental",
+        added = added_files.len(),
+        modified = modified_files.len(),
+        deleted = deleted_files.len(),
+    )
+)]
+pub(crate) async fn apply_file_changes(
+    qdrant: &Qdrant,
+    embedding_client: &EmbeddingClient,
+    chunking_options: &ChunkingOptions,
+    root_path: &Path,
+    collection_id: &str,
+    saved_state: &CodebaseState,
+    added_files: &[String],
+    modified_files: &[String],
+    deleted_files: &[String],
+    progress: &ProgressFn<'_>,
+) -> Result<HashMap<String, Vec<String>>, anyhow::Error> {
+    let timer = telemetry::start(Operation::IncrementalIndex);
+    let files = (added_files.len() + modified_files.len() + deleted_files.len()) as u64;
+
+    // Handle file deletions - remove points for deleted and modified files
+    let files_to_delete: Vec<String> = deleted_files
+        .iter()
+        .chain(modified_files.iter())
+        .cloned()
+        .collect();
+
+    if !files_to_delete.is_empty() {
+        debug!(
+            "Removing points for {} files (deleted: {}, modified: {})",
+            files_to_delete.len(),
+            deleted_files.len(),
+            modified_files.len()
+        );
+
+        // Files with recorded chunk IDs are deleted exactly; the rest by payload filter.
+        // Recorded points of modified files are pruned after the upsert instead.
+        let mut point_ids = Vec::new();
+        let mut conditions = Vec::new();
+        for file_path in &files_to_delete {
+            match saved_state.file_states.get(file_path) {
+                Some(file_state)
+                    if !file_state.chunk_ids.is_empty() && modified_files.contains(file_path) => {}
+                Some(file_state) if !file_state.chunk_ids.is_empty() => {
+                    point_ids.extend(file_state.chunk_ids.iter().cloned());
+                }
+                _ => conditions.push(Condition::matches("file_path", file_path.clone())),
+            }
+        }
+
+        delete_point_ids(qdrant, collection_id, point_ids, files_to_delete.len()).await?;
+        if !conditions.is_empty() {
+            qdrant
+                .delete_points(
+                    DeletePointsBuilder::new(collection_id).points(Filter::should(conditions)),
+                )
+                .await
+                .map_err(|e| {
+                    anyhow::anyhow!(
+                        "Failed to delete points for {} files: {}",
+                        files_to_delete.len(),
+                        e
+                    )
+                })?;
+        }
+        info!(
+            "Deleted points for {} files (deleted: {}, modified: {})",
+            files_to_delete.len(),
+            deleted_files.len(),
+            modified_files.len()
+        );
+    }
+
+    // Process added and modified files - chunk and insert new content
+    let files_to_process: Vec<String> = added_files
+        .iter()
+        .chain(modified_files.iter())
+        .cloned()
+        .collect();
+
+    if files_to_process.is_empty() {
+        timer.succeeded(files);
+        return Ok(HashMap::new());
+    }
+
+    info!(
+        "Processing {} files for insertion (added: {}, modified: {})",
+        files_to_process.len(),
+        added_files.len(),
+        modified_files.len()
+    );
+
+    let chunked = chunk_files(root_path, &files_to_process, chunking_options, progress)?;
+
+    // Embed each file separately so one failing file doesn't hold back the rest
+    progress(IndexProgress::Started {
+        phase: IndexPhase::Embedding,
+        total: chunked.iter().map(|(_, chunks)| chunks.len() as u64).sum(),
+    });
+    let mut all_chunks = Vec::new();
+    for (file_path, chunks) in &chunked {
+        let embedded = embedding_client
+            .embed_chunks_with_progress(chunks, |count| {
+                progress(IndexProgress::Advanced {
+                    phase: IndexPhase::Embedding,
+                    count: count as u64,
+                })
+            })
+            .await;
+        match embedded {
+            Ok(mut embedded) => all_chunks.append(&mut embedded),
+            Err(e) => warn!("Failed to embed file {}: {}", file_path, e),
+        }
+    }
+    progress(IndexProgress::Finished {
+        phase: IndexPhase::Embedding,
+    });
+
+    info!(
+        "Generated {} chunks for {} files",
+        all_chunks.len(),
+        files_to_process.len()
+    );
+
+    if all_chunks.is_empty() {
+        let stale = stale_point_ids(saved_state, modified_files, &HashMap::new());
+        delete_point_ids(qdrant, collection_id, stale, modified_files.len()).await?;
+        timer.succeeded(files);
+        return Ok(HashMap::new());
+    }
+
+    // Convert chunks to points with metadata
+    let mut chunk_points = Vec::new();
+    for chunk in all_chunks {
+        match chunk_to_point(root_path, chunk) {
+            Ok(chunk_point) => chunk_points.push(chunk_point),
+            Err(e) => {
+                warn!("{e}");
+                continue;
+            }
+        }
+    }
+    let (points, chunk_ids) = group_points_by_file(chunk_points);
+
+    // Upsert points (this will automatically update existing points with same ID)
+    upsert_points_with_progress(qdrant, collection_id, points, progress).await?;
+    let stale = stale_point_ids(saved_state, modified_files, &chunk_ids);
+    delete_point_ids(qdrant, collection_id, stale, modified_files.len()).await?;
+
+    info!(
+        "Successfully inserted points for {} files (added: {}, modified: {})",
+        files_to_process.len(),
+        added_files.len(),
+        modified_files.len()
+    );
+
+    timer.succeeded(files);
+    Ok(chunk_ids)
+}
+
+/// Recorded point IDs of `modified_files` that aren't among their new `chunk_ids`
+fn stale_point_ids(
+    saved_state: &CodebaseState,
+    modified_files: &[String],
+    chunk_ids: &HashMap<String, Vec<String>>,
+) -> Vec<String> {
+    modified_files
+        .iter()
+        .filter_map(|file_path| {
+            let recorded = &saved_state.file_states.get(file_path)?.chunk_ids;
+            let current = chunk_ids.get(file_path);
+            Some(
+                recorded
+                    .iter()
+                    .filter(move |id| current.is_none_or(|current| !current.contains(id))),
+            )
+        })
+        .flatten()
+        .cloned()
+        .collect()
+}
+
+/// Delete points by ID, if there are any; `files` is how many files they belong to
+async fn delete_point_ids(
+    qdrant: &Qdrant,
+    collection_id: &str,
+    point_ids: Vec<String>,
+    files: usize,
+) -> Result<(), anyhow::Error> {
+    if point_ids.is_empty() {
+        return Ok(());
+    }
+    qdrant
+        .delete_points(DeletePointsBuilder::new(collection_id).points(point_ids))
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to delete points for {files} files: {e}"))?;
+    Ok(())
+}
+
+/// update_files applies an incremental update for a known set of files
+/// This is the fast path used by `watch`: instead of rescanning the whole project,
+/// only the given root-relative paths are re-hashed, re-chunked and upserted.
+/// Files whose content hash is unchanged are skipped, and a deleted path that names a
+/// directory removes every tracked file beneath it. The project must already be
+/// indexed (see `restore_session`).
+pub async fn update_files<P: AsRef<Path>>(
+    root_path: P,
+    changed_files: &[String],
+    deleted_files: &[String],
+) -> Result<(), anyhow::Error> {
+    let root_path = root_path.as_ref();
+    let index_file_path = state_file_path(root_path)?;
+    let mut state = CodebaseState::from_file(&index_file_path).map_err(|e| {
+        anyhow::anyhow!(
+            "Failed to load state file {}: {e}. Run 'index-codebase' first",
+            index_file_path.display()
+        )
+    })?;
+
+    let config = IndexConfig::load(root_path)?;
+    let hash_algorithm = config.state.hash;
+    let mut changes = classify_file_changes(
+        root_path,
+        &state,
+        changed_files,
+        deleted_files,
+        hash_algorithm,
+    );
+    if changes.is_empty() {
+        debug!("No indexable changes in batch");
+        if !changes.new_states.is_empty() {
+            changes.apply_to(&mut state);
+            state.to_file(&index_file_path)?;
+        }
+        return Ok(());
+    }
+
+    info!(
+        "Updating index - Added: {}, Modified: {}, Deleted: {}",
+        changes.added.len(),
+        changes.modified.len(),
+        changes.deleted.len()
+    );
+
+    let collection_id = generate_collection_id(root_path);
+    let embedding_client = get_embedding_client()?;
+    let chunk_ids = apply_file_changes(
+        &QDRANT_CLIENT,
+        &embedding_client,
+        &config.chunking_options(),
+        root_path,
+        &collection_id,
+        &state,
+        &changes.added,
+        &changes.modified,
+        &changes.deleted,
+        &no_progress,
+    )
+    .await?;
+
+    changes.record_chunk_ids(chunk_ids);
+    changes.apply_to(&mut state);
+    state.to_file(&index_file_path)?;
+
+    Ok(())
+}
+
+/// Everything `status` reports about a project's index
+#[derive(Debug, Clone, Serialize)]
+pub struct IndexStatus {
+    pub root_path: PathBuf,
+    /// Whether both the state file and the collection exist
+    pub indexed: bool,
+    pub collection_id: String,
+    /// Points in the collection; None when it's missing or Qdrant can't be reached
+    pub point_count: Option<u64>,
+    /// Why the collection couldn't be inspected
+    pub collection_error: Option<String>,
+    pub state: Option<StateSummary>,
+    /// Files that changed on disk since the state was written
+    pub pending: Option<PendingChanges>,
+}
+
+/// Root-relative files that differ between the working tree and the saved state
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PendingChanges {
+    pub added: Vec<String>,
+    pub modified: Vec<String>,
+    pub deleted: Vec<String>,
+}
+
+impl PendingChanges {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.modified.is_empty() && self.deleted.is_empty()
+    }
+}
+
+/// Inspect a project's index without changing it
+/// Pending changes are found the same way `restore_session` would find them.
+pub async fn index_status<P: AsRef<Path>>(root_path: P) -> Result<IndexStatus, anyhow::Error> {
+    let root_path = root_path.as_ref();
+    let collection_id = generate_collection_id(root_path);
+
+    let (point_count, collection_error) =
+        match QDRANT_CLIENT.collection_info(collection_id.as_str()).await {
+            Ok(response) => (
+                Some(
+                    response
+                        .result
+                        .and_then(|info| info.points_count)
+                        .unwrap_or_default(),
+                ),
+                None,
+            ),
+            Err(e) => (None, Some(e.to_string())),
+        };
+
+    let state_path = state_file_path(root_path)?;
+    let (state, pending) = if state_path.exists() {
+        let saved_state = CodebaseState::from_file(&state_path)?;
+        let config = IndexConfig::load(root_path)?;
+        let mut changes = detect_changes(
+            root_path,
+            &saved_state,
+            &config,
+            &ignore_rules_hash(root_path),
+        )?;
+        changes.added.sort();
+        changes.modified.sort();
+        changes.deleted.sort();
+        let pending = PendingChanges {
+            added: changes.added,
+            modified: changes.modified,
+            deleted: changes.deleted,
+        };
+        (Some(summarize_state(root_path)?), Some(pending))
+    } else {
+        (None, None)
+    };
+
+    Ok(IndexStatus {
+        root_path: root_path.to_path_buf(),
+        indexed: state.is_some() && point_count.is_some(),
+        collection_id,
+        point_count,
+        collection_error,
+        state,
+        pending,
+    })
+}
+
+/// A collection created by this crate, as listed by `list_collections`
+#[derive(Debug, Clone, Serialize)]
+pub struct CollectionSummary {
+    pub name: String,
+    /// Project the collection was built from, if this user indexed it
+    pub root_path: Option<PathBuf>,
+    pub status: Option<String>,
+    pub points_count: Option<u64>,
+    pub segments_count: Option<u64>,
+    pub dimension: Option<u64>,
+    /// Raw size of the stored vectors (points × dimension × 4 bytes), excluding payloads
+    /// and index overhead
+    pub vector_bytes: Option<u64>,
+    /// Why the collection's details couldn't be fetched
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// List the collections on the Qdrant instance that were created by this crate
+pub async fn list_collections() -> Result<Vec<CollectionSummary>, anyhow::Error> {
+    let response = QDRANT_CLIENT
+        .list_collections()
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to list collections: {e}"))?;
+    let roots = known_roots().unwrap_or_else(|e| {
+        warn!("Failed to read known project roots: {e}");
+        Default::default()
+    });
+
+    let mut names = response
+        .collections
+        .into_iter()
+        .map(|collection| collection.name)
+        .filter(|name| name.starts_with(COLLECTION_PREFIX))
+        .collect::<Vec<_>>();
+    names.sort();
+
+    let mut collections = Vec::with_capacity(names.len());
+    for name in names {
+        let mut summary = CollectionSummary {
+            root_path: roots.get(&name).cloned(),
+            name,
+            status: None,
+            points_count: None,
+            segments_count: None,
+            dimension: None,
+            vector_bytes: None,
+            error: None,
+        };
+        match QDRANT_CLIENT.collection_info(summary.name.as_str()).await {
+            Ok(response) => {
+                if let Some(info) = response.result {
+                    summary.status = CollectionStatus::try_from(info.status)
+                        .ok()
+                        .map(|status| status.as_str_name().to_lowercase());
+                    summary.points_count = info.points_count;
+                    summary.segments_count = Some(info.segments_count);
+                    summary.dimension = collection_dimension(&info);
+                    summary.vector_bytes = summary
+                        .points_count
+                        .zip(summary.dimension)
+                        .map(|(points, dimension)| points * dimension * 4);
+                }
+            }
+            Err(e) => summary.error = Some(e.to_string()),
+        }
+        collections.push(summary);
+    }
+    Ok(collections)
+}
+
+/// Size of a collection's unnamed vector, if it has one
+pub(crate) fn collection_dimension(info: &CollectionInfo) -> Option<u64> {
+    info.config
+        .as_ref()
+        .and_then(|config| config.params.as_ref())
+        .and_then(|params| params.vectors_config.as_ref())
+        .and_then(|vectors| vectors.config.as_ref())
+        .and_then(|config| match config {
+            VectorsConfigKind::Params(params) => Some(params.size),
+            VectorsConfigKind::ParamsMap(_) => None,
+        })
+}
+
+/// What `clear_index` removed
+#[derive(Debug, Clone, Default)]
+pub struct ClearedIndex {
+    pub collection_id: String,
+    /// Whether the collection existed and was deleted
+    pub collection_deleted: bool,
+    /// State and feedback files that were deleted
+    pub removed_files: Vec<PathBuf>,
+}
+
+/// Delete a project's collection, its local state and its relevance feedback, whose
+/// chunk IDs would not match a new index, so it can be indexed from scratch
+/// `.codex/index.toml` is kept.
+pub async fn clear_index<P: AsRef<Path>>(root_path: P) -> Result<ClearedIndex, anyhow::Error> {
+    let root_path = root_path.as_ref();
+    let collection_id = generate_collection_id(root_path);
+
+    let collection_deleted = QDRANT_CLIENT
+        .collection_exists(collection_id.as_str())
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to check collection {collection_id}: {e}"))?;
+    if collection_deleted {
+        QDRANT_CLIENT
+            .delete_collection(collection_id.as_str())
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to delete collection {collection_id}: {e}"))?;
+        info!("Deleted collection {collection_id}");
+    }
+
+    let removed_files = remove_state_files(root_path)?;
+    if let Err(e) = forget_root(root_path) {
+        warn!("Failed to forget project root: {e}");
+    }
+
+    Ok(ClearedIndex {
+        collection_id,
+        collection_deleted,
+        removed_files,
+    })
+}
+
+/// Delete a project's state file and feedback log, and the legacy ones if they're
+/// still around
+fn remove_state_files(root_path: &Path) -> Result<Vec<PathBuf>, anyhow::Error> {
+    let mut removed_files = Vec::new();
+    let state_path = state_file_path(root_path)?;
+    let feedback_path = feedback_file_for_state(&state_path);
+    for path in [
+        state_path,
+        feedback_path,
+        root_path.join(LEGACY_STATE_FILE_NAME),
+        root_path.join(LEGACY_FEEDBACK_FILE_NAME),
+    ] {
+        if !path.is_file() {
+            continue;
+        }
+        fs::remove_file(&path)
+            .map_err(|e| anyhow::anyhow!("Failed to remove {}: {e}", path.display()))?;
+        info!("Removed {}", path.display());
+        removed_files.push(path);
+    }
+    Ok(removed_files)
+}
+
+/// What `repair_state` changed
+#[derive(Debug, Clone, Default)]
+pub struct StateRepair {
+    /// Points deleted because no tracked file owns them
+    pub orphaned_points: usize,
+    /// Tracked files with no points in the collection that were indexed again
+    pub reindexed_files: Vec<String>,
+    /// Tracked files with no points that no longer exist, removed from the state
+    pub dropped_files: Vec<String>,
+    /// Files whose recorded chunk IDs were replaced with the ones in the collection
+    pub refreshed_files: usize,
+}
+
+/// Number of points fetched per scroll request while reconciling the state
+const REPAIR_SCROLL_PAGE_SIZE: u32 = 256;
+
+/// Reconcile a project's state file with its Qdrant collection
+/// Points whose file isn't tracked are deleted, tracked files without any points are
+/// re-chunked and re-embedded (or dropped from the state if they're gone), and the
+/// recorded chunk IDs are replaced with the IDs actually in the collection.
+pub async fn repair_state<P: AsRef<Path>>(root_path: P) -> Result<StateRepair, anyhow::Error> {
+    let root_path = root_path.as_ref();
+    let index_file_path = state_file_path(root_path)?;
+    let mut state = CodebaseState::from_file(&index_file_path).map_err(|e| {
+        anyhow::anyhow!(
+            "Failed to load state file {}: {e}. Run 'index-codebase' first",
+            index_file_path.display()
+        )
+    })?;
+    let collection_id = generate_collection_id(root_path);
+    let indexed = collect_indexed_point_ids(&QDRANT_CLIENT, &collection_id).await?;
+    let mut repair = StateRepair::default();
+
+    let orphan_ids = orphaned_point_ids(&state, &indexed);
+    if !orphan_ids.is_empty() {
+        repair.orphaned_points = orphan_ids.len();
+        QDRANT_CLIENT
+            .delete_points(DeletePointsBuilder::new(collection_id.as_str()).points(orphan_ids))
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to delete orphaned points: {e}"))?;
+    }
+
+    let (refreshed_files, missing) = refresh_chunk_ids(&mut state, &indexed);
+    repair.refreshed_files = refreshed_files;
+
+    let config = IndexConfig::load(root_path)?;
+    let hash_algorithm = config.state.hash;
+    for file_path in missing {
+        let full_path = root_path.join(&file_path);
+        let file_state = get_file_metadata(&full_path)
+            .and_then(|last_modified| FileState::new(&full_path, last_modified, hash_algorithm));
+        match file_state {
+            Ok(file_state) => {
+                state.file_states.insert(file_path.clone(), file_state);
+                repair.reindexed_files.push(file_path);
+            }
+            Err(e) => {
+                debug!("Dropping {file_path} from state: {e}");
+                state.file_states.remove(&file_path);
+                repair.dropped_files.push(file_path);
+            }
+        }
+    }
+
+    if !repair.reindexed_files.is_empty() {
+        let embedding_client = get_embedding_client()?;
+        let chunk_ids = apply_file_changes(
+            &QDRANT_CLIENT,
+            &embedding_client,
+            &config.chunking_options(),
+            root_path,
+            &collection_id,
+            &state,
+            &repair.reindexed_files,
+            &[],
+            &[],
+            &no_progress,
+        )
+        .await?;
+        for (file_path, ids) in chunk_ids {
+            if let Some(file_state) = state.file_states.get_mut(&file_path) {
+                file_state.chunk_ids = ids;
+            }
+        }
+    }
+
+    state.to_file(&index_file_path)?;
+    info!(
+        "Repaired state for {}: {} orphaned points, {} reindexed, {} dropped, {} refreshed",
+        root_path.display(),
+        repair.orphaned_points,
+        repair.reindexed_files.len(),
+        repair.dropped_files.len(),
+        repair.refreshed_files
+    );
+    Ok(repair)
+}
+
+/// IDs of the indexed points whose file isn't tracked in `state`
+fn orphaned_point_ids(
+    state: &CodebaseState,
+    indexed: &HashMap<String, Vec<String>>,
+) -> Vec<String> {
+    indexed
+        .iter()
+        .filter(|(file_path, _)| !state.file_states.contains_key(*file_path))
+        .flat_map(|(_, ids)| ids.iter().cloned())
+        .collect()
+}
+
+/// Replace the recorded chunk IDs of tracked files with the ones in the collection
+/// Returns how many files had stale IDs and the tracked files with no points at all.
+fn refresh_chunk_ids(
+    state: &mut CodebaseState,
+    indexed: &HashMap<String, Vec<String>>,
+) -> (usize, Vec<String>) {
+    let mut refreshed = 0;
+    let mut missing = Vec::new();
+    for (file_path, file_state) in &mut state.file_states {
+        match indexed.get(file_path) {
+            Some(ids) if file_state.chunk_ids != *ids => {
+                file_state.chunk_ids = ids.clone();
+                refreshed += 1;
+            }
+            Some(_) => {}
+            None => missing.push(file_path.clone()),
+        }
+    }
+    missing.sort();
+    (refreshed, missing)
+}
+
+/// Page through a collection and group its point IDs by their `file_path` payload
+async fn collect_indexed_point_ids(
+    qdrant: &Qdrant,
+    collection_id: &str,
+) -> Result<HashMap<String, Vec<String>>, anyhow::Error> {
+    let mut indexed: HashMap<String, Vec<String>> = HashMap::new();
+    let mut offset = None;
+    loop {
+        let mut request = ScrollPointsBuilder::new(collection_id)
+            .limit(REPAIR_SCROLL_PAGE_SIZE)
+            .with_payload(PayloadIncludeSelector {
+                fields: vec!["file_path".to_string()],
+            })
+            .with_vectors(false);
+        if let Some(offset) = offset.take() {
+            request = request.offset(offset);
+        }
+
+        let response = qdrant
+            .scroll(request)
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to read collection {collection_id}: {e}"))?;
+        for point in response.result {
+            let Some(file_path) = point.payload.get("file_path").and_then(|v| v.as_str()) else {
+                continue;
+            };
+            indexed
+                .entry(file_path.clone())
+                .or_default()
+                .push(point_id_to_string(point.id));
+        }
+
+        match response.next_page_offset {
+            Some(next) => offset = Some(next),
+            None => break,
+        }
+    }
+
+    for ids in indexed.values_mut() {
+        ids.sort();
+    }
+    Ok(indexed)
+}
+
+/// Root-relative files to reindex, as worked out from a set of changed and deleted paths
+#[derive(Debug, Clone, Default)]
+pub(crate) struct FileChanges {
+    pub(crate) added: Vec<String>,
+    pub(crate) modified: Vec<String>,
+    pub(crate) deleted: Vec<String>,
+    /// Fresh states for the added and modified files, plus unchanged files whose mtime moved
+    pub(crate) new_states: HashMap<String, FileState>,
+}
+
+impl FileChanges {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.modified.is_empty() && self.deleted.is_empty()
+    }
+
+    /// Attach the point IDs returned by `apply_file_changes` to the new file states
+    pub(crate) fn record_chunk_ids(&mut self, mut chunk_ids: HashMap<String, Vec<String>>) {
+        for (file_path, file_state) in &mut self.new_states {
+            if let Some(ids) = chunk_ids.remove(file_path) {
+                file_state.chunk_ids = ids;
+            }
+        }
+    }
+
+    /// Record these changes in the saved state once they've been applied to the index
+    pub(crate) fn apply_to(self, state: &mut CodebaseState) {
+        for file_path in &self.deleted {
+            state.file_states.remove(file_path);
+        }
+        // These files no longer match the git baseline, so they must be re-checked
+        // even if git later reports them as unchanged from the baseline commit
+        if let Some(git) = state.git.as_mut() {
+            git.dirty_files.extend(
+                self.added
+                    .iter()
+                    .chain(&self.modified)
+                    .chain(&self.deleted)
+                    .cloned(),
+            );
+        }
+        state.file_states.extend(self.new_states);
+    }
+}
+
+/// Work out which files changed since `saved_state` was written
+/// With `change_detection = "git"` and a recorded baseline, only the files git reports
+/// as changed since the baseline commit (plus the baseline's dirty files) are checked;
+/// otherwise, when git fails, or when the ignore rules no longer match
+/// `ignore_rules_hash`, every supported file is scanned, so newly included files show
+/// up as added and newly excluded ones as deleted.
+pub(crate) fn detect_changes(
+    root_path: &Path,
+    saved_state: &CodebaseState,
+    config: &IndexConfig,
+    ignore_rules_hash: &str,
+) -> Result<FileChanges, anyhow::Error> {
+    let hash_algorithm = config.state.hash;
+    let rules_changed = saved_state.ignore_rules_hash.as_deref() != Some(ignore_rules_hash);
+    let baseline = match (config.state.change_detection, &saved_state.git) {
+        (ChangeDetection::Git, _) if rules_changed => {
+            info!("Ignore rules changed, scanning all files");
+            None
+        }
+        (ChangeDetection::Git, Some(baseline)) => Some(baseline),
+        (ChangeDetection::Git, None) => {
+            info!("No git baseline recorded yet, scanning all files");
+            None
+        }
+        (ChangeDetection::Hash, _) => None,
+    };
+
+    if let Some(baseline) = baseline {
+        match git_changes::changed_since(root_path, &baseline.commit) {
+            Ok(git) => {
+                return Ok(classify_git_changes(
+                    root_path,
+                    saved_state,
+                    baseline,
+                    git,
+                    hash_algorithm,
+                ));
+            }
+            Err(e) => warn!("Git change detection failed, scanning all files: {e}"),
+        }
+    }
+
+    let current_file_states =
+        collect_supported_file_states(root_path, hash_algorithm, Some(saved_state))?;
+    Ok(diff_file_states(
+        root_path,
+        saved_state,
+        &current_file_states,
+    ))
+}
+
+/// Classify the candidates from git plus the baseline's dirty files
+/// Dirty paths that no longer exist are treated as deleted, and a dirty directory that
+/// exists again contributes every file beneath it.
+fn classify_git_changes(
+    root_path: &Path,
+    saved_state: &CodebaseState,
+    baseline: &GitBaseline,
+    git: GitChanges,
+    hash_algorithm: HashAlgorithm,
+) -> FileChanges {
+    let ignore_matcher = CodebaseIgnoreMatcher::new(root_path);
+    let mut changed = Vec::new();
+    let mut deleted = git.deleted;
+
+    for file_path in git
+        .changed
+        .into_iter()
+        .chain(baseline.dirty_files.iter().cloned())
+    {
+        let full_path = root_path.join(&file_path);
+        if full_path.is_dir() {
+            let walked = walk_codebase_files(&full_path, |path| {
+                if let Ok(relative) = path.strip_prefix(root_path) {
+                    changed.push(relative.to_string_lossy().to_string());
+                }
+                Ok(true) // Continue walking
+            });
+            if let Err(e) = walked {
+                warn!("Failed to walk {}: {e}", full_path.display());
+            }
+        } else if full_path.exists() {
+            changed.push(file_path);
+        } else {
+            deleted.push(file_path);
+        }
+    }
+
+    changed.retain(|file_path| !ignore_matcher.is_ignored(&root_path.join(file_path), false));
+    changed.sort();
+    changed.dedup();
+    deleted.sort();
+    deleted.dedup();
+    debug!(
+        "Git reported {} changed and {} deleted paths since {}",
+        changed.len(),
+        deleted.len(),
+        baseline.commit
+    );
+
+    classify_file_changes(root_path, saved_state, &changed, &deleted, hash_algorithm)
+}
+
+/// Record the current git commit as the state's baseline for `change_detection = "git"`
+/// Returns whether the baseline changed. Outside a git repository the state is left
+/// without a baseline and later runs fall back to scanning.
+pub(crate) fn refresh_git_baseline(
+    root_path: &Path,
+    config: &IndexConfig,
+    state: &mut CodebaseState,
+) -> bool {
+    if config.state.change_detection != ChangeDetection::Git {
+        return false;
+    }
+    match git_changes::snapshot(root_path) {
+        Ok(baseline) => {
+            let moved = state.git.as_ref() != Some(&baseline);
+            state.git = Some(baseline);
+            moved
+        }
+        Err(e) => {
+            warn!("Not recording a git baseline: {e}");
+            false
+        }
+    }
+}
+
+/// Compare changed and deleted paths against the saved state
+/// Changed files are re-hashed and skipped when their content is unchanged; a deleted
+/// path that names a directory matches every tracked file beneath it.
+pub(crate) fn classify_file_changes(
+    root_path: &Path,
+    state: &CodebaseState,
+    changed_files: &[String],
+    deleted_files: &[String],
+    hash_algorithm: HashAlgorithm,
+) -> FileChanges {
+    let mut added = Vec::new();
+    let mut modified = Vec::new();
+    let mut new_states = HashMap::new();
+
+    for file_path in changed_files {
+        let full_file_path = root_path.join(file_path);
+        if !is_supported_file_extension(&full_file_path) {
+            continue;
+        }
+
+        let last_modified = match get_file_metadata(&full_file_path) {
+            Ok(timestamp) => timestamp,
+            Err(e) => {
+                warn!("Skipping file due to metadata error: {}", e);
+                continue;
+            }
+        };
+        let file_state = match FileState::new(&full_file_path, last_modified, hash_algorithm) {
+            Ok(file_state) => file_state,
+            Err(e) => {
+                warn!("Skipping file {}: {}", file_path, e);
+                continue;
+            }
+        };
+
+        match state.file_states.get(file_path) {
+            Some(saved) if file_state.same_content(saved, &full_file_path) => {
+                debug!("File unchanged: {file_path}");
+                refresh_mtime(&mut new_states, file_path, saved, file_state);
+            }
+            Some(_) => {
+                debug!("File modified: {file_path}");
+                modified.push(file_path.clone());
+                new_states.insert(file_path.clone(), file_state);
+            }
+            None => {
+                debug!("File added: {file_path}");
+                added.push(file_path.clone());
+                new_states.insert(file_path.clone(), file_state);
+            }
+        }
+    }
+
+    // A deleted path may be a file or a whole directory of tracked files
+    let deleted = state
+        .file_states
+        .keys()
+        .filter(|tracked| {
+            deleted_files.iter().any(|deleted| {
+                *tracked == deleted
+                    || tracked
+                        .strip_prefix(deleted.as_str())
+                        .is_some_and(|rest| rest.starts_with(std::path::MAIN_SEPARATOR))
+            })
+        })
+        .cloned()
+        .collect();
+
+    FileChanges {
+        added,
+        modified,
+        deleted,
+        new_states,
+    }
+}
+
+/// Keep the saved hash of an unchanged file but record its new mtime, so the next scan
+/// can skip hashing it again
+fn refresh_mtime(
+    new_states: &mut HashMap<String, FileState>,
+    file_path: &str,
+    saved: &FileState,
+    current: FileState,
+) {
+    if saved.last_modified != current.last_modified {
+        new_states.insert(
+            file_path.to_string(),
+            FileState {
+                last_modified: current.last_modified,
+                ..saved.clone()
+            },
+        );
+    }
+}
+
+/// Compare the saved state with freshly collected file states for the whole project
+/// Files are matched by root-relative path and compared by content hash.
+pub(crate) fn diff_file_states(
+    root_path: &Path,
+    saved_state: &CodebaseState,
+    current_file_states: &HashMap<String, FileState>,
+) -> FileChanges {
+    let mut changes = FileChanges::default();
+
+    // Find added and modified files
+    for (file_path, current_state) in current_file_states {
+        match saved_state.file_states.get(file_path) {
+            Some(saved_state) => {
+                // File existed before, check if modified
+                if current_state == saved_state {
+                    continue;
+                }
+                if current_state.same_content(saved_state, &root_path.join(file_path)) {
+                    refresh_mtime(
+                        &mut changes.new_states,
+                        file_path,
+                        saved_state,
+                        current_state.clone(),
+                    );
+                } else {
+                    debug!("File modified: {file_path}");
+                    changes.modified.push(file_path.clone());
+                    changes
+                        .new_states
+                        .insert(file_path.clone(), current_state.clone());
+                }
+            }
+            None => {
+                // New file
+                debug!("File added: {file_path}");
+                changes.added.push(file_path.clone());
+                changes
+                    .new_states
+                    .insert(file_path.clone(), current_state.clone());
+            }
+        }
+    }
+
+    // Find deleted files
+    for file_path in saved_state.file_states.keys() {
+        if !current_file_states.contains_key(file_path) {
+            debug!("File deleted: {file_path}");
+            changes.deleted.push(file_path.clone());
+        }
+    }
+
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::expect_used)]
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_collect_reuses_states_with_unchanged_mtime() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let file_path = temp_dir.path().join("main.py");
+        std::fs::write(&file_path, "print('hi')\n").expect("Failed to write file");
+        let last_modified = get_file_metadata(&file_path).expect("Failed to stat file");
+
+        // A bogus hash proves the file was not re-read
+        let saved = CodebaseState::new(HashMap::from([(
+            "main.py".to_string(),
+            FileState {
+                content_hash: "not-a-real-hash".to_string(),
+                hash_algorithm: HashAlgorithm::Sha256,
+                last_modified,
+                chunk_ids: Vec::new(),
+            },
+        )]));
+        let states =
+            collect_supported_file_states(temp_dir.path(), HashAlgorithm::Sha256, Some(&saved))
+                .expect("Failed to collect states");
+        assert_eq!(states["main.py"].content_hash, "not-a-real-hash");
+
+        let touched = CodebaseState::new(HashMap::from([(
+            "main.py".to_string(),
+            FileState {
+                last_modified: last_modified - 1,
+                ..states["main.py"].clone()
+            },
+        )]));
+        let states =
+            collect_supported_file_states(temp_dir.path(), HashAlgorithm::Sha256, Some(&touched))
+                .expect("Failed to collect states");
+        assert_ne!(states["main.py"].content_hash, "not-a-real-hash");
+    }
+
+    #[test]
+    fn chunk_points_are_grouped_by_their_file() {
+        let chunk_point = |file_path: &str, symbol_id: &str| {
+            let point_id = generate_point_id(file_path, symbol_id);
+            ChunkPoint {
+                file_path: file_path.to_string(),
+                point: PointStruct::new(point_id.clone(), vec![0.0], Payload::new()),
+                point_id,
+            }
+        };
+
+        let (points, chunk_ids) = group_points_by_file(vec![
+            chunk_point("a.rs", "a::f"),
+            chunk_point("b.rs", "b::g"),
+            chunk_point("a.rs", "a::h"),
+            // The same symbol chunked twice yields one point ID
+            chunk_point("a.rs", "a::f"),
+        ]);
+        assert_eq!(points.len(), 4);
+        let mut expected = vec![
+            generate_point_id("a.rs", "a::f"),
+            generate_point_id("a.rs", "a::h"),
+        ];
+        expected.sort();
+        assert_eq!(chunk_ids["a.rs"], expected);
+        assert_eq!(chunk_ids["b.rs"], [generate_point_id("b.rs", "b::g")]);
+    }
+
+    #[test]
+    fn applied_changes_record_chunk_ids_in_the_state() {
+        let file_state = |chunk_ids: &[&str]| FileState {
+            content_hash: "abc".to_string(),
+            hash_algorithm: HashAlgorithm::Sha256,
+            last_modified: 1,
+            chunk_ids: chunk_ids.iter().map(ToString::to_string).collect(),
+        };
+        let mut state = CodebaseState::new(HashMap::from([
+            ("kept.rs".to_string(), file_state(&["kept-1"])),
+            ("edited.rs".to_string(), file_state(&["old-1", "old-2"])),
+            ("removed.rs".to_string(), file_state(&["removed-1"])),
+        ]));
+
+        let mut changes = FileChanges {
+            added: vec!["new.rs".to_string()],
+            modified: vec!["edited.rs".to_string()],
+            deleted: vec!["removed.rs".to_string()],
+            new_states: HashMap::from([
+                ("new.rs".to_string(), file_state(&[])),
+                ("edited.rs".to_string(), file_state(&[])),
+            ]),
+        };
+        changes.record_chunk_ids(HashMap::from([
+            ("new.rs".to_string(), vec!["new-1".to_string()]),
+            ("edited.rs".to_string(), vec!["edited-1".to_string()]),
+        ]));
+        changes.apply_to(&mut state);
+
+        assert_eq!(state.file_states["kept.rs"].chunk_ids, ["kept-1"]);
+        assert_eq!(state.file_states["edited.rs"].chunk_ids, ["edited-1"]);
+        assert_eq!(state.file_states["new.rs"].chunk_ids, ["new-1"]);
+        assert!(!state.file_states.contains_key("removed.rs"));
+    }
+
+    #[test]
+    fn repair_reconciles_the_state_with_the_collection() {
+        let file_state = |chunk_ids: &[&str]| FileState {
+            content_hash: "abc".to_string(),
+            hash_algorithm: HashAlgorithm::Sha256,
+            last_modified: 1,
+            chunk_ids: chunk_ids.iter().map(ToString::to_string).collect(),
+        };
+        let mut state = CodebaseState::new(HashMap::from([
+            ("current.rs".to_string(), file_state(&["c-1"])),
+            ("stale.rs".to_string(), file_state(&[])),
+            ("lost.rs".to_string(), file_state(&["l-1"])),
+        ]));
+        let indexed = HashMap::from([
+            ("current.rs".to_string(), vec!["c-1".to_string()]),
+            (
+                "stale.rs".to_string(),
+                vec!["s-1".to_string(), "s-2".to_string()],
+            ),
+            ("untracked.rs".to_string(), vec!["u-1".to_string()]),
+        ]);
+
+        assert_eq!(orphaned_point_ids(&state, &indexed), ["u-1"]);
+        let (refreshed, missing) = refresh_chunk_ids(&mut state, &indexed);
+        assert_eq!(refreshed, 1);
+        assert_eq!(missing, ["lost.rs"]);
+        assert_eq!(state.file_states["stale.rs"].chunk_ids, ["s-1", "s-2"]);
+        assert_eq!(state.file_states["current.rs"].chunk_ids, ["c-1"]);
+    }
+
+    #[tokio::test]
+    async fn status_reports_pending_changes_without_touching_the_index() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let root = temp_dir.path();
+
+        let status = index_status(root).await.expect("Failed to read status");
+        assert!(!status.indexed);
+        assert!(status.state.is_none());
+        assert!(status.pending.is_none());
+
+        for (path, content) in [
+            ("kept.py", "a = 1\n"),
+            ("edited.py", "b = 1\n"),
+            ("gone.py", "c = 1\n"),
+        ] {
+            std::fs::write(root.join(path), content).expect("Failed to write file");
+        }
+        let mut file_states = collect_supported_file_states(root, HashAlgorithm::Sha256, None)
+            .expect("Failed to collect states");
+        // Older mtimes make the edit below visible even within the same second
+        for file_state in file_states.values_mut() {
+            file_state.last_modified -= 1;
+        }
+        CodebaseState::new(file_states)
+            .to_file(&state_file_path(root).expect("Failed to locate state"))
+            .expect("Failed to write state");
+        std::fs::write(root.join("edited.py"), "b = 2\n").expect("Failed to edit file");
+        std::fs::remove_file(root.join("gone.py")).expect("Failed to remove file");
+        std::fs::write(root.join("new.py"), "d = 1\n").expect("Failed to write file");
+        std::fs::write(root.join("another.py"), "e = 1\n").expect("Failed to write file");
+
+        // The collection was never created, so the project isn't fully indexed
+        let status = index_status(root).await.expect("Failed to read status");
+        assert!(!status.indexed);
+        assert!(status.point_count.is_none());
+        assert!(status.collection_error.is_some());
+        assert_eq!(
+            status
+                .state
+                .expect("State should be summarized")
+                .tracked_files,
+            3
+        );
+        let pending = status.pending.expect("Pending changes should be listed");
+        assert_eq!(pending.added, ["another.py", "new.py"]);
+        assert_eq!(pending.modified, ["edited.py"]);
+        assert_eq!(pending.deleted, ["gone.py"]);
+    }
+
+    #[test]
+    fn clearing_removes_only_the_state_files() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let root = temp_dir.path();
+        let state_path = state_file_path(root).expect("Failed to locate state");
+        let legacy_path = root.join(LEGACY_STATE_FILE_NAME);
+        let config_path = root.join(".codex").join("index.toml");
+        CodebaseState::new(HashMap::new())
+            .to_file(&state_path)
+            .expect("Failed to write state");
+        std::fs::write(&legacy_path, "{}").expect("Failed to write legacy state");
+        std::fs::write(&config_path, "").expect("Failed to write config");
+        let feedback_path = feedback_file_for_state(&state_path);
+        let legacy_feedback_path = root.join(LEGACY_FEEDBACK_FILE_NAME);
+        std::fs::write(&feedback_path, "").expect("Failed to write feedback");
+        std::fs::write(&legacy_feedback_path, "").expect("Failed to write legacy feedback");
+
+        let removed = remove_state_files(root).expect("Failed to remove state files");
+        assert_eq!(
+            removed,
+            [
+                state_path.clone(),
+                feedback_path,
+                legacy_path.clone(),
+                legacy_feedback_path
+            ]
+        );
+        assert!(!state_path.exists());
+        assert!(!legacy_path.exists());
+        assert!(config_path.exists());
+
+        let removed = remove_state_files(root).expect("Failed to remove state files");
+        assert!(removed.is_empty());
+    }
+
+    #[test]
+    fn collection_dimension_comes_from_the_unnamed_vector() {
+        let info = |vectors_config| CollectionInfo {
+            config: Some(qdrant_client::qdrant::CollectionConfig {
+                params: Some(qdrant_client::qdrant::CollectionParams {
+                    vectors_config,
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        let unnamed = VectorParamsBuilder::new(1024, Distance::Cosine).build();
+        assert_eq!(
+            collection_dimension(&info(Some(unnamed.into()))),
+            Some(1024)
+        );
+        assert_eq!(collection_dimension(&info(None)), None);
+        assert_eq!(collection_dimension(&CollectionInfo::default()), None);
+    }
+
+    #[test]
+    fn payload_indexes_are_created_when_a_field_lacks_one() {
+        let schema = |fields: &[&str]| {
+            fields
+                .iter()
+                .map(|field| (field.to_string(), ()))
+                .collect::<HashMap<_, _>>()
+        };
+        assert!(missing_payload_indexes(&schema(&[])));
+        assert!(missing_payload_indexes(&schema(&[
+            "symbol_kind",
+            "file_path"
+        ])));
+        assert!(!missing_payload_indexes(&schema(&[
+            "symbol_kind",
+            "symbol_name"
+        ])));
+    }
+
+    #[test]
+    fn chunking_reports_progress_per_file() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        std::fs::write(temp_dir.path().join("a.py"), "def a():\n    return 1\n")
+            .expect("Failed to write file");
+        std::fs::write(temp_dir.path().join("b.rs"), "fn b() {}\n").expect("Failed to write file");
+        let files = ["a.py", "b.rs", "missing.py"].map(String::from);
+
+        let events = std::sync::Mutex::new(Vec::new());
+        let progress = |event| events.lock().expect("Progress lock poisoned").push(event);
+        let chunked = chunk_files(
+            temp_dir.path(),
+            &files,
+            &ChunkingOptions::default(),
+            &progress,
+        )
+        .expect("Failed to chunk files");
+
+        // A file that fails to parse is skipped but still counts as done
+        assert_eq!(chunked.len(), 2);
+        let advanced = IndexProgress::Advanced {
+            phase: IndexPhase::Chunking,
+            count: 1,
+        };
+        assert_eq!(
+            events.into_inner().expect("Progress lock poisoned"),
+            [
+                IndexProgress::Started {
+                    phase: IndexPhase::Chunking,
+                    total: 3,
+                },
+                advanced,
+                advanced,
+                advanced,
+                IndexProgress::Finished {
+                    phase: IndexPhase::Chunking,
+                },
+            ]
+        );
+    }
+}
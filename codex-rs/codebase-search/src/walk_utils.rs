@@ -1,7 +1,78 @@
+use ignore::Match;
 use ignore::WalkBuilder;
+use ignore::gitignore::Gitignore;
+use ignore::gitignore::GitignoreBuilder;
+use sha2::Digest;
+use sha2::Sha256;
 use std::path::Path;
+use std::path::PathBuf;
 use tracing::debug;
 
+use crate::index_config::IndexConfig;
+use crate::language_registry::LanguageRegistry;
+
+/// Built-in ignore patterns for common build/cache directories
+/// These are commonly excluded directories in development projects
+const BUILTIN_IGNORE_PATTERNS: &[&str] = &[
+    "target/",        // Rust build directory
+    "build/",         // General build directory
+    "dist/",          // Distribution directory
+    "node_modules/",  // Node.js dependencies
+    ".git/",          // Git metadata
+    ".svn/",          // SVN metadata
+    ".hg/",           // Mercurial metadata
+    "__pycache__/",   // Python bytecode cache
+    ".pytest_cache/", // Pytest cache
+    ".mypy_cache/",   // MyPy cache
+    ".venv/",         // Python virtual environment
+    "venv/",          // Python virtual environment
+    ".env/",          // Environment directory
+    "coverage/",      // Coverage reports
+    ".coverage/",     // Coverage data
+    ".nyc_output/",   // NYC coverage output
+    ".cache/",        // General cache directory
+    "tmp/",           // Temporary files
+    "temp/",          // Temporary files
+    ".tmp/",          // Hidden temporary files
+    ".DS_Store",      // macOS metadata files
+    "Thumbs.db",      // Windows thumbnail cache
+];
+
+/// Skipped unless `[manifests] yaml` is set
+const YAML_MANIFEST_PATTERNS: &[&str] = &["*.yaml", "*.yml"];
+
+/// Ignore files honoured in every directory, in increasing order of precedence
+const IGNORE_FILE_NAMES: &[&str] = &[".gitignore", ".ignore", ".codexignore"];
+
+/// The built-in patterns followed by `[ignore] patterns` from the project's config,
+/// plus YAML files unless the project opted into indexing them
+fn ignore_patterns(root_path: &Path) -> Vec<String> {
+    let mut patterns: Vec<String> = BUILTIN_IGNORE_PATTERNS
+        .iter()
+        .map(|pattern| pattern.to_string())
+        .collect();
+    let config = match IndexConfig::load(root_path) {
+        Ok(config) => config,
+        Err(e) => {
+            debug!(
+                "Not applying configured ignore patterns for {}: {}",
+                root_path.display(),
+                e
+            );
+            IndexConfig::default()
+        }
+    };
+    patterns.extend(config.ignore.patterns);
+    if !config.manifests.yaml {
+        patterns.extend(
+            YAML_MANIFEST_PATTERNS
+                .iter()
+                .map(|pattern| pattern.to_string()),
+        );
+    }
+    patterns
+}
+
 /// Creates a WalkBuilder configured with common ignore patterns for codebase analysis
 /// This function sets up directory walking that respects .gitignore files and excludes
 /// common build and temporary directories that shouldn't be analyzed.
@@ -20,36 +91,9 @@ pub fn create_codebase_walker<P: AsRef<Path>>(root_path: P) -> ignore::WalkBuild
     // Add common ignore patterns for directories that shouldn't be indexed
     builder.add_custom_ignore_filename(".codexignore");
 
-    // Built-in ignore patterns for common build/cache directories
-    // These are commonly excluded directories in development projects
-    let ignore_patterns = vec![
-        "target/",        // Rust build directory
-        "build/",         // General build directory
-        "dist/",          // Distribution directory
-        "node_modules/",  // Node.js dependencies
-        ".git/",          // Git metadata
-        ".svn/",          // SVN metadata
-        ".hg/",           // Mercurial metadata
-        "__pycache__/",   // Python bytecode cache
-        ".pytest_cache/", // Pytest cache
-        ".mypy_cache/",   // MyPy cache
-        ".venv/",         // Python virtual environment
-        "venv/",          // Python virtual environment
-        ".env/",          // Environment directory
-        "coverage/",      // Coverage reports
-        ".coverage/",     // Coverage data
-        ".nyc_output/",   // NYC coverage output
-        ".cache/",        // General cache directory
-        "tmp/",           // Temporary files
-        "temp/",          // Temporary files
-        ".tmp/",          // Hidden temporary files
-        ".DS_Store",      // macOS metadata files
-        "Thumbs.db",      // Windows thumbnail cache
-    ];
-
     // Add these as exclude patterns using override builder
     let mut override_builder = ignore::overrides::OverrideBuilder::new(root_path.as_ref());
-    for pattern in ignore_patterns {
+    for pattern in ignore_patterns(root_path.as_ref()) {
         // The `!` prefix indicates an exclude pattern
         let exclude_pattern = format!("!{pattern}");
         if let Err(e) = override_builder.add(&exclude_pattern) {
@@ -69,6 +113,187 @@ pub fn create_codebase_walker<P: AsRef<Path>>(root_path: P) -> ignore::WalkBuild
     builder
 }
 
+/// Fingerprint of the rules that decide which files get indexed
+/// Covers the built-in and configured patterns and the contents of every ignore file the walker honours
+/// under `root_path` (plus `.git/info/exclude`), so a different fingerprint means files
+/// may have become eligible for indexing or stopped being so.
+pub fn ignore_rules_hash<P: AsRef<Path>>(root_path: P) -> String {
+    let root_path = root_path.as_ref();
+    let mut ignore_files: Vec<PathBuf> = create_codebase_walker(root_path)
+        .build()
+        .flatten()
+        .filter(|entry| {
+            entry
+                .file_type()
+                .is_some_and(|file_type| file_type.is_dir())
+        })
+        .flat_map(|entry| {
+            IGNORE_FILE_NAMES
+                .iter()
+                .map(move |name| entry.path().join(name))
+        })
+        .chain(std::iter::once(root_path.join(".git/info/exclude")))
+        .filter(|path| path.is_file())
+        .collect();
+    ignore_files.sort();
+
+    let mut hasher = Sha256::new();
+    for pattern in ignore_patterns(root_path) {
+        hasher.update(pattern.as_bytes());
+        hasher.update(b"\n");
+    }
+    for ignore_file in ignore_files {
+        let Ok(content) = std::fs::read(&ignore_file) else {
+            continue;
+        };
+        let relative = ignore_file.strip_prefix(root_path).unwrap_or(&ignore_file);
+        hasher.update(relative.to_string_lossy().as_bytes());
+        hasher.update([0]);
+        hasher.update(&content);
+        hasher.update([0]);
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+/// Checks if a path is one of the ignore files that decide what gets indexed
+pub fn is_ignore_file(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| IGNORE_FILE_NAMES.contains(&name))
+}
+
+/// Answers "would the codebase walker skip this path?" for individual paths
+/// The walker only filters while it traverses, so code that learns about paths some
+/// other way (e.g. the file watcher) uses this to apply the same rules: the built-in
+/// patterns, hidden files, and every `.gitignore`/`.ignore`/`.codexignore` in the tree.
+#[derive(Debug, Clone)]
+pub struct CodebaseIgnoreMatcher {
+    root_path: PathBuf,
+    builtin: Option<Gitignore>,
+    /// Matchers for each directory that has ignore files, deepest directory first
+    dir_matchers: Vec<Gitignore>,
+}
+
+impl CodebaseIgnoreMatcher {
+    /// Build a matcher by collecting the ignore files under `root_path`
+    pub fn new<P: AsRef<Path>>(root_path: P) -> Self {
+        let root_path = root_path.as_ref().to_path_buf();
+
+        let mut builtin = GitignoreBuilder::new(&root_path);
+        for pattern in ignore_patterns(&root_path) {
+            if let Err(e) = builtin.add_line(None, &pattern) {
+                debug!("Failed to add ignore pattern '{}': {}", pattern, e);
+            }
+        }
+        let builtin = builtin
+            .build()
+            .map_err(|e| debug!("Failed to build built-in ignore matcher: {}", e))
+            .ok();
+
+        // Ignore files inside ignored directories have no effect, so walking with the
+        // codebase walker itself finds exactly the ones that matter
+        let mut dir_matchers: Vec<Gitignore> = create_codebase_walker(&root_path)
+            .build()
+            .flatten()
+            .filter(|entry| {
+                entry
+                    .file_type()
+                    .is_some_and(|file_type| file_type.is_dir())
+            })
+            .filter_map(|entry| Self::dir_matcher(entry.path()))
+            .collect();
+        dir_matchers.sort_by_key(|matcher| std::cmp::Reverse(matcher.path().components().count()));
+
+        debug!(
+            "Loaded ignore rules from {} directories under {}",
+            dir_matchers.len(),
+            root_path.display()
+        );
+
+        Self {
+            root_path,
+            builtin,
+            dir_matchers,
+        }
+    }
+
+    /// Build the matcher for the ignore files directly inside `dir`, if there are any
+    fn dir_matcher(dir: &Path) -> Option<Gitignore> {
+        let mut builder = GitignoreBuilder::new(dir);
+        let mut found = false;
+        for name in IGNORE_FILE_NAMES {
+            let ignore_file = dir.join(name);
+            if !ignore_file.is_file() {
+                continue;
+            }
+            found = true;
+            if let Some(e) = builder.add(&ignore_file) {
+                debug!("Failed to parse {}: {}", ignore_file.display(), e);
+            }
+        }
+        if !found {
+            return None;
+        }
+
+        builder
+            .build()
+            .map_err(|e| {
+                debug!(
+                    "Failed to build ignore matcher for {}: {}",
+                    dir.display(),
+                    e
+                )
+            })
+            .ok()
+    }
+
+    /// Root directory the rules were collected from
+    pub fn root_path(&self) -> &Path {
+        &self.root_path
+    }
+
+    /// Check whether the walker would skip `path`
+    /// `is_dir` should describe the path itself; for paths that no longer exist pass
+    /// `false` — ignored parent directories are still taken into account.
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        let Ok(relative) = path.strip_prefix(&self.root_path) else {
+            // Paths outside the root are not ours to judge
+            return false;
+        };
+
+        // The walker skips hidden files and directories
+        let hidden = relative.components().any(|component| {
+            matches!(component, std::path::Component::Normal(name)
+                if name.to_string_lossy().starts_with('.'))
+        });
+        if hidden {
+            return true;
+        }
+
+        if self.builtin.as_ref().is_some_and(|builtin| {
+            builtin
+                .matched_path_or_any_parents(path, is_dir)
+                .is_ignore()
+        }) {
+            return true;
+        }
+
+        // The deepest ignore file with an opinion wins, as in git
+        for matcher in &self.dir_matchers {
+            if !path.starts_with(matcher.path()) {
+                continue;
+            }
+            match matcher.matched_path_or_any_parents(path, is_dir) {
+                Match::Ignore(_) => return true,
+                Match::Whitelist(_) => return false,
+                Match::None => {}
+            }
+        }
+
+        false
+    }
+}
+
 /// Walks through a codebase directory and calls the provided closure for each file
 /// This is a simplified interface that handles the common pattern of walking files
 /// while respecting ignore patterns.
@@ -109,10 +334,78 @@ where
     Ok(())
 }
 
-/// Checks if a file extension is supported for code analysis
+/// Checks if a file extension is supported for code analysis, per the language registry
 pub fn is_supported_file_extension(path: &Path) -> bool {
-    matches!(
-        path.extension().and_then(|ext| ext.to_str()),
-        Some("rs" | "py" | "go")
-    )
+    LanguageRegistry::global().supports(path)
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::expect_used)]
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_ignore_rules_hash_tracks_ignore_files() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        std::fs::create_dir(temp_dir.path().join("src")).expect("Failed to create src");
+        let initial = ignore_rules_hash(temp_dir.path());
+        assert_eq!(initial, ignore_rules_hash(temp_dir.path()));
+
+        std::fs::write(temp_dir.path().join("src/.codexignore"), "generated/\n")
+            .expect("Failed to write .codexignore");
+        let with_rules = ignore_rules_hash(temp_dir.path());
+        assert_ne!(initial, with_rules);
+
+        std::fs::write(temp_dir.path().join("src/.codexignore"), "vendor/\n")
+            .expect("Failed to write .codexignore");
+        assert_ne!(with_rules, ignore_rules_hash(temp_dir.path()));
+    }
+
+    #[test]
+    fn test_configured_ignore_patterns() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let root = temp_dir.path();
+        std::fs::create_dir(root.join("generated")).expect("Failed to create generated");
+        std::fs::write(root.join("main.rs"), "fn main() {}\n").expect("write");
+        std::fs::write(root.join("generated/api.rs"), "fn api() {}\n").expect("write");
+        let initial = ignore_rules_hash(root);
+
+        std::fs::create_dir(root.join(".codex")).expect("Failed to create .codex");
+        std::fs::write(
+            IndexConfig::file_path(root),
+            "[ignore]\npatterns = [\"generated/\"]\n",
+        )
+        .expect("Failed to write config");
+        assert_ne!(initial, ignore_rules_hash(root));
+
+        let mut walked = Vec::new();
+        walk_codebase_files(root, |path| {
+            walked.push(path.strip_prefix(root).expect("outside root").to_path_buf());
+            Ok(true)
+        })
+        .expect("Failed to walk");
+        assert_eq!(walked, vec![PathBuf::from("main.rs")]);
+
+        let matcher = CodebaseIgnoreMatcher::new(root);
+        assert!(matcher.is_ignored(&root.join("generated/api.rs"), false));
+        assert!(!matcher.is_ignored(&root.join("main.rs"), false));
+    }
+
+    #[test]
+    fn test_yaml_manifests_are_opt_in() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let root = temp_dir.path();
+        std::fs::write(root.join("deployment.yaml"), "kind: Deployment\n").expect("write");
+        let matcher = CodebaseIgnoreMatcher::new(root);
+        assert!(matcher.is_ignored(&root.join("deployment.yaml"), false));
+        let initial = ignore_rules_hash(root);
+
+        std::fs::create_dir(root.join(".codex")).expect("Failed to create .codex");
+        std::fs::write(IndexConfig::file_path(root), "[manifests]\nyaml = true\n")
+            .expect("Failed to write config");
+        assert_ne!(initial, ignore_rules_hash(root));
+        let matcher = CodebaseIgnoreMatcher::new(root);
+        assert!(!matcher.is_ignored(&root.join("deployment.yaml"), false));
+    }
 }
@@ -0,0 +1,106 @@
+//! Bytes-in, JSON-out entry points to the symbol parser and chunker
+//! They need no filesystem, network or async runtime, so they're what a wasm32 build
+//! (`--no-default-features`, plus `wasm` for JavaScript bindings) offers a browser-based
+//! code viewer. Failures are returned as `{"error": "..."}` rather than panicking.
+
+use serde_json::Value;
+use serde_json::json;
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::wasm_bindgen;
+
+use crate::chunker::ChunkingOptions;
+use crate::chunker::HierarchicalChunker;
+use crate::symbol::Symbol;
+use crate::symbol::SymbolParser;
+
+/// Symbols in `source`, the contents of `file_path`, as `{"symbols": [...]}`
+/// The language is picked by the path's extension.
+#[cfg_attr(feature = "wasm", wasm_bindgen(js_name = parseSymbols))]
+pub fn parse_symbols(file_path: &str, source: &[u8]) -> String {
+    to_json(symbols(file_path, source).map(|symbols| json!({ "symbols": symbols })))
+}
+
+/// Chunks of `source`, the contents of `file_path`, as `{"chunks": [...]}`
+/// `options` is a JSON object with any `ChunkingOptions` fields; the rest (or all of
+/// them, for an empty string) keep their defaults.
+#[cfg_attr(feature = "wasm", wasm_bindgen(js_name = chunkSource))]
+pub fn chunk_source(file_path: &str, source: &[u8], options: &str) -> String {
+    let chunks = chunking_options(options).and_then(|options| {
+        let symbols = symbols(file_path, source)?;
+        HierarchicalChunker::new(options)?.chunk_symbols(&symbols)
+    });
+    to_json(chunks.map(|chunks| json!({ "chunks": chunks })))
+}
+
+fn symbols(file_path: &str, source: &[u8]) -> Result<Vec<Symbol>, anyhow::Error> {
+    let source = std::str::from_utf8(source)
+        .map_err(|e| anyhow::anyhow!("{file_path} is not valid UTF-8: {e}"))?;
+    SymbolParser::new()?.parse_source(file_path, source)
+}
+
+/// `options` laid over the default chunking options
+fn chunking_options(options: &str) -> Result<ChunkingOptions, anyhow::Error> {
+    let mut merged = serde_json::to_value(ChunkingOptions::default())?;
+    if !options.trim().is_empty() {
+        let Value::Object(overrides) = serde_json::from_str(options)
+            .map_err(|e| anyhow::anyhow!("Invalid chunking options: {e}"))?
+        else {
+            return Err(anyhow::anyhow!("Chunking options must be a JSON object"));
+        };
+        if let Value::Object(defaults) = &mut merged {
+            defaults.extend(overrides);
+        }
+    }
+    serde_json::from_value(merged).map_err(|e| anyhow::anyhow!("Invalid chunking options: {e}"))
+}
+
+fn to_json(result: Result<Value, anyhow::Error>) -> String {
+    match result {
+        Ok(value) => value.to_string(),
+        Err(e) => json!({ "error": e.to_string() }).to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::expect_used)]
+    use super::*;
+
+    const SOURCE: &str =
+        "struct Point {\n    x: i32,\n}\n\nfn origin() -> Point {\n    Point { x: 0 }\n}\n";
+
+    fn parse(json: &str) -> Value {
+        serde_json::from_str(json).expect("Output is JSON")
+    }
+
+    #[test]
+    fn parses_and_chunks_source_bytes() {
+        let symbols = parse(&parse_symbols("src/point.rs", SOURCE.as_bytes()));
+        let names: Vec<&str> = symbols["symbols"]
+            .as_array()
+            .expect("Symbols array")
+            .iter()
+            .filter_map(|symbol| symbol["name"].as_str())
+            .collect();
+        assert_eq!(names, ["Point", "origin"]);
+
+        let chunks = parse(&chunk_source(
+            "src/point.rs",
+            SOURCE.as_bytes(),
+            r#"{"min_lines_per_chunk": 1}"#,
+        ));
+        let chunks = chunks["chunks"].as_array().expect("Chunks array");
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[1]["symbol_name"], "origin");
+        assert_eq!(chunks[1]["file_path"], "src/point.rs");
+
+        let unsupported = parse(&parse_symbols("notes.txt", b"hello"));
+        assert_eq!(unsupported["error"], "Unsupported file extension: txt");
+        let invalid = parse(&chunk_source("src/point.rs", &[0xff], ""));
+        assert!(
+            invalid["error"]
+                .as_str()
+                .is_some_and(|e| e.contains("UTF-8"))
+        );
+    }
+}
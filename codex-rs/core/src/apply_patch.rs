@@ -51,6 +51,7 @@ pub(crate) async fn apply_patch(
                         output: FunctionCallOutputPayload {
                             content: "patch rejected by user".to_string(),
                             success: Some(false),
+                            metadata: None,
                         },
                     };
                 }
@@ -62,6 +63,7 @@ pub(crate) async fn apply_patch(
                 output: FunctionCallOutputPayload {
                     content: format!("patch rejected: {reason}"),
                     success: Some(false),
+                    metadata: None,
                 },
             };
         }
@@ -100,6 +102,7 @@ pub(crate) async fn apply_patch(
                 output: FunctionCallOutputPayload {
                     content: "patch rejected by user".to_string(),
                     success: Some(false),
+                    metadata: None,
                 },
             };
         }
@@ -220,6 +223,7 @@ pub(crate) async fn apply_patch(
             output: FunctionCallOutputPayload {
                 content: String::from_utf8_lossy(&stdout).to_string(),
                 success: None,
+                metadata: None,
             },
         },
         Err(e) => ResponseInputItem::FunctionCallOutput {
@@ -227,6 +231,7 @@ pub(crate) async fn apply_patch(
             output: FunctionCallOutputPayload {
                 content: format!("error: {e:#}, stderr: {}", String::from_utf8_lossy(&stderr)),
                 success: Some(false),
+                metadata: None,
             },
         },
     }
@@ -0,0 +1,300 @@
+// Poisoned mutex should fail the program
+#![allow(clippy::unwrap_used)]
+
+//! Commands the shell tool runs in the background, such as dev servers or long
+//! test runs. Each one gets a handle that the model passes to `poll_process`
+//! to read the output written since its last poll, and to `kill_process` to
+//! stop it.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+use std::time::Instant;
+
+use tokio::io::AsyncRead;
+use tokio::io::AsyncReadExt;
+use tokio::process::Child;
+use tokio::sync::Notify;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+
+/// Most background processes a session keeps at once
+const MAX_BACKGROUND_PROCESSES: usize = 8;
+
+/// Longest a poll waits for the process to exit
+const MAX_POLL_WAIT: Duration = Duration::from_secs(30);
+
+/// Unread output kept per process; the oldest output is dropped first
+const MAX_UNREAD_OUTPUT_BYTES: usize = 64 * 1024;
+
+/// How long a kill waits for the process to exit
+const KILL_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long an exited process's output is still read, in case it left
+/// children behind that hold its stdout or stderr open
+const OUTPUT_DRAIN_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// The background processes of a session. Processes still running when the
+/// session ends are killed.
+#[derive(Default)]
+pub(crate) struct BackgroundProcesses {
+    next_id: AtomicU64,
+    processes: Mutex<HashMap<String, BackgroundProcess>>,
+}
+
+struct BackgroundProcess {
+    command: String,
+    started: Instant,
+    output: Arc<Mutex<UnreadOutput>>,
+    /// Exit code once the process has exited, -1 if a signal ended it
+    exit_code: watch::Receiver<Option<i32>>,
+    kill: Arc<Notify>,
+}
+
+/// stdout and stderr written since the last poll, interleaved as they arrived
+#[derive(Default)]
+struct UnreadOutput {
+    bytes: Vec<u8>,
+    dropped_bytes: usize,
+}
+
+impl UnreadOutput {
+    fn push(&mut self, chunk: &[u8]) {
+        self.bytes.extend_from_slice(chunk);
+        if self.bytes.len() > MAX_UNREAD_OUTPUT_BYTES {
+            let excess = self.bytes.len() - MAX_UNREAD_OUTPUT_BYTES;
+            self.bytes.drain(..excess);
+            self.dropped_bytes += excess;
+        }
+    }
+
+    fn take(&mut self) -> (String, usize) {
+        let text = String::from_utf8_lossy(&self.bytes).into_owned();
+        self.bytes.clear();
+        (text, std::mem::take(&mut self.dropped_bytes))
+    }
+}
+
+impl BackgroundProcesses {
+    /// Refuses to start another process when the session already has
+    /// [`MAX_BACKGROUND_PROCESSES`].
+    pub(crate) fn check_capacity(&self) -> Result<(), String> {
+        let count = self.processes.lock().unwrap().len();
+        if count >= MAX_BACKGROUND_PROCESSES {
+            return Err(format!(
+                "{count} background processes are already running; poll the finished ones or \
+                 stop one with kill_process first"
+            ));
+        }
+        Ok(())
+    }
+
+    /// Takes over a freshly spawned `child`, whose stdout and stderr are piped,
+    /// and returns its handle.
+    pub(crate) fn start(&self, command: &[String], mut child: Child) -> Result<String, String> {
+        self.check_capacity()?;
+
+        let output = Arc::new(Mutex::new(UnreadOutput::default()));
+        let mut readers = Vec::new();
+        if let Some(stdout) = child.stdout.take() {
+            readers.push(spawn_reader(stdout, Arc::clone(&output)));
+        }
+        if let Some(stderr) = child.stderr.take() {
+            readers.push(spawn_reader(stderr, Arc::clone(&output)));
+        }
+
+        let (tx_exit_code, exit_code) = watch::channel(None);
+        let kill = Arc::new(Notify::new());
+        tokio::spawn({
+            let kill = Arc::clone(&kill);
+            async move {
+                let status = tokio::select! {
+                    status = child.wait() => status,
+                    () = kill.notified() => {
+                        let _ = child.start_kill();
+                        child.wait().await
+                    }
+                };
+                for reader in readers {
+                    let _ = tokio::time::timeout(OUTPUT_DRAIN_TIMEOUT, reader).await;
+                }
+                let code = status.ok().and_then(|status| status.code()).unwrap_or(-1);
+                let _ = tx_exit_code.send(Some(code));
+            }
+        });
+
+        let handle = format!("bg-{}", self.next_id.fetch_add(1, Ordering::Relaxed) + 1);
+        self.processes.lock().unwrap().insert(
+            handle.clone(),
+            BackgroundProcess {
+                command: command.join(" "),
+                started: Instant::now(),
+                output,
+                exit_code,
+                kill,
+            },
+        );
+        Ok(handle)
+    }
+
+    /// Reports whether the process is still running, with the output written
+    /// since the last poll. Waits up to `wait` (at most [`MAX_POLL_WAIT`]) for
+    /// the process to exit first. Exited processes are forgotten once polled.
+    pub(crate) async fn poll(&self, handle: &str, wait: Duration) -> Result<String, String> {
+        let mut exit_code = self.with_process(handle, |process| process.exit_code.clone())?;
+        if !wait.is_zero() {
+            let _ =
+                tokio::time::timeout(wait.min(MAX_POLL_WAIT), exit_code.wait_for(Option::is_some))
+                    .await;
+        }
+        self.report(handle, false)
+    }
+
+    /// Kills the process and reports the output it wrote since the last poll.
+    pub(crate) async fn kill(&self, handle: &str) -> Result<String, String> {
+        let (kill, mut exit_code) = self.with_process(handle, |process| {
+            (Arc::clone(&process.kill), process.exit_code.clone())
+        })?;
+        if exit_code.borrow().is_some() {
+            return self.report(handle, false);
+        }
+        kill.notify_one();
+        if tokio::time::timeout(KILL_TIMEOUT, exit_code.wait_for(Option::is_some))
+            .await
+            .is_err()
+        {
+            return Err(format!(
+                "process {handle} did not exit within {}s of being killed",
+                KILL_TIMEOUT.as_secs()
+            ));
+        }
+        self.report(handle, true)
+    }
+
+    fn with_process<T>(
+        &self,
+        handle: &str,
+        f: impl FnOnce(&BackgroundProcess) -> T,
+    ) -> Result<T, String> {
+        let processes = self.processes.lock().unwrap();
+        processes
+            .get(handle)
+            .map(f)
+            .ok_or_else(|| unknown_process(handle))
+    }
+
+    fn report(&self, handle: &str, killed: bool) -> Result<String, String> {
+        let mut processes = self.processes.lock().unwrap();
+        let process = processes
+            .get(handle)
+            .ok_or_else(|| unknown_process(handle))?;
+        let (output, dropped_bytes) = process.output.lock().unwrap().take();
+        let elapsed = process.started.elapsed().as_secs_f32();
+        let exit_code = *process.exit_code.borrow();
+        let mut report = match exit_code {
+            None => format!(
+                "process {handle} (`{}`) is still running after {elapsed:.1}s\n",
+                process.command
+            ),
+            Some(_) if killed => format!(
+                "process {handle} (`{}`) was killed after {elapsed:.1}s\n",
+                process.command
+            ),
+            Some(code) => format!(
+                "process {handle} (`{}`) exited with code {code} after {elapsed:.1}s\n",
+                process.command
+            ),
+        };
+        if exit_code.is_some() {
+            processes.remove(handle);
+        }
+
+        if dropped_bytes > 0 {
+            report.push_str(&format!(
+                "... ({dropped_bytes} bytes of earlier output were dropped)\n"
+            ));
+        }
+        if output.is_empty() {
+            report.push_str("(no new output)\n");
+        } else {
+            report.push_str(&output);
+        }
+        Ok(report)
+    }
+}
+
+impl Drop for BackgroundProcesses {
+    fn drop(&mut self) {
+        if let Ok(processes) = self.processes.get_mut() {
+            for process in processes.values() {
+                process.kill.notify_one();
+            }
+        }
+    }
+}
+
+fn unknown_process(handle: &str) -> String {
+    format!("no background process {handle}; it may have exited and already been polled")
+}
+
+fn spawn_reader<R>(mut reader: R, output: Arc<Mutex<UnreadOutput>>) -> JoinHandle<()>
+where
+    R: AsyncRead + Unpin + Send + 'static,
+{
+    tokio::spawn(async move {
+        let mut buf = [0u8; 8192];
+        loop {
+            match reader.read(&mut buf).await {
+                Ok(0) | Err(_) => break,
+                Ok(n) => output.lock().unwrap().push(&buf[..n]),
+            }
+        }
+    })
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::process::Stdio;
+    use tokio::process::Command;
+
+    fn spawn(script: &str) -> Child {
+        Command::new("sh")
+            .args(["-c", script])
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn polls_output_until_exit_and_kills_on_request() {
+        let processes = BackgroundProcesses::default();
+
+        let command = vec!["sh".to_string(), "-c".to_string(), "echo ready".to_string()];
+        let handle = processes
+            .start(&command, spawn("echo ready; sleep 0.2; echo done >&2"))
+            .unwrap();
+        let report = processes
+            .poll(&handle, Duration::from_secs(10))
+            .await
+            .unwrap();
+        assert!(report.starts_with("process bg-1 (`sh -c echo ready`) exited with code 0"));
+        assert!(report.ends_with("ready\ndone\n"), "{report}");
+        assert!(processes.poll(&handle, Duration::ZERO).await.is_err());
+
+        let server = processes
+            .start(&command, spawn("echo listening; sleep 30"))
+            .unwrap();
+        let report = processes.poll(&server, Duration::ZERO).await.unwrap();
+        assert!(report.contains("is still running"), "{report}");
+        let report = processes.kill(&server).await.unwrap();
+        assert!(report.contains("was killed"), "{report}");
+        assert!(processes.check_capacity().is_ok());
+    }
+}
@@ -30,7 +30,6 @@ use crate::util::backoff;
 pub(crate) async fn stream_chat_completions(
     prompt: &Prompt,
     model: &str,
-    include_plan_tool: bool,
     client: &reqwest::Client,
     provider: &ModelProviderInfo,
 ) -> Result<ResponseStream> {
@@ -96,7 +95,11 @@ pub(crate) async fn stream_chat_completions(
                 messages.push(json!({
                     "role": "tool",
                     "tool_call_id": call_id,
-                    "content": output.content,
+                    "content": if provider.structured_function_call_outputs {
+                        output.to_structured_value().to_string()
+                    } else {
+                        output.to_plain_string()
+                    },
                 }));
             }
             ResponseItem::Reasoning { .. } | ResponseItem::Other => {
@@ -106,7 +109,7 @@ pub(crate) async fn stream_chat_completions(
         }
     }
 
-    let tools_json = create_tools_json_for_chat_completions_api(prompt, model, include_plan_tool)?;
+    let tools_json = create_tools_json_for_chat_completions_api(prompt);
     let payload = json!({
         "model": model,
         "messages": messages,
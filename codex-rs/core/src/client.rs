@@ -23,6 +23,7 @@ use crate::client_common::Prompt;
 use crate::client_common::ResponseEvent;
 use crate::client_common::ResponseStream;
 use crate::client_common::ResponsesApiRequest;
+use crate::client_common::create_input_for_responses_api;
 use crate::client_common::create_reasoning_param_for_request;
 use crate::config::Config;
 use crate::config_types::ReasoningEffort as ReasoningEffortConfig;
@@ -77,7 +78,6 @@ impl ModelClient {
                 let response_stream = stream_chat_completions(
                     prompt,
                     &self.config.model,
-                    self.config.include_plan_tool,
                     &self.client,
                     &self.provider,
                 )
@@ -116,11 +116,7 @@ impl ModelClient {
         }
 
         let full_instructions = prompt.get_full_instructions(&self.config.model);
-        let tools_json = create_tools_json_for_responses_api(
-            prompt,
-            &self.config.model,
-            self.config.include_plan_tool,
-        )?;
+        let tools_json = create_tools_json_for_responses_api(prompt);
         let reasoning = create_reasoning_param_for_request(&self.config, self.effort, self.summary);
 
         // Request encrypted COT if we are not storing responses,
@@ -134,7 +130,10 @@ impl ModelClient {
         let payload = ResponsesApiRequest {
             model: &self.config.model,
             instructions: &full_instructions,
-            input: &prompt.input,
+            input: create_input_for_responses_api(
+                &prompt.input,
+                self.provider.structured_function_call_outputs,
+            )?,
             tools: &tools_json,
             tool_choice: "auto",
             parallel_tool_calls: false,
@@ -582,6 +581,7 @@ mod tests {
             request_max_retries: Some(0),
             stream_max_retries: Some(0),
             stream_idle_timeout_ms: Some(1000),
+            structured_function_call_outputs: false,
         };
 
         let events = collect_events(
@@ -641,6 +641,7 @@ mod tests {
             request_max_retries: Some(0),
             stream_max_retries: Some(0),
             stream_idle_timeout_ms: Some(1000),
+            structured_function_call_outputs: false,
         };
 
         let events = collect_events(&[sse1.as_bytes()], provider).await;
@@ -743,6 +744,7 @@ mod tests {
                 request_max_retries: Some(0),
                 stream_max_retries: Some(0),
                 stream_idle_timeout_ms: Some(1000),
+                structured_function_call_outputs: false,
             };
 
             let out = run_sse(evs, provider).await;
@@ -7,7 +7,6 @@ use codex_apply_patch::APPLY_PATCH_TOOL_INSTRUCTIONS;
 use futures::Stream;
 use serde::Serialize;
 use std::borrow::Cow;
-use std::collections::HashMap;
 use std::pin::Pin;
 use std::task::Context;
 use std::task::Poll;
@@ -28,10 +27,10 @@ pub struct Prompt {
     /// Whether to store response on server side (disable_response_storage = !store).
     pub store: bool,
 
-    /// Additional tools sourced from external MCP servers. Note each key is
-    /// the "fully qualified" tool name (i.e., prefixed with the server name),
-    /// which should be reported to the model in place of Tool::name.
-    pub extra_tools: HashMap<String, mcp_types::Tool>,
+    /// Tool definitions offered to the model, in Responses API format. These
+    /// come from the session's tool registry, including tools sourced from
+    /// external MCP servers.
+    pub tools: Vec<serde_json::Value>,
 
     /// Optional override for the built-in BASE_INSTRUCTIONS.
     pub base_instructions_override: Option<String>,
@@ -126,7 +125,7 @@ pub(crate) struct ResponsesApiRequest<'a> {
     // TODO(mbolin): ResponseItem::Other should not be serialized. Currently,
     // we code defensively to avoid this case, but perhaps we should use a
     // separate enum for serialization.
-    pub(crate) input: &'a Vec<ResponseItem>,
+    pub(crate) input: Vec<serde_json::Value>,
     pub(crate) tools: &'a [serde_json::Value],
     pub(crate) tool_choice: &'static str,
     pub(crate) parallel_tool_calls: bool,
@@ -139,6 +138,27 @@ pub(crate) struct ResponsesApiRequest<'a> {
 
 use crate::config::Config;
 
+/// Serializes the conversation for a Responses API request. Function call
+/// outputs are sent as `{ content, success, metadata }` objects when
+/// `structured_outputs` is set, and as bare strings otherwise.
+pub(crate) fn create_input_for_responses_api(
+    input: &[ResponseItem],
+    structured_outputs: bool,
+) -> Result<Vec<serde_json::Value>> {
+    let mut items = Vec::with_capacity(input.len());
+    for item in input {
+        let mut value = serde_json::to_value(item)?;
+        match item {
+            ResponseItem::FunctionCallOutput { output, .. } if structured_outputs => {
+                value["output"] = output.to_structured_value();
+            }
+            _ => {}
+        }
+        items.push(value);
+    }
+    Ok(items)
+}
+
 pub(crate) fn create_reasoning_param_for_request(
     config: &Config,
     effort: ReasoningEffortConfig,
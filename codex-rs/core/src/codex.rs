@@ -9,6 +9,7 @@ use std::sync::Arc;
 use std::sync::Mutex;
 use std::sync::atomic::AtomicU64;
 use std::time::Duration;
+use std::time::Instant;
 
 use async_channel::Receiver;
 use async_channel::Sender;
@@ -17,7 +18,6 @@ use codex_apply_patch::MaybeApplyPatchVerified;
 use codex_apply_patch::maybe_parse_apply_patch_verified;
 use futures::prelude::*;
 use mcp_types::CallToolResult;
-use serde::Serialize;
 use serde_json;
 use tokio::sync::Notify;
 use tokio::sync::oneshot;
@@ -32,10 +32,12 @@ use uuid::Uuid;
 use crate::apply_patch::convert_apply_patch_to_protocol;
 use crate::apply_patch::get_writable_roots;
 use crate::apply_patch::{self};
+use crate::background_process::BackgroundProcesses;
 use crate::client::ModelClient;
 use crate::client_common::Prompt;
 use crate::client_common::ResponseEvent;
 use crate::config::Config;
+use crate::config_types::RetrievalContext;
 use crate::config_types::ShellEnvironmentPolicy;
 use crate::conversation_history::ConversationHistory;
 use crate::error::CodexErr;
@@ -44,20 +46,35 @@ use crate::error::SandboxErr;
 use crate::exec::ExecParams;
 use crate::exec::ExecToolCallOutput;
 use crate::exec::SandboxType;
+use crate::exec::StdoutStream;
 use crate::exec::process_exec_tool_call;
+use crate::exec::spawn_exec_child;
 use crate::exec_env::create_env;
 use crate::mcp_connection_manager::McpConnectionManager;
 use crate::mcp_tool_call::handle_mcp_tool_call;
+use crate::models::CodebaseSearchToolCallParams;
 use crate::models::ContentItem;
+use crate::models::EditFileToolCallParams;
+use crate::models::FunctionCallOutputMetadata;
 use crate::models::FunctionCallOutputPayload;
 use crate::models::FuzzySearchToolCallParams;
+use crate::models::GrepToolCallParams;
+use crate::models::IndexCodebaseToolCallParams;
+use crate::models::KillProcessToolCallParams;
+use crate::models::ListDirToolCallParams;
 use crate::models::LocalShellAction;
+use crate::models::PollProcessToolCallParams;
 use crate::models::ReadFileToolCallParams;
 use crate::models::ReasoningItemReasoningSummary;
 use crate::models::RegexSearchToolCallParams;
 use crate::models::ResponseInputItem;
 use crate::models::ResponseItem;
 use crate::models::ShellToolCallParams;
+use crate::models::WriteFileToolCallParams;
+use crate::openai_tools::OpenAiTool;
+use crate::openai_tools::create_tool_from_struct;
+use crate::openai_tools::mcp_tool_to_openai_tool;
+use crate::plan_tool::PLAN_TOOL;
 use crate::plan_tool::handle_update_plan;
 use crate::project_doc::get_user_instructions;
 use crate::protocol::AgentMessageDeltaEvent;
@@ -80,10 +97,17 @@ use crate::protocol::SandboxPolicy;
 use crate::protocol::SessionConfiguredEvent;
 use crate::protocol::Submission;
 use crate::protocol::TaskCompleteEvent;
+use crate::retrieval_context::retrieve_context;
 use crate::rollout::RolloutRecorder;
 use crate::safety::SafetyCheck;
 use crate::safety::assess_command_safety;
+use crate::safety::assess_index_safety;
 use crate::shell;
+use crate::tool_exec_policy::ToolExecPolicy;
+use crate::tool_params::parse_tool_params;
+use crate::tool_registry::ToolCall;
+use crate::tool_registry::ToolRegistry;
+use crate::tool_registry::tool_handler;
 use crate::user_notification::UserNotification;
 use crate::util::backoff;
 
@@ -199,12 +223,20 @@ pub(crate) struct Session {
     pub(crate) approval_policy: AskForApproval,
     sandbox_policy: SandboxPolicy,
     pub(crate) shell_environment_policy: ShellEnvironmentPolicy,
+    tool_exec_policies: HashMap<String, ToolExecPolicy>,
+    retrieval_context: RetrievalContext,
     pub(crate) writable_roots: Mutex<Vec<PathBuf>>,
     disable_response_storage: bool,
 
     /// Manager for external MCP servers/tools.
     mcp_connection_manager: McpConnectionManager,
 
+    /// Tools offered to the model and the handlers for their calls.
+    tools: ToolRegistry,
+
+    /// Commands the shell tool started in the background.
+    background_processes: BackgroundProcesses,
+
     /// External notifier command (will be passed as args to exec()). When
     /// `None` this feature is disabled.
     notify: Option<Vec<String>>,
@@ -223,6 +255,36 @@ impl Session {
             .map(PathBuf::from)
             .map_or_else(|| self.cwd.clone(), |p| self.cwd.join(p))
     }
+
+    /// Builds the parameters for a command spawned by `tool`, applying the
+    /// tool's `[tools.<name>]` policy when one is configured.
+    pub(crate) fn exec_params(
+        &self,
+        tool: &str,
+        command: Vec<String>,
+        cwd: PathBuf,
+        timeout_ms: Option<u64>,
+    ) -> Result<ExecParams, String> {
+        let Some(policy) = self.tool_exec_policies.get(tool) else {
+            return Ok(ExecParams {
+                command,
+                cwd,
+                timeout_ms,
+                env: create_env(&self.shell_environment_policy),
+            });
+        };
+        policy.check_allowed(tool, &command)?;
+        let env_policy = policy
+            .shell_environment_policy
+            .as_ref()
+            .unwrap_or(&self.shell_environment_policy);
+        Ok(ExecParams {
+            timeout_ms: policy.timeout_ms(&command).or(timeout_ms),
+            env: create_env(env_policy),
+            command,
+            cwd,
+        })
+    }
 }
 
 /// Mutable state of the agent
@@ -687,6 +749,11 @@ async fn submission_loop(
                         });
                     }
                 }
+                let tools = build_tool_registry(
+                    &config.model,
+                    config.include_plan_tool,
+                    &mcp_connection_manager,
+                );
                 let default_shell = shell::default_user_shell().await; // default_shell is Unknown
                 debug!("default_shell: {:?}", default_shell);
                 sess = Some(Arc::new(Session {
@@ -698,9 +765,13 @@ async fn submission_loop(
                     approval_policy,
                     sandbox_policy,
                     shell_environment_policy: config.shell_environment_policy.clone(),
+                    tool_exec_policies: config.tool_exec_policies.clone(),
+                    retrieval_context: config.retrieval_context.clone(),
                     cwd,
                     writable_roots,
                     mcp_connection_manager,
+                    tools,
+                    background_processes: BackgroundProcesses::default(),
                     notify,
                     state: Mutex::new(state),
                     rollout: Mutex::new(rollout_recorder),
@@ -887,6 +958,22 @@ async fn run_task(sess: Arc<Session>, sub_id: String, input: Vec<InputItem>) {
         return;
     }
 
+    // Code retrieved for this input is sent just before it on every turn of
+    // the task, but not recorded, so later tasks don't carry stale context.
+    let retrieved_context = match retrieve_context(&sess.retrieval_context, &sess.cwd, &input).await
+    {
+        Ok(Some(context)) => {
+            sess.notify_background_event(&sub_id, context.summary).await;
+            Some(context.message)
+        }
+        Ok(None) => None,
+        Err(e) => {
+            warn!("skipping codebase context for this turn: {e:#}");
+            None
+        }
+    };
+    let retrieved_context_position = sess.state.lock().unwrap().history.contents().len();
+
     let initial_input_for_turn = ResponseInputItem::from(input);
     sess.record_conversation_items(&[initial_input_for_turn.clone().into()])
         .await;
@@ -908,8 +995,11 @@ async fn run_task(sess: Arc<Session>, sub_id: String, input: Vec<InputItem>) {
         // conversation history on each turn. The rollout file, however, should
         // only record the new items that originated in this turn so that it
         // represents an append-only log without duplicates.
-        let turn_input: Vec<ResponseItem> =
+        let mut turn_input: Vec<ResponseItem> =
             [sess.state.lock().unwrap().history.contents(), pending_input].concat();
+        if let Some(context) = &retrieved_context {
+            turn_input.insert(retrieved_context_position, context.clone());
+        }
 
         let turn_input_messages: Vec<String> = turn_input
             .iter()
@@ -959,6 +1049,29 @@ async fn run_task(sess: Arc<Session>, sub_id: String, input: Vec<InputItem>) {
                                 },
                             );
                         }
+                        (
+                            ResponseItem::FunctionCall { .. },
+                            Some(ResponseInputItem::FunctionCallOutputWithContent {
+                                call_id,
+                                output,
+                                content,
+                            }),
+                        ) => {
+                            items_to_record_in_conversation_history.push(item);
+                            items_to_record_in_conversation_history.push(
+                                ResponseItem::FunctionCallOutput {
+                                    call_id: call_id.clone(),
+                                    output: output.clone(),
+                                },
+                            );
+                            // Function call outputs are text only, so other
+                            // content reaches the model as a user message
+                            items_to_record_in_conversation_history.push(ResponseItem::Message {
+                                id: None,
+                                role: "user".to_string(),
+                                content: content.clone(),
+                            });
+                        }
                         (
                             ResponseItem::FunctionCall { .. },
                             Some(ResponseInputItem::McpToolCallOutput { call_id, result }),
@@ -981,7 +1094,11 @@ async fn run_task(sess: Arc<Session>, sub_id: String, input: Vec<InputItem>) {
                             items_to_record_in_conversation_history.push(
                                 ResponseItem::FunctionCallOutput {
                                     call_id: call_id.clone(),
-                                    output: FunctionCallOutputPayload { content, success },
+                                    output: FunctionCallOutputPayload {
+                                        content,
+                                        success,
+                                        metadata: None,
+                                    },
                                 },
                             );
                         }
@@ -1053,12 +1170,11 @@ async fn run_turn(
     sub_id: String,
     input: Vec<ResponseItem>,
 ) -> CodexResult<Vec<ProcessedResponseItem>> {
-    let extra_tools = sess.mcp_connection_manager.list_all_tools();
     let prompt = Prompt {
         input,
         user_instructions: sess.user_instructions.clone(),
         store: !sess.disable_response_storage,
-        extra_tools,
+        tools: sess.tools.specs().to_vec(),
         base_instructions_override: sess.base_instructions.clone(),
     };
 
@@ -1154,6 +1270,7 @@ async fn try_run_turn(
                 output: FunctionCallOutputPayload {
                     content: "aborted".to_string(),
                     success: Some(false),
+                    metadata: None,
                 },
             })
             .collect::<Vec<_>>()
@@ -1289,6 +1406,7 @@ async fn handle_response_item(
                 command: action.command,
                 workdir: action.working_directory,
                 timeout: action.timeout_ms,
+                background: action.background,
                 explanation: None,
             };
             let effective_call_id = match (call_id, id) {
@@ -1301,21 +1419,32 @@ async fn handle_response_item(
                         output: FunctionCallOutputPayload {
                             content: "LocalShellCall without call_id or id".to_string(),
                             success: None,
+                            metadata: None,
                         },
                     }));
                 }
             };
 
-            let exec_params = to_exec_params(params, sess);
-            Some(
-                handle_container_exec_with_params(
-                    exec_params,
-                    sess,
-                    sub_id.to_string(),
-                    effective_call_id,
-                )
-                .await,
-            )
+            match params.to_exec_params("local_shell", sess) {
+                Ok(exec_params) => Some(
+                    handle_container_exec_with_params(
+                        exec_params,
+                        params.background.unwrap_or(false),
+                        sess,
+                        sub_id.to_string(),
+                        effective_call_id,
+                    )
+                    .await,
+                ),
+                Err(err) => Some(ResponseInputItem::FunctionCallOutput {
+                    call_id: effective_call_id,
+                    output: FunctionCallOutputPayload {
+                        content: err,
+                        success: Some(false),
+                        metadata: None,
+                    },
+                }),
+            }
         }
         ResponseItem::FunctionCallOutput { .. } => {
             debug!("unexpected FunctionCallOutput from stream");
@@ -1333,234 +1462,617 @@ async fn handle_function_call(
     arguments: String,
     call_id: String,
 ) -> ResponseInputItem {
-    match name.as_str() {
-        "container.exec" | "execute_command" => {
-            let params = match parse_container_exec_arguments(arguments, sess, &call_id) {
-                Ok(params) => params,
-                Err(output) => {
-                    return *output;
-                }
-            };
-            handle_container_exec_with_params(params, sess, sub_id, call_id).await
+    match sess.tools.handler(&name) {
+        Some(handler) => {
+            let started = Instant::now();
+            let mut output = handler(
+                sess,
+                ToolCall {
+                    sub_id,
+                    name,
+                    call_id,
+                    arguments,
+                },
+            )
+            .await;
+            // Every handled call, MCP and file tools included, reports its wall time.
+            // Exec calls already carry metadata with the command's own run time, which
+            // is kept.
+            if let ResponseInputItem::FunctionCallOutput {
+                output: payload, ..
+            }
+            | ResponseInputItem::FunctionCallOutputWithContent {
+                output: payload, ..
+            } = &mut output
+            {
+                payload.metadata.get_or_insert_with(|| {
+                    FunctionCallOutputMetadata::default().with_duration(started.elapsed())
+                });
+            }
+            output
         }
-        "read_file" => {
-            let params = match parse_read_file_arguments(arguments, &call_id) {
-                Ok(params) => params,
-                Err(output) => {
-                    return *output;
-                }
-            };
-            let exec_params = params.to_exec_params(sess);
-            handle_container_exec_with_params(exec_params, sess, sub_id, call_id).await
+        None => {
+            // Unknown function: reply with structured failure so the model can adapt.
+            ResponseInputItem::FunctionCallOutput {
+                call_id,
+                output: FunctionCallOutputPayload {
+                    content: format!("unsupported call: {name}"),
+                    success: None,
+                    metadata: None,
+                },
+            }
         }
-        "regex_search" => {
-            let params = match parse_regex_search_arguments(arguments, &call_id) {
-                Ok(params) => params,
-                Err(output) => {
-                    return *output;
-                }
-            };
-            let exec_params = params.to_exec_params(sess);
-            handle_container_exec_with_params(exec_params, sess, sub_id, call_id).await
+    }
+}
+
+/// Builds the tools offered to the model in a session, each registered with the
+/// handler for its calls. MCP tools are registered under their fully qualified names.
+fn build_tool_registry(
+    model: &str,
+    include_plan_tool: bool,
+    mcp_connection_manager: &McpConnectionManager,
+) -> ToolRegistry {
+    // Codex models get the built-in local_shell tool instead of the function tools
+    let codex_model = model.starts_with("codex");
+    let mut registry = ToolRegistry::new(!codex_model);
+    if codex_model {
+        registry.advertise(&OpenAiTool::LocalShell {});
+    }
+
+    registry.register_function::<ShellToolCallParams>(
+        "execute_command",
+        "Runs a shell command, and returns its output.",
+        tool_handler(|sess, call| Box::pin(handle_shell_tool(sess, call))),
+    );
+    registry.register_hidden(
+        "container.exec",
+        tool_handler(|sess, call| Box::pin(handle_shell_tool(sess, call))),
+    );
+    // Advertised to every model, since local_shell can start background processes too
+    registry.register(
+        "poll_process",
+        &create_tool_from_struct::<PollProcessToolCallParams>(
+            "poll_process",
+            "Check on a background process started by the shell tool: whether it is still running, and the output it wrote since the last poll. Set wait_ms to wait for it to exit first.",
+        ),
+        tool_handler(|sess, call| Box::pin(handle_poll_process_tool(sess, call))),
+    );
+    registry.register(
+        "kill_process",
+        &create_tool_from_struct::<KillProcessToolCallParams>(
+            "kill_process",
+            "Stop a background process started by the shell tool, returning the output it wrote since the last poll.",
+        ),
+        tool_handler(|sess, call| Box::pin(handle_kill_process_tool(sess, call))),
+    );
+    registry.register_function::<ReadFileToolCallParams>(
+        "read_file",
+        "Read the contents of a file at the specified path. Output is limited by max_output_tokens or max_bytes when given; a cut-off read ends with the line to continue reading from. PNG, JPEG, GIF and WebP images are attached for you to look at.",
+        tool_handler(|sess, call| Box::pin(handle_read_file_tool(sess, call))),
+    );
+    registry.register_function::<WriteFileToolCallParams>(
+        "write_file",
+        "Create a file with the given content, or replace an existing one when overwrite is set. The path must be inside the workspace. Prefer this over shell redirection or heredocs for creating files.",
+        tool_handler(|sess, call| Box::pin(handle_write_file_tool(sess, call))),
+    );
+    registry.register_function::<EditFileToolCallParams>(
+        "edit_file",
+        "Edit an existing file, either by applying a unified diff or by replacing exact snippets (search/replace). Hunks or snippets that don't match are reported without changing the file. Set dry_run to preview the resulting diff first.",
+        tool_handler(|sess, call| Box::pin(handle_edit_file_tool(sess, call))),
+    );
+    registry.register_function::<ListDirToolCallParams>(
+        "list_dir",
+        "List a directory as a tree of names, kinds and sizes, skipping files ignored by .gitignore, .ignore, .codexignore and common build and cache directories. Prefer this over `ls -R` or `find` for exploring the workspace.",
+        tool_handler(|sess, call| Box::pin(handle_list_dir_tool(sess, call))),
+    );
+    registry.register_function::<RegexSearchToolCallParams>(
+        "regex_search",
+        "Searches for regex patterns in files using ripgrep. Returns up to 50 matches with support for case sensitivity, file inclusion/exclusion patterns.",
+        tool_handler(|sess, call| Box::pin(handle_regex_search_tool(sess, call))),
+    );
+    registry.register_function::<GrepToolCallParams>(
+        "grep_search",
+        "Search file contents for a regular expression, skipping files ignored by .gitignore, .ignore, .codexignore and common build and cache directories. Returns matching lines as path:line: text, up to max_results.",
+        tool_handler(|sess, call| Box::pin(handle_grep_tool(sess, call))),
+    );
+    registry.register_function::<FuzzySearchToolCallParams>(
+        "file_search",
+        "Fast file search based on fuzzy matching against file path. Use if you know part of the file path but don't know where it's located exactly. Response will be capped to 10 results. Make your query more specific if need to filter results further.",
+        tool_handler(|sess, call| Box::pin(handle_file_search_tool(sess, call))),
+    );
+    registry.register_function::<CodebaseSearchToolCallParams>(
+        "codebase_search",
+        "Semantic search over the indexed workspace. Finds functions, types and other code by meaning rather than exact text, and returns the best matching chunks with their file paths, line ranges and scores. Use it to locate code when you don't know the exact names to grep for. Requires the workspace to be indexed; if it isn't, or results look stale, run index_workspace first.",
+        tool_handler(|sess, call| Box::pin(handle_codebase_search_tool(sess, call))),
+    );
+    registry.register_function::<IndexCodebaseToolCallParams>(
+        "index_workspace",
+        "Build the semantic index that codebase_search uses, or bring it up to date with the files changed since it was last indexed. Use it when codebase_search reports a missing index or returns stale results. Indexing a large workspace for the first time can take several minutes.",
+        tool_handler(|sess, call| Box::pin(handle_index_workspace_tool(sess, call))),
+    );
+
+    let update_plan = tool_handler(|sess, call| {
+        Box::pin(handle_update_plan(
+            sess,
+            call.arguments,
+            call.sub_id,
+            call.call_id,
+        ))
+    });
+    if include_plan_tool {
+        registry.register("update_plan", &*PLAN_TOOL, update_plan);
+    } else {
+        registry.register_hidden("update_plan", update_plan);
+    }
+
+    for (name, tool) in mcp_connection_manager.list_all_tools() {
+        let Some((server, tool_name)) = mcp_connection_manager.parse_tool_name(&name) else {
+            continue;
+        };
+        let spec = mcp_tool_to_openai_tool(name.clone(), tool);
+        let handler = tool_handler(move |sess, call| {
+            let server = server.clone();
+            let tool_name = tool_name.clone();
+            Box::pin(async move {
+                // TODO(mbolin): Determine appropriate timeout for tool call.
+                let timeout = None;
+                handle_mcp_tool_call(
+                    sess,
+                    &call.sub_id,
+                    call.call_id,
+                    server,
+                    tool_name,
+                    call.arguments,
+                    timeout,
+                )
+                .await
+            })
+        });
+        registry.register(&name, &spec, handler);
+    }
+
+    registry
+}
+
+async fn handle_shell_tool(sess: &Session, call: ToolCall) -> ResponseInputItem {
+    let ToolCall {
+        sub_id,
+        name,
+        call_id,
+        arguments,
+    } = call;
+    let params = match parse_tool_params::<ShellToolCallParams>(&arguments, &call_id) {
+        Ok(params) => params,
+        Err(output) => {
+            return *output;
         }
-        "file_search" => {
-            let params = match parse_fuzzy_search_arguments(arguments, &call_id) {
-                Ok(params) => params,
-                Err(output) => {
-                    return *output;
-                }
-            };
+    };
+    match params.to_exec_params(&name, sess) {
+        Ok(exec_params) => {
+            let background = params.background.unwrap_or(false);
+            handle_container_exec_with_params(exec_params, background, sess, sub_id, call_id).await
+        }
+        Err(err) => ResponseInputItem::FunctionCallOutput {
+            call_id,
+            output: FunctionCallOutputPayload {
+                content: err,
+                success: Some(false),
+                metadata: None,
+            },
+        },
+    }
+}
+
+async fn handle_poll_process_tool(sess: &Session, call: ToolCall) -> ResponseInputItem {
+    let ToolCall {
+        call_id, arguments, ..
+    } = call;
+    let params = match parse_tool_params::<PollProcessToolCallParams>(&arguments, &call_id) {
+        Ok(params) => params,
+        Err(output) => {
+            return *output;
+        }
+    };
+    let wait = Duration::from_millis(params.wait_ms.unwrap_or(0));
+    process_report_output(
+        call_id,
+        sess.background_processes.poll(&params.handle, wait).await,
+    )
+}
+
+async fn handle_kill_process_tool(sess: &Session, call: ToolCall) -> ResponseInputItem {
+    let ToolCall {
+        call_id, arguments, ..
+    } = call;
+    let params = match parse_tool_params::<KillProcessToolCallParams>(&arguments, &call_id) {
+        Ok(params) => params,
+        Err(output) => {
+            return *output;
+        }
+    };
+    process_report_output(
+        call_id,
+        sess.background_processes.kill(&params.handle).await,
+    )
+}
+
+fn process_report_output(call_id: String, report: Result<String, String>) -> ResponseInputItem {
+    let success = report.is_ok();
+    ResponseInputItem::FunctionCallOutput {
+        call_id,
+        output: FunctionCallOutputPayload {
+            content: report.unwrap_or_else(|err| err),
+            success: Some(success),
+            metadata: None,
+        },
+    }
+}
+
+async fn handle_read_file_tool(sess: &Session, call: ToolCall) -> ResponseInputItem {
+    let ToolCall {
+        call_id, arguments, ..
+    } = call;
+    let params = match parse_tool_params::<ReadFileToolCallParams>(&arguments, &call_id) {
+        Ok(params) => params,
+        Err(output) => {
+            return *output;
+        }
+    };
 
-            // Execute fuzzy search directly using the library
-            match params.execute(sess).await {
-                Ok(output) => ResponseInputItem::FunctionCallOutput {
+    // Read the file in-process instead of shelling out to cat/sed
+    match params.execute(sess) {
+        Ok(output) => {
+            let payload = FunctionCallOutputPayload {
+                content: output.text,
+                success: Some(true),
+                metadata: output
+                    .next_start_line
+                    .map(|line| FunctionCallOutputMetadata {
+                        next_start_line: Some(line as u64),
+                        ..Default::default()
+                    }),
+            };
+            match output.image {
+                Some(image) => ResponseInputItem::FunctionCallOutputWithContent {
                     call_id,
-                    output: FunctionCallOutputPayload {
-                        content: output,
-                        success: Some(true),
-                    },
+                    output: payload,
+                    content: vec![image],
                 },
-                Err(err) => ResponseInputItem::FunctionCallOutput {
+                None => ResponseInputItem::FunctionCallOutput {
                     call_id,
-                    output: FunctionCallOutputPayload {
-                        content: format!("fuzzy_search error: {err}"),
-                        success: Some(false),
-                    },
+                    output: payload,
                 },
             }
         }
-        "update_plan" => handle_update_plan(sess, arguments, sub_id, call_id).await,
-        _ => {
-            match sess.mcp_connection_manager.parse_tool_name(&name) {
-                Some((server, tool_name)) => {
-                    // TODO(mbolin): Determine appropriate timeout for tool call.
-                    let timeout = None;
-                    handle_mcp_tool_call(
-                        sess, &sub_id, call_id, server, tool_name, arguments, timeout,
-                    )
-                    .await
-                }
-                None => {
-                    // Unknown function: reply with structured failure so the model can adapt.
-                    ResponseInputItem::FunctionCallOutput {
-                        call_id,
-                        output: FunctionCallOutputPayload {
-                            content: format!("unsupported call: {name}"),
-                            success: None,
-                        },
-                    }
-                }
-            }
-        }
+        Err(err) => ResponseInputItem::FunctionCallOutput {
+            call_id,
+            output: FunctionCallOutputPayload {
+                content: format!("read_file error: {err}"),
+                success: Some(false),
+                metadata: None,
+            },
+        },
     }
 }
 
-fn to_exec_params(params: ShellToolCallParams, sess: &Session) -> ExecParams {
-    ExecParams {
-        command: params.command,
-        cwd: sess.resolve_path(params.workdir.clone()),
-        timeout_ms: params.timeout,
-        env: create_env(&sess.shell_environment_policy),
+async fn handle_regex_search_tool(sess: &Session, call: ToolCall) -> ResponseInputItem {
+    let ToolCall {
+        sub_id,
+        name,
+        call_id,
+        arguments,
+    } = call;
+    let params = match parse_tool_params::<RegexSearchToolCallParams>(&arguments, &call_id) {
+        Ok(params) => params,
+        Err(output) => {
+            return *output;
+        }
+    };
+    match params.to_exec_params(&name, sess) {
+        Ok(exec_params) => {
+            handle_container_exec_with_params(exec_params, false, sess, sub_id, call_id).await
+        }
+        Err(err) => ResponseInputItem::FunctionCallOutput {
+            call_id,
+            output: FunctionCallOutputPayload {
+                content: err,
+                success: Some(false),
+                metadata: None,
+            },
+        },
     }
 }
 
-fn parse_container_exec_arguments(
-    arguments: String, // json string parameters from assistant message
-    sess: &Session,
-    call_id: &str,
-) -> Result<ExecParams, Box<ResponseInputItem>> {
-    // parse command
-    match serde_json::from_str::<ShellToolCallParams>(&arguments) {
-        Ok(shell_tool_call_params) => Ok(shell_tool_call_params.to_exec_params(sess)),
-        Err(e) => {
-            // allow model to re-sample
-            let output = ResponseInputItem::FunctionCallOutput {
-                call_id: call_id.to_string(),
-                output: FunctionCallOutputPayload {
-                    content: format!("failed to parse function arguments: {e}"),
-                    success: None,
-                },
-            };
-            Err(Box::new(output))
+async fn handle_file_search_tool(sess: &Session, call: ToolCall) -> ResponseInputItem {
+    let ToolCall {
+        call_id, arguments, ..
+    } = call;
+    let params = match parse_tool_params::<FuzzySearchToolCallParams>(&arguments, &call_id) {
+        Ok(params) => params,
+        Err(output) => {
+            return *output;
         }
+    };
+
+    // Execute fuzzy search directly using the library
+    match params.execute(sess).await {
+        Ok(output) => ResponseInputItem::FunctionCallOutput {
+            call_id,
+            output: FunctionCallOutputPayload {
+                content: output,
+                success: Some(true),
+                metadata: None,
+            },
+        },
+        Err(err) => ResponseInputItem::FunctionCallOutput {
+            call_id,
+            output: FunctionCallOutputPayload {
+                content: format!("fuzzy_search error: {err}"),
+                success: Some(false),
+                metadata: None,
+            },
+        },
     }
 }
 
-// parse_read_file_arguments parses json parameters from assistant message
-// we will parse ReadFileToolCallParams to ExecParams to reuse command execution logic
-fn parse_read_file_arguments(
-    arguments: String, // json string parameters from assistant message
-    call_id: &str,
-) -> Result<ReadFileToolCallParams, Box<ResponseInputItem>> {
-    // parse read_file parameters
-    match serde_json::from_str::<ReadFileToolCallParams>(&arguments) {
-        Ok(read_file_params) => {
-            // Validate the parameters
-            match read_file_params.validate() {
-                Ok(()) => Ok(read_file_params),
-                Err(validation_error) => {
-                    // Return validation error to allow model to re-sample
-                    let output = ResponseInputItem::FunctionCallOutput {
-                        call_id: call_id.to_string(),
-                        output: FunctionCallOutputPayload {
-                            content: format!("validation error: {validation_error}"),
-                            success: None,
-                        },
-                    };
-                    Err(Box::new(output))
-                }
-            }
+async fn handle_write_file_tool(sess: &Session, call: ToolCall) -> ResponseInputItem {
+    let ToolCall {
+        sub_id,
+        call_id,
+        arguments,
+        ..
+    } = call;
+    let params = match parse_tool_params::<WriteFileToolCallParams>(&arguments, &call_id) {
+        Ok(params) => params,
+        Err(output) => {
+            return *output;
         }
-        Err(e) => {
-            // allow model to re-sample
-            let output = ResponseInputItem::FunctionCallOutput {
-                call_id: call_id.to_string(),
-                output: FunctionCallOutputPayload {
-                    content: format!("failed to parse function arguments: {e}"),
-                    success: None,
-                },
-            };
-            Err(Box::new(output))
+    };
+
+    // Route the write through apply_patch for approval and writable-root checks
+    match params.to_apply_patch_action(&sess.cwd) {
+        Ok(action) => apply_patch::apply_patch(sess, sub_id, call_id, action).await,
+        Err(err) => ResponseInputItem::FunctionCallOutput {
+            call_id,
+            output: FunctionCallOutputPayload {
+                content: format!("write_file error: {err}"),
+                success: Some(false),
+                metadata: None,
+            },
+        },
+    }
+}
+
+async fn handle_edit_file_tool(sess: &Session, call: ToolCall) -> ResponseInputItem {
+    let ToolCall {
+        sub_id,
+        call_id,
+        arguments,
+        ..
+    } = call;
+    let params = match parse_tool_params::<EditFileToolCallParams>(&arguments, &call_id) {
+        Ok(params) => params,
+        Err(output) => {
+            return *output;
         }
+    };
+
+    if params.is_dry_run() {
+        let (content, success) = match params.preview(&sess.cwd) {
+            Ok(preview) => (preview, true),
+            Err(err) => (format!("edit_file error: {err}"), false),
+        };
+        return ResponseInputItem::FunctionCallOutput {
+            call_id,
+            output: FunctionCallOutputPayload {
+                content,
+                success: Some(success),
+                metadata: None,
+            },
+        };
+    }
+
+    // Route the edit through apply_patch for approval and writable-root checks
+    match params.to_apply_patch_action(&sess.cwd) {
+        Ok(action) => apply_patch::apply_patch(sess, sub_id, call_id, action).await,
+        Err(err) => ResponseInputItem::FunctionCallOutput {
+            call_id,
+            output: FunctionCallOutputPayload {
+                content: format!("edit_file error: {err}"),
+                success: Some(false),
+                metadata: None,
+            },
+        },
     }
 }
 
-// parse_regex_search_arguments parses json parameters from assistant message
-// we will parse RegexSearchToolCallParams to ExecParams to reuse command execution logic
-fn parse_regex_search_arguments(
-    arguments: String, // json string parameters from assistant message
-    call_id: &str,
-) -> Result<RegexSearchToolCallParams, Box<ResponseInputItem>> {
-    // parse regex_search parameters
-    match serde_json::from_str::<RegexSearchToolCallParams>(&arguments) {
-        Ok(regex_search_params) => {
-            // Validate the parameters
-            match regex_search_params.validate() {
-                Ok(()) => Ok(regex_search_params),
-                Err(validation_error) => {
-                    // Return validation error to allow model to re-sample
-                    let output = ResponseInputItem::FunctionCallOutput {
-                        call_id: call_id.to_string(),
-                        output: FunctionCallOutputPayload {
-                            content: format!("validation error: {validation_error}"),
-                            success: None,
-                        },
-                    };
-                    Err(Box::new(output))
-                }
-            }
+async fn handle_list_dir_tool(sess: &Session, call: ToolCall) -> ResponseInputItem {
+    let ToolCall {
+        call_id, arguments, ..
+    } = call;
+    let params = match parse_tool_params::<ListDirToolCallParams>(&arguments, &call_id) {
+        Ok(params) => params,
+        Err(output) => {
+            return *output;
         }
-        Err(e) => {
-            // allow model to re-sample
-            let output = ResponseInputItem::FunctionCallOutput {
-                call_id: call_id.to_string(),
-                output: FunctionCallOutputPayload {
-                    content: format!("failed to parse function arguments: {e}"),
-                    success: None,
-                },
-            };
-            Err(Box::new(output))
+    };
+
+    // Walk the directory in-process with the workspace ignore rules
+    match params.execute(sess).await {
+        Ok(output) => ResponseInputItem::FunctionCallOutput {
+            call_id,
+            output: FunctionCallOutputPayload {
+                content: output,
+                success: Some(true),
+                metadata: None,
+            },
+        },
+        Err(err) => ResponseInputItem::FunctionCallOutput {
+            call_id,
+            output: FunctionCallOutputPayload {
+                content: format!("list_dir error: {err}"),
+                success: Some(false),
+                metadata: None,
+            },
+        },
+    }
+}
+
+async fn handle_grep_tool(sess: &Session, call: ToolCall) -> ResponseInputItem {
+    let ToolCall {
+        call_id, arguments, ..
+    } = call;
+    let params = match parse_tool_params::<GrepToolCallParams>(&arguments, &call_id) {
+        Ok(params) => params,
+        Err(output) => {
+            return *output;
         }
+    };
+
+    // Search in-process over the ignore-aware codebase walk
+    match params.execute(sess).await {
+        Ok(output) => ResponseInputItem::FunctionCallOutput {
+            call_id,
+            output: FunctionCallOutputPayload {
+                content: output,
+                success: Some(true),
+                metadata: None,
+            },
+        },
+        Err(err) => ResponseInputItem::FunctionCallOutput {
+            call_id,
+            output: FunctionCallOutputPayload {
+                content: format!("grep_search error: {err}"),
+                success: Some(false),
+                metadata: None,
+            },
+        },
     }
 }
 
-// parse_fuzzy_search_arguments parses json parameters from assistant message
-// we will parse FuzzySearchToolCallParams to ExecParams to reuse command execution logic
-fn parse_fuzzy_search_arguments(
-    arguments: String, // json string parameters from assistant message
-    call_id: &str,
-) -> Result<FuzzySearchToolCallParams, Box<ResponseInputItem>> {
-    // parse fuzzy_search parameters
-    match serde_json::from_str::<FuzzySearchToolCallParams>(&arguments) {
-        Ok(fuzzy_search_params) => {
-            // Validate the parameters
-            match fuzzy_search_params.validate() {
-                Ok(()) => Ok(fuzzy_search_params),
-                Err(validation_error) => {
-                    // Return validation error to allow model to re-sample
-                    let output = ResponseInputItem::FunctionCallOutput {
-                        call_id: call_id.to_string(),
+async fn handle_index_workspace_tool(sess: &Session, call: ToolCall) -> ResponseInputItem {
+    let ToolCall {
+        sub_id,
+        call_id,
+        arguments,
+        ..
+    } = call;
+    let params = match parse_tool_params::<IndexCodebaseToolCallParams>(&arguments, &call_id) {
+        Ok(params) => params,
+        Err(output) => {
+            return *output;
+        }
+    };
+
+    // Approval is asked for the equivalent CLI invocation, so approving it for the
+    // session also covers later calls
+    let command = vec![
+        "codebase-search".to_string(),
+        "index-codebase".to_string(),
+        sess.cwd.display().to_string(),
+    ];
+    let safety = {
+        let state = sess.state.lock().unwrap();
+        assess_index_safety(
+            &command,
+            sess.approval_policy,
+            &sess.sandbox_policy,
+            &state.approved_commands,
+        )
+    };
+    match safety {
+        SafetyCheck::AutoApprove { .. } => (),
+        SafetyCheck::AskUser => {
+            let rx_approve = sess
+                .request_command_approval(
+                    sub_id.clone(),
+                    call_id.clone(),
+                    command.clone(),
+                    sess.cwd.clone(),
+                    Some(
+                        "Indexing sends the workspace's files to the embedding provider and \
+                         writes the index state under .codex/"
+                            .to_string(),
+                    ),
+                )
+                .await;
+            match rx_approve.await.unwrap_or_default() {
+                ReviewDecision::Approved => (),
+                ReviewDecision::ApprovedForSession => sess.add_approved_command(command),
+                ReviewDecision::Denied | ReviewDecision::Abort => {
+                    return ResponseInputItem::FunctionCallOutput {
+                        call_id,
                         output: FunctionCallOutputPayload {
-                            content: format!("validation error: {validation_error}"),
+                            content: "index_workspace rejected by user".to_string(),
                             success: None,
+                            metadata: None,
                         },
                     };
-                    Err(Box::new(output))
                 }
             }
         }
-        Err(e) => {
-            // allow model to re-sample
-            let output = ResponseInputItem::FunctionCallOutput {
-                call_id: call_id.to_string(),
+        SafetyCheck::Reject { reason } => {
+            return ResponseInputItem::FunctionCallOutput {
+                call_id,
                 output: FunctionCallOutputPayload {
-                    content: format!("failed to parse function arguments: {e}"),
+                    content: format!("index_workspace rejected: {reason}"),
                     success: None,
+                    metadata: None,
                 },
             };
-            Err(Box::new(output))
         }
     }
+
+    match params.execute(sess, &sub_id).await {
+        Ok(output) => ResponseInputItem::FunctionCallOutput {
+            call_id,
+            output: FunctionCallOutputPayload {
+                content: output,
+                success: Some(true),
+                metadata: None,
+            },
+        },
+        Err(err) => ResponseInputItem::FunctionCallOutput {
+            call_id,
+            output: FunctionCallOutputPayload {
+                content: format!("index_workspace error: {err:#}"),
+                success: Some(false),
+                metadata: None,
+            },
+        },
+    }
+}
+
+async fn handle_codebase_search_tool(sess: &Session, call: ToolCall) -> ResponseInputItem {
+    let ToolCall {
+        call_id, arguments, ..
+    } = call;
+    let params = match parse_tool_params::<CodebaseSearchToolCallParams>(&arguments, &call_id) {
+        Ok(params) => params,
+        Err(output) => {
+            return *output;
+        }
+    };
+
+    // Query the semantic index directly using the library
+    match params.execute(sess).await {
+        Ok(output) => ResponseInputItem::FunctionCallOutput {
+            call_id,
+            output: FunctionCallOutputPayload {
+                content: output,
+                success: Some(true),
+                metadata: None,
+            },
+        },
+        Err(err) => ResponseInputItem::FunctionCallOutput {
+            call_id,
+            output: FunctionCallOutputPayload {
+                content: format!("codebase_search error: {err:#}"),
+                success: Some(false),
+                metadata: None,
+            },
+        },
+    }
 }
 
 fn maybe_run_with_user_profile(params: ExecParams, sess: &Session) -> ExecParams {
@@ -1577,10 +2089,28 @@ fn maybe_run_with_user_profile(params: ExecParams, sess: &Session) -> ExecParams
 
 async fn handle_container_exec_with_params(
     params: ExecParams,
+    background: bool,
     sess: &Session,
     sub_id: String,
     call_id: String,
 ) -> ResponseInputItem {
+    // Refuse a background command up front rather than after approval
+    if background {
+        match sess.background_processes.check_capacity() {
+            Ok(()) => {}
+            Err(err) => {
+                return ResponseInputItem::FunctionCallOutput {
+                    call_id,
+                    output: FunctionCallOutputPayload {
+                        content: err,
+                        success: Some(false),
+                        metadata: None,
+                    },
+                };
+            }
+        }
+    }
+
     // check if this was a patch, and apply it if so
     match maybe_parse_apply_patch_verified(&params.command, &params.cwd) {
         MaybeApplyPatchVerified::Body(changes) => {
@@ -1595,6 +2125,7 @@ async fn handle_container_exec_with_params(
                 output: FunctionCallOutputPayload {
                     content: format!("error: {parse_error:#}"),
                     success: None,
+                    metadata: None,
                 },
             };
         }
@@ -1637,6 +2168,7 @@ async fn handle_container_exec_with_params(
                         output: FunctionCallOutputPayload {
                             content: "exec command rejected by user".to_string(),
                             success: None,
+                            metadata: None,
                         },
                     };
                 }
@@ -1653,6 +2185,7 @@ async fn handle_container_exec_with_params(
                 output: FunctionCallOutputPayload {
                     content: format!("exec command rejected: {reason}"),
                     success: None,
+                    metadata: None,
                 },
             };
         }
@@ -1662,12 +2195,20 @@ async fn handle_container_exec_with_params(
         .await;
 
     let params = maybe_run_with_user_profile(params, sess);
+    if background {
+        return start_background_process(params, sandbox_type, sess, &sub_id, call_id).await;
+    }
     let output_result = process_exec_tool_call(
         params.clone(),
         sandbox_type,
         sess.ctrl_c.clone(),
         &sess.sandbox_policy,
         &sess.codex_linux_sandbox_exe,
+        Some(StdoutStream {
+            sub_id: sub_id.clone(),
+            call_id: call_id.clone(),
+            tx_event: sess.tx_event.clone(),
+        }),
     )
     .await;
 
@@ -1684,17 +2225,12 @@ async fn handle_container_exec_with_params(
                 .await;
 
             let is_success = exit_code == 0;
-            let content = format_exec_output(
-                if is_success { &stdout } else { &stderr },
-                exit_code,
-                duration,
-            );
-
             ResponseInputItem::FunctionCallOutput {
                 call_id,
                 output: FunctionCallOutputPayload {
-                    content,
+                    content: if is_success { stdout } else { stderr },
                     success: Some(is_success),
+                    metadata: Some(exec_metadata(exit_code, duration)),
                 },
             }
         }
@@ -1708,12 +2244,65 @@ async fn handle_container_exec_with_params(
                 output: FunctionCallOutputPayload {
                     content: format!("execution error: {e}"),
                     success: None,
+                    metadata: None,
                 },
             }
         }
     }
 }
 
+/// Spawns `params` as a background process and replies with its handle. The
+/// command has been approved and is sandboxed like a foreground one, but a
+/// sandbox denial is only visible in what a later poll reports.
+async fn start_background_process(
+    params: ExecParams,
+    sandbox_type: SandboxType,
+    sess: &Session,
+    sub_id: &str,
+    call_id: String,
+) -> ResponseInputItem {
+    let ExecParams {
+        command, cwd, env, ..
+    } = params;
+    let started = match spawn_exec_child(
+        command.clone(),
+        cwd,
+        env,
+        sandbox_type,
+        &sess.sandbox_policy,
+        &sess.codex_linux_sandbox_exe,
+    )
+    .await
+    {
+        Ok(child) => sess.background_processes.start(&command, child),
+        Err(e) => Err(format!("execution error: {e}")),
+    };
+    let (content, success) = match started {
+        Ok(handle) => {
+            let message = format!(
+                "started process {handle} in the background; read its output with poll_process \
+                 and stop it with kill_process"
+            );
+            sess.notify_exec_command_end(sub_id, &call_id, &message, "", 0)
+                .await;
+            (message, true)
+        }
+        Err(err) => {
+            sess.notify_exec_command_end(sub_id, &call_id, "", &err, -1)
+                .await;
+            (err, false)
+        }
+    };
+    ResponseInputItem::FunctionCallOutput {
+        call_id,
+        output: FunctionCallOutputPayload {
+            content,
+            success: Some(success),
+            metadata: None,
+        },
+    }
+}
+
 async fn handle_sandbox_error(
     error: SandboxErr,
     sandbox_type: SandboxType,
@@ -1722,6 +2311,12 @@ async fn handle_sandbox_error(
     sub_id: String,
     call_id: String,
 ) -> ResponseInputItem {
+    // Close the failed attempt's output stream, so clients don't run it together
+    // with the output of a retry under the same call id
+    let (stdout, stderr, exit_code) = sandbox_failure_output(&error);
+    sess.notify_exec_command_end(&sub_id, &call_id, &stdout, &stderr, exit_code)
+        .await;
+
     // Early out if the user never wants to be asked for approval; just return to the model immediately
     if sess.approval_policy == AskForApproval::Never {
         return ResponseInputItem::FunctionCallOutput {
@@ -1731,6 +2326,7 @@ async fn handle_sandbox_error(
                     "failed in sandbox {sandbox_type:?} with execution error: {error}"
                 ),
                 success: Some(false),
+                metadata: None,
             },
         };
     }
@@ -1778,6 +2374,11 @@ async fn handle_sandbox_error(
                 sess.ctrl_c.clone(),
                 &sess.sandbox_policy,
                 &sess.codex_linux_sandbox_exe,
+                Some(StdoutStream {
+                    sub_id: sub_id.clone(),
+                    call_id: call_id.clone(),
+                    tx_event: sess.tx_event.clone(),
+                }),
             )
             .await;
 
@@ -1794,17 +2395,12 @@ async fn handle_sandbox_error(
                         .await;
 
                     let is_success = exit_code == 0;
-                    let content = format_exec_output(
-                        if is_success { &stdout } else { &stderr },
-                        exit_code,
-                        duration,
-                    );
-
                     ResponseInputItem::FunctionCallOutput {
                         call_id,
                         output: FunctionCallOutputPayload {
-                            content,
+                            content: if is_success { stdout } else { stderr },
                             success: Some(is_success),
+                            metadata: Some(exec_metadata(exit_code, duration)),
                         },
                     }
                 }
@@ -1815,6 +2411,7 @@ async fn handle_sandbox_error(
                         output: FunctionCallOutputPayload {
                             content: format!("retry failed: {e}"),
                             success: None,
+                            metadata: None,
                         },
                     }
                 }
@@ -1827,39 +2424,30 @@ async fn handle_sandbox_error(
                 output: FunctionCallOutputPayload {
                     content: "exec command rejected by user".to_string(),
                     success: None,
+                    metadata: None,
                 },
             }
         }
     }
 }
 
-/// Exec output is a pre-serialized JSON payload
-fn format_exec_output(output: &str, exit_code: i32, duration: Duration) -> String {
-    #[derive(Serialize)]
-    struct ExecMetadata {
-        exit_code: i32,
-        duration_seconds: f32,
+/// The stdout, stderr and exit code reported for a command the sandbox stopped
+fn sandbox_failure_output(error: &SandboxErr) -> (String, String, i32) {
+    match error {
+        SandboxErr::Denied(exit_code, stdout, stderr) => {
+            (stdout.clone(), stderr.clone(), *exit_code)
+        }
+        SandboxErr::Signal(signal) => (String::new(), error.to_string(), 128 + signal),
+        _ => (String::new(), error.to_string(), -1),
     }
+}
 
-    #[derive(Serialize)]
-    struct ExecOutput<'a> {
-        output: &'a str,
-        metadata: ExecMetadata,
+fn exec_metadata(exit_code: i32, duration: Duration) -> FunctionCallOutputMetadata {
+    FunctionCallOutputMetadata {
+        exit_code: Some(exit_code),
+        ..Default::default()
     }
-
-    // round to 1 decimal place
-    let duration_seconds = ((duration.as_secs_f32()) * 10.0).round() / 10.0;
-
-    let payload = ExecOutput {
-        output,
-        metadata: ExecMetadata {
-            exit_code,
-            duration_seconds,
-        },
-    };
-
-    #[expect(clippy::expect_used)]
-    serde_json::to_string(&payload).expect("serialize ExecOutput")
+    .with_duration(duration)
 }
 
 fn get_last_assistant_message_from_turn(responses: &[ResponseItem]) -> Option<String> {
@@ -1881,3 +2469,30 @@ fn get_last_assistant_message_from_turn(responses: &[ResponseItem]) -> Option<St
         }
     })
 }
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+
+    #[test]
+    fn sandbox_failures_end_with_the_failed_attempt_output() {
+        let denied = SandboxErr::Denied(1, "partial\n".to_string(), "denied\n".to_string());
+        assert_eq!(
+            sandbox_failure_output(&denied),
+            ("partial\n".to_string(), "denied\n".to_string(), 1)
+        );
+        assert_eq!(
+            sandbox_failure_output(&SandboxErr::Signal(9)),
+            (
+                String::new(),
+                "command was killed by a signal".to_string(),
+                137
+            )
+        );
+        assert_eq!(
+            sandbox_failure_output(&SandboxErr::Timeout),
+            (String::new(), "command timed out".to_string(), -1)
+        );
+    }
+}
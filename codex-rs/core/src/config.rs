@@ -3,10 +3,12 @@ use crate::config_types::History;
 use crate::config_types::McpServerConfig;
 use crate::config_types::ReasoningEffort;
 use crate::config_types::ReasoningSummary;
+use crate::config_types::RetrievalContext;
 use crate::config_types::SandboxMode;
 use crate::config_types::SandboxWorkplaceWrite;
 use crate::config_types::ShellEnvironmentPolicy;
 use crate::config_types::ShellEnvironmentPolicyToml;
+use crate::config_types::ToolExecPolicyToml;
 use crate::config_types::Tui;
 use crate::config_types::UriBasedFileOpener;
 use crate::flags::OPENAI_DEFAULT_MODEL;
@@ -15,6 +17,7 @@ use crate::model_provider_info::built_in_model_providers;
 use crate::openai_model_info::get_model_info;
 use crate::protocol::AskForApproval;
 use crate::protocol::SandboxPolicy;
+use crate::tool_exec_policy::ToolExecPolicy;
 use dirs::home_dir;
 use serde::Deserialize;
 use std::collections::HashMap;
@@ -52,6 +55,13 @@ pub struct Config {
 
     pub shell_environment_policy: ShellEnvironmentPolicy,
 
+    /// Timeouts, allowed commands and environment for the commands spawned by
+    /// particular tools, keyed by tool name.
+    pub tool_exec_policies: HashMap<String, ToolExecPolicy>,
+
+    /// Code from the workspace's codebase index added to each user turn.
+    pub retrieval_context: RetrievalContext,
+
     /// When `true`, `AgentReasoning` events emitted by the backend will be
     /// suppressed from the frontend output. This can reduce visual noise when
     /// users are only interested in the final agent responses.
@@ -271,6 +281,14 @@ pub struct ConfigToml {
     #[serde(default)]
     pub shell_environment_policy: ShellEnvironmentPolicyToml,
 
+    /// Per-tool overrides for the commands a tool spawns, keyed by tool name.
+    #[serde(default)]
+    pub tools: HashMap<String, ToolExecPolicyToml>,
+
+    /// Search the codebase index with each user message and add the matches
+    /// to the turn.
+    pub retrieval_context: Option<RetrievalContext>,
+
     /// Sandbox mode to use.
     pub sandbox_mode: Option<SandboxMode>,
 
@@ -432,6 +450,11 @@ impl Config {
             .clone();
 
         let shell_environment_policy = cfg.shell_environment_policy.into();
+        let tool_exec_policies = cfg
+            .tools
+            .into_iter()
+            .map(|(tool, policy)| (tool, policy.into()))
+            .collect();
 
         let resolved_cwd = {
             use std::env;
@@ -492,6 +515,8 @@ impl Config {
                 .unwrap_or_else(AskForApproval::default),
             sandbox_policy,
             shell_environment_policy,
+            tool_exec_policies,
+            retrieval_context: cfg.retrieval_context.unwrap_or_default(),
             disable_response_storage: config_profile
                 .disable_response_storage
                 .or(cfg.disable_response_storage)
@@ -804,6 +829,7 @@ disable_response_storage = true
             request_max_retries: Some(4),
             stream_max_retries: Some(10),
             stream_idle_timeout_ms: Some(300_000),
+            structured_function_call_outputs: false,
         };
         let model_provider_map = {
             let mut model_provider_map = built_in_model_providers();
@@ -865,6 +891,8 @@ disable_response_storage = true
                 approval_policy: AskForApproval::Never,
                 sandbox_policy: SandboxPolicy::new_read_only_policy(),
                 shell_environment_policy: ShellEnvironmentPolicy::default(),
+                tool_exec_policies: HashMap::new(),
+                retrieval_context: RetrievalContext::default(),
                 disable_response_storage: false,
                 user_instructions: None,
                 notify: None,
@@ -914,6 +942,8 @@ disable_response_storage = true
             approval_policy: AskForApproval::UnlessTrusted,
             sandbox_policy: SandboxPolicy::new_read_only_policy(),
             shell_environment_policy: ShellEnvironmentPolicy::default(),
+            tool_exec_policies: HashMap::new(),
+            retrieval_context: RetrievalContext::default(),
             disable_response_storage: false,
             user_instructions: None,
             notify: None,
@@ -978,6 +1008,8 @@ disable_response_storage = true
             approval_policy: AskForApproval::OnFailure,
             sandbox_policy: SandboxPolicy::new_read_only_policy(),
             shell_environment_policy: ShellEnvironmentPolicy::default(),
+            tool_exec_policies: HashMap::new(),
+            retrieval_context: RetrievalContext::default(),
             disable_response_storage: true,
             user_instructions: None,
             notify: None,
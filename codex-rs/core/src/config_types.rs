@@ -134,6 +134,43 @@ pub struct ShellEnvironmentPolicyToml {
     pub experimental_use_profile: Option<bool>,
 }
 
+/// Settings for the commands one tool spawns, from a `[tools.<name>]` table
+/// keyed by the tool's name (e.g. `execute_command` or `local_shell`).
+#[derive(Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct ToolExecPolicyToml {
+    /// Timeout for the tool's commands, in place of the one the model asks for.
+    pub timeout_ms: Option<u64>,
+
+    /// Timeouts for commands starting with the given words, e.g.
+    /// `"cargo test" = 600000`. These win over `timeout_ms`.
+    pub command_timeouts_ms: Option<HashMap<String, u64>>,
+
+    /// Command prefixes the tool may run, e.g. `["cargo", "git status"]`.
+    pub allowed_commands: Option<Vec<String>>,
+
+    /// Environment for the tool's commands, in place of the global
+    /// `shell_environment_policy`.
+    pub shell_environment_policy: Option<ShellEnvironmentPolicyToml>,
+}
+
+/// Settings for adding indexed code to each user turn, from `[retrieval_context]`.
+#[derive(Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct RetrievalContext {
+    /// If true, each user message is searched for in the workspace's codebase
+    /// index and the best matches are sent to the model along with it.
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Token budget for the added code.
+    pub max_tokens: Option<usize>,
+
+    /// Number of search results considered before the budget is applied.
+    pub limit: Option<usize>,
+
+    /// Minimum similarity score for a search result to be considered.
+    pub min_score: Option<f32>,
+}
+
 pub type EnvironmentVariablePattern = WildMatchPattern<'*', '?'>;
 
 /// Deriving the `env` based on this policy works as follows:
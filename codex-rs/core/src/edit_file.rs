@@ -0,0 +1,247 @@
+//! In-memory edits behind the `edit_file` tool. A file's text is changed either
+//! by unified-diff hunks or by search/replace pairs, and every hunk or pair that
+//! can't be applied is reported instead of stopping at the first one.
+
+/// Applies each `(search, replace)` pair in order to `original`.
+///
+/// Unless `replace_all` is set, each search text must occur exactly once, so an
+/// ambiguous edit is reported rather than applied to the wrong occurrence.
+pub(crate) fn apply_search_replace(
+    original: &str,
+    edits: &[(&str, &str)],
+    replace_all: bool,
+) -> Result<String, String> {
+    let mut content = original.to_string();
+    let mut conflicts = Vec::new();
+    for (index, (search, replace)) in edits.iter().enumerate() {
+        let number = index + 1;
+        match content.matches(search).count() {
+            0 => conflicts.push(format!(
+                "edit {number}: search text not found{}",
+                whitespace_hint(&content, search)
+            )),
+            1 => content = content.replacen(search, replace, 1),
+            _ if replace_all => content = content.replace(search, replace),
+            count => conflicts.push(format!(
+                "edit {number}: search text found {count} times; include more surrounding \
+                 lines to make it unique, or set replace_all"
+            )),
+        }
+    }
+    if conflicts.is_empty() {
+        Ok(content)
+    } else {
+        Err(conflicts.join("\n"))
+    }
+}
+
+/// Points out when a search text only fails to match because of whitespace.
+fn whitespace_hint(content: &str, search: &str) -> &'static str {
+    let squash = |text: &str| text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if squash(content).contains(&squash(search)) {
+        " (it matches if whitespace is ignored; check indentation and line breaks)"
+    } else {
+        ""
+    }
+}
+
+/// One `@@ -a,b +c,d @@` section of a unified diff.
+#[derive(Debug, Default)]
+struct Hunk {
+    header: String,
+    /// One-indexed line the hunk starts at in the original file
+    old_start: usize,
+    old_lines: Vec<String>,
+    new_lines: Vec<String>,
+}
+
+/// Applies the hunks of a unified diff to `original`.
+///
+/// File headers before the first hunk (`---`/`+++`, `index`) are skipped, so a
+/// diff for a single file can be passed as is. A hunk that isn't found at its stated line
+/// is searched for elsewhere in the file, nearest match first, which tolerates
+/// stale line numbers as long as the context lines still match. The file keeps
+/// its line endings (CRLF if its first line break is one) and whether it ends in a newline.
+pub(crate) fn apply_unified_diff(original: &str, patch: &str) -> Result<String, String> {
+    let hunks = parse_hunks(patch)?;
+    let mut lines: Vec<String> = original.lines().map(str::to_string).collect();
+    let mut conflicts = Vec::new();
+    // Shift between the original line numbers and the partially edited file
+    let mut offset: isize = 0;
+    // Hunks apply in order, so none may match before the end of the previous one
+    let mut cursor = 0;
+
+    for (index, hunk) in hunks.iter().enumerate() {
+        let expected = (hunk.old_start.saturating_sub(1) as isize + offset).max(0) as usize;
+        let position = if hunk.old_lines.is_empty() {
+            Some(expected.clamp(cursor, lines.len()))
+        } else {
+            find_hunk(&lines, &hunk.old_lines, expected, cursor)
+        };
+
+        match position {
+            Some(position) => {
+                lines.splice(
+                    position..position + hunk.old_lines.len(),
+                    hunk.new_lines.iter().cloned(),
+                );
+                offset += hunk.new_lines.len() as isize - hunk.old_lines.len() as isize;
+                cursor = position + hunk.new_lines.len();
+            }
+            None => conflicts.push(format!(
+                "hunk {} ({}) does not apply: these lines were not found in the file:\n{}",
+                index + 1,
+                hunk.header,
+                hunk.old_lines
+                    .iter()
+                    .map(|line| format!("  {line}"))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            )),
+        }
+    }
+
+    if !conflicts.is_empty() {
+        return Err(conflicts.join("\n"));
+    }
+    let line_ending = line_ending(original);
+    let mut content = lines.join(line_ending);
+    if !content.is_empty() && (original.ends_with('\n') || original.is_empty()) {
+        content.push_str(line_ending);
+    }
+    Ok(content)
+}
+
+/// The line ending `text` uses, judged by its first line break
+fn line_ending(text: &str) -> &'static str {
+    match text.find('\n') {
+        Some(index) if text[..index].ends_with('\r') => "\r\n",
+        _ => "\n",
+    }
+}
+
+/// Start of the match for `needle` nearest to `expected`, at or after `cursor`.
+/// Exact matches win; otherwise trailing whitespace is ignored.
+fn find_hunk(lines: &[String], needle: &[String], expected: usize, cursor: usize) -> Option<usize> {
+    if needle.len() > lines.len() {
+        return None;
+    }
+    let candidates = cursor..=lines.len() - needle.len();
+    let nearest = |matches: &dyn Fn(usize) -> bool| {
+        candidates
+            .clone()
+            .filter(|&start| matches(start))
+            .min_by_key(|&start| start.abs_diff(expected))
+    };
+    nearest(&|start| lines[start..start + needle.len()] == *needle).or_else(|| {
+        nearest(&|start| {
+            lines[start..start + needle.len()]
+                .iter()
+                .zip(needle)
+                .all(|(line, wanted)| line.trim_end() == wanted.trim_end())
+        })
+    })
+}
+
+fn parse_hunks(patch: &str) -> Result<Vec<Hunk>, String> {
+    let mut hunks = Vec::new();
+    let mut current: Option<Hunk> = None;
+    for line in patch.lines() {
+        if line.starts_with("@@") {
+            hunks.extend(current.take());
+            current = Some(Hunk {
+                header: line.to_string(),
+                old_start: parse_old_start(line)
+                    .ok_or_else(|| format!("invalid hunk header: {line}"))?,
+                ..Default::default()
+            });
+            continue;
+        }
+        let Some(hunk) = current.as_mut() else {
+            // Anything before the first hunk is a file header
+            continue;
+        };
+        if line.starts_with("diff ") {
+            return Err("patch touches more than one file; edit one file at a time".to_string());
+        }
+        match line.chars().next() {
+            Some('+') => hunk.new_lines.push(line[1..].to_string()),
+            Some('-') => hunk.old_lines.push(line[1..].to_string()),
+            Some(' ') => {
+                hunk.old_lines.push(line[1..].to_string());
+                hunk.new_lines.push(line[1..].to_string());
+            }
+            // "\ No newline at end of file"
+            Some('\\') => {}
+            // A blank context line whose leading space was dropped
+            None => {
+                hunk.old_lines.push(String::new());
+                hunk.new_lines.push(String::new());
+            }
+            Some(_) => return Err(format!("unexpected line in hunk {}: {line}", hunk.header)),
+        }
+    }
+    hunks.extend(current);
+
+    if hunks.is_empty() {
+        return Err("patch contains no @@ hunks".to_string());
+    }
+    Ok(hunks)
+}
+
+/// Original start line from a header like `@@ -12,7 +12,8 @@ fn main() {`
+fn parse_old_start(header: &str) -> Option<usize> {
+    let old_range = header.trim_start_matches('@').trim().split(' ').next()?;
+    let old_start = old_range.strip_prefix('-')?.split(',').next()?;
+    old_start.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+
+    const ORIGINAL: &str = "fn main() {\n    let x = 1;\n    println!(\"{x}\");\n}\n";
+
+    #[test]
+    fn search_replace_requires_unique_matches() {
+        let edited =
+            apply_search_replace(ORIGINAL, &[("let x = 1;", "let x = 2;")], false).unwrap();
+        assert_eq!(edited, ORIGINAL.replace("1;", "2;"));
+
+        let err =
+            apply_search_replace(ORIGINAL, &[("x", "y"), ("missing", "")], false).unwrap_err();
+        assert!(err.contains("edit 1: search text found 2 times"));
+        assert!(err.contains("edit 2: search text not found"));
+
+        let all = apply_search_replace(ORIGINAL, &[("x", "y")], true).unwrap();
+        assert!(!all.contains('x'));
+    }
+
+    #[test]
+    fn unified_diff_applies_with_stale_line_numbers() {
+        let patch = "--- a/main.rs\n+++ b/main.rs\n@@ -10,3 +10,3 @@\n fn main() {\n-    let x = 1;\n+    let x = 2;\n     println!(\"{x}\");\n";
+        let edited = apply_unified_diff(ORIGINAL, patch).unwrap();
+        assert_eq!(edited, ORIGINAL.replace("1;", "2;"));
+    }
+
+    #[test]
+    fn unified_diff_keeps_crlf_line_endings() {
+        let original = ORIGINAL.replace('\n', "\r\n");
+        let patch = "@@ -2,1 +2,1 @@\n-    let x = 1;\n+    let x = 2;\n";
+        let edited = apply_unified_diff(&original, patch).unwrap();
+        assert_eq!(edited, original.replace("1;", "2;"));
+
+        let unterminated = original.trim_end_matches("\r\n");
+        let edited = apply_unified_diff(unterminated, patch).unwrap();
+        assert_eq!(edited, unterminated.replace("1;", "2;"));
+    }
+
+    #[test]
+    fn unified_diff_reports_each_conflicting_hunk() {
+        let patch = "@@ -1,2 +1,2 @@\n fn main() {\n-    let y = 1;\n+    let y = 2;\n@@ -3,2 +3,3 @@\n     println!(\"{x}\");\n+    println!(\"done\");\n }\n";
+        let err = apply_unified_diff(ORIGINAL, patch).unwrap_err();
+        assert!(err.contains("hunk 1 (@@ -1,2 +1,2 @@) does not apply"));
+        assert!(!err.contains("hunk 2"));
+    }
+}
@@ -7,10 +7,13 @@ use std::path::PathBuf;
 use std::process::ExitStatus;
 use std::process::Stdio;
 use std::sync::Arc;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
 use std::time::Duration;
 use std::time::Instant;
 use tracing::debug;
 
+use async_channel::Sender;
 use tokio::io::AsyncRead;
 use tokio::io::AsyncReadExt;
 use tokio::io::BufReader;
@@ -22,6 +25,10 @@ use tracing::trace;
 use crate::error::CodexErr;
 use crate::error::Result;
 use crate::error::SandboxErr;
+use crate::protocol::Event;
+use crate::protocol::EventMsg;
+use crate::protocol::ExecCommandOutputDeltaEvent;
+use crate::protocol::ExecOutputStream;
 use crate::protocol::SandboxPolicy;
 
 // Maximum we send for each stream, which is either:
@@ -32,6 +39,10 @@ const MAX_STREAM_OUTPUT_LINES: usize = 256;
 
 const DEFAULT_TIMEOUT_MS: u64 = 10_000;
 
+/// Cap on the output delta events sent for one command, so a command that
+/// floods its output can't flood the event channel too.
+const MAX_EXEC_OUTPUT_DELTAS_PER_CALL: usize = 10_000;
+
 // Hardcode these since it does not seem worth including the libc crate just
 // for these.
 const SIGKILL_CODE: i32 = 9;
@@ -63,6 +74,15 @@ pub struct ExecParams {
     pub env: HashMap<String, String>,
 }
 
+/// Where to send the output of a running command, as
+/// [`EventMsg::ExecCommandOutputDelta`] events, while it is being read.
+#[derive(Clone)]
+pub struct StdoutStream {
+    pub sub_id: String,
+    pub call_id: String,
+    pub tx_event: Sender<Event>,
+}
+
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum SandboxType {
     None,
@@ -80,53 +100,29 @@ pub async fn process_exec_tool_call(
     ctrl_c: Arc<Notify>,
     sandbox_policy: &SandboxPolicy,
     codex_linux_sandbox_exe: &Option<PathBuf>,
+    stdout_stream: Option<StdoutStream>,
 ) -> Result<ExecToolCallOutput> {
     let start = Instant::now();
 
-    let raw_output_result: std::result::Result<RawExecToolCallOutput, CodexErr> = match sandbox_type
-    {
-        SandboxType::None => exec(params, sandbox_policy, ctrl_c).await,
-        SandboxType::MacosSeatbelt => {
-            let ExecParams {
-                command,
-                cwd,
-                timeout_ms,
-                env,
-            } = params;
-            let child = spawn_command_under_seatbelt(
-                command,
-                sandbox_policy,
-                cwd,
-                StdioPolicy::RedirectForShellTool,
-                env,
-            )
-            .await?;
-            consume_truncated_output(child, ctrl_c, timeout_ms).await
-        }
-        SandboxType::LinuxSeccomp => {
-            let ExecParams {
-                command,
-                cwd,
-                timeout_ms,
-                env,
-            } = params;
-
-            let codex_linux_sandbox_exe = codex_linux_sandbox_exe
-                .as_ref()
-                .ok_or(CodexErr::LandlockSandboxExecutableNotProvided)?;
-            let child = spawn_command_under_linux_sandbox(
-                codex_linux_sandbox_exe,
-                command,
-                sandbox_policy,
-                cwd,
-                StdioPolicy::RedirectForShellTool,
-                env,
-            )
-            .await?;
-
-            consume_truncated_output(child, ctrl_c, timeout_ms).await
-        }
-    };
+    let ExecParams {
+        command,
+        cwd,
+        timeout_ms,
+        env,
+    } = params;
+    let raw_output_result: std::result::Result<RawExecToolCallOutput, CodexErr> = async {
+        let child = spawn_exec_child(
+            command,
+            cwd,
+            env,
+            sandbox_type,
+            sandbox_policy,
+            codex_linux_sandbox_exe,
+        )
+        .await?;
+        consume_truncated_output(child, ctrl_c, timeout_ms, stdout_stream).await
+    }
+    .await;
     let duration = start.elapsed();
     match raw_output_result {
         Ok(raw_output) => {
@@ -168,6 +164,64 @@ pub async fn process_exec_tool_call(
     }
 }
 
+/// Spawns a shell tool command under the sandbox for `sandbox_type`, with its
+/// stdout and stderr piped for the caller to read.
+pub(crate) async fn spawn_exec_child(
+    command: Vec<String>,
+    cwd: PathBuf,
+    env: HashMap<String, String>,
+    sandbox_type: SandboxType,
+    sandbox_policy: &SandboxPolicy,
+    codex_linux_sandbox_exe: &Option<PathBuf>,
+) -> Result<Child> {
+    let child = match sandbox_type {
+        SandboxType::None => {
+            let (program, args) = command.split_first().ok_or_else(|| {
+                CodexErr::Io(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "command args are empty",
+                ))
+            })?;
+            let arg0 = None;
+            spawn_child_async(
+                PathBuf::from(program),
+                args.into(),
+                arg0,
+                cwd,
+                sandbox_policy,
+                StdioPolicy::RedirectForShellTool,
+                env,
+            )
+            .await?
+        }
+        SandboxType::MacosSeatbelt => {
+            spawn_command_under_seatbelt(
+                command,
+                sandbox_policy,
+                cwd,
+                StdioPolicy::RedirectForShellTool,
+                env,
+            )
+            .await?
+        }
+        SandboxType::LinuxSeccomp => {
+            let codex_linux_sandbox_exe = codex_linux_sandbox_exe
+                .as_ref()
+                .ok_or(CodexErr::LandlockSandboxExecutableNotProvided)?;
+            spawn_command_under_linux_sandbox(
+                codex_linux_sandbox_exe,
+                command,
+                sandbox_policy,
+                cwd,
+                StdioPolicy::RedirectForShellTool,
+                env,
+            )
+            .await?
+        }
+    };
+    Ok(child)
+}
+
 pub async fn spawn_command_under_seatbelt(
     command: Vec<String>,
     sandbox_policy: &SandboxPolicy,
@@ -322,36 +376,6 @@ pub struct ExecToolCallOutput {
     pub duration: Duration,
 }
 
-async fn exec(
-    ExecParams {
-        command,
-        cwd,
-        timeout_ms,
-        env,
-    }: ExecParams,
-    sandbox_policy: &SandboxPolicy,
-    ctrl_c: Arc<Notify>,
-) -> Result<RawExecToolCallOutput> {
-    let (program, args) = command.split_first().ok_or_else(|| {
-        CodexErr::Io(io::Error::new(
-            io::ErrorKind::InvalidInput,
-            "command args are empty",
-        ))
-    })?;
-    let arg0 = None;
-    let child = spawn_child_async(
-        PathBuf::from(program),
-        args.into(),
-        arg0,
-        cwd,
-        sandbox_policy,
-        StdioPolicy::RedirectForShellTool,
-        env,
-    )
-    .await?;
-    consume_truncated_output(child, ctrl_c, timeout_ms).await
-}
-
 #[derive(Debug, Clone, Copy)]
 pub enum StdioPolicy {
     RedirectForShellTool,
@@ -434,10 +458,13 @@ async fn spawn_child_async(
 
 /// Consumes the output of a child process, truncating it so it is suitable for
 /// use as the output of a `shell` tool call. Also enforces specified timeout.
+/// When `stdout_stream` is set, the output is also streamed as it is read,
+/// before truncation.
 pub(crate) async fn consume_truncated_output(
     mut child: Child,
     ctrl_c: Arc<Notify>,
     timeout_ms: Option<u64>,
+    stdout_stream: Option<StdoutStream>,
 ) -> Result<RawExecToolCallOutput> {
     // Both stdout and stderr were configured with `Stdio::piped()`
     // above, therefore `take()` should normally return `Some`.  If it doesn't
@@ -454,15 +481,29 @@ pub(crate) async fn consume_truncated_output(
         ))
     })?;
 
+    // Shared by both streams so the cap applies to the command as a whole
+    let delta_budget = Arc::new(AtomicUsize::new(MAX_EXEC_OUTPUT_DELTAS_PER_CALL));
     let stdout_handle = tokio::spawn(read_capped(
         BufReader::new(stdout_reader),
         MAX_STREAM_OUTPUT,
         MAX_STREAM_OUTPUT_LINES,
+        stdout_stream.clone().map(|stream| DeltaSink {
+            stream,
+            kind: ExecOutputStream::Stdout,
+            budget: Arc::clone(&delta_budget),
+            pending: Vec::new(),
+        }),
     ));
     let stderr_handle = tokio::spawn(read_capped(
         BufReader::new(stderr_reader),
         MAX_STREAM_OUTPUT,
         MAX_STREAM_OUTPUT_LINES,
+        stdout_stream.map(|stream| DeltaSink {
+            stream,
+            kind: ExecOutputStream::Stderr,
+            budget: delta_budget,
+            pending: Vec::new(),
+        }),
     ));
 
     let interrupted = ctrl_c.notified();
@@ -496,10 +537,70 @@ pub(crate) async fn consume_truncated_output(
     })
 }
 
+/// Forwards the chunks read from one output stream of a command.
+struct DeltaSink {
+    stream: StdoutStream,
+    kind: ExecOutputStream,
+    /// Delta events left for the command
+    budget: Arc<AtomicUsize>,
+    /// Trailing bytes of a UTF-8 character split across reads
+    pending: Vec<u8>,
+}
+
+impl DeltaSink {
+    async fn send(&mut self, bytes: &[u8]) {
+        self.pending.extend_from_slice(bytes);
+        let complete = utf8_complete_prefix_len(&self.pending);
+        if complete > 0 {
+            let chunk = String::from_utf8_lossy(&self.pending[..complete]).into_owned();
+            self.pending.drain(..complete);
+            self.emit(chunk).await;
+        }
+    }
+
+    /// Sends what is left of a character cut off by the end of the stream.
+    async fn finish(mut self) {
+        if !self.pending.is_empty() {
+            let chunk = String::from_utf8_lossy(&self.pending).into_owned();
+            self.emit(chunk).await;
+        }
+    }
+
+    async fn emit(&mut self, chunk: String) {
+        let has_budget = self
+            .budget
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| n.checked_sub(1))
+            .is_ok();
+        if !has_budget {
+            return;
+        }
+        let event = Event {
+            id: self.stream.sub_id.clone(),
+            msg: EventMsg::ExecCommandOutputDelta(ExecCommandOutputDeltaEvent {
+                call_id: self.stream.call_id.clone(),
+                stream: self.kind,
+                chunk,
+            }),
+        };
+        // The receiver going away shouldn't stop the command's output from being read.
+        let _ = self.stream.tx_event.send(event).await;
+    }
+}
+
+/// Length of the longest prefix of `bytes` that doesn't end inside a UTF-8
+/// character. Invalid sequences count as complete; they are replaced when decoded.
+fn utf8_complete_prefix_len(bytes: &[u8]) -> usize {
+    match std::str::from_utf8(bytes) {
+        Err(e) if e.error_len().is_none() => e.valid_up_to(),
+        _ => bytes.len(),
+    }
+}
+
 async fn read_capped<R: AsyncRead + Unpin>(
     mut reader: R,
     max_output: usize,
     max_lines: usize,
+    mut delta_sink: Option<DeltaSink>,
 ) -> io::Result<Vec<u8>> {
     let mut buf = Vec::with_capacity(max_output.min(8 * 1024));
     let mut tmp = [0u8; 8192];
@@ -513,6 +614,10 @@ async fn read_capped<R: AsyncRead + Unpin>(
             break;
         }
 
+        if let Some(sink) = delta_sink.as_mut() {
+            sink.send(&tmp[..n]).await;
+        }
+
         // Copy into the buffer only while we still have byte and line budget.
         if remaining_bytes > 0 && remaining_lines > 0 {
             let mut copy_len = 0;
@@ -531,6 +636,10 @@ async fn read_capped<R: AsyncRead + Unpin>(
         // Continue reading to EOF to avoid back-pressure, but discard once caps are hit.
     }
 
+    if let Some(sink) = delta_sink {
+        sink.finish().await;
+    }
+
     Ok(buf)
 }
 
@@ -546,3 +655,64 @@ fn synthetic_exit_status(code: i32) -> ExitStatus {
     #[expect(clippy::unwrap_used)]
     std::process::ExitStatus::from_raw(code.try_into().unwrap())
 }
+
+#[cfg(all(test, unix))]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+
+    #[tokio::test]
+    async fn streams_output_deltas_while_reading() {
+        let (tx_event, rx_event) = async_channel::unbounded();
+        let params = ExecParams {
+            command: vec![
+                "sh".to_string(),
+                "-c".to_string(),
+                "printf 'héllo\\n'; printf 'oops' >&2".to_string(),
+            ],
+            cwd: std::env::temp_dir(),
+            timeout_ms: None,
+            env: HashMap::new(),
+        };
+        let stream = StdoutStream {
+            sub_id: "sub".to_string(),
+            call_id: "call".to_string(),
+            tx_event,
+        };
+
+        let output = process_exec_tool_call(
+            params,
+            SandboxType::None,
+            Arc::new(Notify::new()),
+            &SandboxPolicy::DangerFullAccess,
+            &None,
+            Some(stream),
+        )
+        .await
+        .unwrap();
+
+        let mut stdout = String::new();
+        let mut stderr = String::new();
+        while let Ok(event) = rx_event.try_recv() {
+            let EventMsg::ExecCommandOutputDelta(delta) = event.msg else {
+                panic!("unexpected event: {event:?}");
+            };
+            assert_eq!(delta.call_id, "call");
+            match delta.stream {
+                ExecOutputStream::Stdout => stdout.push_str(&delta.chunk),
+                ExecOutputStream::Stderr => stderr.push_str(&delta.chunk),
+            }
+        }
+        assert_eq!(stdout, output.stdout);
+        assert_eq!(stdout, "héllo\n");
+        assert_eq!(stderr, "oops");
+    }
+
+    #[test]
+    fn utf8_prefix_stops_before_split_characters() {
+        let bytes = "hé".as_bytes();
+        assert_eq!(utf8_complete_prefix_len(&bytes[..2]), 1);
+        assert_eq!(utf8_complete_prefix_len(bytes), 3);
+        assert_eq!(utf8_complete_prefix_len(b"\xff\xfe"), 2);
+    }
+}
@@ -6,6 +6,7 @@
 #![deny(clippy::print_stdout, clippy::print_stderr)]
 
 mod apply_patch;
+mod background_process;
 mod bash;
 mod chat_completions;
 mod client;
@@ -18,6 +19,7 @@ pub mod config;
 pub mod config_profile;
 pub mod config_types;
 mod conversation_history;
+mod edit_file;
 pub mod error;
 pub mod exec;
 pub mod exec_env;
@@ -37,9 +39,14 @@ mod openai_tools;
 pub mod plan_tool;
 mod project_doc;
 pub mod protocol;
+mod retrieval_context;
 mod rollout;
 mod safety;
 pub mod shell;
+mod text_file;
+pub mod tool_exec_policy;
+mod tool_params;
+mod tool_registry;
 mod user_notification;
 pub mod util;
 
@@ -35,6 +35,7 @@ pub(crate) async fn handle_mcp_tool_call(
                     output: FunctionCallOutputPayload {
                         content: format!("err: {e}"),
                         success: Some(false),
+                        metadata: None,
                     },
                 };
             }
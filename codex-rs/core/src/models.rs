@@ -1,7 +1,15 @@
 use std::collections::HashMap;
+use std::path::Component;
+use std::path::Path;
+use std::path::PathBuf;
 
-use crate::exec_env::create_env;
 use base64::Engine;
+use codebase_search::progress::IndexPhase;
+use codebase_search::progress::IndexProgress;
+use codebase_search::symbol::SymbolKind;
+use codex_apply_patch::ApplyPatchAction;
+use codex_apply_patch::ApplyPatchFileChange;
+use codex_apply_patch::unified_diff_between;
 use mcp_types::CallToolResult;
 use serde::Deserialize;
 use serde::Deserializer;
@@ -9,10 +17,23 @@ use serde::Serialize;
 use serde::ser::Serializer;
 
 use crate::codex::Session;
+use crate::edit_file::apply_search_replace;
+use crate::edit_file::apply_unified_diff;
 use crate::exec::ExecParams;
 use crate::openai_tools::JsonSchema;
 use crate::openai_tools::ToJsonSchema;
+use crate::protocol::BackgroundEventEvent;
+use crate::protocol::Event;
+use crate::protocol::EventMsg;
 use crate::protocol::InputItem;
+use crate::text_file::MAX_IMAGE_BYTES;
+use crate::text_file::MAX_TEXT_FILE_BYTES;
+use crate::text_file::format_size;
+use crate::text_file::read_text_file;
+use crate::text_file::read_text_file_for_edit;
+use crate::text_file::supported_image_mime;
+use crate::tool_params::ToolParamError;
+use crate::tool_params::ValidatedToolParams;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
@@ -25,6 +46,14 @@ pub enum ResponseInputItem {
         call_id: String,
         output: FunctionCallOutputPayload,
     },
+    /// Function call output with content the model should see that a function
+    /// call output can't carry, such as images. The content is sent to the
+    /// model as a user message right after the output.
+    FunctionCallOutputWithContent {
+        call_id: String,
+        output: FunctionCallOutputPayload,
+        content: Vec<ContentItem>,
+    },
     McpToolCallOutput {
         call_id: String,
         result: Result<CallToolResult, String>,
@@ -39,6 +68,22 @@ pub enum ContentItem {
     OutputText { text: String },
 }
 
+impl ContentItem {
+    /// An image file as an `InputImage` with a base64 data URL, typed by the
+    /// file's extension.
+    pub fn local_image(path: &Path) -> std::io::Result<Self> {
+        let bytes = std::fs::read(path)?;
+        let mime = mime_guess::from_path(path)
+            .first()
+            .map(|m| m.essence_str().to_owned())
+            .unwrap_or_else(|| "application/octet-stream".to_string());
+        let encoded = base64::engine::general_purpose::STANDARD.encode(bytes);
+        Ok(ContentItem::InputImage {
+            image_url: format!("data:{mime};base64,{encoded}"),
+        })
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum ResponseItem {
@@ -92,9 +137,12 @@ impl From<ResponseInputItem> for ResponseItem {
                 content,
                 id: None,
             },
-            ResponseInputItem::FunctionCallOutput { call_id, output } => {
-                Self::FunctionCallOutput { call_id, output }
-            }
+            // The content of FunctionCallOutputWithContent becomes a separate
+            // user message, which the turn loop records after the output
+            ResponseInputItem::FunctionCallOutput { call_id, output }
+            | ResponseInputItem::FunctionCallOutputWithContent {
+                call_id, output, ..
+            } => Self::FunctionCallOutput { call_id, output },
             ResponseInputItem::McpToolCallOutput { call_id, result } => Self::FunctionCallOutput {
                 call_id,
                 output: FunctionCallOutputPayload {
@@ -106,6 +154,7 @@ impl From<ResponseInputItem> for ResponseItem {
                                 .unwrap_or_else(|e| format!("JSON serialization error: {e}"))
                         },
                     ),
+                    metadata: None,
                 },
             },
         }
@@ -133,6 +182,9 @@ pub struct LocalShellExecAction {
     pub working_directory: Option<String>,
     pub env: Option<HashMap<String, String>>,
     pub user: Option<String>,
+    /// Run the command as a background process, see [`ShellToolCallParams::background`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub background: Option<bool>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -150,17 +202,8 @@ impl From<Vec<InputItem>> for ResponseInputItem {
                 .filter_map(|c| match c {
                     InputItem::Text { text } => Some(ContentItem::InputText { text }),
                     InputItem::Image { image_url } => Some(ContentItem::InputImage { image_url }),
-                    InputItem::LocalImage { path } => match std::fs::read(&path) {
-                        Ok(bytes) => {
-                            let mime = mime_guess::from_path(&path)
-                                .first()
-                                .map(|m| m.essence_str().to_owned())
-                                .unwrap_or_else(|| "application/octet-stream".to_string());
-                            let encoded = base64::engine::general_purpose::STANDARD.encode(bytes);
-                            Some(ContentItem::InputImage {
-                                image_url: format!("data:{mime};base64,{encoded}"),
-                            })
-                        }
+                    InputItem::LocalImage { path } => match ContentItem::local_image(&path) {
+                        Ok(image) => Some(image),
                         Err(err) => {
                             tracing::warn!(
                                 "Skipping image {} – could not read file: {}",
@@ -186,18 +229,62 @@ pub struct ShellToolCallParams {
     pub workdir: Option<String>,
     /// This is the maximum time in milliseconds that the command is allowed to run.
     pub timeout: Option<u64>,
+    /// Start the command in the background and return a handle for poll_process and kill_process instead of waiting for it, e.g. for a dev server or a long test run. The timeout does not apply.
+    pub background: Option<bool>,
     /// Optional explanation of what the command is intended to do
     pub explanation: Option<String>,
 }
 
 impl ShellToolCallParams {
-    pub(crate) fn to_exec_params(&self, sess: &Session) -> ExecParams {
-        ExecParams {
-            command: self.command.clone(),
-            cwd: sess.resolve_path(self.workdir.clone()),
-            timeout_ms: self.timeout,
-            env: create_env(&sess.shell_environment_policy),
+    /// The command to run for the `tool` call, with the tool's exec policy applied.
+    pub(crate) fn to_exec_params(&self, tool: &str, sess: &Session) -> Result<ExecParams, String> {
+        sess.exec_params(
+            tool,
+            self.command.clone(),
+            sess.resolve_path(self.workdir.clone()),
+            self.timeout,
+        )
+    }
+}
+
+impl ValidatedToolParams for ShellToolCallParams {
+    fn validate(&self) -> Result<(), ToolParamError> {
+        if self.command.is_empty() {
+            return Err(ToolParamError::new("command", "command cannot be empty"));
+        }
+        Ok(())
+    }
+}
+
+#[derive(macros::ToolSchema, Deserialize, Debug, Clone, PartialEq)]
+pub struct PollProcessToolCallParams {
+    /// Handle of the background process, as returned when it was started
+    pub handle: String,
+    /// Milliseconds to wait for the process to exit before reporting, at most 30000. Defaults to reporting right away.
+    pub wait_ms: Option<u64>,
+}
+
+impl ValidatedToolParams for PollProcessToolCallParams {
+    fn validate(&self) -> Result<(), ToolParamError> {
+        if self.handle.trim().is_empty() {
+            return Err(ToolParamError::new("handle", "handle cannot be empty"));
+        }
+        Ok(())
+    }
+}
+
+#[derive(macros::ToolSchema, Deserialize, Debug, Clone, PartialEq)]
+pub struct KillProcessToolCallParams {
+    /// Handle of the background process, as returned when it was started
+    pub handle: String,
+}
+
+impl ValidatedToolParams for KillProcessToolCallParams {
+    fn validate(&self) -> Result<(), ToolParamError> {
+        if self.handle.trim().is_empty() {
+            return Err(ToolParamError::new("handle", "handle cannot be empty"));
         }
+        Ok(())
     }
 }
 
@@ -211,57 +298,195 @@ pub struct ReadFileToolCallParams {
     pub start_line_one_indexed: Option<u64>,
     /// The one-indexed line number to end reading at (inclusive).
     pub end_line_one_indexed_inclusive: Option<u64>,
+    /// Approximate token budget for the returned lines. Output stops at a line boundary, with the line to continue reading from.
+    pub max_output_tokens: Option<u64>,
+    /// Byte budget for the returned lines. Output stops at a line boundary, with the line to continue reading from.
+    pub max_bytes: Option<u64>,
     /// One sentence explanation as to why this tool is being used, and how it contributes to the goal.
     pub explanation: Option<String>,
 }
 
+/// Lines read by the read_file tool
+#[derive(Debug, Clone)]
+pub(crate) struct ReadFileOutput {
+    pub(crate) text: String,
+    /// Line to continue reading from when the output was cut short
+    pub(crate) next_start_line: Option<usize>,
+    /// The file itself when it is an image, for the model to look at
+    pub(crate) image: Option<ContentItem>,
+}
+
 impl ReadFileToolCallParams {
-    pub(crate) fn to_exec_params(&self, sess: &Session) -> ExecParams {
-        let command = if self.should_read_entire_file {
-            // use `cat` to read the entire file
-            vec!["cat".to_string(), self.path.clone()]
+    /// Most lines returned by a single read
+    const MAX_LINES: usize = 2000;
+    /// Most bytes of file content returned by a single read
+    const MAX_BYTES: usize = 256 * 1024;
+    /// Rough size of a token, for turning `max_output_tokens` into a byte budget
+    const BYTES_PER_TOKEN: usize = 4;
+
+    /// Reads the file in-process rather than through `cat`/`sed`, so it works the same on
+    /// every platform. Invalid UTF-8 is replaced rather than rejected.
+    pub(crate) fn execute(&self, sess: &Session) -> anyhow::Result<ReadFileOutput> {
+        let path = sess.resolve_path(Some(self.path.clone()));
+        if path.is_dir() {
+            return Err(anyhow::anyhow!(
+                "{} is a directory, not a file",
+                path.display()
+            ));
+        }
+        if let Some(mime) = supported_image_mime(&path) {
+            return self.read_image(&path, &mime);
+        }
+        let content = read_text_file(&path, &self.path).map_err(anyhow::Error::msg)?;
+
+        let (start_line, end_line) = if self.should_read_entire_file {
+            (1, None)
         } else {
-            // use `sed` to read specific lines of a file
-            let start_line = self.start_line_one_indexed.unwrap_or(1);
-            let end_line = self.end_line_one_indexed_inclusive.unwrap_or(1);
-            vec![
-                "sed".to_string(),
-                "-n".to_string(),
-                format!("{start_line},{end_line}p"),
-                self.path.clone(),
-            ]
-        };
-        ExecParams {
-            command,
-            cwd: sess.resolve_path(None),
-            timeout_ms: None,
-            env: create_env(&sess.shell_environment_policy),
+            (
+                self.start_line_one_indexed.unwrap_or(1) as usize,
+                self.end_line_one_indexed_inclusive
+                    .map(|line| line as usize),
+            )
+        };
+        Self::number_lines(&content, start_line, end_line, self.byte_budget())
+            .map_err(anyhow::Error::msg)
+    }
+
+    /// Attaches an image file instead of returning it as text.
+    fn read_image(&self, path: &Path, mime: &str) -> anyhow::Result<ReadFileOutput> {
+        let size = std::fs::metadata(path)
+            .map_err(|e| anyhow::anyhow!("failed to read {}: {e}", self.path))?
+            .len();
+        if size > MAX_IMAGE_BYTES {
+            return Err(anyhow::anyhow!(
+                "{} is an image too large to attach ({}, {mime}; the limit is {})",
+                self.path,
+                format_size(size),
+                format_size(MAX_IMAGE_BYTES)
+            ));
         }
+        let image = ContentItem::local_image(path)
+            .map_err(|e| anyhow::anyhow!("failed to read {}: {e}", self.path))?;
+        Ok(ReadFileOutput {
+            text: format!(
+                "{} is an image ({}, {mime}); it is attached for you to look at.",
+                self.path,
+                format_size(size)
+            ),
+            next_start_line: None,
+            image: Some(image),
+        })
     }
+
+    /// Bytes of output allowed by `max_bytes`, `max_output_tokens` and the hard limit
+    fn byte_budget(&self) -> usize {
+        let requested = [
+            self.max_bytes.map(|bytes| bytes as usize),
+            self.max_output_tokens
+                .map(|tokens| (tokens as usize).saturating_mul(Self::BYTES_PER_TOKEN)),
+        ];
+        requested
+            .into_iter()
+            .flatten()
+            .fold(Self::MAX_BYTES, usize::min)
+    }
+
+    /// Formats `start_line..=end_line` of `content` with one-indexed line number prefixes,
+    /// ending with a notice when the range was cut short by the line or byte limit. A
+    /// first line that alone exceeds the limit is cut off rather than left out.
+    fn number_lines(
+        content: &str,
+        start_line: usize,
+        end_line: Option<usize>,
+        max_bytes: usize,
+    ) -> Result<ReadFileOutput, String> {
+        let lines: Vec<&str> = content.lines().collect();
+        let total_lines = lines.len();
+        if total_lines == 0 {
+            return Ok(ReadFileOutput {
+                text: "(empty file)".to_string(),
+                next_start_line: None,
+                image: None,
+            });
+        }
+        if start_line > total_lines {
+            return Err(format!(
+                "start_line_one_indexed ({start_line}) is past the end of the file ({total_lines} lines)"
+            ));
+        }
+
+        let end_line = end_line.unwrap_or(total_lines).min(total_lines);
+        let mut output = String::new();
+        let mut last_line = start_line - 1;
+        for (index, line) in lines[start_line - 1..end_line].iter().enumerate() {
+            let line_number = start_line + index;
+            if line_number - start_line >= Self::MAX_LINES {
+                break;
+            }
+            let numbered = format!("{line_number:>6}\t{line}\n");
+            if output.len() + numbered.len() > max_bytes {
+                if output.is_empty() {
+                    let mut cut = max_bytes.min(line.len());
+                    while !line.is_char_boundary(cut) {
+                        cut -= 1;
+                    }
+                    output.push_str(&format!(
+                        "{line_number:>6}\t{} ... (line cut off after {cut} of {} bytes)\n",
+                        &line[..cut],
+                        line.len()
+                    ));
+                    last_line = line_number;
+                }
+                break;
+            }
+            output.push_str(&numbered);
+            last_line = line_number;
+        }
+
+        let next_start_line = (last_line < end_line).then_some(last_line + 1);
+        if let Some(next_start_line) = next_start_line {
+            output.push_str(&format!(
+                "... (truncated: showing lines {start_line}-{last_line} of {total_lines}; \
+                 read from line {next_start_line} to see more)\n"
+            ));
+        }
+        Ok(ReadFileOutput {
+            text: output,
+            next_start_line,
+            image: None,
+        })
+    }
+}
+
+impl ValidatedToolParams for ReadFileToolCallParams {
     /// Validates the parameters to ensure logical consistency
-    pub fn validate(&self) -> Result<(), String> {
+    fn validate(&self) -> Result<(), ToolParamError> {
         // Validate line numbers when both are present
         if let (Some(start_line), Some(end_line)) = (
             self.start_line_one_indexed,
             self.end_line_one_indexed_inclusive,
         ) {
             if start_line > end_line {
-                return Err(format!(
-                    "start_line_one_indexed ({start_line}) must be less than or equal to end_line_one_indexed_inclusive ({end_line})"
+                return Err(ToolParamError::new(
+                    "start_line_one_indexed",
+                    format!(
+                        "start_line_one_indexed ({start_line}) must be less than or equal to end_line_one_indexed_inclusive ({end_line})"
+                    ),
                 ));
             }
 
             // Validate that line numbers are valid (greater than 0)
             if start_line == 0 {
-                return Err(
-                    "start_line_one_indexed must be greater than 0 (one-indexed)".to_string(),
-                );
+                return Err(ToolParamError::new(
+                    "start_line_one_indexed",
+                    "start_line_one_indexed must be greater than 0 (one-indexed)",
+                ));
             }
             if end_line == 0 {
-                return Err(
-                    "end_line_one_indexed_inclusive must be greater than 0 (one-indexed)"
-                        .to_string(),
-                );
+                return Err(ToolParamError::new(
+                    "end_line_one_indexed_inclusive",
+                    "end_line_one_indexed_inclusive must be greater than 0 (one-indexed)",
+                ));
             }
         }
 
@@ -270,12 +495,15 @@ impl ReadFileToolCallParams {
             && (self.start_line_one_indexed.is_none()
                 || self.end_line_one_indexed_inclusive.is_none())
         {
-            return Err("start_line_one_indexed and end_line_one_indexed_inclusive are required when should_read_entire_file is false".to_string());
+            return Err(ToolParamError::new(
+                "start_line_one_indexed",
+                "start_line_one_indexed and end_line_one_indexed_inclusive are required when should_read_entire_file is false",
+            ));
         }
 
         // Validate path is not empty
         if self.path.trim().is_empty() {
-            return Err("path cannot be empty".to_string());
+            return Err(ToolParamError::new("path", "path cannot be empty"));
         }
 
         Ok(())
@@ -297,7 +525,7 @@ pub struct RegexSearchToolCallParams {
 }
 
 impl RegexSearchToolCallParams {
-    pub(crate) fn to_exec_params(&self, sess: &Session) -> ExecParams {
+    pub(crate) fn to_exec_params(&self, tool: &str, sess: &Session) -> Result<ExecParams, String> {
         let mut command = vec!["rg".to_string()];
 
         // Add max count limit to avoid overwhelming output
@@ -323,32 +551,36 @@ impl RegexSearchToolCallParams {
         // Add the query as the last argument
         command.push(self.query.clone());
 
-        ExecParams {
-            command,
-            cwd: sess.resolve_path(None), // Search in current working directory
-            timeout_ms: Some(30000),      // 30 second timeout for searches
-            env: create_env(&sess.shell_environment_policy),
-        }
+        // Search in the current working directory with a 30 second timeout
+        sess.exec_params(tool, command, sess.resolve_path(None), Some(30000))
     }
+}
 
+impl ValidatedToolParams for RegexSearchToolCallParams {
     /// Validates the parameters to ensure they are valid for regex search
-    pub fn validate(&self) -> Result<(), String> {
+    fn validate(&self) -> Result<(), ToolParamError> {
         // Validate query is not empty
         if self.query.trim().is_empty() {
-            return Err("query cannot be empty".to_string());
+            return Err(ToolParamError::new("query", "query cannot be empty"));
         }
 
         // Basic validation for include pattern
         if let Some(include_pattern) = &self.include_pattern {
             if include_pattern.trim().is_empty() {
-                return Err("include_pattern cannot be empty when specified".to_string());
+                return Err(ToolParamError::new(
+                    "include_pattern",
+                    "include_pattern cannot be empty when specified",
+                ));
             }
         }
 
         // Basic validation for exclude pattern
         if let Some(exclude_pattern) = &self.exclude_pattern {
             if exclude_pattern.trim().is_empty() {
-                return Err("exclude_pattern cannot be empty when specified".to_string());
+                return Err(ToolParamError::new(
+                    "exclude_pattern",
+                    "exclude_pattern cannot be empty when specified",
+                ));
             }
         }
 
@@ -431,20 +663,870 @@ impl FuzzySearchToolCallParams {
             Ok(output)
         }
     }
+}
+
+impl ValidatedToolParams for FuzzySearchToolCallParams {
+    fn validate(&self) -> Result<(), ToolParamError> {
+        if self.query.trim().is_empty() {
+            return Err(ToolParamError::new("query", "Query cannot be empty"));
+        }
+        Ok(())
+    }
+}
+
+#[derive(macros::ToolSchema, Deserialize, Debug, Clone, PartialEq)]
+pub struct CodebaseSearchToolCallParams {
+    /// A natural language description of the code to find, e.g. "where are retries configured"
+    pub query: String,
+    /// Maximum number of code chunks to return (defaults to 10)
+    pub limit: Option<u64>,
+    /// Minimum similarity score between 0 and 1 for a chunk to be returned (defaults to 0.3)
+    pub min_score: Option<f32>,
+    /// Only return chunks for these kinds of symbol (defaults to all kinds)
+    pub kinds: Option<Vec<SymbolKindFilter>>,
+    /// One sentence explanation as to why this tool is being used, and how it contributes to the goal.
+    pub explanation: Option<String>,
+}
+
+/// Kinds of symbol recorded in the codebase index, for narrowing `codebase_search`
+#[derive(macros::ToolEnum, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SymbolKindFilter {
+    Function,
+    Method,
+    Struct,
+    Enum,
+    Trait,
+    Impl,
+    Module,
+    Constant,
+    Variable,
+    Class,
+    Interface,
+    Type,
+    Macro,
+    Property,
+    Table,
+    View,
+    Resource,
+    Import,
+}
+
+impl From<SymbolKindFilter> for SymbolKind {
+    fn from(kind: SymbolKindFilter) -> Self {
+        match kind {
+            SymbolKindFilter::Function => SymbolKind::Function,
+            SymbolKindFilter::Method => SymbolKind::Method,
+            SymbolKindFilter::Struct => SymbolKind::Struct,
+            SymbolKindFilter::Enum => SymbolKind::Enum,
+            SymbolKindFilter::Trait => SymbolKind::Trait,
+            SymbolKindFilter::Impl => SymbolKind::Impl,
+            SymbolKindFilter::Module => SymbolKind::Module,
+            SymbolKindFilter::Constant => SymbolKind::Constant,
+            SymbolKindFilter::Variable => SymbolKind::Variable,
+            SymbolKindFilter::Class => SymbolKind::Class,
+            SymbolKindFilter::Interface => SymbolKind::Interface,
+            SymbolKindFilter::Type => SymbolKind::Type,
+            SymbolKindFilter::Macro => SymbolKind::Macro,
+            SymbolKindFilter::Property => SymbolKind::Property,
+            SymbolKindFilter::Table => SymbolKind::Table,
+            SymbolKindFilter::View => SymbolKind::View,
+            SymbolKindFilter::Resource => SymbolKind::Resource,
+            SymbolKindFilter::Import => SymbolKind::Import,
+        }
+    }
+}
+
+impl CodebaseSearchToolCallParams {
+    /// Results returned when the model doesn't ask for a limit
+    const DEFAULT_LIMIT: u64 = 10;
+    /// Upper bound on the limit, to keep the output within a reasonable size
+    const MAX_LIMIT: u64 = 50;
+    const DEFAULT_MIN_SCORE: f32 = 0.3;
+    /// Lines of each chunk included in the output; the rest can be read with `read_file`
+    const MAX_SNIPPET_LINES: usize = 40;
+
+    pub(crate) async fn execute(&self, sess: &Session) -> anyhow::Result<String> {
+        let limit = self
+            .limit
+            .unwrap_or(Self::DEFAULT_LIMIT)
+            .min(Self::MAX_LIMIT) as usize;
+        let min_score = self.min_score.unwrap_or(Self::DEFAULT_MIN_SCORE);
+
+        // Symbol kinds are indexed under their variant names
+        let filter = codebase_search::retriever::SearchFilter {
+            kinds: self
+                .kinds
+                .iter()
+                .flatten()
+                .map(|&kind| format!("{:?}", SymbolKind::from(kind)))
+                .collect(),
+            ..Default::default()
+        };
+
+        // Search the semantic index of the session's workspace
+        let results = codebase_search::retriever::search_codebase_filtered(
+            self.query.clone(),
+            &sess.cwd,
+            limit,
+            min_score,
+            &filter,
+        )
+        .await?;
 
-    pub(crate) fn validate(&self) -> Result<(), String> {
+        // Format the results for the AI model
+        if results.is_empty() {
+            return Ok(format!(
+                "No indexed code matched: \"{}\". Try rephrasing the query or lowering min_score.",
+                self.query
+            ));
+        }
+
+        let mut output = format!(
+            "Found {} relevant code chunks (best match first):\n",
+            results.len()
+        );
+        for result in &results {
+            let chunk = &result.chunk;
+            output.push_str(&format!(
+                "\n{}:{}-{} {} {} (score: {:.2})\n",
+                chunk.file_path.display(),
+                chunk.start_line,
+                chunk.end_line,
+                chunk.symbol_kind,
+                chunk.symbol_name,
+                result.score
+            ));
+
+            let lines: Vec<&str> = chunk.content.lines().collect();
+            output.push_str("```\n");
+            for line in lines.iter().take(Self::MAX_SNIPPET_LINES) {
+                output.push_str(line);
+                output.push('\n');
+            }
+            if lines.len() > Self::MAX_SNIPPET_LINES {
+                output.push_str(&format!(
+                    "... ({} more lines)\n",
+                    lines.len() - Self::MAX_SNIPPET_LINES
+                ));
+            }
+            output.push_str("```\n");
+        }
+
+        Ok(output)
+    }
+}
+
+impl ValidatedToolParams for CodebaseSearchToolCallParams {
+    fn validate(&self) -> Result<(), ToolParamError> {
         if self.query.trim().is_empty() {
-            return Err("Query cannot be empty".to_string());
+            return Err(ToolParamError::new("query", "query cannot be empty"));
+        }
+        if self.limit == Some(0) {
+            return Err(ToolParamError::new("limit", "limit must be greater than 0"));
+        }
+        if let Some(min_score) = self
+            .min_score
+            .filter(|min_score| !(0.0..=1.0).contains(min_score))
+        {
+            return Err(ToolParamError::new(
+                "min_score",
+                format!("min_score ({min_score}) must be between 0 and 1"),
+            ));
         }
         Ok(())
     }
 }
 
+#[derive(macros::ToolSchema, Deserialize, Debug, Clone, PartialEq)]
+pub struct IndexCodebaseToolCallParams {
+    /// One sentence explanation as to why this tool is being used, and how it contributes to the goal.
+    pub explanation: Option<String>,
+}
+
+impl IndexCodebaseToolCallParams {
+    /// Builds the semantic index of the workspace, which is what `codebase_search`
+    /// searches, or brings an existing one up to date, reporting each indexing phase
+    /// as a background event.
+    pub(crate) async fn execute(&self, sess: &Session, sub_id: &str) -> anyhow::Result<String> {
+        let root = &sess.cwd;
+
+        let progress = |event: IndexProgress| {
+            if let IndexProgress::Started { phase, total } = event {
+                let unit = match phase {
+                    IndexPhase::Chunking => "files",
+                    IndexPhase::Embedding | IndexPhase::Upserting => "chunks",
+                };
+                // Progress is informational, so it is dropped if the channel is full
+                let _ = sess.tx_event.try_send(Event {
+                    id: sub_id.to_string(),
+                    msg: EventMsg::BackgroundEvent(BackgroundEventEvent {
+                        message: format!("{} {total} {unit} for the codebase index", phase.label()),
+                    }),
+                });
+            }
+        };
+
+        let before = codebase_search::vector_db::index_status(root).await?;
+        let summary = match (&before.state, &before.pending) {
+            // The state file outlived its collection, so nothing is left to update
+            (Some(_), _) if !before.indexed => {
+                codebase_search::vector_db::init_session_with_progress(root, &progress).await?;
+                format!("Rebuilt the missing index of {}", root.display())
+            }
+            (Some(_), Some(pending)) => {
+                codebase_search::vector_db::restore_session_with_progress(root, &progress).await?;
+                if pending.is_empty() {
+                    format!("The index of {} was already up to date", root.display())
+                } else {
+                    format!(
+                        "Refreshed the index of {}: re-indexed {} added, {} modified and {} deleted files",
+                        root.display(),
+                        pending.added.len(),
+                        pending.modified.len(),
+                        pending.deleted.len()
+                    )
+                }
+            }
+            _ => {
+                codebase_search::vector_db::restore_session_with_progress(root, &progress).await?;
+                format!("Built the index of {}", root.display())
+            }
+        };
+
+        let after = codebase_search::vector_db::index_status(root).await?;
+        let chunks = after
+            .point_count
+            .map_or_else(|| "an unknown number of".to_string(), |n| n.to_string());
+        let files = after.state.map_or(0, |state| state.tracked_files);
+        Ok(format!(
+            "{summary}; it holds {chunks} chunks from {files} files."
+        ))
+    }
+}
+
+impl ValidatedToolParams for IndexCodebaseToolCallParams {
+    fn validate(&self) -> Result<(), ToolParamError> {
+        Ok(())
+    }
+}
+
+#[derive(macros::ToolSchema, Deserialize, Debug, Clone, PartialEq)]
+pub struct WriteFileToolCallParams {
+    /// The path of the file to write, relative to the current working directory. It must stay inside the workspace.
+    pub path: String,
+    /// The full content to write to the file
+    pub content: String,
+    /// Whether to create missing parent directories (defaults to false)
+    pub create_dirs: Option<bool>,
+    /// Whether to replace the file if it already exists (defaults to false)
+    pub overwrite: Option<bool>,
+    /// One sentence explanation as to why this tool is being used, and how it contributes to the goal.
+    pub explanation: Option<String>,
+}
+
+impl WriteFileToolCallParams {
+    /// Largest content accepted by a single write
+    const MAX_CONTENT_BYTES: usize = 1024 * 1024;
+
+    /// Resolves the write against `cwd` into an apply_patch action, so it goes through the
+    /// same approval and writable-root checks as a patch.
+    pub(crate) fn to_apply_patch_action(&self, cwd: &Path) -> Result<ApplyPatchAction, String> {
+        let path = resolve_workspace_path(cwd, &self.path)?;
+        if path.is_dir() {
+            return Err(format!("{} is a directory", self.path));
+        }
+
+        let parent_exists = path.parent().is_none_or(Path::exists);
+        if !parent_exists && !self.create_dirs.unwrap_or(false) {
+            return Err(format!(
+                "the parent directory of {} does not exist; set create_dirs to create it",
+                self.path
+            ));
+        }
+
+        let change = if path.exists() {
+            if !self.overwrite.unwrap_or(false) {
+                return Err(format!(
+                    "{} already exists; set overwrite to replace it",
+                    self.path
+                ));
+            }
+            let original = read_text_file_for_edit(&path, &self.path)?;
+            ApplyPatchFileChange::Update {
+                unified_diff: unified_diff_between(&original, &self.content),
+                move_path: None,
+                new_content: self.content.clone(),
+            }
+        } else {
+            ApplyPatchFileChange::Add {
+                content: self.content.clone(),
+            }
+        };
+        Ok(ApplyPatchAction::from_changes(HashMap::from([(
+            path, change,
+        )])))
+    }
+}
+
+impl ValidatedToolParams for WriteFileToolCallParams {
+    fn validate(&self) -> Result<(), ToolParamError> {
+        if self.path.trim().is_empty() {
+            return Err(ToolParamError::new("path", "path cannot be empty"));
+        }
+        if self.content.contains('\0') {
+            return Err(ToolParamError::new(
+                "content",
+                "content contains NUL bytes; write_file only writes text files",
+            ));
+        }
+        if self.content.len() > Self::MAX_CONTENT_BYTES {
+            return Err(ToolParamError::new(
+                "content",
+                format!(
+                    "content is {} bytes, more than the {} byte limit for a single write",
+                    self.content.len(),
+                    Self::MAX_CONTENT_BYTES
+                ),
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[derive(macros::ToolSchema, Deserialize, Debug, Clone, PartialEq)]
+pub struct EditFileToolCallParams {
+    /// The path of the file to edit, relative to the current working directory. It must stay inside the workspace.
+    pub path: String,
+    /// A unified diff with `@@ -a,b +c,d @@` hunks to apply to the file. Provide either patch or search/replace, not both.
+    pub patch: Option<String>,
+    /// Exact snippets of the file to replace, each by the entry at the same index in replace. Each must occur exactly once unless replace_all is set.
+    pub search: Option<Vec<String>>,
+    /// Replacement text for each entry in search
+    pub replace: Option<Vec<String>>,
+    /// Replace every occurrence of each search snippet instead of requiring it to be unique (defaults to false)
+    pub replace_all: Option<bool>,
+    /// Only return the diff the edit would produce, without changing the file (defaults to false)
+    pub dry_run: Option<bool>,
+    /// One sentence explanation as to why this tool is being used, and how it contributes to the goal.
+    pub explanation: Option<String>,
+}
+
+impl EditFileToolCallParams {
+    pub(crate) fn is_dry_run(&self) -> bool {
+        self.dry_run.unwrap_or(false)
+    }
+
+    /// Applies the edit to the file's current content in memory, returning the absolute path,
+    /// the original content and the edited content. Hunks or snippets that don't match are
+    /// all reported in the error.
+    fn edited_content(&self, cwd: &Path) -> Result<(PathBuf, String, String), String> {
+        let path = resolve_workspace_path(cwd, &self.path)?;
+        let original = read_text_file_for_edit(&path, &self.path)?;
+
+        let edited = match (&self.patch, &self.search, &self.replace) {
+            (Some(patch), _, _) => apply_unified_diff(&original, patch),
+            (None, Some(search), Some(replace)) => {
+                let edits: Vec<(&str, &str)> = search
+                    .iter()
+                    .map(String::as_str)
+                    .zip(replace.iter().map(String::as_str))
+                    .collect();
+                apply_search_replace(&original, &edits, self.replace_all.unwrap_or(false))
+            }
+            _ => Err("either patch or search and replace must be provided".to_string()),
+        }
+        .map_err(|conflicts| format!("{} was not changed:\n{conflicts}", self.path))?;
+
+        if edited == original {
+            return Err(format!("the edit leaves {} unchanged", self.path));
+        }
+        Ok((path, original, edited))
+    }
+
+    /// The unified diff the edit would make, for dry runs
+    pub(crate) fn preview(&self, cwd: &Path) -> Result<String, String> {
+        let (_, original, edited) = self.edited_content(cwd)?;
+        Ok(format!(
+            "Dry run, {} was not modified. The edit would apply this diff:\n{}",
+            self.path,
+            unified_diff_between(&original, &edited)
+        ))
+    }
+
+    /// Resolves the edit into an apply_patch update, so it goes through the same approval and
+    /// writable-root checks as a patch.
+    pub(crate) fn to_apply_patch_action(&self, cwd: &Path) -> Result<ApplyPatchAction, String> {
+        let (path, original, edited) = self.edited_content(cwd)?;
+        let change = ApplyPatchFileChange::Update {
+            unified_diff: unified_diff_between(&original, &edited),
+            move_path: None,
+            new_content: edited,
+        };
+        Ok(ApplyPatchAction::from_changes(HashMap::from([(
+            path, change,
+        )])))
+    }
+}
+
+impl ValidatedToolParams for EditFileToolCallParams {
+    fn validate(&self) -> Result<(), ToolParamError> {
+        if self.path.trim().is_empty() {
+            return Err(ToolParamError::new("path", "path cannot be empty"));
+        }
+        match (&self.patch, &self.search, &self.replace) {
+            (Some(_), None, None) => {}
+            (Some(_), _, _) => {
+                return Err(ToolParamError::new(
+                    "patch",
+                    "provide either patch or search/replace, not both",
+                ));
+            }
+            (None, Some(search), Some(replace)) => {
+                if search.is_empty() {
+                    return Err(ToolParamError::new(
+                        "search",
+                        "search must contain at least one snippet",
+                    ));
+                }
+                if search.len() != replace.len() {
+                    return Err(ToolParamError::new(
+                        "replace",
+                        format!(
+                            "search has {} entries but replace has {}; they must match",
+                            search.len(),
+                            replace.len()
+                        ),
+                    ));
+                }
+                if search.iter().any(String::is_empty) {
+                    return Err(ToolParamError::new(
+                        "search",
+                        "search snippets cannot be empty",
+                    ));
+                }
+            }
+            (None, _, _) => {
+                return Err(ToolParamError::new(
+                    "patch",
+                    "either patch or search and replace must be provided",
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(macros::ToolSchema, Deserialize, Debug, Clone, PartialEq)]
+pub struct ListDirToolCallParams {
+    /// The directory to list, relative to the current working directory (defaults to the workspace root)
+    pub path: Option<String>,
+    /// How many levels deep to list; 1 lists only the directory's direct entries (defaults to 1, at most 10)
+    pub depth: Option<u64>,
+    /// Whether to include hidden files and directories, whose names start with a dot (defaults to false)
+    pub include_hidden: Option<bool>,
+    /// Optional glob that file names must match (e.g. "*.rs"); directories are kept only if they contain a match
+    pub glob: Option<String>,
+    /// One sentence explanation as to why this tool is being used, and how it contributes to the goal.
+    pub explanation: Option<String>,
+}
+
+impl ListDirToolCallParams {
+    const DEFAULT_DEPTH: u64 = 1;
+    const MAX_DEPTH: u64 = 10;
+    /// Most entries returned by a single listing
+    const MAX_ENTRIES: usize = 500;
+
+    pub(crate) async fn execute(&self, sess: &Session) -> anyhow::Result<String> {
+        let relative = self.path.clone().unwrap_or_else(|| ".".to_string());
+        let dir = resolve_workspace_path(&sess.cwd, &relative).map_err(anyhow::Error::msg)?;
+        if !dir.is_dir() {
+            return Err(anyhow::anyhow!("{relative} is not a directory"));
+        }
+        // A deep listing can walk a large tree, so it runs off the async runtime
+        let params = self.clone();
+        tokio::task::spawn_blocking(move || params.listing(&dir, &relative)).await?
+    }
+
+    /// Lists `dir` as an indented tree of names, kinds and sizes. Ignore rules are those of
+    /// the codebase walker: .gitignore, .ignore, .codexignore and the built-in build and
+    /// cache directories.
+    fn listing(&self, dir: &Path, display_name: &str) -> anyhow::Result<String> {
+        let depth = self
+            .depth
+            .unwrap_or(Self::DEFAULT_DEPTH)
+            .clamp(1, Self::MAX_DEPTH) as usize;
+        let glob = self
+            .glob
+            .as_deref()
+            .map(wildmatch::WildMatchPattern::<'*', '?'>::new);
+
+        let mut walker = codebase_search::walk_utils::create_codebase_walker(dir);
+        walker
+            .hidden(!self.include_hidden.unwrap_or(false))
+            .max_depth(Some(depth))
+            .sort_by_file_name(|a, b| a.cmp(b));
+
+        // (depth, line, is_dir) for each entry, in walk order
+        let mut entries: Vec<(usize, String, bool)> = Vec::new();
+        for entry in walker.build().flatten() {
+            if entry.depth() == 0 {
+                continue;
+            }
+            let name = entry.file_name().to_string_lossy().to_string();
+            let file_type = entry.file_type();
+            let is_dir = file_type.is_some_and(|file_type| file_type.is_dir());
+            let line = if is_dir {
+                format!("{name}/")
+            } else if file_type.is_some_and(|file_type| file_type.is_symlink()) {
+                match std::fs::read_link(entry.path()) {
+                    Ok(target) => format!("{name} -> {} (symlink)", target.display()),
+                    Err(_) => format!("{name} (symlink)"),
+                }
+            } else {
+                if glob.as_ref().is_some_and(|glob| !glob.matches(&name)) {
+                    continue;
+                }
+                let size = entry.metadata().map(|metadata| metadata.len()).unwrap_or(0);
+                format!("{name} (file, {})", format_size(size))
+            };
+            entries.push((entry.depth(), line, is_dir));
+        }
+
+        if glob.is_some() {
+            // Keep a directory only if some file below it matched
+            let mut keep = vec![false; entries.len()];
+            let mut open_dirs: Vec<usize> = Vec::new();
+            for (index, (depth, _, is_dir)) in entries.iter().enumerate() {
+                while open_dirs
+                    .last()
+                    .is_some_and(|&dir_index| entries[dir_index].0 >= *depth)
+                {
+                    open_dirs.pop();
+                }
+                if *is_dir {
+                    open_dirs.push(index);
+                } else {
+                    keep[index] = true;
+                    for &dir_index in &open_dirs {
+                        keep[dir_index] = true;
+                    }
+                }
+            }
+            let mut keep = keep.into_iter();
+            entries.retain(|_| keep.next().unwrap_or(false));
+        }
+
+        if entries.is_empty() {
+            return Ok(format!("{display_name} has no entries to show"));
+        }
+        let total = entries.len();
+        let mut output = format!("{display_name}/\n");
+        for (depth, line, _) in entries.iter().take(Self::MAX_ENTRIES) {
+            output.push_str(&"  ".repeat(*depth));
+            output.push_str(line);
+            output.push('\n');
+        }
+        if total > Self::MAX_ENTRIES {
+            output.push_str(&format!(
+                "... ({} more entries not shown; list a subdirectory or use a smaller depth)\n",
+                total - Self::MAX_ENTRIES
+            ));
+        }
+        Ok(output)
+    }
+}
+
+impl ValidatedToolParams for ListDirToolCallParams {
+    fn validate(&self) -> Result<(), ToolParamError> {
+        if self.depth == Some(0) {
+            return Err(ToolParamError::new("depth", "depth must be at least 1"));
+        }
+        if self
+            .glob
+            .as_ref()
+            .is_some_and(|glob| glob.trim().is_empty())
+        {
+            return Err(ToolParamError::new(
+                "glob",
+                "glob cannot be empty when specified",
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[derive(macros::ToolSchema, Deserialize, Debug, Clone, PartialEq)]
+pub struct GrepToolCallParams {
+    /// The regular expression to search for (Rust regex syntax)
+    pub pattern: String,
+    /// File or directory to search, relative to the current working directory (defaults to the workspace root)
+    pub path: Option<String>,
+    /// Whether the search ignores case (defaults to false)
+    pub case_insensitive: Option<bool>,
+    /// Maximum number of matching lines to return (defaults to 100, at most 1000)
+    pub max_results: Option<u64>,
+    /// One sentence explanation as to why this tool is being used, and how it contributes to the goal.
+    pub explanation: Option<String>,
+}
+
+impl GrepToolCallParams {
+    const DEFAULT_MAX_RESULTS: u64 = 100;
+    const MAX_RESULTS_LIMIT: u64 = 1000;
+    /// Matching lines longer than this are cut short in the output
+    const MAX_LINE_CHARS: usize = 300;
+
+    pub(crate) async fn execute(&self, sess: &Session) -> anyhow::Result<String> {
+        let relative = self.path.clone().unwrap_or_else(|| ".".to_string());
+        let target = resolve_workspace_path(&sess.cwd, &relative).map_err(anyhow::Error::msg)?;
+        if !target.exists() {
+            return Err(anyhow::anyhow!("{relative} does not exist"));
+        }
+        // The walk reads every file under the target, so it runs off the async runtime
+        let params = self.clone();
+        let cwd = sess.cwd.clone();
+        tokio::task::spawn_blocking(move || params.search(&target, &cwd)).await?
+    }
+
+    /// Searches `target`, a file or a directory walked with the codebase ignore rules, and
+    /// lists matches as `path:line: text` with paths relative to `cwd`. Files over
+    /// [`MAX_TEXT_FILE_BYTES`] and files that aren't UTF-8 text are skipped.
+    fn search(&self, target: &Path, cwd: &Path) -> anyhow::Result<String> {
+        let regex = regex::RegexBuilder::new(&self.pattern)
+            .case_insensitive(self.case_insensitive.unwrap_or(false))
+            .build()
+            .map_err(|e| anyhow::anyhow!("invalid pattern: {e}"))?;
+        let max_results = self
+            .max_results
+            .unwrap_or(Self::DEFAULT_MAX_RESULTS)
+            .min(Self::MAX_RESULTS_LIMIT) as usize;
+
+        let mut matches: Vec<String> = Vec::new();
+        let mut files_searched = 0;
+        let mut limit_reached = false;
+        let mut unreadable = 0;
+        let mut search_file = |path: &Path| -> bool {
+            // A file that can't be read (permissions, a dangling symlink, a race with
+            // deletion) is skipped rather than failing the whole search
+            let content = match Self::searchable_text(path) {
+                Ok(Some(content)) => content,
+                Ok(None) => return true,
+                Err(_) => {
+                    unreadable += 1;
+                    return true;
+                }
+            };
+            files_searched += 1;
+            let display_path = path.strip_prefix(cwd).unwrap_or(path);
+            for (index, line) in content.lines().enumerate() {
+                if !regex.is_match(line) {
+                    continue;
+                }
+                if matches.len() == max_results {
+                    limit_reached = true;
+                    return false;
+                }
+                let text: String = line.chars().take(Self::MAX_LINE_CHARS).collect();
+                let ellipsis = if text.len() < line.len() { "..." } else { "" };
+                matches.push(format!(
+                    "{}:{}: {}{ellipsis}",
+                    display_path.display(),
+                    index + 1,
+                    text.trim_end()
+                ));
+            }
+            true
+        };
+
+        if target.is_file() {
+            search_file(target);
+        } else {
+            codebase_search::walk_utils::walk_codebase_files(target, |path| Ok(search_file(path)))?;
+        }
+
+        let skipped = if unreadable > 0 {
+            format!(" ({unreadable} unreadable files skipped)")
+        } else {
+            String::new()
+        };
+        if matches.is_empty() {
+            return Ok(format!(
+                "No matches for /{}/ in {files_searched} files{skipped}",
+                self.pattern
+            ));
+        }
+        let mut output = matches.join("\n");
+        output.push('\n');
+        if limit_reached {
+            output.push_str(&format!(
+                "... (stopped after {max_results} matches; narrow the pattern or path, or raise max_results)\n"
+            ));
+        }
+        if unreadable > 0 {
+            output.push_str(&format!("...{skipped}\n"));
+        }
+        Ok(output)
+    }
+
+    /// The text of `path`, or None if it is too large or not UTF-8
+    fn searchable_text(path: &Path) -> std::io::Result<Option<String>> {
+        if std::fs::metadata(path)?.len() > MAX_TEXT_FILE_BYTES {
+            return Ok(None);
+        }
+        Ok(String::from_utf8(std::fs::read(path)?).ok())
+    }
+}
+
+impl ValidatedToolParams for GrepToolCallParams {
+    fn validate(&self) -> Result<(), ToolParamError> {
+        if self.pattern.is_empty() {
+            return Err(ToolParamError::new("pattern", "pattern cannot be empty"));
+        }
+        if self.max_results == Some(0) {
+            return Err(ToolParamError::new(
+                "max_results",
+                "max_results must be greater than 0",
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// `path` joined to `cwd`, or an error if it escapes the workspace, whether through
+/// `..` or through a symlink inside the workspace that points outside it
+fn resolve_workspace_path(cwd: &Path, path: &str) -> Result<PathBuf, String> {
+    let resolved = normalize_path(&cwd.join(path));
+    let real_cwd = cwd.canonicalize().unwrap_or_else(|_| normalize_path(cwd));
+    let inside = resolved.starts_with(normalize_path(cwd))
+        && canonicalize_existing(&resolved).is_some_and(|real| real.starts_with(&real_cwd));
+    if !inside {
+        return Err(format!(
+            "path {path} is outside the workspace {}",
+            cwd.display()
+        ));
+    }
+    Ok(resolved)
+}
+
+/// `path` with symlinks resolved: its longest existing ancestor canonicalized and the
+/// missing components appended. `None` if that ancestor is a dangling symlink, which a
+/// write could follow anywhere.
+fn canonicalize_existing(path: &Path) -> Option<PathBuf> {
+    let mut existing = path;
+    let mut missing = Vec::new();
+    loop {
+        if let Ok(canonical) = existing.canonicalize() {
+            return Some(
+                missing
+                    .iter()
+                    .rev()
+                    .fold(canonical, |real, name| real.join(name)),
+            );
+        }
+        if existing.is_symlink() {
+            return None;
+        }
+        missing.push(existing.file_name()?);
+        existing = existing.parent()?;
+    }
+}
+
+/// Lexically resolves `.` and `..` components, without touching the filesystem
+fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other.as_os_str()),
+        }
+    }
+    normalized
+}
+
 #[derive(Debug, Clone)]
 pub struct FunctionCallOutputPayload {
     pub content: String,
-    #[expect(dead_code)]
     pub success: Option<bool>,
+    /// Details about how the call ran, such as the exit code of a command
+    pub metadata: Option<FunctionCallOutputMetadata>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct FunctionCallOutputMetadata {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<i32>,
+    /// Wall time of the call, rounded to 1 decimal place
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration_seconds: Option<f32>,
+    /// Line a paged read continues from, when its output was cut short
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_start_line: Option<u64>,
+    /// Argument that failed validation, when the call was rejected
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub invalid_argument: Option<String>,
+}
+
+impl FunctionCallOutputMetadata {
+    pub fn with_duration(mut self, duration: std::time::Duration) -> Self {
+        self.duration_seconds = Some((duration.as_secs_f32() * 10.0).round() / 10.0);
+        self
+    }
+}
+
+impl FunctionCallOutputPayload {
+    /// The output as a bare string, the only form every provider accepts. Exec
+    /// output is sent as a pre-serialized JSON payload carrying its metadata.
+    pub fn to_plain_string(&self) -> String {
+        #[derive(Serialize)]
+        struct ExecMetadata {
+            exit_code: i32,
+            duration_seconds: f32,
+        }
+
+        #[derive(Serialize)]
+        struct ExecOutput<'a> {
+            output: &'a str,
+            metadata: ExecMetadata,
+        }
+
+        let Some(exit_code) = self.metadata.as_ref().and_then(|m| m.exit_code) else {
+            return self.content.clone();
+        };
+        let payload = ExecOutput {
+            output: &self.content,
+            metadata: ExecMetadata {
+                exit_code,
+                duration_seconds: self
+                    .metadata
+                    .as_ref()
+                    .and_then(|m| m.duration_seconds)
+                    .unwrap_or_default(),
+            },
+        };
+        serde_json::to_string(&payload).unwrap_or_else(|_| self.content.clone())
+    }
+
+    /// The output as a `{ content, success, metadata }` object, for providers
+    /// configured with `structured_function_call_outputs`.
+    pub fn to_structured_value(&self) -> serde_json::Value {
+        #[derive(Serialize)]
+        struct StructuredOutput<'a> {
+            content: &'a str,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            success: Option<bool>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            metadata: Option<&'a FunctionCallOutputMetadata>,
+        }
+
+        serde_json::json!(StructuredOutput {
+            content: &self.content,
+            success: self.success,
+            metadata: self.metadata.as_ref(),
+        })
+    }
 }
 
 // The Responses API expects two *different* shapes depending on success vs failure:
@@ -462,9 +1544,10 @@ impl Serialize for FunctionCallOutputPayload {
         // of whether the function call succeeded or failed. The boolean is purely informational
         // for local bookkeeping and is NOT sent to the OpenAI endpoint. Sending the nested object
         // form `{ content, success:false }` triggers the 400 we are still seeing. Mirror the JS CLI
-        // exactly: always emit a bare string.
+        // exactly: always emit a bare string. Providers that accept the object form opt in
+        // through `structured_function_call_outputs`, see `to_structured_value`.
 
-        serializer.serialize_str(&self.content)
+        serializer.serialize_str(&self.to_plain_string())
     }
 }
 
@@ -477,6 +1560,7 @@ impl<'de> Deserialize<'de> for FunctionCallOutputPayload {
         Ok(FunctionCallOutputPayload {
             content: s,
             success: None,
+            metadata: None,
         })
     }
 }
@@ -503,6 +1587,7 @@ mod tests {
     #![allow(clippy::unwrap_used)]
     use super::*;
     use crate::openai_tools::ToJsonSchema;
+    use crate::openai_tools::ToolEnum;
 
     #[test]
     fn serializes_success_as_plain_string() {
@@ -511,6 +1596,7 @@ mod tests {
             output: FunctionCallOutputPayload {
                 content: "ok".into(),
                 success: None,
+                metadata: None,
             },
         };
 
@@ -528,6 +1614,7 @@ mod tests {
             output: FunctionCallOutputPayload {
                 content: "bad".into(),
                 success: Some(false),
+                metadata: None,
             },
         };
 
@@ -537,6 +1624,36 @@ mod tests {
         assert_eq!(v.get("output").unwrap().as_str().unwrap(), "bad");
     }
 
+    #[test]
+    fn exec_output_metadata_follows_output_mode() {
+        let output = FunctionCallOutputPayload {
+            content: "hello\n".into(),
+            success: Some(true),
+            metadata: Some(FunctionCallOutputMetadata {
+                exit_code: Some(0),
+                duration_seconds: Some(1.5),
+                ..Default::default()
+            }),
+        };
+
+        let plain: serde_json::Value = serde_json::from_str(&output.to_plain_string()).unwrap();
+        assert_eq!(
+            plain,
+            serde_json::json!({
+                "output": "hello\n",
+                "metadata": {"exit_code": 0, "duration_seconds": 1.5},
+            })
+        );
+        assert_eq!(
+            output.to_structured_value(),
+            serde_json::json!({
+                "content": "hello\n",
+                "success": true,
+                "metadata": {"exit_code": 0, "duration_seconds": 1.5},
+            })
+        );
+    }
+
     #[test]
     fn deserialize_shell_tool_call_params() {
         let json = r#"{
@@ -551,6 +1668,7 @@ mod tests {
                 command: vec!["ls".to_string(), "-l".to_string()],
                 workdir: Some("/tmp".to_string()),
                 timeout: Some(1000),
+                background: None,
                 explanation: None,
             },
             params
@@ -564,6 +1682,8 @@ mod tests {
             should_read_entire_file: false,
             start_line_one_indexed: Some(1),
             end_line_one_indexed_inclusive: Some(10),
+            max_output_tokens: None,
+            max_bytes: None,
             explanation: None,
         };
         assert!(params.validate().is_ok());
@@ -576,11 +1696,13 @@ mod tests {
             should_read_entire_file: false,
             start_line_one_indexed: Some(10),
             end_line_one_indexed_inclusive: Some(5),
+            max_output_tokens: None,
+            max_bytes: None,
             explanation: None,
         };
         let result = params.validate();
         assert!(result.is_err());
-        assert!(result.unwrap_err().contains("start_line_one_indexed (10) must be less than or equal to end_line_one_indexed_inclusive (5)"));
+        assert!(result.unwrap_err().message.contains("start_line_one_indexed (10) must be less than or equal to end_line_one_indexed_inclusive (5)"));
     }
 
     #[test]
@@ -590,6 +1712,8 @@ mod tests {
             should_read_entire_file: false,
             start_line_one_indexed: Some(0),
             end_line_one_indexed_inclusive: Some(10),
+            max_output_tokens: None,
+            max_bytes: None,
             explanation: None,
         };
         let result = params.validate();
@@ -597,6 +1721,7 @@ mod tests {
         assert!(
             result
                 .unwrap_err()
+                .message
                 .contains("start_line_one_indexed must be greater than 0")
         );
     }
@@ -608,6 +1733,8 @@ mod tests {
             should_read_entire_file: false,
             start_line_one_indexed: None,
             end_line_one_indexed_inclusive: Some(10),
+            max_output_tokens: None,
+            max_bytes: None,
             explanation: None,
         };
         let result = params.validate();
@@ -615,6 +1742,7 @@ mod tests {
         assert!(
             result
                 .unwrap_err()
+                .message
                 .contains("start_line_one_indexed and end_line_one_indexed_inclusive are required")
         );
     }
@@ -626,11 +1754,13 @@ mod tests {
             should_read_entire_file: true,
             start_line_one_indexed: None,
             end_line_one_indexed_inclusive: None,
+            max_output_tokens: None,
+            max_bytes: None,
             explanation: None,
         };
         let result = params.validate();
         assert!(result.is_err());
-        assert!(result.unwrap_err().contains("path cannot be empty"));
+        assert!(result.unwrap_err().message.contains("path cannot be empty"));
     }
 
     #[test]
@@ -640,6 +1770,8 @@ mod tests {
             should_read_entire_file: true,
             start_line_one_indexed: None,
             end_line_one_indexed_inclusive: None,
+            max_output_tokens: None,
+            max_bytes: None,
             explanation: None,
         };
         assert!(params.validate().is_ok());
@@ -652,9 +1784,355 @@ mod tests {
             should_read_entire_file: false,
             start_line_one_indexed: Some(5),
             end_line_one_indexed_inclusive: Some(5),
+            max_output_tokens: None,
+            max_bytes: None,
+            explanation: None,
+        };
+        assert!(params.validate().is_ok());
+    }
+
+    #[test]
+    fn test_read_file_number_lines_slices_and_prefixes() {
+        let content = "fn main() {\n    println!(\"héllo\");\n}\n";
+        let output = ReadFileToolCallParams::number_lines(
+            content,
+            2,
+            Some(10),
+            ReadFileToolCallParams::MAX_BYTES,
+        )
+        .unwrap();
+        assert_eq!(output.text, "     2\t    println!(\"héllo\");\n     3\t}\n");
+        assert_eq!(output.next_start_line, None);
+
+        let err = ReadFileToolCallParams::number_lines(
+            content,
+            4,
+            None,
+            ReadFileToolCallParams::MAX_BYTES,
+        )
+        .unwrap_err();
+        assert!(err.contains("past the end of the file (3 lines)"));
+    }
+
+    #[test]
+    fn test_read_file_number_lines_truncates_long_files() {
+        let content = "x\n".repeat(ReadFileToolCallParams::MAX_LINES + 5);
+        let output = ReadFileToolCallParams::number_lines(
+            &content,
+            1,
+            None,
+            ReadFileToolCallParams::MAX_BYTES,
+        )
+        .unwrap();
+        assert_eq!(
+            output.text.lines().count(),
+            ReadFileToolCallParams::MAX_LINES + 1
+        );
+        assert!(output.text.ends_with(&format!(
+            "... (truncated: showing lines 1-{} of {}; read from line {} to see more)\n",
+            ReadFileToolCallParams::MAX_LINES,
+            ReadFileToolCallParams::MAX_LINES + 5,
+            ReadFileToolCallParams::MAX_LINES + 1
+        )));
+    }
+
+    #[test]
+    fn test_read_file_budget_stops_at_line_boundary_with_cursor() {
+        let params = ReadFileToolCallParams {
+            path: "test.txt".to_string(),
+            should_read_entire_file: true,
+            start_line_one_indexed: None,
+            end_line_one_indexed_inclusive: None,
+            max_output_tokens: Some(7),
+            max_bytes: Some(100),
+            explanation: None,
+        };
+        // The token budget is the tighter one: about 28 bytes
+        assert_eq!(params.byte_budget(), 28);
+
+        let content = "alpha\nbeta\ngamma\n";
+        let output =
+            ReadFileToolCallParams::number_lines(content, 1, None, params.byte_budget()).unwrap();
+        assert!(output.text.starts_with("     1\talpha\n     2\tbeta\n..."));
+        assert_eq!(output.next_start_line, Some(3));
+
+        // A single line over the budget is cut off instead of returning nothing
+        let output = ReadFileToolCallParams::number_lines("abcdefgh\nz\n", 1, None, 4).unwrap();
+        assert!(
+            output
+                .text
+                .starts_with("     1\tabcd ... (line cut off after 4 of 8 bytes)\n")
+        );
+        assert_eq!(output.next_start_line, Some(2));
+    }
+
+    #[test]
+    fn test_read_file_attaches_images() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("diagram.png");
+        std::fs::write(&path, b"\x89PNG\r\n\x1a\n").unwrap();
+        let params = ReadFileToolCallParams {
+            path: "diagram.png".to_string(),
+            should_read_entire_file: true,
+            start_line_one_indexed: None,
+            end_line_one_indexed_inclusive: None,
+            max_output_tokens: None,
+            max_bytes: None,
+            explanation: None,
+        };
+
+        let mime = supported_image_mime(&path).unwrap();
+        let output = params.read_image(&path, &mime).unwrap();
+        assert_eq!(
+            output.text,
+            "diagram.png is an image (8 B, image/png); it is attached for you to look at."
+        );
+        let Some(ContentItem::InputImage { image_url }) = output.image else {
+            panic!("expected an image attachment");
+        };
+        assert_eq!(image_url, "data:image/png;base64,iVBORw0KGgo=");
+    }
+
+    #[test]
+    fn test_write_file_rejects_paths_outside_the_workspace() {
+        let cwd = std::env::temp_dir().join("codex-write-file-test");
+        let params = WriteFileToolCallParams {
+            path: "../escape.txt".to_string(),
+            content: "hi".to_string(),
+            create_dirs: None,
+            overwrite: None,
+            explanation: None,
+        };
+        assert!(params.validate().is_ok());
+        let err = params.to_apply_patch_action(&cwd).unwrap_err();
+        assert!(err.contains("outside the workspace"));
+    }
+
+    #[test]
+    fn test_write_file_checks_parent_and_existing_file() {
+        let cwd = tempfile::tempdir().unwrap();
+        std::fs::write(cwd.path().join("exists.txt"), "old\n").unwrap();
+        let params = WriteFileToolCallParams {
+            path: "new/dir/file.txt".to_string(),
+            content: "new\n".to_string(),
+            create_dirs: None,
+            overwrite: None,
+            explanation: None,
+        };
+        assert!(params.to_apply_patch_action(cwd.path()).is_err());
+
+        let create = WriteFileToolCallParams {
+            create_dirs: Some(true),
+            ..params.clone()
+        };
+        let action = create.to_apply_patch_action(cwd.path()).unwrap();
+        assert_eq!(
+            action.changes().get(&cwd.path().join("new/dir/file.txt")),
+            Some(&ApplyPatchFileChange::Add {
+                content: "new\n".to_string()
+            })
+        );
+
+        let existing = WriteFileToolCallParams {
+            path: "./exists.txt".to_string(),
+            ..params
+        };
+        assert!(existing.to_apply_patch_action(cwd.path()).is_err());
+        let overwrite = WriteFileToolCallParams {
+            overwrite: Some(true),
+            ..existing
+        };
+        let action = overwrite.to_apply_patch_action(cwd.path()).unwrap();
+        assert!(matches!(
+            action.changes().get(&cwd.path().join("exists.txt")),
+            Some(ApplyPatchFileChange::Update { new_content, .. }) if new_content == "new\n"
+        ));
+    }
+
+    #[test]
+    fn test_edit_file_validation() {
+        let params = EditFileToolCallParams {
+            path: "src/main.rs".to_string(),
+            patch: None,
+            search: Some(vec!["old".to_string()]),
+            replace: Some(vec!["new".to_string()]),
+            replace_all: None,
+            dry_run: None,
             explanation: None,
         };
         assert!(params.validate().is_ok());
+
+        let mismatched = EditFileToolCallParams {
+            replace: Some(vec![]),
+            ..params.clone()
+        };
+        assert!(mismatched.validate().is_err());
+
+        let both = EditFileToolCallParams {
+            patch: Some("@@ -1 +1 @@\n-old\n+new\n".to_string()),
+            ..params.clone()
+        };
+        assert!(both.validate().is_err());
+
+        let neither = EditFileToolCallParams {
+            search: None,
+            replace: None,
+            ..params
+        };
+        assert!(neither.validate().is_err());
+    }
+
+    #[test]
+    fn test_list_dir_respects_ignore_rules_and_glob() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        std::fs::create_dir_all(root.join("src/nested")).unwrap();
+        std::fs::create_dir_all(root.join("target/debug")).unwrap();
+        std::fs::create_dir_all(root.join("docs")).unwrap();
+        std::fs::write(root.join("src/lib.rs"), "x".repeat(2048)).unwrap();
+        std::fs::write(root.join("src/nested/mod.rs"), "").unwrap();
+        std::fs::write(root.join("docs/guide.md"), "").unwrap();
+        std::fs::write(root.join("target/debug/out"), "").unwrap();
+        std::fs::write(root.join(".env"), "").unwrap();
+
+        let params = ListDirToolCallParams {
+            path: None,
+            depth: Some(3),
+            include_hidden: None,
+            glob: None,
+            explanation: None,
+        };
+        let listing = params.listing(root, ".").unwrap();
+        assert_eq!(
+            listing,
+            "./\n  docs/\n    guide.md (file, 0 B)\n  src/\n    lib.rs (file, 2.0 KiB)\n    nested/\n      mod.rs (file, 0 B)\n"
+        );
+
+        let rust_only = ListDirToolCallParams {
+            include_hidden: Some(true),
+            glob: Some("*.rs".to_string()),
+            ..params
+        };
+        let listing = rust_only.listing(root, ".").unwrap();
+        assert!(!listing.contains("docs/"));
+        assert!(!listing.contains("target/"));
+        assert!(listing.contains("      mod.rs"));
+    }
+
+    #[test]
+    fn test_grep_skips_ignored_files_and_stops_at_max_results() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::fs::create_dir_all(root.join("target")).unwrap();
+        std::fs::write(
+            root.join("src/lib.rs"),
+            "fn retry() {}\nfn Retry_all() {}\n",
+        )
+        .unwrap();
+        std::fs::write(root.join("target/gen.rs"), "fn retry() {}\n").unwrap();
+
+        let params = GrepToolCallParams {
+            pattern: r"fn retry".to_string(),
+            path: None,
+            case_insensitive: Some(true),
+            max_results: None,
+            explanation: None,
+        };
+        assert_eq!(
+            params.search(root, root).unwrap(),
+            "src/lib.rs:1: fn retry() {}\nsrc/lib.rs:2: fn Retry_all() {}\n"
+        );
+
+        let limited = GrepToolCallParams {
+            max_results: Some(1),
+            ..params
+        };
+        let output = limited.search(&root.join("src/lib.rs"), root).unwrap();
+        assert!(output.starts_with("src/lib.rs:1: fn retry() {}\n... (stopped after 1 matches"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_workspace_paths_cannot_escape_through_symlinks() {
+        let workspace = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        let cwd = workspace.path();
+        std::fs::write(outside.path().join("secret.txt"), "secret\n").unwrap();
+        std::fs::create_dir(cwd.join("src")).unwrap();
+        std::os::unix::fs::symlink(outside.path(), cwd.join("link")).unwrap();
+        std::os::unix::fs::symlink(outside.path().join("new.txt"), cwd.join("dangling")).unwrap();
+        std::os::unix::fs::symlink(cwd.join("src"), cwd.join("src_link")).unwrap();
+
+        for escaping in ["link", "link/secret.txt", "link/new/file.txt", "dangling"] {
+            let err = resolve_workspace_path(cwd, escaping).unwrap_err();
+            assert!(err.contains("outside the workspace"), "{escaping}: {err}");
+        }
+        assert_eq!(
+            resolve_workspace_path(cwd, "src_link/new/lib.rs").unwrap(),
+            normalize_path(&cwd.join("src_link/new/lib.rs"))
+        );
+        assert!(resolve_workspace_path(cwd, "src/../new.txt").is_ok());
+    }
+
+    #[test]
+    fn test_grep_skips_large_and_non_utf8_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        std::fs::write(root.join("lib.rs"), "fn retry() {}\n").unwrap();
+        std::fs::write(root.join("latin1.py"), b"fn retry() {} caf\xe9\n").unwrap();
+        let large = std::fs::File::create(root.join("dump.sql")).unwrap();
+        std::io::Write::write_all(&mut &large, b"fn retry() {}\n").unwrap();
+        large.set_len(MAX_TEXT_FILE_BYTES + 1).unwrap();
+
+        let params = GrepToolCallParams {
+            pattern: r"fn retry".to_string(),
+            path: None,
+            case_insensitive: None,
+            max_results: None,
+            explanation: None,
+        };
+        assert_eq!(
+            params.search(root, root).unwrap(),
+            "lib.rs:1: fn retry() {}\n"
+        );
+        assert_eq!(
+            params.search(&root.join("dump.sql"), root).unwrap(),
+            "No matches for /fn retry/ in 0 files"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_grep_skips_unreadable_files() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        std::fs::write(root.join("lib.rs"), "fn retry() {}\n").unwrap();
+        let locked = root.join("locked.rs");
+        std::fs::write(&locked, "fn retry() {}\n").unwrap();
+        std::fs::set_permissions(&locked, std::fs::Permissions::from_mode(0o000)).unwrap();
+        if std::fs::read(&locked).is_ok() {
+            // Permissions don't stop root
+            return;
+        }
+
+        let params = GrepToolCallParams {
+            pattern: r"fn retry".to_string(),
+            path: None,
+            case_insensitive: None,
+            max_results: None,
+            explanation: None,
+        };
+        assert_eq!(
+            params.search(root, root).unwrap(),
+            "lib.rs:1: fn retry() {}\n... (1 unreadable files skipped)\n"
+        );
+        assert_eq!(
+            params.search(&locked, root).unwrap(),
+            "No matches for /fn retry/ in 0 files (1 unreadable files skipped)"
+        );
     }
 
     #[test]
@@ -674,4 +2152,90 @@ mod tests {
         };
         assert!(params.validate().is_ok());
     }
+
+    #[test]
+    fn test_codebase_search_kinds_are_enum_values() {
+        let schema = serde_json::to_value(CodebaseSearchToolCallParams::to_json_schema()).unwrap();
+        let kinds = &schema["properties"]["kinds"];
+        assert_eq!(kinds["type"], "array");
+        assert_eq!(kinds["items"]["type"], "string");
+        assert_eq!(kinds["items"]["enum"][0], "function");
+        assert_eq!(
+            kinds["items"]["enum"].as_array().unwrap().len(),
+            SymbolKindFilter::VALUES.len()
+        );
+
+        let params: CodebaseSearchToolCallParams =
+            serde_json::from_str(r#"{"query": "retry", "kinds": ["method", "trait"]}"#).unwrap();
+        assert_eq!(
+            params.kinds,
+            Some(vec![SymbolKindFilter::Method, SymbolKindFilter::Trait])
+        );
+        assert!(
+            serde_json::from_str::<CodebaseSearchToolCallParams>(
+                r#"{"query": "retry", "kinds": ["widget"]}"#
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_symbol_kind_filters_match_symbol_kinds() {
+        // Exhaustive, so a new SymbolKind doesn't compile until it can be filtered on
+        fn filter_for(kind: SymbolKind) -> SymbolKindFilter {
+            match kind {
+                SymbolKind::Function => SymbolKindFilter::Function,
+                SymbolKind::Method => SymbolKindFilter::Method,
+                SymbolKind::Struct => SymbolKindFilter::Struct,
+                SymbolKind::Enum => SymbolKindFilter::Enum,
+                SymbolKind::Trait => SymbolKindFilter::Trait,
+                SymbolKind::Impl => SymbolKindFilter::Impl,
+                SymbolKind::Module => SymbolKindFilter::Module,
+                SymbolKind::Constant => SymbolKindFilter::Constant,
+                SymbolKind::Variable => SymbolKindFilter::Variable,
+                SymbolKind::Class => SymbolKindFilter::Class,
+                SymbolKind::Interface => SymbolKindFilter::Interface,
+                SymbolKind::Type => SymbolKindFilter::Type,
+                SymbolKind::Macro => SymbolKindFilter::Macro,
+                SymbolKind::Property => SymbolKindFilter::Property,
+                SymbolKind::Table => SymbolKindFilter::Table,
+                SymbolKind::View => SymbolKindFilter::View,
+                SymbolKind::Resource => SymbolKindFilter::Resource,
+                SymbolKind::Import => SymbolKindFilter::Import,
+            }
+        }
+
+        for value in SymbolKindFilter::VALUES {
+            let filter: SymbolKindFilter =
+                serde_json::from_value(serde_json::json!(value)).unwrap();
+            let kind = SymbolKind::from(filter);
+            assert_eq!(SymbolKind::from_name(value), Some(kind.clone()), "{value}");
+            assert_eq!(filter_for(kind), filter);
+        }
+    }
+
+    #[test]
+    fn test_codebase_search_validation() {
+        let params: CodebaseSearchToolCallParams =
+            serde_json::from_str(r#"{"query": "where is the config loaded"}"#).unwrap();
+        assert!(params.validate().is_ok());
+
+        let empty = CodebaseSearchToolCallParams {
+            query: "  ".to_string(),
+            ..params.clone()
+        };
+        assert!(empty.validate().is_err());
+
+        let zero_limit = CodebaseSearchToolCallParams {
+            limit: Some(0),
+            ..params.clone()
+        };
+        assert!(zero_limit.validate().is_err());
+
+        let bad_score = CodebaseSearchToolCallParams {
+            min_score: Some(1.5),
+            ..params
+        };
+        assert!(bad_score.validate().is_err());
+    }
 }
@@ -1,21 +1,20 @@
 use serde::Serialize;
 use serde_json::json;
 use std::collections::BTreeMap;
-use std::sync::LazyLock;
 use tracing::debug;
 
 use crate::client_common::Prompt;
-use crate::models::FuzzySearchToolCallParams;
-use crate::models::ReadFileToolCallParams;
-use crate::models::RegexSearchToolCallParams;
-use crate::models::ShellToolCallParams;
-use crate::plan_tool::PLAN_TOOL;
 
 /// Trait for types that can provide JSON schema for OpenAI tools
 pub trait ToJsonSchema {
     fn to_json_schema() -> JsonSchema;
 }
 
+/// Trait for enums used as tool parameter fields, listing the values the model may send
+pub trait ToolEnum {
+    const VALUES: &'static [&'static str];
+}
+
 #[derive(Debug, Clone, Serialize)]
 pub(crate) struct ResponsesApiTool {
     pub(crate) name: &'static str,
@@ -43,7 +42,7 @@ pub(crate) enum JsonSchema {
     Number,
     Boolean,
     Array {
-        items: Box<JsonSchema>,
+        items: Box<Property>,
     },
     Object {
         properties: BTreeMap<String, Property>,
@@ -82,63 +81,11 @@ where
     })
 }
 
-/// Tool usage specification
-static DEFAULT_TOOLS: LazyLock<Vec<OpenAiTool>> = LazyLock::new(|| {
-    vec![
-        create_tool_from_struct::<ShellToolCallParams>(
-            "execute_command",
-            "Runs a shell command, and returns its output.",
-        ),
-        create_tool_from_struct::<ReadFileToolCallParams>(
-            "read_file",
-            "Read the contents of a file at the specified path.",
-        ),
-        create_tool_from_struct::<RegexSearchToolCallParams>(
-            "regex_search",
-            "Searches for regex patterns in files using ripgrep. Returns up to 50 matches with support for case sensitivity, file inclusion/exclusion patterns.",
-        ),
-        create_tool_from_struct::<FuzzySearchToolCallParams>(
-            "file_search",
-            "Fast file search based on fuzzy matching against file path. Use if you know part of the file path but don't know where it's located exactly. Response will be capped to 10 results. Make your query more specific if need to filter results further.",
-        ),
-    ]
-});
-
-static DEFAULT_CODEX_MODEL_TOOLS: LazyLock<Vec<OpenAiTool>> =
-    LazyLock::new(|| vec![OpenAiTool::LocalShell {}]);
-
 /// Returns JSON values that are compatible with Function Calling in the
 /// Responses API:
 /// https://platform.openai.com/docs/guides/function-calling?api-mode=responses
-pub(crate) fn create_tools_json_for_responses_api(
-    prompt: &Prompt,
-    model: &str,
-    include_plan_tool: bool,
-) -> crate::error::Result<Vec<serde_json::Value>> {
-    // Assemble tool list: built-in tools + any extra tools from the prompt.
-    debug!("model: {:?}", model);
-    let default_tools = if model.starts_with("codex") {
-        &DEFAULT_CODEX_MODEL_TOOLS
-    } else {
-        &DEFAULT_TOOLS
-    };
-    let mut tools_json = Vec::with_capacity(default_tools.len() + prompt.extra_tools.len());
-    for t in default_tools.iter() {
-        tools_json.push(serde_json::to_value(t)?);
-    }
-    tools_json.extend(
-        prompt
-            .extra_tools
-            .clone()
-            .into_iter()
-            .map(|(name, tool)| mcp_tool_to_openai_tool(name, tool)),
-    );
-
-    if include_plan_tool {
-        tools_json.push(serde_json::to_value(PLAN_TOOL.clone())?);
-    }
-
-    Ok(tools_json)
+pub(crate) fn create_tools_json_for_responses_api(prompt: &Prompt) -> Vec<serde_json::Value> {
+    prompt.tools.clone()
 }
 
 /// Returns JSON values that are compatible with Function Calling in the
@@ -146,16 +93,13 @@ pub(crate) fn create_tools_json_for_responses_api(
 /// https://platform.openai.com/docs/guides/function-calling?api-mode=chat
 pub(crate) fn create_tools_json_for_chat_completions_api(
     prompt: &Prompt,
-    model: &str,
-    include_plan_tool: bool,
-) -> crate::error::Result<Vec<serde_json::Value>> {
+) -> Vec<serde_json::Value> {
     // We start with the JSON for the Responses API and than rewrite it to match
     // the chat completions tool call format.
-    let responses_api_tools_json =
-        create_tools_json_for_responses_api(prompt, model, include_plan_tool)?;
+    let responses_api_tools_json = create_tools_json_for_responses_api(prompt);
 
     debug!("responses_api_tools_json: {:?}", responses_api_tools_json);
-    let tools_json = responses_api_tools_json
+    responses_api_tools_json
         .into_iter()
         .filter_map(|mut tool| {
             if tool.get("type") != Some(&serde_json::Value::String("function".to_string())) {
@@ -173,11 +117,10 @@ pub(crate) fn create_tools_json_for_chat_completions_api(
                 None
             }
         })
-        .collect::<Vec<serde_json::Value>>();
-    Ok(tools_json)
+        .collect::<Vec<serde_json::Value>>()
 }
 
-fn mcp_tool_to_openai_tool(
+pub(crate) fn mcp_tool_to_openai_tool(
     fully_qualified_name: String,
     tool: mcp_types::Tool,
 ) -> serde_json::Value {
@@ -13,6 +13,9 @@ use crate::openai_tools::Property;
 use crate::openai_tools::ResponsesApiTool;
 use crate::protocol::Event;
 use crate::protocol::EventMsg;
+use crate::tool_params::ToolParamError;
+use crate::tool_params::ValidatedToolParams;
+use crate::tool_params::parse_tool_params;
 
 // Types for the TODO tool arguments matching codex-vscode/todo-mcp/src/main.rs
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -38,6 +41,26 @@ pub struct UpdatePlanArgs {
     pub plan: Vec<PlanItemArg>,
 }
 
+impl ValidatedToolParams for UpdatePlanArgs {
+    fn validate(&self) -> Result<(), ToolParamError> {
+        if self.plan.iter().any(|item| item.step.trim().is_empty()) {
+            return Err(ToolParamError::new("plan", "plan steps cannot be empty"));
+        }
+        let in_progress = self
+            .plan
+            .iter()
+            .filter(|item| matches!(item.status, StepStatus::InProgress))
+            .count();
+        if in_progress > 1 {
+            return Err(ToolParamError::new(
+                "plan",
+                format!("{in_progress} steps are in_progress; at most one step can be"),
+            ));
+        }
+        Ok(())
+    }
+}
+
 pub(crate) static PLAN_TOOL: LazyLock<OpenAiTool> = LazyLock::new(|| {
     let mut plan_item_props = BTreeMap::new();
     plan_item_props.insert(
@@ -58,11 +81,11 @@ pub(crate) static PLAN_TOOL: LazyLock<OpenAiTool> = LazyLock::new(|| {
     );
 
     let plan_items_schema = JsonSchema::Array {
-        items: Box::new(JsonSchema::Object {
+        items: Box::new(Property::Simple(JsonSchema::Object {
             properties: plan_item_props,
             required: &["step", "status"],
             additional_properties: false,
-        }),
+        })),
     };
 
     let mut properties = BTreeMap::new();
@@ -107,13 +130,14 @@ pub(crate) async fn handle_update_plan(
     sub_id: String,
     call_id: String,
 ) -> ResponseInputItem {
-    match parse_update_plan_arguments(arguments, &call_id) {
+    match parse_tool_params::<UpdatePlanArgs>(&arguments, &call_id) {
         Ok(args) => {
             let output = ResponseInputItem::FunctionCallOutput {
                 call_id,
                 output: FunctionCallOutputPayload {
                     content: "Plan updated".to_string(),
                     success: Some(true),
+                    metadata: None,
                 },
             };
             session
@@ -127,22 +151,3 @@ pub(crate) async fn handle_update_plan(
         Err(output) => *output,
     }
 }
-
-fn parse_update_plan_arguments(
-    arguments: String,
-    call_id: &str,
-) -> Result<UpdatePlanArgs, Box<ResponseInputItem>> {
-    match serde_json::from_str::<UpdatePlanArgs>(&arguments) {
-        Ok(args) => Ok(args),
-        Err(e) => {
-            let output = ResponseInputItem::FunctionCallOutput {
-                call_id: call_id.to_string(),
-                output: FunctionCallOutputPayload {
-                    content: format!("failed to parse function arguments: {e}"),
-                    success: None,
-                },
-            };
-            Err(Box::new(output))
-        }
-    }
-}
@@ -318,6 +318,11 @@ pub enum EventMsg {
     /// Notification that the server is about to execute a command.
     ExecCommandBegin(ExecCommandBeginEvent),
 
+    /// Incremental chunk of output from a running command. A command retried
+    /// without the sandbox ends the failed attempt with `ExecCommandEnd` and
+    /// streams the retry after a new `ExecCommandBegin` with the same call id.
+    ExecCommandOutputDelta(ExecCommandOutputDeltaEvent),
+
     ExecCommandEnd(ExecCommandEndEvent),
 
     ExecApprovalRequest(ExecApprovalRequestEvent),
@@ -452,6 +457,24 @@ pub struct ExecCommandBeginEvent {
     pub cwd: PathBuf,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExecOutputStream {
+    Stdout,
+    Stderr,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ExecCommandOutputDeltaEvent {
+    /// Identifier for the ExecCommandBegin that produced this output.
+    pub call_id: String,
+    /// Which stream the chunk was read from.
+    pub stream: ExecOutputStream,
+    /// Output as read from the stream. Chunks end on UTF-8 character
+    /// boundaries; invalid sequences are replaced.
+    pub chunk: String,
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct ExecCommandEndEvent {
     /// Identifier for the ExecCommandBegin that finished.
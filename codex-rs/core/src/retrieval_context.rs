@@ -0,0 +1,171 @@
+//! Retrieval-augmented context for user turns, enabled with
+//! `[retrieval_context]`. Each user message is searched for in the workspace's
+//! codebase index, and the best matches are sent to the model just before the
+//! message, so it can answer from the code without calling `codebase_search`
+//! first.
+
+use std::path::Path;
+use std::time::Duration;
+
+use codebase_search::context_builder::BuiltContext;
+use codebase_search::context_builder::ContextBuilder;
+use codebase_search::retriever::SearchFilter;
+use codebase_search::retriever::search_codebase_filtered;
+
+use crate::config_types::RetrievalContext;
+use crate::models::ContentItem;
+use crate::models::ResponseItem;
+use crate::protocol::InputItem;
+
+/// Token budget for the added code when none is configured
+const DEFAULT_MAX_TOKENS: usize = 4000;
+
+/// Search results considered when no limit is configured
+const DEFAULT_LIMIT: usize = 8;
+
+const DEFAULT_MIN_SCORE: f32 = 0.3;
+
+/// Longest the turn waits for the search before going ahead without context
+const SEARCH_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Code retrieved for a user turn, ready to be sent ahead of the user's message
+pub(crate) struct RetrievedContext {
+    pub(crate) message: ResponseItem,
+    /// Short description of what was added, for a background event
+    pub(crate) summary: String,
+}
+
+/// Searches the index of `cwd` for the text of `input`. Returns `Ok(None)` when
+/// the feature is off, the input has no text, or nothing fits the budget.
+pub(crate) async fn retrieve_context(
+    settings: &RetrievalContext,
+    cwd: &Path,
+    input: &[InputItem],
+) -> anyhow::Result<Option<RetrievedContext>> {
+    if !settings.enabled {
+        return Ok(None);
+    }
+    let query = input
+        .iter()
+        .filter_map(|item| match item {
+            InputItem::Text { text } => Some(text.as_str()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    if query.trim().is_empty() {
+        return Ok(None);
+    }
+
+    let results = tokio::time::timeout(
+        SEARCH_TIMEOUT,
+        search_codebase_filtered(
+            query,
+            cwd,
+            settings.limit.unwrap_or(DEFAULT_LIMIT),
+            settings.min_score.unwrap_or(DEFAULT_MIN_SCORE),
+            &SearchFilter::default(),
+        ),
+    )
+    .await
+    .map_err(|_| anyhow::anyhow!("search timed out after {}s", SEARCH_TIMEOUT.as_secs()))??;
+
+    let context =
+        ContextBuilder::new(settings.max_tokens.unwrap_or(DEFAULT_MAX_TOKENS)).build(&results);
+    Ok(context_message(&context))
+}
+
+fn context_message(context: &BuiltContext) -> Option<RetrievedContext> {
+    if context.sources.is_empty() {
+        return None;
+    }
+    let text = format!(
+        "<codebase_context>\nCode from the workspace's codebase index that may be relevant to \
+         the next message. It was retrieved automatically by similarity, so check that it \
+         applies before relying on it.\n\n{}</codebase_context>",
+        context.text
+    );
+    Some(RetrievedContext {
+        message: ResponseItem::Message {
+            id: None,
+            role: "user".to_string(),
+            content: vec![ContentItem::InputText { text }],
+        },
+        summary: format!(
+            "Added {} code excerpt{} (~{} tokens) from the codebase index",
+            context.sources.len(),
+            if context.sources.len() == 1 { "" } else { "s" },
+            context.tokens
+        ),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+    use std::path::PathBuf;
+
+    use codebase_search::context_builder::ContextSource;
+
+    #[tokio::test]
+    async fn context_is_added_only_when_enabled_and_found() {
+        let disabled = RetrievalContext::default();
+        let text = vec![InputItem::Text {
+            text: "where are sessions created?".to_string(),
+        }];
+        let missing_index = Path::new("/nonexistent");
+        assert!(
+            retrieve_context(&disabled, missing_index, &text)
+                .await
+                .unwrap()
+                .is_none()
+        );
+
+        let enabled = RetrievalContext {
+            enabled: true,
+            ..Default::default()
+        };
+        let image_only = vec![InputItem::LocalImage {
+            path: PathBuf::from("screenshot.png"),
+        }];
+        assert!(
+            retrieve_context(&enabled, missing_index, &image_only)
+                .await
+                .unwrap()
+                .is_none()
+        );
+
+        assert!(context_message(&BuiltContext::default()).is_none());
+        let context = BuiltContext {
+            text: "[1] src/lib.rs:1-3 (Function main, score 0.80)\n```\nfn main() {}\n```\n\n"
+                .to_string(),
+            sources: vec![ContextSource {
+                index: 1,
+                id: "chunk-1".to_string(),
+                file_path: PathBuf::from("src/lib.rs"),
+                start_line: 1,
+                end_line: 3,
+                symbol_name: "main".to_string(),
+                score: 0.8,
+                truncated: false,
+            }],
+            tokens: 20,
+            omitted: 0,
+        };
+        let retrieved = context_message(&context).unwrap();
+        assert_eq!(
+            retrieved.summary,
+            "Added 1 code excerpt (~20 tokens) from the codebase index"
+        );
+        let ResponseItem::Message { role, content, .. } = retrieved.message else {
+            panic!("expected a message");
+        };
+        assert_eq!(role, "user");
+        assert!(matches!(
+            content.as_slice(),
+            [ContentItem::InputText { text }]
+                if text.starts_with("<codebase_context>\n") && text.contains("fn main() {}")
+        ));
+    }
+}
@@ -134,6 +134,37 @@ pub fn assess_command_safety(
     }
 }
 
+/// Indexing the workspace for `codebase_search` sends its files to the embedding
+/// provider and writes the index state under the workspace. It runs in-process, so
+/// no sandbox can confine it: unless the user approved `command` (the equivalent
+/// `codebase-search` invocation), the sandbox policy must already allow network
+/// access, which also implies the workspace is writable.
+pub fn assess_index_safety(
+    command: &[String],
+    approval_policy: AskForApproval,
+    sandbox_policy: &SandboxPolicy,
+    approved: &HashSet<Vec<String>>,
+) -> SafetyCheck {
+    if approved.contains(command) {
+        return SafetyCheck::AutoApprove {
+            sandbox_type: SandboxType::None,
+        };
+    }
+
+    match approval_policy {
+        AskForApproval::UnlessTrusted => SafetyCheck::AskUser,
+        _ if sandbox_policy.has_full_network_access() => SafetyCheck::AutoApprove {
+            sandbox_type: SandboxType::None,
+        },
+        AskForApproval::OnFailure => SafetyCheck::AskUser,
+        AskForApproval::Never => SafetyCheck::Reject {
+            reason: "indexing sends the workspace to the embedding provider, which the \
+                     sandbox policy does not allow"
+                .to_string(),
+        },
+    }
+}
+
 pub fn get_platform_sandbox() -> Option<SandboxType> {
     if cfg!(target_os = "macos") {
         Some(SandboxType::MacosSeatbelt)
@@ -255,4 +286,52 @@ mod tests {
             &cwd,
         ))
     }
+    #[test]
+    fn indexing_needs_network_access_or_approval() {
+        let command = vec!["codebase-search".to_string(), "index-codebase".to_string()];
+        let none = HashSet::new();
+        let network = SandboxPolicy::WorkspaceWrite {
+            writable_roots: vec![],
+            network_access: true,
+        };
+        let offline = SandboxPolicy::new_workspace_write_policy();
+        let check = |approval, sandbox: &SandboxPolicy, approved: &HashSet<Vec<String>>| {
+            assess_index_safety(&command, approval, sandbox, approved)
+        };
+
+        assert!(matches!(
+            check(AskForApproval::Never, &network, &none),
+            SafetyCheck::AutoApprove { .. }
+        ));
+        assert!(matches!(
+            check(
+                AskForApproval::OnFailure,
+                &SandboxPolicy::DangerFullAccess,
+                &none
+            ),
+            SafetyCheck::AutoApprove { .. }
+        ));
+        assert!(matches!(
+            check(
+                AskForApproval::UnlessTrusted,
+                &SandboxPolicy::DangerFullAccess,
+                &none
+            ),
+            SafetyCheck::AskUser
+        ));
+        assert!(matches!(
+            check(AskForApproval::OnFailure, &offline, &none),
+            SafetyCheck::AskUser
+        ));
+        assert!(matches!(
+            check(AskForApproval::Never, &SandboxPolicy::ReadOnly, &none),
+            SafetyCheck::Reject { .. }
+        ));
+
+        let approved = HashSet::from([command.clone()]);
+        assert!(matches!(
+            check(AskForApproval::UnlessTrusted, &offline, &approved),
+            SafetyCheck::AutoApprove { .. }
+        ));
+    }
 }
@@ -0,0 +1,173 @@
+//! Guards that keep the file tools from handing binary or huge files to the
+//! model. Files are refused with a message naming their size and likely type,
+//! so the model can pick a better tool instead of retrying.
+
+use std::path::Path;
+
+/// Largest file the file tools read into memory
+pub(crate) const MAX_TEXT_FILE_BYTES: u64 = 10 * 1024 * 1024;
+
+/// Largest image the file tools attach for the model to look at
+pub(crate) const MAX_IMAGE_BYTES: u64 = 20 * 1024 * 1024;
+
+/// Image types models accept as input
+const SUPPORTED_IMAGE_MIMES: &[&str] = &["image/png", "image/jpeg", "image/gif", "image/webp"];
+
+/// Bytes at the start of a file inspected when deciding whether it is binary
+const SNIFF_BYTES: usize = 8 * 1024;
+
+/// Share of control bytes above which a file without NUL bytes still counts as binary
+const MAX_CONTROL_BYTE_RATIO: f64 = 0.1;
+
+/// Reads `path` as text for a file tool. Binary files and files over
+/// [`MAX_TEXT_FILE_BYTES`] are refused; invalid UTF-8 in text files is replaced.
+/// `display_path` is the path as the model gave it.
+pub(crate) fn read_text_file(path: &Path, display_path: &str) -> Result<String, String> {
+    let bytes = read_text_bytes(path, display_path)?;
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// Reads `path` as text for a tool that writes it back. Unlike [`read_text_file`],
+/// invalid UTF-8 is refused, since replacing it would corrupt the file on write.
+pub(crate) fn read_text_file_for_edit(path: &Path, display_path: &str) -> Result<String, String> {
+    let bytes = read_text_bytes(path, display_path)?;
+    String::from_utf8(bytes).map_err(|e| {
+        format!(
+            "{display_path} is not valid UTF-8 (at byte {}) and can't be edited as text",
+            e.utf8_error().valid_up_to()
+        )
+    })
+}
+
+fn read_text_bytes(path: &Path, display_path: &str) -> Result<Vec<u8>, String> {
+    let size = std::fs::metadata(path)
+        .map_err(|e| format!("failed to read {display_path}: {e}"))?
+        .len();
+    if size > MAX_TEXT_FILE_BYTES {
+        return Err(format!(
+            "{display_path} is too large to open ({}, {}; the limit is {}). Search it with \
+             grep_search or read part of it with a shell command instead.",
+            format_size(size),
+            guess_mime(path, &[]),
+            format_size(MAX_TEXT_FILE_BYTES)
+        ));
+    }
+
+    let bytes = std::fs::read(path).map_err(|e| format!("failed to read {display_path}: {e}"))?;
+    check_text(path, display_path, &bytes)?;
+    Ok(bytes)
+}
+
+/// The mime type of `path` if its extension names an image type models accept.
+pub(crate) fn supported_image_mime(path: &Path) -> Option<String> {
+    mime_guess::from_path(path)
+        .iter()
+        .map(|mime| mime.essence_str().to_string())
+        .find(|mime| SUPPORTED_IMAGE_MIMES.contains(&mime.as_str()))
+}
+
+/// Refuses `bytes` if they look like the content of a binary file.
+pub(crate) fn check_text(path: &Path, display_path: &str, bytes: &[u8]) -> Result<(), String> {
+    if looks_binary(bytes) {
+        return Err(format!(
+            "{display_path} looks like a binary file ({}, {}) and can't be handled as text",
+            format_size(bytes.len() as u64),
+            guess_mime(path, bytes)
+        ));
+    }
+    Ok(())
+}
+
+/// Whether the start of `bytes` has NUL bytes or mostly control characters.
+pub(crate) fn looks_binary(bytes: &[u8]) -> bool {
+    let sample = &bytes[..bytes.len().min(SNIFF_BYTES)];
+    if sample.is_empty() {
+        return false;
+    }
+    if sample.contains(&0) {
+        return true;
+    }
+    let control_bytes = sample
+        .iter()
+        .filter(|&&b| b < 0x20 && !matches!(b, b'\t' | b'\n' | b'\r' | 0x0c | 0x1b))
+        .count();
+    control_bytes as f64 / sample.len() as f64 > MAX_CONTROL_BYTE_RATIO
+}
+
+/// Mime type from the file's magic number, falling back to its extension.
+fn guess_mime(path: &Path, bytes: &[u8]) -> String {
+    const MAGIC: &[(&[u8], &str)] = &[
+        (b"\x89PNG\r\n\x1a\n", "image/png"),
+        (b"\xff\xd8\xff", "image/jpeg"),
+        (b"GIF8", "image/gif"),
+        (b"%PDF-", "application/pdf"),
+        (b"PK\x03\x04", "application/zip"),
+        (b"\x1f\x8b", "application/gzip"),
+        (b"\x7fELF", "application/x-elf"),
+        (b"\0asm", "application/wasm"),
+    ];
+    MAGIC
+        .iter()
+        .find(|(magic, _)| bytes.starts_with(magic))
+        .map(|(_, mime)| mime.to_string())
+        .or_else(|| {
+            mime_guess::from_path(path)
+                .first()
+                .map(|mime| mime.essence_str().to_string())
+        })
+        .unwrap_or_else(|| "application/octet-stream".to_string())
+}
+
+/// Human-readable file size, e.g. `512 B` or `1.5 KiB`
+pub(crate) fn format_size(bytes: u64) -> String {
+    const UNITS: &[&str] = &["KiB", "MiB", "GiB"];
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit + 1 < UNITS.len() {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{size:.1} {}", UNITS[unit])
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+
+    #[test]
+    fn binary_files_are_refused_with_size_and_type() {
+        let dir = tempfile::tempdir().unwrap();
+        let png = dir.path().join("logo.dat");
+        std::fs::write(&png, b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR").unwrap();
+        let err = read_text_file(&png, "logo.dat").unwrap_err();
+        assert_eq!(
+            err,
+            "logo.dat looks like a binary file (16 B, image/png) and can't be handled as text"
+        );
+
+        let text = dir.path().join("notes.txt");
+        std::fs::write(&text, "caf\u{e9}\tok\r\n\x1b[1mbold\x1b[0m\n").unwrap();
+        assert!(read_text_file(&text, "notes.txt").is_ok());
+        assert!(looks_binary(b"\x01\x02\x03\x04abcdef"));
+        assert!(!looks_binary(b""));
+    }
+
+    #[test]
+    fn edits_refuse_invalid_utf8() {
+        let dir = tempfile::tempdir().unwrap();
+        let latin1 = dir.path().join("latin1.txt");
+        std::fs::write(&latin1, b"caf\xe9\n").unwrap();
+        assert_eq!(
+            read_text_file(&latin1, "latin1.txt").unwrap(),
+            "caf\u{fffd}\n"
+        );
+        assert_eq!(
+            read_text_file_for_edit(&latin1, "latin1.txt").unwrap_err(),
+            "latin1.txt is not valid UTF-8 (at byte 3) and can't be edited as text"
+        );
+    }
+}
@@ -0,0 +1,260 @@
+//! Per-tool settings for the commands that tools spawn, configured in
+//! `[tools.<name>]` tables. A tool without a table, or a setting left unset,
+//! falls back to the global behavior: the timeout the model asks for, any
+//! command, and the global `shell_environment_policy`.
+
+use std::path::Path;
+
+use crate::bash::try_parse_bash;
+use crate::bash::try_parse_word_only_commands_sequence;
+use crate::config_types::ShellEnvironmentPolicy;
+use crate::config_types::ToolExecPolicyToml;
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ToolExecPolicy {
+    /// Timeout for the tool's commands, in place of the one the model asks for.
+    pub timeout_ms: Option<u64>,
+
+    /// Timeouts for commands starting with the given words, longest prefix first.
+    pub command_timeouts_ms: Vec<(Vec<String>, u64)>,
+
+    /// Command prefixes the tool may run. `None` allows any command.
+    pub allowed_commands: Option<Vec<Vec<String>>>,
+
+    /// Environment for the tool's commands, in place of the global policy.
+    pub shell_environment_policy: Option<ShellEnvironmentPolicy>,
+}
+
+impl From<ToolExecPolicyToml> for ToolExecPolicy {
+    fn from(toml: ToolExecPolicyToml) -> Self {
+        let mut command_timeouts_ms: Vec<(Vec<String>, u64)> = toml
+            .command_timeouts_ms
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(prefix, timeout)| (split_words(&prefix), timeout))
+            .filter(|(prefix, _)| !prefix.is_empty())
+            .collect();
+        command_timeouts_ms.sort_by(|(a, _), (b, _)| b.len().cmp(&a.len()).then_with(|| a.cmp(b)));
+        let allowed_commands = toml.allowed_commands.map(|allowed| {
+            allowed
+                .iter()
+                .map(|prefix| split_words(prefix))
+                .filter(|prefix| !prefix.is_empty())
+                .collect()
+        });
+
+        Self {
+            timeout_ms: toml.timeout_ms,
+            command_timeouts_ms,
+            allowed_commands,
+            shell_environment_policy: toml.shell_environment_policy.map(Into::into),
+        }
+    }
+}
+
+impl ToolExecPolicy {
+    /// Refuses `command` unless every command it runs starts with one of the
+    /// allowed prefixes. `tool` is only used in the message.
+    pub(crate) fn check_allowed(&self, tool: &str, command: &[String]) -> Result<(), String> {
+        let Some(allowed) = &self.allowed_commands else {
+            return Ok(());
+        };
+        let Some(commands) = commands_run(command) else {
+            return Err(format!(
+                "the {tool} tool can only run plain commands that it can check against its \
+                 allowed commands; `{}` is too complex to check",
+                command.join(" ")
+            ));
+        };
+        match commands.iter().find(|cmd| {
+            !allowed
+                .iter()
+                .any(|prefix| starts_with_words(cmd, prefix, false))
+        }) {
+            Some(cmd) => Err(format!(
+                "`{}` is not allowed for the {tool} tool; allowed commands: {}",
+                cmd.join(" "),
+                allowed
+                    .iter()
+                    .map(|prefix| prefix.join(" "))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// Configured timeout for `command`: the longest timeout among the
+    /// commands it runs that have one, else the tool's timeout.
+    pub(crate) fn timeout_ms(&self, command: &[String]) -> Option<u64> {
+        commands_run(command)
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|cmd| {
+                self.command_timeouts_ms
+                    .iter()
+                    .find(|(prefix, _)| starts_with_words(cmd, prefix, true))
+                    .map(|(_, timeout)| *timeout)
+            })
+            .max()
+            .or(self.timeout_ms)
+    }
+}
+
+/// The commands `command` runs: the commands of a `bash -lc` script, or
+/// `command` itself. `None` if the script is more than plain commands joined
+/// by `&&`, `||`, `;` or `|`.
+fn commands_run(command: &[String]) -> Option<Vec<Vec<String>>> {
+    match command {
+        [shell, flag, script]
+            if is_system_shell(shell) && matches!(flag.as_str(), "-lc" | "-c") =>
+        {
+            let tree = try_parse_bash(script)?;
+            try_parse_word_only_commands_sequence(&tree, script)
+        }
+        _ => Some(vec![command.to_vec()]),
+    }
+}
+
+/// Whether `command` starts with the words of `prefix`. A program given as a
+/// path must equal the prefix's program exactly, so an allowed `cargo` does not
+/// allow `./cargo` or `/tmp/evil/cargo`. With `by_file_name` the program is
+/// compared by file name instead (`/usr/bin/cargo` matches `cargo`), which is
+/// only fit for picking timeouts.
+fn starts_with_words(command: &[String], prefix: &[String], by_file_name: bool) -> bool {
+    match (command.split_first(), prefix.split_first()) {
+        (Some((program, args)), Some((prefix_program, prefix_args))) => {
+            let program = if by_file_name {
+                program_name(program)
+            } else {
+                program
+            };
+            program == prefix_program && args.starts_with(prefix_args)
+        }
+        _ => false,
+    }
+}
+
+/// Whether `program` is bash, sh or zsh, by name or in `/bin` or `/usr/bin`.
+/// A shell elsewhere could be anything, so its script is not looked into.
+fn is_system_shell(program: &str) -> bool {
+    let path = Path::new(program);
+    let in_system_dir = match path.parent() {
+        Some(parent) => {
+            parent.as_os_str().is_empty()
+                || parent == Path::new("/bin")
+                || parent == Path::new("/usr/bin")
+        }
+        None => false,
+    };
+    in_system_dir && matches!(program_name(program), "bash" | "sh" | "zsh")
+}
+
+fn program_name(program: &str) -> &str {
+    Path::new(program)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or(program)
+}
+
+fn split_words(s: &str) -> Vec<String> {
+    s.split_whitespace().map(String::from).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+    use std::collections::HashMap;
+
+    fn cmd(words: &[&str]) -> Vec<String> {
+        words.iter().map(|w| w.to_string()).collect()
+    }
+
+    #[test]
+    fn allowed_commands_and_timeouts_apply_to_each_command_in_a_script() {
+        let policy = ToolExecPolicy::from(ToolExecPolicyToml {
+            timeout_ms: Some(5_000),
+            command_timeouts_ms: Some(HashMap::from([
+                ("cargo".to_string(), 60_000),
+                ("cargo test".to_string(), 600_000),
+            ])),
+            allowed_commands: Some(vec!["cargo".to_string(), "git status".to_string()]),
+            shell_environment_policy: None,
+        });
+
+        assert_eq!(
+            policy.timeout_ms(&cmd(&["cargo", "test", "-p", "core"])),
+            Some(600_000)
+        );
+        assert_eq!(
+            policy.timeout_ms(&cmd(&["/usr/bin/cargo", "build"])),
+            Some(60_000)
+        );
+        assert_eq!(
+            policy.timeout_ms(&cmd(&["bash", "-lc", "git status && cargo test"])),
+            Some(600_000)
+        );
+        assert_eq!(policy.timeout_ms(&cmd(&["git", "status"])), Some(5_000));
+
+        assert!(
+            policy
+                .check_allowed("shell", &cmd(&["cargo", "fmt"]))
+                .is_ok()
+        );
+        assert!(
+            policy
+                .check_allowed("shell", &cmd(&["bash", "-lc", "git status; cargo check"]))
+                .is_ok()
+        );
+        assert_eq!(
+            policy
+                .check_allowed("shell", &cmd(&["bash", "-lc", "cargo check && git push"]))
+                .unwrap_err(),
+            "`git push` is not allowed for the shell tool; allowed commands: cargo, git status"
+        );
+        assert!(
+            policy
+                .check_allowed("shell", &cmd(&["bash", "-lc", "cargo test > out.txt"]))
+                .is_err()
+        );
+        assert!(
+            ToolExecPolicy::default()
+                .check_allowed("shell", &cmd(&["rm", "-rf", "target"]))
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn allowed_programs_given_as_paths_must_match_exactly() {
+        let policy = ToolExecPolicy::from(ToolExecPolicyToml {
+            timeout_ms: None,
+            command_timeouts_ms: None,
+            allowed_commands: Some(vec!["cargo".to_string(), "/usr/bin/git".to_string()]),
+            shell_environment_policy: None,
+        });
+
+        for rejected in [
+            cmd(&["./cargo", "build"]),
+            cmd(&["/tmp/evil/cargo", "build"]),
+            cmd(&["bash", "-lc", "../cargo build"]),
+            cmd(&["/tmp/evil/bash", "-lc", "cargo build"]),
+            cmd(&["git", "status"]),
+        ] {
+            assert!(
+                policy.check_allowed("shell", &rejected).is_err(),
+                "{rejected:?} should be rejected"
+            );
+        }
+        assert!(
+            policy
+                .check_allowed("shell", &cmd(&["/bin/bash", "-lc", "cargo build"]))
+                .is_ok()
+        );
+        assert!(
+            policy
+                .check_allowed("shell", &cmd(&["/usr/bin/git", "status"]))
+                .is_ok()
+        );
+    }
+}
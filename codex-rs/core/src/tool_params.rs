@@ -0,0 +1,122 @@
+//! Parsing and validation shared by the built-in tools.
+//!
+//! Every tool's arguments are deserialized and checked the same way before the
+//! tool runs, so a bad call always gets the same kind of answer: what was wrong
+//! and with which argument, for the model to fix and call again.
+
+use std::fmt;
+
+use serde::de::DeserializeOwned;
+
+use crate::models::FunctionCallOutputMetadata;
+use crate::models::FunctionCallOutputPayload;
+use crate::models::ResponseInputItem;
+
+/// Why a tool call's arguments were rejected, phrased for the model.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ToolParamError {
+    /// Name of the argument at fault
+    pub(crate) field: &'static str,
+    pub(crate) message: String,
+}
+
+impl ToolParamError {
+    pub(crate) fn new(field: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            field,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for ToolParamError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+/// Arguments of a built-in tool, checked before the tool runs.
+pub(crate) trait ValidatedToolParams: DeserializeOwned {
+    /// Checks the arguments for problems the JSON schema can't express.
+    fn validate(&self) -> Result<(), ToolParamError>;
+}
+
+/// Deserializes and validates the JSON `arguments` of a tool call. On failure,
+/// returns the output that tells the model what to fix so it can re-sample.
+pub(crate) fn parse_tool_params<T: ValidatedToolParams>(
+    arguments: &str,
+    call_id: &str,
+) -> Result<T, Box<ResponseInputItem>> {
+    let rejected = |content: String, invalid_argument: Option<&str>| {
+        Box::new(ResponseInputItem::FunctionCallOutput {
+            call_id: call_id.to_string(),
+            output: FunctionCallOutputPayload {
+                content,
+                success: None,
+                metadata: invalid_argument.map(|field| FunctionCallOutputMetadata {
+                    invalid_argument: Some(field.to_string()),
+                    ..Default::default()
+                }),
+            },
+        })
+    };
+    let params: T = serde_json::from_str(arguments)
+        .map_err(|e| rejected(format!("failed to parse function arguments: {e}"), None))?;
+    params
+        .validate()
+        .map_err(|e| rejected(format!("validation error: {e}"), Some(e.field)))?;
+    Ok(params)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Deserialize)]
+    struct Params {
+        count: u32,
+    }
+
+    impl ValidatedToolParams for Params {
+        fn validate(&self) -> Result<(), ToolParamError> {
+            if self.count == 0 {
+                return Err(ToolParamError::new("count", "count must be greater than 0"));
+            }
+            Ok(())
+        }
+    }
+
+    fn rejection(arguments: &str) -> FunctionCallOutputPayload {
+        match parse_tool_params::<Params>(arguments, "call-1").map(|_| ()) {
+            Err(output) => match *output {
+                ResponseInputItem::FunctionCallOutput { output, .. } => output,
+                other => panic!("unexpected output: {other:?}"),
+            },
+            Ok(()) => panic!("arguments {arguments} were accepted"),
+        }
+    }
+
+    #[test]
+    fn rejections_name_the_invalid_argument() {
+        assert!(parse_tool_params::<Params>(r#"{"count": 2}"#, "call-1").is_ok());
+
+        let invalid = rejection(r#"{"count": 0}"#);
+        assert_eq!(
+            invalid.content,
+            "validation error: count must be greater than 0"
+        );
+        assert_eq!(
+            invalid.metadata.and_then(|m| m.invalid_argument).as_deref(),
+            Some("count")
+        );
+
+        let malformed = rejection(r#"{"count": "two"}"#);
+        assert!(
+            malformed
+                .content
+                .starts_with("failed to parse function arguments:")
+        );
+        assert_eq!(malformed.metadata, None);
+    }
+}
@@ -0,0 +1,175 @@
+//! Registry of the tools a session offers the model.
+//!
+//! Each function tool is registered once, with the definition that is sent to
+//! the model and the handler that runs when the model calls it. The tool list in
+//! every request and the dispatch of every function call both come from here,
+//! so a tool can't be advertised without a handler or the other way round.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+use serde::Serialize;
+use tracing::warn;
+
+use crate::codex::Session;
+use crate::models::ResponseInputItem;
+use crate::openai_tools::ToJsonSchema;
+use crate::openai_tools::create_tool_from_struct;
+use crate::tool_params::ValidatedToolParams;
+
+/// A function call from the model, addressed to a registered tool.
+#[derive(Debug, Clone)]
+pub(crate) struct ToolCall {
+    /// Id of the submission (turn) the call belongs to
+    pub(crate) sub_id: String,
+    /// Name the tool was called by
+    pub(crate) name: String,
+    pub(crate) call_id: String,
+    /// JSON-encoded arguments, as sent by the model
+    pub(crate) arguments: String,
+}
+
+pub(crate) type ToolFuture<'a> = Pin<Box<dyn Future<Output = ResponseInputItem> + Send + 'a>>;
+
+/// Runs a tool call and produces the output returned to the model.
+pub(crate) type ToolHandler =
+    Arc<dyn for<'a> Fn(&'a Session, ToolCall) -> ToolFuture<'a> + Send + Sync>;
+
+/// Wraps a closure as a [`ToolHandler`], e.g.
+/// `tool_handler(|sess, call| Box::pin(handle_read_file(sess, call)))`.
+pub(crate) fn tool_handler<F>(handler: F) -> ToolHandler
+where
+    F: for<'a> Fn(&'a Session, ToolCall) -> ToolFuture<'a> + Send + Sync + 'static,
+{
+    Arc::new(handler)
+}
+
+#[derive(Clone)]
+pub(crate) struct ToolRegistry {
+    /// Tool definitions sent to the model, in registration order
+    specs: Vec<serde_json::Value>,
+    handlers: HashMap<String, ToolHandler>,
+    /// Whether built-in function tools are advertised. Models with their own
+    /// shell tool aren't offered them, though calls to them are still handled.
+    advertise_builtin_functions: bool,
+}
+
+impl ToolRegistry {
+    pub(crate) fn new(advertise_builtin_functions: bool) -> Self {
+        Self {
+            specs: Vec::new(),
+            handlers: HashMap::new(),
+            advertise_builtin_functions,
+        }
+    }
+
+    /// Registers a built-in function tool whose parameters are described by `T`.
+    /// Its handler is expected to parse them with
+    /// [`parse_tool_params`](crate::tool_params::parse_tool_params).
+    pub(crate) fn register_function<T: ToJsonSchema + ValidatedToolParams>(
+        &mut self,
+        name: &'static str,
+        description: &'static str,
+        handler: ToolHandler,
+    ) {
+        if self.advertise_builtin_functions {
+            self.register(
+                name,
+                &create_tool_from_struct::<T>(name, description),
+                handler,
+            );
+        } else {
+            self.register_hidden(name, handler);
+        }
+    }
+
+    /// Registers a tool the model is told about and can call by `name`.
+    pub(crate) fn register<S: Serialize>(&mut self, name: &str, spec: &S, handler: ToolHandler) {
+        match serde_json::to_value(spec) {
+            Ok(spec) => {
+                if self.handlers.insert(name.to_string(), handler).is_some() {
+                    warn!("tool `{name}` registered more than once; keeping the last handler");
+                    self.specs.retain(|existing| existing["name"] != name);
+                }
+                self.specs.push(spec);
+            }
+            Err(e) => warn!("not registering tool `{name}`: invalid definition: {e}"),
+        }
+    }
+
+    /// Registers a handler for a name the model may call without it being
+    /// advertised, e.g. a legacy alias of another tool.
+    pub(crate) fn register_hidden(&mut self, name: &str, handler: ToolHandler) {
+        self.handlers.insert(name.to_string(), handler);
+    }
+
+    /// Advertises a tool whose calls don't arrive as function calls (such as
+    /// `local_shell`), so there is no handler to register.
+    pub(crate) fn advertise<S: Serialize>(&mut self, spec: &S) {
+        match serde_json::to_value(spec) {
+            Ok(spec) => self.specs.push(spec),
+            Err(e) => warn!("not advertising tool: invalid definition: {e}"),
+        }
+    }
+
+    pub(crate) fn handler(&self, name: &str) -> Option<ToolHandler> {
+        self.handlers.get(name).cloned()
+    }
+
+    /// Tool definitions in the shape the Responses API expects.
+    pub(crate) fn specs(&self) -> &[serde_json::Value] {
+        &self.specs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::FunctionCallOutputPayload;
+    use serde_json::json;
+
+    fn echo() -> ToolHandler {
+        tool_handler(|_sess, call| {
+            Box::pin(async move {
+                ResponseInputItem::FunctionCallOutput {
+                    call_id: call.call_id,
+                    output: FunctionCallOutputPayload {
+                        content: call.arguments,
+                        success: Some(true),
+                        metadata: None,
+                    },
+                }
+            })
+        })
+    }
+
+    #[test]
+    fn advertised_tools_follow_registrations() {
+        let mut registry = ToolRegistry::new(true);
+        registry.register(
+            "first",
+            &json!({"type": "function", "name": "first"}),
+            echo(),
+        );
+        registry.advertise(&json!({"type": "local_shell"}));
+        registry.register_hidden("alias", echo());
+        registry.register(
+            "first",
+            &json!({"type": "function", "name": "first", "v": 2}),
+            echo(),
+        );
+
+        assert_eq!(
+            registry.specs(),
+            &[
+                json!({"type": "local_shell"}),
+                json!({"type": "function", "name": "first", "v": 2}),
+            ]
+        );
+        assert!(registry.handler("first").is_some());
+        assert!(registry.handler("alias").is_some());
+        assert!(registry.handler("local_shell").is_none());
+    }
+}
@@ -66,6 +66,7 @@ async fn includes_session_id_and_model_headers_in_request() {
         request_max_retries: Some(0),
         stream_max_retries: Some(0),
         stream_idle_timeout_ms: None,
+        structured_function_call_outputs: false,
     };
 
     // Init session
@@ -140,6 +141,7 @@ async fn includes_base_instructions_override_in_request() {
         request_max_retries: Some(0),
         stream_max_retries: Some(0),
         stream_idle_timeout_ms: None,
+        structured_function_call_outputs: false,
     };
 
     let codex_home = TempDir::new().unwrap();
@@ -89,6 +89,7 @@ async fn retries_on_early_close() {
         request_max_retries: Some(0),
         stream_max_retries: Some(1),
         stream_idle_timeout_ms: Some(2000),
+        structured_function_call_outputs: false,
     };
 
     let ctrl_c = std::sync::Arc::new(tokio::sync::Notify::new());
@@ -253,6 +253,9 @@ impl EventProcessor for EventProcessorWithHumanOutput {
                     cwd.to_string_lossy(),
                 );
             }
+            EventMsg::ExecCommandOutputDelta(_) => {
+                // The output is printed in full once the command ends.
+            }
             EventMsg::ExecCommandEnd(ExecCommandEndEvent {
                 call_id,
                 stdout,
@@ -41,6 +41,91 @@ pub fn derive_tool_schema(input: TokenStream) -> TokenStream {
     TokenStream::from(expanded)
 }
 
+/// Derive macro that lists the values a field of this enum type can take in a tool schema.
+///
+/// Implements `ToolEnum` for enums whose variants have no fields. Values are the
+/// variant names in snake_case, or the name given with `#[serde(rename = "...")]`,
+/// so they match what `#[serde(rename_all = "snake_case")]` deserializes. Fields of
+/// the enum's type (or `Option`/`Vec` of it) in a `ToolSchema` struct get these as
+/// their `enum` values.
+///
+/// # Example
+/// ```rust
+/// #[derive(ToolEnum, Deserialize)]
+/// #[serde(rename_all = "snake_case")]
+/// enum Units {
+///     Celsius,
+///     Fahrenheit,
+/// }
+/// ```
+#[proc_macro_derive(ToolEnum)]
+pub fn derive_tool_enum(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let name = &input.ident;
+
+    let syn::Data::Enum(data) = &input.data else {
+        return syn::Error::new_spanned(&input.ident, "ToolEnum can only be derived for enums")
+            .to_compile_error()
+            .into();
+    };
+
+    let mut values = Vec::new();
+    for variant in &data.variants {
+        if !matches!(variant.fields, syn::Fields::Unit) {
+            return syn::Error::new_spanned(&variant.ident, "ToolEnum variants cannot have fields")
+                .to_compile_error()
+                .into();
+        }
+        let value = serde_rename(&variant.attrs)
+            .unwrap_or_else(|| to_snake_case(&variant.ident.to_string()));
+        values.push(value);
+    }
+
+    let expanded = quote! {
+        impl crate::openai_tools::ToolEnum for #name {
+            const VALUES: &'static [&'static str] = &[#(#values),*];
+        }
+    };
+
+    TokenStream::from(expanded)
+}
+
+/// The name from a `#[serde(rename = "...")]` attribute, if there is one
+fn serde_rename(attrs: &[syn::Attribute]) -> Option<String> {
+    let mut rename = None;
+    for attr in attrs {
+        if !attr.path().is_ident("serde") {
+            continue;
+        }
+        let _ = attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rename") {
+                let value: syn::LitStr = meta.value()?.parse()?;
+                rename = Some(value.value());
+            } else if meta.input.peek(syn::Token![=]) {
+                // Skip the value of other `key = value` options
+                let _: syn::Expr = meta.value()?.parse()?;
+            }
+            Ok(())
+        });
+    }
+    rename
+}
+
+fn to_snake_case(name: &str) -> String {
+    let mut snake = String::new();
+    for (i, ch) in name.chars().enumerate() {
+        if ch.is_uppercase() {
+            if i > 0 {
+                snake.push('_');
+            }
+            snake.extend(ch.to_lowercase());
+        } else {
+            snake.push(ch);
+        }
+    }
+    snake
+}
+
 fn generate_schema_from_struct(input: &DeriveInput) -> proc_macro2::TokenStream {
     let mut properties = Vec::new();
     let mut required = Vec::new();
@@ -131,6 +216,41 @@ fn is_option_type(ty: &syn::Type) -> bool {
     false
 }
 
+/// The type argument of a path segment like `Vec<T>` or `Option<T>`
+fn first_generic_argument(segment: &syn::PathSegment) -> Option<&syn::Type> {
+    let syn::PathArguments::AngleBracketed(args) = &segment.arguments else {
+        return None;
+    };
+    match args.args.first() {
+        Some(syn::GenericArgument::Type(inner_type)) => Some(inner_type),
+        _ => None,
+    }
+}
+
+/// Schema for the items of a `Vec<T>` field, which carry no description
+fn map_rust_type_to_item(ty: &syn::Type) -> proc_macro2::TokenStream {
+    let name = match ty {
+        syn::Type::Path(type_path) if type_path.path.segments.len() == 1 => {
+            type_path.path.segments[0].ident.to_string()
+        }
+        _ => return quote! { Property::Simple(JsonSchema::String) },
+    };
+    match name.as_str() {
+        "String" | "str" => quote! { Property::Simple(JsonSchema::String) },
+        "i32" | "i64" | "u32" | "u64" | "f32" | "f64" => {
+            quote! { Property::Simple(JsonSchema::Number) }
+        }
+        "bool" => quote! { Property::Simple(JsonSchema::Boolean) },
+        _ => quote! {
+            Property::WithDescription {
+                schema: JsonSchema::String,
+                description: None,
+                enum_values: Some(<#ty as crate::openai_tools::ToolEnum>::VALUES),
+            }
+        },
+    }
+}
+
 fn map_rust_type_to_property(
     ty: &syn::Type,
     description: Option<&str>,
@@ -175,10 +295,16 @@ fn map_rust_type_to_property(
                         }
                     },
                     "Vec" => {
+                        let items = path
+                            .segments
+                            .first()
+                            .and_then(first_generic_argument)
+                            .map(map_rust_type_to_item)
+                            .unwrap_or_else(|| quote! { Property::Simple(JsonSchema::String) });
                         quote! {
                             Property::WithDescription {
                                 schema: JsonSchema::Array {
-                                    items: Box::new(JsonSchema::String),
+                                    items: Box::new(#items),
                                 },
                                 description: #desc,
                                 enum_values: None,
@@ -205,19 +331,22 @@ fn map_rust_type_to_property(
                             }
                         }
                     }
+                    // Any other type is an enum deriving ToolEnum, serialized as a string
                     _ => quote! {
                         Property::WithDescription {
                             schema: JsonSchema::String,
                             description: #desc,
-                            enum_values: None,
+                            enum_values: Some(
+                                <#ty as crate::openai_tools::ToolEnum>::VALUES,
+                            ),
                         }
-                    }, // Default to string
+                    },
                 }
             } else if segments.len() == 2 && segments[0].to_string() == "Vec" {
                 quote! {
                     Property::WithDescription {
                         schema: JsonSchema::Array {
-                            items: Box::new(JsonSchema::String),
+                            items: Box::new(Property::Simple(JsonSchema::String)),
                         },
                         description: #desc,
                         enum_values: None,
@@ -15,6 +15,7 @@ use codex_core::protocol::EventMsg;
 use codex_core::protocol::ExecApprovalRequestEvent;
 use codex_core::protocol::ExecCommandBeginEvent;
 use codex_core::protocol::ExecCommandEndEvent;
+use codex_core::protocol::ExecCommandOutputDeltaEvent;
 use codex_core::protocol::InputItem;
 use codex_core::protocol::McpToolCallBeginEvent;
 use codex_core::protocol::McpToolCallEndEvent;
@@ -383,6 +384,12 @@ impl ChatWidget<'_> {
                 }
                 self.request_redraw();
             }
+            EventMsg::ExecCommandOutputDelta(ExecCommandOutputDeltaEvent { chunk, .. }) => {
+                // Show the latest line of a running command in the status indicator.
+                if let Some(line) = chunk.lines().map(str::trim).rfind(|line| !line.is_empty()) {
+                    self.update_latest_log(line.to_string());
+                }
+            }
             EventMsg::ExecCommandEnd(ExecCommandEndEvent {
                 call_id,
                 exit_code,